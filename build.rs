@@ -0,0 +1,28 @@
+//! Generates the protobuf messages and gRPC services from `proto/` for the
+//! `proto` and `grpc` features. The schemas are parsed with `protox`, so
+//! building doesn't need `protoc` installed.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "proto")]
+    proto::generate();
+}
+
+#[cfg(feature = "proto")]
+mod proto {
+    fn compile(file: &str) -> prost_types::FileDescriptorSet {
+        println!("cargo:rerun-if-changed={file}");
+        protox::compile([file], ["proto"]).unwrap_or_else(|error| panic!("{file}: {error}"))
+    }
+
+    pub fn generate() {
+        prost_build::Config::new()
+            .compile_fds(compile("proto/rs_poker.proto"))
+            .expect("generating proto/rs_poker.proto");
+
+        #[cfg(feature = "grpc")]
+        tonic_build::configure()
+            .compile_fds(compile("proto/strategy.proto"))
+            .expect("generating proto/strategy.proto");
+    }
+}
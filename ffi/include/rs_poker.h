@@ -0,0 +1,111 @@
+/*
+ * C interface to the rs_poker hand evaluator, range parser and equity
+ * calculator.
+ *
+ * Link against librs_poker_ffi (shared or static). Every function is
+ * thread safe as long as the objects passed to it aren't shared between
+ * threads without synchronization.
+ *
+ * Cards are a single byte: suit * 13 + value, where value is 0 (two)
+ * through 12 (ace) and suit is 0 (spades), 1 (clubs), 2 (hearts) or
+ * 3 (diamonds).
+ *
+ * This header is part of the stable ABI. Functions and enum values are
+ * only ever added, and RSP_ABI_VERSION is bumped when that happens.
+ */
+#ifndef RS_POKER_H
+#define RS_POKER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RSP_ABI_VERSION 1
+
+/* Return codes. Everything other than RSP_OK is an error. */
+typedef enum RspStatus {
+  RSP_OK = 0,
+  RSP_NULL_POINTER = -1,
+  RSP_INVALID_UTF8 = -2,
+  RSP_PARSE_ERROR = -3,
+  RSP_INVALID_CARD = -4,
+  RSP_DUPLICATE_CARD = -5,
+  RSP_INVALID_ARGUMENT = -6,
+  RSP_OUT_OF_BOUNDS = -7,
+} RspStatus;
+
+typedef enum RspRankCategory {
+  RSP_HIGH_CARD = 0,
+  RSP_ONE_PAIR = 1,
+  RSP_TWO_PAIR = 2,
+  RSP_THREE_OF_A_KIND = 3,
+  RSP_STRAIGHT = 4,
+  RSP_FLUSH = 5,
+  RSP_FULL_HOUSE = 6,
+  RSP_FOUR_OF_A_KIND = 7,
+  RSP_STRAIGHT_FLUSH = 8,
+} RspRankCategory;
+
+/*
+ * The strength of a made hand. Compare the category first and then the
+ * value; higher is better for both. rsp_rank_compare does this.
+ */
+typedef struct RspRank {
+  uint32_t category;
+  uint32_t value;
+} RspRank;
+
+/* A parsed hand range. Create with rsp_range_parse, free with
+ * rsp_range_free. */
+typedef struct RspRange RspRange;
+
+/* The ABI version of the loaded library. */
+uint32_t rsp_abi_version(void);
+
+/* A static, human readable description of a status code. */
+const char *rsp_status_message(int32_t status);
+
+/* Parse a card like "As" or "Td" into its byte encoding. */
+int32_t rsp_card_parse(const char *card, uint8_t *out);
+
+/* Rank 5 to 7 cards. */
+int32_t rsp_rank_cards(const uint8_t *cards, size_t len, RspRank *out);
+
+/* Parse a hand like "AsKsQsJsTs" and rank it. */
+int32_t rsp_rank_str(const char *hand, RspRank *out);
+
+/* -1, 0 or 1 as a is weaker than, equal to or stronger than b. */
+int32_t rsp_rank_compare(RspRank a, RspRank b);
+
+/* Parse a comma separated range like "AKs+,QQ+,T9s". */
+int32_t rsp_range_parse(const char *range, RspRange **out);
+
+/* The number of two card combinations in the range. */
+size_t rsp_range_len(const RspRange *range);
+
+/* Copy the two cards of combination idx into out. */
+int32_t rsp_range_get(const RspRange *range, size_t idx, uint8_t out[2]);
+
+/* Free a range. Passing NULL does nothing. */
+void rsp_range_free(RspRange *range);
+
+/*
+ * Estimate the all in equity of num_players holdem hands with Monte Carlo
+ * simulation.
+ *
+ * hole_cards holds two cards per player, board holds 0 to 5 community
+ * cards, and out_equity receives one value per player. Ties are split, so
+ * the equities add up to 1.
+ */
+int32_t rsp_equity(const uint8_t *hole_cards, size_t num_players,
+                   const uint8_t *board, size_t board_len, size_t iterations,
+                   float *out_equity);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RS_POKER_H */
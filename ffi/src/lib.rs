@@ -0,0 +1,467 @@
+//! C bindings for the `rs_poker` hand evaluator, range parser and equity
+//! calculator.
+//!
+//! The matching header is `include/rs_poker.h`; any change to the exported
+//! functions or types here has to be made there as well. Cards cross the
+//! boundary as a single byte (`suit * 13 + value`), the `u8` encoding
+//! described in `rs_poker::core::codec`.
+#![deny(clippy::all)]
+
+use std::ffi::{CStr, c_char};
+use std::ptr;
+
+use rs_poker::core::{Card, CardBitSet, Hand, RSPokerError, Rank, Rankable};
+use rs_poker::holdem::{MonteCarloGame, RangeParser};
+
+pub const RSP_ABI_VERSION: u32 = 1;
+
+pub const RSP_OK: i32 = 0;
+pub const RSP_NULL_POINTER: i32 = -1;
+pub const RSP_INVALID_UTF8: i32 = -2;
+pub const RSP_PARSE_ERROR: i32 = -3;
+pub const RSP_INVALID_CARD: i32 = -4;
+pub const RSP_DUPLICATE_CARD: i32 = -5;
+pub const RSP_INVALID_ARGUMENT: i32 = -6;
+pub const RSP_OUT_OF_BOUNDS: i32 = -7;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RspRank {
+    pub category: u32,
+    pub value: u32,
+}
+
+impl From<Rank> for RspRank {
+    fn from(rank: Rank) -> Self {
+        let (category, value) = match rank {
+            Rank::HighCard(v) => (0, v),
+            Rank::OnePair(v) => (1, v),
+            Rank::TwoPair(v) => (2, v),
+            Rank::ThreeOfAKind(v) => (3, v),
+            Rank::Straight(v) => (4, v),
+            Rank::Flush(v) => (5, v),
+            Rank::FullHouse(v) => (6, v),
+            Rank::FourOfAKind(v) => (7, v),
+            Rank::StraightFlush(v) => (8, v),
+        };
+        Self { category, value }
+    }
+}
+
+/// A parsed hand range, opaque to C.
+pub struct RspRange {
+    combos: Vec<[u8; 2]>,
+}
+
+/// Turn the result of a fallible body into a status code, writing the
+/// value through `out` on success.
+fn write_out<T>(out: *mut T, result: Result<T, i32>) -> i32 {
+    if out.is_null() {
+        return RSP_NULL_POINTER;
+    }
+    match result {
+        Ok(value) => {
+            // Safety: checked for null above, the caller promises that a
+            // non-null `out` is valid for writes.
+            unsafe { out.write(value) };
+            RSP_OK
+        }
+        Err(status) => status,
+    }
+}
+
+/// Borrow a C string as a `&str`.
+///
+/// # Safety
+///
+/// `s` must be null or a valid nul terminated string.
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, i32> {
+    if s.is_null() {
+        return Err(RSP_NULL_POINTER);
+    }
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|_| RSP_INVALID_UTF8)
+}
+
+/// Borrow `len` bytes as a slice. A null pointer is only allowed when
+/// `len` is zero.
+///
+/// # Safety
+///
+/// A non-null `data` must be valid for `len` reads.
+unsafe fn to_slice<'a, T>(data: *const T, len: usize) -> Result<&'a [T], i32> {
+    if len == 0 {
+        Ok(&[])
+    } else if data.is_null() {
+        Err(RSP_NULL_POINTER)
+    } else {
+        Ok(unsafe { std::slice::from_raw_parts(data, len) })
+    }
+}
+
+fn to_card(byte: u8) -> Result<Card, i32> {
+    Card::try_from_u8(byte).map_err(|_| RSP_INVALID_CARD)
+}
+
+/// Add `cards` to `seen`, failing on any card that's already there.
+fn add_cards(seen: &mut CardBitSet, cards: &[u8]) -> Result<Vec<Card>, i32> {
+    cards
+        .iter()
+        .map(|&byte| {
+            let card = to_card(byte)?;
+            if seen.contains(card) {
+                return Err(RSP_DUPLICATE_CARD);
+            }
+            seen.insert(card);
+            Ok(card)
+        })
+        .collect()
+}
+
+fn rank_hand(hand: &Hand) -> Result<RspRank, i32> {
+    if !(5..=7).contains(&hand.count()) {
+        return Err(RSP_INVALID_ARGUMENT);
+    }
+    Ok(hand.rank().into())
+}
+
+fn parse_error(error: RSPokerError) -> i32 {
+    match error {
+        RSPokerError::DuplicateCardInHand(_) => RSP_DUPLICATE_CARD,
+        RSPokerError::HoldemHandSize => RSP_INVALID_ARGUMENT,
+        _ => RSP_PARSE_ERROR,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn rsp_abi_version() -> u32 {
+    RSP_ABI_VERSION
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn rsp_status_message(status: i32) -> *const c_char {
+    let message: &'static CStr = match status {
+        RSP_OK => c"ok",
+        RSP_NULL_POINTER => c"unexpected null pointer",
+        RSP_INVALID_UTF8 => c"string is not valid UTF-8",
+        RSP_PARSE_ERROR => c"unable to parse input",
+        RSP_INVALID_CARD => c"card byte out of range",
+        RSP_DUPLICATE_CARD => c"the same card was used twice",
+        RSP_INVALID_ARGUMENT => c"invalid argument",
+        RSP_OUT_OF_BOUNDS => c"index out of bounds",
+        _ => c"unknown status",
+    };
+    message.as_ptr()
+}
+
+/// # Safety
+///
+/// `card` must be a valid nul terminated string and `out` valid for
+/// writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsp_card_parse(card: *const c_char, out: *mut u8) -> i32 {
+    let result = unsafe { to_str(card) }
+        .and_then(|s| Card::try_from(s).map(u8::from).map_err(|_| RSP_PARSE_ERROR));
+    write_out(out, result)
+}
+
+/// # Safety
+///
+/// `cards` must be valid for `len` reads and `out` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsp_rank_cards(cards: *const u8, len: usize, out: *mut RspRank) -> i32 {
+    let result = unsafe { to_slice(cards, len) }.and_then(|cards| {
+        let cards = add_cards(&mut CardBitSet::new(), cards)?;
+        rank_hand(&Hand::new_with_cards(cards))
+    });
+    write_out(out, result)
+}
+
+/// # Safety
+///
+/// `hand` must be a valid nul terminated string and `out` valid for
+/// writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsp_rank_str(hand: *const c_char, out: *mut RspRank) -> i32 {
+    let result = unsafe { to_str(hand) }
+        .and_then(|s| Hand::new_from_str(s).map_err(parse_error))
+        .and_then(|hand| rank_hand(&hand));
+    write_out(out, result)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn rsp_rank_compare(a: RspRank, b: RspRank) -> i32 {
+    (a.category, a.value).cmp(&(b.category, b.value)) as i32
+}
+
+/// # Safety
+///
+/// `range` must be a valid nul terminated string and `out` valid for
+/// writes. On success the range written to `out` must be freed with
+/// `rsp_range_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsp_range_parse(range: *const c_char, out: *mut *mut RspRange) -> i32 {
+    // Checked up front so a successful parse is never leaked.
+    if out.is_null() {
+        return RSP_NULL_POINTER;
+    }
+    let result = unsafe { to_str(range) }.and_then(|s| {
+        let hands = RangeParser::parse_many(s).map_err(parse_error)?;
+        let combos = hands
+            .iter()
+            .map(|hand| {
+                let mut cards = hand.iter().copied().map(u8::from);
+                [cards.next().unwrap_or(0), cards.next().unwrap_or(0)]
+            })
+            .collect();
+        Ok(Box::into_raw(Box::new(RspRange { combos })))
+    });
+    write_out(out, result)
+}
+
+/// # Safety
+///
+/// `range` must be null or a range returned by `rsp_range_parse`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsp_range_len(range: *const RspRange) -> usize {
+    unsafe { range.as_ref() }.map_or(0, |r| r.combos.len())
+}
+
+/// # Safety
+///
+/// `range` must be null or a range returned by `rsp_range_parse`, and
+/// `out` valid for two writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsp_range_get(range: *const RspRange, idx: usize, out: *mut u8) -> i32 {
+    let Some(range) = (unsafe { range.as_ref() }) else {
+        return RSP_NULL_POINTER;
+    };
+    let result = range.combos.get(idx).copied().ok_or(RSP_OUT_OF_BOUNDS);
+    write_out(out.cast::<[u8; 2]>(), result)
+}
+
+/// # Safety
+///
+/// `range` must be null or a range returned by `rsp_range_parse` that
+/// hasn't been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsp_range_free(range: *mut RspRange) {
+    if !range.is_null() {
+        drop(unsafe { Box::from_raw(range) });
+    }
+}
+
+/// # Safety
+///
+/// `hole_cards` must be valid for `num_players * 2` reads, `board` for
+/// `board_len` reads and `out_equity` for `num_players` writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsp_equity(
+    hole_cards: *const u8,
+    num_players: usize,
+    board: *const u8,
+    board_len: usize,
+    iterations: usize,
+    out_equity: *mut f32,
+) -> i32 {
+    if num_players < 2 || board_len > 5 || iterations == 0 {
+        return RSP_INVALID_ARGUMENT;
+    }
+    let Some(num_cards) = num_players.checked_mul(2) else {
+        return RSP_INVALID_ARGUMENT;
+    };
+    if out_equity.is_null() {
+        return RSP_NULL_POINTER;
+    }
+    let equity = (|| {
+        let hole_cards = unsafe { to_slice(hole_cards, num_cards) }?;
+        let board = unsafe { to_slice(board, board_len) }?;
+
+        let mut seen = CardBitSet::new();
+        let board = add_cards(&mut seen, board)?;
+        let hands = hole_cards
+            .chunks_exact(2)
+            .map(|hole| {
+                let mut cards = add_cards(&mut seen, hole)?;
+                cards.extend_from_slice(&board);
+                Ok(Hand::new_with_cards(cards))
+            })
+            .collect::<Result<Vec<_>, i32>>()?;
+
+        let mut game = MonteCarloGame::new(hands).map_err(parse_error)?;
+        Ok(game.estimate_equity(iterations))
+    })();
+
+    match equity {
+        Ok(equity) => {
+            // Safety: checked for null above, the caller promises room for
+            // one value per player.
+            unsafe { ptr::copy_nonoverlapping(equity.as_ptr(), out_equity, num_players) };
+            RSP_OK
+        }
+        Err(status) => status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::*;
+
+    fn card(s: &str) -> u8 {
+        let s = CString::new(s).unwrap();
+        let mut out = 0;
+        assert_eq!(RSP_OK, unsafe { rsp_card_parse(s.as_ptr(), &mut out) });
+        out
+    }
+
+    fn rank(s: &str) -> RspRank {
+        let s = CString::new(s).unwrap();
+        let mut out = RspRank {
+            category: 0,
+            value: 0,
+        };
+        assert_eq!(RSP_OK, unsafe { rsp_rank_str(s.as_ptr(), &mut out) });
+        out
+    }
+
+    #[test]
+    fn test_card_parse() {
+        assert_eq!(u8::from(Card::try_from("As").unwrap()), card("As"));
+        let mut out = 0;
+        let bad = CString::new("Zz").unwrap();
+        assert_eq!(RSP_PARSE_ERROR, unsafe {
+            rsp_card_parse(bad.as_ptr(), &mut out)
+        });
+        assert_eq!(RSP_NULL_POINTER, unsafe {
+            rsp_card_parse(ptr::null(), &mut out)
+        });
+    }
+
+    #[test]
+    fn test_rank() {
+        let royal = rank("AsKsQsJsTs");
+        assert_eq!(8, royal.category);
+
+        let cards: Vec<u8> = ["2c", "2d", "9h", "9s", "Kd", "3c", "4h"]
+            .iter()
+            .map(|c| card(c))
+            .collect();
+        let mut two_pair = RspRank {
+            category: 0,
+            value: 0,
+        };
+        assert_eq!(RSP_OK, unsafe {
+            rsp_rank_cards(cards.as_ptr(), cards.len(), &mut two_pair)
+        });
+        assert_eq!(2, two_pair.category);
+        assert_eq!(1, rsp_rank_compare(royal, two_pair));
+        assert_eq!(-1, rsp_rank_compare(two_pair, royal));
+        assert_eq!(0, rsp_rank_compare(royal, royal));
+
+        let dupes = [card("As"), card("As"), card("Kd"), card("2c"), card("3c")];
+        assert_eq!(RSP_DUPLICATE_CARD, unsafe {
+            rsp_rank_cards(dupes.as_ptr(), dupes.len(), &mut two_pair)
+        });
+        assert_eq!(RSP_INVALID_ARGUMENT, unsafe {
+            rsp_rank_cards(cards.as_ptr(), 3, &mut two_pair)
+        });
+        let bad = [52, 1, 2, 3, 4];
+        assert_eq!(RSP_INVALID_CARD, unsafe {
+            rsp_rank_cards(bad.as_ptr(), bad.len(), &mut two_pair)
+        });
+    }
+
+    #[test]
+    fn test_range() {
+        let s = CString::new("KQo+,AA").unwrap();
+        let mut range = ptr::null_mut();
+        assert_eq!(RSP_OK, unsafe { rsp_range_parse(s.as_ptr(), &mut range) });
+        // 24 off suit KQ+ combos and 6 combos of aces.
+        assert_eq!(30, unsafe { rsp_range_len(range) });
+
+        let mut combo = [0_u8; 2];
+        assert_eq!(RSP_OK, unsafe {
+            rsp_range_get(range, 29, combo.as_mut_ptr())
+        });
+        assert!(combo.iter().all(|&c| c < 52));
+        assert_ne!(combo[0], combo[1]);
+        assert_eq!(RSP_OUT_OF_BOUNDS, unsafe {
+            rsp_range_get(range, 30, combo.as_mut_ptr())
+        });
+        unsafe { rsp_range_free(range) };
+        unsafe { rsp_range_free(ptr::null_mut()) };
+
+        let bad = CString::new("KZ").unwrap();
+        assert_eq!(RSP_PARSE_ERROR, unsafe {
+            rsp_range_parse(bad.as_ptr(), &mut range)
+        });
+    }
+
+    #[test]
+    fn test_equity() {
+        let hole = [card("As"), card("Ah"), card("7c"), card("2d")];
+        let mut equity = [0.0_f32; 2];
+        assert_eq!(RSP_OK, unsafe {
+            rsp_equity(hole.as_ptr(), 2, ptr::null(), 0, 2_000, equity.as_mut_ptr())
+        });
+        assert!(equity[0] > 0.75);
+        assert!((equity[0] + equity[1] - 1.0).abs() < 1e-3);
+
+        // On this river the seven high has made a full house.
+        let board = [card("7d"), card("7h"), card("2c"), card("3s"), card("9s")];
+        assert_eq!(RSP_OK, unsafe {
+            rsp_equity(hole.as_ptr(), 2, board.as_ptr(), 5, 10, equity.as_mut_ptr())
+        });
+        assert_eq!([0.0, 1.0], equity);
+
+        let overlap = [card("As"), card("Ah"), card("As"), card("2d")];
+        assert_eq!(RSP_DUPLICATE_CARD, unsafe {
+            rsp_equity(overlap.as_ptr(), 2, ptr::null(), 0, 10, equity.as_mut_ptr())
+        });
+        assert_eq!(RSP_INVALID_ARGUMENT, unsafe {
+            rsp_equity(hole.as_ptr(), 1, ptr::null(), 0, 10, equity.as_mut_ptr())
+        });
+    }
+
+    #[test]
+    fn test_status_messages() {
+        for status in [
+            RSP_OK,
+            RSP_NULL_POINTER,
+            RSP_INVALID_UTF8,
+            RSP_PARSE_ERROR,
+            RSP_INVALID_CARD,
+            RSP_DUPLICATE_CARD,
+            RSP_INVALID_ARGUMENT,
+            RSP_OUT_OF_BOUNDS,
+        ] {
+            let message = unsafe { CStr::from_ptr(rsp_status_message(status)) };
+            assert_ne!("unknown status", message.to_str().unwrap());
+        }
+    }
+
+    /// Everything exported has to be declared in the C header.
+    #[test]
+    fn test_header_matches() {
+        let header = include_str!("../include/rs_poker.h");
+        for name in [
+            "rsp_abi_version",
+            "rsp_status_message",
+            "rsp_card_parse",
+            "rsp_rank_cards",
+            "rsp_rank_str",
+            "rsp_rank_compare",
+            "rsp_range_parse",
+            "rsp_range_len",
+            "rsp_range_get",
+            "rsp_range_free",
+            "rsp_equity",
+        ] {
+            assert!(header.contains(&format!("{name}(")), "{name} missing");
+        }
+        assert!(header.contains(&format!("#define RSP_ABI_VERSION {RSP_ABI_VERSION}")));
+    }
+}
@@ -0,0 +1,11 @@
+#![no_main]
+
+extern crate libfuzzer_sys;
+extern crate rs_poker;
+
+use libfuzzer_sys::fuzz_target;
+use rs_poker::arena::fuzz::EngineInput;
+
+fuzz_target!(|input: EngineInput| {
+    input.run();
+});
@@ -0,0 +1,384 @@
+//! Python bindings for `rs_poker`.
+//!
+//! Built into a wheel with `maturin build` (or `maturin develop` for a
+//! local virtualenv) from this directory, then imported as `rs_poker`:
+//!
+//! ```python
+//! import rs_poker
+//!
+//! rs_poker.rank_hand("AsKsQsJsTs").category   # "StraightFlush"
+//! rs_poker.equity(["AsAh", "7c2d"], iterations=10_000)
+//! rs_poker.parse_range("KQo+,AA")
+//! rs_poker.simulate([100.0, 100.0], 10.0, 5.0, agents=["calling", "random"])
+//! ```
+#![deny(clippy::all)]
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use rs_poker::arena::agent::{AllInAgent, CallingAgent, FoldingAgent, RandomAgent};
+use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+use rs_poker::core::{self, RSPokerError, Rankable};
+use rs_poker::holdem::{MonteCarloGame, RangeParser};
+
+fn value_error(error: impl ToString) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// A single playing card, for example `Card("As")`.
+#[pyclass(module = "rs_poker", frozen, eq, ord, hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Card(core::Card);
+
+#[pymethods]
+impl Card {
+    #[new]
+    pub fn new(card: &str) -> PyResult<Self> {
+        core::Card::try_from(card).map(Self).map_err(value_error)
+    }
+
+    /// The card from its integer encoding, `suit * 13 + value`.
+    #[staticmethod]
+    pub fn from_int(card: u8) -> PyResult<Self> {
+        if card < 52 {
+            Ok(Self(core::Card::from(card)))
+        } else {
+            Err(PyValueError::new_err("card must be in 0..52"))
+        }
+    }
+
+    pub fn to_int(&self) -> u8 {
+        u8::from(self.0)
+    }
+
+    /// The value character, `2` through `A`.
+    #[getter]
+    pub fn value(&self) -> char {
+        char::from(self.0.value)
+    }
+
+    /// The suit character, one of `s`, `c`, `h` or `d`.
+    #[getter]
+    pub fn suit(&self) -> char {
+        char::from(self.0.suit)
+    }
+
+    pub fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!("Card('{}')", self.0)
+    }
+}
+
+/// The strength of a made hand. Ranks compare the way hands do, higher is
+/// better.
+#[pyclass(module = "rs_poker", frozen, eq, ord, hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rank(core::Rank);
+
+#[pymethods]
+impl Rank {
+    /// The hand category, for example `"TwoPair"`.
+    #[getter]
+    pub fn category(&self) -> &'static str {
+        self.parts().0
+    }
+
+    /// Breaks ties between hands of the same category, higher is better.
+    #[getter]
+    pub fn value(&self) -> u32 {
+        self.parts().1
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!("Rank({}, {})", self.category(), self.value())
+    }
+}
+
+impl Rank {
+    fn parts(&self) -> (&'static str, u32) {
+        match self.0 {
+            core::Rank::HighCard(v) => ("HighCard", v),
+            core::Rank::OnePair(v) => ("OnePair", v),
+            core::Rank::TwoPair(v) => ("TwoPair", v),
+            core::Rank::ThreeOfAKind(v) => ("ThreeOfAKind", v),
+            core::Rank::Straight(v) => ("Straight", v),
+            core::Rank::Flush(v) => ("Flush", v),
+            core::Rank::FullHouse(v) => ("FullHouse", v),
+            core::Rank::FourOfAKind(v) => ("FourOfAKind", v),
+            core::Rank::StraightFlush(v) => ("StraightFlush", v),
+        }
+    }
+}
+
+/// A set of cards, for example `Hand("AsKd")`.
+#[pyclass(module = "rs_poker", eq)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hand(core::Hand);
+
+#[pymethods]
+impl Hand {
+    #[new]
+    #[pyo3(signature = (cards = ""))]
+    pub fn new(cards: &str) -> PyResult<Self> {
+        core::Hand::new_from_str(cards)
+            .map(Self)
+            .map_err(value_error)
+    }
+
+    pub fn add(&mut self, card: Card) {
+        self.0.insert(card.0);
+    }
+
+    pub fn cards(&self) -> Vec<Card> {
+        self.0.iter().map(Card).collect()
+    }
+
+    /// Rank the best five card hand. The hand needs at least five cards.
+    pub fn rank(&self) -> PyResult<Rank> {
+        if self.0.count() < 5 {
+            return Err(PyValueError::new_err("a hand needs five cards to rank"));
+        }
+        Ok(Rank(self.0.rank()))
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.0.count()
+    }
+
+    pub fn __contains__(&self, card: Card) -> bool {
+        self.0.contains(&card.0)
+    }
+
+    pub fn __str__(&self) -> String {
+        self.0.iter().map(|c| c.to_string()).collect()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!("Hand('{}')", self.__str__())
+    }
+}
+
+/// Rank a hand of five to seven cards, for example `"AsKsQsJsTs"`.
+#[pyfunction]
+pub fn rank_hand(hand: &str) -> PyResult<Rank> {
+    Hand::new(hand)?.rank()
+}
+
+/// Every two card combination in a comma separated range like
+/// `"KQo+,AA"`.
+#[pyfunction]
+pub fn parse_range(range: &str) -> PyResult<Vec<(Card, Card)>> {
+    let hands = RangeParser::parse_many(range).map_err(value_error)?;
+    Ok(hands
+        .iter()
+        .map(|hand| (Card(hand[0]), Card(hand[1])))
+        .collect())
+}
+
+/// Estimate the all in equity of holdem hands with Monte Carlo simulation.
+/// Ties are split so the equities add up to one.
+#[pyfunction]
+#[pyo3(signature = (hands, board = "", iterations = 10_000))]
+pub fn equity(hands: Vec<String>, board: &str, iterations: usize) -> PyResult<Vec<f32>> {
+    if hands.len() < 2 || iterations == 0 {
+        return Err(PyValueError::new_err(
+            "equity needs at least two hands and one iteration",
+        ));
+    }
+    let board = core::Hand::new_from_str(board).map_err(value_error)?;
+    let mut seen = board;
+    let hands = hands
+        .iter()
+        .map(|hole| {
+            let mut hole = core::Hand::new_from_str(hole)?;
+            if let Some(card) = hole.iter().find(|card| seen.contains(card)) {
+                return Err(RSPokerError::DuplicateCardInHand(card));
+            }
+            seen.extend(hole.iter());
+            hole.extend(board.iter());
+            Ok(hole)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(value_error)?;
+
+    let mut game = MonteCarloGame::new(hands).map_err(value_error)?;
+    Ok(game.estimate_equity(iterations))
+}
+
+/// The outcome of a single simulated hand.
+#[pyclass(module = "rs_poker", get_all, frozen)]
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    pub id: u128,
+    pub starting_stacks: Vec<f32>,
+    pub stacks: Vec<f32>,
+    pub player_winnings: Vec<f32>,
+    pub board: Vec<Card>,
+    /// Each player's hole cards.
+    pub hands: Vec<Vec<Card>>,
+}
+
+fn make_agent(name: &str) -> PyResult<Box<dyn Agent>> {
+    Ok(match name {
+        "calling" => Box::<CallingAgent>::default(),
+        "folding" => Box::<FoldingAgent>::default(),
+        "all_in" => Box::<AllInAgent>::default(),
+        "random" => Box::<RandomAgent>::default(),
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "unknown agent {name:?}, expected calling, folding, all_in or random"
+            )));
+        }
+    })
+}
+
+/// Play a single hand of holdem between simple built in agents. `agents`
+/// names one agent per seat (`"calling"`, `"folding"`, `"all_in"` or
+/// `"random"`) and defaults to all random.
+#[pyfunction]
+#[pyo3(signature = (stacks, big_blind, small_blind, ante = 0.0, dealer_idx = 0, agents = None))]
+pub fn simulate(
+    stacks: Vec<f32>,
+    big_blind: f32,
+    small_blind: f32,
+    ante: f32,
+    dealer_idx: usize,
+    agents: Option<Vec<String>>,
+) -> PyResult<SimulationResult> {
+    let num_players = stacks.len();
+    if !(2..=9).contains(&num_players) || dealer_idx >= num_players {
+        return Err(PyValueError::new_err(
+            "simulate needs 2 to 9 stacks and a dealer in range",
+        ));
+    }
+    let names = agents.unwrap_or_else(|| vec!["random".to_string(); num_players]);
+    if names.len() != num_players {
+        return Err(PyValueError::new_err("expected one agent per stack"));
+    }
+    let agents = names
+        .iter()
+        .map(|name| make_agent(name))
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let game_state = GameState::new_starting(stacks, big_blind, small_blind, ante, dealer_idx);
+    let mut sim = HoldemSimulationBuilder::default()
+        .game_state(game_state)
+        .agents(agents)
+        .build()
+        .map_err(value_error)?;
+    sim.run(&mut rand::rng());
+
+    let state = &sim.game_state;
+    let board = state.board_set();
+    let hands = state
+        .hands
+        .iter()
+        .map(|hand| {
+            core::CardSet::from(*hand)
+                .difference(board)
+                .iter()
+                .map(Card)
+                .collect()
+        })
+        .collect();
+    Ok(SimulationResult {
+        id: sim.id,
+        starting_stacks: state.starting_stacks.to_vec(),
+        stacks: state.stacks.clone(),
+        player_winnings: state.player_winnings.clone(),
+        board: state.board.iter().copied().map(Card).collect(),
+        hands,
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "rs_poker")]
+fn rs_poker_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Card>()?;
+    m.add_class::<Hand>()?;
+    m.add_class::<Rank>()?;
+    m.add_class::<SimulationResult>()?;
+    m.add_function(wrap_pyfunction!(rank_hand, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_range, m)?)?;
+    m.add_function(wrap_pyfunction!(equity, m)?)?;
+    m.add_function(wrap_pyfunction!(simulate, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card() {
+        let card = Card::new("Td").unwrap();
+        assert_eq!('T', card.value());
+        assert_eq!('d', card.suit());
+        assert_eq!(card, Card::from_int(card.to_int()).unwrap());
+        assert_eq!("Card('Td')", card.__repr__());
+        assert!(Card::new("1x").is_err());
+        assert!(Card::from_int(52).is_err());
+    }
+
+    #[test]
+    fn test_rank() {
+        let royal = rank_hand("AsKsQsJsTs").unwrap();
+        assert_eq!("StraightFlush", royal.category());
+        let pair = rank_hand("AsAdQs2c3d").unwrap();
+        assert_eq!("OnePair", pair.category());
+        assert!(royal > pair);
+
+        let mut hand = Hand::new("AsAd").unwrap();
+        assert!(hand.rank().is_err());
+        for card in ["Ac", "2d", "2h"] {
+            hand.add(Card::new(card).unwrap());
+        }
+        assert_eq!(5, hand.__len__());
+        assert_eq!("FullHouse", hand.rank().unwrap().category());
+    }
+
+    #[test]
+    fn test_parse_range() {
+        let combos = parse_range("KQo+,AA").unwrap();
+        assert_eq!(30, combos.len());
+        assert!(combos.iter().all(|(a, b)| a != b));
+        assert!(parse_range("KZ").is_err());
+    }
+
+    #[test]
+    fn test_equity() {
+        let equity = equity(vec!["AsAh".into(), "7c2d".into()], "", 2_000).unwrap();
+        assert!(equity[0] > 0.75);
+        assert!((equity[0] + equity[1] - 1.0).abs() < 1e-3);
+
+        let river = super::equity(vec!["AsAh".into(), "7c2d".into()], "7d7h2c3s9s", 10).unwrap();
+        assert_eq!(vec![0.0, 1.0], river);
+
+        assert!(super::equity(vec!["AsAh".into(), "AsKd".into()], "", 10).is_err());
+        assert!(super::equity(vec!["AsAh".into()], "", 10).is_err());
+    }
+
+    #[test]
+    fn test_simulate() {
+        let result = simulate(
+            vec![100.0; 3],
+            10.0,
+            5.0,
+            0.0,
+            0,
+            Some(vec!["calling".into(), "calling".into(), "all_in".into()]),
+        )
+        .unwrap();
+        assert_eq!(3, result.hands.len());
+        assert!(result.hands.iter().all(|h| h.len() == 2));
+        assert_eq!(5, result.board.len());
+        let total: f32 = result.stacks.iter().sum();
+        assert_eq!(300.0, total);
+
+        assert!(simulate(vec![100.0; 2], 10.0, 5.0, 0.0, 0, Some(vec!["x".into(); 2])).is_err());
+        assert!(simulate(vec![100.0], 10.0, 5.0, 0.0, 0, None).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+-- Tables written by PostgresHistorian (the `postgres` feature).
+--
+-- One row in poker_hands per completed hand, and one row in poker_actions
+-- for every action of that hand, in the order they happened. Cards are
+-- written like "As", boards and hole cards as concatenated cards
+-- ("AsKd7c"). The full action, as the same JSON the rest of the crate
+-- uses, is kept in poker_actions.details.
+--
+-- PostgresHistorian::create_schema runs this file. Every statement is
+-- idempotent so it's safe to run on every start.
+
+CREATE TABLE IF NOT EXISTS poker_hands (
+    simulation_id UUID PRIMARY KEY,
+    num_players SMALLINT NOT NULL,
+    dealer_idx SMALLINT NOT NULL,
+    small_blind REAL NOT NULL,
+    big_blind REAL NOT NULL,
+    ante REAL NOT NULL,
+    board TEXT NOT NULL,
+    hole_cards TEXT[] NOT NULL,
+    starting_stacks REAL[] NOT NULL,
+    final_stacks REAL[] NOT NULL,
+    player_winnings REAL[] NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS poker_actions (
+    simulation_id UUID NOT NULL REFERENCES poker_hands (simulation_id) ON DELETE CASCADE,
+    -- The position of the action in the hand, starting at zero.
+    seq INTEGER NOT NULL,
+    -- The round the game was in after the action, like "Preflop".
+    round TEXT NOT NULL,
+    -- The action type, like "played_action" or "award".
+    kind TEXT NOT NULL,
+    -- The seat the action is about, if any.
+    player_idx SMALLINT,
+    -- The chips involved: the player's total bet in the round after a
+    -- played action, the size of a forced bet, or the amount awarded.
+    amount REAL,
+    details JSONB NOT NULL,
+    PRIMARY KEY (simulation_id, seq)
+);
+
+CREATE INDEX IF NOT EXISTS poker_actions_kind ON poker_actions (kind);
@@ -45,6 +45,8 @@ pub enum ForcedBetType {
     Ante,
     SmallBlind,
     BigBlind,
+    /// The stud bet made by the player showing the lowest card.
+    BringIn,
 }
 
 /// A player tried to play an action and failed
@@ -113,6 +115,26 @@ pub struct AwardPayload {
     pub idx: usize,
 }
 
+/// A player's hole cards at showdown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShowHandPayload {
+    pub idx: usize,
+    /// The hole cards the player showed, or `None` if they mucked.
+    pub hand: Option<Hand>,
+}
+
+/// A player in a draw game threw away cards and was dealt new ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DrawPayload {
+    pub idx: usize,
+    pub discarded: Hand,
+    /// The cards dealt in place of the discards, as many as were thrown
+    /// away unless the deck ran out.
+    pub drawn: Hand,
+}
+
 /// Represents an action that can happen in a game.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -137,6 +159,11 @@ pub enum Action {
     DealCommunity(Card),
     /// There was some pot given to a player
     Award(AwardPayload),
+    /// A player at showdown showed or mucked their hand. Players show in
+    /// showdown order, before any pot is awarded.
+    ShowHand(ShowHandPayload),
+    /// A player in a draw game drew. Standing pat is a draw of no cards.
+    Draw(DrawPayload),
 }
 
 #[cfg(test)]
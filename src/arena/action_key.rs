@@ -0,0 +1,281 @@
+//! Compact, canonical keys for the betting in a hand.
+//!
+//! Two hands that were bet the same way get the same key, whoever held
+//! what cards, so keys can name a betting line for looking up a strategy or
+//! for telling spots apart. A key is a short ASCII string with one token
+//! per action and a `/` between streets:
+//!
+//! - `f` fold
+//! - `x` check
+//! - `c` call, all in or not
+//! - `b` bet or raise, followed by its size unless sizes are ignored
+//! - `a` bet or raise all in
+//!
+//! Blinds and antes aren't part of the key.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::action::AgentAction;
+//! use rs_poker::arena::action_key::{ActionKeyEncoder, BetSizing};
+//! use rs_poker::arena::agent::{CallingAgent, VecReplayAgent};
+//! use rs_poker::arena::historian::VecHistorian;
+//! use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+//!
+//! let historian = VecHistorian::default();
+//! let records = historian.get_storage();
+//! let agents: Vec<Box<dyn Agent>> = vec![
+//!     // The button opens to three big blinds, then checks it down.
+//!     Box::new(VecReplayAgent::new_with_default(
+//!         vec![AgentAction::Bet(6.0)],
+//!         AgentAction::Bet(0.0),
+//!     )),
+//!     Box::<CallingAgent>::default(),
+//! ];
+//! let mut sim = HoldemSimulationBuilder::default()
+//!     .game_state(GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0))
+//!     .agents(agents)
+//!     .historians(vec![Box::new(historian)])
+//!     .build()
+//!     .unwrap();
+//! sim.run(&mut rand::rng());
+//!
+//! let records = records.borrow();
+//! let actions = records.iter().map(|record| &record.action);
+//! let key = ActionKeyEncoder::encode(BetSizing::Exact, actions.clone());
+//! assert_eq!("b3c/xx/xx/xx", key.as_str());
+//! let key = ActionKeyEncoder::encode(BetSizing::Ignore, actions);
+//! assert_eq!("bc/xx/xx/xx", key.as_str());
+//! ```
+use std::fmt;
+
+use super::action::{Action, PlayedActionPayload};
+use super::game_state::Round;
+
+/// How the size of a bet or raise goes into a key. The coarser the sizing
+/// the more lines share a key.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BetSizing {
+    /// What the bet was to in big blinds, to two decimal places.
+    #[default]
+    Exact,
+    /// How much the bet went up by as a fraction of the pot after calling,
+    /// rounded to the nearest of these fractions and written as a percent.
+    PotFractions(Vec<f32>),
+    /// Only that there was a bet or raise, not how big.
+    Ignore,
+}
+
+impl BetSizing {
+    /// A third, half, three quarters, the pot, one and a half and twice the
+    /// pot.
+    pub fn standard_pot_fractions() -> Self {
+        BetSizing::PotFractions(vec![0.33, 0.5, 0.75, 1.0, 1.5, 2.0])
+    }
+}
+
+/// The key of a betting line. See the module docs for the format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionKey(String);
+
+impl ActionKey {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for ActionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Default for ActionKeyEncoder {
+    fn default() -> Self {
+        Self::new(BetSizing::default())
+    }
+}
+
+/// Builds an [`ActionKey`] one action at a time, so a historian can keep
+/// the key of the hand so far as actions come in.
+#[derive(Debug, Clone)]
+pub struct ActionKeyEncoder {
+    sizing: BetSizing,
+    big_blind: f32,
+    round: Option<Round>,
+    key: String,
+}
+
+impl ActionKeyEncoder {
+    pub fn new(sizing: BetSizing) -> Self {
+        Self {
+            sizing,
+            big_blind: 1.0,
+            round: None,
+            key: String::new(),
+        }
+    }
+
+    /// The key of every action in `actions`.
+    pub fn encode<'a>(
+        sizing: BetSizing,
+        actions: impl IntoIterator<Item = &'a Action>,
+    ) -> ActionKey {
+        let mut encoder = Self::new(sizing);
+        for action in actions {
+            encoder.push(action);
+        }
+        encoder.key()
+    }
+
+    /// Add an action to the key. A new game starts a new key, and anything
+    /// that isn't a player's decision is skipped.
+    pub fn push(&mut self, action: &Action) {
+        match action {
+            Action::GameStart(payload) => {
+                self.big_blind = payload.big_blind;
+                self.round = None;
+                self.key.clear();
+            }
+            Action::PlayedAction(payload) => self.push_played(payload),
+            Action::FailedAction(payload) => self.push_played(&payload.result),
+            _ => {}
+        }
+    }
+
+    /// The key of the actions so far.
+    pub fn key(&self) -> ActionKey {
+        ActionKey(self.key.clone())
+    }
+
+    fn push_played(&mut self, payload: &PlayedActionPayload) {
+        if self.round.is_some_and(|round| round != payload.round) {
+            self.key.push('/');
+        }
+        self.round = Some(payload.round);
+
+        if payload.final_bet > payload.starting_bet {
+            if payload.player_stack <= 0.0 {
+                self.key.push('a');
+            } else {
+                self.key.push('b');
+                self.push_size(payload);
+            }
+        } else if payload.final_player_bet > payload.starting_player_bet {
+            self.key.push('c');
+        } else if payload.final_bet > payload.final_player_bet {
+            self.key.push('f');
+        } else {
+            self.key.push('x');
+        }
+    }
+
+    fn push_size(&mut self, payload: &PlayedActionPayload) {
+        match &self.sizing {
+            BetSizing::Exact => {
+                let big_blinds = payload.final_bet / self.big_blind.max(f32::EPSILON);
+                let size = format!("{big_blinds:.2}");
+                let size = size.trim_end_matches('0').trim_end_matches('.');
+                self.key.push_str(size);
+            }
+            BetSizing::PotFractions(fractions) => {
+                let to_call = payload.starting_bet - payload.starting_player_bet;
+                let pot = (payload.starting_pot + to_call).max(f32::EPSILON);
+                let fraction = payload.raise_amount() / pot;
+                let bucket = fractions
+                    .iter()
+                    .copied()
+                    .min_by(|a, b| (a - fraction).abs().total_cmp(&(b - fraction).abs()))
+                    .unwrap_or(fraction);
+                self.key.push_str(&format!("{}", (bucket * 100.0).round()));
+            }
+            BetSizing::Ignore => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::action::AgentAction;
+    use crate::arena::agent::VecReplayAgent;
+    use crate::arena::historian::VecHistorian;
+    use crate::arena::{Agent, GameState, HoldemSimulationBuilder};
+
+    /// The actions of a three handed hand where each seat plays its list
+    /// of actions and then folds.
+    fn played(actions: [Vec<AgentAction>; 3]) -> Vec<Action> {
+        let historian = VecHistorian::default();
+        let records = historian.get_storage();
+        let agents: Vec<Box<dyn Agent>> = actions
+            .into_iter()
+            .map(|actions| Box::new(VecReplayAgent::new(actions)) as Box<dyn Agent>)
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.5, 0))
+            .agents(agents)
+            .historians(vec![Box::new(historian)])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        records.borrow().iter().map(|r| r.action.clone()).collect()
+    }
+
+    #[test]
+    fn test_bet_sizes() {
+        use AgentAction::*;
+        // The button opens, the small blind three bets, the big blind folds
+        // and the button calls. On the flop the small blind bets half the
+        // pot, the button shoves and the small blind calls.
+        let actions = played([
+            vec![Bet(5.0), Bet(15.0), AllIn],
+            vec![Bet(15.0), Bet(17.0), AllIn],
+            vec![Fold],
+        ]);
+
+        let key = ActionKeyEncoder::encode(BetSizing::Exact, &actions);
+        assert_eq!("b2.5b7.5fc/b8.5ac", key.as_str());
+        // The open is 3 chips more than the 6.5 chip pot after calling and
+        // the three bet 10 more than 13.5.
+        let key = ActionKeyEncoder::encode(BetSizing::standard_pot_fractions(), &actions);
+        assert_eq!("b50b75fc/b50ac", key.as_str());
+        let key = ActionKeyEncoder::encode(BetSizing::Ignore, &actions);
+        assert_eq!("bbfc/bac", key.as_str());
+    }
+
+    #[test]
+    fn test_keys_are_per_hand() {
+        let first = played([vec![AgentAction::Fold], vec![AgentAction::Fold], vec![]]);
+        let mut encoder = ActionKeyEncoder::new(BetSizing::Exact);
+        for action in &first {
+            encoder.push(action);
+        }
+        assert_eq!("ff", encoder.key().as_str());
+
+        // Failed actions count as what the simulation turned them into: a
+        // fold with nothing to call is a check.
+        let second = played([
+            vec![AgentAction::Bet(2.0)],
+            vec![AgentAction::Bet(2.0)],
+            vec![AgentAction::Fold, AgentAction::Fold],
+        ]);
+        for action in &second {
+            encoder.push(action);
+        }
+        assert!(encoder.key().as_str().starts_with("ccx/"));
+        assert_eq!(
+            ActionKeyEncoder::encode(BetSizing::Exact, &second),
+            encoder.key()
+        );
+    }
+}
@@ -6,6 +6,7 @@ use super::{Agent, AgentGenerator};
 /// stand in for a player who is a calling
 /// station for the rest of a hand.
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AllInAgent;
 
 impl Agent for AllInAgent {
@@ -16,6 +17,7 @@ impl Agent for AllInAgent {
 
 /// Default `AgentGenerator` for `AllInAgent`.
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AllInAgentGenerator;
 
 impl AgentGenerator for AllInAgentGenerator {
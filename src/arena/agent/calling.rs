@@ -6,6 +6,7 @@ use super::{Agent, AgentGenerator};
 /// stand in for a player who is a calling
 /// station for the rest of a hand.
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CallingAgent;
 
 impl Agent for CallingAgent {
@@ -16,6 +17,7 @@ impl Agent for CallingAgent {
 
 /// Default `AgentGenerator` for `CallingAgent`.
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CallingAgentGenerator;
 
 impl AgentGenerator for CallingAgentGenerator {
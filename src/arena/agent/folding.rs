@@ -4,6 +4,7 @@ use super::{Agent, AgentGenerator};
 
 /// A simple agent that folds unless there is only one active player left.
 #[derive(Default, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FoldingAgent;
 
 impl Agent for FoldingAgent {
@@ -19,6 +20,7 @@ impl Agent for FoldingAgent {
 
 /// Default Generator for `FoldingAgent`.
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FoldingAgentGenerator;
 
 impl AgentGenerator for FoldingAgentGenerator {
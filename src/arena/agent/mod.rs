@@ -9,6 +9,7 @@ mod random;
 mod replay;
 
 use super::{Historian, action::AgentAction, game_state::GameState};
+use crate::core::Hand;
 /// This is the trait that you need to implement in order to implenet
 /// different strategies. It's up to you to to implement the logic and state.
 ///
@@ -19,6 +20,14 @@ pub trait Agent {
     /// This is the method that will be called by the game to get the action
     fn act(&mut self, id: u128, game_state: &GameState) -> AgentAction;
 
+    /// In a draw game this is called when it's the agent's turn to draw,
+    /// to pick the cards to throw away. The agent is the player to act in
+    /// the game state, and cards that aren't in its hand are ignored. By
+    /// default the agent stands pat.
+    fn discard(&mut self, _id: u128, _game_state: &GameState) -> Hand {
+        Hand::default()
+    }
+
     // Some Agents may need to be able to see the changes in the game
     // state. This is the method that will be called to create historians
     // when starting a new simulation game.
@@ -1,17 +1,18 @@
-use rand::{Rng, rng};
+use rand::Rng;
 
 use crate::{
     arena::{
         action::AgentAction,
         game_state::{GameState, Round},
     },
-    core::Hand,
+    core::{Hand, rng},
     holdem::MonteCarloGame,
 };
 
 use super::{Agent, AgentGenerator};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandomAgent {
     percent_fold: Vec<f64>,
     percent_call: Vec<f64>,
@@ -91,6 +92,8 @@ impl Agent for RandomAgent {
     }
 }
 
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandomAgentGenerator {
     percent_fold: Vec<f64>,
     percent_call: Vec<f64>,
@@ -122,6 +125,7 @@ impl Default for RandomAgentGenerator {
 /// The percent_call is the percent that the agent will not bet even though it
 /// values the pot above the current bet or 0 if it's the first to act.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandomPotControlAgent {
     percent_call: Vec<f64>,
 }
@@ -143,8 +147,8 @@ impl RandomPotControlAgent {
         let to_act_idx = game_state.to_act_idx();
         game_state
             .hands
-            .clone()
-            .into_iter()
+            .iter()
+            .copied()
             .enumerate()
             .map(|(hand_idx, hand)| {
                 if hand_idx == to_act_idx {
@@ -261,7 +265,7 @@ mod tests {
         ];
 
         // Add two random cards to every hand.
-        for hand in game_state.hands.iter_mut() {
+        for hand in game_state.hands_mut().iter_mut() {
             hand.insert(deck.deal(&mut rng).unwrap());
             hand.insert(deck.deal(&mut rng).unwrap());
         }
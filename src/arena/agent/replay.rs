@@ -6,6 +6,7 @@ use super::Agent;
 /// from a vector. It consumes the vector making it fast but
 /// hard to reuse or introspect what actions were taken.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VecReplayAgent {
     actions: Vec<AgentAction>,
     idx: usize,
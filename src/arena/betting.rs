@@ -0,0 +1,184 @@
+//! Betting structures that limit how much a player can bet.
+//!
+//! [`GameState`] only knows no limit betting, where a player can bet up to
+//! their whole stack. A [`HoldemSimulation`](super::HoldemSimulation) built
+//! with another structure cuts agents' bets down to what the structure
+//! allows before playing them.
+use super::GameState;
+use super::action::AgentAction;
+use super::game_state::Round;
+
+/// How much a player may bet.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BettingStructure {
+    /// Anything up to the player's stack.
+    #[default]
+    NoLimit,
+    /// Raises up to the size of the pot after calling.
+    PotLimit,
+    /// Every bet and raise is the small bet before the turn and the big bet
+    /// from the turn on, until the street reaches `cap` bets. In stud the
+    /// small bet is for third and fourth street, and in draw games for the
+    /// betting before the draw and after the first.
+    FixedLimit {
+        small_bet: f32,
+        big_bet: f32,
+        cap: u8,
+    },
+}
+
+impl BettingStructure {
+    /// Fixed limit with the big bet twice the small bet and four bets a
+    /// street.
+    pub fn fixed_limit(small_bet: f32) -> Self {
+        BettingStructure::FixedLimit {
+            small_bet,
+            big_bet: small_bet * 2.0,
+            cap: 4,
+        }
+    }
+
+    /// The largest total for the round the player to act can bet, `None` if
+    /// only their stack limits it.
+    ///
+    /// ```
+    /// use rs_poker::arena::GameState;
+    /// use rs_poker::arena::betting::BettingStructure;
+    ///
+    /// let mut game_state = GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.0, 0);
+    /// game_state.advance_round(); // Ante
+    /// game_state.advance_round(); // Deal preflop
+    /// game_state.advance_round(); // Preflop
+    /// game_state.do_bet(1.0, true).unwrap();
+    /// game_state.do_bet(2.0, true).unwrap();
+    ///
+    /// assert_eq!(None, BettingStructure::NoLimit.max_bet(&game_state));
+    /// // Call 2 to make the pot 5, then raise 5.
+    /// assert_eq!(Some(7.0), BettingStructure::PotLimit.max_bet(&game_state));
+    /// assert_eq!(
+    ///     Some(4.0),
+    ///     BettingStructure::fixed_limit(2.0).max_bet(&game_state)
+    /// );
+    /// ```
+    pub fn max_bet(&self, game_state: &GameState) -> Option<f32> {
+        let call = game_state.current_round_bet();
+        match *self {
+            BettingStructure::NoLimit => None,
+            BettingStructure::PotLimit => {
+                let to_call = call - game_state.current_round_current_player_bet();
+                Some(call + game_state.total_pot + to_call)
+            }
+            BettingStructure::FixedLimit {
+                small_bet,
+                big_bet,
+                cap,
+            } => {
+                let bet_level = game_state
+                    .street(game_state.round)
+                    .map_or(0, |street| street.bet_level());
+                let size = match game_state.round {
+                    Round::Preflop
+                    | Round::Flop
+                    | Round::ThirdStreet
+                    | Round::FourthStreet
+                    | Round::FirstDraw => small_bet,
+                    _ => big_bet,
+                };
+                Some(if bet_level >= cap {
+                    call
+                } else if bet_level == 0 {
+                    // The first bet, which in stud completes the bring-in.
+                    size.max(call)
+                } else {
+                    call + size
+                })
+            }
+        }
+    }
+
+    /// The action to play in place of `action` so that it keeps to the
+    /// structure. Bets over the limit are cut down to it, and in fixed limit
+    /// any raise is made the fixed size.
+    pub fn constrain(&self, game_state: &GameState, action: AgentAction) -> AgentAction {
+        let Some(max) = self.max_bet(game_state) else {
+            return action;
+        };
+        let all_in =
+            game_state.current_round_current_player_bet() + game_state.current_player_stack();
+        let is_fixed = matches!(self, BettingStructure::FixedLimit { .. });
+        match action {
+            AgentAction::AllIn if all_in > max => AgentAction::Bet(max),
+            AgentAction::Bet(bet) if bet > max => AgentAction::Bet(max),
+            AgentAction::Bet(bet) if is_fixed && bet > game_state.current_round_bet() => {
+                AgentAction::Bet(max.min(all_in))
+            }
+            action => action,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preflop() -> GameState {
+        let mut game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        game_state.advance_round();
+        game_state.advance_round();
+        game_state.advance_round();
+        game_state.do_bet(1.0, true).unwrap();
+        game_state.do_bet(2.0, true).unwrap();
+        game_state
+    }
+
+    #[test]
+    fn test_fixed_limit_caps() {
+        let limit = BettingStructure::fixed_limit(2.0);
+        let mut game_state = preflop();
+
+        assert_eq!(
+            AgentAction::Bet(4.0),
+            limit.constrain(&game_state, AgentAction::AllIn)
+        );
+        assert_eq!(
+            AgentAction::Bet(4.0),
+            limit.constrain(&game_state, AgentAction::Bet(3.0))
+        );
+        assert_eq!(
+            AgentAction::Bet(2.0),
+            limit.constrain(&game_state, AgentAction::Bet(2.0))
+        );
+        assert_eq!(
+            AgentAction::Fold,
+            limit.constrain(&game_state, AgentAction::Fold)
+        );
+
+        // The blind is the first bet, so three raises cap it.
+        for bet in [4.0, 6.0, 8.0] {
+            game_state.do_bet(bet, false).unwrap();
+        }
+        assert_eq!(Some(8.0), limit.max_bet(&game_state));
+        assert_eq!(
+            AgentAction::Bet(8.0),
+            limit.constrain(&game_state, AgentAction::AllIn)
+        );
+    }
+
+    #[test]
+    fn test_pot_limit_allows_small_all_in() {
+        let mut game_state = GameState::new_starting(vec![100.0, 5.0], 2.0, 1.0, 0.0, 0);
+        game_state.advance_round();
+        game_state.advance_round();
+        game_state.advance_round();
+        game_state.do_bet(1.0, true).unwrap();
+        game_state.do_bet(2.0, true).unwrap();
+        game_state.do_bet(7.0, false).unwrap();
+
+        // Five chips is less than the pot, so all in stands.
+        assert_eq!(
+            AgentAction::AllIn,
+            BettingStructure::PotLimit.constrain(&game_state, AgentAction::AllIn)
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+
+use crate::arena::GameState;
+use crate::core::{Card, CardSet, Hand, Rankable, Suit};
+
+/// Runouts of the board sampled for each hand strength estimate.
+const BOARD_SAMPLES: usize = 32;
+/// Opponent hands sampled on each runout before the river.
+const OPPONENT_SAMPLES: usize = 32;
+/// Hand strengths remembered per thread before the cache starts over.
+const STRENGTH_CACHE_SIZE: usize = 1 << 20;
+
+thread_local! {
+    /// Every visit to a chance node buckets the cards dealt, so the same
+    /// hands come up over and over during training. Their strengths are
+    /// kept, by the hole cards and board with suits relabelled, and by the
+    /// power they're raised to.
+    static STRENGTH_CACHE: RefCell<HashMap<(CardSet, CardSet, i32), f32>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Decides which cards share a branch of a chance node, and so which deals
+/// the CFR tree treats as the same.
+///
+/// Implementations must be cheap to create, as every action generator gets
+/// its own.
+pub trait CardAbstraction: Default {
+    /// The chance node child for `card`, given the cards the player had
+    /// already seen. See `ActionGenerator::card_to_idx`.
+    fn card_to_idx(&self, game_state: &GameState, known: CardSet, card: Card) -> usize;
+}
+
+/// Every card gets its own branch.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCardAbstraction;
+
+impl CardAbstraction for NoCardAbstraction {
+    fn card_to_idx(&self, _game_state: &GameState, _known: CardSet, card: Card) -> usize {
+        u8::from(card) as usize
+    }
+}
+
+/// Cards that only differ by suits that can be swapped without changing
+/// anything share a branch. This is lossless: `AsKs` and `AhKh` play the
+/// same, as do `2c` and `2d` on a board without clubs or diamonds.
+///
+/// Suits the player has seen are labelled in order of the values seen in
+/// them, and the suits they haven't seen are interchangeable, so a card's
+/// branch is its value and the label of its suit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SuitIsomorphism;
+
+impl CardAbstraction for SuitIsomorphism {
+    fn card_to_idx(&self, _game_state: &GameState, known: CardSet, card: Card) -> usize {
+        let mut values = [0_u16; 4];
+        let mut known = known;
+        known.remove(card);
+        for seen in known {
+            values[seen.suit as usize] |= 1 << seen.value as u16;
+        }
+        let mut seen_suits: Vec<Suit> = Suit::suits()
+            .into_iter()
+            .filter(|suit| values[*suit as usize] != 0)
+            .collect();
+        seen_suits.sort_by_key(|suit| std::cmp::Reverse(values[*suit as usize]));
+        let label = seen_suits
+            .iter()
+            .position(|suit| *suit == card.suit)
+            .unwrap_or(seen_suits.len());
+        label * 13 + card.value as usize
+    }
+}
+
+/// Only values count, suits are ignored. This is lossy as it can't tell a
+/// flush draw from a rainbow board, but shrinks the chance nodes to 13
+/// branches, which makes it a quick way to get a rough solution.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValueOnlyAbstraction;
+
+impl CardAbstraction for ValueOnlyAbstraction {
+    fn card_to_idx(&self, _game_state: &GameState, _known: CardSet, card: Card) -> usize {
+        card.value as usize
+    }
+}
+
+/// Hands are bucketed by equity, their expected hand strength (EHS): how
+/// often they beat a random hand once the board is out. `BUCKETS` equally
+/// wide buckets of equity are used.
+///
+/// Only complete deals are bucketed, the two hole cards and then the flop,
+/// turn and river, so the first hole card and the first two cards of the
+/// flop all go down branch zero. The estimate is sampled, but seeded from
+/// the cards so the same hand always lands in the same bucket, and cached so
+/// it's only sampled once per thread. Hands that only differ by swapping
+/// suits share an estimate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EquityAbstraction<const BUCKETS: usize = 10>;
+
+impl<const BUCKETS: usize> CardAbstraction for EquityAbstraction<BUCKETS> {
+    fn card_to_idx(&self, game_state: &GameState, known: CardSet, card: Card) -> usize {
+        hand_strength_bucket(game_state, known, card, BUCKETS, 1)
+    }
+}
+
+/// Hands are bucketed by the expected square of their hand strength
+/// (EHS2). Squaring gives hands whose strength swings a lot with the board,
+/// like draws, more weight than steady hands of the same equity, so draws
+/// and made hands end up apart. Otherwise the same as
+/// [`EquityAbstraction`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ehs2Abstraction<const BUCKETS: usize = 10>;
+
+impl<const BUCKETS: usize> CardAbstraction for Ehs2Abstraction<BUCKETS> {
+    fn card_to_idx(&self, game_state: &GameState, known: CardSet, card: Card) -> usize {
+        hand_strength_bucket(game_state, known, card, BUCKETS, 2)
+    }
+}
+
+/// The bucket of the expected hand strength, raised to `power`, of the
+/// cards seen once `card` is dealt. Deals that aren't complete yet go in
+/// bucket zero.
+fn hand_strength_bucket(
+    game_state: &GameState,
+    known: CardSet,
+    card: Card,
+    buckets: usize,
+    power: i32,
+) -> usize {
+    let mut seen = known;
+    seen.insert(card);
+    if !matches!(seen.count(), 2 | 5 | 6 | 7) {
+        return 0;
+    }
+    let Some((hole, board)) = split_hole_cards(game_state, seen) else {
+        return 0;
+    };
+    let strength = cached_hand_strength(hole, board, power);
+    ((strength * buckets as f32) as usize).min(buckets.saturating_sub(1))
+}
+
+/// `expected_hand_strength` of the hole cards and board, from the cache if
+/// it's been worked out on this thread before.
+fn cached_hand_strength(hole: CardSet, board: CardSet, power: i32) -> f32 {
+    let (hole, board) = canonical_suits(hole, board);
+    let key = (hole, board, power);
+    if let Some(strength) = STRENGTH_CACHE.with_borrow(|cache| cache.get(&key).copied()) {
+        return strength;
+    }
+    let strength = expected_hand_strength(hole, board, power);
+    STRENGTH_CACHE.with_borrow_mut(|cache| {
+        if cache.len() >= STRENGTH_CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(key, strength);
+    });
+    strength
+}
+
+/// Relabel the suits of `hole` and `board` so that hands that only differ
+/// by swapping suits come out the same. Suits are ordered by the values
+/// they have in the hole cards, then on the board.
+fn canonical_suits(hole: CardSet, board: CardSet) -> (CardSet, CardSet) {
+    let mut values = [(0_u16, 0_u16); 4];
+    for card in hole {
+        values[card.suit as usize].0 |= 1 << card.value as u16;
+    }
+    for card in board {
+        values[card.suit as usize].1 |= 1 << card.value as u16;
+    }
+    let mut suits = Suit::suits();
+    suits.sort_by_key(|suit| std::cmp::Reverse(values[*suit as usize]));
+    let relabel = |cards: CardSet| {
+        cards
+            .into_iter()
+            .map(|card| {
+                let label = suits.iter().position(|suit| *suit == card.suit).unwrap();
+                Card::new(card.value, Suit::suits()[label])
+            })
+            .collect::<CardSet>()
+    };
+    (relabel(hole), relabel(board))
+}
+
+/// Split the cards a player has seen into their hole cards and the board.
+///
+/// While a deal is being recorded the game state doesn't have its cards
+/// yet, so hole cards are the seen cards in a hand in the game state that
+/// aren't on its board. Otherwise, as when a path is rebuilt for hole cards
+/// the game state doesn't hold, they're the seen cards off its board.
+fn split_hole_cards(game_state: &GameState, seen: CardSet) -> Option<(CardSet, CardSet)> {
+    let on_board = game_state.board_set();
+    let in_hand = game_state
+        .hands
+        .iter()
+        .fold(CardSet::new(), |cards, hand| cards | CardSet::from(*hand));
+    [(seen & in_hand) - on_board, seen - on_board]
+        .into_iter()
+        .find(|hole| hole.count() == 2)
+        .map(|hole| (hole, seen - hole))
+}
+
+/// Average how often `hole` beats a random hand over runouts of `board`,
+/// raising the strength on each runout to `power` first.
+fn expected_hand_strength(hole: CardSet, board: CardSet, power: i32) -> f32 {
+    let seed = hole
+        .into_iter()
+        .chain(board)
+        .fold(board.count() as u64, |seed, card| {
+            seed.wrapping_mul(67) ^ u64::from(u8::from(card))
+        });
+    let mut rng = StdRng::seed_from_u64(seed);
+    let num_board = 5_usize.saturating_sub(board.count());
+    let remaining: Vec<Card> = (!(hole | board)).into_iter().collect();
+
+    let runouts = if num_board == 0 { 1 } else { BOARD_SAMPLES };
+    let mut total = 0.0;
+    for _ in 0..runouts {
+        let mut runout = board;
+        sample(&mut rng, remaining.len(), num_board)
+            .into_iter()
+            .for_each(|i| runout.insert(remaining[i]));
+        let ours = Hand::from(runout | hole).rank();
+        let left: Vec<Card> = (!(runout | hole)).into_iter().collect();
+
+        // The river is known, so every opponent hand is played.
+        let opponents: Vec<(Card, Card)> = if num_board == 0 {
+            (0..left.len())
+                .flat_map(|i| (i + 1..left.len()).map(move |j| (i, j)))
+                .map(|(i, j)| (left[i], left[j]))
+                .collect()
+        } else {
+            (0..OPPONENT_SAMPLES)
+                .map(|_| {
+                    let pair = sample(&mut rng, left.len(), 2);
+                    (left[pair.index(0)], left[pair.index(1)])
+                })
+                .collect()
+        };
+        let won: f32 = opponents
+            .iter()
+            .map(|(first, second)| {
+                let mut theirs = runout;
+                theirs.insert(*first);
+                theirs.insert(*second);
+                match ours.cmp(&Hand::from(theirs).rank()) {
+                    Ordering::Greater => 1.0,
+                    Ordering::Equal => 0.5,
+                    Ordering::Less => 0.0,
+                }
+            })
+            .sum();
+        total += (won / opponents.len() as f32).powi(power);
+    }
+    total / runouts as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::hand_history::parse_cards;
+
+    /// The branches taken dealing `cards` one at a time.
+    fn path<C: CardAbstraction>(cards: &str) -> Vec<usize> {
+        let game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut known = CardSet::new();
+        parse_cards(cards)
+            .unwrap()
+            .into_iter()
+            .map(|card| {
+                let idx = C::default().card_to_idx(&game_state, known, card);
+                known.insert(card);
+                idx
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_suit_isomorphism() {
+        let suited = path::<SuitIsomorphism>("AsKs");
+        assert_eq!(suited, path::<SuitIsomorphism>("AhKh"));
+        assert_ne!(suited, path::<SuitIsomorphism>("AsKd"));
+        assert_eq!(
+            path::<SuitIsomorphism>("AsKdQs2c"),
+            path::<SuitIsomorphism>("AhKcQh2d")
+        );
+        // A new suit on a board showing a spade is the same whichever it is.
+        assert_eq!(
+            path::<SuitIsomorphism>("AsKs2c"),
+            path::<SuitIsomorphism>("AsKs2h")
+        );
+        assert_ne!(
+            path::<SuitIsomorphism>("AsKs2s"),
+            path::<SuitIsomorphism>("AsKs2h")
+        );
+        assert!(suited.iter().all(|idx| *idx < 52));
+    }
+
+    #[test]
+    fn test_value_only() {
+        assert_eq!(
+            path::<ValueOnlyAbstraction>("AsKs"),
+            path::<ValueOnlyAbstraction>("AdKc")
+        );
+        assert_eq!(
+            vec![u8::from(Card::try_from("2h").unwrap()) as usize],
+            path::<NoCardAbstraction>("2h")
+        );
+    }
+
+    /// The bucket of the last of `cards` with everything before it seen,
+    /// and the hands dealt.
+    fn bucket<C: CardAbstraction>(hands: [&str; 2], cards: &str) -> usize {
+        let mut game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        *game_state.hands_mut() = hands
+            .into_iter()
+            .map(|hand| Hand::new_from_str(hand).unwrap())
+            .collect();
+        let mut cards = parse_cards(cards).unwrap();
+        let card = cards.pop().unwrap();
+        C::default().card_to_idx(&game_state, CardSet::from(&cards[..]), card)
+    }
+
+    #[test]
+    fn test_equity_buckets() {
+        let hands = ["AsAd", "7c2h"];
+        // Aces win about 85% of the time.
+        assert_eq!(8, bucket::<EquityAbstraction>(hands, "AsAd"));
+        assert!(bucket::<EquityAbstraction>(hands, "7c2h") < 5);
+        // Same cards, same bucket.
+        assert_eq!(
+            bucket::<EquityAbstraction<50>>(hands, "7c2h"),
+            bucket::<EquityAbstraction<50>>(hands, "2h7c")
+        );
+        // Half a deal isn't bucketed.
+        assert_eq!(0, bucket::<EquityAbstraction>(hands, "As"));
+        assert_eq!(0, bucket::<EquityAbstraction>(hands, "AsAdKhQh"));
+    }
+
+    #[test]
+    fn test_strength_cache() {
+        let cards = |cards| CardSet::from(&parse_cards(cards).unwrap()[..]);
+        let strength = cached_hand_strength(cards("AsKs"), cards("Qs2c7d"), 1);
+        assert_eq!(
+            strength,
+            cached_hand_strength(cards("AsKs"), cards("Qs2c7d"), 1)
+        );
+        // The same hand with the suits swapped.
+        assert_eq!(
+            strength,
+            cached_hand_strength(cards("AhKh"), cards("Qh2s7c"), 1)
+        );
+        let (hole, board) = canonical_suits(cards("AsKs"), cards("Qs2c7d"));
+        assert!(STRENGTH_CACHE.with_borrow(|cache| cache.contains_key(&(hole, board, 1))));
+    }
+
+    #[test]
+    fn test_ehs2_buckets() {
+        // The nuts on the river.
+        let hands = ["AsKs", "7c2h"];
+        assert_eq!(9, bucket::<Ehs2Abstraction>(hands, "AsKsQsJsTs"));
+        // Squaring never moves a hand up.
+        let draw = bucket::<Ehs2Abstraction<50>>(hands, "AsKs2s7s9d");
+        let equity = bucket::<EquityAbstraction<50>>(hands, "AsKs2s7s9d");
+        assert!(draw < equity, "{draw} {equity}");
+    }
+}
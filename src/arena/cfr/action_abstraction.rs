@@ -0,0 +1,301 @@
+use std::marker::PhantomData;
+
+use crate::arena::GameState;
+use crate::arena::action::{AgentAction, PlayedActionPayload};
+use crate::core::{Card, CardSet};
+
+use super::action_generator::choose_action;
+use super::{ActionGenerator, CFRState, CardAbstraction, NoCardAbstraction, TraversalState};
+
+/// Decides which actions the solver considers, and which child of a
+/// decision node each of them goes down.
+///
+/// Abstract actions are numbered from zero, which is always fold. Any bet a
+/// player could make, including ones the abstraction doesn't offer, is
+/// mapped to one of them, so hands played by other agents still land in the
+/// tree.
+///
+/// Implementations must be cheap to create, as every action generator gets
+/// its own.
+pub trait ActionAbstraction: Default {
+    /// The concrete actions to consider in `game_state`.
+    fn actions(&self, game_state: &GameState) -> Vec<AgentAction>;
+
+    /// The abstract action `action` maps to in `game_state`.
+    fn action_to_idx(&self, game_state: &GameState, action: &AgentAction) -> usize;
+
+    /// The abstract action a played action maps to, from the payload's
+    /// starting values since the game state is from after it.
+    fn played_action_to_idx(&self, game_state: &GameState, payload: &PlayedActionPayload) -> usize;
+
+    /// The number of abstract actions there can be in `game_state`.
+    fn num_actions(&self, game_state: &GameState) -> usize;
+}
+
+/// The bet sizes for a [`PotFractionActions`], as fractions of the pot.
+///
+/// ```
+/// use rs_poker::arena::cfr::{AbstractCFRActionGenerator, BetSizes, PotFractionActions};
+///
+/// /// Half pot and two times pot.
+/// struct HalfAndOverbet;
+///
+/// impl BetSizes for HalfAndOverbet {
+///     const POT_FRACTIONS: &'static [f32] = &[0.5, 2.0];
+/// }
+///
+/// type Generator = AbstractCFRActionGenerator<PotFractionActions<HalfAndOverbet>>;
+/// ```
+pub trait BetSizes {
+    /// Bet sizes from smallest to largest. A size of one bets the pot, and
+    /// a raise is sized on the pot after calling.
+    const POT_FRACTIONS: &'static [f32];
+}
+
+/// A third of the pot, two thirds and the pot.
+pub struct DefaultBetSizes;
+
+impl BetSizes for DefaultBetSizes {
+    const POT_FRACTIONS: &'static [f32] = &[0.33, 0.66, 1.0];
+}
+
+/// Fold, check or call, a bet for each of the `S` pot fractions and all in.
+///
+/// Fold is child 0, call 1, the pot fractions 2 onwards in order and all in
+/// last. Sizes smaller than the minimum raise or as big as going all in
+/// aren't offered. Other bets go to the size they're closest to, or all in
+/// if they put the player's whole stack in.
+pub struct PotFractionActions<S: BetSizes = DefaultBetSizes> {
+    sizes: PhantomData<S>,
+}
+
+impl<S: BetSizes> Default for PotFractionActions<S> {
+    fn default() -> Self {
+        Self { sizes: PhantomData }
+    }
+}
+
+impl<S: BetSizes> PotFractionActions<S> {
+    /// The child index of going all in.
+    pub fn all_in_idx() -> usize {
+        2 + S::POT_FRACTIONS.len()
+    }
+
+    /// What each pot fraction bets, as the player's total bet for the
+    /// round.
+    fn bet_sizes(pot: f32, call: f32, player_bet: f32) -> impl Iterator<Item = f32> {
+        let pot_after_call = pot + (call - player_bet).max(0.0);
+        S::POT_FRACTIONS
+            .iter()
+            .map(move |fraction| call + fraction * pot_after_call)
+    }
+
+    /// The child a bet of `amount` goes down.
+    fn bet_to_idx(pot: f32, call: f32, player_bet: f32, all_in: f32, amount: f32) -> usize {
+        if amount <= call {
+            return 1;
+        }
+        if amount >= all_in {
+            return Self::all_in_idx();
+        }
+        Self::bet_sizes(pot, call, player_bet)
+            .map(|size| (size - amount).abs())
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(Self::all_in_idx(), |(idx, _)| idx + 2)
+    }
+}
+
+impl<S: BetSizes> ActionAbstraction for PotFractionActions<S> {
+    fn actions(&self, game_state: &GameState) -> Vec<AgentAction> {
+        let legal = game_state.legal_actions();
+        let mut actions = Vec::with_capacity(self.num_actions(game_state));
+        if legal.can_fold {
+            actions.push(AgentAction::Fold);
+        }
+        actions.push(AgentAction::Bet(legal.call));
+        if let Some((min_raise, all_in)) = legal.raise {
+            let player_bet = game_state.current_round_current_player_bet();
+            for size in Self::bet_sizes(game_state.total_pot, legal.call, player_bet) {
+                let last = actions.last().cloned();
+                if size >= min_raise && size < all_in && last != Some(AgentAction::Bet(size)) {
+                    actions.push(AgentAction::Bet(size));
+                }
+            }
+            actions.push(AgentAction::AllIn);
+        }
+        actions
+    }
+
+    fn action_to_idx(&self, game_state: &GameState, action: &AgentAction) -> usize {
+        let call = game_state.current_round_bet();
+        let player_bet = game_state.current_round_current_player_bet();
+        let all_in = player_bet + game_state.current_player_stack();
+        match action {
+            AgentAction::Fold => 0,
+            AgentAction::Bet(amount) => {
+                Self::bet_to_idx(game_state.total_pot, call, player_bet, all_in, *amount)
+            }
+            AgentAction::AllIn if all_in <= call => 1,
+            AgentAction::AllIn => Self::all_in_idx(),
+        }
+    }
+
+    fn played_action_to_idx(
+        &self,
+        _game_state: &GameState,
+        payload: &PlayedActionPayload,
+    ) -> usize {
+        match payload.action {
+            AgentAction::Fold => 0,
+            _ => Self::bet_to_idx(
+                payload.starting_pot,
+                payload.starting_bet,
+                payload.starting_player_bet,
+                payload.final_player_bet + payload.player_stack,
+                payload.final_player_bet,
+            ),
+        }
+    }
+
+    fn num_actions(&self, _game_state: &GameState) -> usize {
+        Self::all_in_idx() + 1
+    }
+}
+
+/// An action generator for no limit that takes its actions from an
+/// `ActionAbstraction` and maps cards to chance nodes with a
+/// `CardAbstraction`.
+///
+/// `BasicCFRActionGenerator` only knows fold, call and all in. This lets
+/// the solver consider the bet sizes picked with `A`.
+pub struct AbstractCFRActionGenerator<
+    A: ActionAbstraction = PotFractionActions,
+    C: CardAbstraction = NoCardAbstraction,
+> {
+    cfr_state: CFRState,
+    traversal_state: TraversalState,
+    action_abstraction: A,
+    card_abstraction: C,
+}
+
+impl<A: ActionAbstraction, C: CardAbstraction> ActionGenerator
+    for AbstractCFRActionGenerator<A, C>
+{
+    fn new(cfr_state: CFRState, traversal_state: TraversalState) -> Self {
+        Self {
+            cfr_state,
+            traversal_state,
+            action_abstraction: A::default(),
+            card_abstraction: C::default(),
+        }
+    }
+
+    fn action_to_idx(&self, game_state: &GameState, action: &AgentAction) -> usize {
+        self.action_abstraction.action_to_idx(game_state, action)
+    }
+
+    fn played_action_to_idx(&self, game_state: &GameState, payload: &PlayedActionPayload) -> usize {
+        self.action_abstraction
+            .played_action_to_idx(game_state, payload)
+    }
+
+    fn num_potential_actions(&self, game_state: &GameState) -> usize {
+        self.action_abstraction.num_actions(game_state)
+    }
+
+    fn gen_possible_actions(&self, game_state: &GameState) -> Vec<AgentAction> {
+        self.action_abstraction.actions(game_state)
+    }
+
+    fn gen_action(&self, game_state: &GameState) -> AgentAction {
+        let possible = self.action_abstraction.actions(game_state);
+        choose_action(
+            self,
+            &self.cfr_state,
+            &self.traversal_state,
+            game_state,
+            &possible,
+        )
+    }
+
+    fn card_to_idx(&self, game_state: &GameState, known: CardSet, card: Card) -> usize {
+        self.card_abstraction.card_to_idx(game_state, known, card)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::super::{CFRAgent, FixedGameStateIteratorGen, StateStore};
+    use super::*;
+
+    #[test]
+    fn test_pot_fraction_actions() {
+        // The small blind is first to act, with 3 in the pot and 1 to call.
+        let game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut game_state = game_state;
+        game_state.advance_round();
+        game_state.advance_round();
+        game_state.advance_round();
+        game_state.do_bet(1.0, true).unwrap();
+        game_state.do_bet(2.0, true).unwrap();
+
+        let abstraction = PotFractionActions::<DefaultBetSizes>::default();
+        let actions = abstraction.actions(&game_state);
+        assert_eq!(AgentAction::Fold, actions[0]);
+        assert_eq!(AgentAction::Bet(2.0), actions[1]);
+        // A third of the pot is less than a min raise so it's skipped.
+        assert_eq!(
+            vec![
+                AgentAction::Bet(2.0 + 0.66 * 4.0),
+                AgentAction::Bet(6.0),
+                AgentAction::AllIn
+            ],
+            actions[2..]
+        );
+        let indices: Vec<usize> = actions
+            .iter()
+            .map(|action| abstraction.action_to_idx(&game_state, action))
+            .collect();
+        assert_eq!(vec![0, 1, 3, 4, 5], indices);
+
+        // Bets that aren't offered go to the closest size.
+        assert_eq!(
+            4,
+            abstraction.action_to_idx(&game_state, &AgentAction::Bet(7.0))
+        );
+        assert_eq!(
+            5,
+            abstraction.action_to_idx(&game_state, &AgentAction::Bet(1000.0))
+        );
+    }
+
+    #[test]
+    fn test_cfr_agent_plays() {
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        let agents: Vec<Box<dyn Agent>> = (0..2)
+            .map(|idx| {
+                let (cfr_state, traversal_state) = state_store.new_state(game_state.clone(), idx);
+                Box::new(CFRAgent::<
+                    AbstractCFRActionGenerator,
+                    FixedGameStateIteratorGen,
+                >::new(
+                    state_store.clone(),
+                    cfr_state,
+                    traversal_state,
+                    FixedGameStateIteratorGen::new(1),
+                )) as Box<dyn Agent>
+            })
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state)
+            .agents(agents)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        assert!(sim.game_state.is_complete());
+    }
+}
@@ -1,10 +1,13 @@
-use std::cell::Ref;
-
 use tracing::event;
 
-use crate::arena::{GameState, action::AgentAction};
+use crate::arena::{
+    GameState,
+    action::{AgentAction, PlayedActionPayload},
+    game_state::MAX_LEGAL_ACTIONS,
+};
+use crate::core::{Card, CardSet};
 
-use super::{CFRState, Node, NodeData, TraversalState};
+use super::{CFRState, NodeData, TraversalState};
 
 pub trait ActionGenerator {
     /// Create a new action generator
@@ -26,6 +29,17 @@ pub trait ActionGenerator {
     /// action. All other are defined by the implentation
     fn action_to_idx(&self, game_state: &GameState, action: &AgentAction) -> usize;
 
+    /// Given an action the historian saw played return the index of the
+    /// action in the children array.
+    ///
+    /// Historians only see the game state after the action, so by default
+    /// this is `action_to_idx` on that state. Generators whose indices depend
+    /// on the state before the action should use the payload's starting
+    /// values instead.
+    fn played_action_to_idx(&self, game_state: &GameState, payload: &PlayedActionPayload) -> usize {
+        self.action_to_idx(game_state, &payload.action)
+    }
+
     /// How many potential actions in total might be generated.
     ///
     /// At a given node there might be fewere that will be
@@ -47,60 +61,60 @@ pub trait ActionGenerator {
     // Using the current and the CFR's tree's regret state choose a single action to
     // play.
     fn gen_action(&self, game_state: &GameState) -> AgentAction;
-}
 
-pub struct BasicCFRActionGenerator {
-    cfr_state: CFRState,
-    traversal_state: TraversalState,
+    /// Given a card dealt to the player whose tree this is, return the index
+    /// of the chance node child it leads to.
+    ///
+    /// `known` is every card the player had seen before this one, their hole
+    /// cards and the board, including earlier cards of the same deal that
+    /// aren't in `game_state` yet.
+    ///
+    /// By default every card gets its own child. Returning the same index
+    /// for several cards buckets them together, so they share everything
+    /// below that point in the tree.
+    fn card_to_idx(&self, _game_state: &GameState, _known: CardSet, card: Card) -> usize {
+        u8::from(card) as usize
+    }
 }
 
-impl BasicCFRActionGenerator {
-    pub fn new(cfr_state: CFRState, traversal_state: TraversalState) -> Self {
-        BasicCFRActionGenerator {
-            cfr_state,
-            traversal_state,
-        }
-    }
+/// Pick the action the regret matcher of the node `traversal_state` is
+/// about to move to chooses, out of `possible`.
+pub(crate) fn choose_action<T: ActionGenerator>(
+    action_generator: &T,
+    cfr_state: &CFRState,
+    traversal_state: &TraversalState,
+    game_state: &GameState,
+    possible: &[AgentAction],
+) -> AgentAction {
+    // Use the crate rng so that a seeded run picks the same actions.
+    let mut rng = crate::core::rng();
 
-    fn get_target_node(&self) -> Option<Ref<Node>> {
-        let from_node_idx = self.traversal_state.node_idx();
-        let from_child_idx = self.traversal_state.chosen_child_idx();
-        self.cfr_state
-            .get(from_node_idx)
-            .unwrap()
-            .get_child(from_child_idx)
-            .and_then(|idx| self.cfr_state.get(idx))
-    }
-}
+    let target = cfr_state
+        .get(traversal_state.node_idx())
+        .unwrap()
+        .get_child(traversal_state.chosen_child_idx())
+        .and_then(|idx| cfr_state.get(idx));
 
-impl ActionGenerator for BasicCFRActionGenerator {
-    fn gen_action(&self, game_state: &GameState) -> AgentAction {
-        let possible = self.gen_possible_actions(game_state);
-        // For now always use the thread rng.
-        // At somepoint we will want to be able to pass seeded or deterministic action
-        // choices.
-        let mut rng = rand::rng();
-
-        // We expect there to be a target node with a regret matcher
-        match self.get_target_node() {
-            Some(node) => {
-                if let NodeData::Player(pd) = &node.data {
-                    let next_action = pd
-                        .regret_matcher
-                        .as_ref()
-                        .map_or(0, |matcher| matcher.next_action(&mut rng));
-
-                    event!(
-                        tracing::Level::DEBUG,
-                        next_action = next_action,
-                        "Next action index"
-                    );
-
-                    // Find the first action that matches the index picked from the regret matcher
-                    possible
+    // We expect there to be a target node with a regret matcher
+    match target {
+        Some(node) => {
+            if let NodeData::Player(pd) = &*node.data {
+                let next_action = pd
+                    .regret_matcher
+                    .as_ref()
+                    .map_or(0, |matcher| matcher.next_action(&mut rng));
+
+                event!(
+                    tracing::Level::DEBUG,
+                    next_action = next_action,
+                    "Next action index"
+                );
+
+                // Find the first action that matches the index picked from the regret matcher
+                possible
                     .iter()
                     .find_map(|action| {
-                        if self.action_to_idx(game_state, action) == next_action {
+                        if action_generator.action_to_idx(game_state, action) == next_action {
                             Some(action.clone())
                         } else {
                             None
@@ -113,42 +127,63 @@ impl ActionGenerator for BasicCFRActionGenerator {
                         event!(tracing::Level::WARN, fallback = ?fallback, "No action found for next action index");
                         fallback
                     })
-                } else {
-                    panic!("Expected player node");
-                }
-            }
-            _ => {
-                panic!("Expected target node");
+            } else {
+                panic!("Expected player node");
             }
         }
+        _ => {
+            panic!("Expected target node");
+        }
     }
+}
 
-    fn new(cfr_state: CFRState, traversal_state: TraversalState) -> Self {
+pub struct BasicCFRActionGenerator {
+    cfr_state: CFRState,
+    traversal_state: TraversalState,
+}
+
+impl BasicCFRActionGenerator {
+    pub fn new(cfr_state: CFRState, traversal_state: TraversalState) -> Self {
         BasicCFRActionGenerator {
             cfr_state,
             traversal_state,
         }
     }
 
-    fn gen_possible_actions(&self, game_state: &GameState) -> Vec<AgentAction> {
-        let mut res: Vec<AgentAction> = Vec::with_capacity(3);
-        let to_call =
-            game_state.current_round_bet() - game_state.current_round_current_player_bet();
-        if to_call > 0.0 {
-            res.push(AgentAction::Fold);
-        }
-        // Call, Match the current bet (if the bet is 0 this is a check)
-        res.push(AgentAction::Bet(game_state.current_round_bet()));
+    /// Write fold (when there's a bet to call), call and all in (when the
+    /// player has more than it takes to call) into `buf`, so that picking an
+    /// action doesn't allocate.
+    fn write_possible_actions<'a>(
+        game_state: &GameState,
+        buf: &'a mut [AgentAction; MAX_LEGAL_ACTIONS],
+    ) -> &'a [AgentAction] {
+        game_state.legal_actions().write_without_min_raise_to(buf)
+    }
+}
 
-        let all_in_ammount =
-            game_state.current_round_current_player_bet() + game_state.current_player_stack();
+impl ActionGenerator for BasicCFRActionGenerator {
+    fn gen_action(&self, game_state: &GameState) -> AgentAction {
+        let mut buf = [const { AgentAction::Fold }; MAX_LEGAL_ACTIONS];
+        let possible = Self::write_possible_actions(game_state, &mut buf);
+        choose_action(
+            self,
+            &self.cfr_state,
+            &self.traversal_state,
+            game_state,
+            possible,
+        )
+    }
 
-        if all_in_ammount > game_state.current_round_bet() {
-            // All-in, Bet all the money
-            // Bet everything we have bet so far plus the remaining stack
-            res.push(AgentAction::AllIn);
+    fn new(cfr_state: CFRState, traversal_state: TraversalState) -> Self {
+        BasicCFRActionGenerator {
+            cfr_state,
+            traversal_state,
         }
-        res
+    }
+
+    fn gen_possible_actions(&self, game_state: &GameState) -> Vec<AgentAction> {
+        let mut buf = [const { AgentAction::Fold }; MAX_LEGAL_ACTIONS];
+        Self::write_possible_actions(game_state, &mut buf).to_vec()
     }
 
     fn action_to_idx(&self, _game_state: &GameState, action: &AgentAction) -> usize {
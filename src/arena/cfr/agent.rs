@@ -1,18 +1,84 @@
-use std::cell::RefMut;
-
-use little_sorry::RegretMatcher;
-use ndarray::ArrayView1;
+use ndarray::Array1;
 use tracing::event;
 
-use crate::arena::{Agent, GameState, Historian, HoldemSimulationBuilder, action::AgentAction};
+use crate::arena::{
+    Agent, GameState, GameStatePool, Historian, HoldemSimulationBuilder, action::AgentAction,
+};
 
 use super::{
-    CFRHistorian, GameStateIteratorGen, NodeData,
+    CFRHistorian, GameStateIteratorGen, InfoSet, InfoSetTable, NodeData, NodeMut, RegretMatcher,
+    RegretUpdate, WarmStart,
     action_generator::ActionGenerator,
     state::{CFRState, TraversalState},
     state_store::StateStore,
+    warm_start::{PriorPosition, WarmStartHistorian},
 };
 
+thread_local! {
+    /// Rollouts copy the game state for every action explored, so the
+    /// copies are recycled rather than allocated each time.
+    static GAME_STATE_POOL: GameStatePool = GameStatePool::new(64);
+}
+
+/// Regret based pruning, which stops playing out actions that have done so
+/// badly they're unlikely to ever be played.
+///
+/// A decision's regrets are updated once for each game state its actions
+/// are played out from. Once it's had `warmup` updates, an action whose
+/// regret falls below `threshold` is pruned: it isn't played out for the
+/// next `skip` updates and its regret is left as it is. Then it's played
+/// out again and pruned again only if its regret is still below the
+/// threshold, so actions that start doing well come back.
+///
+/// Prune state isn't saved with the tree, so a loaded tree starts with
+/// nothing pruned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PruneConfig {
+    /// Regret below which an action is pruned. Regrets are in chips.
+    pub threshold: f32,
+    /// How many updates a pruned action sits out.
+    pub skip: u32,
+    /// How many updates a decision needs before any of its actions are
+    /// pruned.
+    pub warmup: usize,
+}
+
+impl Default for PruneConfig {
+    fn default() -> Self {
+        Self {
+            threshold: -100.0,
+            skip: 20,
+            warmup: 10,
+        }
+    }
+}
+
+/// The CFR settings shared by the trainers built on `CFRAgent`: how
+/// regrets are updated and whether actions are pruned.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::cfr::{CFRConfig, HeadsUpLimitConfig, RegretUpdate};
+///
+/// // Discounted CFR with the exponents from the paper.
+/// let config = HeadsUpLimitConfig {
+///     cfr: CFRConfig {
+///         regret_update: RegretUpdate::DCFR,
+///         ..CFRConfig::default()
+///     },
+///     ..HeadsUpLimitConfig::default()
+/// };
+/// assert_eq!(None, config.cfr.pruning);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CFRConfig {
+    /// How regrets are updated, plain regret matching by default.
+    pub regret_update: RegretUpdate,
+    /// Regret based pruning, off by default.
+    pub pruning: Option<PruneConfig>,
+}
+
 pub struct CFRAgent<T, I>
 where
     T: ActionGenerator + 'static,
@@ -23,6 +89,9 @@ where
     cfr_state: CFRState,
     action_generator: T,
     gamestate_iterator_gen: I,
+    config: CFRConfig,
+    warm_start: Option<(WarmStart, PriorPosition)>,
+    infosets: Option<InfoSetTable>,
     force_recompute: bool,
 
     // This will be the next action to play
@@ -53,13 +122,58 @@ where
             traversal_state,
             action_generator,
             gamestate_iterator_gen,
+            config: CFRConfig::default(),
+            warm_start: None,
+            infosets: None,
 
             force_recompute: false,
             forced_action: None,
         }
     }
 
-    fn new_with_forced_action(
+    /// Train with `config`, replacing any regret update or pruning set
+    /// before. The agents playing out each action to value it use the
+    /// same.
+    pub fn with_config(mut self, config: CFRConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Update regrets with `regret_update` instead of plain regret
+    /// matching. The agents playing out each action to value it use the
+    /// same.
+    pub fn with_regret_update(mut self, regret_update: RegretUpdate) -> Self {
+        self.config.regret_update = regret_update;
+        self
+    }
+
+    /// Skip playing out actions with very negative regret, see
+    /// `PruneConfig`. The agents playing out each action prune the same
+    /// way.
+    pub fn with_pruning(mut self, pruning: PruneConfig) -> Self {
+        self.config.pruning = Some(pruning);
+        self
+    }
+
+    /// Start new decisions from a blueprint, see `WarmStart`. This takes a
+    /// place in the blueprint for the player, which has to be given back
+    /// with `WarmStart::pop_traversal` once the hand is over. The agents
+    /// playing out each action start from the same blueprint.
+    pub fn with_warm_start(mut self, warm_start: WarmStart) -> Self {
+        let position = warm_start.push_traversal(self.traversal_state.player_idx());
+        self.warm_start = Some((warm_start, position));
+        self
+    }
+
+    /// Share regret matchers between decisions in the same `InfoSet`, see
+    /// `InfoSetTable`. The agents playing out each action share the same
+    /// table.
+    pub fn with_infosets(mut self, infosets: InfoSetTable) -> Self {
+        self.infosets = Some(infosets);
+        self
+    }
+
+    pub(crate) fn new_with_forced_action(
         state_store: StateStore,
         cfr_state: CFRState,
         traversal_state: TraversalState,
@@ -73,6 +187,9 @@ where
             traversal_state,
             action_generator,
             gamestate_iterator_gen,
+            config: CFRConfig::default(),
+            warm_start: None,
+            infosets: None,
             force_recompute: false,
             forced_action: Some(forced_action),
         }
@@ -84,7 +201,7 @@ where
 
     fn reward(&mut self, game_state: &GameState, action: AgentAction) -> f32 {
         let num_agents = game_state.num_players;
-        let mut rand = rand::rng();
+        let mut rand = crate::core::rng();
 
         // Debug assertions to show that checking for rewards doesn't move us through
         // the tree
@@ -97,29 +214,35 @@ where
             .map(|i| {
                 let (cfr_state, traversal_state) = self.state_store.push_traversal(i);
 
-                if i == self.traversal_state.player_idx() {
-                    Box::new(CFRAgent::<T, I>::new_with_forced_action(
+                let mut agent = if i == self.traversal_state.player_idx() {
+                    CFRAgent::<T, I>::new_with_forced_action(
                         self.state_store.clone(),
                         cfr_state,
                         traversal_state,
                         self.gamestate_iterator_gen.clone(),
                         action.clone(),
-                    ))
+                    )
                 } else {
-                    Box::new(CFRAgent::<T, I>::new(
+                    CFRAgent::<T, I>::new(
                         self.state_store.clone(),
                         cfr_state,
                         traversal_state,
                         self.gamestate_iterator_gen.clone(),
-                    ))
+                    )
+                }
+                .with_config(self.config);
+                agent.infosets = self.infosets.clone();
+                if let Some((warm_start, _)) = &self.warm_start {
+                    agent = agent.with_warm_start(warm_start.clone());
                 }
+                Box::new(agent)
             })
             .collect();
 
         let dyn_agents = agents.into_iter().map(|a| a as Box<dyn Agent>).collect();
 
         let mut sim = HoldemSimulationBuilder::default()
-            .game_state(game_state.clone())
+            .game_state(GAME_STATE_POOL.with(|pool| pool.copy_of(game_state)))
             .agents(dyn_agents)
             .build()
             .unwrap();
@@ -129,6 +252,9 @@ where
         // After each agent explores we need to return the traversal state
         for player_idx in 0..num_agents {
             self.state_store.pop_traversal(player_idx);
+            if let Some((warm_start, _)) = &self.warm_start {
+                warm_start.pop_traversal(player_idx);
+            }
         }
 
         debug_assert_eq!(
@@ -142,8 +268,11 @@ where
             "Child index should be the same after exploration"
         );
 
-        sim.game_state
-            .player_reward(self.traversal_state.player_idx())
+        let reward = sim
+            .game_state
+            .player_reward(self.traversal_state.player_idx());
+        GAME_STATE_POOL.with(|pool| pool.recycle(sim.game_state));
+        reward
     }
 
     fn target_node_idx(&self) -> Option<usize> {
@@ -155,7 +284,7 @@ where
             .get_child(from_child_idx)
     }
 
-    fn get_mut_target_node(&mut self) -> RefMut<super::Node> {
+    fn get_mut_target_node(&mut self) -> NodeMut<'_> {
         let target_node_idx = self.target_node_idx().unwrap();
         self.cfr_state.get_mut(target_node_idx).unwrap()
     }
@@ -167,7 +296,7 @@ where
         match self.target_node_idx() {
             Some(t) => {
                 let target_node = self.cfr_state.get(t).unwrap();
-                if let NodeData::Player(ref player_data) = target_node.data {
+                if let NodeData::Player(ref player_data) = *target_node.data {
                     assert_eq!(
                         player_data.player_idx,
                         self.traversal_state.player_idx(),
@@ -196,13 +325,74 @@ where
 
     fn ensure_regret_matcher(&mut self, game_state: &GameState) {
         let target_node_idx = self.ensure_target_node(game_state);
+        if self.has_regret_matcher() {
+            return;
+        }
+        let num_experts = self.action_generator.num_potential_actions(game_state);
+        let regret_matcher = match (&self.infosets, self.shared_infoset(target_node_idx)) {
+            (Some(infosets), Some(infoset)) => infosets
+                .get_or_insert(&infoset, num_experts, || {
+                    self.new_regret_matcher(game_state, num_experts)
+                })
+                .unwrap_or_else(|| self.new_regret_matcher(game_state, num_experts)),
+            _ => self.new_regret_matcher(game_state, num_experts),
+        };
         let mut target_node = self.cfr_state.get_mut(target_node_idx).unwrap();
-        if let NodeData::Player(ref mut player_data) = target_node.data {
-            if player_data.regret_matcher.is_none() {
-                let num_experts = self.action_generator.num_potential_actions(game_state);
-                let regret_matcher = Box::new(RegretMatcher::new(num_experts).unwrap());
-                player_data.regret_matcher = Some(regret_matcher);
-            }
+        if let NodeData::Player(ref mut player_data) = *target_node.data {
+            player_data.regret_matcher = Some(Box::new(regret_matcher));
+        }
+    }
+
+    /// A regret matcher for a decision reached for the first time, from
+    /// the blueprint if there is one.
+    fn new_regret_matcher(&self, game_state: &GameState, num_experts: usize) -> RegretMatcher {
+        let warm_started = self.warm_start.as_ref().and_then(|(warm_start, position)| {
+            let actions: Vec<_> = self
+                .action_generator
+                .gen_possible_actions(game_state)
+                .into_iter()
+                .map(|action| {
+                    (
+                        self.action_generator.action_to_idx(game_state, &action),
+                        action,
+                    )
+                })
+                .collect();
+            warm_start.regret_matcher(
+                self.traversal_state.player_idx(),
+                position,
+                game_state,
+                &actions,
+                num_experts,
+            )
+        });
+        warm_started.unwrap_or_else(|| RegretMatcher::new(num_experts).unwrap())
+    }
+
+    /// The infoset of the decision at `node_idx`, if regrets are shared.
+    fn shared_infoset(&self, node_idx: usize) -> Option<InfoSet> {
+        self.infosets.as_ref()?;
+        InfoSet::from_node(&self.cfr_state, node_idx)
+    }
+
+    /// Copy the shared matcher for the target node's infoset into it, so
+    /// it plays what every decision in the infoset has learned.
+    fn refresh_from_infoset(&mut self) {
+        let Some(target_node_idx) = self.target_node_idx() else {
+            return;
+        };
+        let Some(shared) = self
+            .shared_infoset(target_node_idx)
+            .and_then(|infoset| self.infosets.as_ref()?.get(&infoset))
+        else {
+            return;
+        };
+        let mut target_node = self.cfr_state.get_mut(target_node_idx).unwrap();
+        if let NodeData::Player(player_data) = &mut *target_node.data
+            && let Some(matcher) = &mut player_data.regret_matcher
+            && matcher.num_actions() == shared.num_actions()
+        {
+            **matcher = shared;
         }
     }
 
@@ -214,7 +404,7 @@ where
         self.target_node_idx()
             .map(|t| {
                 let target_node = self.cfr_state.get(t).unwrap();
-                if let NodeData::Player(ref player_data) = target_node.data {
+                if let NodeData::Player(ref player_data) = *target_node.data {
                     player_data.regret_matcher.is_some()
                 } else {
                     false
@@ -223,19 +413,77 @@ where
             .unwrap_or(false)
     }
 
+    /// Count down the actions that are pruned and return their indices.
+    /// If every action is pruned none are, so there's something to play
+    /// out.
+    fn take_pruned(&mut self, game_state: &GameState, actions: &[AgentAction]) -> Vec<usize> {
+        if self.config.pruning.is_none() {
+            return Vec::new();
+        }
+        let indices: Vec<usize> = actions
+            .iter()
+            .map(|action| self.action_generator.action_to_idx(game_state, action))
+            .collect();
+        let mut target_node = self.get_mut_target_node();
+        let pruned: Vec<usize> = indices
+            .iter()
+            .copied()
+            .filter(|&idx| target_node.get_pruned(idx) > 0)
+            .collect();
+        if pruned.len() == indices.len() {
+            for idx in pruned {
+                target_node.set_pruned(idx, 0);
+            }
+            return Vec::new();
+        }
+        for &idx in &pruned {
+            let remaining = target_node.get_pruned(idx);
+            target_node.set_pruned(idx, remaining - 1);
+        }
+        pruned
+    }
+
+    /// Prune the actions that were just played out if their regret has
+    /// fallen below the threshold.
+    fn prune_after_update(&mut self, explored: &[usize]) {
+        let Some(pruning) = self.config.pruning else {
+            return;
+        };
+        let mut target_node = self.get_mut_target_node();
+        let regrets = match &*target_node.data {
+            NodeData::Player(player_data) => match &player_data.regret_matcher {
+                Some(matcher) if matcher.num_updates() >= pruning.warmup => matcher.regrets(),
+                _ => return,
+            },
+            _ => return,
+        };
+        for &idx in explored {
+            if regrets[idx] < pruning.threshold {
+                target_node.set_pruned(idx, pruning.skip);
+            }
+        }
+    }
+
     pub fn explore_all_actions(&mut self, game_state: &GameState) {
         let actions = self.action_generator.gen_possible_actions(game_state);
 
         // We assume that any non-explored action would be bad for the player, so we
         // assign them a reward of losing our entire stack.
-        let mut rewards: Vec<f32> =
-            vec![0.0; self.action_generator.num_potential_actions(game_state)];
+        let mut rewards: Array1<f32> =
+            Array1::zeros(self.action_generator.num_potential_actions(game_state));
         let mut explored_game_states = 0;
 
+        let infosets = self.infosets.clone();
+        let infoset = self
+            .target_node_idx()
+            .and_then(|node_idx| self.shared_infoset(node_idx));
+
         let game_states: Vec<_> = self.gamestate_iterator_gen.generate(game_state).collect();
         for starting_gamestate in game_states {
             // Keep track of the number of game states we have explored
             explored_game_states += 1;
+            let pruned = self.take_pruned(&starting_gamestate, &actions);
+            let mut explored = Vec::with_capacity(actions.len());
 
             // For every action try it and see what the result is
             for action in actions.clone() {
@@ -251,29 +499,63 @@ where
                     reward_idx,
                     rewards.len()
                 );
+                if pruned.contains(&reward_idx) {
+                    continue;
+                }
 
                 rewards[reward_idx] += self.reward(&starting_gamestate, action);
+                explored.push(reward_idx);
             }
 
             // normalize the rewards by the number of game states we have explored
             if explored_game_states > 0 {
-                for reward in &mut rewards {
-                    *reward /= explored_game_states as f32;
-                }
+                rewards /= explored_game_states as f32;
             }
 
             // Update the regret matcher with the rewards
+            let regret_update = self.config.regret_update;
             let mut target_node = self.get_mut_target_node();
-            if let NodeData::Player(player_data) = &mut target_node.data {
+            if let NodeData::Player(player_data) = &mut *target_node.data {
                 let regret_matcher = player_data.regret_matcher.as_mut().unwrap();
-                regret_matcher
-                    .update_regret(ArrayView1::from(&rewards))
-                    .unwrap();
+                // Pruned actions earn what the strategy does on the rest,
+                // which leaves their regret where it was.
+                if !pruned.is_empty() {
+                    let strategy = regret_matcher.current_weight();
+                    let (value, weight) = explored.iter().fold((0.0, 0.0), |(v, w), &idx| {
+                        (v + strategy[idx] * rewards[idx], w + strategy[idx])
+                    });
+                    let value = if weight > 0.0 {
+                        value / weight
+                    } else {
+                        explored.iter().map(|&idx| rewards[idx]).sum::<f32>()
+                            / explored.len() as f32
+                    };
+                    for &idx in &pruned {
+                        rewards[idx] = value;
+                    }
+                }
+                let shared =
+                    infosets
+                        .as_ref()
+                        .zip(infoset.as_ref())
+                        .and_then(|(table, infoset)| {
+                            table.update(infoset, rewards.view(), regret_update)
+                        });
+                match shared {
+                    Some(shared) => **regret_matcher = shared,
+                    None => regret_matcher
+                        .update_regret_with(rewards.view(), regret_update)
+                        .unwrap(),
+                }
             } else {
                 // This should never happen since ensure_target_node
                 // has been called before this.
                 panic!("Expected player data");
             }
+            drop(target_node);
+            self.prune_after_update(&explored);
+
+            GAME_STATE_POOL.with(|pool| pool.recycle(starting_gamestate));
         }
     }
 }
@@ -313,6 +595,8 @@ where
                 self.ensure_regret_matcher(game_state);
                 // Explore all the potential actions
                 self.explore_all_actions(game_state);
+            } else {
+                self.refresh_from_infoset();
             }
             // Now the regret matcher should have all the needed data
             // to choose an action.
@@ -324,15 +608,24 @@ where
     /// since it needs to keep track of the game state
     /// and the actions taken.
     fn historian(&self) -> Option<Box<dyn Historian>> {
-        Some(Box::new(self.build_historian()) as Box<dyn Historian>)
+        let historian = self.build_historian();
+        Some(match &self.warm_start {
+            Some((warm_start, position)) => Box::new(WarmStartHistorian {
+                historian,
+                prior: warm_start.historian(self.traversal_state.player_idx(), position.clone()),
+            }) as Box<dyn Historian>,
+            None => Box::new(historian) as Box<dyn Historian>,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use ndarray::array;
+
     use crate::arena::GameState;
-    use crate::arena::cfr::{BasicCFRActionGenerator, FixedGameStateIteratorGen};
+    use crate::arena::cfr::{BasicCFRActionGenerator, FixedGameStateIteratorGen, PlayerData};
 
     use super::*;
 
@@ -349,6 +642,112 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_with_config() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        let (cfr_state, traversal_state) = state_store.new_state(game_state, 0);
+        let config = CFRConfig {
+            regret_update: RegretUpdate::DCFR,
+            pruning: Some(PruneConfig::default()),
+        };
+        let agent = CFRAgent::<BasicCFRActionGenerator, FixedGameStateIteratorGen>::new(
+            state_store,
+            cfr_state,
+            traversal_state,
+            FixedGameStateIteratorGen::new(1),
+        )
+        .with_regret_update(RegretUpdate::Plus)
+        .with_config(config);
+        assert_eq!(config, agent.config);
+
+        let agent = agent.with_regret_update(RegretUpdate::Linear);
+        assert_eq!(RegretUpdate::Linear, agent.config.regret_update);
+        assert_eq!(config.pruning, agent.config.pruning);
+    }
+
+    #[test]
+    fn test_pruning_skips_and_revives() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        let (mut cfr_state, traversal_state) = state_store.new_state(game_state.clone(), 0);
+        let mut matcher = RegretMatcher::new(3).unwrap();
+        matcher
+            .update_regret(array![-200.0, 10.0, 5.0].view())
+            .unwrap();
+        cfr_state.add(
+            traversal_state.node_idx(),
+            traversal_state.chosen_child_idx(),
+            NodeData::Player(PlayerData {
+                regret_matcher: Some(Box::new(matcher)),
+                player_idx: 0,
+            }),
+        );
+        let mut agent = CFRAgent::<BasicCFRActionGenerator, FixedGameStateIteratorGen>::new(
+            state_store.clone(),
+            cfr_state,
+            traversal_state,
+            FixedGameStateIteratorGen::new(1),
+        )
+        .with_pruning(PruneConfig {
+            threshold: -100.0,
+            skip: 2,
+            warmup: 1,
+        });
+        let actions = vec![
+            AgentAction::Fold,
+            AgentAction::Bet(10.0),
+            AgentAction::AllIn,
+        ];
+
+        // Only folding has done badly enough to be pruned.
+        agent.prune_after_update(&[0, 1, 2]);
+        assert_eq!(vec![0], agent.take_pruned(&game_state, &actions));
+        assert_eq!(vec![0], agent.take_pruned(&game_state, &actions));
+        // Then it's played out again.
+        assert!(agent.take_pruned(&game_state, &actions).is_empty());
+
+        // Something is always played out.
+        let mut target_node = agent.get_mut_target_node();
+        for idx in 0..3 {
+            target_node.set_pruned(idx, 1);
+        }
+        drop(target_node);
+        assert!(agent.take_pruned(&game_state, &actions).is_empty());
+        assert_eq!(0, agent.get_mut_target_node().get_pruned(0));
+    }
+
+    #[test]
+    fn test_run_heads_up_pruned() {
+        let game_state = GameState::new_starting(vec![50.0; 2], 5.0, 2.5, 0.0, 0);
+        let mut state_store = StateStore::new();
+        let agents: Vec<Box<dyn Agent>> = (0..2)
+            .map(|i| {
+                let (cfr_state, traversal_state) = state_store.new_state(game_state.clone(), i);
+                Box::new(
+                    CFRAgent::<BasicCFRActionGenerator, FixedGameStateIteratorGen>::new(
+                        state_store.clone(),
+                        cfr_state,
+                        traversal_state,
+                        FixedGameStateIteratorGen::new(4),
+                    )
+                    .with_pruning(PruneConfig {
+                        threshold: 0.0,
+                        skip: 1,
+                        warmup: 1,
+                    }),
+                ) as Box<dyn Agent>
+            })
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state)
+            .agents(agents)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        assert!(sim.game_state.is_complete());
+    }
+
     #[test]
     fn test_run_heads_up() {
         let num_agents = 2;
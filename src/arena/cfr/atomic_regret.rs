@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Regrets and strategy sums for a fixed set of infosets that any number
+/// of threads can add to at once without a lock.
+///
+/// Every value is an `f32` kept in an `AtomicU32`, and adding to it is a
+/// compare and swap loop, so threads that update the same infoset retry
+/// rather than wait on each other. For infosets that are hot enough for
+/// even that to contend, `RegretDeltas` collects a thread's updates and
+/// adds them in batches.
+///
+/// Each infoset has the same number of actions, and the current strategy
+/// is regret matching over the positive regrets.
+///
+/// `ParallelTrainer` keeps the regrets its shards share in one with
+/// `Accumulation::Atomic`.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::cfr::AtomicRegretTable;
+///
+/// let table = AtomicRegretTable::new(10, 3);
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| table.add_regrets(7, &[1.0, 0.0, 3.0]));
+///     }
+/// });
+///
+/// let mut strategy = [0.0; 3];
+/// table.current_strategy(7, &mut strategy);
+/// assert_eq!([0.25, 0.0, 0.75], strategy);
+/// ```
+#[derive(Debug)]
+pub struct AtomicRegretTable {
+    num_actions: usize,
+    regrets: Box<[AtomicU32]>,
+    strategy_sums: Box<[AtomicU32]>,
+}
+
+fn zeros(len: usize) -> Box<[AtomicU32]> {
+    (0..len)
+        .map(|_| AtomicU32::new(0.0_f32.to_bits()))
+        .collect()
+}
+
+fn atomic_add(value: &AtomicU32, delta: f32) {
+    // The closure always returns Some so this can't fail.
+    let _ = value.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+        Some((f32::from_bits(bits) + delta).to_bits())
+    });
+}
+
+fn load(value: &AtomicU32) -> f32 {
+    f32::from_bits(value.load(Ordering::Relaxed))
+}
+
+/// Normalize `weights` into `out`, or spread evenly if none are positive.
+fn normalize(weights: impl Iterator<Item = f32> + Clone, out: &mut [f32]) {
+    let total: f32 = weights.clone().map(|w| w.max(0.0)).sum();
+    if total > 0.0 {
+        for (o, w) in out.iter_mut().zip(weights) {
+            *o = w.max(0.0) / total;
+        }
+    } else {
+        out.fill(1.0 / out.len() as f32);
+    }
+}
+
+impl AtomicRegretTable {
+    pub fn new(num_infosets: usize, num_actions: usize) -> Self {
+        Self {
+            num_actions,
+            regrets: zeros(num_infosets * num_actions),
+            strategy_sums: zeros(num_infosets * num_actions),
+        }
+    }
+
+    pub fn num_infosets(&self) -> usize {
+        self.regrets.len() / self.num_actions.max(1)
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.num_actions
+    }
+
+    fn slots(&self, infoset: usize) -> std::ops::Range<usize> {
+        assert!(
+            infoset < self.num_infosets(),
+            "Infoset {infoset} is out of range"
+        );
+        infoset * self.num_actions..(infoset + 1) * self.num_actions
+    }
+
+    /// Add to the regret of every action at `infoset`.
+    pub fn add_regrets(&self, infoset: usize, deltas: &[f32]) {
+        assert_eq!(self.num_actions, deltas.len());
+        for (value, delta) in self.regrets[self.slots(infoset)].iter().zip(deltas) {
+            atomic_add(value, *delta);
+        }
+    }
+
+    /// Add the probability of each action at `infoset`, weighted by how
+    /// likely the player was to reach it, to the running strategy sum.
+    pub fn add_strategy(&self, infoset: usize, weights: &[f32]) {
+        assert_eq!(self.num_actions, weights.len());
+        for (value, weight) in self.strategy_sums[self.slots(infoset)].iter().zip(weights) {
+            atomic_add(value, *weight);
+        }
+    }
+
+    /// The accumulated regrets at `infoset`.
+    pub fn regrets(&self, infoset: usize) -> Vec<f32> {
+        self.regrets[self.slots(infoset)].iter().map(load).collect()
+    }
+
+    /// The accumulated strategy sums at `infoset`.
+    pub fn strategy_sums(&self, infoset: usize) -> Vec<f32> {
+        self.strategy_sums[self.slots(infoset)]
+            .iter()
+            .map(load)
+            .collect()
+    }
+
+    /// Write the strategy to play now at `infoset` into `out`: each action
+    /// in proportion to its positive regret, or all evenly if none have any.
+    pub fn current_strategy(&self, infoset: usize, out: &mut [f32]) {
+        assert_eq!(self.num_actions, out.len());
+        normalize(self.regrets[self.slots(infoset)].iter().map(load), out);
+    }
+
+    /// Write the average strategy over all the updates at `infoset` into
+    /// `out`. This is the one that converges.
+    pub fn average_strategy(&self, infoset: usize, out: &mut [f32]) {
+        assert_eq!(self.num_actions, out.len());
+        normalize(
+            self.strategy_sums[self.slots(infoset)].iter().map(load),
+            out,
+        );
+    }
+}
+
+/// One thread's regret and strategy updates, held back and added to an
+/// `AtomicRegretTable` in batches.
+///
+/// Updates to the same infoset are summed locally, so a hot infoset costs
+/// one atomic add per action per flush rather than one per update. The
+/// deltas are flushed after `flush_every` updates and when this is dropped,
+/// so nothing is lost, but other threads don't see a thread's updates until
+/// then.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::cfr::{AtomicRegretTable, RegretDeltas};
+///
+/// let table = AtomicRegretTable::new(4, 2);
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             let mut deltas = RegretDeltas::new(&table, 64);
+///             for _ in 0..100 {
+///                 deltas.add_regrets(1, &[1.0, -1.0]);
+///             }
+///         });
+///     }
+/// });
+/// assert_eq!(vec![400.0, -400.0], table.regrets(1));
+/// ```
+#[derive(Debug)]
+pub struct RegretDeltas<'a> {
+    table: &'a AtomicRegretTable,
+    flush_every: usize,
+    pending_updates: usize,
+    /// Regret deltas followed by strategy deltas, per infoset.
+    deltas: HashMap<usize, Vec<f32>>,
+}
+
+impl<'a> RegretDeltas<'a> {
+    pub fn new(table: &'a AtomicRegretTable, flush_every: usize) -> Self {
+        Self {
+            table,
+            flush_every: flush_every.max(1),
+            pending_updates: 0,
+            deltas: HashMap::new(),
+        }
+    }
+
+    fn entry(&mut self, infoset: usize) -> &mut [f32] {
+        let num_actions = self.table.num_actions();
+        self.deltas
+            .entry(infoset)
+            .or_insert_with(|| vec![0.0; 2 * num_actions])
+    }
+
+    fn updated(&mut self) {
+        self.pending_updates += 1;
+        if self.pending_updates >= self.flush_every {
+            self.flush();
+        }
+    }
+
+    pub fn add_regrets(&mut self, infoset: usize, deltas: &[f32]) {
+        assert_eq!(self.table.num_actions(), deltas.len());
+        for (total, delta) in self.entry(infoset).iter_mut().zip(deltas) {
+            *total += delta;
+        }
+        self.updated();
+    }
+
+    pub fn add_strategy(&mut self, infoset: usize, weights: &[f32]) {
+        let num_actions = self.table.num_actions();
+        assert_eq!(num_actions, weights.len());
+        for (total, weight) in self.entry(infoset)[num_actions..].iter_mut().zip(weights) {
+            *total += weight;
+        }
+        self.updated();
+    }
+
+    /// How many updates are waiting to be added to the table.
+    pub fn pending_updates(&self) -> usize {
+        self.pending_updates
+    }
+
+    /// Add everything held back to the table.
+    pub fn flush(&mut self) {
+        let num_actions = self.table.num_actions();
+        for (infoset, deltas) in self.deltas.drain() {
+            let (regrets, strategy) = deltas.split_at(num_actions);
+            self.table.add_regrets(infoset, regrets);
+            self.table.add_strategy(infoset, strategy);
+        }
+        self.pending_updates = 0;
+    }
+}
+
+impl Drop for RegretDeltas<'_> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_adds_are_not_lost() {
+        let table = AtomicRegretTable::new(2, 3);
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..1_000 {
+                        table.add_regrets(0, &[1.0, -1.0, 0.5]);
+                        table.add_strategy(0, &[0.5, 0.5, 0.0]);
+                    }
+                });
+            }
+        });
+        assert_eq!(vec![8_000.0, -8_000.0, 4_000.0], table.regrets(0));
+        assert_eq!(vec![0.0; 3], table.regrets(1));
+        assert_eq!(vec![4_000.0, 4_000.0, 0.0], table.strategy_sums(0));
+
+        let mut strategy = [0.0; 3];
+        table.current_strategy(0, &mut strategy);
+        assert_eq!([2.0 / 3.0, 0.0, 1.0 / 3.0], strategy);
+        table.average_strategy(0, &mut strategy);
+        assert_eq!([0.5, 0.5, 0.0], strategy);
+
+        // Nothing has positive regret yet so play evenly.
+        table.current_strategy(1, &mut strategy);
+        assert_eq!([1.0 / 3.0; 3], strategy);
+    }
+
+    #[test]
+    fn test_deltas_flush() {
+        let table = AtomicRegretTable::new(3, 2);
+        let mut deltas = RegretDeltas::new(&table, 3);
+        deltas.add_regrets(2, &[1.0, 2.0]);
+        deltas.add_strategy(2, &[1.0, 0.0]);
+        assert_eq!(2, deltas.pending_updates());
+        assert_eq!(vec![0.0, 0.0], table.regrets(2));
+
+        // The third update flushes.
+        deltas.add_regrets(0, &[-1.0, 1.0]);
+        assert_eq!(0, deltas.pending_updates());
+        assert_eq!(vec![1.0, 2.0], table.regrets(2));
+        assert_eq!(vec![-1.0, 1.0], table.regrets(0));
+
+        // Anything left over is added when the deltas are dropped.
+        deltas.add_regrets(2, &[1.0, 1.0]);
+        drop(deltas);
+        assert_eq!(vec![2.0, 3.0], table.regrets(2));
+        let mut strategy = [0.0; 2];
+        table.average_strategy(2, &mut strategy);
+        assert_eq!([1.0, 0.0], strategy);
+    }
+}
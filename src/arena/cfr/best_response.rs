@@ -0,0 +1,1070 @@
+//! Measure how exploitable a trained strategy is with an exact best response.
+//!
+//! A best response plays every decision of one seat to win the most against
+//! the strategy of the other, knowing how often it takes each action. What
+//! the two seats' best responses win, on average, is the exploitability. It's
+//! zero for a Nash equilibrium, so watching it fall is how to tell that
+//! training is converging.
+//!
+//! [`BestResponse`] knows what the seat it plays knows: its own cards, the
+//! board and the betting. At each of its decisions it weighs every hand the
+//! opponent could hold by how likely the deal and the opponent's play so far
+//! make it, and takes the one action that wins the most against all of them.
+//! From a game state before the hole cards are dealt it plays every deal,
+//! each as likely as any other, and finds each player's strategy for their
+//! cards the way the `CFRHistorian` grew their tree, under their own hole
+//! cards. From a game state with the hands already dealt there's only the
+//! one deal, and the opponent holds the hand dealt to them.
+//!
+//! [`KnownHandsBestResponse`] also sees the opponent's cards, and picks its
+//! actions deal by deal. Knowing more can only help, so what it wins is an
+//! upper bound on what [`BestResponse`] wins. With the hands already dealt
+//! the two are the same.
+//!
+//! Both walk the whole game tree from a `GameState`, every action the
+//! `ActionGenerator` allows and every card that can come, replaying the hand
+//! through a simulation to reach each node. That only finishes for small
+//! games, such as a hand from the turn or river, or a whole hand dealt from a
+//! deck cut down with `GameState::deck`.
+//! [`LocalBestResponse`](super::LocalBestResponse) estimates a lower bound for
+//! bigger ones.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::arena::action::{Action, AgentAction};
+use crate::arena::agent::FoldingAgent;
+use crate::arena::errors::ExploitabilityError;
+use crate::arena::game_state::Round;
+use crate::arena::{Agent, GameState, Historian, HistorianError, HoldemSimulationBuilder};
+use crate::core::{Card, CardSet};
+
+use super::{ActionGenerator, BasicCFRActionGenerator, CFRState, StrategyProfile, TraversalState};
+
+/// How many hole cards each player is dealt.
+const HOLE_CARDS: usize = 2;
+
+/// How exploitable a strategy is, from [`BestResponse::exploitability`] or
+/// [`KnownHandsBestResponse::exploitability`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exploitability {
+    /// What a best response wins in each seat, in chips, counting what the
+    /// seat had already put in the pot as lost.
+    pub best_response: [f32; 2],
+    /// The average of the two, in thousandths of a big blind per hand.
+    pub mbb_per_hand: f32,
+}
+
+impl Exploitability {
+    fn new(best_response: [f32; 2], game_state: &GameState) -> Self {
+        let chips = (best_response[0] + best_response[1]) / 2.0;
+        Self {
+            best_response,
+            mbb_per_hand: chips / game_state.big_blind * 1000.0,
+        }
+    }
+}
+
+impl fmt::Display for Exploitability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} mbb/hand", self.mbb_per_hand)
+    }
+}
+
+/// The strategies of every player in `cfr_states`, in seat order, each from
+/// their own tree.
+fn profile_of(cfr_states: &[CFRState]) -> StrategyProfile {
+    let mut profile = StrategyProfile::new();
+    for (player_idx, cfr_state) in cfr_states.iter().enumerate() {
+        profile.extend(StrategyProfile::from_cfr_state(cfr_state, player_idx));
+    }
+    profile
+}
+
+/// Compute the exploitability of the average strategy trained in
+/// `cfr_states`, playing from `game_state`, with a [`BestResponse`].
+/// `cfr_states` has each player's tree in seat order, and each player's
+/// strategy comes from their own tree, as in
+/// [`StrategyProfile::from_state_store`].
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::{BasicCFRActionGenerator, CFRState, exploitability};
+/// use rs_poker::core::Hand;
+///
+/// // Nine cards, just enough for two hands and a board. The big blind is
+/// // all in, so the small blind calls or folds.
+/// let mut game_state = GameState::new_starting(vec![10.0, 2.0], 2.0, 1.0, 0.0, 0);
+/// game_state.deck = Hand::new_from_str("AsKsQsJsTs9s8s7s6s").unwrap().into();
+/// let cfr_states = vec![CFRState::new(game_state.clone()); 2];
+/// let result = exploitability::<BasicCFRActionGenerator>(&cfr_states, &game_state).unwrap();
+/// assert!(result.mbb_per_hand > 0.0);
+/// ```
+pub fn exploitability<T: ActionGenerator + 'static>(
+    cfr_states: &[CFRState],
+    game_state: &GameState,
+) -> Result<Exploitability, ExploitabilityError> {
+    BestResponse::<T>::new(profile_of(cfr_states)).exploitability(game_state)
+}
+
+/// Compute the exploitability of the average strategy trained in
+/// `cfr_states` the same as [`exploitability`], but with a
+/// [`KnownHandsBestResponse`] that sees the opponent's cards. This is an
+/// upper bound, see the module docs.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::{
+///     BasicCFRActionGenerator, CFRState, exploitability, known_hands_exploitability,
+/// };
+/// use rs_poker::core::Hand;
+///
+/// let mut game_state = GameState::new_starting(vec![10.0, 2.0], 2.0, 1.0, 0.0, 0);
+/// game_state.deck = Hand::new_from_str("AsKsQsJsTs9s8s7s6s").unwrap().into();
+/// let cfr_states = vec![CFRState::new(game_state.clone()); 2];
+/// let known =
+///     known_hands_exploitability::<BasicCFRActionGenerator>(&cfr_states, &game_state).unwrap();
+/// let guessing = exploitability::<BasicCFRActionGenerator>(&cfr_states, &game_state).unwrap();
+/// assert!(known.mbb_per_hand > guessing.mbb_per_hand);
+/// ```
+pub fn known_hands_exploitability<T: ActionGenerator + 'static>(
+    cfr_states: &[CFRState],
+    game_state: &GameState,
+) -> Result<Exploitability, ExploitabilityError> {
+    KnownHandsBestResponse::<T>::new(profile_of(cfr_states)).exploitability(game_state)
+}
+
+/// Plays an exact best response against a heads up [`StrategyProfile`],
+/// knowing only its own cards. See the module docs.
+///
+/// The game state is either from before the hole cards are dealt, and then
+/// every deal is played, or has both players' hands dealt.
+///
+/// `T` is the `ActionGenerator` the profile was trained with. It finds the
+/// strategy's decisions in the profile the same way the `CFRHistorian`
+/// built them, and gives the best response its actions. Decisions the
+/// profile has no strategy for are played uniformly at random.
+pub struct BestResponse<T: ActionGenerator = BasicCFRActionGenerator> {
+    profile: StrategyProfile,
+    action_generator: PhantomData<T>,
+}
+
+impl<T: ActionGenerator + 'static> BestResponse<T> {
+    pub fn new(profile: StrategyProfile) -> Self {
+        Self {
+            profile,
+            action_generator: PhantomData,
+        }
+    }
+
+    /// What a best response in seat `br_idx` wins from `game_state` on
+    /// average over the deals, in chips, counting what it had already put
+    /// in the pot as lost.
+    pub fn value(&self, game_state: &GameState, br_idx: usize) -> Result<f32, ExploitabilityError> {
+        Walker::<T>::new(&self.profile, game_state, br_idx, Knowledge::OwnCards)
+            .map(|walker| walker.value())
+    }
+
+    /// The best response's winnings in both seats and their average.
+    pub fn exploitability(
+        &self,
+        game_state: &GameState,
+    ) -> Result<Exploitability, ExploitabilityError> {
+        let best_response = [self.value(game_state, 0)?, self.value(game_state, 1)?];
+        Ok(Exploitability::new(best_response, game_state))
+    }
+}
+
+/// Plays a best response against a heads up [`StrategyProfile`] the same
+/// as [`BestResponse`], but knowing the opponent's cards as well. See the
+/// module docs.
+pub struct KnownHandsBestResponse<T: ActionGenerator = BasicCFRActionGenerator> {
+    profile: StrategyProfile,
+    action_generator: PhantomData<T>,
+}
+
+impl<T: ActionGenerator + 'static> KnownHandsBestResponse<T> {
+    pub fn new(profile: StrategyProfile) -> Self {
+        Self {
+            profile,
+            action_generator: PhantomData,
+        }
+    }
+
+    /// What a best response in seat `br_idx` wins from `game_state` on
+    /// average over the deals, in chips, counting what it had already put
+    /// in the pot as lost.
+    pub fn value(&self, game_state: &GameState, br_idx: usize) -> Result<f32, ExploitabilityError> {
+        Walker::<T>::new(&self.profile, game_state, br_idx, Knowledge::BothHands)
+            .map(|walker| walker.value())
+    }
+
+    /// The best response's winnings in both seats and their average.
+    pub fn exploitability(
+        &self,
+        game_state: &GameState,
+    ) -> Result<Exploitability, ExploitabilityError> {
+        let best_response = [self.value(game_state, 0)?, self.value(game_state, 1)?];
+        Ok(Exploitability::new(best_response, game_state))
+    }
+}
+
+/// The cards the best response picks its actions by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Knowledge {
+    OwnCards,
+    BothHands,
+}
+
+/// Walks the game tree for one seat's best response.
+///
+/// Every node is walked once for all the deals that can reach it, each
+/// weighted by how likely the opponent's play and the board cards dealt
+/// since make it. Deals the opponent never plays into, and deals with a
+/// hole card that's come on the board, are left out below that point.
+struct Walker<'a, T: ActionGenerator> {
+    profile: &'a StrategyProfile,
+    game_state: &'a GameState,
+    br_idx: usize,
+    knowledge: Knowledge,
+    /// Each player's hole cards in every deal, in the order the hand deals
+    /// them. Empty for hands that were dealt in the game state.
+    deals: Vec<[Vec<Card>; 2]>,
+    /// The hole cards of both players in each deal.
+    hole_cards: Vec<CardSet>,
+    /// The seat each hole card is dealt to, in order.
+    deal_order: Vec<usize>,
+    action_generator: PhantomData<T>,
+}
+
+impl<'a, T: ActionGenerator + 'static> Walker<'a, T> {
+    fn new(
+        profile: &'a StrategyProfile,
+        game_state: &'a GameState,
+        br_idx: usize,
+        knowledge: Knowledge,
+    ) -> Result<Self, ExploitabilityError> {
+        let deals = deals(game_state)?;
+        let hole_cards = deals
+            .iter()
+            .map(|deal| deal.iter().flatten().copied().collect())
+            .collect();
+        let deal_order = if deals[0][0].is_empty() {
+            Vec::new()
+        } else {
+            deal_order(game_state)
+        };
+        Ok(Self {
+            profile,
+            game_state,
+            br_idx,
+            knowledge,
+            deals,
+            hole_cards,
+            deal_order,
+            action_generator: PhantomData,
+        })
+    }
+
+    /// The best response's winnings, averaged over the deals.
+    fn value(&self) -> f32 {
+        let live: Vec<(usize, f32)> = (0..self.deals.len()).map(|deal| (deal, 1.0)).collect();
+        let total: f32 = self.walk(&mut Script::default(), &live).iter().sum();
+        total / self.deals.len() as f32
+    }
+
+    /// What the best response wins from the node `script` leads to, for
+    /// each of the `live` deals, weighted by its reach.
+    fn walk(&self, script: &mut Script, live: &[(usize, f32)]) -> Vec<f32> {
+        let Some(&(first, _)) = live.first() else {
+            return Vec::new();
+        };
+        match self.replay(first, script) {
+            Node::Terminal(end) => {
+                // Without a showdown the cards don't change who wins.
+                if end.num_active_players() + end.num_all_in_players() == 1 {
+                    let value = self.payoff(&end);
+                    return live.iter().map(|(_, reach)| reach * value).collect();
+                }
+                live.iter()
+                    .map(|&(deal, reach)| {
+                        let value = if deal == first {
+                            self.payoff(&end)
+                        } else {
+                            match self.replay(deal, script) {
+                                Node::Terminal(end) => self.payoff(&end),
+                                _ => unreachable!("Deals only differ in the cards"),
+                            }
+                        };
+                        reach * value
+                    })
+                    .collect()
+            }
+            Node::Decision {
+                game_state: at,
+                path,
+                generator,
+            } => {
+                let idx = at.to_act_idx();
+                let possible = generator.gen_possible_actions(&at);
+                if idx == self.br_idx {
+                    let values: Vec<Vec<f32>> = possible
+                        .iter()
+                        .map(|action| {
+                            script.actions.push(action.clone());
+                            let values = self.walk(script, live);
+                            script.actions.pop();
+                            values
+                        })
+                        .collect();
+                    self.best_values(live, &values)
+                } else {
+                    let first = self.strategy(&generator, &at, idx, &path, &possible);
+                    let strategies = self.opponent_strategies(script, live, idx, &possible, first);
+                    let mut total = vec![0.0; live.len()];
+                    for (action_idx, action) in possible.iter().enumerate() {
+                        let (positions, reached): (Vec<usize>, Vec<(usize, f32)>) = live
+                            .iter()
+                            .zip(&strategies)
+                            .enumerate()
+                            .map(|(pos, (&(deal, reach), strategy))| {
+                                (pos, (deal, reach * strategy[action_idx]))
+                            })
+                            .filter(|(_, (_, reach))| *reach > 0.0)
+                            .unzip();
+                        if reached.is_empty() {
+                            continue;
+                        }
+                        script.actions.push(action.clone());
+                        let values = self.walk(script, &reached);
+                        script.actions.pop();
+                        for (pos, value) in positions.into_iter().zip(values) {
+                            total[pos] += value;
+                        }
+                    }
+                    total
+                }
+            }
+            Node::Deal { unseen, num_cards } => {
+                let cards: Vec<Card> = unseen.into_iter().collect();
+                let mut total = vec![0.0; live.len()];
+                for dealt in combinations(&cards, num_cards) {
+                    let dealt_set: CardSet = dealt.iter().copied().collect();
+                    let (positions, reached): (Vec<usize>, Vec<(usize, f32)>) = live
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (deal, _))| self.hole_cards[*deal].is_disjoint(dealt_set))
+                        .map(|(pos, &(deal, reach))| {
+                            // Every card the deal's players weren't dealt is
+                            // as likely to come.
+                            let left = cards.len() - self.hole_cards[deal].count();
+                            (pos, (deal, reach / num_combinations(left, num_cards)))
+                        })
+                        .unzip();
+                    if reached.is_empty() {
+                        continue;
+                    }
+                    script.cards.extend_from_slice(&dealt);
+                    let values = self.walk(script, &reached);
+                    script.cards.truncate(script.cards.len() - num_cards);
+                    for (pos, value) in positions.into_iter().zip(values) {
+                        total[pos] += value;
+                    }
+                }
+                total
+            }
+        }
+    }
+
+    /// The value of each `live` deal when the best response takes the
+    /// action that does best over every deal it can't tell apart.
+    fn best_values(&self, live: &[(usize, f32)], values: &[Vec<f32>]) -> Vec<f32> {
+        let mut totals: HashMap<(CardSet, CardSet), Vec<f32>> = HashMap::new();
+        for (pos, &(deal, _)) in live.iter().enumerate() {
+            let total = totals
+                .entry(self.known_cards(deal))
+                .or_insert_with(|| vec![0.0; values.len()]);
+            for (action_total, action_values) in total.iter_mut().zip(values) {
+                *action_total += action_values[pos];
+            }
+        }
+        let best: HashMap<(CardSet, CardSet), usize> = totals
+            .into_iter()
+            .map(|(known, total)| {
+                let best = total
+                    .iter()
+                    .enumerate()
+                    .fold((0, f32::NEG_INFINITY), |best, (action_idx, value)| {
+                        if *value > best.1 {
+                            (action_idx, *value)
+                        } else {
+                            best
+                        }
+                    })
+                    .0;
+                (known, best)
+            })
+            .collect();
+        live.iter()
+            .enumerate()
+            .map(|(pos, &(deal, _))| values[best[&self.known_cards(deal)]][pos])
+            .collect()
+    }
+
+    /// The hole cards the best response knows in `deal`, its own and, if
+    /// it sees them, the opponent's.
+    fn known_cards(&self, deal: usize) -> (CardSet, CardSet) {
+        let [own, other] = [self.br_idx, 1 - self.br_idx]
+            .map(|idx| self.deals[deal][idx].iter().copied().collect::<CardSet>());
+        match self.knowledge {
+            Knowledge::OwnCards => (own, CardSet::new()),
+            Knowledge::BothHands => (own, other),
+        }
+    }
+
+    /// How often the opponent takes each of `possible` in every `live`
+    /// deal. Their strategy only depends on their own hole cards, so it's
+    /// looked up once for each hand they can hold. `first` is their strategy
+    /// in the first live deal.
+    fn opponent_strategies(
+        &self,
+        script: &Script,
+        live: &[(usize, f32)],
+        idx: usize,
+        possible: &[AgentAction],
+        first: Vec<f32>,
+    ) -> Vec<Vec<f32>> {
+        let mut by_hand: HashMap<&[Card], Vec<f32>> = HashMap::new();
+        by_hand.insert(&self.deals[live[0].0][idx], first);
+        live.iter()
+            .map(|&(deal, _)| {
+                by_hand
+                    .entry(&self.deals[deal][idx])
+                    .or_insert_with(|| match self.replay(deal, script) {
+                        Node::Decision {
+                            game_state: at,
+                            path,
+                            generator,
+                        } => self.strategy(&generator, &at, idx, &path, possible),
+                        _ => unreachable!("Deals only differ in the cards"),
+                    })
+                    .clone()
+            })
+            .collect()
+    }
+
+    /// What the best response's stack gained at the end of a hand.
+    fn payoff(&self, end: &GameState) -> f32 {
+        end.stacks[self.br_idx]
+            - self.game_state.stacks[self.br_idx]
+            - self.game_state.player_bet[self.br_idx]
+    }
+
+    /// How often the strategy takes each of `possible` at the decision
+    /// reached by `path`.
+    fn strategy(
+        &self,
+        generator: &T,
+        game_state: &GameState,
+        player_idx: usize,
+        path: &[usize],
+        possible: &[AgentAction],
+    ) -> Vec<f32> {
+        let uniform = vec![1.0 / possible.len() as f32; possible.len()];
+        let Some(probabilities) = self.profile.get(player_idx, path) else {
+            return uniform;
+        };
+        let strategy: Vec<f32> = possible
+            .iter()
+            .map(|action| {
+                probabilities
+                    .get(generator.action_to_idx(game_state, action))
+                    .copied()
+                    .unwrap_or(0.0)
+            })
+            .collect();
+        let total: f32 = strategy.iter().sum();
+        if total > 0.0 {
+            strategy.into_iter().map(|p| p / total).collect()
+        } else {
+            uniform
+        }
+    }
+
+    /// Play the hand from the game state with the hole cards of `deal`,
+    /// following `script`, and stop at the first decision or deal it has
+    /// nothing for.
+    fn replay(&self, deal: usize, script: &Script) -> Node<T> {
+        let game_state = self.game_state;
+        let replay = Rc::new(RefCell::new(Replay::<T> {
+            actions: script.actions.clone(),
+            next_action: 0,
+            cards_left: script.cards.len(),
+            paths: [vec![0], vec![0]],
+            dealt_cards: [CardSet::new(); 2],
+            generators: [0, 1].map(|idx| {
+                T::new(
+                    CFRState::new(game_state.clone()),
+                    TraversalState::new_root(idx),
+                )
+            }),
+            frontier: None,
+        }));
+        let agents: Vec<Box<dyn Agent>> = (0..2)
+            .map(|_| {
+                Box::new(ScriptedAgent {
+                    replay: replay.clone(),
+                }) as Box<dyn Agent>
+            })
+            .collect();
+        let historians: Vec<Box<dyn Historian>> = vec![Box::new(ScriptedHistorian {
+            replay: replay.clone(),
+        })];
+
+        let mut next_hole_card = [0; 2];
+        let mut stacked: Vec<Card> = self
+            .deal_order
+            .iter()
+            .map(|&idx| {
+                next_hole_card[idx] += 1;
+                self.deals[deal][idx][next_hole_card[idx] - 1]
+            })
+            .collect();
+        stacked.extend_from_slice(&script.cards);
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state.clone())
+            .agents(agents)
+            .historians(historians)
+            .stacked_deck(stacked)
+            .build()
+            .unwrap();
+        sim.run(&mut crate::core::rng());
+
+        let mut replay = replay.borrow_mut();
+        match replay.frontier.take() {
+            Some(Frontier::Decision(at)) => {
+                let idx = at.to_act_idx();
+                Node::Decision {
+                    path: replay.paths[idx].clone(),
+                    generator: T::new(
+                        CFRState::new(game_state.clone()),
+                        TraversalState::new_root(idx),
+                    ),
+                    game_state: at,
+                }
+            }
+            Some(Frontier::Deal(at)) => Node::Deal {
+                // The deal's hole cards are dead in the replay, but the
+                // board can't tell them from the rest of the deck.
+                unseen: !at.dead_cards() | self.hole_cards[deal],
+                // Once nobody's left to bet the order the board comes in
+                // doesn't matter, so the rest of it comes at once.
+                num_cards: if at.num_active_players() < 2 {
+                    5 - at.board.len()
+                } else if at.board.is_empty() {
+                    3
+                } else {
+                    1
+                },
+            },
+            None => Node::Terminal(sim.game_state),
+        }
+    }
+}
+
+/// Every way to deal the hole cards the hand still has to deal from
+/// `game_state`, checking it can be best responded to.
+fn deals(game_state: &GameState) -> Result<Vec<[Vec<Card>; 2]>, ExploitabilityError> {
+    if game_state.num_players != 2 {
+        return Err(ExploitabilityError::NotHeadsUp(game_state.num_players));
+    }
+    if game_state.is_complete() {
+        return Err(ExploitabilityError::Complete);
+    }
+    if matches!(
+        game_state.round,
+        Round::Starting | Round::Ante | Round::DealPreflop
+    ) {
+        let cards: Vec<Card> = (!game_state.dead_cards()).into_iter().collect();
+        let mut deals = Vec::new();
+        for first in combinations(&cards, HOLE_CARDS) {
+            let rest: Vec<Card> = cards
+                .iter()
+                .filter(|card| !first.contains(card))
+                .copied()
+                .collect();
+            for second in combinations(&rest, HOLE_CARDS) {
+                deals.push([first.clone(), second]);
+            }
+        }
+        return Ok(deals);
+    }
+
+    let board = game_state.board_set();
+    if let Some(idx) =
+        (0..2).find(|idx| (CardSet::from(game_state.hands[*idx]) - board).count() < HOLE_CARDS)
+    {
+        return Err(ExploitabilityError::HandNotDealt(idx));
+    }
+    Ok(vec![[Vec::new(), Vec::new()]])
+}
+
+/// The seat each hole card goes to, in the order the hand deals them.
+fn deal_order(game_state: &GameState) -> Vec<usize> {
+    let order = Rc::new(RefCell::new(Vec::new()));
+    let agents: Vec<Box<dyn Agent>> = (0..2)
+        .map(|_| Box::new(FoldingAgent) as Box<dyn Agent>)
+        .collect();
+    let mut sim = HoldemSimulationBuilder::default()
+        .game_state(game_state.clone())
+        .agents(agents)
+        .historians(vec![Box::new(DealOrderHistorian {
+            order: order.clone(),
+        })])
+        .build()
+        .unwrap();
+    sim.run(&mut crate::core::rng());
+    order.take()
+}
+
+/// The actions and board cards leading to a node, in the order they're
+/// played and dealt.
+#[derive(Default)]
+struct Script {
+    actions: Vec<AgentAction>,
+    cards: Vec<Card>,
+}
+
+enum Node<T> {
+    Terminal(GameState),
+    Decision {
+        game_state: GameState,
+        /// The acting player's path in their tree.
+        path: Vec<usize>,
+        generator: T,
+    },
+    Deal {
+        /// The cards neither player has seen, including the deal's hole
+        /// cards.
+        unseen: CardSet,
+        num_cards: usize,
+    },
+}
+
+/// The first point of a replay the script didn't cover.
+enum Frontier {
+    Decision(GameState),
+    Deal(GameState),
+}
+
+/// What a replay has played of its script and each player's path through
+/// their tree, kept the way the `CFRHistorian` keeps it.
+struct Replay<T: ActionGenerator> {
+    actions: Vec<AgentAction>,
+    next_action: usize,
+    cards_left: usize,
+    paths: [Vec<usize>; 2],
+    dealt_cards: [CardSet; 2],
+    generators: [T; 2],
+    frontier: Option<Frontier>,
+}
+
+impl<T: ActionGenerator> Replay<T> {
+    fn record_card(&mut self, game_state: &GameState, idx: usize, card: Card) {
+        let known = CardSet::from(game_state.hands[idx]) | self.dealt_cards[idx];
+        let card_idx = self.generators[idx].card_to_idx(game_state, known, card);
+        self.dealt_cards[idx].insert(card);
+        self.paths[idx].push(card_idx);
+    }
+}
+
+/// Plays the script's actions, and folds once the replay has gone past it
+/// to get the hand over with.
+struct ScriptedAgent<T: ActionGenerator> {
+    replay: Rc<RefCell<Replay<T>>>,
+}
+
+impl<T: ActionGenerator> Agent for ScriptedAgent<T> {
+    fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+        let mut replay = self.replay.borrow_mut();
+        if replay.frontier.is_some() {
+            return AgentAction::Fold;
+        }
+        match replay.actions.get(replay.next_action).cloned() {
+            Some(action) => {
+                replay.next_action += 1;
+                action
+            }
+            None => {
+                replay.frontier = Some(Frontier::Decision(game_state.clone()));
+                AgentAction::Fold
+            }
+        }
+    }
+}
+
+/// Follows the hand along each player's tree, and notices when the board
+/// is dealt past the script's cards.
+struct ScriptedHistorian<T: ActionGenerator> {
+    replay: Rc<RefCell<Replay<T>>>,
+}
+
+impl<T: ActionGenerator> Historian for ScriptedHistorian<T> {
+    fn record_action(
+        &mut self,
+        _id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        let mut replay = self.replay.borrow_mut();
+        if replay.frontier.is_some() {
+            return Ok(());
+        }
+        match action {
+            // Each player's tree only has their own hole cards.
+            Action::DealStartingHand(payload) => {
+                replay.record_card(game_state, payload.idx, payload.card);
+            }
+            Action::DealCommunity(card) => {
+                if replay.cards_left == 0 {
+                    replay.frontier = Some(Frontier::Deal(game_state.clone()));
+                } else {
+                    replay.cards_left -= 1;
+                    for idx in 0..2 {
+                        replay.record_card(game_state, idx, card);
+                    }
+                }
+            }
+            Action::PlayedAction(payload) => {
+                let idx = replay.generators[0].played_action_to_idx(game_state, &payload);
+                replay.paths.iter_mut().for_each(|path| path.push(idx));
+            }
+            Action::FailedAction(payload) => {
+                let idx = replay.generators[0].played_action_to_idx(game_state, &payload.result);
+                replay.paths.iter_mut().for_each(|path| path.push(idx));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Records the seat of every hole card dealt.
+struct DealOrderHistorian {
+    order: Rc<RefCell<Vec<usize>>>,
+}
+
+impl Historian for DealOrderHistorian {
+    fn record_action(
+        &mut self,
+        _id: u128,
+        _game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        if let Action::DealStartingHand(payload) = action {
+            self.order.borrow_mut().push(payload.idx);
+        }
+        Ok(())
+    }
+}
+
+/// How many ways there are to pick `k` of `n` cards.
+fn num_combinations(n: usize, k: usize) -> f32 {
+    (0..k).fold(1.0, |total, i| total * (n - i) as f32 / (i + 1) as f32)
+}
+
+/// Every way to pick `k` of `cards`, each in the order of `cards`.
+fn combinations(cards: &[Card], k: usize) -> Vec<Vec<Card>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    (0..cards.len())
+        .flat_map(|i| {
+            combinations(&cards[i + 1..], k - 1)
+                .into_iter()
+                .map(move |mut rest| {
+                    rest.insert(0, cards[i]);
+                    rest
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::game_state::{Round, RoundData};
+    use crate::core::{Hand, PlayerBitSet, Rankable};
+
+    use super::*;
+
+    /// On the river player 0 is all in with two pair and player 1 has to
+    /// call with a pair of tens or fold.
+    fn river_all_in() -> GameState {
+        let hand_zero = Hand::new_from_str("AsKsKcAcTh4d8d").unwrap();
+        let hand_one = Hand::new_from_str("JdTcKcAcTh4d8d").unwrap();
+        let board = (hand_zero & hand_one).iter().collect();
+        let round_data = RoundData::new_with_bets(100.0, PlayerBitSet::new(2), 1, vec![900.0, 0.0]);
+        GameState::new(
+            Round::River,
+            round_data,
+            board,
+            vec![hand_zero, hand_one],
+            vec![0.0, 900.0],
+            vec![1000.0, 100.0],
+            5.0,
+            0.0,
+            0.0,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_errors() {
+        let best_response =
+            KnownHandsBestResponse::<BasicCFRActionGenerator>::new(StrategyProfile::new());
+        let game_state = GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.0, 0);
+        assert_eq!(
+            Err(ExploitabilityError::NotHeadsUp(3)),
+            best_response.exploitability(&game_state)
+        );
+        let mut game_state = river_all_in();
+        game_state.hands_mut()[1] = game_state.board_set().into();
+        assert_eq!(
+            Err(ExploitabilityError::HandNotDealt(1)),
+            best_response.exploitability(&game_state)
+        );
+        game_state.round = Round::Complete;
+        assert_eq!(
+            Err(ExploitabilityError::Complete),
+            BestResponse::<BasicCFRActionGenerator>::new(StrategyProfile::new())
+                .exploitability(&game_state)
+        );
+    }
+
+    /// Nine cards, enough for two hands and a board. Player 1 is all in
+    /// from the big blind, so player 0 in the small blind calls or folds
+    /// and nobody decides anything else.
+    fn small_deck() -> GameState {
+        let mut game_state = GameState::new_starting(vec![10.0, 2.0], 2.0, 1.0, 0.0, 0);
+        game_state.deck = Hand::new_from_str("AsKsQsJsTs9h8h7d2c").unwrap().into();
+        game_state
+    }
+
+    /// What calling wins the small blind in every deal of `small_deck`,
+    /// keyed by their hole cards.
+    fn calls(game_state: &GameState) -> HashMap<(Card, Card), Vec<f32>> {
+        let mut calls: HashMap<(Card, Card), Vec<f32>> = HashMap::new();
+        for [zero, one] in deals(game_state).unwrap() {
+            let board = game_state.deck - zero.iter().chain(&one).copied().collect::<CardSet>();
+            let rank = |hole: &[Card]| {
+                let mut hand: Hand = board.into();
+                hand.extend(hole.iter().copied());
+                hand.rank()
+            };
+            let won = match rank(&zero).cmp(&rank(&one)) {
+                std::cmp::Ordering::Greater => 2.0,
+                std::cmp::Ordering::Less => -2.0,
+                std::cmp::Ordering::Equal => 0.0,
+            };
+            calls.entry((zero[0], zero[1])).or_default().push(won);
+        }
+        calls
+    }
+
+    /// The small blind's strategy calling with the hands in `calling` and
+    /// folding the rest.
+    fn small_blind_profile(
+        hands: &HashMap<(Card, Card), Vec<f32>>,
+        calling: &[(Card, Card)],
+    ) -> StrategyProfile {
+        let mut profile = StrategyProfile::new();
+        for &(lo, hi) in hands.keys() {
+            let strategy = if calling.contains(&(lo, hi)) {
+                vec![0.0, 1.0, 0.0]
+            } else {
+                vec![1.0, 0.0, 0.0]
+            };
+            // The hand deals each player's cards lowest first.
+            let path = vec![0, u8::from(lo.min(hi)).into(), u8::from(lo.max(hi)).into()];
+            profile.insert(0, path, strategy);
+        }
+        profile
+    }
+
+    #[test]
+    fn test_guesses_the_opponents_hand() {
+        let game_state = small_deck();
+        let calls = calls(&game_state);
+        let num_deals: usize = calls.values().map(Vec::len).sum();
+        assert_eq!(36 * 21, num_deals);
+
+        // The small blind's best response calls when it wins more than the
+        // blind it gives up, over every hand the big blind can hold.
+        let winning: Vec<(Card, Card)> = calls
+            .iter()
+            .filter(|(_, won)| won.iter().sum::<f32>() > -(won.len() as f32))
+            .map(|(hole, _)| *hole)
+            .collect();
+        assert!(!winning.is_empty() && winning.len() < calls.len());
+        let game_value = calls
+            .values()
+            .map(|won| won.iter().sum::<f32>().max(-(won.len() as f32)))
+            .sum::<f32>()
+            / num_deals as f32;
+
+        let best_response =
+            BestResponse::<BasicCFRActionGenerator>::new(small_blind_profile(&calls, &winning));
+        assert!((best_response.value(&game_state, 0).unwrap() - game_value).abs() < 1e-4);
+        // The big blind can't do anything about it.
+        assert!((best_response.value(&game_state, 1).unwrap() + game_value).abs() < 1e-4);
+        let result = best_response.exploitability(&game_state).unwrap();
+        assert!(result.mbb_per_hand.abs() < 0.1, "{result}");
+
+        // Always calling gives the big blind what the small blind loses
+        // calling with the hands it should fold.
+        let always_call = small_blind_profile(&calls, &calls.keys().copied().collect::<Vec<_>>());
+        let called = calls.values().flatten().sum::<f32>() / num_deals as f32;
+        let result = BestResponse::<BasicCFRActionGenerator>::new(always_call.clone())
+            .exploitability(&game_state)
+            .unwrap();
+        assert!((result.best_response[0] - game_value).abs() < 1e-4);
+        assert!((result.best_response[1] + called).abs() < 1e-4);
+        assert!(result.mbb_per_hand > 0.0, "{result}");
+
+        // Seeing the big blind's cards the small blind folds every deal it
+        // loses, not just the hands that lose on average.
+        let known_hands = calls
+            .values()
+            .flatten()
+            .map(|won| won.max(-1.0))
+            .sum::<f32>()
+            / num_deals as f32;
+        let known = KnownHandsBestResponse::<BasicCFRActionGenerator>::new(always_call)
+            .exploitability(&game_state)
+            .unwrap();
+        assert!((known.best_response[0] - known_hands).abs() < 1e-4);
+        assert!(known.mbb_per_hand > result.mbb_per_hand);
+    }
+
+    #[test]
+    fn test_exploitability() {
+        let game_state = small_deck();
+        let cfr_states = vec![CFRState::new(game_state.clone()); 2];
+        let from_trees =
+            exploitability::<BasicCFRActionGenerator>(&cfr_states, &game_state).unwrap();
+        let from_profile = BestResponse::<BasicCFRActionGenerator>::new(StrategyProfile::new())
+            .exploitability(&game_state)
+            .unwrap();
+        assert_eq!(from_profile, from_trees);
+    }
+
+    #[test]
+    fn test_same_with_the_hands_dealt() {
+        let game_state = river_all_in();
+        let profile = StrategyProfile::new();
+        assert_eq!(
+            KnownHandsBestResponse::<BasicCFRActionGenerator>::new(profile.clone())
+                .exploitability(&game_state),
+            BestResponse::<BasicCFRActionGenerator>::new(profile).exploitability(&game_state)
+        );
+    }
+
+    #[test]
+    fn test_folding_is_unexploitable() {
+        let game_state = river_all_in();
+        let mut profile = StrategyProfile::new();
+        // Player 1 has nothing to go on but the root of their tree.
+        profile.insert(1, vec![0], vec![1.0, 0.0, 0.0]);
+
+        let result = KnownHandsBestResponse::<BasicCFRActionGenerator>::new(profile)
+            .exploitability(&game_state)
+            .unwrap();
+        assert_eq!([100.0, -100.0], result.best_response);
+        assert_eq!(0.0, result.mbb_per_hand);
+    }
+
+    #[test]
+    fn test_calling_is_exploitable() {
+        let game_state = river_all_in();
+        let result = KnownHandsBestResponse::<BasicCFRActionGenerator>::new(StrategyProfile::new())
+            .exploitability(&game_state)
+            .unwrap();
+
+        // Calling loses the whole stack, folding only what's in already.
+        let possible = BasicCFRActionGenerator::new(
+            CFRState::new(game_state.clone()),
+            TraversalState::new_root(1),
+        )
+        .gen_possible_actions(&game_state);
+        let call = 1.0 - 1.0 / possible.len() as f32;
+        let expected = (1.0 - call) * 100.0 + call * 1000.0;
+        assert!((result.best_response[0] - expected).abs() < 1e-3);
+        assert_eq!(-100.0, result.best_response[1]);
+        assert!(result.mbb_per_hand > 0.0, "{result}");
+    }
+
+    #[test]
+    fn test_known_hands_exploitability() {
+        // Untrained trees play uniformly, the same as an empty profile.
+        let game_state = river_all_in();
+        let cfr_states = vec![CFRState::new(game_state.clone()); 2];
+        let from_trees =
+            known_hands_exploitability::<BasicCFRActionGenerator>(&cfr_states, &game_state)
+                .unwrap();
+        let from_profile =
+            KnownHandsBestResponse::<BasicCFRActionGenerator>::new(StrategyProfile::new())
+                .exploitability(&game_state)
+                .unwrap();
+        assert_eq!(from_profile, from_trees);
+    }
+
+    #[test]
+    fn test_all_in_on_the_turn_deals_every_river() {
+        // Both players are all in on the turn, so there's nothing to decide
+        // and the best responses add up to nothing.
+        let hand_zero = Hand::new_from_str("AsKsKcAcTh4d").unwrap();
+        let hand_one = Hand::new_from_str("JdJcKcAcTh4d").unwrap();
+        let board = (hand_zero & hand_one).iter().collect();
+        let round_data = RoundData::new_with_bets(100.0, PlayerBitSet::new(2), 0, vec![0.0, 0.0]);
+        let game_state = GameState::new(
+            Round::Turn,
+            round_data,
+            board,
+            vec![hand_zero, hand_one],
+            vec![0.0, 0.0],
+            vec![100.0, 100.0],
+            5.0,
+            0.0,
+            0.0,
+            0,
+        );
+
+        let result = KnownHandsBestResponse::<BasicCFRActionGenerator>::new(StrategyProfile::new())
+            .exploitability(&game_state)
+            .unwrap();
+        assert!(result.mbb_per_hand.abs() < 1e-2, "{result}");
+        // Player one wins with a jack for trips or a queen for the straight,
+        // six of the 44 rivers.
+        let expected = 100.0 * (38.0 - 6.0) / 44.0;
+        assert!(
+            (result.best_response[0] - expected).abs() < 1e-3,
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_combinations() {
+        let cards: Vec<Card> = CardSet::from(Hand::new_from_str("AsKsQsJs").unwrap())
+            .into_iter()
+            .collect();
+        assert_eq!(4, combinations(&cards, 3).len());
+        assert_eq!(6, combinations(&cards, 2).len());
+        assert_eq!(vec![Vec::<Card>::new()], combinations(&cards, 0));
+    }
+}
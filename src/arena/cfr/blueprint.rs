@@ -0,0 +1,218 @@
+use std::marker::PhantomData;
+use std::path::Path;
+
+use anyhow::Result;
+use rand::Rng;
+use tracing::event;
+
+use crate::arena::action::AgentAction;
+use crate::arena::{Agent, AgentGenerator, GameState};
+use crate::core::rng;
+
+use super::{ActionGenerator, StateStore};
+
+/// Plays the average strategy of a trained `StateStore`, a blueprint, so a
+/// trained strategy can be pitted against other agents in the arena.
+///
+/// Every decision is looked up with `StateStore::query`, going through the
+/// action generator `T` the blueprint was trained with, and an action is
+/// sampled from the strategy there. Nothing is trained while playing.
+///
+/// With an `epsilon` above zero the agent explores, picking uniformly from
+/// the actions on offer that often instead. Spots the blueprint never
+/// reached or trained are checked if that's free and folded if not.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::cfr::{
+///     BasicCFRActionGenerator, BlueprintAgent, OutcomeSamplingConfig, StateStore,
+/// };
+/// use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+///
+/// let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+/// let mut state_store = StateStore::new();
+/// OutcomeSamplingConfig::default().train::<BasicCFRActionGenerator, _>(
+///     &mut state_store,
+///     &game_state,
+///     100,
+///     &mut rand::rng(),
+/// );
+///
+/// let agents: Vec<Box<dyn Agent>> = (0..2)
+///     .map(|_| {
+///         Box::new(
+///             BlueprintAgent::<BasicCFRActionGenerator>::new(state_store.clone())
+///                 .with_epsilon(0.05),
+///         ) as Box<dyn Agent>
+///     })
+///     .collect();
+/// let mut sim = HoldemSimulationBuilder::default()
+///     .game_state(game_state)
+///     .agents(agents)
+///     .build()
+///     .unwrap();
+/// sim.run(&mut rand::rng());
+/// assert!(sim.game_state.is_complete());
+/// ```
+pub struct BlueprintAgent<T: ActionGenerator> {
+    state_store: StateStore,
+    epsilon: f32,
+    _generator: PhantomData<T>,
+}
+
+impl<T: ActionGenerator> BlueprintAgent<T> {
+    pub fn new(state_store: StateStore) -> Self {
+        Self {
+            state_store,
+            epsilon: 0.0,
+            _generator: PhantomData,
+        }
+    }
+
+    /// Load the blueprint saved with `StateStore::save_to_file`.
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        Ok(Self::new(StateStore::load_from_file(path)?))
+    }
+
+    /// Explore with probability `epsilon`, clamped to between zero and one.
+    pub fn with_epsilon(mut self, epsilon: f32) -> Self {
+        self.epsilon = epsilon.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn epsilon(&self) -> f32 {
+        self.epsilon
+    }
+
+    pub fn state_store(&self) -> &StateStore {
+        &self.state_store
+    }
+}
+
+impl<T: ActionGenerator> Clone for BlueprintAgent<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state_store: self.state_store.clone(),
+            epsilon: self.epsilon,
+            _generator: PhantomData,
+        }
+    }
+}
+
+impl<T: ActionGenerator> Agent for BlueprintAgent<T> {
+    fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+        let player_idx = game_state.to_act_idx();
+        // Hands have the board in them too.
+        let mut hole_cards = game_state.hands[player_idx];
+        for card in game_state.board.iter() {
+            hole_cards.remove(card);
+        }
+
+        let mut rng = rng();
+        match self.state_store.query::<T>(game_state, hole_cards) {
+            Ok(dist) => {
+                let action = if self.epsilon > 0.0 && rng.random::<f32>() < self.epsilon {
+                    dist.actions()
+                        .get(rng.random_range(0..dist.len().max(1)))
+                        .map(|(action, _)| action.clone())
+                } else {
+                    dist.sample(&mut rng)
+                };
+                if let Some(action) = action {
+                    return action;
+                }
+            }
+            Err(error) => {
+                event!(tracing::Level::DEBUG, ?error, player_idx, "off blueprint");
+            }
+        }
+
+        if game_state.current_round_bet() > game_state.current_round_current_player_bet() {
+            AgentAction::Fold
+        } else {
+            AgentAction::Bet(game_state.current_round_bet())
+        }
+    }
+}
+
+/// Builds a `BlueprintAgent` sharing the same blueprint for every game.
+pub struct BlueprintAgentGenerator<T: ActionGenerator> {
+    agent: BlueprintAgent<T>,
+}
+
+impl<T: ActionGenerator> BlueprintAgentGenerator<T> {
+    pub fn new(agent: BlueprintAgent<T>) -> Self {
+        Self { agent }
+    }
+}
+
+impl<T: ActionGenerator + 'static> AgentGenerator for BlueprintAgentGenerator<T> {
+    fn generate(&self, _game_state: &GameState) -> Box<dyn Agent> {
+        Box::new(self.agent.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::HoldemSimulationBuilder;
+    use crate::arena::agent::CallingAgent;
+    use crate::arena::cfr::replay::LineReplayer;
+    use crate::arena::cfr::{BasicCFRActionGenerator, OutcomeSamplingConfig};
+
+    use super::*;
+
+    fn trained(game_state: &GameState) -> StateStore {
+        let mut state_store = StateStore::new();
+        OutcomeSamplingConfig::default().train::<BasicCFRActionGenerator, _>(
+            &mut state_store,
+            game_state,
+            200,
+            &mut rand::rng(),
+        );
+        state_store
+    }
+
+    #[test]
+    fn test_plays_against_others() {
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let generator = BlueprintAgentGenerator::new(
+            BlueprintAgent::<BasicCFRActionGenerator>::new(trained(&game_state)).with_epsilon(0.1),
+        );
+        for _ in 0..20 {
+            let agents: Vec<Box<dyn Agent>> =
+                vec![generator.generate(&game_state), Box::new(CallingAgent)];
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(game_state.clone())
+                .agents(agents)
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+            assert!(sim.game_state.is_complete());
+            assert_eq!(40.0, sim.game_state.stacks.iter().sum::<f32>());
+        }
+    }
+
+    #[test]
+    fn test_off_blueprint() {
+        // Nothing's trained, so the button folds to the big blind and the
+        // big blind checks once the button's called.
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut replayer = LineReplayer::<BasicCFRActionGenerator>::new(game_state, 0);
+        let replayed = replayer.replay(&[]).unwrap();
+        let mut preflop = replayed.decision.as_ref().unwrap().0.clone();
+        let mut agent = BlueprintAgent::<BasicCFRActionGenerator>::new(StateStore::new());
+        assert_eq!(AgentAction::Fold, agent.act(0, &preflop));
+
+        preflop.do_bet(2.0, false).unwrap();
+        assert_eq!(1, preflop.to_act_idx());
+        assert_eq!(AgentAction::Bet(2.0), agent.act(0, &preflop));
+    }
+
+    #[test]
+    fn test_epsilon_clamped() {
+        let agent = BlueprintAgent::<BasicCFRActionGenerator>::new(StateStore::new());
+        assert_eq!(1.0, agent.clone().with_epsilon(2.0).epsilon());
+        assert_eq!(0.0, agent.with_epsilon(-1.0).epsilon());
+    }
+}
@@ -0,0 +1,239 @@
+use std::ops::ControlFlow;
+
+use super::{CFRState, Checkpoint, StateStore};
+
+/// Hooks into a training run, for progress bars, logging or stopping early
+/// without writing the training loop out again.
+///
+/// Every method does nothing by default, so only the ones needed have to be
+/// written. `()` is the callback that does nothing at all, which is what the
+/// trainers' plain `train` methods use.
+///
+/// Pass one to `OutcomeSamplingConfig::train_with`,
+/// `HeadsUpLimitConfig::train_from_with` or `Checkpoint::train_with`.
+///
+/// # Example
+///
+/// ```
+/// use std::ops::ControlFlow;
+///
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::{
+///     BasicCFRActionGenerator, CFRState, OutcomeSamplingConfig, StateStore, TrainingCallback,
+/// };
+///
+/// /// Stop once the trees have grown past a size.
+/// struct MaxNodes {
+///     added: usize,
+///     limit: usize,
+/// }
+///
+/// impl TrainingCallback for MaxNodes {
+///     fn on_node_expanded(
+///         &mut self,
+///         _player_idx: usize,
+///         _cfr_state: &CFRState,
+///         _node_idx: usize,
+///     ) {
+///         self.added += 1;
+///     }
+///
+///     fn on_iteration(
+///         &mut self,
+///         _iteration: usize,
+///         _state_store: &StateStore,
+///     ) -> ControlFlow<()> {
+///         if self.added >= self.limit {
+///             ControlFlow::Break(())
+///         } else {
+///             ControlFlow::Continue(())
+///         }
+///     }
+/// }
+///
+/// let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+/// let mut state_store = StateStore::new();
+/// let mut callback = MaxNodes {
+///     added: 0,
+///     limit: 10,
+/// };
+/// let trained = OutcomeSamplingConfig::default().train_with::<BasicCFRActionGenerator, _>(
+///     &mut state_store,
+///     &game_state,
+///     1_000,
+///     &mut rand::rng(),
+///     &mut callback,
+/// );
+/// assert!(trained < 1_000);
+/// assert!(callback.added >= 10);
+/// ```
+pub trait TrainingCallback {
+    /// Called after every iteration with how many iterations the run has
+    /// done so far, counting from one. Returning `ControlFlow::Break` stops
+    /// training there.
+    fn on_iteration(&mut self, iteration: usize, state_store: &StateStore) -> ControlFlow<()> {
+        let _ = (iteration, state_store);
+        ControlFlow::Continue(())
+    }
+
+    /// Called for every node an iteration added to `player_idx`'s tree,
+    /// once the iteration is over and before `on_iteration`.
+    fn on_node_expanded(&mut self, player_idx: usize, cfr_state: &CFRState, node_idx: usize) {
+        let _ = (player_idx, cfr_state, node_idx);
+    }
+
+    /// Called by `Checkpoint::train_with` once a run has been recorded in
+    /// the checkpoint, which makes it the place to save it.
+    fn on_checkpoint(&mut self, checkpoint: &Checkpoint) {
+        let _ = checkpoint;
+    }
+}
+
+impl TrainingCallback for () {}
+
+impl<C: TrainingCallback + ?Sized> TrainingCallback for &mut C {
+    fn on_iteration(&mut self, iteration: usize, state_store: &StateStore) -> ControlFlow<()> {
+        (**self).on_iteration(iteration, state_store)
+    }
+
+    fn on_node_expanded(&mut self, player_idx: usize, cfr_state: &CFRState, node_idx: usize) {
+        (**self).on_node_expanded(player_idx, cfr_state, node_idx)
+    }
+
+    fn on_checkpoint(&mut self, checkpoint: &Checkpoint) {
+        (**self).on_checkpoint(checkpoint)
+    }
+}
+
+/// How many nodes each tree in `state_store` has. Nodes are only ever
+/// appended, so any past these were added since.
+pub(crate) fn tree_sizes(state_store: &StateStore) -> Vec<usize> {
+    (0..state_store.len())
+        .map(|player_idx| {
+            state_store.get_state(player_idx).map_or(0, |cfr_state| {
+                cfr_state.internal_state().borrow().nodes.len()
+            })
+        })
+        .collect()
+}
+
+/// Tell `callback` about every node added since the trees were `sizes`
+/// big, then about the iteration, and update `sizes`.
+pub(crate) fn finish_iteration(
+    callback: &mut impl TrainingCallback,
+    state_store: &StateStore,
+    sizes: &mut Vec<usize>,
+    iteration: usize,
+) -> ControlFlow<()> {
+    let new_sizes = tree_sizes(state_store);
+    for (player_idx, &size) in new_sizes.iter().enumerate() {
+        let start = sizes.get(player_idx).copied().unwrap_or(0);
+        if start == size {
+            continue;
+        }
+        let cfr_state = state_store.get_state(player_idx).unwrap();
+        for node_idx in start..size {
+            callback.on_node_expanded(player_idx, &cfr_state, node_idx);
+        }
+    }
+    *sizes = new_sizes;
+    callback.on_iteration(iteration, state_store)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::GameState;
+    use crate::arena::cfr::{
+        BasicCFRActionGenerator, OutcomeSamplingConfig, RegretUpdate, TrainingConfig,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        iterations: Vec<usize>,
+        expanded: Vec<(usize, usize)>,
+        checkpoints: Vec<usize>,
+        stop_at: Option<usize>,
+    }
+
+    impl TrainingCallback for Recorder {
+        fn on_iteration(&mut self, iteration: usize, _state_store: &StateStore) -> ControlFlow<()> {
+            self.iterations.push(iteration);
+            if self.stop_at == Some(iteration) {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+
+        fn on_node_expanded(&mut self, player_idx: usize, cfr_state: &CFRState, node_idx: usize) {
+            assert!(cfr_state.get(node_idx).is_some());
+            self.expanded.push((player_idx, node_idx));
+        }
+
+        fn on_checkpoint(&mut self, checkpoint: &Checkpoint) {
+            self.checkpoints.push(checkpoint.iterations);
+        }
+    }
+
+    #[test]
+    fn test_every_node_expanded() {
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        let mut recorder = Recorder::default();
+        let trained = OutcomeSamplingConfig::default().train_with::<BasicCFRActionGenerator, _>(
+            &mut state_store,
+            &game_state,
+            20,
+            &mut rand::rng(),
+            &mut recorder,
+        );
+        assert_eq!(20, trained);
+        assert_eq!((1..=20).collect::<Vec<_>>(), recorder.iterations);
+
+        // Everything but the roots was added while training.
+        let sizes = tree_sizes(&state_store);
+        assert_eq!(sizes.iter().sum::<usize>() - 2, recorder.expanded.len());
+        for (player_idx, size) in sizes.into_iter().enumerate() {
+            let mut expanded: Vec<_> = recorder
+                .expanded
+                .iter()
+                .filter(|(p, _)| *p == player_idx)
+                .map(|(_, node_idx)| *node_idx)
+                .collect();
+            expanded.sort();
+            assert_eq!((1..size).collect::<Vec<_>>(), expanded);
+        }
+    }
+
+    #[test]
+    fn test_stop_early() {
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let config = TrainingConfig::new::<BasicCFRActionGenerator>(
+            "outcome_sampling",
+            RegretUpdate::Vanilla,
+            game_state.clone(),
+        );
+        let mut checkpoint = Checkpoint::new(config);
+        let mut recorder = Recorder {
+            stop_at: Some(3),
+            ..Recorder::default()
+        };
+        let trained =
+            checkpoint.train_with(10, 1, &mut recorder, |store, iterations, rng, callback| {
+                OutcomeSamplingConfig::default().train_with::<BasicCFRActionGenerator, _>(
+                    store,
+                    &game_state,
+                    iterations,
+                    rng,
+                    callback,
+                )
+            });
+        assert_eq!(3, trained);
+        assert_eq!(vec![1, 2, 3], recorder.iterations);
+        assert_eq!(vec![3], recorder.checkpoints);
+        assert_eq!(3, checkpoint.iterations);
+        assert_eq!(3, checkpoint.convergence.last().unwrap().iteration);
+    }
+}
@@ -0,0 +1,198 @@
+use rand::Rng;
+
+use crate::arena::GameState;
+use crate::core::{Card, CardSet};
+
+use super::ActionGenerator;
+
+/// The cards a chance node can deal, given every card already known to be
+/// out of the deck.
+///
+/// A hand's hole cards and board are dealt without replacement, so each
+/// card still in the deck is equally likely and the dead ones can't come
+/// at all. Sampling from 52 cards and throwing away the dead ones gets the
+/// same cards, but it hides how likely each one was, and that's what
+/// importance weighting a sampled chance outcome has to divide by.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::ChanceSampler;
+///
+/// let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+/// let mut sampler = ChanceSampler::from_game_state(&game_state);
+/// assert_eq!(52, sampler.len());
+///
+/// let (card, probability) = sampler.sample(&mut rand::rng()).unwrap();
+/// assert_eq!(1.0 / 52.0, probability);
+/// assert_eq!(0.0, sampler.probability(card));
+/// assert_eq!(
+///     1.0 / 51.0,
+///     sampler.probability(sampler.remaining().iter().next().unwrap())
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChanceSampler {
+    remaining: CardSet,
+}
+
+impl ChanceSampler {
+    /// A sampler dealing every card but the `dead` ones.
+    pub fn new(dead: CardSet) -> Self {
+        Self { remaining: !dead }
+    }
+
+    /// A sampler dealing every card that isn't in a hand or on the board of
+    /// `game_state`.
+    pub fn from_game_state(game_state: &GameState) -> Self {
+        Self::new(game_state.dead_cards())
+    }
+
+    /// The cards that can still be dealt.
+    pub fn remaining(&self) -> CardSet {
+        self.remaining
+    }
+
+    /// How many cards can still be dealt.
+    pub fn len(&self) -> usize {
+        self.remaining.count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    /// Take `card` out, for a card that was dealt some other way.
+    pub fn remove(&mut self, card: Card) {
+        self.remaining.remove(card);
+    }
+
+    /// How likely `card` is to be the next one dealt, zero if it's dead.
+    pub fn probability(&self, card: Card) -> f32 {
+        if self.remaining.contains(card) {
+            1.0 / self.len() as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Deal a card and take it out, along with how likely it was to be
+    /// dealt. `None` once every card is gone.
+    pub fn sample<R: Rng>(&mut self, rng: &mut R) -> Option<(Card, f32)> {
+        let card = self.remaining.sample_one(rng)?;
+        let probability = self.probability(card);
+        self.remaining.remove(card);
+        Some((card, probability))
+    }
+
+    /// How likely each child of a chance node is, for the node where
+    /// `action_generator` buckets the next card given the `known` cards.
+    /// Cards sharing a child add up, so a child is only as likely as the
+    /// live cards in it. Children are in index order, and ones no card
+    /// leads to are left out.
+    pub fn child_probabilities<T: ActionGenerator>(
+        &self,
+        action_generator: &T,
+        game_state: &GameState,
+        known: CardSet,
+    ) -> Vec<(usize, f32)> {
+        let mut counts: Vec<(usize, usize)> = Vec::new();
+        for card in self.remaining.iter() {
+            let child_idx = action_generator.card_to_idx(game_state, known, card);
+            match counts.binary_search_by_key(&child_idx, |(idx, _)| *idx) {
+                Ok(pos) => counts[pos].1 += 1,
+                Err(pos) => counts.insert(pos, (child_idx, 1)),
+            }
+        }
+        let total = self.len() as f32;
+        counts
+            .into_iter()
+            .map(|(child_idx, count)| (child_idx, count as f32 / total))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::cfr::{
+        BasicCFRActionGenerator, CFRHistorian, CFRState, LimitCFRActionGenerator, StateStore,
+        TraversalState, ValueOnlyAbstraction,
+    };
+    use crate::core::{Hand, Suit, Value};
+
+    use super::*;
+
+    #[test]
+    fn test_dead_cards() {
+        let mut game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        game_state.hands_mut()[0] = Hand::new_from_str("AsAh").unwrap();
+        game_state.hands_mut()[1] = Hand::new_from_str("KsKh").unwrap();
+        let mut sampler = ChanceSampler::from_game_state(&game_state);
+        assert_eq!(48, sampler.len());
+        assert_eq!(0.0, sampler.probability(Card::new(Value::Ace, Suit::Spade)));
+        assert_eq!(
+            1.0 / 48.0,
+            sampler.probability(Card::new(Value::Ace, Suit::Club))
+        );
+
+        // Dealing everything gets each live card once.
+        let mut rng = rand::rng();
+        let mut dealt = CardSet::new();
+        let mut expected = 48;
+        while let Some((card, probability)) = sampler.sample(&mut rng) {
+            assert_eq!(1.0 / expected as f32, probability);
+            assert!(!game_state.dead_cards().contains(card));
+            dealt.insert(card);
+            expected -= 1;
+        }
+        assert_eq!(0, expected);
+        assert_eq!(!game_state.dead_cards(), dealt);
+        assert!(sampler.is_empty());
+    }
+
+    #[test]
+    fn test_child_probabilities() {
+        let mut game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        game_state.hands_mut()[0] = Hand::new_from_str("AsAh").unwrap();
+        game_state.hands_mut()[1] = Hand::new_from_str("Ks2d").unwrap();
+        let sampler = ChanceSampler::from_game_state(&game_state);
+        let generator = LimitCFRActionGenerator::<ValueOnlyAbstraction>::new(
+            CFRState::new(game_state.clone()),
+            TraversalState::new_root(0),
+        );
+        let known = CardSet::from(game_state.hands[0]);
+        let children = sampler.child_probabilities(&generator, &game_state, known);
+
+        // One child per value, weighted by how many of it are left.
+        assert_eq!(13, children.len());
+        let of = |value| {
+            let idx = generator.card_to_idx(&game_state, known, Card::new(value, Suit::Club));
+            children.iter().find(|(i, _)| *i == idx).unwrap().1
+        };
+        assert_eq!(2.0 / 48.0, of(Value::Ace));
+        assert_eq!(3.0 / 48.0, of(Value::King));
+        assert_eq!(4.0 / 48.0, of(Value::Seven));
+        let total: f32 = children.iter().map(|(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_historian_chance_reach() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        let (cfr_state, traversal_state) = state_store.new_state(game_state.clone(), 0);
+        let mut historian =
+            CFRHistorian::<BasicCFRActionGenerator>::new(traversal_state, cfr_state);
+
+        // The second hole card can't be the first.
+        historian
+            .record_card(&game_state, Card::new(Value::Ace, Suit::Spade))
+            .unwrap();
+        assert_eq!(1.0 / 52.0, historian.chance_reach);
+        historian
+            .record_card(&game_state, Card::new(Value::Ace, Suit::Heart))
+            .unwrap();
+        assert_eq!(1.0 / 52.0 * (1.0 / 51.0), historian.chance_reach);
+    }
+}
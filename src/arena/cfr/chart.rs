@@ -0,0 +1,584 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::arena::GameState;
+use crate::arena::errors::ExportError;
+use crate::core::{Card, CardSet, Hand};
+
+use super::replay::LineReplayer;
+use super::{ActionGenerator, CFRState, NodeData, StateStore};
+
+/// One action at one spot of a `StrategyChart`. These are the columns of
+/// the CSV and the fields of each object in the JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartRow {
+    /// The seat acting.
+    pub player_idx: usize,
+    /// Their position this hand, such as `BTN`.
+    pub position: String,
+    /// The street, such as `Preflop`.
+    pub street: String,
+    /// The betting so far as an `ActionKey` with exact sizes, such as
+    /// `b2.5c` for an open to two and a half big blinds that was called.
+    /// Empty for the first decision of the hand.
+    pub line: String,
+    /// The board so far, such as `AhKd2c`. Where a card abstraction puts
+    /// several cards in one branch, the first of them stands for the rest.
+    pub board: String,
+    /// The hole card class, such as `AKs`, `AKo` or `QQ`. Where a card
+    /// abstraction puts hands of several classes in one branch they're all
+    /// listed, separated by commas.
+    pub hand: String,
+    /// The action as an `ActionKey` token, such as `f`, `x`, `c`, `a` or
+    /// `b7.5` for a bet or raise to seven and a half big blinds.
+    pub action: String,
+    /// How often the average strategy takes the action, between 0 and 1.
+    pub probability: f32,
+}
+
+/// The format to write a `StrategyChart` in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartFormat {
+    /// An array of `ChartRow` objects.
+    Json,
+    /// A header of the `ChartRow` field names and a line per row.
+    Csv,
+}
+
+impl FromStr for ChartFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ChartFormat::Json),
+            "csv" => Ok(ChartFormat::Csv),
+            _ => Err(ExportError::InvalidExportFormat(s.to_string())),
+        }
+    }
+}
+
+/// The average strategy of trained trees as a flat table, for loading into
+/// charts and other tools without walking the tree.
+///
+/// There's a row for every action at every spot, where a spot is the
+/// betting line, board and hole card class of a decision. Decisions in the
+/// same spot, such as `AsKs` and `AhKh` with the same betting, are averaged
+/// weighted by how often they were reached. Only the actions the action
+/// generator offers there are listed, and their probabilities add up to
+/// one.
+///
+/// Working out the betting plays the hand out along every line in the
+/// tree, and naming hands goes through every hole card pair, so this is
+/// meant for once training is done.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::{
+///     BasicCFRActionGenerator, ChartFormat, OutcomeSamplingConfig, StateStore, StrategyChart,
+/// };
+///
+/// let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+/// let mut state_store = StateStore::new();
+/// OutcomeSamplingConfig::default().train::<BasicCFRActionGenerator, _>(
+///     &mut state_store,
+///     &game_state,
+///     50,
+///     &mut rand::rng(),
+/// );
+///
+/// let chart = StrategyChart::from_state_store::<BasicCFRActionGenerator>(&state_store);
+/// let first = &chart.rows()[0];
+/// println!(
+///     "{} {}: {} {} {:.0}%",
+///     first.position,
+///     first.line,
+///     first.hand,
+///     first.action,
+///     first.probability * 100.0
+/// );
+///
+/// let mut csv = Vec::new();
+/// chart.write(&mut csv, ChartFormat::Csv).unwrap();
+/// assert!(csv.starts_with(b"player_idx,position,street,line,board,hand,action,probability\n"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StrategyChart {
+    rows: Vec<ChartRow>,
+}
+
+impl StrategyChart {
+    /// The chart of every player in `state_store`, each from the tree that
+    /// player trained. `T` is the action generator the trees were built
+    /// with.
+    pub fn from_state_store<T: ActionGenerator + 'static>(state_store: &StateStore) -> Self {
+        let mut rows = Vec::new();
+        for player_idx in 0..state_store.len() {
+            if let Some(cfr_state) = state_store.get_state(player_idx) {
+                rows.extend(Self::from_cfr_state::<T>(&cfr_state, player_idx).rows);
+            }
+        }
+        Self { rows }
+    }
+
+    /// The chart of `player_idx` from their tree.
+    pub fn from_cfr_state<T: ActionGenerator + 'static>(
+        cfr_state: &CFRState,
+        player_idx: usize,
+    ) -> Self {
+        let mut charter = Charter::<T>::new(cfr_state.starting_game_state(), player_idx);
+        let internal = cfr_state.internal_state().borrow();
+        let mut stack = vec![(0, 1.0, Vec::new(), Vec::new())];
+        while let Some((node_idx, weight, line, chance)) = stack.pop() {
+            let Some(node) = internal.nodes.get(node_idx) else {
+                continue;
+            };
+            if let NodeData::Player(player_data) = &*node.data
+                && player_data.player_idx == player_idx
+                && let Some(matcher) = &player_data.regret_matcher
+            {
+                charter.add(&line, &chance, weight, &matcher.best_weight());
+            }
+            for (child_idx, child_node_idx) in node.iter_children() {
+                let mut line = line.clone();
+                let mut chance = chance.clone();
+                match &*node.data {
+                    NodeData::Player(_) => line.push(child_idx),
+                    NodeData::Chance => chance.push(child_idx),
+                    _ => {}
+                }
+                let count = node.get_count(child_idx).max(1) as f32;
+                stack.push((child_node_idx, count, line, chance));
+            }
+        }
+        Self {
+            rows: charter.rows(),
+        }
+    }
+
+    pub fn rows(&self) -> &[ChartRow] {
+        &self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Write the chart to `writer` in `format`.
+    pub fn write(&self, writer: impl Write, format: ChartFormat) -> Result<(), ExportError> {
+        match format {
+            ChartFormat::Json => Ok(serde_json::to_writer_pretty(writer, self)?),
+            ChartFormat::Csv => self.write_csv(writer),
+        }
+    }
+
+    /// Write the chart to a file at `path` in `format`.
+    pub fn save_to_file(&self, path: &Path, format: ChartFormat) -> Result<(), ExportError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer, format)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_csv(&self, mut writer: impl Write) -> Result<(), ExportError> {
+        writeln!(
+            writer,
+            "player_idx,position,street,line,board,hand,action,probability"
+        )?;
+        for row in &self.rows {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{}",
+                row.player_idx,
+                csv_field(&row.position),
+                csv_field(&row.street),
+                csv_field(&row.line),
+                csv_field(&row.board),
+                csv_field(&row.hand),
+                csv_field(&row.action),
+                row.probability
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Quote a field that has anything CSV gives a meaning to.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `QQ`, `AKs` or `AKo`.
+fn hand_class(first: Card, second: Card) -> String {
+    let (high, low) = if first.value >= second.value {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    let mut class = format!("{}{}", high.value.to_char(), low.value.to_char());
+    if high.value != low.value {
+        class.push(if high.suit == low.suit { 's' } else { 'o' });
+    }
+    class
+}
+
+/// Every hole card pair in a branch of the first two chance nodes: their
+/// classes, and the first pair to stand for the rest when labelling the
+/// board.
+#[derive(Default)]
+struct HoleBranch {
+    classes: BTreeSet<String>,
+    cards: Option<[Card; 2]>,
+}
+
+/// A spot's decisions so far, summed weighted by how often each was
+/// reached.
+struct Spot {
+    position: String,
+    street: String,
+    weight: f32,
+    /// The action index, its token and its weighted probability.
+    actions: Vec<(usize, String, f32)>,
+}
+
+/// Builds the rows of one player's chart.
+struct Charter<T: ActionGenerator> {
+    game_state: GameState,
+    player_idx: usize,
+    replayer: LineReplayer<T>,
+    /// Whether the tree deals the player's hole cards, rather than starting
+    /// with them known.
+    deals_hole_cards: bool,
+    hole_branches: HashMap<(usize, usize), HoleBranch>,
+    boards: HashMap<Vec<usize>, String>,
+    spots: BTreeMap<(String, String, String), Spot>,
+}
+
+impl<T: ActionGenerator> Charter<T> {
+    fn new(game_state: GameState, player_idx: usize) -> Self {
+        let mut charter = Self {
+            deals_hole_cards: game_state.hands[player_idx].count() == 0,
+            replayer: LineReplayer::new(game_state.clone(), player_idx),
+            game_state,
+            player_idx,
+            hole_branches: HashMap::new(),
+            boards: HashMap::new(),
+            spots: BTreeMap::new(),
+        };
+        if charter.deals_hole_cards {
+            charter.hole_branches = charter.find_hole_branches();
+        }
+        charter
+    }
+
+    /// Go through every hole card pair the way the historian deals them to
+    /// find the branch each lands in.
+    fn find_hole_branches(&self) -> HashMap<(usize, usize), HoleBranch> {
+        let mut branches: HashMap<(usize, usize), HoleBranch> = HashMap::new();
+        let cards: Vec<Card> = (0..52).map(Card::from).collect();
+        for &first in &cards {
+            let first_idx =
+                self.replayer
+                    .generator()
+                    .card_to_idx(&self.game_state, CardSet::new(), first);
+            let mut game_state = self.game_state.clone();
+            game_state.hands_mut()[self.player_idx].insert(first);
+            let known = CardSet::from(game_state.hands[self.player_idx]);
+            for &second in cards.iter().filter(|card| **card != first) {
+                let second_idx = self
+                    .replayer
+                    .generator()
+                    .card_to_idx(&game_state, known, second);
+                let branch = branches.entry((first_idx, second_idx)).or_default();
+                branch.classes.insert(hand_class(first, second));
+                branch.cards.get_or_insert([first, second]);
+            }
+        }
+        branches
+    }
+
+    /// The hole cards and board that `chance` leads to, as labels, with
+    /// cards standing for their branch.
+    fn cards(&mut self, chance: &[usize]) -> Option<(String, String)> {
+        let starting = CardSet::from(self.game_state.hands[self.player_idx]);
+        let starting_board: Vec<Card> = self.game_state.board.iter().copied().collect();
+        let (hole, hand, dealt) = if self.deals_hole_cards {
+            let branch = self
+                .hole_branches
+                .get(&(*chance.first()?, *chance.get(1)?))?;
+            let classes: Vec<&str> = branch.classes.iter().map(String::as_str).collect();
+            let hole: CardSet = branch.cards?.into_iter().collect();
+            (hole, classes.join(","), &chance[2..])
+        } else {
+            let mut hole = starting;
+            for card in &starting_board {
+                hole.remove(*card);
+            }
+            let hole_cards: Vec<Card> = hole.into_iter().collect();
+            let hand = match hole_cards[..] {
+                [first, second] => hand_class(first, second),
+                _ => hole_cards.iter().map(Card::to_string).collect(),
+            };
+            (hole, hand, chance)
+        };
+        if !self.boards.contains_key(chance) {
+            let board = self.find_board(hole, &starting_board, dealt)?;
+            self.boards.insert(chance.to_vec(), board);
+        }
+        Some((hand, self.boards[chance].clone()))
+    }
+
+    /// The first card in each branch of the board the way the historian
+    /// deals them, given the hole cards standing for the player's.
+    fn find_board(&self, hole: CardSet, starting: &[Card], dealt: &[usize]) -> Option<String> {
+        let mut board = starting.to_vec();
+        for &card_idx in dealt {
+            // Each street is dealt onto the board as it was before it, with
+            // the flop's earlier cards only known to the historian.
+            let before_street: &[Card] = if board.len() < 3 { &[] } else { &board };
+            let mut hand = hole;
+            hand.extend(before_street.iter().copied());
+            let mut game_state = self.game_state.clone();
+            game_state.hands_mut()[self.player_idx] = Hand::from(hand);
+            *game_state.board_mut() = before_street.iter().copied().collect();
+
+            let mut known = hole;
+            known.extend(board.iter().copied());
+            let card = (0..52).map(Card::from).find(|card| {
+                !known.contains(*card)
+                    && self
+                        .replayer
+                        .generator()
+                        .card_to_idx(&game_state, known, *card)
+                        == card_idx
+            })?;
+            board.push(card);
+        }
+        Some(board.iter().map(Card::to_string).collect())
+    }
+
+    /// Add a decision reached by `line` and `chance` whose average strategy
+    /// is `weights`, indexed by action.
+    fn add(&mut self, line: &[usize], chance: &[usize], weight: f32, weights: &[f32]) {
+        let Some(replayed) = self.replayer.replay(line) else {
+            return;
+        };
+        let Some((game_state, possible)) = &replayed.decision else {
+            return;
+        };
+        let Some((hand, board)) = self.cards(chance) else {
+            return;
+        };
+        let total: f32 = possible
+            .iter()
+            .map(|(idx, _)| weights.get(*idx).copied().unwrap_or(0.0))
+            .sum();
+        let line_key = replayed.key.to_string();
+
+        let mut actions = Vec::with_capacity(possible.len());
+        for (idx, _) in possible {
+            let mut child = line.to_vec();
+            child.push(*idx);
+            let token = self
+                .replayer
+                .replay(&child)
+                .and_then(|child| {
+                    child
+                        .key
+                        .as_str()
+                        .strip_prefix(line_key.as_str())
+                        .map(str::to_string)
+                })
+                .unwrap_or_else(|| idx.to_string());
+            let probability = if total > 0.0 {
+                weights.get(*idx).copied().unwrap_or(0.0) / total
+            } else {
+                1.0 / possible.len() as f32
+            };
+            actions.push((*idx, token, probability));
+        }
+
+        let spot = self
+            .spots
+            .entry((line_key, board, hand))
+            .or_insert_with(|| Spot {
+                position: game_state
+                    .position_of(self.player_idx)
+                    .map(|position| position.to_string())
+                    .unwrap_or_default(),
+                street: game_state.round.to_string(),
+                weight: 0.0,
+                actions: actions
+                    .iter()
+                    .map(|(idx, token, _)| (*idx, token.clone(), 0.0))
+                    .collect(),
+            });
+        spot.weight += weight;
+        for (idx, _, probability) in actions {
+            if let Some(action) = spot.actions.iter_mut().find(|action| action.0 == idx) {
+                action.2 += weight * probability;
+            }
+        }
+    }
+
+    fn rows(self) -> Vec<ChartRow> {
+        let mut rows = Vec::new();
+        for ((line, board, hand), spot) in self.spots {
+            for (_, action, weighted) in spot.actions {
+                rows.push(ChartRow {
+                    player_idx: self.player_idx,
+                    position: spot.position.clone(),
+                    street: spot.street.clone(),
+                    line: line.clone(),
+                    board: board.clone(),
+                    hand: hand.clone(),
+                    action,
+                    probability: weighted / spot.weight,
+                });
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use crate::arena::cfr::{
+        BasicCFRActionGenerator, OutcomeSamplingConfig, PlayerData, RegretMatcher,
+    };
+
+    use super::*;
+
+    /// A tree where the button was dealt `AsKs` and `AhKh` and raised one
+    /// of them more often.
+    fn tree() -> CFRState {
+        let game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut cfr_state = CFRState::new(game_state);
+        let hole = cfr_state.add(0, 0, NodeData::Chance);
+        for (first, second, rewards) in [
+            ("As", "Ks", array![0.0, 0.0, 8.0]),
+            ("Ah", "Kh", array![0.0, 4.0, 4.0]),
+        ] {
+            let first = Card::try_from(first).unwrap();
+            let second = Card::try_from(second).unwrap();
+            let dealt = cfr_state.add(hole, u8::from(first) as usize, NodeData::Chance);
+            let mut matcher = RegretMatcher::new(3).unwrap();
+            matcher.update_regret(rewards.view()).unwrap();
+            cfr_state.add(
+                dealt,
+                u8::from(second) as usize,
+                NodeData::Player(PlayerData {
+                    regret_matcher: Some(Box::new(matcher)),
+                    player_idx: 0,
+                }),
+            );
+        }
+        cfr_state
+    }
+
+    #[test]
+    fn test_rows() {
+        let cfr_state = tree();
+        let chart = StrategyChart::from_cfr_state::<BasicCFRActionGenerator>(&cfr_state, 0);
+        // One spot, AKs on the button before anything's happened, with a
+        // row per action.
+        assert_eq!(3, chart.len());
+        for row in chart.rows() {
+            assert_eq!("BTN", row.position);
+            assert_eq!("Preflop", row.street);
+            assert_eq!("", row.line);
+            assert_eq!("", row.board);
+            assert_eq!("AKs", row.hand);
+        }
+        let actions: Vec<(&str, f32)> = chart
+            .rows()
+            .iter()
+            .map(|row| (row.action.as_str(), row.probability))
+            .collect();
+        // The two hands averaged, one always going all in and the other
+        // calling or going all in half the time.
+        assert_eq!(vec![("f", 0.0), ("c", 0.25), ("a", 0.75)], actions);
+
+        // Nothing for the other player.
+        assert!(StrategyChart::from_cfr_state::<BasicCFRActionGenerator>(&cfr_state, 1).is_empty());
+    }
+
+    #[test]
+    fn test_trained() {
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        OutcomeSamplingConfig::default().train::<BasicCFRActionGenerator, _>(
+            &mut state_store,
+            &game_state,
+            200,
+            &mut rand::rng(),
+        );
+        let chart = StrategyChart::from_state_store::<BasicCFRActionGenerator>(&state_store);
+
+        let mut spots: BTreeMap<_, f32> = BTreeMap::new();
+        for row in chart.rows() {
+            let expected_board = match row.street.as_str() {
+                "Preflop" => 0,
+                "Flop" => 6,
+                "Turn" => 8,
+                _ => 10,
+            };
+            assert_eq!(expected_board, row.board.len(), "{row:?}");
+            assert!((2..=3).contains(&row.hand.len()), "{row:?}");
+            *spots
+                .entry((row.player_idx, &row.line, &row.board, &row.hand))
+                .or_default() += row.probability;
+        }
+        assert!(spots.values().all(|total| (total - 1.0).abs() < 1e-4));
+        assert!(chart.rows().iter().any(|row| row.street != "Preflop"));
+    }
+
+    #[test]
+    fn test_write() {
+        let chart = StrategyChart {
+            rows: vec![ChartRow {
+                player_idx: 1,
+                position: "BB".to_string(),
+                street: "Flop".to_string(),
+                line: "b2.5c".to_string(),
+                board: "AhKd2c".to_string(),
+                hand: "AKo,AKs".to_string(),
+                action: "x".to_string(),
+                probability: 0.5,
+            }],
+        };
+
+        let mut csv = Vec::new();
+        chart.write(&mut csv, ChartFormat::Csv).unwrap();
+        assert_eq!(
+            "player_idx,position,street,line,board,hand,action,probability\n\
+             1,BB,Flop,b2.5c,AhKd2c,\"AKo,AKs\",x,0.5\n",
+            String::from_utf8(csv).unwrap()
+        );
+
+        let mut json = Vec::new();
+        chart.write(&mut json, ChartFormat::Json).unwrap();
+        let loaded: StrategyChart = serde_json::from_slice(&json).unwrap();
+        assert_eq!(chart, loaded);
+
+        assert_eq!(ChartFormat::Csv, "CSV".parse().unwrap());
+        assert!("xml".parse::<ChartFormat>().is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tempfile::NamedTempFile;
+
+use crate::arena::GameState;
+use crate::arena::errors::{CheckpointError, VersionedFileError};
+use crate::arena::versioned::{
+    SaveFormat, SaveOptions, Versioned, load_versioned, save_versioned_as,
+};
+use crate::core::{CrateRng, rng, with_seed};
+
+use super::{ConvergenceTracker, RegretUpdate, StateStore, TrainingCallback};
+
+/// What a store is being trained with. A checkpoint only carries on
+/// training with the same config, since mixing trainers, regret updates or
+/// abstractions in one tree gives a strategy that's none of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainingConfig {
+    /// A name for the trainer, such as `"outcome_sampling"`.
+    pub trainer: String,
+    /// The CFR variant.
+    pub regret_update: RegretUpdate,
+    /// The action generator or abstraction the trees are built with.
+    pub abstraction: String,
+    /// Where every hand is played from.
+    pub game_state: GameState,
+}
+
+impl TrainingConfig {
+    /// A config for `trainer` building its trees with `A`, which is
+    /// recorded by its type name.
+    pub fn new<A: ?Sized>(
+        trainer: &str,
+        regret_update: RegretUpdate,
+        game_state: GameState,
+    ) -> Self {
+        Self {
+            trainer: trainer.to_string(),
+            regret_update,
+            abstraction: std::any::type_name::<A>().to_string(),
+            game_state,
+        }
+    }
+
+    /// Check that training with `self` can carry on from a store trained
+    /// with `trained`.
+    pub fn check_compatible(&self, trained: &TrainingConfig) -> Result<(), CheckpointError> {
+        let mismatch = |field: &'static str, expected: String, found: String| {
+            Err(CheckpointError::Mismatch {
+                field,
+                expected,
+                found,
+            })
+        };
+        if self.trainer != trained.trainer {
+            return mismatch("trainer", self.trainer.clone(), trained.trainer.clone());
+        }
+        if self.regret_update != trained.regret_update {
+            return mismatch(
+                "regret_update",
+                format!("{:?}", self.regret_update),
+                format!("{:?}", trained.regret_update),
+            );
+        }
+        if self.abstraction != trained.abstraction {
+            return mismatch(
+                "abstraction",
+                self.abstraction.clone(),
+                trained.abstraction.clone(),
+            );
+        }
+        if self.game_state != trained.game_state {
+            return mismatch(
+                "game_state",
+                format!("{:?}", self.game_state),
+                format!("{:?}", trained.game_state),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A `StateStore` saved along with how it was trained.
+///
+/// Training through `train` seeds the crate's rng, so with the seeds
+/// recorded here a run can be repeated exactly. It also records how far
+/// training has converged after each run in `convergence`.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::{
+///     BasicCFRActionGenerator, Checkpoint, OutcomeSamplingConfig, TrainingConfig, resume_training,
+/// };
+///
+/// let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+/// let sampling = OutcomeSamplingConfig::default();
+/// let config = TrainingConfig::new::<BasicCFRActionGenerator>(
+///     "outcome_sampling",
+///     sampling.regret_update,
+///     game_state.clone(),
+/// );
+/// let train = |store: &mut _, iterations, rng: &mut _| {
+///     sampling.train::<BasicCFRActionGenerator, _>(store, &game_state, iterations, rng)
+/// };
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("checkpoint");
+/// let mut checkpoint = Checkpoint::new(config.clone());
+/// checkpoint.train(10, 1, train);
+/// checkpoint.save_to_file(&path).unwrap();
+///
+/// let checkpoint = resume_training(&path, &config, 10, 2, train).unwrap();
+/// assert_eq!(20, checkpoint.iterations);
+/// assert_eq!(vec![1, 2], checkpoint.seeds);
+/// assert_eq!(20, checkpoint.convergence.last().unwrap().iteration);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub config: TrainingConfig,
+    /// How many iterations the store has been trained for in total.
+    pub iterations: usize,
+    /// The seed each call to `train` used, in order.
+    pub seeds: Vec<u64>,
+    pub state_store: StateStore,
+    /// How training converged, measured after every call to `train`.
+    pub convergence: ConvergenceTracker,
+}
+
+impl Versioned for Checkpoint {
+    const KIND: &'static str = "checkpoint";
+    // Version 2 added `convergence`, which starts out empty for older files.
+    const VERSION: u32 = 2;
+
+    fn migrate(version: u32, mut data: Value) -> Result<Value, VersionedFileError> {
+        if version == 1 {
+            data.as_object_mut()
+                .ok_or_else(|| VersionedFileError::Migration {
+                    from: version,
+                    reason: "expected an object".to_string(),
+                })?
+                .insert("convergence".to_string(), json!({ "points": [] }));
+        }
+        Ok(data)
+    }
+
+    fn validate(&self) -> Result<(), VersionedFileError> {
+        self.state_store.validate()
+    }
+}
+
+impl Checkpoint {
+    /// A checkpoint with an untrained store.
+    pub fn new(config: TrainingConfig) -> Self {
+        Self {
+            config,
+            iterations: 0,
+            seeds: Vec::new(),
+            state_store: StateStore::new(),
+            convergence: ConvergenceTracker::new(),
+        }
+    }
+
+    /// Train the store for `iterations` more iterations with `train`, which
+    /// is given the store, the number of iterations and an rng. The crate's
+    /// rng is seeded from `seed` while it runs. A convergence point is
+    /// recorded once it's done.
+    pub fn train(
+        &mut self,
+        iterations: usize,
+        seed: u64,
+        train: impl FnOnce(&mut StateStore, usize, &mut CrateRng),
+    ) {
+        self.train_with(
+            iterations,
+            seed,
+            &mut (),
+            |state_store, iterations, rng, _| {
+                train(state_store, iterations, rng);
+                iterations
+            },
+        );
+    }
+
+    /// Train the same as `train`, with `train` also given `callback` to
+    /// pass on to the trainer and returning how many iterations it trained,
+    /// which is what's recorded and returned. `callback.on_checkpoint` is
+    /// called once the run has been recorded.
+    pub fn train_with<C: TrainingCallback>(
+        &mut self,
+        iterations: usize,
+        seed: u64,
+        callback: &mut C,
+        train: impl FnOnce(&mut StateStore, usize, &mut CrateRng, &mut C) -> usize,
+    ) -> usize {
+        let trained = with_seed(seed, || {
+            train(&mut self.state_store, iterations, &mut rng(), callback)
+        });
+        self.iterations += trained;
+        self.seeds.push(seed);
+        self.convergence.record(self.iterations, &self.state_store);
+        callback.on_checkpoint(self);
+        trained
+    }
+
+    /// Save the checkpoint as a versioned binary file.
+    pub fn save_to_file(&self, path: &Path) -> Result<(), CheckpointError> {
+        self.save_to_file_as(path, SaveFormat::Binary)
+    }
+
+    /// Save the checkpoint in any format, see `StateStore::save_to_file_as`.
+    ///
+    /// The file is written next to `path` and moved over it once it's
+    /// complete, so a crash while saving leaves the last checkpoint intact.
+    pub fn save_to_file_as(
+        &self,
+        path: &Path,
+        options: impl Into<SaveOptions>,
+    ) -> Result<(), CheckpointError> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let file = NamedTempFile::new_in(dir).map_err(VersionedFileError::from)?;
+        save_versioned_as(file.path(), self, options)?;
+        file.persist(path)
+            .map_err(|e| VersionedFileError::from(e.error))?;
+        Ok(())
+    }
+
+    /// Load a checkpoint saved in any format.
+    pub fn load_from_file(path: &Path) -> Result<Self, CheckpointError> {
+        Ok(load_versioned(path)?)
+    }
+}
+
+/// Carry on training the checkpoint at `path` with `config` for
+/// `iterations` more iterations, then save it back over the original.
+///
+/// Fails without training if the checkpoint was trained with a different
+/// config.
+pub fn resume_training(
+    path: &Path,
+    config: &TrainingConfig,
+    iterations: usize,
+    seed: u64,
+    train: impl FnOnce(&mut StateStore, usize, &mut CrateRng),
+) -> Result<Checkpoint, CheckpointError> {
+    let mut checkpoint = Checkpoint::load_from_file(path)?;
+    config.check_compatible(&checkpoint.config)?;
+    checkpoint.train(iterations, seed, train);
+    checkpoint.save_to_file(path)?;
+    Ok(checkpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::cfr::{BasicCFRActionGenerator, OutcomeSamplingConfig, StrategyProfile};
+
+    use super::*;
+
+    fn config(regret_update: RegretUpdate) -> TrainingConfig {
+        TrainingConfig::new::<BasicCFRActionGenerator>(
+            "outcome_sampling",
+            regret_update,
+            GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0),
+        )
+    }
+
+    fn train(config: &TrainingConfig) -> impl Fn(&mut StateStore, usize, &mut CrateRng) {
+        let sampling = OutcomeSamplingConfig {
+            regret_update: config.regret_update,
+            ..OutcomeSamplingConfig::default()
+        };
+        let game_state = config.game_state.clone();
+        move |store, iterations, rng| {
+            sampling.train::<BasicCFRActionGenerator, _>(store, &game_state, iterations, rng)
+        }
+    }
+
+    #[test]
+    fn test_resume_matches_one_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoint");
+        let config = config(RegretUpdate::Plus);
+
+        let mut checkpoint = Checkpoint::new(config.clone());
+        checkpoint.train(20, 3, train(&config));
+        checkpoint.save_to_file(&path).unwrap();
+        let resumed = resume_training(&path, &config, 20, 4, train(&config)).unwrap();
+        assert_eq!(40, resumed.iterations);
+
+        assert!(!StrategyProfile::from_state_store(&resumed.state_store).is_empty());
+
+        // Replaying the seeds without stopping gives the same trees.
+        let mut uninterrupted = Checkpoint::new(config.clone());
+        for seed in &resumed.seeds {
+            uninterrupted.train(20, *seed, train(&config));
+        }
+        assert_eq!(
+            StrategyProfile::from_state_store(&uninterrupted.state_store),
+            StrategyProfile::from_state_store(&resumed.state_store)
+        );
+
+        let loaded = Checkpoint::load_from_file(&path).unwrap();
+        assert_eq!(config, loaded.config);
+        assert_eq!(vec![3, 4], loaded.seeds);
+        assert_eq!(resumed.convergence, loaded.convergence);
+        assert_eq!(
+            vec![20, 40],
+            loaded
+                .convergence
+                .points()
+                .iter()
+                .map(|point| point.iteration)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            StrategyProfile::from_state_store(&resumed.state_store),
+            StrategyProfile::from_state_store(&loaded.state_store)
+        );
+    }
+
+    #[test]
+    fn test_resume_checks_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoint");
+        let mut checkpoint = Checkpoint::new(config(RegretUpdate::Vanilla));
+        checkpoint.train(5, 1, train(&checkpoint.config.clone()));
+        checkpoint.save_to_file(&path).unwrap();
+
+        let other = config(RegretUpdate::Plus);
+        let err = resume_training(&path, &other, 5, 2, train(&other)).unwrap_err();
+        assert!(matches!(
+            err,
+            CheckpointError::Mismatch {
+                field: "regret_update",
+                ..
+            }
+        ));
+        // Nothing was trained or saved.
+        assert_eq!(5, Checkpoint::load_from_file(&path).unwrap().iterations);
+    }
+}
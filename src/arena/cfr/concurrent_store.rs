@@ -0,0 +1,258 @@
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::arena::GameState;
+use crate::arena::errors::MergeError;
+
+use super::{CFRState, StateStore, state::CFRStateInternal};
+
+/// A store of every player's CFR tree that can be shared between threads.
+///
+/// `StateStore` and `CFRState` hand out `Rc`s to the trees so agents and
+/// historians can share them cheaply, which ties them to one thread. This
+/// keeps the same trees behind a lock each, so threads working on different
+/// players' trees don't wait on each other and any number of them can read
+/// the same tree at once.
+///
+/// CFR agents still train against a `StateStore`. A thread takes a
+/// `snapshot` to train on, and adds what it learned back with
+/// `update_from`, or reads and changes a tree in place with `read` and
+/// `write`. Updates are merged into the trees under their locks rather than
+/// replacing them, so threads training at the same time all keep their
+/// work.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::ConcurrentStateStore;
+///
+/// let game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+/// let store = ConcurrentStateStore::new(game_state, 2);
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             let snapshot = store.snapshot();
+///             let trained = store.snapshot();
+///             let mut cfr_state = trained.get_state(0).unwrap();
+///             cfr_state.get_mut(0).unwrap().increment_count(0);
+///             store.update_from(&trained, &snapshot).unwrap();
+///         });
+///     }
+/// });
+/// let root_visits = store.read(0, |tree| tree.nodes.get(0).unwrap().get_count(0));
+/// assert_eq!(Some(4), root_visits);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConcurrentStateStore {
+    trees: Arc<[RwLock<CFRStateInternal>]>,
+}
+
+impl ConcurrentStateStore {
+    /// A store with a fresh tree starting from `game_state` for each of
+    /// `num_players`.
+    pub fn new(game_state: GameState, num_players: usize) -> Self {
+        let trees = (0..num_players)
+            .map(|_| CFRState::new(game_state.clone()))
+            .collect();
+        Self::from_trees(trees)
+    }
+
+    /// A store with a copy of every tree in `state_store`.
+    pub fn from_state_store(state_store: &StateStore) -> Self {
+        let trees = (0..state_store.len())
+            .filter_map(|player_idx| state_store.get_state(player_idx))
+            .collect();
+        Self::from_trees(trees)
+    }
+
+    fn from_trees(trees: Vec<CFRState>) -> Self {
+        Self {
+            trees: trees
+                .into_iter()
+                .map(|tree| RwLock::new(tree.internal_state().borrow().clone()))
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.trees.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    /// A single threaded copy of every tree, for training on. Each tree is
+    /// copied as it is when its turn comes, so trees written to while the
+    /// snapshot is taken can be from different moments.
+    pub fn snapshot(&self) -> StateStore {
+        StateStore::from_states(
+            (0..self.len())
+                .filter_map(|player_idx| self.get_state(player_idx))
+                .collect(),
+        )
+    }
+
+    /// A single threaded copy of the tree of `player_idx`.
+    pub fn get_state(&self, player_idx: usize) -> Option<CFRState> {
+        self.read(player_idx, |tree| CFRState::from_internal(tree.clone()))
+    }
+
+    /// Merge every tree in `state_store` into the tree for the same
+    /// player, see `CFRState::merge_from`, each under its write lock.
+    ///
+    /// Every tree in `state_store` needs a player here. A tree that fails
+    /// to merge is left as it was, though trees before it have already
+    /// been merged.
+    pub fn merge_from(&self, state_store: &StateStore) -> Result<(), MergeError> {
+        for player_idx in 0..state_store.len() {
+            let cfr_state = state_store.get_state(player_idx).unwrap();
+            let other = cfr_state.internal_state().borrow();
+            self.write(player_idx, |tree| tree.merge_from(&other))
+                .unwrap_or(Err(MergeError::MissingPlayer(player_idx)))?;
+        }
+        Ok(())
+    }
+
+    /// Add what `trained` learned since it was `snapshot` into the trees,
+    /// see `StateStore::since`. `snapshot` is a copy taken from this store
+    /// and `trained` another copy of it that's been trained on. Other
+    /// threads can have updated the trees since, and what they added is
+    /// kept.
+    pub fn update_from(
+        &self,
+        trained: &StateStore,
+        snapshot: &StateStore,
+    ) -> Result<(), MergeError> {
+        self.merge_from(&trained.since(snapshot)?)
+    }
+
+    /// Run `f` on the tree of `player_idx` while holding a read lock on it.
+    ///
+    /// A thread that panicked holding the lock leaves the tree as it was
+    /// when it panicked, and it's still read.
+    pub fn read<R>(&self, player_idx: usize, f: impl FnOnce(&CFRStateInternal) -> R) -> Option<R> {
+        let tree = self.trees.get(player_idx)?;
+        Some(f(&tree.read().unwrap_or_else(PoisonError::into_inner)))
+    }
+
+    /// Run `f` on the tree of `player_idx` while holding a write lock on
+    /// it. Other players' trees can still be read and written.
+    pub fn write<R>(
+        &self,
+        player_idx: usize,
+        f: impl FnOnce(&mut CFRStateInternal) -> R,
+    ) -> Option<R> {
+        let tree = self.trees.get(player_idx)?;
+        Some(f(&mut tree.write().unwrap_or_else(PoisonError::into_inner)))
+    }
+}
+
+impl From<&StateStore> for ConcurrentStateStore {
+    fn from(state_store: &StateStore) -> Self {
+        Self::from_state_store(state_store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use crate::arena::cfr::{NodeData, PlayerData, RegretMatcher};
+
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<ConcurrentStateStore>();
+    }
+
+    #[test]
+    fn test_concurrent_updates_add_up() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        let store = ConcurrentStateStore::new(game_state, 2);
+
+        // Every thread trains a copy of the same trees at once.
+        std::thread::scope(|s| {
+            for thread_idx in 0..8 {
+                let store = &store;
+                s.spawn(move || {
+                    let snapshot = store.snapshot();
+                    let trained = store.snapshot();
+                    for player_idx in 0..2 {
+                        let mut cfr_state = trained.get_state(player_idx).unwrap();
+                        // Threads that already finished have added the node.
+                        let existing = cfr_state.get(0).unwrap().get_child(0);
+                        let player = existing.unwrap_or_else(|| {
+                            cfr_state.add(
+                                0,
+                                0,
+                                NodeData::Player(PlayerData {
+                                    regret_matcher: Some(Box::new(RegretMatcher::new(2).unwrap())),
+                                    player_idx,
+                                }),
+                            )
+                        });
+                        cfr_state.get_mut(0).unwrap().increment_count(0);
+                        let mut node = cfr_state.get_mut(player).unwrap();
+                        node.increment_count(thread_idx % 2);
+                        if let NodeData::Player(player_data) = &mut *node.data {
+                            let matcher = player_data.regret_matcher.as_mut().unwrap();
+                            matcher.update_regret(array![0.0, 1.0].view()).unwrap();
+                        }
+                    }
+                    store.update_from(&trained, &snapshot).unwrap();
+                });
+            }
+        });
+
+        for player_idx in 0..2 {
+            let cfr_state = store.get_state(player_idx).unwrap();
+            assert_eq!(8, cfr_state.get(0).unwrap().get_count(0));
+            let player = cfr_state.get(0).unwrap().get_child(0).unwrap();
+            let player = cfr_state.get(player).unwrap();
+            assert_eq!(
+                vec![(0, 4), (1, 4)],
+                player.iter_counts().collect::<Vec<_>>()
+            );
+            match &*player.data {
+                NodeData::Player(player_data) => {
+                    let matcher = player_data.regret_matcher.as_ref().unwrap();
+                    assert_eq!(8, matcher.num_updates());
+                }
+                _ => panic!("Expected player data"),
+            }
+        }
+        let state_store = store.snapshot();
+        assert_eq!(2, state_store.len());
+        assert_eq!(1, state_store.traversal_len(0));
+    }
+
+    #[test]
+    fn test_merge_from_missing_player() {
+        let game_state = GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.0, 0);
+        let store = ConcurrentStateStore::new(game_state.clone(), 2);
+        let other = ConcurrentStateStore::new(game_state, 3);
+        assert_eq!(
+            Err(MergeError::MissingPlayer(2)),
+            store.merge_from(&other.snapshot())
+        );
+        assert!(store.read(2, |tree| tree.nodes.len()).is_none());
+    }
+
+    #[test]
+    fn test_poisoned_lock() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        let store = ConcurrentStateStore::new(game_state, 2);
+        let panicked = std::thread::scope(|s| {
+            s.spawn(|| store.write(0, |_| panic!("Panic holding the lock")))
+                .join()
+                .is_err()
+        });
+        assert!(panicked);
+        assert_eq!(Some(1), store.read(0, |tree| tree.nodes.len()));
+        assert_eq!(Some(1), store.write(0, |tree| tree.nodes.len()));
+    }
+}
@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+
+use crate::arena::GameState;
+use crate::arena::errors::ExploitabilityError;
+
+use super::{ActionGenerator, BestResponse, NodeData, StateStore, StrategyProfile};
+
+/// How far training had got at one iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConvergencePoint {
+    /// How many iterations had been trained.
+    pub iteration: usize,
+    /// The positive regret per update, averaged over every decision. Regret
+    /// grows slower than the number of updates as CFR converges, so this
+    /// falls towards zero.
+    pub average_positive_regret: f32,
+    /// The entropy of the average strategy in nats, averaged over every
+    /// decision. It falls as decisions settle on fewer actions and levels
+    /// off once they stop changing.
+    pub strategy_entropy: f32,
+    /// The exploitability in mbb/hand, for the points it was worked out
+    /// for.
+    pub exploitability: Option<f32>,
+}
+
+/// Records how training converges over time, so there's something to go
+/// on when deciding to stop.
+///
+/// Each call to `record` walks every tree in a `StateStore`, which is cheap
+/// next to training but not free, so it's meant for every so many
+/// iterations rather than every one. `record_with_exploitability` also works
+/// out an exact exploitability, which walks the whole game and
+/// is only practical for small games, so it's usually done less often still.
+///
+/// The tracker is saved with a `Checkpoint`, which records a point after
+/// every run of `Checkpoint::train`.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::{
+///     BasicCFRActionGenerator, ConvergenceTracker, OutcomeSamplingConfig, StateStore,
+/// };
+///
+/// let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+/// let sampling = OutcomeSamplingConfig::default();
+/// let mut state_store = StateStore::new();
+/// let mut tracker = ConvergenceTracker::new();
+/// for round in 1..=3 {
+///     sampling.train::<BasicCFRActionGenerator, _>(
+///         &mut state_store,
+///         &game_state,
+///         10,
+///         &mut rand::rng(),
+///     );
+///     tracker.record(round * 10, &state_store);
+/// }
+/// assert_eq!(3, tracker.points().len());
+/// assert_eq!(30, tracker.average_positive_regret().last().unwrap().0);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConvergenceTracker {
+    points: Vec<ConvergencePoint>,
+}
+
+impl ConvergenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure the trees in `state_store` after `iteration` iterations. A
+    /// point for the same iteration as the last one replaces it.
+    pub fn record(&mut self, iteration: usize, state_store: &StateStore) -> ConvergencePoint {
+        let (average_positive_regret, strategy_entropy) = measure(state_store);
+        self.push(ConvergencePoint {
+            iteration,
+            average_positive_regret,
+            strategy_entropy,
+            exploitability: None,
+        })
+    }
+
+    /// Measure the trees in `state_store` the same as `record`, and work out
+    /// the exploitability of their average strategy playing from
+    /// `game_state`, see `exploitability`. `T` is the action
+    /// generator the trees were trained with.
+    pub fn record_with_exploitability<T: ActionGenerator + 'static>(
+        &mut self,
+        iteration: usize,
+        state_store: &StateStore,
+        game_state: &GameState,
+    ) -> Result<ConvergencePoint, ExploitabilityError> {
+        let exploitability = BestResponse::<T>::new(StrategyProfile::from_state_store(state_store))
+            .exploitability(game_state)?;
+        let (average_positive_regret, strategy_entropy) = measure(state_store);
+        Ok(self.push(ConvergencePoint {
+            iteration,
+            average_positive_regret,
+            strategy_entropy,
+            exploitability: Some(exploitability.mbb_per_hand),
+        }))
+    }
+
+    /// Every point recorded, in order.
+    pub fn points(&self) -> &[ConvergencePoint] {
+        &self.points
+    }
+
+    /// The last point recorded.
+    pub fn last(&self) -> Option<&ConvergencePoint> {
+        self.points.last()
+    }
+
+    /// The average positive regret at each iteration recorded.
+    pub fn average_positive_regret(&self) -> Vec<(usize, f32)> {
+        self.series(|point| Some(point.average_positive_regret))
+    }
+
+    /// The strategy entropy at each iteration recorded.
+    pub fn strategy_entropy(&self) -> Vec<(usize, f32)> {
+        self.series(|point| Some(point.strategy_entropy))
+    }
+
+    /// The exploitability at each iteration it was worked out for.
+    pub fn exploitability(&self) -> Vec<(usize, f32)> {
+        self.series(|point| point.exploitability)
+    }
+
+    fn series(&self, value: impl Fn(&ConvergencePoint) -> Option<f32>) -> Vec<(usize, f32)> {
+        self.points
+            .iter()
+            .filter_map(|point| Some((point.iteration, value(point)?)))
+            .collect()
+    }
+
+    fn push(&mut self, point: ConvergencePoint) -> ConvergencePoint {
+        if self
+            .points
+            .last()
+            .is_some_and(|last| last.iteration == point.iteration)
+        {
+            self.points.pop();
+        }
+        self.points.push(point);
+        point
+    }
+}
+
+/// The average positive regret per update and the average strategy
+/// entropy over every decision that's been updated.
+fn measure(state_store: &StateStore) -> (f32, f32) {
+    let mut decisions = 0;
+    let mut positive_regret = 0.0;
+    let mut entropy = 0.0;
+    for player_idx in 0..state_store.len() {
+        let Some(cfr_state) = state_store.get_state(player_idx) else {
+            continue;
+        };
+        let internal = cfr_state.internal_state().borrow();
+        for node in internal.nodes.iter() {
+            let NodeData::Player(player_data) = &*node.data else {
+                continue;
+            };
+            let Some(matcher) = &player_data.regret_matcher else {
+                continue;
+            };
+            if matcher.num_updates() == 0 {
+                continue;
+            }
+            let weights = matcher.best_weight();
+            let total: f32 = weights.iter().sum();
+            if !total.is_finite() || total <= 0.0 {
+                continue;
+            }
+            decisions += 1;
+            positive_regret += matcher
+                .regrets()
+                .iter()
+                .map(|regret| regret.max(0.0))
+                .sum::<f32>()
+                / matcher.num_updates() as f32;
+            entropy -= weights
+                .iter()
+                .map(|weight| weight / total)
+                .filter(|p| *p > 0.0)
+                .map(|p| p * p.ln())
+                .sum::<f32>();
+        }
+    }
+    if decisions == 0 {
+        return (0.0, 0.0);
+    }
+    (
+        positive_regret / decisions as f32,
+        entropy / decisions as f32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use crate::arena::cfr::{PlayerData, RegretMatcher};
+
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        let (mut cfr_state, _) = state_store.new_state(game_state, 0);
+        let mut tracker = ConvergenceTracker::new();
+        assert_eq!(0.0, tracker.record(0, &state_store).strategy_entropy);
+
+        // One decision, with its expected entropy and regret per update
+        // worked out by hand.
+        let mut matcher = RegretMatcher::new(2).unwrap();
+        matcher.update_regret(array![0.0, 2.0].view()).unwrap();
+        matcher.update_regret(array![3.0, 0.0].view()).unwrap();
+        let entropy: f32 = -matcher
+            .best_weight()
+            .iter()
+            .filter(|p| **p > 0.0)
+            .map(|p| p * p.ln())
+            .sum::<f32>();
+        assert!(entropy > 0.0);
+        let regret: f32 = matcher.regrets().iter().map(|r| r.max(0.0)).sum();
+        cfr_state.add(
+            0,
+            0,
+            NodeData::Player(PlayerData {
+                regret_matcher: Some(Box::new(matcher)),
+                player_idx: 0,
+            }),
+        );
+
+        let point = tracker.record(10, &state_store);
+        assert!((point.strategy_entropy - entropy).abs() < 1e-6);
+        assert_eq!(regret / 2.0, point.average_positive_regret);
+        assert_eq!(None, point.exploitability);
+
+        // Recording the same iteration again replaces it.
+        tracker.record(10, &state_store);
+        assert_eq!(
+            vec![(0, 0.0), (10, point.strategy_entropy)],
+            tracker.strategy_entropy()
+        );
+        assert!(tracker.exploitability().is_empty());
+
+        let json = serde_json::to_string(&tracker).unwrap();
+        assert_eq!(tracker, serde_json::from_str(&json).unwrap());
+    }
+}
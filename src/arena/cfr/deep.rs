@@ -0,0 +1,621 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rand::Rng;
+
+use crate::arena::{
+    Agent, GameState, HoldemSimulationBuilder, action::AgentAction, errors::ModelError,
+    mcts::ActionMenu, model::Featurizer,
+};
+
+use super::reservoir::ReservoirBuffer;
+
+/// What the traversing player learned at one of their decisions: how much
+/// better each action did than the strategy they were playing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdvantageSample {
+    pub features: Vec<f32>,
+    /// One per `ActionMenu` slot, as a fraction of the player's starting
+    /// stack. Zero for the slots that weren't legal.
+    pub advantages: Vec<f32>,
+    pub legal_slots: Vec<usize>,
+    /// The iteration the sample came from, starting at 1. Deep CFR weights
+    /// samples by this when fitting, as linear CFR does.
+    pub iteration: u32,
+}
+
+/// The strategy a player was playing at a decision, for fitting a model of
+/// the average strategy, which is the one that converges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategySample {
+    pub features: Vec<f32>,
+    /// One per `ActionMenu` slot, summing to one.
+    pub strategy: Vec<f32>,
+    pub iteration: u32,
+}
+
+/// A function approximator for one player's cumulative advantages, taking
+/// the place of the regret table in tabular CFR.
+pub trait AdvantageModel {
+    fn num_features(&self) -> usize;
+
+    fn num_actions(&self) -> usize;
+
+    /// Write the predicted advantage of each action into `out`.
+    fn predict(&mut self, features: &[f32], out: &mut [f32]) -> Result<(), ModelError>;
+
+    /// Fit the model to `samples`, returning the training loss. Deep CFR
+    /// trains from scratch each iteration, but that's left to the model.
+    fn fit(&mut self, samples: &[AdvantageSample]) -> Result<f32, ModelError>;
+}
+
+type SharedModel = Rc<RefCell<Box<dyn AdvantageModel>>>;
+
+/// How many hands `DeepCfrTrainer` traverses and how much it keeps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepCfrConfig {
+    /// Hands traversed for each player in each iteration.
+    pub traversals_per_iteration: usize,
+    /// Size of each player's advantage reservoir.
+    pub advantage_capacity: usize,
+    /// Size of the reservoir of strategy samples, shared by every player.
+    pub strategy_capacity: usize,
+    /// The raises in the `ActionMenu`, as fractions of the pot.
+    pub bet_fractions: Vec<f32>,
+}
+
+impl Default for DeepCfrConfig {
+    fn default() -> Self {
+        Self {
+            traversals_per_iteration: 100,
+            advantage_capacity: 100_000,
+            strategy_capacity: 100_000,
+            bet_fractions: vec![0.5, 1.0],
+        }
+    }
+}
+
+/// The training loop of Deep CFR.
+///
+/// Each iteration, for each player in turn, it plays
+/// `traversals_per_iteration` hands from the game state generator with
+/// every player following the strategy their advantage model gives by
+/// regret matching. At each of the traversing player's decisions every
+/// legal action is played out once, and how much better each did than the
+/// strategy goes into that player's reservoir. Then the player's model is
+/// fit to everything in their reservoir.
+///
+/// Other players' decisions go into a shared reservoir of strategy
+/// samples, for fitting a model of the average strategy once training is
+/// done.
+///
+/// Actions are the slots of an `ActionMenu`, and models see states through
+/// a `Featurizer`, the same as the models in `arena::model`.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::cfr::{AdvantageModel, AdvantageSample, DeepCfrConfig, DeepCfrTrainer};
+/// use rs_poker::arena::errors::ModelError;
+/// use rs_poker::arena::game_state::RandomGameStateGenerator;
+/// use rs_poker::arena::model::BasicFeaturizer;
+///
+/// /// Knows nothing, so everyone plays uniformly at random.
+/// struct Untrained;
+///
+/// impl AdvantageModel for Untrained {
+///     fn num_features(&self) -> usize {
+///         BasicFeaturizer::NUM_FEATURES
+///     }
+///
+///     fn num_actions(&self) -> usize {
+///         5
+///     }
+///
+///     fn predict(&mut self, _features: &[f32], out: &mut [f32]) -> Result<(), ModelError> {
+///         out.fill(0.0);
+///         Ok(())
+///     }
+///
+///     fn fit(&mut self, _samples: &[AdvantageSample]) -> Result<f32, ModelError> {
+///         Ok(0.0)
+///     }
+/// }
+///
+/// let mut trainer = DeepCfrTrainer::new(
+///     DeepCfrConfig {
+///         traversals_per_iteration: 2,
+///         ..Default::default()
+///     },
+///     Box::new(BasicFeaturizer),
+///     vec![Box::new(Untrained), Box::new(Untrained)],
+/// )
+/// .unwrap();
+/// let mut game_states = RandomGameStateGenerator::new(2, 100.0, 200.0, 10.0, 5.0, 0.0);
+/// trainer
+///     .train(2, &mut game_states, &mut rand::rng())
+///     .unwrap();
+/// assert_eq!(2, trainer.iteration());
+/// assert!(!trainer.advantage_samples(0).is_empty());
+/// ```
+pub struct DeepCfrTrainer {
+    config: DeepCfrConfig,
+    menu: ActionMenu,
+    featurizer: Rc<dyn Featurizer>,
+    models: Vec<SharedModel>,
+    advantage_buffers: Vec<ReservoirBuffer<AdvantageSample>>,
+    strategy_buffer: ReservoirBuffer<StrategySample>,
+    iteration: u32,
+}
+
+impl DeepCfrTrainer {
+    /// Takes one model per player. Fails unless each takes the featurizer's
+    /// features and has an output for each slot of the menu.
+    pub fn new(
+        config: DeepCfrConfig,
+        featurizer: Box<dyn Featurizer>,
+        models: Vec<Box<dyn AdvantageModel>>,
+    ) -> Result<Self, ModelError> {
+        let menu = ActionMenu::new(config.bet_fractions.clone());
+        for model in &models {
+            if model.num_features() != featurizer.num_features() {
+                return Err(ModelError::WrongFeatureCount {
+                    expected: model.num_features(),
+                    found: featurizer.num_features(),
+                });
+            }
+            if model.num_actions() != menu.num_slots() {
+                return Err(ModelError::WrongActionCount {
+                    expected: menu.num_slots(),
+                    found: model.num_actions(),
+                });
+            }
+        }
+        Ok(Self {
+            advantage_buffers: (0..models.len())
+                .map(|_| ReservoirBuffer::new(config.advantage_capacity))
+                .collect(),
+            strategy_buffer: ReservoirBuffer::new(config.strategy_capacity),
+            models: models
+                .into_iter()
+                .map(|model| Rc::new(RefCell::new(model)))
+                .collect(),
+            featurizer: featurizer.into(),
+            menu,
+            config,
+            iteration: 0,
+        })
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.models.len()
+    }
+
+    /// How many iterations have been run.
+    pub fn iteration(&self) -> u32 {
+        self.iteration
+    }
+
+    pub fn menu(&self) -> &ActionMenu {
+        &self.menu
+    }
+
+    pub fn advantage_samples(&self, player: usize) -> &[AdvantageSample] {
+        self.advantage_buffers[player].items()
+    }
+
+    pub fn strategy_samples(&self) -> &[StrategySample] {
+        self.strategy_buffer.items()
+    }
+
+    /// The strategy `player`'s model gives for the player to act in
+    /// `game_state`, one probability per menu slot.
+    pub fn strategy(&self, player: usize, game_state: &GameState) -> Result<Vec<f32>, ModelError> {
+        let mut features = Vec::new();
+        self.featurizer.featurize(game_state, &mut features);
+        let legal = self.menu.legal_slots(game_state);
+        strategy(&self.models[player], &features, &legal)
+    }
+
+    /// Run `iterations` more iterations, returning the loss from each fit
+    /// with one entry per player for each iteration.
+    pub fn train<I, R>(
+        &mut self,
+        iterations: usize,
+        game_states: &mut I,
+        rng: &mut R,
+    ) -> Result<Vec<Vec<f32>>, ModelError>
+    where
+        I: Iterator<Item = GameState>,
+        R: Rng,
+    {
+        (0..iterations)
+            .map(|_| self.run_iteration(game_states, rng))
+            .collect()
+    }
+
+    /// Traverse hands for each player in turn and fit their model after,
+    /// returning each player's training loss. Stops traversing early if
+    /// `game_states` runs out.
+    ///
+    /// # Panics
+    ///
+    /// If a game state doesn't have one player per model.
+    pub fn run_iteration<I, R>(
+        &mut self,
+        game_states: &mut I,
+        rng: &mut R,
+    ) -> Result<Vec<f32>, ModelError>
+    where
+        I: Iterator<Item = GameState>,
+        R: Rng,
+    {
+        self.iteration += 1;
+        #[cfg(feature = "metrics")]
+        crate::arena::metrics::record_iteration("deep_cfr");
+        let mut losses = Vec::with_capacity(self.num_players());
+        for player in 0..self.num_players() {
+            for game_state in game_states
+                .by_ref()
+                .take(self.config.traversals_per_iteration)
+            {
+                self.traverse(game_state, player, rng)?;
+            }
+            let loss = self.models[player]
+                .borrow_mut()
+                .fit(self.advantage_buffers[player].items())?;
+            losses.push(loss);
+        }
+        Ok(losses)
+    }
+
+    /// Play one hand from `game_state` with `traverser` exploring every
+    /// action at their decisions, and keep the samples.
+    fn traverse<R: Rng>(
+        &mut self,
+        game_state: GameState,
+        traverser: usize,
+        rng: &mut R,
+    ) -> Result<(), ModelError> {
+        assert_eq!(
+            self.num_players(),
+            game_state.num_players,
+            "There should be one model per player"
+        );
+        let traversal = Rc::new(Traversal {
+            menu: self.menu.clone(),
+            featurizer: self.featurizer.clone(),
+            models: self.models.clone(),
+            traverser,
+            iteration: self.iteration,
+            advantages: RefCell::new(Vec::new()),
+            strategies: RefCell::new(Vec::new()),
+            error: RefCell::new(None),
+        });
+        traversal.play(game_state, true, None);
+
+        let traversal = Rc::try_unwrap(traversal)
+            .ok()
+            .expect("Agents are dropped with the simulation");
+        if let Some(error) = traversal.error.into_inner() {
+            return Err(error);
+        }
+        for sample in traversal.advantages.into_inner() {
+            self.advantage_buffers[traverser].push(sample, rng);
+        }
+        for sample in traversal.strategies.into_inner() {
+            self.strategy_buffer.push(sample, rng);
+        }
+        Ok(())
+    }
+}
+
+/// Regret matching over the predicted advantages: legal slots in
+/// proportion to their positive advantage, or uniformly if none are
+/// positive.
+fn strategy(
+    model: &SharedModel,
+    features: &[f32],
+    legal: &[usize],
+) -> Result<Vec<f32>, ModelError> {
+    let mut model = model.borrow_mut();
+    let mut advantages = vec![0.0; model.num_actions()];
+    model.predict(features, &mut advantages)?;
+
+    let mut strategy = vec![0.0; advantages.len()];
+    let total: f32 = legal.iter().map(|s| advantages[*s].max(0.0)).sum();
+    for &slot in legal {
+        strategy[slot] = if total > 0.0 {
+            advantages[slot].max(0.0) / total
+        } else {
+            1.0 / legal.len() as f32
+        };
+    }
+    Ok(strategy)
+}
+
+/// Everything the agents in one traversal share.
+struct Traversal {
+    menu: ActionMenu,
+    featurizer: Rc<dyn Featurizer>,
+    models: Vec<SharedModel>,
+    traverser: usize,
+    iteration: u32,
+    advantages: RefCell<Vec<AdvantageSample>>,
+    strategies: RefCell<Vec<StrategySample>>,
+    /// The first error from a model. Agents can't return one, so they play
+    /// uniformly and the trainer reports it once the hand is over.
+    error: RefCell<Option<ModelError>>,
+}
+
+impl Traversal {
+    /// Play the hand out from `game_state`, with the player to act
+    /// starting with `forced` if given, and return the traverser's reward
+    /// as a fraction of their starting stack.
+    fn play(
+        self: &Rc<Self>,
+        game_state: GameState,
+        explore: bool,
+        forced: Option<AgentAction>,
+    ) -> f32 {
+        let to_act = game_state.to_act_idx();
+        let agents: Vec<Box<dyn Agent>> = (0..game_state.num_players)
+            .map(|idx| {
+                Box::new(TraversalAgent {
+                    traversal: self.clone(),
+                    explore,
+                    forced: forced.clone().filter(|_| idx == to_act),
+                }) as Box<dyn Agent>
+            })
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state)
+            .agents(agents)
+            .build()
+            .unwrap();
+        sim.run(&mut crate::core::rng());
+
+        let scale = sim.game_state.starting_stacks[self.traverser].max(1.0);
+        sim.game_state.player_reward(self.traverser) / scale
+    }
+
+    fn strategy(&self, player: usize, features: &[f32], legal: &[usize]) -> Vec<f32> {
+        strategy(&self.models[player], features, legal).unwrap_or_else(|error| {
+            self.error.borrow_mut().get_or_insert(error);
+            let mut uniform = vec![0.0; self.menu.num_slots()];
+            for &slot in legal {
+                uniform[slot] = 1.0 / legal.len() as f32;
+            }
+            uniform
+        })
+    }
+}
+
+struct TraversalAgent {
+    traversal: Rc<Traversal>,
+    /// Whether this is the hand being traversed, rather than a playout of
+    /// one action, and so should record samples.
+    explore: bool,
+    forced: Option<AgentAction>,
+}
+
+impl Agent for TraversalAgent {
+    fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+        if let Some(action) = self.forced.take() {
+            return action;
+        }
+        let traversal = &self.traversal;
+        let player = game_state.to_act_idx();
+        let mut features = Vec::new();
+        traversal.featurizer.featurize(game_state, &mut features);
+        let legal = traversal.menu.legal_slots(game_state);
+        let strategy = traversal.strategy(player, &features, &legal);
+
+        if self.explore && player == traversal.traverser {
+            let mut advantages = vec![0.0; strategy.len()];
+            for &slot in &legal {
+                let action = traversal.menu.action(game_state, slot);
+                advantages[slot] = traversal.play(game_state.clone(), false, action);
+            }
+            let value: f32 = legal.iter().map(|s| strategy[*s] * advantages[*s]).sum();
+            for &slot in &legal {
+                advantages[slot] -= value;
+            }
+            traversal.advantages.borrow_mut().push(AdvantageSample {
+                features,
+                advantages,
+                legal_slots: legal.clone(),
+                iteration: traversal.iteration,
+            });
+        } else if self.explore {
+            traversal.strategies.borrow_mut().push(StrategySample {
+                features,
+                strategy: strategy.clone(),
+                iteration: traversal.iteration,
+            });
+        }
+
+        let mut target = crate::core::rng().random_range(0.0..1.0);
+        let slot = legal
+            .iter()
+            .copied()
+            .find(|s| {
+                target -= strategy[*s];
+                target < 0.0
+            })
+            .or(legal.last().copied());
+        slot.and_then(|slot| traversal.menu.action(game_state, slot))
+            .unwrap_or(AgentAction::Bet(game_state.current_round_bet()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::game_state::{CloneGameStateGenerator, Round, RoundData};
+    use crate::arena::hand_history::parse_cards;
+    use crate::arena::model::BasicFeaturizer;
+    use crate::core::{Hand, PlayerBitSet};
+
+    /// Predicts the iteration weighted mean advantage of each slot, whatever
+    /// the features.
+    #[derive(Default)]
+    struct MeanModel {
+        means: Vec<f32>,
+    }
+
+    impl AdvantageModel for MeanModel {
+        fn num_features(&self) -> usize {
+            BasicFeaturizer::NUM_FEATURES
+        }
+
+        fn num_actions(&self) -> usize {
+            5
+        }
+
+        fn predict(&mut self, _features: &[f32], out: &mut [f32]) -> Result<(), ModelError> {
+            out.fill(0.0);
+            for (o, m) in out.iter_mut().zip(&self.means) {
+                *o = *m;
+            }
+            Ok(())
+        }
+
+        fn fit(&mut self, samples: &[AdvantageSample]) -> Result<f32, ModelError> {
+            let mut sums = [0.0; 5];
+            let mut weights = [0.0; 5];
+            for sample in samples {
+                for &slot in &sample.legal_slots {
+                    sums[slot] += sample.iteration as f32 * sample.advantages[slot];
+                    weights[slot] += sample.iteration as f32;
+                }
+            }
+            self.means = sums
+                .iter()
+                .zip(&weights)
+                .map(|(s, w)| if *w > 0.0 { s / w } else { 0.0 })
+                .collect();
+            Ok(0.0)
+        }
+    }
+
+    fn nuts_on_the_river() -> GameState {
+        let board = parse_cards("QsJsTs2d3c").unwrap();
+        let hands = ["AsKs", "7h8h"]
+            .iter()
+            .map(|hole| {
+                let mut hand = Hand::new_from_str(hole).unwrap();
+                hand.extend(board.iter().copied());
+                hand
+            })
+            .collect();
+        GameState::new(
+            Round::River,
+            RoundData::new(2, 10.0, PlayerBitSet::new(2), 0),
+            board,
+            hands,
+            vec![100.0, 100.0],
+            vec![50.0, 50.0],
+            10.0,
+            5.0,
+            0.0,
+            1,
+        )
+    }
+
+    fn trainer(traversals: usize) -> DeepCfrTrainer {
+        DeepCfrTrainer::new(
+            DeepCfrConfig {
+                traversals_per_iteration: traversals,
+                ..Default::default()
+            },
+            Box::new(BasicFeaturizer),
+            vec![Box::<MeanModel>::default(), Box::<MeanModel>::default()],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_checks_model_sizes() {
+        struct Wide;
+
+        impl AdvantageModel for Wide {
+            fn num_features(&self) -> usize {
+                BasicFeaturizer::NUM_FEATURES
+            }
+
+            fn num_actions(&self) -> usize {
+                7
+            }
+
+            fn predict(&mut self, _features: &[f32], _out: &mut [f32]) -> Result<(), ModelError> {
+                Ok(())
+            }
+
+            fn fit(&mut self, _samples: &[AdvantageSample]) -> Result<f32, ModelError> {
+                Ok(0.0)
+            }
+        }
+
+        let result = DeepCfrTrainer::new(
+            DeepCfrConfig::default(),
+            Box::new(BasicFeaturizer),
+            vec![Box::new(Wide)],
+        );
+        assert!(matches!(
+            result,
+            Err(ModelError::WrongActionCount {
+                expected: 5,
+                found: 7
+            })
+        ));
+    }
+
+    #[test]
+    fn test_shoving_the_nuts_has_the_most_advantage() {
+        let mut trainer = trainer(100);
+        let mut game_states = CloneGameStateGenerator::new(nuts_on_the_river());
+        let mut rng = rand::rng();
+        let losses = trainer.train(1, &mut game_states, &mut rng).unwrap();
+        assert_eq!(vec![vec![0.0; 2]], losses);
+        assert_eq!(1, trainer.iteration());
+
+        let samples = trainer.advantage_samples(0);
+        assert!(samples.len() >= 100);
+        assert!(samples.iter().all(|s| s.iteration == 1));
+        assert!(!trainer.strategy_samples().is_empty());
+
+        // Folding the nuts does worse than the strategy on average.
+        let folds: f32 = samples
+            .iter()
+            .filter(|s| s.legal_slots.contains(&0))
+            .map(|s| s.advantages[0])
+            .sum();
+        assert!(folds < 0.0);
+
+        // Against a player that hasn't learned anything yet, first to act,
+        // all in wins half the time and doubles up the other half. Checking
+        // lets them bet, and then anything can happen.
+        let first: Vec<&AdvantageSample> = samples
+            .iter()
+            .filter(|s| !s.legal_slots.contains(&0))
+            .collect();
+        let total = |slot: usize| first.iter().map(|s| s.advantages[slot]).sum::<f32>();
+        assert!(total(4) > total(1), "{} {}", total(4), total(1));
+
+        let strategy = trainer.strategy(0, &nuts_on_the_river()).unwrap();
+        assert_eq!(1.0, strategy.iter().sum::<f32>().round());
+        assert_eq!(0.0, strategy[0]);
+    }
+
+    #[test]
+    fn test_stops_when_game_states_run_out() {
+        let mut trainer = trainer(10);
+        let mut game_states = std::iter::repeat_n(nuts_on_the_river(), 3);
+        trainer
+            .run_iteration(&mut game_states, &mut rand::rng())
+            .unwrap();
+        // All three hands went to the first player.
+        assert!(trainer.advantage_samples(0).len() >= 3);
+        assert!(trainer.advantage_samples(1).is_empty());
+    }
+}
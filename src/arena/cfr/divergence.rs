@@ -0,0 +1,220 @@
+//! Compare two strategies trained over the same abstraction.
+use std::fmt;
+
+use crate::arena::errors::StrategyComparisonError;
+
+use super::StrategyProfile;
+
+/// Probabilities below this are raised to it for the KL divergence, so an
+/// action one strategy never takes doesn't make the divergence infinite.
+const MIN_PROBABILITY: f32 = 1e-6;
+
+/// How differently two strategies play one decision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotDivergence {
+    pub player_idx: usize,
+    /// The child indices from the root of the tree to the decision.
+    pub path: Vec<usize>,
+    /// Half the summed absolute differences of the action probabilities,
+    /// from zero for the same strategy to one for strategies that never
+    /// take the same action.
+    pub total_variation: f32,
+    /// The KL divergence of the second strategy from the first, in nats.
+    pub kl_divergence: f32,
+}
+
+/// The decision by decision differences between two [`StrategyProfile`]s,
+/// for tracking how a strategy moves between training runs.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::cfr::{StrategyComparison, StrategyProfile};
+///
+/// let mut before = StrategyProfile::new();
+/// before.insert(0, vec![0], vec![0.5, 0.5]);
+/// before.insert(1, vec![0, 1], vec![0.2, 0.8]);
+/// let mut after = before.clone();
+/// after.insert(1, vec![0, 1], vec![0.6, 0.4]);
+///
+/// let comparison = StrategyComparison::new(&before, &after).unwrap();
+/// assert_eq!(2, comparison.spots().len());
+/// let top = &comparison.top(1)[0];
+/// assert_eq!((1, &vec![0, 1]), (top.player_idx, &top.path));
+/// assert!((top.total_variation - 0.4).abs() < 1e-6);
+/// assert!((comparison.mean_total_variation() - 0.2).abs() < 1e-6);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StrategyComparison {
+    /// Decisions in both profiles, most divergent first.
+    spots: Vec<SpotDivergence>,
+    only_in_first: usize,
+    only_in_second: usize,
+}
+
+impl StrategyComparison {
+    /// Compare every decision the two profiles share. Decisions in only one
+    /// of them are counted but can't be compared.
+    pub fn new(
+        first: &StrategyProfile,
+        second: &StrategyProfile,
+    ) -> Result<Self, StrategyComparisonError> {
+        let mut comparison = Self::default();
+        for (player_idx, path, p) in first.iter() {
+            let Some(q) = second.get(player_idx, path) else {
+                comparison.only_in_first += 1;
+                continue;
+            };
+            if p.len() != q.len() {
+                return Err(StrategyComparisonError::DifferentActions {
+                    player_idx,
+                    path: path.to_vec(),
+                    first: p.len(),
+                    second: q.len(),
+                });
+            }
+            comparison.spots.push(SpotDivergence {
+                player_idx,
+                path: path.to_vec(),
+                total_variation: total_variation(p, q),
+                kl_divergence: kl_divergence(p, q),
+            });
+        }
+        comparison.only_in_second = second.len() - comparison.spots.len();
+        comparison
+            .spots
+            .sort_by(|a, b| b.total_variation.total_cmp(&a.total_variation));
+        Ok(comparison)
+    }
+
+    /// Every decision in both profiles, most divergent first.
+    pub fn spots(&self) -> &[SpotDivergence] {
+        &self.spots
+    }
+
+    /// The `n` decisions whose strategies differ the most.
+    pub fn top(&self, n: usize) -> &[SpotDivergence] {
+        &self.spots[..n.min(self.spots.len())]
+    }
+
+    /// How many decisions only the first profile has.
+    pub fn only_in_first(&self) -> usize {
+        self.only_in_first
+    }
+
+    /// How many decisions only the second profile has.
+    pub fn only_in_second(&self) -> usize {
+        self.only_in_second
+    }
+
+    /// The average total variation over the shared decisions, zero if there
+    /// are none.
+    pub fn mean_total_variation(&self) -> f32 {
+        self.mean(|spot| spot.total_variation)
+    }
+
+    /// The largest total variation of any shared decision.
+    pub fn max_total_variation(&self) -> f32 {
+        self.spots.first().map_or(0.0, |spot| spot.total_variation)
+    }
+
+    /// The average KL divergence over the shared decisions.
+    pub fn mean_kl_divergence(&self) -> f32 {
+        self.mean(|spot| spot.kl_divergence)
+    }
+
+    fn mean(&self, f: impl Fn(&SpotDivergence) -> f32) -> f32 {
+        if self.spots.is_empty() {
+            return 0.0;
+        }
+        self.spots.iter().map(f).sum::<f32>() / self.spots.len() as f32
+    }
+}
+
+impl fmt::Display for SpotDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "player {} at {:?}: total variation {:.4}, KL {:.4}",
+            self.player_idx, self.path, self.total_variation, self.kl_divergence
+        )
+    }
+}
+
+impl fmt::Display for StrategyComparison {
+    /// How many decisions were compared and the aggregate distances.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} shared decisions, {} only in the first, {} only in the second",
+            self.spots.len(),
+            self.only_in_first,
+            self.only_in_second
+        )?;
+        writeln!(
+            f,
+            "total variation: mean {:.4}, max {:.4}",
+            self.mean_total_variation(),
+            self.max_total_variation()
+        )?;
+        write!(f, "KL divergence: mean {:.4}", self.mean_kl_divergence())
+    }
+}
+
+fn total_variation(p: &[f32], q: &[f32]) -> f32 {
+    p.iter().zip(q).map(|(p, q)| (p - q).abs()).sum::<f32>() / 2.0
+}
+
+fn kl_divergence(p: &[f32], q: &[f32]) -> f32 {
+    p.iter()
+        .zip(q)
+        .filter(|(p, _)| **p > 0.0)
+        .map(|(p, q)| p * (p / q.max(MIN_PROBABILITY)).ln())
+        .sum::<f32>()
+        .max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let mut first = StrategyProfile::new();
+        first.insert(0, vec![0], vec![1.0, 0.0]);
+        first.insert(0, vec![1], vec![0.5, 0.5]);
+        first.insert(1, vec![0, 0], vec![0.25, 0.75]);
+        let mut second = StrategyProfile::new();
+        second.insert(0, vec![0], vec![0.0, 1.0]);
+        second.insert(0, vec![1], vec![0.5, 0.5]);
+        second.insert(1, vec![0, 2], vec![0.5, 0.5]);
+
+        let comparison = StrategyComparison::new(&first, &second).unwrap();
+        assert_eq!(1, comparison.only_in_first());
+        assert_eq!(1, comparison.only_in_second());
+        let spots = comparison.spots();
+        assert_eq!(2, spots.len());
+        assert_eq!(vec![0], spots[0].path);
+        assert_eq!(1.0, spots[0].total_variation);
+        // Never taking an action the first strategy always takes is as far
+        // as the floor allows.
+        assert!((spots[0].kl_divergence - -MIN_PROBABILITY.ln()).abs() < 1e-3);
+        assert_eq!(0.0, spots[1].total_variation);
+        assert_eq!(0.0, spots[1].kl_divergence);
+        assert_eq!(1.0, comparison.max_total_variation());
+        assert_eq!(0.5, comparison.mean_total_variation());
+        assert!(spots[0].to_string().starts_with("player 0 at [0]"));
+        assert!(comparison.to_string().starts_with("2 shared decisions"));
+
+        second.insert(0, vec![1], vec![0.2, 0.3, 0.5]);
+        assert_eq!(
+            Err(StrategyComparisonError::DifferentActions {
+                player_idx: 0,
+                path: vec![1],
+                first: 2,
+                second: 3,
+            }),
+            StrategyComparison::new(&first, &second)
+        );
+    }
+}
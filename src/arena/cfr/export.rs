@@ -138,17 +138,17 @@ pub fn generate_dot(state: &CFRState) -> String {
     let nodes = &inner_state.borrow().nodes;
 
     // Process nodes
-    for node in nodes {
-        let (color, shape, style) = match &node.data {
+    for node in nodes.iter() {
+        let (color, shape, style) = match &*node.data {
             NodeData::Root => (COLOR_ROOT, "doubleoctagon", "filled"),
             NodeData::Chance => (COLOR_CHANCE, "ellipse", "filled"),
             NodeData::Player(_) => (COLOR_PLAYER, "box", "rounded,filled"),
             NodeData::Terminal(_) => (COLOR_TERMINAL, "hexagon", "filled"),
         };
 
-        let total_visits: u32 = (0..52).map(|i| node.get_count(i)).sum();
+        let total_visits: u32 = node.iter_counts().map(|(_, count)| count).sum();
 
-        let label = match &node.data {
+        let label = match &*node.data {
             NodeData::Root => format!(
                 "Root Node\\nIndex: {}\\nTotal Visits: {}",
                 node.idx, total_visits
@@ -170,7 +170,7 @@ pub fn generate_dot(state: &CFRState) -> String {
             ),
         };
 
-        let tooltip = match &node.data {
+        let tooltip = match &*node.data {
             NodeData::Terminal(td) => format!(
                 "Average Utility: {:.2}",
                 if total_visits > 0 {
@@ -180,8 +180,8 @@ pub fn generate_dot(state: &CFRState) -> String {
                 }
             ),
             _ => {
-                let (most_common_idx, most_common_count) = (0..52)
-                    .map(|i| (i, node.get_count(i)))
+                let (most_common_idx, most_common_count) = node
+                    .iter_counts()
                     .max_by_key(|&(_, count)| count)
                     .unwrap_or((0, 0));
                 format!(
@@ -201,10 +201,10 @@ pub fn generate_dot(state: &CFRState) -> String {
             node.idx, label, shape, style, color, tooltip
         ));
 
-        let total_count: u32 = (0..52).map(|i| node.get_count(i)).sum();
+        let total_count: u32 = node.iter_counts().map(|(_, count)| count).sum();
 
         // Group nodes by level for better layout
-        if let NodeData::Player(_) = node.data {
+        if let NodeData::Player(_) = &*node.data {
             output.push_str(&format!(
                 "  {{rank=same; node_{};}}  // Group player nodes\n",
                 node.idx
@@ -212,7 +212,7 @@ pub fn generate_dot(state: &CFRState) -> String {
         }
 
         for (child_idx, child_node_idx) in node.iter_children() {
-            let edge_label = match &node.data {
+            let edge_label = match &*node.data {
                 NodeData::Chance => Card::from(child_idx as u8).to_string(),
                 NodeData::Player(_) => {
                     if child_idx == 0 {
@@ -1,10 +1,10 @@
 use crate::arena::action::Action;
 use crate::arena::game_state::Round;
 
-use crate::arena::action::AgentAction;
+use crate::arena::action::PlayedActionPayload;
 
 use crate::arena::Historian;
-use crate::core::Card;
+use crate::core::{Card, CardSet};
 
 use crate::arena::GameState;
 
@@ -12,6 +12,7 @@ use crate::arena::HistorianError;
 
 use super::ActionGenerator;
 use super::CFRState;
+use super::ChanceSampler;
 use super::NodeData;
 use super::PlayerData;
 use super::TerminalData;
@@ -31,7 +32,14 @@ use super::TraversalState;
 /// - `cfr_state`: The current state of the CFR algorithm, including node data
 ///   and counts.
 /// - `action_generator`: An instance of the action generator used to map
-///   actions to indices.
+///   actions and cards to indices.
+/// - `dealt_cards`: The cards recorded for the player so far. Cards are
+///   recorded before the game state has them, so this is what lets the second
+///   card of a deal see the first.
+/// - `chance_reach`: How likely the cards recorded so far were to be dealt,
+///   each given every card in the game state at the time, see `ChanceSampler`.
+///   It's what an importance weighted estimate divides by to undo the sampling
+///   of the cards.
 ///
 /// # Trait Implementations
 /// - `Historian`: Implements the `Historian` trait, allowing the `CFRHistorian`
@@ -43,6 +51,8 @@ where
     pub traversal_state: TraversalState,
     pub cfr_state: CFRState,
     pub action_generator: T,
+    pub dealt_cards: CardSet,
+    pub chance_reach: f32,
 }
 
 impl<T> CFRHistorian<T>
@@ -55,6 +65,8 @@ where
             traversal_state,
             cfr_state,
             action_generator,
+            dealt_cards: CardSet::new(),
+            chance_reach: 1.0,
         }
     }
 
@@ -92,13 +104,17 @@ where
 
     pub(crate) fn record_card(
         &mut self,
-        _game_state: &GameState,
+        game_state: &GameState,
         card: Card,
     ) -> Result<(), HistorianError> {
-        let card_value: u8 = card.into();
+        let player_idx = self.traversal_state.player_idx();
+        let known = CardSet::from(game_state.hands[player_idx]) | self.dealt_cards;
+        let card_idx = self.action_generator.card_to_idx(game_state, known, card);
+        let sampler = ChanceSampler::new(game_state.dead_cards() | self.dealt_cards);
+        self.chance_reach *= sampler.probability(card);
+        self.dealt_cards.insert(card);
         let to_node_idx = self.ensure_target_node(NodeData::Chance)?;
-        self.traversal_state
-            .move_to(to_node_idx, card_value as usize);
+        self.traversal_state.move_to(to_node_idx, card_idx);
 
         Ok(())
     }
@@ -106,13 +122,14 @@ where
     pub(crate) fn record_action(
         &mut self,
         game_state: &GameState,
-        action: AgentAction,
-        player_idx: usize,
+        payload: &PlayedActionPayload,
     ) -> Result<(), HistorianError> {
-        let action_idx = self.action_generator.action_to_idx(game_state, &action);
+        let action_idx = self
+            .action_generator
+            .played_action_to_idx(game_state, payload);
         let to_node_idx = self.ensure_target_node(NodeData::Player(PlayerData {
             regret_matcher: Option::default(),
-            player_idx,
+            player_idx: payload.idx,
         }))?;
         self.traversal_state.move_to(to_node_idx, action_idx);
         Ok(())
@@ -132,7 +149,7 @@ where
         // For terminal nodes we will never have a child so we repurpose
         // the child visited counter.
         node.increment_count(0);
-        if let NodeData::Terminal(td) = &mut node.data {
+        if let NodeData::Terminal(td) = &mut *node.data {
             td.total_utility += reward;
             Ok(())
         } else {
@@ -163,6 +180,12 @@ where
             // Rather than use award since it can be for a side pot we use the final award ammount
             // in the terminal node.
             Action::Award(_) => Ok(()),
+            // What's shown at showdown can't change the outcome either.
+            Action::ShowHand(_) => Ok(()),
+            // The tree only knows hold'em, it has nowhere to put a draw.
+            Action::Draw(_) => Err(HistorianError::CFRUnexpectedNode(
+                "draws aren't part of the tree".to_string(),
+            )),
             Action::DealStartingHand(payload) => {
                 // We only record our own hand
                 // so the state can be shared between simulation runs.
@@ -172,14 +195,10 @@ where
                     Ok(())
                 }
             }
-            Action::PlayedAction(payload) => {
-                self.record_action(game_state, payload.action, payload.idx)
+            Action::PlayedAction(payload) => self.record_action(game_state, &payload),
+            Action::FailedAction(failed_action_payload) => {
+                self.record_action(game_state, &failed_action_payload.result)
             }
-            Action::FailedAction(failed_action_payload) => self.record_action(
-                game_state,
-                failed_action_payload.result.action,
-                failed_action_payload.result.idx,
-            ),
             Action::DealCommunity(card) => self.record_card(game_state, card),
         }
     }
@@ -0,0 +1,315 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use ndarray::ArrayView1;
+use serde::{Deserialize, Serialize};
+
+use super::{CFRState, NodeData, RegretMatcher, RegretUpdate, StateStore};
+
+/// What a player knows at a decision: who they are, the actions taken on
+/// each street so far, and the bucket their cards are in now.
+///
+/// The bucket is the chance child of the last card dealt. With a bucketing
+/// `CardAbstraction` such as `EquityAbstraction` that's the bucket of the
+/// hand as it stands, and the buckets it was in on earlier streets are
+/// forgotten. That's imperfect recall: hands that took different routes to
+/// the same bucket, with the same betting, share one decision. With an
+/// abstraction that keeps every card distinct the last card says little
+/// about the hand, so keying by infoset only makes sense with bucketing.
+///
+/// Unlike a node index, an infoset doesn't depend on the order the tree was
+/// grown in, so it names the same decision in any tree built with the same
+/// action generator.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct InfoSet {
+    pub player_idx: usize,
+    /// The child index of every action, one list per street. A new street
+    /// starts with the cards dealt after some betting.
+    pub betting: Vec<Vec<usize>>,
+    /// The chance child of the last card dealt, if any were.
+    pub bucket: Option<usize>,
+}
+
+impl InfoSet {
+    /// The infoset of the decision at `node_idx`, read from the path down
+    /// to it. `None` if it isn't a player node.
+    pub fn from_node(cfr_state: &CFRState, node_idx: usize) -> Option<Self> {
+        let player_idx = match &*cfr_state.get(node_idx)?.data {
+            NodeData::Player(player_data) => player_data.player_idx,
+            _ => return None,
+        };
+
+        // Walk up to the root collecting the kind of each parent and the
+        // child taken from it.
+        let mut path = Vec::new();
+        let mut idx = node_idx;
+        loop {
+            let node = cfr_state.get(idx)?;
+            let (Some(parent), Some(child_idx)) = (node.parent, node.parent_child_idx) else {
+                break;
+            };
+            let is_chance = cfr_state.get(parent)?.data.is_chance();
+            path.push((is_chance, child_idx));
+            idx = parent;
+        }
+
+        let mut betting = vec![Vec::new()];
+        let mut bucket = None;
+        for (parent_idx, (is_chance, child_idx)) in path.into_iter().rev().enumerate() {
+            // The root's only child is where play starts.
+            if parent_idx == 0 {
+                continue;
+            }
+            if is_chance {
+                if !betting.last().is_some_and(Vec::is_empty) {
+                    betting.push(Vec::new());
+                }
+                bucket = Some(child_idx);
+            } else {
+                betting.last_mut().unwrap().push(child_idx);
+            }
+        }
+        if betting.len() > 1 && betting.last().is_some_and(Vec::is_empty) {
+            betting.pop();
+        }
+
+        Some(Self {
+            player_idx,
+            betting,
+            bucket,
+        })
+    }
+}
+
+/// Regret matchers shared by every decision in the same `InfoSet`.
+///
+/// Each path through a tree gets its own node, and each node its own
+/// regret matcher, so decisions that play the same learn separately and
+/// each gets only the samples that reached it. With a table passed to
+/// `CFRAgent::with_infosets` every update goes to the infoset's matcher
+/// instead, and the node keeps a copy of it so the rest of the tree code
+/// reads it as before. One table serves every player.
+///
+/// Clones share the same matchers. Nodes only get a fresh copy when
+/// they're played through, so call `write_to` before reading a strategy
+/// out of the trees, and `from_state_store` to carry on training a store
+/// that was saved.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::cfr::{EquityAbstraction, HeadsUpLimitConfig, InfoSetTable, StateStore};
+///
+/// let mut config = HeadsUpLimitConfig::default();
+/// config.infosets = Some(InfoSetTable::new());
+/// let mut state_store = StateStore::new();
+/// config.train::<EquityAbstraction, _>(&mut state_store, 1, &mut rand::rng());
+///
+/// let table = config.infosets.unwrap();
+/// assert!(!table.is_empty());
+/// table.write_to(&state_store);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InfoSetTable {
+    matchers: Rc<RefCell<HashMap<InfoSet, RegretMatcher>>>,
+}
+
+impl InfoSetTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A table of the matchers already in `state_store`'s trees. Where
+    /// several nodes share an infoset the first one found is kept, which
+    /// after `write_to` are all the same.
+    pub fn from_state_store(state_store: &StateStore) -> Self {
+        let table = Self::new();
+        for (infoset, matcher) in state_store_matchers(state_store) {
+            table
+                .matchers
+                .borrow_mut()
+                .entry(infoset)
+                .or_insert(matcher);
+        }
+        table
+    }
+
+    /// How many infosets have a matcher.
+    pub fn len(&self) -> usize {
+        self.matchers.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matchers.borrow().is_empty()
+    }
+
+    /// A copy of the matcher for `infoset`.
+    pub fn get(&self, infoset: &InfoSet) -> Option<RegretMatcher> {
+        self.matchers.borrow().get(infoset).cloned()
+    }
+
+    /// Copy every shared matcher into the nodes of `state_store`'s trees
+    /// that are in its infoset, so the trees hold what was learned. Nodes
+    /// that were never played through are left without one.
+    pub fn write_to(&self, state_store: &StateStore) {
+        let matchers = self.matchers.borrow();
+        for player_idx in 0..state_store.len() {
+            let Some(mut cfr_state) = state_store.get_state(player_idx) else {
+                continue;
+            };
+            let num_nodes = cfr_state.internal_state().borrow().nodes.len();
+            for node_idx in 0..num_nodes {
+                let Some(matcher) =
+                    InfoSet::from_node(&cfr_state, node_idx).and_then(|i| matchers.get(&i))
+                else {
+                    continue;
+                };
+                let mut node = cfr_state.get_mut(node_idx).unwrap();
+                if let NodeData::Player(player_data) = &mut *node.data
+                    && let Some(node_matcher) = &mut player_data.regret_matcher
+                    && node_matcher.num_actions() == matcher.num_actions()
+                {
+                    **node_matcher = matcher.clone();
+                }
+            }
+        }
+    }
+
+    /// The matcher for `infoset`, starting it from `new` if there isn't one
+    /// yet. `None` if the one there has a different number of actions, in
+    /// which case the decision can't share it.
+    pub(crate) fn get_or_insert(
+        &self,
+        infoset: &InfoSet,
+        num_actions: usize,
+        new: impl FnOnce() -> RegretMatcher,
+    ) -> Option<RegretMatcher> {
+        let mut matchers = self.matchers.borrow_mut();
+        let matcher = matchers.entry(infoset.clone()).or_insert_with(new);
+        (matcher.num_actions() == num_actions).then(|| matcher.clone())
+    }
+
+    /// Update the matcher for `infoset` with `rewards` and return a copy of
+    /// it, or `None` if there's no matcher it fits.
+    pub(crate) fn update(
+        &self,
+        infoset: &InfoSet,
+        rewards: ArrayView1<f32>,
+        regret_update: RegretUpdate,
+    ) -> Option<RegretMatcher> {
+        let mut matchers = self.matchers.borrow_mut();
+        let matcher = matchers
+            .get_mut(infoset)
+            .filter(|matcher| matcher.num_actions() == rewards.len())?;
+        matcher.update_regret_with(rewards, regret_update).ok()?;
+        Some(matcher.clone())
+    }
+}
+
+/// Every player node with a regret matcher in `state_store`, with its
+/// infoset.
+fn state_store_matchers(state_store: &StateStore) -> Vec<(InfoSet, RegretMatcher)> {
+    let mut found = Vec::new();
+    for player_idx in 0..state_store.len() {
+        let Some(cfr_state) = state_store.get_state(player_idx) else {
+            continue;
+        };
+        let num_nodes = cfr_state.internal_state().borrow().nodes.len();
+        for node_idx in 0..num_nodes {
+            let Some(infoset) = InfoSet::from_node(&cfr_state, node_idx) else {
+                continue;
+            };
+            if let NodeData::Player(player_data) = &*cfr_state.get(node_idx).unwrap().data
+                && let Some(matcher) = &player_data.regret_matcher
+            {
+                found.push((infoset, (**matcher).clone()));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use crate::arena::GameState;
+    use crate::arena::cfr::PlayerData;
+
+    use super::*;
+
+    fn player(player_idx: usize) -> NodeData {
+        NodeData::Player(PlayerData {
+            regret_matcher: None,
+            player_idx,
+        })
+    }
+
+    #[test]
+    fn test_from_node() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut cfr_state = CFRState::new(game_state);
+
+        // Two ways of dealing into bucket 3, then the same betting.
+        let hole = cfr_state.add(0, 0, NodeData::Chance);
+        let mut decisions = Vec::new();
+        for first in [1, 2] {
+            let dealt = cfr_state.add(hole, first, NodeData::Chance);
+            let small_blind = cfr_state.add(dealt, 3, player(0));
+            let big_blind = cfr_state.add(small_blind, 1, player(1));
+            let flop = cfr_state.add(big_blind, 1, NodeData::Chance);
+            decisions.push(cfr_state.add(flop, 7, player(1)));
+        }
+
+        let infoset = InfoSet::from_node(&cfr_state, decisions[0]).unwrap();
+        assert_eq!(
+            InfoSet {
+                player_idx: 1,
+                betting: vec![vec![1, 1]],
+                bucket: Some(7),
+            },
+            infoset
+        );
+        assert_eq!(Some(infoset), InfoSet::from_node(&cfr_state, decisions[1]));
+        assert_eq!(None, InfoSet::from_node(&cfr_state, 0));
+    }
+
+    #[test]
+    fn test_shared_updates() {
+        let table = InfoSetTable::new();
+        let infoset = InfoSet {
+            player_idx: 0,
+            betting: vec![vec![]],
+            bucket: None,
+        };
+        assert!(
+            table
+                .get_or_insert(&infoset, 3, || RegretMatcher::new(3).unwrap())
+                .is_some()
+        );
+        // A decision with a different number of actions can't share it.
+        assert!(
+            table
+                .get_or_insert(&infoset, 2, || RegretMatcher::new(2).unwrap())
+                .is_none()
+        );
+        assert!(
+            table
+                .update(&infoset, array![0.0, 1.0].view(), RegretUpdate::Vanilla)
+                .is_none()
+        );
+
+        for _ in 0..2 {
+            table
+                .update(
+                    &infoset,
+                    array![0.0, 1.0, 0.0].view(),
+                    RegretUpdate::Vanilla,
+                )
+                .unwrap();
+        }
+        assert_eq!(1, table.len());
+        assert_eq!(2, table.get(&infoset).unwrap().num_updates());
+    }
+}
@@ -0,0 +1,522 @@
+//! Estimate how exploitable a strategy is with local best response.
+//!
+//! An exact best response has to walk every decision of the game, which
+//! is out of reach for anything bigger than a toy abstraction. Local best
+//! response (LBR) plays hands against the strategy instead. It keeps track
+//! of the range the strategy could hold, weighting every hand by how likely
+//! the strategy was to take the actions it took holding it, and at each of
+//! its own decisions picks the action that wins the most assuming the hand
+//! is checked down after it. A bet is valued with how often the range folds
+//! to it, read from the strategy, and the range's equity when it doesn't.
+//!
+//! LBR only looks one action ahead, so it's not a best response and what it
+//! wins is a lower bound on the exploitability. It's also an average over
+//! sampled hands, reported with its standard error.
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use rand::Rng;
+use rand::seq::index::sample;
+
+use crate::arena::action::AgentAction;
+use crate::arena::errors::LbrError;
+use crate::arena::{Agent, GameState, HoldemSimulationBuilder};
+use crate::core::{Card, CardSet, Hand, Rankable};
+use crate::holdem::Range;
+
+use super::{ActionGenerator, BasicCFRActionGenerator, CFRState, StrategyProfile, TraversalState};
+
+/// How long to run a [`LocalBestResponse`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LbrConfig {
+    /// Hands to play, with LBR switching seats every hand.
+    pub hands: usize,
+    /// Hands from the range and runouts to sample for each equity estimate.
+    pub equity_samples: usize,
+}
+
+impl Default for LbrConfig {
+    fn default() -> Self {
+        Self {
+            hands: 1_000,
+            equity_samples: 200,
+        }
+    }
+}
+
+/// What LBR won against a strategy, in thousandths of a big blind per hand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LbrEstimate {
+    pub hands: usize,
+    /// The average win, a lower bound on the exploitability.
+    pub mbb_per_hand: f32,
+    /// The standard error of the average.
+    pub std_error: f32,
+}
+
+impl fmt::Display for LbrEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1} ± {:.1} mbb/hand over {} hands",
+            self.mbb_per_hand, self.std_error, self.hands
+        )
+    }
+}
+
+/// Plays local best response against a heads up [`StrategyProfile`]. See
+/// the module docs.
+///
+/// `T` is the `ActionGenerator` the profile was trained with. It finds the
+/// strategy's decisions in the profile the same way the `CFRHistorian`
+/// built them, and gives LBR its actions, so LBR only bets the sizes the
+/// abstraction knows about. Decisions the profile has no strategy for are
+/// played uniformly at random.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::{
+///     BasicCFRActionGenerator, LbrConfig, LocalBestResponse, StrategyProfile,
+/// };
+///
+/// // A strategy with nothing in it plays every action as often, which
+/// // throws money away.
+/// let lbr = LocalBestResponse::<BasicCFRActionGenerator>::new(
+///     StrategyProfile::new(),
+///     LbrConfig {
+///         hands: 20,
+///         equity_samples: 50,
+///     },
+/// );
+/// let game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+/// let estimate = lbr.estimate(&game_state).unwrap();
+/// assert_eq!(20, estimate.hands);
+/// assert!(estimate.std_error > 0.0);
+/// ```
+pub struct LocalBestResponse<T: ActionGenerator = BasicCFRActionGenerator> {
+    profile: Rc<StrategyProfile>,
+    config: LbrConfig,
+    action_generator: PhantomData<T>,
+}
+
+impl<T: ActionGenerator + 'static> LocalBestResponse<T> {
+    pub fn new(profile: StrategyProfile, config: LbrConfig) -> Self {
+        Self {
+            profile: Rc::new(profile),
+            config,
+            action_generator: PhantomData,
+        }
+    }
+
+    /// Play `config.hands` hands from `game_state` and average what LBR
+    /// won. LBR sits in seat zero for the even hands and seat one for the
+    /// odd ones.
+    pub fn estimate(&self, game_state: &GameState) -> Result<LbrEstimate, LbrError> {
+        if game_state.num_players != 2 {
+            return Err(LbrError::NotHeadsUp(game_state.num_players));
+        }
+        if game_state.is_complete() {
+            return Err(LbrError::Complete);
+        }
+
+        let mut rng = crate::core::rng();
+        let wins: Vec<f64> = (0..self.config.hands)
+            .map(|hand| {
+                let chips = self.play_hand(game_state, hand % 2, &mut rng);
+                f64::from(chips) / f64::from(game_state.big_blind) * 1000.0
+            })
+            .collect();
+
+        let n = wins.len().max(1) as f64;
+        let mean = wins.iter().sum::<f64>() / n;
+        let variance = wins.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+        Ok(LbrEstimate {
+            hands: wins.len(),
+            mbb_per_hand: mean as f32,
+            std_error: (variance / n).sqrt() as f32,
+        })
+    }
+
+    /// Play one hand with LBR in seat `lbr_idx`, returning the chips it won.
+    fn play_hand<R: Rng>(&self, game_state: &GameState, lbr_idx: usize, rng: &mut R) -> f32 {
+        let opponent = 1 - lbr_idx;
+        let tracker = Rc::new(RefCell::new(Tracker::<T> {
+            profile: self.profile.clone(),
+            generators: [0, 1].map(|idx| {
+                T::new(
+                    CFRState::new(game_state.clone()),
+                    TraversalState::new_root(idx),
+                )
+            }),
+            lbr_idx,
+            opponent,
+            steps: Vec::new(),
+            board_len: game_state.board.len(),
+            range: Range::full(),
+            equity_samples: self.config.equity_samples,
+        }));
+        let mut agents: Vec<Box<dyn Agent>> = Vec::with_capacity(2);
+        for idx in 0..2 {
+            agents.push(Box::new(TrackedAgent {
+                tracker: tracker.clone(),
+                is_lbr: idx == lbr_idx,
+            }));
+        }
+
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state.clone())
+            .agents(agents)
+            .build()
+            .unwrap();
+        sim.run(rng);
+        sim.game_state.stacks[lbr_idx] - game_state.stacks[lbr_idx]
+    }
+}
+
+/// A step of the strategy's path through its tree after its hole cards.
+enum Step {
+    Action(usize),
+    Card(Card),
+}
+
+/// What both players have seen of the hand and what LBR thinks the
+/// strategy holds.
+struct Tracker<T: ActionGenerator> {
+    profile: Rc<StrategyProfile>,
+    generators: [T; 2],
+    lbr_idx: usize,
+    opponent: usize,
+    steps: Vec<Step>,
+    board_len: usize,
+    /// How likely the strategy is to hold each hand.
+    range: Range,
+    equity_samples: usize,
+}
+
+impl<T: ActionGenerator> Tracker<T> {
+    /// Catch up with community cards dealt since the last decision, and
+    /// take the cards LBR can see out of the range.
+    fn sync(&mut self, game_state: &GameState) {
+        for card in &game_state.board[self.board_len.min(game_state.board.len())..] {
+            self.steps.push(Step::Card(*card));
+        }
+        self.board_len = game_state.board.len();
+        self.range
+            .remove_cards(CardSet::from(game_state.hands[self.lbr_idx]));
+    }
+
+    /// The strategy's path to the decision it's at, if it held `hole`.
+    fn path(&self, game_state: &GameState, hole: (Card, Card)) -> Vec<usize> {
+        let generator = &self.generators[self.opponent];
+        let mut path = vec![0];
+        let mut known = CardSet::new();
+        let mut deal = |path: &mut Vec<usize>, card: Card| {
+            path.push(generator.card_to_idx(game_state, known, card));
+            known.insert(card);
+        };
+        // Hole cards are dealt sorted.
+        deal(&mut path, hole.0.min(hole.1));
+        deal(&mut path, hole.0.max(hole.1));
+        for step in &self.steps {
+            match step {
+                Step::Action(idx) => path.push(*idx),
+                Step::Card(card) => deal(&mut path, *card),
+            }
+        }
+        path
+    }
+
+    /// How often the strategy takes each of `possible` holding `hole`.
+    fn strategy(
+        &self,
+        game_state: &GameState,
+        possible: &[AgentAction],
+        hole: (Card, Card),
+    ) -> Vec<f32> {
+        let generator = &self.generators[self.opponent];
+        let uniform = vec![1.0 / possible.len() as f32; possible.len()];
+        let Some(probabilities) = self
+            .profile
+            .get(self.opponent, &self.path(game_state, hole))
+        else {
+            return uniform;
+        };
+        let strategy: Vec<f32> = possible
+            .iter()
+            .map(|action| {
+                probabilities
+                    .get(generator.action_to_idx(game_state, action))
+                    .copied()
+                    .unwrap_or(0.0)
+            })
+            .collect();
+        let total: f32 = strategy.iter().sum();
+        if total > 0.0 {
+            strategy.into_iter().map(|p| p / total).collect()
+        } else {
+            uniform
+        }
+    }
+
+    /// Move past `action`, and if the strategy took it weight its range
+    /// by how likely it was to.
+    fn observe(&mut self, game_state: &GameState, action: &AgentAction) {
+        let idx = game_state.to_act_idx();
+        let generator = &self.generators[idx];
+        let action_idx = generator.action_to_idx(game_state, action);
+        if idx == self.opponent {
+            let possible = generator.gen_possible_actions(game_state);
+            let position = possible
+                .iter()
+                .position(|a| generator.action_to_idx(game_state, a) == action_idx);
+            let combos: Vec<(Card, Card, f32)> = self.range.combos().collect();
+            for (first, second, weight) in combos {
+                let likelihood = position.map_or(0.0, |position| {
+                    self.strategy(game_state, &possible, (first, second))[position]
+                });
+                self.range.set_weight(first, second, weight * likelihood);
+            }
+        }
+        self.steps.push(Step::Action(action_idx));
+    }
+
+    /// Sample an action from the strategy for the cards it really holds.
+    fn strategy_action(&self, game_state: &GameState) -> AgentAction {
+        let possible = self.generators[self.opponent].gen_possible_actions(game_state);
+        let hole: Vec<Card> = (CardSet::from(game_state.hands[self.opponent])
+            - game_state.board_set())
+        .into_iter()
+        .collect();
+        let strategy = self.strategy(game_state, &possible, (hole[0], hole[1]));
+
+        let mut target = crate::core::rng().random_range(0.0..1.0_f32);
+        for (action, probability) in possible.iter().zip(&strategy) {
+            if target < *probability {
+                return action.clone();
+            }
+            target -= probability;
+        }
+        // Rounding can leave a sliver past the last action.
+        possible.last().unwrap_or(&AgentAction::Fold).clone()
+    }
+
+    /// The action that wins LBR the most if the hand is checked down after
+    /// it. Ties go to the more passive action.
+    fn lbr_action(&mut self, game_state: &GameState) -> AgentAction {
+        let idx = self.lbr_idx;
+        let equity = self.equity(game_state);
+        let legal = game_state.legal_actions();
+        let stack = game_state.stacks[idx];
+        let player_bet = game_state.current_round_player_bet(idx);
+        let to_call = (legal.call - player_bet).clamp(0.0, stack);
+        let pot = game_state.total_pot;
+
+        let mut best: Option<(AgentAction, f32)> = None;
+        for action in self.generators[idx].gen_possible_actions(game_state) {
+            let put_in = match action {
+                AgentAction::Fold => 0.0,
+                AgentAction::Bet(amount) => (amount - player_bet).clamp(to_call, stack),
+                AgentAction::AllIn => stack,
+            };
+            let ev = if action == AgentAction::Fold {
+                0.0
+            } else if put_in <= to_call {
+                equity * (pot + to_call) - to_call
+            } else {
+                let fold = self.fold_probability(game_state, &action);
+                let called = (put_in - to_call).min(game_state.stacks[self.opponent]);
+                fold * pot + (1.0 - fold) * (equity * (pot + put_in + called) - put_in)
+            };
+            if best.as_ref().is_none_or(|(_, best_ev)| ev > *best_ev) {
+                best = Some((action, ev));
+            }
+        }
+        best.map_or(AgentAction::Fold, |(action, _)| action)
+    }
+
+    /// How likely the range is to fold if LBR plays `action`.
+    fn fold_probability(&mut self, game_state: &GameState, action: &AgentAction) -> f32 {
+        let mut after = game_state.clone();
+        let bet = match action {
+            AgentAction::Bet(amount) => *amount,
+            _ => {
+                game_state.current_round_player_bet(self.lbr_idx) + game_state.stacks[self.lbr_idx]
+            }
+        };
+        if after.do_bet(bet, false).is_err()
+            || after.is_complete()
+            || after.to_act_idx() != self.opponent
+            || !after.round_data.needs_action.get(self.opponent)
+        {
+            return 0.0;
+        }
+        let possible = self.generators[self.opponent].gen_possible_actions(&after);
+        let Some(fold) = possible.iter().position(|a| *a == AgentAction::Fold) else {
+            return 0.0;
+        };
+
+        let action_idx = self.generators[self.lbr_idx].action_to_idx(game_state, action);
+        self.steps.push(Step::Action(action_idx));
+        let (folds, total) =
+            self.range
+                .combos()
+                .fold((0.0, 0.0), |(folds, total), (first, second, weight)| {
+                    let strategy = self.strategy(&after, &possible, (first, second));
+                    (folds + weight * strategy[fold], total + weight)
+                });
+        self.steps.pop();
+        if total > 0.0 { folds / total } else { 0.0 }
+    }
+
+    /// LBR's share of the pot at showdown against the range.
+    fn equity(&self, game_state: &GameState) -> f32 {
+        let board = game_state.board_set();
+        let hole = CardSet::from(game_state.hands[self.lbr_idx]) - board;
+        let dead = board | hole;
+        let combos: Vec<(Card, Card, f32)> = self
+            .range
+            .combos()
+            .filter(|(first, second, _)| !dead.contains(*first) && !dead.contains(*second))
+            .collect();
+        let total: f32 = combos.iter().map(|(_, _, weight)| weight).sum();
+        if total <= 0.0 {
+            return 0.5;
+        }
+        let cumulative: Vec<f32> = combos
+            .iter()
+            .scan(0.0, |sum, (_, _, weight)| {
+                *sum += weight;
+                Some(*sum)
+            })
+            .collect();
+
+        let remaining: Vec<Card> = (!dead).into_iter().collect();
+        let num_board = 5_usize.saturating_sub(game_state.board.len());
+        let samples = self.equity_samples.max(1);
+        let mut rng = crate::core::rng();
+        let mut won = 0.0;
+        for _ in 0..samples {
+            let target = rng.random_range(0.0..total);
+            let pick = cumulative
+                .partition_point(|sum| *sum <= target)
+                .min(combos.len() - 1);
+            let (first, second, _) = combos[pick];
+            // Two extra cards in case the range's hand is dealt.
+            let mut runout = board;
+            sample(&mut rng, remaining.len(), num_board + 2)
+                .into_iter()
+                .map(|i| remaining[i])
+                .filter(|card| *card != first && *card != second)
+                .take(num_board)
+                .for_each(|card| runout.insert(card));
+
+            let mut theirs = runout;
+            theirs.insert(first);
+            theirs.insert(second);
+            let ours = Hand::from(runout | hole).rank();
+            let theirs = Hand::from(theirs).rank();
+            won += match ours.cmp(&theirs) {
+                std::cmp::Ordering::Greater => 1.0,
+                std::cmp::Ordering::Equal => 0.5,
+                std::cmp::Ordering::Less => 0.0,
+            };
+        }
+        won / samples as f32
+    }
+}
+
+/// Plays either LBR or the strategy, keeping the shared tracker up to date.
+struct TrackedAgent<T: ActionGenerator> {
+    tracker: Rc<RefCell<Tracker<T>>>,
+    is_lbr: bool,
+}
+
+impl<T: ActionGenerator> Agent for TrackedAgent<T> {
+    fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+        let mut tracker = self.tracker.borrow_mut();
+        tracker.sync(game_state);
+        let action = if self.is_lbr {
+            tracker.lbr_action(game_state)
+        } else {
+            tracker.strategy_action(game_state)
+        };
+        tracker.observe(game_state, &action);
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_errors() {
+        let lbr = LocalBestResponse::<BasicCFRActionGenerator>::new(
+            StrategyProfile::new(),
+            LbrConfig::default(),
+        );
+        let game_state = GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.0, 0);
+        assert_eq!(Err(LbrError::NotHeadsUp(3)), lbr.estimate(&game_state));
+    }
+
+    #[test]
+    fn test_range_follows_the_strategy() {
+        // The strategy only goes all in preflop with aces.
+        let mut profile = StrategyProfile::new();
+        let aces = (Card::try_from("Ac").unwrap(), Card::try_from("Ad").unwrap());
+        let kings = (Card::try_from("Kc").unwrap(), Card::try_from("Kd").unwrap());
+        let game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut tracker = Tracker::<BasicCFRActionGenerator> {
+            profile: Rc::new(StrategyProfile::new()),
+            generators: [0, 1].map(|idx| {
+                BasicCFRActionGenerator::new(
+                    CFRState::new(game_state.clone()),
+                    TraversalState::new_root(idx),
+                )
+            }),
+            lbr_idx: 1 - game_state.to_act_idx(),
+            opponent: game_state.to_act_idx(),
+            steps: Vec::new(),
+            board_len: 0,
+            range: Range::full(),
+            equity_samples: 10,
+        };
+        let opponent = tracker.opponent;
+        for (first, second, _) in Range::full().combos() {
+            let all_in = if (first, second) == aces {
+                vec![0.0, 0.0, 1.0]
+            } else {
+                vec![0.0, 1.0, 0.0]
+            };
+            profile.insert(opponent, tracker.path(&game_state, (first, second)), all_in);
+        }
+        tracker.profile = Rc::new(profile);
+
+        tracker.observe(&game_state, &AgentAction::AllIn);
+        assert_eq!(1, tracker.range.combos().count());
+        assert_eq!(1.0, tracker.range.weight(aces.0, aces.1));
+        assert_eq!(0.0, tracker.range.weight(kings.0, kings.1));
+    }
+
+    #[test]
+    fn test_beats_random_play() {
+        let lbr = LocalBestResponse::<BasicCFRActionGenerator>::new(
+            StrategyProfile::new(),
+            LbrConfig {
+                hands: 4000,
+                equity_samples: 50,
+            },
+        );
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let estimate = crate::core::with_seed(42, || lbr.estimate(&game_state)).unwrap();
+        assert_eq!(4000, estimate.hands);
+        assert!(
+            estimate.mbb_per_hand > 2.0 * estimate.std_error,
+            "{estimate}"
+        );
+    }
+}
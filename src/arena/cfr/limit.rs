@@ -0,0 +1,449 @@
+use std::ops::ControlFlow;
+
+use rand::Rng;
+
+use crate::arena::action::{AgentAction, PlayedActionPayload};
+use crate::arena::game_state::Round;
+use crate::arena::{Agent, GameState, HoldemSimulationBuilder};
+use crate::core::{Card, CardSet};
+
+use super::action_generator::choose_action;
+use super::callback::{finish_iteration, tree_sizes};
+use super::{
+    ActionGenerator, CFRAgent, CFRConfig, CFRState, CardAbstraction, InfoSetTable,
+    NoCardAbstraction, PerRoundFixedGameStateIteratorGen, StateStore, TrainingCallback,
+    TraversalState, WarmStart,
+};
+
+/// Generates the actions of fixed limit hold'em: fold, check or call, and
+/// bet or raise by the fixed amount.
+///
+/// Bets are one big blind preflop and on the flop and two on the turn and
+/// river. Each round is capped at four bets, with the big blind counting as
+/// the first one preflop. Children are fold at 0, call at 1 and raise at 2,
+/// and cards are mapped to chance nodes by `C`.
+pub struct LimitCFRActionGenerator<C: CardAbstraction = NoCardAbstraction> {
+    cfr_state: CFRState,
+    traversal_state: TraversalState,
+    card_abstraction: C,
+}
+
+impl<C: CardAbstraction> LimitCFRActionGenerator<C> {
+    /// The most raises allowed in the current round.
+    fn max_raises(game_state: &GameState) -> u8 {
+        if game_state.round == Round::Preflop {
+            3
+        } else {
+            4
+        }
+    }
+
+    /// The size of a bet or raise in the current round.
+    pub fn bet_size(game_state: &GameState) -> f32 {
+        match game_state.round {
+            Round::Preflop | Round::Flop => game_state.big_blind,
+            _ => 2.0 * game_state.big_blind,
+        }
+    }
+
+    fn write_possible_actions<'a>(
+        game_state: &GameState,
+        buf: &'a mut [AgentAction; 3],
+    ) -> &'a [AgentAction] {
+        let legal = game_state.legal_actions();
+        let mut len = 0;
+        if legal.can_fold {
+            buf[len] = AgentAction::Fold;
+            len += 1;
+        }
+        buf[len] = AgentAction::Bet(legal.call);
+        len += 1;
+        if let Some((_, all_in)) = legal.raise
+            && game_state.round_data.total_raise_count < Self::max_raises(game_state)
+        {
+            buf[len] = AgentAction::Bet((legal.call + Self::bet_size(game_state)).min(all_in));
+            len += 1;
+        }
+        &buf[..len]
+    }
+}
+
+impl<C: CardAbstraction> ActionGenerator for LimitCFRActionGenerator<C> {
+    fn new(cfr_state: CFRState, traversal_state: TraversalState) -> Self {
+        Self {
+            cfr_state,
+            traversal_state,
+            card_abstraction: C::default(),
+        }
+    }
+
+    fn action_to_idx(&self, game_state: &GameState, action: &AgentAction) -> usize {
+        let to_call = game_state.current_round_bet();
+        match action {
+            AgentAction::Fold => 0,
+            AgentAction::Bet(amount) if *amount <= to_call => 1,
+            AgentAction::Bet(_) => 2,
+            AgentAction::AllIn => {
+                let all_in = game_state.current_round_current_player_bet()
+                    + game_state.current_player_stack();
+                if all_in <= to_call { 1 } else { 2 }
+            }
+        }
+    }
+
+    fn played_action_to_idx(
+        &self,
+        _game_state: &GameState,
+        payload: &PlayedActionPayload,
+    ) -> usize {
+        match payload.action {
+            AgentAction::Fold => 0,
+            _ if payload.final_bet > payload.starting_bet => 2,
+            _ => 1,
+        }
+    }
+
+    fn num_potential_actions(&self, _game_state: &GameState) -> usize {
+        3
+    }
+
+    fn gen_possible_actions(&self, game_state: &GameState) -> Vec<AgentAction> {
+        let mut buf = [const { AgentAction::Fold }; 3];
+        Self::write_possible_actions(game_state, &mut buf).to_vec()
+    }
+
+    fn gen_action(&self, game_state: &GameState) -> AgentAction {
+        let mut buf = [const { AgentAction::Fold }; 3];
+        let possible = Self::write_possible_actions(game_state, &mut buf);
+        choose_action(
+            self,
+            &self.cfr_state,
+            &self.traversal_state,
+            game_state,
+            possible,
+        )
+    }
+
+    fn card_to_idx(&self, game_state: &GameState, known: CardSet, card: Card) -> usize {
+        self.card_abstraction.card_to_idx(game_state, known, card)
+    }
+}
+
+/// A ready made setup for solving heads up limit hold'em, the usual
+/// benchmark for a CFR implementation.
+///
+/// The actions are fixed by the rules, see `LimitCFRActionGenerator`, so the
+/// only choices left are how cards are abstracted, picked with the
+/// `CardAbstraction` type given to `train`, and how many hands are played
+/// out to value each action.
+///
+/// ```
+/// use rs_poker::arena::cfr::{HeadsUpLimitConfig, StateStore, SuitIsomorphism};
+///
+/// let config = HeadsUpLimitConfig::default();
+/// let mut state_store = StateStore::new();
+/// config.train::<SuitIsomorphism, _>(&mut state_store, 1, &mut rand::rng());
+/// assert_eq!(2, state_store.len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct HeadsUpLimitConfig {
+    /// The small bet, which is also the big blind. The small blind is half
+    /// of it and the big bet twice it.
+    pub small_bet: f32,
+    /// Each player's starting stack in small bets. A hand capped on every
+    /// street costs 24, so anything more than that is never all in.
+    pub stack_small_bets: f32,
+    /// How many hands each new decision plays out per action to value it.
+    pub hands_per_decision: PerRoundFixedGameStateIteratorGen,
+    /// The regret update and pruning, plain regret matching without
+    /// pruning by default.
+    pub cfr: CFRConfig,
+    /// A blueprint to start new decisions from, see `WarmStart`.
+    pub warm_start: Option<WarmStart>,
+    /// Regret matchers shared between decisions in the same infoset, see
+    /// `InfoSetTable`. Off by default.
+    pub infosets: Option<InfoSetTable>,
+}
+
+impl Default for HeadsUpLimitConfig {
+    fn default() -> Self {
+        Self {
+            small_bet: 2.0,
+            stack_small_bets: 50.0,
+            hands_per_decision: PerRoundFixedGameStateIteratorGen::new(1, 1, 1, 1),
+            cfr: CFRConfig::default(),
+            warm_start: None,
+            infosets: None,
+        }
+    }
+}
+
+impl HeadsUpLimitConfig {
+    /// A new hand with player 0 on the button, and so in the small blind.
+    /// The simulation posts the blinds and deals.
+    pub fn game_state(&self) -> GameState {
+        GameState::new_starting(
+            vec![self.stack_small_bets * self.small_bet; 2],
+            self.small_bet,
+            self.small_bet / 2.0,
+            0.0,
+            0,
+        )
+    }
+
+    /// Train on `hands` hands played from the start.
+    pub fn train<C: CardAbstraction + 'static, R: Rng>(
+        &self,
+        state_store: &mut StateStore,
+        hands: usize,
+        rng: &mut R,
+    ) {
+        self.train_from::<C, R>(state_store, &self.game_state(), hands, rng);
+    }
+
+    /// Train on `hands` hands played from `game_state`. An empty
+    /// `state_store` gets a tree for each player rooted at `game_state`,
+    /// otherwise its trees are trained further, so they have to have been
+    /// rooted at the same state.
+    pub fn train_from<C: CardAbstraction + 'static, R: Rng>(
+        &self,
+        state_store: &mut StateStore,
+        game_state: &GameState,
+        hands: usize,
+        rng: &mut R,
+    ) {
+        self.train_from_with::<C, R>(state_store, game_state, hands, rng, &mut ());
+    }
+
+    /// Train the same as `train_from`, telling `callback` about every hand
+    /// and every node added. Returns how many hands were trained on, which
+    /// is fewer than `hands` if the callback stopped early.
+    pub fn train_from_with<C: CardAbstraction + 'static, R: Rng>(
+        &self,
+        state_store: &mut StateStore,
+        game_state: &GameState,
+        hands: usize,
+        rng: &mut R,
+        callback: &mut impl TrainingCallback,
+    ) -> usize {
+        if state_store.is_empty() {
+            for player_idx in 0..2 {
+                state_store.new_state(game_state.clone(), player_idx);
+                state_store.pop_traversal(player_idx);
+            }
+        }
+        let mut sizes = tree_sizes(state_store);
+        let mut trained = 0;
+        for _ in 0..hands {
+            let agents: Vec<Box<dyn Agent>> = (0..2)
+                .map(|player_idx| {
+                    let (cfr_state, traversal_state) = state_store.push_traversal(player_idx);
+                    let mut agent = CFRAgent::<
+                        LimitCFRActionGenerator<C>,
+                        PerRoundFixedGameStateIteratorGen,
+                    >::new(
+                        state_store.clone(),
+                        cfr_state,
+                        traversal_state,
+                        self.hands_per_decision.clone(),
+                    )
+                    .with_config(self.cfr);
+                    if let Some(warm_start) = &self.warm_start {
+                        agent = agent.with_warm_start(warm_start.clone());
+                    }
+                    if let Some(infosets) = &self.infosets {
+                        agent = agent.with_infosets(infosets.clone());
+                    }
+                    Box::new(agent) as Box<dyn Agent>
+                })
+                .collect();
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(game_state.clone())
+                .agents(agents)
+                .build()
+                .unwrap();
+            sim.run(rng);
+            for player_idx in 0..2 {
+                state_store.pop_traversal(player_idx);
+                if let Some(warm_start) = &self.warm_start {
+                    warm_start.pop_traversal(player_idx);
+                }
+            }
+            #[cfg(feature = "metrics")]
+            crate::arena::metrics::record_iteration("heads_up_limit");
+            trained += 1;
+            if let ControlFlow::Break(()) =
+                finish_iteration(callback, state_store, &mut sizes, trained)
+            {
+                break;
+            }
+        }
+        #[cfg(feature = "metrics")]
+        crate::arena::metrics::record_state_store(state_store);
+        trained
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::cfr::{
+        EquityAbstraction, InfoSet, NodeData, RegretUpdate, ValueOnlyAbstraction,
+    };
+
+    type Generator = LimitCFRActionGenerator<NoCardAbstraction>;
+
+    fn actions(game_state: &GameState) -> Vec<AgentAction> {
+        let generator = Generator::new(
+            CFRState::new(game_state.clone()),
+            TraversalState::new_root(0),
+        );
+        generator.gen_possible_actions(game_state)
+    }
+
+    #[test]
+    fn test_preflop_raises_are_capped() {
+        let mut game_state = HeadsUpLimitConfig::default().game_state();
+        while game_state.round != Round::Preflop {
+            game_state.advance_round();
+        }
+        game_state.do_bet(1.0, true).unwrap();
+        game_state.do_bet(2.0, true).unwrap();
+
+        // The small blind can fold, call or raise to two small bets.
+        assert_eq!(
+            vec![
+                AgentAction::Fold,
+                AgentAction::Bet(2.0),
+                AgentAction::Bet(4.0)
+            ],
+            actions(&game_state)
+        );
+        game_state.do_bet(4.0, false).unwrap();
+        game_state.do_bet(6.0, false).unwrap();
+        game_state.do_bet(8.0, false).unwrap();
+        // That's four bets, so all that's left is calling.
+        assert_eq!(
+            vec![AgentAction::Fold, AgentAction::Bet(8.0)],
+            actions(&game_state)
+        );
+    }
+
+    #[test]
+    fn test_big_bets_on_the_turn() {
+        let mut game_state = HeadsUpLimitConfig::default().game_state();
+        while game_state.round != Round::Turn {
+            game_state.advance_round();
+        }
+        assert_eq!(
+            vec![AgentAction::Bet(0.0), AgentAction::Bet(4.0)],
+            actions(&game_state)
+        );
+        let generator = Generator::new(
+            CFRState::new(game_state.clone()),
+            TraversalState::new_root(0),
+        );
+        assert_eq!(
+            2,
+            generator.action_to_idx(&game_state, &AgentAction::Bet(4.0))
+        );
+        assert_eq!(
+            1,
+            generator.action_to_idx(&game_state, &AgentAction::Bet(0.0))
+        );
+    }
+
+    #[test]
+    fn test_train() {
+        let config = HeadsUpLimitConfig::default();
+        let mut state_store = StateStore::new();
+        let mut rng = rand::rng();
+        let num_nodes = |state_store: &StateStore| {
+            state_store
+                .get_state(0)
+                .unwrap()
+                .internal_state()
+                .borrow()
+                .nodes
+                .len()
+        };
+        config.train::<ValueOnlyAbstraction, _>(&mut state_store, 1, &mut rng);
+        let nodes = num_nodes(&state_store);
+        assert!(nodes > 1);
+
+        // Training again carries on with the same trees.
+        config.train::<ValueOnlyAbstraction, _>(&mut state_store, 1, &mut rng);
+        assert_eq!(2, state_store.len());
+        assert_eq!(1, state_store.traversal_len(0));
+        assert!(num_nodes(&state_store) >= nodes);
+    }
+
+    #[test]
+    fn test_train_dcfr() {
+        let config = HeadsUpLimitConfig {
+            cfr: CFRConfig {
+                regret_update: RegretUpdate::DCFR,
+                ..CFRConfig::default()
+            },
+            ..HeadsUpLimitConfig::default()
+        };
+        let mut state_store = StateStore::new();
+        config.train::<ValueOnlyAbstraction, _>(&mut state_store, 3, &mut rand::rng());
+
+        // The discounted updates leave every decision with a strategy.
+        let mut decisions = 0;
+        for player_idx in 0..2 {
+            let cfr_state = state_store.get_state(player_idx).unwrap();
+            let num_nodes = cfr_state.internal_state().borrow().nodes.len();
+            for node_idx in 0..num_nodes {
+                if let NodeData::Player(player_data) = &*cfr_state.get(node_idx).unwrap().data
+                    && let Some(matcher) = &player_data.regret_matcher
+                    && matcher.num_updates() > 0
+                {
+                    decisions += 1;
+                    let total: f32 = matcher.best_weight().iter().sum();
+                    assert!((total - 1.0).abs() < 1e-4, "{total}");
+                    assert!(matcher.regrets().iter().all(|r| r.is_finite()));
+                }
+            }
+        }
+        assert!(decisions > 0);
+    }
+
+    #[test]
+    fn test_train_shared_infosets() {
+        let config = HeadsUpLimitConfig {
+            infosets: Some(InfoSetTable::new()),
+            ..HeadsUpLimitConfig::default()
+        };
+        let mut state_store = StateStore::new();
+        config.train::<EquityAbstraction, _>(&mut state_store, 2, &mut rand::rng());
+        let table = config.infosets.unwrap();
+        table.write_to(&state_store);
+
+        // Every decision played holds its infoset's matcher.
+        let mut decisions = 0;
+        for player_idx in 0..2 {
+            let cfr_state = state_store.get_state(player_idx).unwrap();
+            let num_nodes = cfr_state.internal_state().borrow().nodes.len();
+            for node_idx in 0..num_nodes {
+                let Some(infoset) = InfoSet::from_node(&cfr_state, node_idx) else {
+                    continue;
+                };
+                if let NodeData::Player(player_data) = &*cfr_state.get(node_idx).unwrap().data
+                    && let Some(matcher) = &player_data.regret_matcher
+                {
+                    decisions += 1;
+                    let shared = table.get(&infoset).unwrap();
+                    assert_eq!(shared.regrets(), matcher.regrets());
+                }
+            }
+        }
+        assert!(!table.is_empty());
+        assert!(table.len() <= decisions);
+        assert_eq!(
+            table.len(),
+            InfoSetTable::from_state_store(&state_store).len()
+        );
+    }
+}
@@ -0,0 +1,921 @@
+//! A CFR tree kept in a memory mapped file.
+//!
+//! `NodeStore` keeps every node in memory, so a tree can't grow past RAM.
+//! `MmapNodeStore` keeps the same nodes in a file with one fixed size
+//! record per node, and the operating system pages in the records that
+//! are touched. Nodes are read and written by index, so a tree too big to
+//! load can still be walked, queried and trained one node at a time.
+//!
+//! The layout of a file is:
+//!
+//! - The magic bytes `RSNODES\0`, the record size as a little endian `u32`, a
+//!   `u32` version and a `u64` count of nodes.
+//! - One record per node, in index order. A record has the node's kind, links
+//!   and visit counts, and for player nodes the regret matcher's strategy,
+//!   strategy sums and rewards, each padded to `MAX_CHILDREN`.
+//!
+//! Unlike a `NodeStore`, where nodes can have any number of children, a
+//! record only has room for `MAX_CHILDREN`, and doesn't keep which actions
+//! are pruned.
+//!
+//! `MmapNodeStore` is a `NodeStorage`, so a tree made with
+//! `CFRState::with_storage` stays in the file while agents and trainers
+//! update it.
+//!
+//! The file grows by doubling, so it's usually bigger than the nodes in it.
+//! Everything is little endian, and files can be moved between machines.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::cfr::{MmapNodeStore, NodeData};
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! let path = dir.path().join("tree.nodes");
+//! let mut store = MmapNodeStore::create(&path).unwrap();
+//! let root = store.add(None, 0, &NodeData::Root).unwrap();
+//! let chance = store.add(Some(root), 0, &NodeData::Chance).unwrap();
+//! store.increment_count(root, 0);
+//! store.flush().unwrap();
+//!
+//! let store = MmapNodeStore::open(&path).unwrap();
+//! assert_eq!(2, store.len());
+//! assert_eq!(Some(chance), store.child(root, 0));
+//! assert_eq!(1, store.count(root, 0));
+//! ```
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+
+use memmap2::MmapMut;
+
+use crate::arena::errors::MmapNodeStoreError;
+
+use super::regret_matcher::RegretMatcherData;
+use super::{
+    LinkStorage, Node, NodeData, NodeStorage, NodeStore, PlayerData, RegretMatcher, TerminalData,
+};
+
+/// The most children a node can have in a `MmapNodeStore`, one per card.
+pub const MAX_CHILDREN: usize = 52;
+
+const MAGIC: &[u8; 8] = b"RSNODES\0";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 24;
+
+/// Stands in for a missing parent or child index.
+const NONE: u32 = u32::MAX;
+
+const KIND_ROOT: u8 = 0;
+const KIND_CHANCE: u8 = 1;
+const KIND_PLAYER: u8 = 2;
+const KIND_TERMINAL: u8 = 3;
+
+// Where each field is in a record.
+const KIND: usize = 0;
+const HAS_MATCHER: usize = 1;
+const NUM_ACTIONS: usize = 2;
+const PLAYER_IDX: usize = 4;
+const PARENT: usize = 8;
+const PARENT_CHILD_IDX: usize = 12;
+/// The terminal utility, or the matcher's cumulative reward.
+const VALUE: usize = 16;
+const NUM_UPDATES: usize = 24;
+const CHILDREN: usize = 32;
+const COUNTS: usize = CHILDREN + 4 * MAX_CHILDREN;
+const P: usize = COUNTS + 4 * MAX_CHILDREN;
+const SUM_P: usize = P + 4 * MAX_CHILDREN;
+const EXPERT_REWARD: usize = SUM_P + 4 * MAX_CHILDREN;
+
+/// The size of every node's record in bytes.
+pub const RECORD_LEN: usize = EXPERT_REWARD + 4 * MAX_CHILDREN;
+
+/// The nodes of a CFR tree in a memory mapped file.
+///
+/// This has the same operations as `NodeStore`, but nodes are copied in
+/// and out as `Node`s rather than borrowed, since they're stored as bytes.
+/// `from_node_store` writes out a tree trained in memory, and
+/// `to_node_store` loads one back.
+///
+/// Changes are written to the file by the operating system in its own
+/// time, `flush` makes sure they're on disk.
+///
+/// As a `NodeStorage`, the node last borrowed to change is kept decoded
+/// until another one is, or the store is flushed or dropped, and then its
+/// data is written back.
+#[derive(Debug)]
+pub struct MmapNodeStore {
+    file: File,
+    map: Records,
+    len: usize,
+    /// The data of the node borrowed through `NodeStorage::node_mut`, not
+    /// written back yet.
+    pending: Option<(usize, NodeData)>,
+}
+
+/// The mapped file. The links and counts are read and changed here in
+/// place.
+#[derive(Debug)]
+struct Records(MmapMut);
+
+impl Deref for Records {
+    type Target = MmapMut;
+
+    fn deref(&self) -> &MmapMut {
+        &self.0
+    }
+}
+
+impl DerefMut for Records {
+    fn deref_mut(&mut self) -> &mut MmapMut {
+        &mut self.0
+    }
+}
+
+impl MmapNodeStore {
+    /// Create an empty store at `path`, replacing anything already there.
+    pub fn create(path: &Path) -> Result<Self, MmapNodeStoreError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut map = map_file(&file, (HEADER_LEN + 16 * RECORD_LEN) as u64)?;
+        map[..MAGIC.len()].copy_from_slice(MAGIC);
+        write_u32(&mut map, 8, RECORD_LEN as u32);
+        write_u32(&mut map, 12, VERSION);
+        let mut store = Self {
+            file,
+            map,
+            len: 0,
+            pending: None,
+        };
+        store.write_len();
+        Ok(store)
+    }
+
+    /// Open a store created with `create`.
+    pub fn open(path: &Path) -> Result<Self, MmapNodeStoreError> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        if file.metadata()?.len() < HEADER_LEN as u64 {
+            return Err(MmapNodeStoreError::BadMagic);
+        }
+        let map = map_file(&file, 0)?;
+        if &map[..MAGIC.len()] != MAGIC {
+            return Err(MmapNodeStoreError::BadMagic);
+        }
+        let version = read_u32(&map, 12);
+        if version != VERSION {
+            return Err(MmapNodeStoreError::UnsupportedVersion(version));
+        }
+        let record_len = read_u32(&map, 8) as usize;
+        if record_len != RECORD_LEN {
+            return Err(MmapNodeStoreError::RecordSize {
+                expected: RECORD_LEN,
+                found: record_len,
+            });
+        }
+        let len = read_u64(&map, 16) as usize;
+        if len.saturating_mul(RECORD_LEN) > map.len() - HEADER_LEN {
+            return Err(MmapNodeStoreError::Truncated(len));
+        }
+        Ok(Self {
+            file,
+            map,
+            len,
+            pending: None,
+        })
+    }
+
+    /// Write every node of `nodes` to a new store at `path`.
+    pub fn from_node_store(path: &Path, nodes: &NodeStore) -> Result<Self, MmapNodeStoreError> {
+        let mut store = Self::create(path)?;
+        store.reserve(nodes.len())?;
+        for node in nodes.iter() {
+            store.push_node(&node.to_node())?;
+        }
+        Ok(store)
+    }
+
+    /// Read every node into memory.
+    pub fn to_node_store(&self) -> Result<NodeStore, MmapNodeStoreError> {
+        let mut nodes = NodeStore::new();
+        for idx in 0..self.len {
+            nodes.push_node(&self.read_node(idx)?);
+        }
+        Ok(nodes)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Add a node as the `child_idx` child of `parent` and return its index.
+    /// Without a parent this is the root.
+    pub fn add(
+        &mut self,
+        parent: Option<usize>,
+        child_idx: usize,
+        data: &NodeData,
+    ) -> Result<usize, MmapNodeStoreError> {
+        let idx = self.len;
+        let node = match parent {
+            Some(parent) => {
+                assert!(parent < self.len, "Parent {parent} isn't in the store");
+                assert!(
+                    child_idx < MAX_CHILDREN,
+                    "Child index {child_idx} is more than a node can have"
+                );
+                assert_eq!(None, self.child(parent, child_idx));
+                Node::new(idx, parent, child_idx, data.clone())
+            }
+            // The root is its own parent.
+            None => {
+                let mut root = Node::new_root();
+                root.data = data.clone();
+                root
+            }
+        };
+        self.push_node(&node)?;
+        if let Some(parent) = parent {
+            let offset = offset(parent) + CHILDREN + 4 * child_idx;
+            write_u32(&mut self.map, offset, idx as u32);
+        }
+        Ok(idx)
+    }
+
+    /// Add a copy of `node` at the end, with its links as they are.
+    pub fn push_node(&mut self, node: &Node) -> Result<usize, MmapNodeStoreError> {
+        let idx = self.len;
+        self.reserve(idx + 1)?;
+        self.len += 1;
+        self.write_len();
+        if let Err(e) = self.set(idx, node) {
+            self.len -= 1;
+            self.write_len();
+            return Err(e);
+        }
+        Ok(idx)
+    }
+
+    /// A copy of the node at `idx`.
+    pub fn get(&self, idx: usize) -> Result<Option<Node>, MmapNodeStoreError> {
+        if idx >= self.len {
+            return Ok(None);
+        }
+        self.read_node(idx).map(Some)
+    }
+
+    /// Replace the node at `idx` with `node`, links and all.
+    pub fn set(&mut self, idx: usize, node: &Node) -> Result<(), MmapNodeStoreError> {
+        assert!(idx < self.len, "Node {idx} isn't in the store");
+        if node.num_child_slots() > MAX_CHILDREN {
+            return Err(MmapNodeStoreError::TooManyChildren(node.num_child_slots()));
+        }
+        self.write_pending()?;
+        let mut record = [0u8; RECORD_LEN];
+        write_data(&mut record, &node.data)?;
+        write_u32(&mut record, PARENT, to_index(node.parent));
+        write_u32(
+            &mut record,
+            PARENT_CHILD_IDX,
+            to_index(node.parent_child_idx),
+        );
+        for child_idx in 0..MAX_CHILDREN {
+            write_u32(
+                &mut record,
+                CHILDREN + 4 * child_idx,
+                to_index(node.get_child(child_idx)),
+            );
+            write_u32(
+                &mut record,
+                COUNTS + 4 * child_idx,
+                node.get_count(child_idx),
+            );
+        }
+        let offset = offset(idx);
+        self.map[offset..offset + RECORD_LEN].copy_from_slice(&record);
+        Ok(())
+    }
+
+    /// Read the node at `idx`, let `f` change it and write it back.
+    pub fn update<R>(
+        &mut self,
+        idx: usize,
+        f: impl FnOnce(&mut Node) -> R,
+    ) -> Result<Option<R>, MmapNodeStoreError> {
+        let Some(mut node) = self.get(idx)? else {
+            return Ok(None);
+        };
+        let result = f(&mut node);
+        self.set(idx, &node)?;
+        Ok(Some(result))
+    }
+
+    /// The index of the `child_idx` child of the node at `idx`, read
+    /// without copying the node.
+    pub fn child(&self, idx: usize, child_idx: usize) -> Option<usize> {
+        if idx >= self.len {
+            return None;
+        }
+        self.map.get_child(idx, child_idx)
+    }
+
+    /// How many times the `child_idx` child of the node at `idx` has been
+    /// visited.
+    pub fn count(&self, idx: usize, child_idx: usize) -> u32 {
+        if idx >= self.len {
+            return 0;
+        }
+        self.map.get_count(idx, child_idx)
+    }
+
+    pub fn increment_count(&mut self, idx: usize, child_idx: usize) {
+        assert!(idx < self.len, "Node {idx} isn't in the store");
+        let count = self.map.get_count(idx, child_idx);
+        self.map.set_count(idx, child_idx, count + 1);
+    }
+
+    /// The average strategy of the player node at `idx`, read straight from
+    /// its strategy sums. `None` if it isn't a player node, has no regret
+    /// matcher or hasn't been updated.
+    pub fn average_strategy(&self, idx: usize) -> Option<Vec<f32>> {
+        if idx >= self.len {
+            return None;
+        }
+        if let Some((pending_idx, data)) = &self.pending
+            && *pending_idx == idx
+        {
+            let NodeData::Player(PlayerData {
+                regret_matcher: Some(matcher),
+                ..
+            }) = data
+            else {
+                return None;
+            };
+            return normalized(matcher.strategy_sum());
+        }
+        let record = &self.map[offset(idx)..offset(idx) + RECORD_LEN];
+        if record[KIND] != KIND_PLAYER || record[HAS_MATCHER] == 0 {
+            return None;
+        }
+        let sums: Vec<f32> = (0..record[NUM_ACTIONS] as usize)
+            .map(|action_idx| read_f32(record, SUM_P + 4 * action_idx))
+            .collect();
+        normalized(&sums)
+    }
+
+    /// Make sure everything written so far is on disk.
+    pub fn flush(&mut self) -> Result<(), MmapNodeStoreError> {
+        self.write_pending()?;
+        Ok(self.map.flush()?)
+    }
+
+    /// Grow the file so it has room for at least `num_nodes` records.
+    fn reserve(&mut self, num_nodes: usize) -> Result<(), MmapNodeStoreError> {
+        let needed = HEADER_LEN + num_nodes * RECORD_LEN;
+        if needed <= self.map.len() {
+            return Ok(());
+        }
+        let capacity = num_nodes.next_power_of_two().max(16);
+        self.map.flush()?;
+        self.map = map_file(&self.file, (HEADER_LEN + capacity * RECORD_LEN) as u64)?;
+        Ok(())
+    }
+
+    fn write_len(&mut self) {
+        write_u64(&mut self.map, 16, self.len as u64);
+    }
+
+    /// Write the data of the node borrowed to change back to its record.
+    fn write_pending(&mut self) -> Result<(), MmapNodeStoreError> {
+        if let Some((idx, data)) = self.pending.take() {
+            write_data(&mut self.map[offset(idx)..offset(idx) + RECORD_LEN], &data)?;
+        }
+        Ok(())
+    }
+
+    fn read_data(&self, idx: usize) -> Result<NodeData, MmapNodeStoreError> {
+        match &self.pending {
+            Some((pending_idx, data)) if *pending_idx == idx => Ok(data.clone()),
+            _ => read_data(&self.map[offset(idx)..offset(idx) + RECORD_LEN], idx),
+        }
+    }
+
+    fn read_node(&self, idx: usize) -> Result<Node, MmapNodeStoreError> {
+        let mut node = Node::new(idx, 0, 0, self.read_data(idx)?);
+        node.parent = self.map.parent(idx);
+        node.parent_child_idx = self.map.parent_child_idx(idx);
+        for child_idx in 0..MAX_CHILDREN {
+            if let Some(child) = self.map.get_child(idx, child_idx) {
+                node.set_child(child_idx, child);
+            }
+            node.set_count(child_idx, self.map.get_count(idx, child_idx));
+        }
+        Ok(node)
+    }
+}
+
+/// Writes back the node borrowed last, as `flush` would.
+impl Drop for MmapNodeStore {
+    fn drop(&mut self) {
+        // There's nowhere to report an error from here, `flush` first to
+        // see them.
+        let _ = self.write_pending();
+    }
+}
+
+/// Nodes that can't be read or written, from a corrupt file or a regret
+/// matcher that doesn't fit in a record, panic, the same as running out
+/// of memory would for a `NodeStore`.
+impl NodeStorage for MmapNodeStore {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn add(&mut self, parent: Option<usize>, child_idx: usize, data: NodeData) -> usize {
+        MmapNodeStore::add(self, parent, child_idx, &data)
+            .unwrap_or_else(|e| panic!("Couldn't add a node to the file: {e}"))
+    }
+
+    fn push_node(&mut self, node: &Node) -> usize {
+        MmapNodeStore::push_node(self, node)
+            .unwrap_or_else(|e| panic!("Couldn't add a node to the file: {e}"))
+    }
+
+    fn data(&self, idx: usize) -> Option<Cow<'_, NodeData>> {
+        if idx >= self.len {
+            return None;
+        }
+        Some(match &self.pending {
+            Some((pending_idx, data)) if *pending_idx == idx => Cow::Borrowed(data),
+            _ => Cow::Owned(
+                self.read_data(idx)
+                    .unwrap_or_else(|e| panic!("Couldn't read node {idx}: {e}")),
+            ),
+        })
+    }
+
+    fn links(&self) -> &(dyn LinkStorage + 'static) {
+        &self.map
+    }
+
+    fn node_mut(
+        &mut self,
+        idx: usize,
+    ) -> Option<(&mut NodeData, &mut (dyn LinkStorage + 'static))> {
+        if idx >= self.len {
+            return None;
+        }
+        if self
+            .pending
+            .as_ref()
+            .is_none_or(|(pending_idx, _)| *pending_idx != idx)
+        {
+            let data = self
+                .read_data(idx)
+                .unwrap_or_else(|e| panic!("Couldn't read node {idx}: {e}"));
+            self.write_pending()
+                .unwrap_or_else(|e| panic!("Couldn't write a node back to the file: {e}"));
+            self.pending = Some((idx, data));
+        }
+        let (_, data) = self.pending.as_mut().unwrap();
+        Some((data, &mut self.map))
+    }
+
+    /// The nodes are in the file, so only the one borrowed to change is
+    /// on the heap.
+    fn memory_bytes(&self) -> usize {
+        self.pending.as_ref().map_or(0, |_| size_of::<NodeData>())
+    }
+
+    fn copy_nodes(&self) -> NodeStore {
+        self.to_node_store()
+            .unwrap_or_else(|e| panic!("Couldn't read the nodes: {e}"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        MmapNodeStore::flush(self).map_err(io::Error::other)
+    }
+}
+
+/// Records have room for `MAX_CHILDREN` and no pruning, so nothing is ever
+/// skipped.
+impl LinkStorage for Records {
+    fn parent(&self, idx: usize) -> Option<usize> {
+        from_index(read_u32(self, offset(idx) + PARENT))
+    }
+
+    fn parent_child_idx(&self, idx: usize) -> Option<usize> {
+        from_index(read_u32(self, offset(idx) + PARENT_CHILD_IDX))
+    }
+
+    fn num_slots(&self, _idx: usize) -> usize {
+        MAX_CHILDREN
+    }
+
+    fn get_child(&self, idx: usize, child_idx: usize) -> Option<usize> {
+        if child_idx >= MAX_CHILDREN {
+            return None;
+        }
+        from_index(read_u32(self, offset(idx) + CHILDREN + 4 * child_idx))
+    }
+
+    fn get_count(&self, idx: usize, child_idx: usize) -> u32 {
+        if child_idx >= MAX_CHILDREN {
+            return 0;
+        }
+        read_u32(self, offset(idx) + COUNTS + 4 * child_idx)
+    }
+
+    fn get_pruned(&self, _idx: usize, _child_idx: usize) -> u32 {
+        0
+    }
+
+    fn set_count(&mut self, idx: usize, child_idx: usize, count: u32) {
+        assert!(
+            child_idx < MAX_CHILDREN,
+            "Child index {child_idx} is more than a node can have"
+        );
+        write_u32(self, offset(idx) + COUNTS + 4 * child_idx, count);
+    }
+
+    fn set_pruned(&mut self, _idx: usize, _child_idx: usize, _pruned: u32) {}
+}
+
+fn map_file(file: &File, min_len: u64) -> Result<Records, MmapNodeStoreError> {
+    if file.metadata()?.len() < min_len {
+        file.set_len(min_len)?;
+    }
+    // SAFETY: the file is only changed through this map while the store
+    // is open. Another process changing it underneath is the same as
+    // any other corrupt file, reads are bounds checked.
+    let map = unsafe { MmapMut::map_mut(file)? };
+    Ok(Records(map))
+}
+
+fn offset(idx: usize) -> usize {
+    HEADER_LEN + idx * RECORD_LEN
+}
+
+/// Each action's share of `sums`, if they add up to anything.
+fn normalized(sums: &[f32]) -> Option<Vec<f32>> {
+    let total: f32 = sums.iter().sum();
+    (total.is_finite() && total > 0.0).then(|| sums.iter().map(|sum| sum / total).collect())
+}
+
+/// Write `data` into the node's `record`, leaving its links and counts as
+/// they are.
+fn write_data(record: &mut [u8], data: &NodeData) -> Result<(), MmapNodeStoreError> {
+    let (kind, player_idx, value, matcher) = match data {
+        NodeData::Root => (KIND_ROOT, 0, 0.0, None),
+        NodeData::Chance => (KIND_CHANCE, 0, 0.0, None),
+        NodeData::Terminal(terminal) => (KIND_TERMINAL, 0, terminal.total_utility, None),
+        NodeData::Player(player_data) => {
+            let matcher = player_data
+                .regret_matcher
+                .as_deref()
+                .map(|matcher| RegretMatcherData::from(matcher.clone()));
+            let value = matcher.as_ref().map_or(0.0, |m| m.cumulative_reward);
+            (KIND_PLAYER, player_data.player_idx, value, matcher)
+        }
+    };
+    let num_actions = matcher.as_ref().map_or(0, |matcher| matcher.p.len());
+    if num_actions > MAX_CHILDREN {
+        return Err(MmapNodeStoreError::TooManyActions(num_actions));
+    }
+
+    record[..PARENT].fill(0);
+    record[VALUE..CHILDREN].fill(0);
+    record[P..RECORD_LEN].fill(0);
+    record[KIND] = kind;
+    write_u32(record, PLAYER_IDX, player_idx as u32);
+    write_f32(record, VALUE, value);
+    if let Some(matcher) = matcher {
+        record[HAS_MATCHER] = 1;
+        record[NUM_ACTIONS] = num_actions as u8;
+        write_u64(record, NUM_UPDATES, matcher.num_updates as u64);
+        for (start, values) in [
+            (P, &matcher.p),
+            (SUM_P, &matcher.sum_p),
+            (EXPERT_REWARD, &matcher.expert_reward),
+        ] {
+            for (action_idx, value) in values.iter().enumerate() {
+                write_f32(record, start + 4 * action_idx, *value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read the data of node `idx` from its `record`.
+fn read_data(record: &[u8], idx: usize) -> Result<NodeData, MmapNodeStoreError> {
+    Ok(match record[KIND] {
+        KIND_ROOT => NodeData::Root,
+        KIND_CHANCE => NodeData::Chance,
+        KIND_TERMINAL => NodeData::Terminal(TerminalData::new(read_f32(record, VALUE))),
+        KIND_PLAYER => {
+            let regret_matcher = if record[HAS_MATCHER] == 0 {
+                None
+            } else {
+                let read_actions = |start: usize| -> Vec<f32> {
+                    (0..record[NUM_ACTIONS] as usize)
+                        .map(|action_idx| read_f32(record, start + 4 * action_idx))
+                        .collect()
+                };
+                let matcher = RegretMatcher::try_from(RegretMatcherData {
+                    p: read_actions(P),
+                    sum_p: read_actions(SUM_P),
+                    expert_reward: read_actions(EXPERT_REWARD),
+                    cumulative_reward: read_f32(record, VALUE),
+                    num_updates: read_u64(record, NUM_UPDATES) as usize,
+                })?;
+                Some(Box::new(matcher))
+            };
+            NodeData::Player(PlayerData {
+                regret_matcher,
+                player_idx: read_u32(record, PLAYER_IDX) as usize,
+            })
+        }
+        kind => return Err(MmapNodeStoreError::BadKind { idx, kind }),
+    })
+}
+
+fn to_index(idx: Option<usize>) -> u32 {
+    idx.map_or(NONE, |idx| {
+        u32::try_from(idx).expect("Node indexes fit in a u32")
+    })
+}
+
+fn from_index(idx: u32) -> Option<usize> {
+    (idx != NONE).then_some(idx as usize)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u64(bytes: &mut [u8], offset: usize, value: u64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_f32(bytes: &mut [u8], offset: usize, value: f32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::arena::GameState;
+    use crate::arena::cfr::{BasicCFRActionGenerator, CFRState, OutcomeSamplingConfig, StateStore};
+    use crate::core::{Hand, with_seed};
+
+    fn small_deck() -> GameState {
+        let mut game_state = GameState::new_starting(vec![10.0, 2.0], 2.0, 1.0, 0.0, 0);
+        game_state.deck = Hand::new_from_str("AsKsQsJsTs9h8h7d2c").unwrap().into();
+        game_state
+    }
+
+    /// A store with each player's tree in a file in `dir`.
+    fn file_store(dir: &Path, game_state: &GameState) -> StateStore {
+        StateStore::from_states(
+            (0..2)
+                .map(|player_idx| {
+                    let path = dir.join(format!("player{player_idx}.nodes"));
+                    CFRState::with_storage(
+                        game_state.clone(),
+                        MmapNodeStore::create(&path).unwrap(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn trained_player(player_idx: usize) -> NodeData {
+        let mut matcher = RegretMatcher::new(3).unwrap();
+        matcher.update_regret(array![0.0, 1.0, 3.0].view()).unwrap();
+        matcher.update_regret(array![2.0, 0.0, 1.0].view()).unwrap();
+        NodeData::Player(PlayerData {
+            regret_matcher: Some(Box::new(matcher)),
+            player_idx,
+        })
+    }
+
+    #[test]
+    fn test_round_trip_node_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree.nodes");
+
+        let mut nodes = NodeStore::new();
+        nodes.add(None, 0, NodeData::Root);
+        let chance = nodes.add(Some(0), 0, NodeData::Chance);
+        let player = nodes.add(Some(chance), 51, trained_player(1));
+        nodes.add(Some(player), 2, NodeData::Terminal(TerminalData::new(-4.5)));
+        nodes.get_mut(chance).unwrap().increment_count(51);
+        nodes.get_mut(player).unwrap().increment_count(2);
+
+        MmapNodeStore::from_node_store(&path, &nodes)
+            .unwrap()
+            .flush()
+            .unwrap();
+        let store = MmapNodeStore::open(&path).unwrap();
+        assert_eq!(4, store.len());
+        assert_eq!(Some(player), store.child(chance, 51));
+        assert_eq!(1, store.count(player, 2));
+
+        let loaded = store.to_node_store().unwrap();
+        for (node, loaded) in nodes.iter().zip(loaded.iter()) {
+            assert_eq!(node.parent, loaded.parent);
+            assert_eq!(node.parent_child_idx, loaded.parent_child_idx);
+            assert_eq!(
+                node.iter_children().collect::<Vec<_>>(),
+                loaded.iter_children().collect::<Vec<_>>()
+            );
+            match (node.data, loaded.data) {
+                (NodeData::Player(a), NodeData::Player(b)) => {
+                    assert_eq!(a.player_idx, b.player_idx);
+                    assert_eq!(a.regret_matcher, b.regret_matcher);
+                }
+                (NodeData::Terminal(a), NodeData::Terminal(b)) => {
+                    assert_eq!(a.total_utility, b.total_utility);
+                }
+                (a, b) => assert_eq!(a.to_string(), b.to_string()),
+            }
+        }
+
+        let NodeData::Player(player_data) = nodes.get(player).unwrap().data else {
+            panic!("Expected a player node");
+        };
+        let weights = player_data.regret_matcher.as_ref().unwrap().best_weight();
+        let total: f32 = weights.iter().sum();
+        let expected: Vec<f32> = weights.iter().map(|w| w / total).collect();
+        assert_eq!(Some(expected), store.average_strategy(player));
+        assert_eq!(None, store.average_strategy(chance));
+    }
+
+    #[test]
+    fn test_grow_and_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree.nodes");
+        let mut store = MmapNodeStore::create(&path).unwrap();
+        let root = store.add(None, 0, &NodeData::Root).unwrap();
+        let mut parent = root;
+        // Enough nodes to grow the file a few times.
+        for _ in 0..100 {
+            parent = store.add(Some(parent), 0, &NodeData::Chance).unwrap();
+            store.increment_count(root, 0);
+        }
+        assert_eq!(101, store.len());
+        assert_eq!(100, store.count(root, 0));
+
+        let player = store.add(Some(parent), 3, &trained_player(0)).unwrap();
+        store
+            .update(player, |node| {
+                if let NodeData::Player(player_data) = &mut node.data {
+                    let matcher = player_data.regret_matcher.as_mut().unwrap();
+                    matcher.update_regret(array![5.0, 0.0, 0.0].view()).unwrap();
+                }
+            })
+            .unwrap()
+            .unwrap();
+        drop(store);
+
+        let store = MmapNodeStore::open(&path).unwrap();
+        let node = store.get(player).unwrap().unwrap();
+        let NodeData::Player(player_data) = &node.data else {
+            panic!("Expected a player node");
+        };
+        assert_eq!(
+            3,
+            player_data.regret_matcher.as_ref().unwrap().num_updates()
+        );
+        assert_eq!(Some(parent), node.parent);
+        assert_eq!(Some(3), node.parent_child_idx);
+        assert!(store.get(player + 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_too_many_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = MmapNodeStore::create(&dir.path().join("tree.nodes")).unwrap();
+        let root = store.add(None, 0, &NodeData::Root).unwrap();
+        // Fine in a NodeStore, but past what a record holds.
+        let mut node = Node::new(1, root, 0, NodeData::Chance);
+        node.set_child(MAX_CHILDREN + 8, 2);
+        assert!(matches!(
+            store.push_node(&node),
+            Err(MmapNodeStoreError::TooManyChildren(61))
+        ));
+        assert_eq!(1, store.len());
+    }
+
+    #[test]
+    fn test_train_in_the_file() {
+        let game_state = small_deck();
+        let train = |state_store: &mut StateStore| {
+            with_seed(7, || {
+                OutcomeSamplingConfig::default().train::<BasicCFRActionGenerator, _>(
+                    state_store,
+                    &game_state,
+                    300,
+                    &mut StdRng::seed_from_u64(7),
+                )
+            })
+        };
+        let mut in_memory = StateStore::new();
+        train(&mut in_memory);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut in_file = file_store(dir.path(), &game_state);
+        train(&mut in_file);
+        drop(in_file);
+
+        // The same training, kept in the files.
+        for player_idx in 0..2 {
+            let trained = in_memory.get_state(player_idx).unwrap();
+            let path = dir.path().join(format!("player{player_idx}.nodes"));
+            let store = MmapNodeStore::open(&path).unwrap();
+            assert_eq!(trained.internal_state().borrow().nodes.len(), store.len());
+            let reopened = CFRState::with_storage(game_state.clone(), store);
+            assert_eq!(trained.average_strategy(), reopened.average_strategy());
+            assert_eq!(trained.stats().total_visits, reopened.stats().total_visits);
+        }
+        assert!(
+            !in_memory
+                .get_state(0)
+                .unwrap()
+                .average_strategy()
+                .is_empty()
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_train_in_the_file() {
+        use crate::arena::cfr::ParallelTrainer;
+        use crate::core::CrateRng;
+
+        let game_state = small_deck();
+        let train = |state_store: &mut StateStore, iterations: usize, rng: &mut CrateRng| {
+            OutcomeSamplingConfig::default().train_with::<BasicCFRActionGenerator, _>(
+                state_store,
+                &game_state,
+                iterations,
+                rng,
+                &mut (),
+            )
+        };
+        let trainer = ParallelTrainer::new(2, 3).with_merge_every(50);
+        let mut in_memory = StateStore::new();
+        trainer.train(&mut in_memory, 200, train).unwrap();
+
+        // Shards train copies in memory, and are merged into the files.
+        let dir = tempfile::tempdir().unwrap();
+        let mut in_file = file_store(dir.path(), &game_state);
+        trainer.train(&mut in_file, 200, train).unwrap();
+        for player_idx in 0..2 {
+            let cfr_state = in_file.get_state(player_idx).unwrap();
+            cfr_state.flush().unwrap();
+            let path = dir.path().join(format!("player{player_idx}.nodes"));
+            let reopened =
+                CFRState::with_storage(game_state.clone(), MmapNodeStore::open(&path).unwrap());
+            let trained = in_memory.get_state(player_idx).unwrap();
+            assert_eq!(trained.average_strategy(), reopened.average_strategy());
+            assert_eq!(trained.average_strategy(), cfr_state.average_strategy());
+        }
+    }
+
+    #[test]
+    fn test_open_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree.nodes");
+        std::fs::write(&path, b"not a node store at all").unwrap();
+        assert!(matches!(
+            MmapNodeStore::open(&path),
+            Err(MmapNodeStoreError::BadMagic)
+        ));
+    }
+}
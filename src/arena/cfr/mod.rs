@@ -28,7 +28,10 @@
 //!
 //! The action generator is responsible for generating possible actions, mapping
 //! actions into indices in the children array of the nodes, and deciding on the
-//! least regretted action to take.
+//! least regretted action to take. It also maps dealt cards to the children of
+//! chance nodes, which is where a `CardAbstraction` buckets cards together.
+//! `AbstractCFRActionGenerator` takes its actions from an `ActionAbstraction`
+//! instead, such as bets sized as fractions of the pot.
 //!
 //! ActionGenerator must be stateless, so that the same action
 //! generator can be used as a type parameter for agents and historians.
@@ -38,26 +41,103 @@
 //! The agent is responsible for deciding which action to take when it is
 //! their turn. For that the agent looks in the tree. Then it will simulate all
 //! the possible actions and update the regret values for each action taken.
-//! Then it will use the CFR+ algorithm to choose the action to take.
+//! Then it will use regret matching to choose the action to take. Regrets
+//! get plain updates unless a `RegretUpdate` such as CFR+ is picked with
+//! `CFRAgent::with_regret_update` or in a trainer's config. Actions with
+//! very negative regret can be skipped for a while with
+//! `CFRAgent::with_pruning`, and decisions in the same `InfoSet` can share
+//! one regret matcher with `CFRAgent::with_infosets`.
+mod abstraction;
+mod action_abstraction;
 mod action_generator;
 mod agent;
+mod atomic_regret;
+mod best_response;
+mod blueprint;
+mod callback;
+mod chance;
+mod chart;
+mod checkpoint;
+mod concurrent_store;
+mod convergence;
+mod deep;
+mod divergence;
 mod export;
 mod gamestate_iterator_gen;
 mod historian;
+mod infoset;
+mod lbr;
+mod limit;
+#[cfg(feature = "mmap")]
+mod mmap_store;
 mod node;
+mod node_store;
+mod outcome_sampling;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod query;
+mod regret_matcher;
+mod replay;
+mod reservoir;
+mod spot;
 mod state;
 mod state_store;
-
+mod strategy;
+mod subgame;
+mod tree_iter;
+mod warm_start;
+
+pub use abstraction::{
+    CardAbstraction, Ehs2Abstraction, EquityAbstraction, NoCardAbstraction, SuitIsomorphism,
+    ValueOnlyAbstraction,
+};
+pub use action_abstraction::{
+    AbstractCFRActionGenerator, ActionAbstraction, BetSizes, DefaultBetSizes, PotFractionActions,
+};
 pub use action_generator::{ActionGenerator, BasicCFRActionGenerator};
-pub use agent::CFRAgent;
+pub use agent::{CFRAgent, CFRConfig, PruneConfig};
+pub use atomic_regret::{AtomicRegretTable, RegretDeltas};
+pub use best_response::{
+    BestResponse, Exploitability, KnownHandsBestResponse, exploitability,
+    known_hands_exploitability,
+};
+pub use blueprint::{BlueprintAgent, BlueprintAgentGenerator};
+pub use callback::TrainingCallback;
+pub use chance::ChanceSampler;
+pub use chart::{ChartFormat, ChartRow, StrategyChart};
+pub use checkpoint::{Checkpoint, TrainingConfig, resume_training};
+pub use concurrent_store::ConcurrentStateStore;
+pub use convergence::{ConvergencePoint, ConvergenceTracker};
+pub use deep::{AdvantageModel, AdvantageSample, DeepCfrConfig, DeepCfrTrainer, StrategySample};
+pub use divergence::{SpotDivergence, StrategyComparison};
 pub use export::{ExportFormat, export_cfr_state, export_to_dot, export_to_png, export_to_svg};
 pub use gamestate_iterator_gen::{
     FixedGameStateIteratorGen, GameStateIteratorGen, PerRoundFixedGameStateIteratorGen,
 };
 pub use historian::CFRHistorian;
+pub use infoset::{InfoSet, InfoSetTable};
+pub use lbr::{LbrConfig, LbrEstimate, LocalBestResponse};
+pub use limit::{HeadsUpLimitConfig, LimitCFRActionGenerator};
+#[cfg(feature = "mmap")]
+pub use mmap_store::{MAX_CHILDREN, MmapNodeStore, RECORD_LEN};
 pub use node::{Node, NodeData, PlayerData, TerminalData};
-pub use state::{CFRState, TraversalState};
+pub use node_store::{
+    CHANCE_CHILDREN, LinkStorage, NodeDataRef, NodeLinks, NodeMut, NodeRef, NodeStorage, NodeStore,
+    NodeView,
+};
+pub use outcome_sampling::OutcomeSamplingConfig;
+#[cfg(feature = "rayon")]
+pub use parallel::{Accumulation, ParallelTrainer};
+pub use query::ActionDistribution;
+pub use regret_matcher::{IterationWeights, RegretMatcher, RegretUpdate};
+pub use reservoir::ReservoirBuffer;
+pub use spot::{SpotSolution, solve_spot};
+pub use state::{CFRState, TraversalState, TreeStats};
 pub use state_store::StateStore;
+pub use strategy::{StrategyEntry, StrategyProfile};
+pub use subgame::Subgame;
+pub use tree_iter::TreeIter;
+pub use warm_start::WarmStart;
 
 #[cfg(test)]
 mod tests {
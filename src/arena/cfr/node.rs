@@ -1,48 +1,17 @@
-use serde::{Deserialize, Serialize, Serializer, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::ser::SerializeStruct;
-use serde::de;
 
-#[derive(Debug, Clone)]
+use super::RegretMatcher;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerData {
-    pub regret_matcher: Option<Box<little_sorry::RegretMatcher>>,
+    /// Saved with everything it's learned, so training can be resumed. Files
+    /// written before that have `null` here.
+    #[serde(default)]
+    pub regret_matcher: Option<Box<RegretMatcher>>,
     pub player_idx: usize,
 }
 
-// Custom Serialize for PlayerData to handle RegretMatcher
-impl Serialize for PlayerData {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut state = serializer.serialize_struct("PlayerData", 2)?;
-        // Skip RegretMatcher serialization for now (will need to be recreated)
-        state.serialize_field("regret_matcher", &None::<()>)?;
-        state.serialize_field("player_idx", &self.player_idx)?;
-        state.end()
-    }
-}
-
-// Custom Deserialize for PlayerData to handle RegretMatcher
-impl<'de> Deserialize<'de> for PlayerData {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        #[derive(Deserialize)]
-        struct PlayerDataHelper {
-            #[serde(default)]
-            regret_matcher: Option<()>,
-            player_idx: usize,
-        }
-
-        let helper = PlayerDataHelper::deserialize(deserializer)?;
-        Ok(PlayerData {
-            regret_matcher: None, // We recreate this as needed
-            player_idx: helper.player_idx,
-        })
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerminalData {
     pub total_utility: f32,
@@ -125,13 +94,30 @@ pub struct Node {
     pub parent: Option<usize>,
     pub parent_child_idx: Option<usize>,
 
-    // We use an array of Option<usize> to represent the children of the node.
-    // The index of the array is the action index or the card index for chance nodes.
+    // We use a Vec of Option<usize> to represent the children of the node.
+    // The index is the action index or the card index for chance nodes.
     //
-    // This limits the number of possible agent actions to 52, but in return we
-    // get contiguous memory for no pointer chasing.
-    children: [Option<usize>; 52],
-    count: [u32; 52],
+    // They grow as children are set, so a node can have any number of
+    // actions and only takes room for the ones it has.
+    children: Vec<Option<usize>>,
+    count: Vec<u32>,
+    // How many more times each child is skipped by regret based pruning.
+    // This isn't saved, a loaded tree starts with nothing pruned.
+    pruned: Vec<u32>,
+}
+
+/// Saved nodes list at least this many children and counts, the fixed
+/// number nodes used to have, so trees that fit in it save the same as
+/// they always have.
+const SAVED_CHILDREN: usize = 52;
+
+/// `values` padded with `empty` to at least `SAVED_CHILDREN` long.
+fn padded<T: Copy>(values: &[T], empty: T) -> Vec<T> {
+    let mut padded = values.to_vec();
+    if padded.len() < SAVED_CHILDREN {
+        padded.resize(SAVED_CHILDREN, empty);
+    }
+    padded
 }
 
 // Custom Serialize for Node to handle the arrays
@@ -146,9 +132,8 @@ impl Serialize for Node {
         state.serialize_field("parent", &self.parent)?;
         state.serialize_field("parent_child_idx", &self.parent_child_idx)?;
 
-        // Convert fixed arrays to Vec for serialization
-        let children_vec: Vec<Option<usize>> = self.children.to_vec();
-        let count_vec: Vec<u32> = self.count.to_vec();
+        let children_vec = padded(&self.children, None);
+        let count_vec = padded(&self.count, 0);
         
         state.serialize_field("children", &children_vec)?;
         state.serialize_field("count", &count_vec)?;
@@ -174,25 +159,14 @@ impl<'de> Deserialize<'de> for Node {
 
         let helper = NodeHelper::deserialize(deserializer)?;
         
-        // Convert Vec back to fixed arrays
-        let mut children = [None; 52];
-        let mut count = [0; 52];
-        
-        for (i, child) in helper.children.iter().enumerate().take(52) {
-            children[i] = *child;
-        }
-        
-        for (i, c) in helper.count.iter().enumerate().take(52) {
-            count[i] = *c;
-        }
-        
         Ok(Node {
             idx: helper.idx,
             data: helper.data,
             parent: helper.parent,
             parent_child_idx: helper.parent_child_idx,
-            children,
-            count,
+            children: helper.children,
+            count: helper.count,
+            pruned: Vec::new(),
         })
     }
 }
@@ -204,8 +178,9 @@ impl Node {
             data: NodeData::Root,
             parent: Some(0),
             parent_child_idx: None,
-            children: [None; 52],
-            count: [0; 52],
+            children: Vec::new(),
+            count: Vec::new(),
+            pruned: Vec::new(),
         }
     }
 
@@ -238,28 +213,65 @@ impl Node {
             data,
             parent: Some(parent),
             parent_child_idx: Some(parent_child_idx),
-            children: [None; 52],
-            count: [0; 52],
+            children: Vec::new(),
+            count: Vec::new(),
+            pruned: Vec::new(),
         }
     }
 
     // Set child node at the provided index
     pub fn set_child(&mut self, idx: usize, child: usize) {
-        assert_eq!(self.children[idx], None);
+        assert_eq!(self.get_child(idx), None);
+        grow(&mut self.children, idx, None);
         self.children[idx] = Some(child);
     }
 
     // Get the child node at the provided index
     pub fn get_child(&self, idx: usize) -> Option<usize> {
-        self.children[idx]
+        self.children.get(idx).copied().flatten()
+    }
+
+    pub(super) fn set_count(&mut self, idx: usize, count: u32) {
+        if count == 0 && idx >= self.count.len() {
+            return;
+        }
+        grow(&mut self.count, idx, 0);
+        self.count[idx] = count;
     }
 
     // Increment the count for the provided index
     pub fn increment_count(&mut self, idx: usize) {
         assert!(idx == 0 || !self.data.is_terminal());
+        grow(&mut self.count, idx, 0);
         self.count[idx] += 1;
     }
 
+    /// How many more times the child at `idx` will be skipped because it
+    /// was pruned.
+    pub fn get_pruned(&self, idx: usize) -> u32 {
+        self.pruned.get(idx).copied().unwrap_or(0)
+    }
+
+    pub fn set_pruned(&mut self, idx: usize, pruned: u32) {
+        if pruned == 0 && idx >= self.pruned.len() {
+            return;
+        }
+        grow(&mut self.pruned, idx, 0);
+        self.pruned[idx] = pruned;
+    }
+
+    /// One more than the highest child index with a child, count or
+    /// pruning set.
+    pub fn num_child_slots(&self) -> usize {
+        let used = |values: &[u32]| values.iter().rposition(|&v| v > 0).map_or(0, |i| i + 1);
+        let children = self
+            .children
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |i| i + 1);
+        children.max(used(&self.count)).max(used(&self.pruned))
+    }
+
     /// Get an iterator over all the node's children with their indices
     ///
     /// This is useful for traversing the tree for visualization or debugging.
@@ -286,7 +298,14 @@ impl Node {
     ///
     /// The count for the specified child
     pub fn get_count(&self, idx: usize) -> u32 {
-        self.count[idx]
+        self.count.get(idx).copied().unwrap_or(0)
+    }
+}
+
+/// Make `values` long enough to have an `idx`.
+fn grow<T: Copy>(values: &mut Vec<T>, idx: usize, empty: T) {
+    if idx >= values.len() {
+        values.resize(idx + 1, empty);
     }
 }
 
@@ -397,10 +416,34 @@ mod tests {
         assert_eq!(deserialized_node.get_count(3), 1);
     }
     
+    #[test]
+    fn test_node_many_children() {
+        let mut node = Node::new(1, 0, 0, NodeData::Chance);
+        node.set_child(100, 2);
+        node.increment_count(100);
+        assert_eq!(Some(2), node.get_child(100));
+        assert_eq!(None, node.get_child(200));
+        assert_eq!(101, node.num_child_slots());
+
+        let json = serde_json::to_string(&node).unwrap();
+        let loaded: Node = serde_json::from_str(&json).unwrap();
+        assert_eq!(Some(2), loaded.get_child(100));
+        assert_eq!(1, loaded.get_count(100));
+
+        // Nodes that fit in the old fixed size save as they did.
+        let small = Node::new(1, 0, 0, NodeData::Chance);
+        let json = serde_json::to_value(&small).unwrap();
+        assert_eq!(52, json["children"].as_array().unwrap().len());
+        assert_eq!(52, json["count"].as_array().unwrap().len());
+    }
+
     #[test]
     fn test_player_data_serialization() {
-        // Create PlayerData with a RegretMatcher
-        let mut regret_matcher = little_sorry::RegretMatcher::new(5).unwrap();
+        // Create PlayerData with a RegretMatcher that has learned something
+        let mut regret_matcher = RegretMatcher::new(5).unwrap();
+        regret_matcher
+            .update_regret(ndarray::array![0.0, 1.0, 2.0, 0.0, 0.0].view())
+            .unwrap();
         let player_data = PlayerData {
             regret_matcher: Some(Box::new(regret_matcher)),
             player_idx: 7,
@@ -415,8 +458,16 @@ mod tests {
         // Verify player index was preserved
         assert_eq!(deserialized_data.player_idx, 7);
         
-        // Verify RegretMatcher was dropped during serialization (as expected)
-        assert!(deserialized_data.regret_matcher.is_none());
+        // Verify RegretMatcher kept what it learned
+        assert_eq!(
+            deserialized_data.regret_matcher,
+            player_data.regret_matcher
+        );
+
+        // Files written before regret matchers were saved still load
+        let old: PlayerData =
+            serde_json::from_str(r#"{"regret_matcher":null,"player_idx":3}"#).unwrap();
+        assert!(old.regret_matcher.is_none());
     }
     
     #[test]
@@ -446,17 +497,17 @@ mod tests {
             assert_eq!(original_data.is_terminal(), deserialized_data.is_terminal());
             
             // For terminal data, also check the utility value
-            if let NodeData::Terminal(ref original_terminal) = original_data {
-                if let NodeData::Terminal(ref deserialized_terminal) = deserialized_data {
-                    assert_eq!(original_terminal.total_utility, deserialized_terminal.total_utility);
-                }
+            if let NodeData::Terminal(ref original_terminal) = original_data
+                && let NodeData::Terminal(ref deserialized_terminal) = deserialized_data
+            {
+                assert_eq!(original_terminal.total_utility, deserialized_terminal.total_utility);
             }
             
             // For player data, check the player index
-            if let NodeData::Player(ref original_player) = original_data {
-                if let NodeData::Player(ref deserialized_player) = deserialized_data {
-                    assert_eq!(original_player.player_idx, deserialized_player.player_idx);
-                }
+            if let NodeData::Player(ref original_player) = original_data
+                && let NodeData::Player(ref deserialized_player) = deserialized_data
+            {
+                assert_eq!(original_player.player_idx, deserialized_player.player_idx);
             }
         }
     }
@@ -0,0 +1,716 @@
+use std::borrow::Cow;
+use std::cell::{Ref, RefMut};
+use std::fmt;
+use std::io;
+use std::ops::{Deref, DerefMut, Range};
+
+use serde::de::Error;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Node, NodeData, RegretMatcher};
+
+/// How many slots chance nodes start with, one per card, so dealing never
+/// has to grow them.
+pub const CHANCE_CHILDREN: usize = 52;
+
+/// Stands in for a missing parent or child index.
+const NONE: u32 = u32::MAX;
+
+/// Storage for the nodes of a CFR tree, as parallel arrays rather than an
+/// array of nodes.
+///
+/// Traversing the tree mostly reads which child to go to next and bumps a
+/// visit count. Keeping the links and counts apart from the node data, and
+/// as compact indexes, means those reads touch a few small arrays instead
+/// of pulling whole nodes into cache.
+///
+/// Each node's children and visit counts live in a slice of two shared
+/// pools. Chance nodes get a slot for every card up front. Player nodes
+/// start with none and grow as actions are taken, so they only take as
+/// much room as the actions they've seen.
+///
+/// Nodes are read through `NodeView`s from `get` and `get_mut`.
+///
+/// This is the `NodeStorage` a `CFRState` uses unless it's given another.
+#[derive(Debug, Clone, Default)]
+pub struct NodeStore {
+    data: Vec<NodeData>,
+    links: NodeLinks,
+}
+
+/// Where every node is in the tree and how often each child has been
+/// visited. This is the part of `NodeStore` that traversal reads.
+#[derive(Clone, Default)]
+pub struct NodeLinks {
+    parent: Vec<u32>,
+    parent_child_idx: Vec<u32>,
+    /// Where each node's slots start in `children` and `counts`.
+    child_start: Vec<u32>,
+    /// How many slots each node has.
+    child_len: Vec<u32>,
+    /// The node index of each child, or `NONE` if it hasn't been created.
+    children: Vec<u32>,
+    /// How many times each child has been visited.
+    counts: Vec<u32>,
+    /// How many more times each child is skipped by pruning.
+    pruned: Vec<u32>,
+}
+
+impl fmt::Debug for NodeLinks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeLinks")
+            .field("num_nodes", &self.parent.len())
+            .field("num_slots", &self.children.len())
+            .finish()
+    }
+}
+
+/// Where the nodes of a CFR tree are kept. A `CFRState` keeps its tree in
+/// any of them, so agents and trainers work the same whether it's a
+/// `NodeStore` in memory or, with the `mmap` feature, a `MmapNodeStore` in
+/// a file.
+///
+/// Node data is borrowed where the storage has it in memory and copied
+/// out where it doesn't. The links and visit counts are read and changed
+/// in place through `LinkStorage`.
+pub trait NodeStorage: fmt::Debug + Send + Sync {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add a node as the `child_idx` child of `parent` and return its
+    /// index. Without a parent this is the root.
+    fn add(&mut self, parent: Option<usize>, child_idx: usize, data: NodeData) -> usize;
+
+    /// Add a copy of `node` at the end, with its links as they are.
+    fn push_node(&mut self, node: &Node) -> usize;
+
+    /// The data of the node at `idx`.
+    fn data(&self, idx: usize) -> Option<Cow<'_, NodeData>>;
+
+    /// The links and visit counts of every node.
+    fn links(&self) -> &(dyn LinkStorage + 'static);
+
+    /// The data of the node at `idx` to change, along with the links.
+    fn node_mut(&mut self, idx: usize)
+    -> Option<(&mut NodeData, &mut (dyn LinkStorage + 'static))>;
+
+    /// Roughly how many bytes of heap the nodes take.
+    fn memory_bytes(&self) -> usize;
+
+    /// A copy of every node, in memory.
+    fn copy_nodes(&self) -> NodeStore;
+
+    /// Make sure every change so far is stored. Nothing to do for storage
+    /// in memory.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The links and visit counts of the nodes in a `NodeStorage`, which
+/// `NodeView` reads and changes.
+pub trait LinkStorage {
+    fn parent(&self, idx: usize) -> Option<usize>;
+
+    fn parent_child_idx(&self, idx: usize) -> Option<usize>;
+
+    /// How many children the node at `idx` has room for so far.
+    fn num_slots(&self, idx: usize) -> usize;
+
+    fn get_child(&self, idx: usize, child_idx: usize) -> Option<usize>;
+
+    fn get_count(&self, idx: usize, child_idx: usize) -> u32;
+
+    fn get_pruned(&self, idx: usize, child_idx: usize) -> u32;
+
+    /// Set how many times the `child_idx` child of the node at `idx` has
+    /// been visited, making room for it if there isn't any.
+    fn set_count(&mut self, idx: usize, child_idx: usize, count: u32);
+
+    /// Skip the `child_idx` child of the node at `idx` the next `pruned`
+    /// times.
+    fn set_pruned(&mut self, idx: usize, child_idx: usize, pruned: u32);
+}
+
+fn to_index(idx: Option<usize>) -> u32 {
+    idx.map_or(NONE, |idx| {
+        u32::try_from(idx).expect("Node indexes fit in a u32")
+    })
+}
+
+fn from_index(idx: u32) -> Option<usize> {
+    (idx != NONE).then_some(idx as usize)
+}
+
+/// How many slots a new node starts with.
+fn initial_slots(data: &NodeData) -> usize {
+    match data {
+        NodeData::Chance => CHANCE_CHILDREN,
+        NodeData::Player(_) => 0,
+        // The root only ever goes to child 0, and terminal nodes count
+        // their visits in slot 0.
+        NodeData::Root | NodeData::Terminal(_) => 1,
+    }
+}
+
+impl NodeLinks {
+    fn slots(&self, idx: usize) -> Range<usize> {
+        let start = self.child_start[idx] as usize;
+        start..start + self.child_len[idx] as usize
+    }
+
+    fn push(&mut self, parent: Option<usize>, parent_child_idx: Option<usize>, slots: usize) {
+        self.parent.push(to_index(parent));
+        self.parent_child_idx.push(to_index(parent_child_idx));
+        self.child_start.push(to_index(Some(self.children.len())));
+        self.child_len.push(slots as u32);
+        self.children.resize(self.children.len() + slots, NONE);
+        self.counts.resize(self.counts.len() + slots, 0);
+        self.pruned.resize(self.pruned.len() + slots, 0);
+    }
+
+    /// The position of `child_idx` in the pools, moving the node's slots to
+    /// the end of the pools with room for it if they're too short.
+    fn slot(&mut self, idx: usize, child_idx: usize) -> usize {
+        let old = self.slots(idx);
+        if child_idx >= old.len() {
+            let len = (child_idx + 1).next_power_of_two().max(4);
+            let start = self.children.len();
+            self.children.extend_from_within(old.clone());
+            self.counts.extend_from_within(old.clone());
+            self.pruned.extend_from_within(old.clone());
+            self.children.resize(start + len, NONE);
+            self.counts.resize(start + len, 0);
+            self.pruned.resize(start + len, 0);
+            self.child_start[idx] = to_index(Some(start));
+            self.child_len[idx] = len as u32;
+        }
+        self.child_start[idx] as usize + child_idx
+    }
+}
+
+impl LinkStorage for NodeLinks {
+    fn parent(&self, idx: usize) -> Option<usize> {
+        from_index(self.parent[idx])
+    }
+
+    fn parent_child_idx(&self, idx: usize) -> Option<usize> {
+        from_index(self.parent_child_idx[idx])
+    }
+
+    fn num_slots(&self, idx: usize) -> usize {
+        self.child_len[idx] as usize
+    }
+
+    fn get_child(&self, idx: usize, child_idx: usize) -> Option<usize> {
+        if child_idx < self.child_len[idx] as usize {
+            from_index(self.children[self.child_start[idx] as usize + child_idx])
+        } else {
+            None
+        }
+    }
+
+    fn get_count(&self, idx: usize, child_idx: usize) -> u32 {
+        if child_idx < self.child_len[idx] as usize {
+            self.counts[self.child_start[idx] as usize + child_idx]
+        } else {
+            0
+        }
+    }
+
+    fn get_pruned(&self, idx: usize, child_idx: usize) -> u32 {
+        if child_idx < self.child_len[idx] as usize {
+            self.pruned[self.child_start[idx] as usize + child_idx]
+        } else {
+            0
+        }
+    }
+
+    fn set_count(&mut self, idx: usize, child_idx: usize, count: u32) {
+        let slot = self.slot(idx, child_idx);
+        self.counts[slot] = count;
+    }
+
+    fn set_pruned(&mut self, idx: usize, child_idx: usize, pruned: u32) {
+        let slot = self.slot(idx, child_idx);
+        self.pruned[slot] = pruned;
+    }
+}
+
+impl NodeStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Add a node as the `child_idx` child of `parent` and return its index.
+    /// Without a parent this is the root.
+    pub fn add(&mut self, parent: Option<usize>, child_idx: usize, data: NodeData) -> usize {
+        let idx = self.data.len();
+        match parent {
+            Some(parent) => {
+                self.links
+                    .push(Some(parent), Some(child_idx), initial_slots(&data));
+                let slot = self.links.slot(parent, child_idx);
+                assert_eq!(self.links.children[slot], NONE);
+                self.links.children[slot] = to_index(Some(idx));
+            }
+            // The root is its own parent.
+            None => self.links.push(Some(0), None, initial_slots(&data)),
+        }
+        self.data.push(data);
+        idx
+    }
+
+    /// Add a copy of `node` at the end, with its links as they are. Used
+    /// when loading, where the links are checked afterwards.
+    pub fn push_node(&mut self, node: &Node) -> usize {
+        let idx = self.data.len();
+        let slots = initial_slots(&node.data).max(node.num_child_slots());
+        self.links.push(node.parent, node.parent_child_idx, slots);
+        let start = self.links.child_start[idx] as usize;
+        for child_idx in 0..slots {
+            self.links.children[start + child_idx] = to_index(node.get_child(child_idx));
+            self.links.counts[start + child_idx] = node.get_count(child_idx);
+            self.links.pruned[start + child_idx] = node.get_pruned(child_idx);
+        }
+        self.data.push(node.data.clone());
+        idx
+    }
+
+    /// Roughly how many bytes of heap the store holds, counting the
+    /// capacity of its arrays and the regret matchers of player nodes.
+    pub fn memory_bytes(&self) -> usize {
+        let links = &self.links;
+        let arrays = self.data.capacity() * size_of::<NodeData>()
+            + (links.parent.capacity()
+                + links.parent_child_idx.capacity()
+                + links.child_start.capacity()
+                + links.child_len.capacity()
+                + links.children.capacity()
+                + links.counts.capacity()
+                + links.pruned.capacity())
+                * size_of::<u32>();
+        let regret_matchers: usize = self
+            .data
+            .iter()
+            .filter_map(|data| match data {
+                NodeData::Player(player_data) => player_data.regret_matcher.as_deref(),
+                _ => None,
+            })
+            // Each action has three f32 arrays plus an alias table entry.
+            .map(|matcher| size_of::<RegretMatcher>() + matcher.num_actions() * 20)
+            .sum();
+        arrays + regret_matchers
+    }
+
+    pub fn get(&self, idx: usize) -> Option<NodeView<&NodeData, &NodeLinks>> {
+        let data = self.data.get(idx)?;
+        Some(NodeView::new(idx, data, &self.links))
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<NodeView<&mut NodeData, &mut NodeLinks>> {
+        let data = self.data.get_mut(idx)?;
+        Some(NodeView::new(idx, data, &mut self.links))
+    }
+
+    /// Every node, in index order.
+    pub fn iter(&self) -> impl Iterator<Item = NodeView<&NodeData, &NodeLinks>> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(idx, data)| NodeView::new(idx, data, &self.links))
+    }
+}
+
+impl NodeStorage for NodeStore {
+    fn len(&self) -> usize {
+        NodeStore::len(self)
+    }
+
+    fn add(&mut self, parent: Option<usize>, child_idx: usize, data: NodeData) -> usize {
+        NodeStore::add(self, parent, child_idx, data)
+    }
+
+    fn push_node(&mut self, node: &Node) -> usize {
+        NodeStore::push_node(self, node)
+    }
+
+    fn data(&self, idx: usize) -> Option<Cow<'_, NodeData>> {
+        self.data.get(idx).map(Cow::Borrowed)
+    }
+
+    fn links(&self) -> &(dyn LinkStorage + 'static) {
+        &self.links
+    }
+
+    fn node_mut(
+        &mut self,
+        idx: usize,
+    ) -> Option<(&mut NodeData, &mut (dyn LinkStorage + 'static))> {
+        let data = self.data.get_mut(idx)?;
+        Some((data, &mut self.links))
+    }
+
+    fn memory_bytes(&self) -> usize {
+        NodeStore::memory_bytes(self)
+    }
+
+    fn copy_nodes(&self) -> NodeStore {
+        self.clone()
+    }
+}
+
+impl dyn NodeStorage {
+    pub fn get(&self, idx: usize) -> Option<NodeView<Cow<'_, NodeData>, &dyn LinkStorage>> {
+        let data = self.data(idx)?;
+        Some(NodeView::new(idx, data, self.links()))
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<NodeView<&mut NodeData, &mut dyn LinkStorage>> {
+        let (data, links) = self.node_mut(idx)?;
+        Some(NodeView::new(idx, data, links))
+    }
+
+    /// Every node, in index order.
+    pub fn iter(&self) -> impl Iterator<Item = NodeView<Cow<'_, NodeData>, &dyn LinkStorage>> {
+        (0..self.len()).filter_map(|idx| self.get(idx))
+    }
+}
+
+/// Cloning copies the nodes into a `NodeStore`, wherever they were kept.
+impl Clone for Box<dyn NodeStorage> {
+    fn clone(&self) -> Self {
+        Box::new(self.copy_nodes())
+    }
+}
+
+/// Saved the same as a `NodeStore`, and loaded back into one.
+impl Serialize for dyn NodeStorage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for node in self.iter() {
+            seq.serialize_element(&node.to_node())?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Box<dyn NodeStorage> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Box::new(NodeStore::deserialize(deserializer)?))
+    }
+}
+
+/// Nodes are saved one after another in the same form as `Node`, so files
+/// don't depend on how the store is laid out.
+impl Serialize for NodeStore {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for node in self.iter() {
+            seq.serialize_element(&node.to_node())?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeStore {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let nodes = Vec::<Node>::deserialize(deserializer)?;
+        let mut store = NodeStore::new();
+        for (idx, node) in nodes.iter().enumerate() {
+            if node.idx != idx {
+                return Err(D::Error::custom(format!(
+                    "node {idx} has index {}",
+                    node.idx
+                )));
+            }
+            store.push_node(node);
+        }
+        Ok(store)
+    }
+}
+
+/// A single node in a `NodeStorage`.
+///
+/// `data` is a reference to the node's data, or a copy of it, and the
+/// links are read through methods. It comes as `NodeRef` and `NodeMut`
+/// when borrowed out of a `CFRState`, or with plain references from
+/// `NodeStore::get`.
+pub struct NodeView<D, L> {
+    pub idx: usize,
+    pub data: D,
+    pub parent: Option<usize>,
+    pub parent_child_idx: Option<usize>,
+    links: L,
+}
+
+/// A node borrowed from a `CFRState`.
+pub type NodeRef<'a> = NodeView<NodeDataRef<'a>, Ref<'a, dyn LinkStorage>>;
+
+/// A node mutably borrowed from a `CFRState`.
+pub type NodeMut<'a> = NodeView<RefMut<'a, NodeData>, RefMut<'a, dyn LinkStorage>>;
+
+/// The data of a node borrowed from a `CFRState`, or a copy of it when the
+/// tree's `NodeStorage` doesn't keep it in memory.
+pub enum NodeDataRef<'a> {
+    Borrowed(Ref<'a, NodeData>),
+    Copied(NodeData),
+}
+
+impl Deref for NodeDataRef<'_> {
+    type Target = NodeData;
+
+    fn deref(&self) -> &NodeData {
+        match self {
+            NodeDataRef::Borrowed(data) => data,
+            NodeDataRef::Copied(data) => data,
+        }
+    }
+}
+
+impl<D, L> NodeView<D, L>
+where
+    D: Deref<Target = NodeData>,
+    L: Deref,
+    L::Target: LinkStorage,
+{
+    pub(crate) fn new(idx: usize, data: D, links: L) -> Self {
+        NodeView {
+            idx,
+            parent: links.parent(idx),
+            parent_child_idx: links.parent_child_idx(idx),
+            data,
+            links,
+        }
+    }
+
+    // Get the child node at the provided index
+    pub fn get_child(&self, idx: usize) -> Option<usize> {
+        self.links.get_child(self.idx, idx)
+    }
+
+    /// Get the count for a specific child index
+    pub fn get_count(&self, idx: usize) -> u32 {
+        self.links.get_count(self.idx, idx)
+    }
+
+    /// How many more times the child at `idx` will be skipped because it
+    /// was pruned.
+    pub fn get_pruned(&self, idx: usize) -> u32 {
+        self.links.get_pruned(self.idx, idx)
+    }
+
+    /// Get an iterator over all the node's children as tuples of
+    /// (child_idx, child_node_idx).
+    pub fn iter_children(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.links.num_slots(self.idx))
+            .filter_map(|idx| self.get_child(idx).map(|child| (idx, child)))
+    }
+
+    /// Get an iterator over the visit counts that aren't zero as tuples of
+    /// (child_idx, count).
+    pub fn iter_counts(&self) -> impl Iterator<Item = (usize, u32)> + '_ {
+        (0..self.links.num_slots(self.idx))
+            .map(|idx| (idx, self.get_count(idx)))
+            .filter(|&(_, count)| count > 0)
+    }
+
+    /// Copy the node out of the store.
+    pub fn to_node(&self) -> Node {
+        let mut node = Node::new(0, 0, 0, self.data.clone());
+        node.idx = self.idx;
+        node.parent = self.parent;
+        node.parent_child_idx = self.parent_child_idx;
+        for (child_idx, child) in self.iter_children() {
+            node.set_child(child_idx, child);
+        }
+        for child_idx in 0..self.links.num_slots(self.idx) {
+            node.set_count(child_idx, self.get_count(child_idx));
+            node.set_pruned(child_idx, self.get_pruned(child_idx));
+        }
+        node
+    }
+}
+
+impl<D, L> NodeView<D, L>
+where
+    D: Deref<Target = NodeData>,
+    L: DerefMut,
+    L::Target: LinkStorage,
+{
+    // Increment the count for the provided index
+    pub fn increment_count(&mut self, idx: usize) {
+        assert!(idx == 0 || !self.data.is_terminal());
+        let count = self.get_count(idx);
+        self.links.set_count(self.idx, idx, count + 1);
+    }
+
+    /// Add `count` visits to the child at `idx`.
+    pub fn add_count(&mut self, idx: usize, count: u32) {
+        assert!(idx == 0 || !self.data.is_terminal());
+        let count = self.get_count(idx).saturating_add(count);
+        self.links.set_count(self.idx, idx, count);
+    }
+
+    /// Take `count` visits away from the child at `idx`.
+    pub fn sub_count(&mut self, idx: usize, count: u32) {
+        let count = self.get_count(idx).saturating_sub(count);
+        self.links.set_count(self.idx, idx, count);
+    }
+
+    /// Skip the child at `idx` the next `pruned` times, or stop skipping it
+    /// with zero.
+    pub fn set_pruned(&mut self, idx: usize, pruned: u32) {
+        if pruned == 0 && self.get_pruned(idx) == 0 {
+            return;
+        }
+        self.links.set_pruned(self.idx, idx, pruned);
+    }
+}
+
+impl<D, L> fmt::Debug for NodeView<D, L>
+where
+    D: Deref<Target = NodeData>,
+    L: Deref,
+    L::Target: LinkStorage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("idx", &self.idx)
+            .field("data", &*self.data)
+            .field("parent", &self.parent)
+            .field("parent_child_idx", &self.parent_child_idx)
+            .field("children", &self.iter_children().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::cfr::{PlayerData, TerminalData};
+
+    fn player(player_idx: usize) -> NodeData {
+        NodeData::Player(PlayerData {
+            regret_matcher: None,
+            player_idx,
+        })
+    }
+
+    #[test]
+    fn test_player_nodes_grow() {
+        let mut store = NodeStore::new();
+        store.add(None, 0, NodeData::Root);
+        let player_idx = store.add(Some(0), 0, player(0));
+        assert_eq!(0, store.links.slots(player_idx).len());
+
+        let terminal = store.add(
+            Some(player_idx),
+            2,
+            NodeData::Terminal(TerminalData::default()),
+        );
+        store.get_mut(player_idx).unwrap().increment_count(2);
+        assert_eq!(4, store.links.slots(player_idx).len());
+
+        // Growing past the first slots keeps what was there.
+        let chance = store.add(Some(player_idx), 9, NodeData::Chance);
+        let node = store.get(player_idx).unwrap();
+        assert_eq!(16, store.links.slots(player_idx).len());
+        assert_eq!(
+            vec![(2, terminal), (9, chance)],
+            node.iter_children().collect::<Vec<_>>()
+        );
+        assert_eq!(1, node.get_count(2));
+        assert_eq!(0, node.get_count(40));
+        assert_eq!(None, node.get_child(40));
+        assert_eq!(CHANCE_CHILDREN, store.links.slots(chance).len());
+    }
+
+    #[test]
+    fn test_pruned_not_saved() {
+        let mut store = NodeStore::new();
+        store.add(None, 0, NodeData::Root);
+        let player_idx = store.add(Some(0), 0, player(0));
+        store.get_mut(player_idx).unwrap().set_pruned(1, 3);
+        // Growing the slots keeps what's pruned.
+        store.get_mut(player_idx).unwrap().increment_count(9);
+        let node = store.get(player_idx).unwrap();
+        assert_eq!(3, node.get_pruned(1));
+        assert_eq!(0, node.get_pruned(9));
+        assert_eq!(3, node.to_node().get_pruned(1));
+
+        let json = serde_json::to_string(&store).unwrap();
+        let loaded: NodeStore = serde_json::from_str(&json).unwrap();
+        assert_eq!(0, loaded.get(player_idx).unwrap().get_pruned(1));
+    }
+
+    #[test]
+    fn test_round_trip_nodes() {
+        let mut store = NodeStore::new();
+        store.add(None, 0, NodeData::Root);
+        let chance = store.add(Some(0), 0, NodeData::Chance);
+        let player_idx = store.add(Some(chance), 51, player(1));
+        store.get_mut(chance).unwrap().increment_count(51);
+        store.get_mut(player_idx).unwrap().increment_count(1);
+
+        let json = serde_json::to_string(&store).unwrap();
+        let loaded: NodeStore = serde_json::from_str(&json).unwrap();
+        assert_eq!(store.len(), loaded.len());
+        for (node, loaded) in store.iter().zip(loaded.iter()) {
+            assert_eq!(node.parent, loaded.parent);
+            assert_eq!(node.parent_child_idx, loaded.parent_child_idx);
+            assert_eq!(
+                node.iter_children().collect::<Vec<_>>(),
+                loaded.iter_children().collect::<Vec<_>>()
+            );
+            for child_idx in 0..CHANCE_CHILDREN {
+                assert_eq!(node.get_count(child_idx), loaded.get_count(child_idx));
+            }
+        }
+    }
+
+    #[test]
+    fn test_many_children() {
+        let mut store = NodeStore::new();
+        store.add(None, 0, NodeData::Root);
+        let player_idx = store.add(Some(0), 0, player(0));
+        // Far more actions than there are cards.
+        let terminal = store.add(
+            Some(player_idx),
+            300,
+            NodeData::Terminal(TerminalData::default()),
+        );
+        store.get_mut(player_idx).unwrap().increment_count(300);
+        assert_eq!(512, store.links.slots(player_idx).len());
+
+        let json = serde_json::to_string(&store).unwrap();
+        let loaded: NodeStore = serde_json::from_str(&json).unwrap();
+        let node = loaded.get(player_idx).unwrap();
+        assert_eq!(Some(terminal), node.get_child(300));
+        assert_eq!(vec![(300, 1)], node.iter_counts().collect::<Vec<_>>());
+    }
+}
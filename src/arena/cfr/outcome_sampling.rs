@@ -0,0 +1,431 @@
+use std::cell::RefCell;
+use std::ops::ControlFlow;
+use std::rc::Rc;
+
+use ndarray::ArrayView1;
+use rand::Rng;
+use rand::distr::Distribution;
+use rand::distr::weighted::WeightedIndex;
+
+use crate::arena::action::AgentAction;
+use crate::arena::{Agent, GameState, Historian, HoldemSimulationBuilder};
+
+use super::callback::{finish_iteration, tree_sizes};
+use super::{
+    ActionGenerator, CFRHistorian, CFRState, NodeData, PlayerData, RegretMatcher, RegretUpdate,
+    StateStore, TrainingCallback, TraversalState,
+};
+
+/// Outcome sampling Monte Carlo CFR.
+///
+/// Every iteration plays a single hand. One player, the traverser, samples
+/// their actions from their current strategy mixed with some uniform
+/// exploration, while everyone else plays their current strategy and the
+/// cards are dealt as usual. When the hand is over the traverser's regrets
+/// are updated at each decision they made, with the result weighted by how
+/// unlikely the traverser was to play the way they did.
+///
+/// Nothing is played out that wasn't dealt, so an iteration is much cheaper
+/// than `CFRAgent`'s, which values every action at a new decision, and the
+/// trees only grow along the hands that were played. That makes it the
+/// option for trees too big to train any other way, at the price of noisier
+/// updates and so more iterations.
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::{
+///     BasicCFRActionGenerator, OutcomeSamplingConfig, StateStore, StrategyProfile,
+/// };
+///
+/// let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+/// let mut state_store = StateStore::new();
+/// OutcomeSamplingConfig::default().train::<BasicCFRActionGenerator, _>(
+///     &mut state_store,
+///     &game_state,
+///     100,
+///     &mut rand::rng(),
+/// );
+///
+/// let profile = StrategyProfile::from_state_store(&state_store);
+/// assert!(!profile.is_empty());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutcomeSamplingConfig {
+    /// How much of the traverser's sampling is uniform over the possible
+    /// actions, between 0 and 1. More explores actions the strategy has
+    /// given up on, at the cost of larger importance weights.
+    pub exploration: f32,
+    /// How regrets are updated, plain regret matching by default.
+    pub regret_update: RegretUpdate,
+}
+
+impl Default for OutcomeSamplingConfig {
+    fn default() -> Self {
+        Self {
+            exploration: 0.6,
+            regret_update: RegretUpdate::default(),
+        }
+    }
+}
+
+impl OutcomeSamplingConfig {
+    /// Train on `iterations` hands played from `game_state`, with iteration
+    /// `i` updating player `i` modulo the number of players. An empty
+    /// `state_store` gets a tree for each player rooted at `game_state`,
+    /// otherwise its trees are trained further, so they have to have been
+    /// rooted at the same state.
+    pub fn train<T: ActionGenerator + 'static, R: Rng>(
+        &self,
+        state_store: &mut StateStore,
+        game_state: &GameState,
+        iterations: usize,
+        rng: &mut R,
+    ) {
+        self.train_with::<T, R>(state_store, game_state, iterations, rng, &mut ());
+    }
+
+    /// Train the same as `train`, telling `callback` about every iteration
+    /// and every node added. Returns how many iterations were trained,
+    /// which is fewer than `iterations` if the callback stopped early.
+    pub fn train_with<T: ActionGenerator + 'static, R: Rng>(
+        &self,
+        state_store: &mut StateStore,
+        game_state: &GameState,
+        iterations: usize,
+        rng: &mut R,
+        callback: &mut impl TrainingCallback,
+    ) -> usize {
+        let num_players = game_state.num_players;
+        if state_store.is_empty() {
+            for player_idx in 0..num_players {
+                state_store.new_state(game_state.clone(), player_idx);
+                state_store.pop_traversal(player_idx);
+            }
+        }
+        let mut sizes = tree_sizes(state_store);
+        let mut trained = 0;
+        for iteration in 0..iterations {
+            let traverser = iteration % num_players;
+            let decisions = Rc::new(RefCell::new(Vec::new()));
+            let agents: Vec<Box<dyn Agent>> = (0..num_players)
+                .map(|player_idx| {
+                    let (cfr_state, traversal_state) = state_store.push_traversal(player_idx);
+                    Box::new(SamplingAgent::<T>::new(
+                        cfr_state,
+                        traversal_state,
+                        self.exploration,
+                        (player_idx == traverser).then(|| decisions.clone()),
+                    )) as Box<dyn Agent>
+                })
+                .collect();
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(game_state.clone())
+                .agents(agents)
+                .build()
+                .unwrap();
+            sim.run(rng);
+            for player_idx in 0..num_players {
+                state_store.pop_traversal(player_idx);
+            }
+
+            let mut cfr_state = state_store.get_state(traverser).unwrap();
+            update_regrets(
+                &mut cfr_state,
+                &decisions.borrow(),
+                sim.game_state.player_reward(traverser),
+                self.regret_update,
+            );
+            #[cfg(feature = "metrics")]
+            crate::arena::metrics::record_iteration("outcome_sampling");
+            trained += 1;
+            if let ControlFlow::Break(()) =
+                finish_iteration(callback, state_store, &mut sizes, trained)
+            {
+                break;
+            }
+        }
+        #[cfg(feature = "metrics")]
+        crate::arena::metrics::record_state_store(state_store);
+        trained
+    }
+}
+
+/// A decision the traverser made during a hand.
+#[derive(Debug, Clone)]
+struct SampledDecision {
+    node_idx: usize,
+    /// The regret matcher index of the action played.
+    action_idx: usize,
+    /// The indices of every action that could have been played.
+    possible: Vec<usize>,
+    /// How likely the action was to be sampled.
+    sample_prob: f32,
+    /// How likely the strategy was to play the action.
+    strategy_prob: f32,
+}
+
+/// Update the regrets at each of the traverser's `decisions`, last first,
+/// given what the hand was worth to them.
+fn update_regrets(
+    cfr_state: &mut CFRState,
+    decisions: &[SampledDecision],
+    utility: f32,
+    regret_update: RegretUpdate,
+) {
+    // The chance the traverser's own sampling reached each decision.
+    // Everyone else played their strategy, so that's all the importance
+    // weighting needs.
+    let reach: Vec<f32> = decisions
+        .iter()
+        .scan(1.0, |reach, decision| {
+            let before = *reach;
+            *reach *= decision.sample_prob;
+            Some(before)
+        })
+        .collect();
+
+    // The sampled estimate of what the strategy is worth after a decision.
+    let mut value = utility;
+    for (decision, reach) in decisions.iter().zip(reach).rev() {
+        let action_value = value / decision.sample_prob;
+        let mut node = cfr_state.get_mut(decision.node_idx).unwrap();
+        let NodeData::Player(player_data) = &mut *node.data else {
+            panic!("Expected player data at index {}", decision.node_idx);
+        };
+        let regret_matcher = player_data.regret_matcher.as_mut().unwrap();
+
+        // Actions that weren't played are estimated at zero. Ones that
+        // couldn't have been get the worst reward, so they never gain
+        // regret over an action that could.
+        let played = action_value / reach;
+        let worst = played.min(0.0);
+        let rewards: Vec<f32> = (0..regret_matcher.num_actions())
+            .map(|idx| {
+                if idx == decision.action_idx {
+                    played
+                } else if decision.possible.contains(&idx) {
+                    0.0
+                } else {
+                    worst
+                }
+            })
+            .collect();
+        regret_matcher
+            .update_regret_with(ArrayView1::from(&rewards), regret_update)
+            .unwrap();
+
+        value = decision.strategy_prob * action_value;
+    }
+}
+
+/// Plays the current strategy out of its tree, exploring if it's the
+/// traverser.
+struct SamplingAgent<T: ActionGenerator> {
+    cfr_state: CFRState,
+    traversal_state: TraversalState,
+    action_generator: T,
+    exploration: f32,
+    /// Where the traverser's decisions go, `None` for everyone else.
+    decisions: Option<Rc<RefCell<Vec<SampledDecision>>>>,
+}
+
+impl<T: ActionGenerator> SamplingAgent<T> {
+    fn new(
+        cfr_state: CFRState,
+        traversal_state: TraversalState,
+        exploration: f32,
+        decisions: Option<Rc<RefCell<Vec<SampledDecision>>>>,
+    ) -> Self {
+        let action_generator = T::new(cfr_state.clone(), traversal_state.clone());
+        Self {
+            cfr_state,
+            traversal_state,
+            action_generator,
+            exploration,
+            decisions,
+        }
+    }
+
+    /// The node for this decision, created with a fresh regret matcher if
+    /// it's the first time here.
+    fn ensure_target_node(&mut self, game_state: &GameState) -> usize {
+        let parent_idx = self.traversal_state.node_idx();
+        let child_idx = self.traversal_state.chosen_child_idx();
+        let target = self.cfr_state.get(parent_idx).unwrap().get_child(child_idx);
+        let node_idx = target.unwrap_or_else(|| {
+            self.cfr_state.add(
+                parent_idx,
+                child_idx,
+                NodeData::Player(PlayerData {
+                    regret_matcher: None,
+                    player_idx: self.traversal_state.player_idx(),
+                }),
+            )
+        });
+
+        let mut node = self.cfr_state.get_mut(node_idx).unwrap();
+        let NodeData::Player(player_data) = &mut *node.data else {
+            panic!("Expected player data at index {node_idx}, found {node:?}");
+        };
+        if player_data.regret_matcher.is_none() {
+            let num_actions = self.action_generator.num_potential_actions(game_state);
+            player_data.regret_matcher = Some(Box::new(RegretMatcher::new(num_actions).unwrap()));
+        }
+        node_idx
+    }
+}
+
+impl<T: ActionGenerator + 'static> Agent for SamplingAgent<T> {
+    fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+        let node_idx = self.ensure_target_node(game_state);
+        let possible = self.action_generator.gen_possible_actions(game_state);
+        let idxs: Vec<usize> = possible
+            .iter()
+            .map(|action| self.action_generator.action_to_idx(game_state, action))
+            .collect();
+
+        // The current strategy over the actions that can be played.
+        let mut strategy: Vec<f32> = {
+            let node = self.cfr_state.get(node_idx).unwrap();
+            let NodeData::Player(player_data) = &*node.data else {
+                unreachable!("ensure_target_node made a player node");
+            };
+            let weights = player_data
+                .regret_matcher
+                .as_ref()
+                .unwrap()
+                .current_weight();
+            idxs.iter().map(|&idx| weights[idx]).collect()
+        };
+        let total: f32 = strategy.iter().sum();
+        if total > 0.0 {
+            strategy.iter_mut().for_each(|p| *p /= total);
+        } else {
+            strategy.fill(1.0 / possible.len() as f32);
+        }
+
+        let exploration = if self.decisions.is_some() {
+            self.exploration
+        } else {
+            0.0
+        };
+        let sampling: Vec<f32> = strategy
+            .iter()
+            .map(|p| exploration / possible.len() as f32 + (1.0 - exploration) * p)
+            .collect();
+        let choice = WeightedIndex::new(&sampling)
+            .unwrap()
+            .sample(&mut crate::core::rng());
+
+        if let Some(decisions) = &self.decisions {
+            decisions.borrow_mut().push(SampledDecision {
+                node_idx,
+                action_idx: idxs[choice],
+                possible: idxs,
+                sample_prob: sampling[choice],
+                strategy_prob: strategy[choice],
+            });
+        }
+        possible[choice].clone()
+    }
+
+    fn historian(&self) -> Option<Box<dyn Historian>> {
+        Some(Box::new(CFRHistorian::<T>::new(
+            self.traversal_state.clone(),
+            self.cfr_state.clone(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::arena::cfr::{BasicCFRActionGenerator, StrategyProfile};
+    use crate::arena::game_state::{Round, RoundData};
+    use crate::core::{Hand, PlayerBitSet};
+
+    #[test]
+    fn test_fold_when_beaten() {
+        // Player 0 is all in on the river with two pair against a pair of
+        // tens, so the only decision is player 1's.
+        let hand_zero = Hand::new_from_str("AsKsKcAcTh4d8d").unwrap();
+        let hand_one = Hand::new_from_str("JdTcKcAcTh4d8d").unwrap();
+        let round_data = RoundData::new_with_bets(100.0, PlayerBitSet::new(2), 1, vec![900.0, 0.0]);
+        let game_state = GameState::new(
+            Round::River,
+            round_data,
+            (hand_zero & hand_one).iter().collect(),
+            vec![hand_zero, hand_one],
+            vec![0.0, 900.0],
+            vec![1000.0, 100.0],
+            5.0,
+            0.0,
+            0.0,
+            0,
+        );
+
+        let mut state_store = StateStore::new();
+        let mut rng = StdRng::seed_from_u64(42);
+        crate::core::with_seed(42, || {
+            OutcomeSamplingConfig::default().train::<BasicCFRActionGenerator, _>(
+                &mut state_store,
+                &game_state,
+                200,
+                &mut rng,
+            )
+        });
+
+        let profile = StrategyProfile::from_state_store(&state_store);
+        let (_, _, strategy) = profile
+            .iter()
+            .find(|(player_idx, _, _)| *player_idx == 1)
+            .unwrap();
+        assert!(strategy[0] > 0.9, "{strategy:?}");
+        assert_eq!(1, state_store.traversal_len(1));
+    }
+
+    #[test]
+    fn test_trees_grow_along_the_hand() {
+        let game_state = GameState::new_starting(vec![20.0; 3], 2.0, 1.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        let config = OutcomeSamplingConfig::default();
+        let num_nodes = |state_store: &StateStore, player_idx| {
+            state_store
+                .get_state(player_idx)
+                .unwrap()
+                .internal_state()
+                .borrow()
+                .nodes
+                .len()
+        };
+
+        config.train::<BasicCFRActionGenerator, _>(
+            &mut state_store,
+            &game_state,
+            1,
+            &mut rand::rng(),
+        );
+        assert_eq!(3, state_store.len());
+        // A single hand adds a single path to each tree: the root, the two
+        // hole cards, at most five board cards, the decisions and the end.
+        for player_idx in 0..3 {
+            let nodes = num_nodes(&state_store, player_idx);
+            assert!(nodes > 3, "{nodes}");
+            assert!(nodes < 40, "{nodes}");
+        }
+
+        config.train::<BasicCFRActionGenerator, _>(
+            &mut state_store,
+            &game_state,
+            30,
+            &mut rand::rng(),
+        );
+        assert_eq!(3, state_store.len());
+        for player_idx in 0..3 {
+            assert_eq!(1, state_store.traversal_len(player_idx));
+        }
+    }
+}
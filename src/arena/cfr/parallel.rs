@@ -0,0 +1,585 @@
+use rayon::prelude::*;
+
+use crate::arena::errors::MergeError;
+use crate::core::{CrateRng, rng, with_seed};
+
+use super::callback::{finish_iteration, tree_sizes};
+use super::{
+    AtomicRegretTable, ConcurrentStateStore, NodeData, PlayerData, RegretDeltas, RegretMatcher,
+    StateStore, TrainingCallback,
+};
+
+/// How the shards of a `ParallelTrainer` add up what they learn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Accumulation {
+    /// Every shard keeps what it learns to itself until the end of the
+    /// round, when it's merged in shard order. Runs are repeatable.
+    #[default]
+    Merge,
+    /// The regrets and strategy sums of the decisions the trees already
+    /// had when the round started are kept in an `AtomicRegretTable`.
+    /// Every `sync_every` iterations each shard adds what it learned to
+    /// the table through its own `RegretDeltas`, without a lock, and takes
+    /// the totals of every shard back, so the shards play against each
+    /// other's regrets during the round as well as after it. Decisions
+    /// first reached during the round are merged at the end as with
+    /// `Merge`. The float sums depend on the order the threads add in, so
+    /// runs aren't repeatable.
+    Atomic { sync_every: usize },
+}
+
+/// Trains CFR on every thread of the rayon pool at once.
+///
+/// The iterations are shared out between `num_shards` shards, each with a
+/// copy of the store being trained that only it trains, so no thread ever
+/// waits on another. Every `merge_every` iterations the shards stop, and
+/// what each learned since its copy, see `StateStore::since`, is merged
+/// into the store with `StateStore::merge_from`, which sums their visits,
+/// regrets and average strategies. The next round every shard starts from
+/// a fresh copy of the merged store, so each plays against the regrets all
+/// of them built up, the same as one run training that many iterations
+/// would, rather than converging on its own.
+///
+/// Each round copies every tree for every shard, so merging less often is
+/// faster, while merging more often keeps the shards closer to one run and
+/// gives a `TrainingCallback` more chances to look at the merged trees and
+/// stop.
+///
+/// Shards are trained with the crate rng seeded from the trainer's seed,
+/// the round and the shard, see `shard_seed`, so a run trains the same
+/// trees however the shards end up scheduled. That doesn't hold with
+/// `Accumulation::Atomic`, which trades it for sharing regrets between
+/// shards more often than merging would, see `with_accumulation`.
+///
+/// Stores and trees aren't `Send`, so the training is done by a closure on
+/// the thread that runs the shard, given the shard's store, how many
+/// iterations to train and an rng, and returning how many it trained.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::cfr::{
+///     BasicCFRActionGenerator, OutcomeSamplingConfig, ParallelTrainer, StateStore,
+/// };
+///
+/// let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+/// let sampling = OutcomeSamplingConfig::default();
+/// let mut state_store = StateStore::new();
+/// let trained = ParallelTrainer::new(4, 42)
+///     .with_merge_every(40)
+///     .train(&mut state_store, 100, |store, iterations, rng| {
+///         sampling.train_with::<BasicCFRActionGenerator, _>(
+///             store,
+///             &game_state,
+///             iterations,
+///             rng,
+///             &mut (),
+///         )
+///     })
+///     .unwrap();
+/// assert_eq!(100, trained);
+/// assert_eq!(2, state_store.len());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallelTrainer {
+    num_shards: usize,
+    merge_every: usize,
+    seed: u64,
+    accumulation: Accumulation,
+}
+
+impl ParallelTrainer {
+    /// A trainer with `num_shards` shards, at least one, merging every
+    /// 1,000 iterations.
+    pub fn new(num_shards: usize, seed: u64) -> Self {
+        Self {
+            num_shards: num_shards.max(1),
+            merge_every: 1_000,
+            seed,
+            accumulation: Accumulation::Merge,
+        }
+    }
+
+    /// Merge the shards every `iterations` iterations, counted over all of
+    /// them.
+    pub fn with_merge_every(mut self, iterations: usize) -> Self {
+        self.merge_every = iterations.max(1);
+        self
+    }
+
+    /// Add up what the shards learn the way `accumulation` says.
+    /// `Accumulation::Atomic` syncs every `sync_every` iterations of each
+    /// shard, at least one.
+    pub fn with_accumulation(mut self, accumulation: Accumulation) -> Self {
+        self.accumulation = match accumulation {
+            Accumulation::Atomic { sync_every } => Accumulation::Atomic {
+                sync_every: sync_every.max(1),
+            },
+            merge => merge,
+        };
+        self
+    }
+
+    pub fn num_shards(&self) -> usize {
+        self.num_shards
+    }
+
+    pub fn merge_every(&self) -> usize {
+        self.merge_every
+    }
+
+    pub fn accumulation(&self) -> Accumulation {
+        self.accumulation
+    }
+
+    /// The seed of the crate rng while `shard_idx` trains in the round
+    /// after `round` merges.
+    pub fn shard_seed(&self, round: usize, shard_idx: usize) -> u64 {
+        self.seed
+            .wrapping_add((round * self.num_shards + shard_idx) as u64)
+    }
+
+    /// Train `iterations` iterations into `state_store` with `train`,
+    /// returning how many were trained.
+    pub fn train<F>(
+        &self,
+        state_store: &mut StateStore,
+        iterations: usize,
+        train: F,
+    ) -> Result<usize, MergeError>
+    where
+        F: Fn(&mut StateStore, usize, &mut CrateRng) -> usize + Sync,
+    {
+        self.train_with(state_store, iterations, &mut (), train)
+    }
+
+    /// Train the same as `train`, telling `callback` about every merge.
+    /// `on_iteration` gets the number of iterations trained so far and the
+    /// merged store, and can stop training there, and `on_node_expanded`
+    /// gets the nodes the merge added.
+    pub fn train_with<C, F>(
+        &self,
+        state_store: &mut StateStore,
+        iterations: usize,
+        callback: &mut C,
+        train: F,
+    ) -> Result<usize, MergeError>
+    where
+        C: TrainingCallback,
+        F: Fn(&mut StateStore, usize, &mut CrateRng) -> usize + Sync,
+    {
+        let mut sizes = tree_sizes(state_store);
+        let mut trained = 0;
+        let mut round = 0;
+        while trained < iterations {
+            let round_iterations = self.merge_every.min(iterations - trained);
+            let shared = ConcurrentStateStore::from_state_store(state_store);
+            let regrets = match self.accumulation {
+                Accumulation::Merge => None,
+                Accumulation::Atomic { .. } => Some(SharedRegrets::new(state_store)),
+            };
+            let results: Vec<Result<(ConcurrentStateStore, usize), MergeError>> = (0..self
+                .num_shards)
+                .into_par_iter()
+                .map(|shard_idx| {
+                    let shard_iterations = round_iterations / self.num_shards
+                        + usize::from(shard_idx < round_iterations % self.num_shards);
+                    if shard_iterations == 0 {
+                        return Ok((
+                            ConcurrentStateStore::from_state_store(&StateStore::new()),
+                            0,
+                        ));
+                    }
+                    with_seed(self.shard_seed(round, shard_idx), || {
+                        self.train_shard(&shared, regrets.as_ref(), shard_iterations, &train)
+                    })
+                })
+                .collect();
+
+            // Merge in shard order so the float sums don't depend on
+            // scheduling.
+            let mut round_trained = 0;
+            for result in results {
+                let (learned, shard_trained) = result?;
+                state_store.merge_from(&learned.snapshot())?;
+                round_trained += shard_trained;
+            }
+            // The merge added up the shards' totals of the shared
+            // decisions, which they'd already taken from each other.
+            if let Some(regrets) = &regrets {
+                regrets.apply(state_store)?;
+            }
+
+            trained += round_trained;
+            round += 1;
+            if round_trained == 0
+                || finish_iteration(callback, state_store, &mut sizes, trained).is_break()
+            {
+                break;
+            }
+        }
+        Ok(trained)
+    }
+
+    /// Train `iterations` iterations of one shard on a copy of `shared`,
+    /// returning what it learned and how many it trained. With `regrets`
+    /// the shard syncs the shared decisions with it as it goes.
+    fn train_shard<F>(
+        &self,
+        shared: &ConcurrentStateStore,
+        regrets: Option<&SharedRegrets>,
+        iterations: usize,
+        train: &F,
+    ) -> Result<(ConcurrentStateStore, usize), MergeError>
+    where
+        F: Fn(&mut StateStore, usize, &mut CrateRng) -> usize,
+    {
+        let base = shared.snapshot();
+        let mut store = shared.snapshot();
+        let mut rng = rng();
+        let trained = match (regrets, self.accumulation) {
+            (Some(regrets), Accumulation::Atomic { sync_every }) => {
+                // Flushed by hand after every sync.
+                let mut deltas = RegretDeltas::new(&regrets.table, usize::MAX);
+                let mut synced = regrets.base.clone();
+                let mut trained = 0;
+                let mut left = iterations;
+                while left > 0 {
+                    let chunk = sync_every.min(left);
+                    trained += train(&mut store, chunk, &mut rng);
+                    left -= chunk;
+
+                    regrets.add(&mut deltas, &store, &synced);
+                    deltas.flush();
+                    regrets.apply(&store)?;
+                    synced = regrets.totals(&store);
+                }
+                trained
+            }
+            _ => train(&mut store, iterations, &mut rng),
+        };
+        let learned = store.since(&base)?;
+        Ok((ConcurrentStateStore::from_state_store(&learned), trained))
+    }
+}
+
+/// The regrets and the strategy sum of a decision.
+type Totals = (Vec<f32>, Vec<f32>);
+
+/// The decisions `Accumulation::Atomic` shares between the shards in a
+/// round, the ones with a regret matcher when it started. Each is an
+/// infoset of `table`, which holds what the shards have added to them
+/// since.
+struct SharedRegrets {
+    /// The player and node of each infoset.
+    nodes: Vec<(usize, usize)>,
+    /// The totals of each infoset when the round started.
+    base: Vec<Totals>,
+    table: AtomicRegretTable,
+}
+
+fn regret_matcher(data: &NodeData) -> Option<&RegretMatcher> {
+    match data {
+        NodeData::Player(player_data) => player_data.regret_matcher.as_deref(),
+        _ => None,
+    }
+}
+
+impl SharedRegrets {
+    fn new(state_store: &StateStore) -> Self {
+        let mut nodes = Vec::new();
+        let mut num_actions = 0;
+        for player_idx in 0..state_store.len() {
+            let cfr_state = state_store.get_state(player_idx).unwrap();
+            let internal = cfr_state.internal_state().borrow();
+            for node in internal.nodes.iter() {
+                if let Some(matcher) = regret_matcher(&node.data) {
+                    nodes.push((player_idx, node.idx));
+                    num_actions = num_actions.max(matcher.num_actions());
+                }
+            }
+        }
+        let mut regrets = Self {
+            table: AtomicRegretTable::new(nodes.len(), num_actions),
+            nodes,
+            base: Vec::new(),
+        };
+        regrets.base = regrets.totals(state_store);
+        regrets
+    }
+
+    /// The totals of every infoset in `state_store`, a copy of the store
+    /// the round started with.
+    fn totals(&self, state_store: &StateStore) -> Vec<Totals> {
+        self.nodes
+            .iter()
+            .map(|&(player_idx, node_idx)| {
+                let cfr_state = state_store.get_state(player_idx).unwrap();
+                let node = cfr_state.get(node_idx).unwrap();
+                let matcher = regret_matcher(&node.data).unwrap();
+                (matcher.regrets(), matcher.strategy_sum().to_vec())
+            })
+            .collect()
+    }
+
+    /// Add what `state_store` learned since its totals were `synced` to
+    /// `deltas`.
+    fn add(&self, deltas: &mut RegretDeltas<'_>, state_store: &StateStore, synced: &[Totals]) {
+        let num_actions = self.table.num_actions();
+        let since = |now: &[f32], then: &[f32]| {
+            let mut delta: Vec<f32> = now.iter().zip(then).map(|(now, then)| now - then).collect();
+            delta.resize(num_actions, 0.0);
+            delta
+        };
+        for (infoset, (now, then)) in self.totals(state_store).iter().zip(synced).enumerate() {
+            deltas.add_regrets(infoset, &since(&now.0, &then.0));
+            deltas.add_strategy(infoset, &since(&now.1, &then.1));
+        }
+    }
+
+    /// Set every infoset in `state_store` to what the round started with
+    /// plus what the shards have added to the table.
+    fn apply(&self, state_store: &StateStore) -> Result<(), MergeError> {
+        for (infoset, (&(player_idx, node_idx), base)) in
+            self.nodes.iter().zip(&self.base).enumerate()
+        {
+            let total = |base: &[f32], added: Vec<f32>| -> Vec<f32> {
+                base.iter()
+                    .zip(added)
+                    .map(|(base, added)| base + added)
+                    .collect()
+            };
+            let regrets = total(&base.0, self.table.regrets(infoset));
+            let strategy_sum = total(&base.1, self.table.strategy_sums(infoset));
+
+            let mut cfr_state = state_store.get_state(player_idx).unwrap();
+            let mut node = cfr_state.get_mut(node_idx).unwrap();
+            if let NodeData::Player(PlayerData {
+                regret_matcher: Some(matcher),
+                ..
+            }) = &mut *node.data
+            {
+                matcher
+                    .set_totals(&regrets, &strategy_sum)
+                    .map_err(|e| MergeError::RegretMatcher(node_idx, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use crate::arena::GameState;
+    use crate::arena::cfr::{
+        BasicCFRActionGenerator, BestResponse, OutcomeSamplingConfig, StrategyProfile,
+    };
+    use crate::core::Hand;
+
+    use super::*;
+
+    fn total_visits(state_store: &StateStore) -> u32 {
+        (0..state_store.len())
+            .map(|player_idx| {
+                let cfr_state = state_store.get_state(player_idx).unwrap();
+                let internal = cfr_state.internal_state().borrow();
+                internal
+                    .nodes
+                    .get(0)
+                    .unwrap()
+                    .iter_counts()
+                    .map(|(_, count)| count)
+                    .sum::<u32>()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_same_however_scheduled() {
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let sampling = OutcomeSamplingConfig::default();
+        let run = || {
+            let mut state_store = StateStore::new();
+            let trained = ParallelTrainer::new(3, 7)
+                .with_merge_every(25)
+                .train(&mut state_store, 100, |store, iterations, rng| {
+                    sampling.train_with::<BasicCFRActionGenerator, _>(
+                        store,
+                        &game_state,
+                        iterations,
+                        rng,
+                        &mut (),
+                    )
+                })
+                .unwrap();
+            assert_eq!(100, trained);
+            state_store
+        };
+        let first = run();
+        let second = run();
+        assert_eq!(
+            first.get_state(0).unwrap().average_strategy(),
+            second.get_state(0).unwrap().average_strategy()
+        );
+
+        // Every hand played goes through each root once.
+        assert_eq!(200, total_visits(&first));
+    }
+
+    #[test]
+    fn test_keeps_existing_trees() {
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let sampling = OutcomeSamplingConfig::default();
+        let mut state_store = StateStore::new();
+        sampling.train::<BasicCFRActionGenerator, _>(
+            &mut state_store,
+            &game_state,
+            10,
+            &mut rand::rng(),
+        );
+
+        struct StopAfter(usize, Vec<usize>);
+        impl TrainingCallback for StopAfter {
+            fn on_iteration(&mut self, iteration: usize, _: &StateStore) -> ControlFlow<()> {
+                self.1.push(iteration);
+                if iteration >= self.0 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+        }
+        let mut callback = StopAfter(20, Vec::new());
+        let trained = ParallelTrainer::new(2, 1)
+            .with_merge_every(10)
+            .train_with(
+                &mut state_store,
+                100,
+                &mut callback,
+                |store, iterations, rng| {
+                    sampling.train_with::<BasicCFRActionGenerator, _>(
+                        store,
+                        &game_state,
+                        iterations,
+                        rng,
+                        &mut (),
+                    )
+                },
+            )
+            .unwrap();
+        assert_eq!(20, trained);
+        assert_eq!(vec![10, 20], callback.1);
+        assert_eq!(2 * (10 + 20), total_visits(&state_store));
+    }
+
+    /// Nine cards and the big blind all in, so the best response can walk
+    /// every deal.
+    fn small_deck() -> GameState {
+        let mut game_state = GameState::new_starting(vec![10.0, 2.0], 2.0, 1.0, 0.0, 0);
+        game_state.deck = Hand::new_from_str("AsKsQsJsTs9h8h7d2c").unwrap().into();
+        game_state
+    }
+
+    fn exploitability(state_store: &StateStore, game_state: &GameState) -> f32 {
+        BestResponse::<BasicCFRActionGenerator>::new(StrategyProfile::from_state_store(state_store))
+            .exploitability(game_state)
+            .unwrap()
+            .mbb_per_hand
+    }
+
+    #[test]
+    fn test_converges_like_one_run() {
+        let game_state = small_deck();
+        let sampling = OutcomeSamplingConfig::default();
+        let train = |store: &mut StateStore, iterations: usize, rng: &mut CrateRng| {
+            sampling.train_with::<BasicCFRActionGenerator, _>(
+                store,
+                &game_state,
+                iterations,
+                rng,
+                &mut (),
+            )
+        };
+
+        // Each shard trains ten iterations a round, against the regrets all
+        // of them have built up, so four shards of 500 train about as well
+        // as one run of 2,000. Averaged over a few seeds to even out the
+        // sampling.
+        let seeds = [0, 1, 2];
+        let mut one_run = 0.0;
+        let mut sharded = 0.0;
+        for seed in seeds {
+            let mut state_store = StateStore::new();
+            with_seed(seed, || train(&mut state_store, 4 * 500, &mut rng()));
+            one_run += exploitability(&state_store, &game_state) / seeds.len() as f32;
+
+            let mut state_store = StateStore::new();
+            ParallelTrainer::new(4, seed)
+                .with_merge_every(40)
+                .train(&mut state_store, 4 * 500, train)
+                .unwrap();
+            sharded += exploitability(&state_store, &game_state) / seeds.len() as f32;
+        }
+        let untrained = exploitability(&StateStore::new(), &game_state);
+        assert!(one_run < untrained / 3.0, "{one_run} {untrained}");
+        assert!(sharded < one_run * 1.2, "{sharded} {one_run}");
+    }
+
+    #[test]
+    fn test_atomic_accumulation() {
+        let game_state = small_deck();
+        let sampling = OutcomeSamplingConfig::default();
+        let train = |store: &mut StateStore, iterations: usize, rng: &mut CrateRng| {
+            sampling.train_with::<BasicCFRActionGenerator, _>(
+                store,
+                &game_state,
+                iterations,
+                rng,
+                &mut (),
+            )
+        };
+        let run = |accumulation: Accumulation, seed: u64| {
+            let mut state_store = StateStore::new();
+            with_seed(seed, || train(&mut state_store, 200, &mut rng()));
+            ParallelTrainer::new(4, seed)
+                .with_merge_every(800)
+                .with_accumulation(accumulation)
+                .train(&mut state_store, 800, train)
+                .unwrap();
+            assert_eq!(2 * (200 + 800), total_visits(&state_store));
+            state_store
+        };
+
+        // Syncing once at the end of the round adds up the same as merging,
+        // less the float error of adding in whatever order.
+        let merged = SharedRegrets::new(&run(Accumulation::Merge, 0)).base;
+        let synced_once =
+            SharedRegrets::new(&run(Accumulation::Atomic { sync_every: 800 }, 0)).base;
+        assert!(!merged.is_empty());
+        assert_eq!(merged.len(), synced_once.len());
+        for (merged, synced) in merged.iter().zip(&synced_once) {
+            for (merged, synced) in [(&merged.0, &synced.0), (&merged.1, &synced.1)] {
+                for (merged, synced) in merged.iter().zip(synced) {
+                    assert!((merged - synced).abs() <= 1e-3 * merged.abs().max(1.0));
+                }
+            }
+        }
+
+        // Syncing as they go, the shards play against each other's regrets
+        // all through the round, which merging only does after it. Averaged
+        // over a few seeds to even out the sampling.
+        let seeds = [1, 2, 3];
+        let mut merged = 0.0;
+        let mut synced = 0.0;
+        for seed in seeds {
+            let state_store = run(Accumulation::Merge, seed);
+            merged += exploitability(&state_store, &game_state) / seeds.len() as f32;
+            let state_store = run(Accumulation::Atomic { sync_every: 10 }, seed);
+            synced += exploitability(&state_store, &game_state) / seeds.len() as f32;
+        }
+        assert!(synced < merged, "{synced} {merged}");
+    }
+}
@@ -0,0 +1,563 @@
+use rand::Rng;
+use rand::distr::Distribution;
+use rand::distr::weighted::WeightedIndex;
+
+use crate::arena::GameState;
+use crate::arena::action::AgentAction;
+use crate::arena::errors::QueryError;
+use crate::arena::game_state::Round;
+use crate::core::{Card, CardSet, Hand};
+
+use super::replay::LineReplayer;
+use super::{ActionGenerator, CFRState, NodeData};
+
+/// The trained strategy at one decision: every action on offer and how
+/// often the average strategy plays it. See `CFRState::query`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionDistribution {
+    actions: Vec<(AgentAction, f32)>,
+}
+
+impl ActionDistribution {
+    /// Each action with its probability, in the order the action generator
+    /// offers them. The probabilities add up to one.
+    pub fn actions(&self) -> &[(AgentAction, f32)] {
+        &self.actions
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// How often `action` is played, zero if it isn't on offer.
+    pub fn probability(&self, action: &AgentAction) -> f32 {
+        self.actions
+            .iter()
+            .filter(|(other, _)| other == action)
+            .map(|(_, probability)| probability)
+            .sum()
+    }
+
+    /// The action played most often, the first of them on a tie.
+    pub fn most_likely(&self) -> Option<&AgentAction> {
+        self.actions
+            .iter()
+            .fold(
+                None,
+                |best: Option<&(AgentAction, f32)>, entry| match best {
+                    Some(best) if best.1 >= entry.1 => Some(best),
+                    _ => Some(entry),
+                },
+            )
+            .map(|(action, _)| action)
+    }
+
+    /// Pick an action the way the strategy does.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Option<AgentAction> {
+        let dist = WeightedIndex::new(self.actions.iter().map(|(_, p)| *p)).ok()?;
+        Some(self.actions[dist.sample(rng)].0.clone())
+    }
+}
+
+/// The betting streets of hold'em, the only ones in the trees.
+const STREETS: [Round; 4] = [Round::Preflop, Round::Flop, Round::Turn, Round::River];
+
+fn street_num(round: Round) -> Option<usize> {
+    STREETS.iter().position(|street| *street == round)
+}
+
+/// How many bets there were on `round` and who made the last.
+fn bets_on(game_state: &GameState, round: Round) -> (u8, Option<usize>) {
+    game_state
+        .street(round)
+        .map_or((0, None), |street| (street.num_bets, street.last_aggressor))
+}
+
+/// Whether `idx` has folded. All in players aren't active either, but are
+/// still in the hand.
+fn folded(game_state: &GameState, idx: usize) -> bool {
+    !game_state.player_active.get(idx) && !game_state.player_all_in.get(idx)
+}
+
+/// The strategy at the decision in `game_state`, see `CFRState::query`.
+pub(crate) fn query<T: ActionGenerator>(
+    cfr_state: &CFRState,
+    game_state: &GameState,
+    hole_cards: Hand,
+) -> Result<ActionDistribution, QueryError> {
+    let player_idx = game_state.to_act_idx();
+    if game_state.is_complete()
+        || street_num(game_state.round).is_none()
+        || !game_state.player_active.get(player_idx)
+    {
+        return Err(QueryError::NoDecision);
+    }
+    let hole = CardSet::from(hole_cards);
+    if hole.count() != 2 {
+        return Err(QueryError::WrongHoleCards(hole.count()));
+    }
+    let starting = cfr_state.starting_game_state();
+    if starting.num_players != game_state.num_players
+        || !game_state.board.starts_with(&starting.board)
+    {
+        return Err(QueryError::DifferentGame);
+    }
+
+    let mut finder = SpotFinder::<T>::new(cfr_state, starting, game_state, hole);
+    let nodes = finder.find().ok_or(QueryError::NotInTree)?;
+    finder.distribution(&nodes)
+}
+
+/// Looks for the node a live hand has got to in a tree.
+///
+/// The tree is keyed by action and card indices, while a game state only
+/// keeps a summary of the betting. So the lines of the tree are played out
+/// from its starting game state, following those that bet the way the hand
+/// did, to find the ones that reach the same decision. Bets the action
+/// generator doesn't make can't be matched exactly, so of the lines with
+/// the same bets and raises on every street the one with the closest
+/// amounts is taken.
+struct SpotFinder<'a, T: ActionGenerator> {
+    cfr_state: &'a CFRState,
+    target: &'a GameState,
+    replayer: LineReplayer<T>,
+    /// The chance children of the hole cards, for each order they could
+    /// have been dealt in. Empty if the tree starts with them known.
+    holes: Vec<Vec<usize>>,
+    /// The chance children of the board cards dealt since the tree's
+    /// starting game state.
+    board: Vec<usize>,
+}
+
+impl<'a, T: ActionGenerator> SpotFinder<'a, T> {
+    fn new(
+        cfr_state: &'a CFRState,
+        starting: GameState,
+        target: &'a GameState,
+        hole: CardSet,
+    ) -> Self {
+        let player_idx = target.to_act_idx();
+        let replayer = LineReplayer::<T>::new(starting, player_idx);
+        let starting = replayer.game_state();
+        let generator = replayer.generator();
+
+        let holes = if starting.hands[player_idx].count() == 0 {
+            let cards: Vec<Card> = hole.iter().collect();
+            let mut holes: Vec<Vec<usize>> = Vec::with_capacity(2);
+            for (first, second) in [(cards[0], cards[1]), (cards[1], cards[0])] {
+                let first_idx = generator.card_to_idx(starting, CardSet::new(), first);
+                let mut game_state = starting.clone();
+                game_state.hands_mut()[player_idx].insert(first);
+                let known = CardSet::from(game_state.hands[player_idx]);
+                let second_idx = generator.card_to_idx(&game_state, known, second);
+                if !holes.contains(&vec![first_idx, second_idx]) {
+                    holes.push(vec![first_idx, second_idx]);
+                }
+            }
+            holes
+        } else {
+            vec![Vec::new()]
+        };
+
+        // The flop is dealt in order, the way the simulation sorts it.
+        let mut cards: Vec<Card> = target.board.iter().copied().collect();
+        if starting.board.is_empty() && cards.len() >= 3 {
+            cards[..3].sort();
+        }
+        let mut board = Vec::with_capacity(cards.len());
+        for dealt in starting.board.len()..cards.len() {
+            let street_start = if dealt < 3 { 0 } else { dealt };
+            let before_street = &cards[..street_start];
+            let mut game_state = starting.clone();
+            let mut hand = hole;
+            hand.extend(before_street.iter().copied());
+            game_state.hands_mut()[player_idx] = Hand::from(hand);
+            *game_state.board_mut() = before_street.iter().copied().collect();
+            let mut known = hole;
+            known.extend(cards[..dealt].iter().copied());
+            board.push(generator.card_to_idx(&game_state, known, cards[dealt]));
+        }
+
+        Self {
+            cfr_state,
+            target,
+            replayer,
+            holes,
+            board,
+        }
+    }
+
+    /// The decision nodes of the line that matches the target best, one
+    /// for each order of the hole cards the tree has.
+    fn find(&mut self) -> Option<Vec<usize>> {
+        let mut best: Option<(f32, Vec<usize>)> = None;
+        let mut stack = vec![Vec::new()];
+        while let Some(line) = stack.pop() {
+            let Some(replayed) = self.replayer.replay(&line) else {
+                continue;
+            };
+            let Some((reached, possible)) = &replayed.decision else {
+                continue;
+            };
+            if !self.consistent(reached) {
+                continue;
+            }
+            let nodes: Vec<usize> = (0..self.holes.len())
+                .filter_map(|hole| self.node_at(&line, hole))
+                .collect();
+            if nodes.is_empty() {
+                continue;
+            }
+            if self.matches(reached) {
+                let distance = self.distance(reached);
+                if best.as_ref().is_none_or(|(closest, _)| distance < *closest) {
+                    best = Some((distance, nodes));
+                }
+                continue;
+            }
+            for (action_idx, _) in possible.iter().rev() {
+                let in_tree = nodes.iter().any(|&node_idx| {
+                    self.cfr_state
+                        .get(node_idx)
+                        .and_then(|node| node.get_child(*action_idx))
+                        .is_some()
+                });
+                if in_tree {
+                    let mut child = line.clone();
+                    child.push(*action_idx);
+                    stack.push(child);
+                }
+            }
+        }
+        best.map(|(_, nodes)| nodes)
+    }
+
+    /// The decision node `line` reaches with the `hole`th order of the hole
+    /// cards, if the tree has one.
+    fn node_at(&mut self, line: &[usize], hole: usize) -> Option<usize> {
+        let starting_board = self.replayer.game_state().board.len();
+        let mut node_idx = self.cfr_state.get(0)?.get_child(0)?;
+        for &card_idx in &self.holes[hole] {
+            node_idx = self.chance_child(node_idx, card_idx)?;
+        }
+        let mut dealt = 0;
+        for step in 0..=line.len() {
+            let replayed = self.replayer.replay(&line[..step])?;
+            let (reached, _) = replayed.decision.as_ref()?;
+            while dealt < reached.board.len() - starting_board {
+                node_idx = self.chance_child(node_idx, *self.board.get(dealt)?)?;
+                dealt += 1;
+            }
+            let node = self.cfr_state.get(node_idx)?;
+            let NodeData::Player(player_data) = &*node.data else {
+                return None;
+            };
+            if player_data.player_idx != reached.to_act_idx() {
+                return None;
+            }
+            if step == line.len() {
+                return Some(node_idx);
+            }
+            node_idx = node.get_child(line[step])?;
+        }
+        None
+    }
+
+    fn chance_child(&self, node_idx: usize, card_idx: usize) -> Option<usize> {
+        let node = self.cfr_state.get(node_idx)?;
+        if !node.data.is_chance() {
+            return None;
+        }
+        node.get_child(card_idx)
+    }
+
+    /// Whether the betting that got to `reached` could carry on to the
+    /// target: no one folded who's still in, earlier streets had the same
+    /// bets and raises, and this one no more.
+    fn consistent(&self, reached: &GameState) -> bool {
+        let (Some(at), Some(target_at)) =
+            (street_num(reached.round), street_num(self.target.round))
+        else {
+            return false;
+        };
+        if at > target_at
+            || (0..self.target.num_players)
+                .any(|idx| folded(reached, idx) && !folded(self.target, idx))
+        {
+            return false;
+        }
+        STREETS[..=at].iter().enumerate().all(|(num, round)| {
+            let bets = bets_on(reached, *round);
+            let target_bets = bets_on(self.target, *round);
+            if num < at {
+                bets == target_bets
+            } else {
+                bets.0 <= target_bets.0
+            }
+        })
+    }
+
+    /// Whether `reached` is the target's decision, give or take bet sizes.
+    fn matches(&self, reached: &GameState) -> bool {
+        reached.round == self.target.round
+            && reached.to_act_idx() == self.target.to_act_idx()
+            && (0..self.target.num_players)
+                .all(|idx| folded(reached, idx) == folded(self.target, idx))
+            && STREETS
+                .iter()
+                .all(|round| bets_on(reached, *round) == bets_on(self.target, *round))
+    }
+
+    /// How far the amounts bet to get to `reached` are from the target's,
+    /// in big blinds.
+    fn distance(&self, reached: &GameState) -> f32 {
+        let bet = |game_state: &GameState, round: Round, idx: usize| {
+            game_state
+                .street(round)
+                .map_or(0.0, |street| street.player_bet[idx])
+        };
+        let mut distance = 0.0;
+        for round in STREETS {
+            for idx in 0..self.target.num_players {
+                distance += (bet(reached, round, idx) - bet(self.target, round, idx)).abs();
+            }
+        }
+        distance / self.target.big_blind.max(f32::EPSILON)
+    }
+
+    /// The average strategy of `nodes`, weighted by how often each was
+    /// reached, over the actions on offer in the target.
+    fn distribution(&self, nodes: &[usize]) -> Result<ActionDistribution, QueryError> {
+        let mut summed: Vec<f32> = Vec::new();
+        for &node_idx in nodes {
+            let node = self.cfr_state.get(node_idx).ok_or(QueryError::NotInTree)?;
+            let NodeData::Player(player_data) = &*node.data else {
+                continue;
+            };
+            let Some(matcher) = &player_data.regret_matcher else {
+                continue;
+            };
+            let weights = matcher.best_weight();
+            let total: f32 = weights.iter().sum();
+            if !total.is_finite() || total <= 0.0 {
+                continue;
+            }
+            let visits = node
+                .parent
+                .zip(node.parent_child_idx)
+                .and_then(|(parent, child_idx)| {
+                    self.cfr_state
+                        .get(parent)
+                        .map(|parent| parent.get_count(child_idx))
+                })
+                .unwrap_or(0)
+                .max(1) as f32;
+            if summed.len() < weights.len() {
+                summed.resize(weights.len(), 0.0);
+            }
+            for (sum, weight) in summed.iter_mut().zip(weights) {
+                *sum += visits * weight / total;
+            }
+        }
+        if summed.is_empty() {
+            return Err(QueryError::Untrained);
+        }
+
+        let possible = self.replayer.possible(self.target);
+        let total: f32 = possible
+            .iter()
+            .map(|(idx, _)| summed.get(*idx).copied().unwrap_or(0.0))
+            .sum();
+        let num_possible = possible.len() as f32;
+        let actions = possible
+            .into_iter()
+            .map(|(idx, action)| {
+                let probability = if total > 0.0 {
+                    summed.get(idx).copied().unwrap_or(0.0) / total
+                } else {
+                    1.0 / num_possible
+                };
+                (action, probability)
+            })
+            .collect();
+        Ok(ActionDistribution { actions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use crate::arena::cfr::{
+        BasicCFRActionGenerator, OutcomeSamplingConfig, PlayerData, RegretMatcher, StateStore,
+    };
+
+    use super::*;
+
+    fn hand(cards: &str) -> Hand {
+        Hand::new_from_str(cards).unwrap()
+    }
+
+    /// Add a decision for `player_idx`, trained once with `rewards` if
+    /// there are any.
+    fn player(
+        cfr_state: &mut CFRState,
+        parent: usize,
+        child: usize,
+        player_idx: usize,
+        rewards: Option<ndarray::Array1<f32>>,
+    ) -> usize {
+        let regret_matcher = rewards.map(|rewards| {
+            let mut matcher = RegretMatcher::new(3).unwrap();
+            matcher.update_regret(rewards.view()).unwrap();
+            Box::new(matcher)
+        });
+        cfr_state.add(
+            parent,
+            child,
+            NodeData::Player(PlayerData {
+                regret_matcher,
+                player_idx,
+            }),
+        )
+    }
+
+    fn probabilities(dist: &ActionDistribution) -> Vec<f32> {
+        dist.actions().iter().map(|(_, p)| *p).collect()
+    }
+
+    /// A tree whose owner was dealt `first` then `second`, and where the
+    /// first decision goes, under the second card's chance node.
+    fn dealt(first: &str, second: &str) -> (CFRState, usize, usize) {
+        let game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut cfr_state = CFRState::new(game_state);
+        let hole = cfr_state.add(0, 0, NodeData::Chance);
+        let first = u8::from(Card::try_from(first).unwrap()) as usize;
+        let second = u8::from(Card::try_from(second).unwrap()) as usize;
+        let dealt = cfr_state.add(hole, first, NodeData::Chance);
+        (cfr_state, dealt, second)
+    }
+
+    fn preflop(cfr_state: &CFRState) -> GameState {
+        let mut replayer =
+            LineReplayer::<BasicCFRActionGenerator>::new(cfr_state.starting_game_state(), 0);
+        let replayed = replayer.replay(&[]).unwrap();
+        replayed.decision.as_ref().unwrap().0.clone()
+    }
+
+    #[test]
+    fn test_first_decision() {
+        let (mut cfr_state, dealt, second) = dealt("As", "Ks");
+        player(
+            &mut cfr_state,
+            dealt,
+            second,
+            0,
+            Some(array![0.0, 4.0, 4.0]),
+        );
+        let game_state = preflop(&cfr_state);
+
+        // The hole cards can be given in either order.
+        for cards in ["AsKs", "KsAs"] {
+            let dist = cfr_state
+                .query::<BasicCFRActionGenerator>(&game_state, hand(cards))
+                .unwrap();
+            assert_eq!(vec![0.0, 0.5, 0.5], probabilities(&dist));
+            assert_eq!(0.0, dist.probability(&AgentAction::Fold));
+            assert_eq!(Some(&dist.actions()[1].0), dist.most_likely());
+        }
+
+        assert_eq!(
+            Err(QueryError::NotInTree),
+            cfr_state.query::<BasicCFRActionGenerator>(&game_state, hand("QsQh"))
+        );
+        assert_eq!(
+            Err(QueryError::WrongHoleCards(1)),
+            cfr_state.query::<BasicCFRActionGenerator>(&game_state, hand("As"))
+        );
+    }
+
+    #[test]
+    fn test_untrained() {
+        let (mut cfr_state, dealt, second) = dealt("As", "Ks");
+        player(&mut cfr_state, dealt, second, 0, None);
+        let game_state = preflop(&cfr_state);
+        assert_eq!(
+            Err(QueryError::Untrained),
+            cfr_state.query::<BasicCFRActionGenerator>(&game_state, hand("AsKs"))
+        );
+    }
+
+    #[test]
+    fn test_closest_bet() {
+        // The big blind's tree, where the button only ever goes all in.
+        let (mut cfr_state, dealt, second) = dealt("Qh", "Qd");
+        let button = player(&mut cfr_state, dealt, second, 0, None);
+        player(&mut cfr_state, button, 2, 1, Some(array![0.0, 8.0, 0.0]));
+
+        // The button raises to 10 instead, which the tree doesn't have, so
+        // it's taken as the all in.
+        let mut game_state = preflop(&cfr_state);
+        game_state.do_bet(10.0, false).unwrap();
+        assert_eq!(1, game_state.to_act_idx());
+        let dist = cfr_state
+            .query::<BasicCFRActionGenerator>(&game_state, hand("QhQd"))
+            .unwrap();
+        assert_eq!(vec![0.0, 1.0, 0.0], probabilities(&dist));
+
+        // Calling isn't in the tree at all.
+        let mut game_state = preflop(&cfr_state);
+        game_state.do_bet(2.0, false).unwrap();
+        assert_eq!(
+            Err(QueryError::NotInTree),
+            cfr_state.query::<BasicCFRActionGenerator>(&game_state, hand("QhQd"))
+        );
+    }
+
+    #[test]
+    fn test_trained() {
+        let game_state = GameState::new_starting(vec![20.0; 2], 2.0, 1.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        OutcomeSamplingConfig::default().train::<BasicCFRActionGenerator, _>(
+            &mut state_store,
+            &game_state,
+            500,
+            &mut rand::rng(),
+        );
+        let cfr_state = state_store.get_state(0).unwrap();
+        let decision = preflop(&cfr_state);
+
+        // Every hand the button was dealt is found, though outcome sampling
+        // might not have trained them all yet.
+        let hole = cfr_state.get(0).unwrap().get_child(0).unwrap();
+        let mut dealt = Vec::new();
+        for (first, first_child) in cfr_state.get(hole).unwrap().iter_children() {
+            for (second, _) in cfr_state.get(first_child).unwrap().iter_children() {
+                dealt.push(Hand::new_with_cards(vec![
+                    Card::from(first as u8),
+                    Card::from(second as u8),
+                ]));
+            }
+        }
+        assert!(!dealt.is_empty());
+        let mut trained = 0;
+        for hole_cards in dealt {
+            let dist = match state_store.query::<BasicCFRActionGenerator>(&decision, hole_cards) {
+                Ok(dist) => dist,
+                Err(err) => {
+                    assert_eq!(QueryError::Untrained, err);
+                    continue;
+                }
+            };
+            trained += 1;
+            let total: f32 = dist.actions().iter().map(|(_, p)| p).sum();
+            assert!((total - 1.0).abs() < 1e-5);
+            assert!(dist.sample(&mut rand::rng()).is_some());
+        }
+        assert!(trained > 0);
+    }
+}
@@ -0,0 +1,723 @@
+use ndarray::{Array1, ArrayView1, Zip};
+use rand::Rng;
+use rand_distr::Distribution;
+use rand_distr::weighted::WeightedAliasIndex;
+use serde::{Deserialize, Serialize};
+
+use crate::arena::errors::RegretMatcherError;
+
+/// Regret matching over a fixed number of actions, the learner at every
+/// decision in a CFR tree.
+///
+/// This works the same as `little_sorry::RegretMatcher`, but everything it
+/// has learned can be serialized, so a saved `CFRState` or `StateStore`
+/// carries on training where it left off when it's loaded. The alias table
+/// actions are sampled from isn't saved, it's rebuilt from the current
+/// strategy.
+///
+/// # Example
+///
+/// ```
+/// use ndarray::array;
+/// use rs_poker::arena::cfr::RegretMatcher;
+///
+/// let mut matcher = RegretMatcher::new(2).unwrap();
+/// matcher.update_regret(array![0.0, 1.0].view()).unwrap();
+///
+/// let json = serde_json::to_string(&matcher).unwrap();
+/// let loaded: RegretMatcher = serde_json::from_str(&json).unwrap();
+/// assert_eq!(matcher, loaded);
+/// assert_eq!(vec![0.0, 1.0], loaded.best_weight());
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RegretMatcherData", into = "RegretMatcherData")]
+pub struct RegretMatcher {
+    /// The current strategy.
+    p: Array1<f32>,
+    /// The sum of every strategy played, for the average strategy.
+    sum_p: Array1<f32>,
+    /// What each action would have earned if it was always played.
+    expert_reward: Array1<f32>,
+    /// What the strategies played earned.
+    cumulative_reward: f32,
+    dist: WeightedAliasIndex<f32>,
+    num_updates: usize,
+}
+
+/// How a `RegretMatcher` turns rewards into regrets and averages the
+/// strategies it played.
+///
+/// The matcher doesn't remember which was used, it's up to whatever is
+/// training to pass the same one every time.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum RegretUpdate {
+    /// Plain regret matching. Regrets add up as they are and every strategy
+    /// counts the same in the average.
+    #[default]
+    Vanilla,
+    /// CFR+. Regrets are floored at zero after every update, so an action
+    /// that did badly early on recovers as soon as it starts doing well,
+    /// and the strategy from the `t`th update counts `t` times in the
+    /// average, so early strategies wash out.
+    Plus,
+    /// Discounted CFR. After every update positive regrets are multiplied
+    /// by `t^alpha / (t^alpha + 1)` and negative ones by
+    /// `t^beta / (t^beta + 1)`, where `t` counts the updates, and the
+    /// average so far by `(t / (t + 1))^gamma`. `RegretUpdate::DCFR` has
+    /// the values the paper recommends.
+    Discounted { alpha: f32, beta: f32, gamma: f32 },
+    /// Linear CFR. The regrets and the strategy from the `t`th update both
+    /// count `t` times.
+    Linear,
+}
+
+impl RegretUpdate {
+    /// Discounted CFR with the parameters Brown and Sandholm found worked
+    /// best in no limit hold'em: alpha 1.5, beta 0 and gamma 2.
+    pub const DCFR: Self = Self::Discounted {
+        alpha: 1.5,
+        beta: 0.0,
+        gamma: 2.0,
+    };
+
+    /// The weights for the `t`th update, counting from one. Plain regret
+    /// matching has none, it keeps the behavior of `little_sorry`.
+    pub fn iteration_weights(&self, t: usize) -> Option<IterationWeights> {
+        let t = t as f32;
+        match *self {
+            Self::Vanilla => None,
+            Self::Plus => Some(IterationWeights {
+                negative: 0.0,
+                strategy: t,
+                ..IterationWeights::default()
+            }),
+            Self::Discounted { alpha, beta, gamma } => {
+                let average = (t / (t + 1.0)).powf(gamma);
+                Some(IterationWeights {
+                    positive: t.powf(alpha) / (t.powf(alpha) + 1.0),
+                    negative: t.powf(beta) / (t.powf(beta) + 1.0),
+                    strategy: average,
+                    average,
+                    ..IterationWeights::default()
+                })
+            }
+            Self::Linear => Some(IterationWeights {
+                regret: t,
+                strategy: t,
+                ..IterationWeights::default()
+            }),
+        }
+    }
+}
+
+/// How much one update counts against the ones before it, for
+/// `RegretMatcher::update_regret_weighted`. The default counts everything
+/// the same.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationWeights {
+    /// Multiplies the regrets from this update.
+    pub regret: f32,
+    /// Multiplies the positive regrets once this update is added.
+    pub positive: f32,
+    /// Multiplies the negative regrets once this update is added. Zero
+    /// floors them.
+    pub negative: f32,
+    /// Multiplies this update's strategy in the average.
+    pub strategy: f32,
+    /// Multiplies the average so far.
+    pub average: f32,
+}
+
+impl Default for IterationWeights {
+    fn default() -> Self {
+        Self {
+            regret: 1.0,
+            positive: 1.0,
+            negative: 1.0,
+            strategy: 1.0,
+            average: 1.0,
+        }
+    }
+}
+
+/// The part of a `RegretMatcher` that's saved.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RegretMatcherData {
+    pub(crate) p: Vec<f32>,
+    pub(crate) sum_p: Vec<f32>,
+    pub(crate) expert_reward: Vec<f32>,
+    pub(crate) cumulative_reward: f32,
+    pub(crate) num_updates: usize,
+}
+
+impl RegretMatcher {
+    /// A matcher that starts out playing every action as often.
+    pub fn new(num_actions: usize) -> Result<Self, RegretMatcherError> {
+        Self::new_from_p(vec![1.0 / num_actions as f32; num_actions])
+    }
+
+    /// A matcher that starts out playing the strategy `p`.
+    pub fn new_from_p(p: Vec<f32>) -> Result<Self, RegretMatcherError> {
+        let num_actions = p.len();
+        Ok(Self {
+            dist: alias_table(&p)?,
+            p: Array1::from(p),
+            sum_p: Array1::zeros(num_actions),
+            expert_reward: Array1::zeros(num_actions),
+            cumulative_reward: 0.0,
+            num_updates: 0,
+        })
+    }
+
+    /// A matcher for `mapping.len()` actions that starts from what `prior`
+    /// has learned. Action `i` takes the regret of `prior`'s action
+    /// `mapping[i]`, and an even share of its average strategy with the
+    /// other actions mapped to it. Actions mapped to `None`, or past the end
+    /// of `prior`, start at zero. Everything taken is scaled by `weight`.
+    pub fn warm_started(
+        prior: &RegretMatcher,
+        mapping: &[Option<usize>],
+        weight: f32,
+    ) -> Result<Self, RegretMatcherError> {
+        let mapping: Vec<Option<usize>> = mapping
+            .iter()
+            .map(|idx| idx.filter(|&idx| idx < prior.num_actions()))
+            .collect();
+        let mut shares = vec![0; prior.num_actions()];
+        for idx in mapping.iter().flatten() {
+            shares[*idx] += 1;
+        }
+        let regrets = prior.regrets();
+        let expert_reward: Array1<f32> = mapping
+            .iter()
+            .map(|idx| idx.map_or(0.0, |idx| regrets[idx] * weight))
+            .collect();
+        let sum_p: Array1<f32> = mapping
+            .iter()
+            .map(|idx| idx.map_or(0.0, |idx| prior.sum_p[idx] * weight / shares[idx] as f32))
+            .collect();
+
+        let num_actions = mapping.len();
+        let total: f32 = expert_reward.iter().map(|r| r.max(0.0)).sum();
+        let p = if total > 0.0 {
+            expert_reward.mapv(|r| r.max(0.0) / total)
+        } else {
+            Array1::from(vec![1.0 / num_actions as f32; num_actions])
+        };
+        Ok(Self {
+            dist: alias_table(p.as_slice().unwrap())?,
+            p,
+            sum_p,
+            expert_reward,
+            cumulative_reward: 0.0,
+            num_updates: prior.num_updates,
+        })
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.p.len()
+    }
+
+    /// How many updates the matcher has had.
+    pub fn num_updates(&self) -> usize {
+        self.num_updates
+    }
+
+    /// Sample an action from the current strategy.
+    pub fn next_action<R: Rng>(&self, rng: &mut R) -> usize {
+        self.dist.sample(rng)
+    }
+
+    /// Learn from what each action would have earned this time.
+    pub fn update_regret(&mut self, rewards: ArrayView1<f32>) -> Result<(), RegretMatcherError> {
+        self.update_regret_with(rewards, RegretUpdate::Vanilla)
+    }
+
+    /// Learn from what each action would have earned this time, updating
+    /// the regrets and the average the way `update` says.
+    pub fn update_regret_with(
+        &mut self,
+        rewards: ArrayView1<f32>,
+        update: RegretUpdate,
+    ) -> Result<(), RegretMatcherError> {
+        match update.iteration_weights(self.num_updates + 1) {
+            Some(weights) => self.update_regret_weighted(rewards, weights),
+            None => {
+                self.update_vanilla(rewards);
+                self.dist = alias_table(self.p.as_slice().unwrap())?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Learn from what each action would have earned this time, weighting
+    /// the update with `weights`. This is what every `RegretUpdate` but
+    /// plain regret matching uses, and it's open for schemes of your own.
+    pub fn update_regret_weighted(
+        &mut self,
+        rewards: ArrayView1<f32>,
+        weights: IterationWeights,
+    ) -> Result<(), RegretMatcherError> {
+        // The weighted regrets are kept as the expert rewards, against a
+        // cumulative reward of zero, so the whole update is one pass over
+        // the actions with nothing allocated.
+        let played = weights.regret * self.p.dot(&rewards) + self.cumulative_reward;
+        Zip::from(&mut self.expert_reward)
+            .and(&rewards)
+            .for_each(|expert, &reward| {
+                let regret = *expert + reward * weights.regret - played;
+                *expert = if regret > 0.0 {
+                    regret * weights.positive
+                } else {
+                    regret * weights.negative
+                };
+            });
+        self.cumulative_reward = 0.0;
+
+        self.match_regrets(0.0);
+        self.sum_p *= weights.average;
+        self.sum_p.scaled_add(weights.strategy, &self.p);
+        self.num_updates += 1;
+        self.dist = alias_table(self.p.as_slice().unwrap())?;
+        Ok(())
+    }
+
+    fn update_vanilla(&mut self, rewards: ArrayView1<f32>) {
+        self.cumulative_reward += self.p.dot(&rewards);
+        self.expert_reward += &rewards;
+        if self.match_regrets(self.cumulative_reward) {
+            self.sum_p += &self.p;
+            self.num_updates += 1;
+        } else {
+            // No action would have done better than what was played, so
+            // start over from the uniform strategy.
+            self.cumulative_reward = 0.0;
+            self.expert_reward.fill(0.0);
+            self.num_updates = 0;
+        }
+    }
+
+    /// Set the current strategy to the positive regrets, the expert rewards
+    /// less `cumulative_reward`, in proportion, in place. With no positive
+    /// regret every action is played as often and this returns false.
+    fn match_regrets(&mut self, cumulative_reward: f32) -> bool {
+        Zip::from(&mut self.p)
+            .and(&self.expert_reward)
+            .for_each(|p, &expert| *p = (expert - cumulative_reward).max(0.0));
+        let total = self.p.sum();
+        if total > 0.0 {
+            self.p /= total;
+            true
+        } else {
+            self.p.fill(1.0 / self.num_actions() as f32);
+            false
+        }
+    }
+
+    /// Add what `other` has learned to this matcher, as if it had had
+    /// `other`'s updates as well. Regrets, the average strategy and the
+    /// number of updates are summed, and the current strategy is worked out
+    /// again from the summed regrets. Both have to be for the same number
+    /// of actions.
+    pub fn merge(&mut self, other: &RegretMatcher) -> Result<(), RegretMatcherError> {
+        if self.num_actions() != other.num_actions() {
+            return Err(RegretMatcherError::DifferentNumberOfActions {
+                expected: self.num_actions(),
+                found: other.num_actions(),
+            });
+        }
+        self.expert_reward += &other.expert_reward;
+        self.cumulative_reward += other.cumulative_reward;
+        self.sum_p += &other.sum_p;
+        self.num_updates += other.num_updates;
+
+        self.match_regrets(self.cumulative_reward);
+        self.dist = alias_table(self.p.as_slice().unwrap())?;
+        Ok(())
+    }
+
+    /// Take out what `base` has learned, the opposite of `merge`. For a
+    /// copy of `base` that's been updated since, this leaves just those
+    /// updates, ready to `merge` into whatever `base` has become. Both have
+    /// to be for the same number of actions.
+    pub fn subtract(&mut self, base: &RegretMatcher) -> Result<(), RegretMatcherError> {
+        if self.num_actions() != base.num_actions() {
+            return Err(RegretMatcherError::DifferentNumberOfActions {
+                expected: self.num_actions(),
+                found: base.num_actions(),
+            });
+        }
+        self.expert_reward -= &base.expert_reward;
+        self.cumulative_reward -= base.cumulative_reward;
+        self.sum_p -= &base.sum_p;
+        self.num_updates = self.num_updates.saturating_sub(base.num_updates);
+
+        self.match_regrets(self.cumulative_reward);
+        self.dist = alias_table(self.p.as_slice().unwrap())?;
+        Ok(())
+    }
+
+    /// How much better each action would have done than the strategies
+    /// played, over every update so far.
+    pub fn regrets(&self) -> Vec<f32> {
+        (&self.expert_reward - self.cumulative_reward).to_vec()
+    }
+
+    /// The sum of every strategy played, weighted the way the updates said.
+    pub fn strategy_sum(&self) -> &[f32] {
+        self.sum_p.as_slice().unwrap()
+    }
+
+    /// Replace the regrets and the sum of the strategies played with totals
+    /// kept elsewhere, such as in an `AtomicRegretTable`, and work the
+    /// current strategy out again. Both have to be for the same number of
+    /// actions as the matcher.
+    pub fn set_totals(
+        &mut self,
+        regrets: &[f32],
+        strategy_sum: &[f32],
+    ) -> Result<(), RegretMatcherError> {
+        for found in [regrets.len(), strategy_sum.len()] {
+            if found != self.num_actions() {
+                return Err(RegretMatcherError::DifferentNumberOfActions {
+                    expected: self.num_actions(),
+                    found,
+                });
+            }
+        }
+        self.expert_reward = Array1::from(regrets.to_vec());
+        self.cumulative_reward = 0.0;
+        self.sum_p = Array1::from(strategy_sum.to_vec());
+
+        self.match_regrets(0.0);
+        self.dist = alias_table(self.p.as_slice().unwrap())?;
+        Ok(())
+    }
+
+    /// The strategy the matcher is playing now.
+    pub fn current_weight(&self) -> &[f32] {
+        self.p.as_slice().unwrap()
+    }
+
+    /// The average strategy over every update, weighted the way the
+    /// updates said.
+    pub fn best_weight(&self) -> Vec<f32> {
+        (self.sum_p.clone() / self.sum_p.sum()).to_vec()
+    }
+}
+
+fn alias_table(p: &[f32]) -> Result<WeightedAliasIndex<f32>, RegretMatcherError> {
+    WeightedAliasIndex::new(p.to_vec()).map_err(|_| RegretMatcherError::InvalidWeights)
+}
+
+/// The alias table is built from the strategy, so it's left out.
+impl PartialEq for RegretMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.p == other.p
+            && self.sum_p == other.sum_p
+            && self.expert_reward == other.expert_reward
+            && self.cumulative_reward == other.cumulative_reward
+            && self.num_updates == other.num_updates
+    }
+}
+
+impl From<RegretMatcher> for RegretMatcherData {
+    fn from(matcher: RegretMatcher) -> Self {
+        Self {
+            p: matcher.p.to_vec(),
+            sum_p: matcher.sum_p.to_vec(),
+            expert_reward: matcher.expert_reward.to_vec(),
+            cumulative_reward: matcher.cumulative_reward,
+            num_updates: matcher.num_updates,
+        }
+    }
+}
+
+impl TryFrom<RegretMatcherData> for RegretMatcher {
+    type Error = RegretMatcherError;
+
+    fn try_from(data: RegretMatcherData) -> Result<Self, Self::Error> {
+        let num_actions = data.p.len();
+        if data.sum_p.len() != num_actions || data.expert_reward.len() != num_actions {
+            return Err(RegretMatcherError::MismatchedLengths);
+        }
+        Ok(Self {
+            dist: alias_table(&data.p)?,
+            p: Array1::from(data.p),
+            sum_p: Array1::from(data.sum_p),
+            expert_reward: Array1::from(data.expert_reward),
+            cumulative_reward: data.cumulative_reward,
+            num_updates: data.num_updates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_matches_little_sorry() {
+        let mut ours = RegretMatcher::new(3).unwrap();
+        let mut theirs = little_sorry::RegretMatcher::new(3).unwrap();
+        for rewards in [
+            array![1.0, -2.0, 0.5],
+            array![0.0, 3.0, -1.0],
+            array![-1.0, -1.0, -1.0],
+        ] {
+            ours.update_regret(rewards.view()).unwrap();
+            theirs.update_regret(rewards.view()).unwrap();
+            for (theirs, ours) in theirs.best_weight().iter().zip(ours.best_weight()) {
+                assert!((theirs - ours).abs() < 1e-6, "{theirs} != {ours}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_plus_floors_regrets() {
+        let mut vanilla = RegretMatcher::new(2).unwrap();
+        let mut plus = RegretMatcher::new(2).unwrap();
+        for rewards in [array![0.0, 10.0], array![4.0, 0.0]] {
+            vanilla.update_regret(rewards.view()).unwrap();
+            plus.update_regret_with(rewards.view(), RegretUpdate::Plus)
+                .unwrap();
+        }
+
+        // The first action is still in the red without the floor.
+        assert_eq!(&[0.0, 1.0], vanilla.current_weight());
+        assert_eq!(&[4.0 / 9.0, 5.0 / 9.0], plus.current_weight());
+        // The second strategy counts twice.
+        let best = plus.best_weight();
+        assert!((best[0] - 8.0 / 27.0).abs() < 1e-6, "{best:?}");
+    }
+
+    #[test]
+    fn test_discounted() {
+        // With alpha and beta zero every regret is halved after an update,
+        // and with gamma zero every strategy counts the same.
+        let mut halved = RegretMatcher::new(2).unwrap();
+        let update = RegretUpdate::Discounted {
+            alpha: 0.0,
+            beta: 0.0,
+            gamma: 0.0,
+        };
+        halved
+            .update_regret_with(array![2.0, 0.0].view(), update)
+            .unwrap();
+        assert_eq!(&[1.0, 0.0], halved.current_weight());
+        // Regrets of 1 and -1 were halved, so this makes them 0.5 and 1.5
+        // before halving again.
+        halved
+            .update_regret_with(array![0.0, 2.0].view(), update)
+            .unwrap();
+        assert_eq!(&[0.25, 0.75], halved.current_weight());
+        assert_eq!(vec![0.625, 0.375], halved.best_weight());
+
+        let mut dcfr = RegretMatcher::new(3).unwrap();
+        for rewards in [array![1.0, 0.0, 0.0], array![0.0, 0.0, 5.0]] {
+            dcfr.update_regret_with(rewards.view(), RegretUpdate::DCFR)
+                .unwrap();
+        }
+        // The second action never did better than the strategy, and the
+        // third is well ahead of the first.
+        let weights = dcfr.current_weight();
+        assert_eq!(0.0, weights[1]);
+        assert!(weights[2] > 0.9, "{weights:?}");
+    }
+
+    #[test]
+    fn test_linear() {
+        let mut linear = RegretMatcher::new(2).unwrap();
+        for rewards in [array![0.0, 1.0], array![3.0, 0.0]] {
+            linear
+                .update_regret_with(rewards.view(), RegretUpdate::Linear)
+                .unwrap();
+        }
+        assert_eq!(2, linear.num_updates());
+
+        // The second update's regrets count twice, so the first action
+        // ends up 5.5 ahead instead of 2.5, and its strategy counts twice
+        // in the average.
+        let close = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-6);
+        assert!(close(&[11.0 / 12.0, 1.0 / 12.0], linear.current_weight()));
+        assert!(close(&[11.0 / 18.0, 7.0 / 18.0], &linear.best_weight()));
+
+        // The same weights by hand.
+        let mut weighted = RegretMatcher::new(2).unwrap();
+        for (t, rewards) in [array![0.0, 1.0], array![3.0, 0.0]].iter().enumerate() {
+            let weights = IterationWeights {
+                regret: (t + 1) as f32,
+                strategy: (t + 1) as f32,
+                ..IterationWeights::default()
+            };
+            weighted
+                .update_regret_weighted(rewards.view(), weights)
+                .unwrap();
+        }
+        assert_eq!(linear, weighted);
+    }
+
+    #[test]
+    fn test_warm_started() {
+        let mut prior = RegretMatcher::new(2).unwrap();
+        prior.update_regret(array![0.0, 3.0].view()).unwrap();
+        assert_eq!(vec![-1.5, 1.5], prior.regrets());
+
+        // The second prior action is split in two and there's a new action
+        // it knows nothing about.
+        let mapping = [Some(0), Some(1), Some(1), None];
+        let matcher = RegretMatcher::warm_started(&prior, &mapping, 0.5).unwrap();
+        assert_eq!(vec![-0.75, 0.75, 0.75, 0.0], matcher.regrets());
+        assert_eq!(&[0.0, 0.5, 0.5, 0.0], matcher.current_weight());
+        assert_eq!(vec![0.0, 0.5, 0.5, 0.0], matcher.best_weight());
+        assert_eq!(1, matcher.num_updates());
+
+        let mut matcher = RegretMatcher::warm_started(&prior, &[None, Some(7)], 1.0).unwrap();
+        assert_eq!(&[0.5, 0.5], matcher.current_weight());
+        matcher.update_regret(array![1.0, 0.0].view()).unwrap();
+        assert_eq!(&[1.0, 0.0], matcher.current_weight());
+    }
+
+    #[test]
+    fn test_wide_matches_per_action() {
+        // The update works on every action at once. Check it against the
+        // same sums worked out one action at a time.
+        let num_actions = 64;
+        for update in [RegretUpdate::Plus, RegretUpdate::DCFR, RegretUpdate::Linear] {
+            let mut matcher = RegretMatcher::new(num_actions).unwrap();
+            let mut regrets = vec![0.0_f32; num_actions];
+            let mut sum_p = vec![0.0_f32; num_actions];
+            for t in 1..=20 {
+                let rewards: Vec<f32> = (0..num_actions)
+                    .map(|idx| ((idx * 7 + t * 13) % 23) as f32 - 11.0)
+                    .collect();
+                let weights = update.iteration_weights(t).unwrap();
+                let p = matcher.current_weight().to_vec();
+                let played: f32 = (0..num_actions).map(|idx| p[idx] * rewards[idx]).sum();
+                for idx in 0..num_actions {
+                    let regret = regrets[idx] + (rewards[idx] - played) * weights.regret;
+                    regrets[idx] = if regret > 0.0 {
+                        regret * weights.positive
+                    } else {
+                        regret * weights.negative
+                    };
+                }
+                let total: f32 = regrets.iter().map(|r| r.max(0.0)).sum();
+                for idx in 0..num_actions {
+                    let p = if total > 0.0 {
+                        regrets[idx].max(0.0) / total
+                    } else {
+                        1.0 / num_actions as f32
+                    };
+                    sum_p[idx] = sum_p[idx] * weights.average + p * weights.strategy;
+                }
+
+                matcher
+                    .update_regret_with(ArrayView1::from(&rewards), update)
+                    .unwrap();
+                for (expected, found) in regrets.iter().zip(matcher.regrets()) {
+                    assert!(
+                        (expected - found).abs() < 1e-3 * expected.abs().max(1.0),
+                        "{update:?} update {t}: {expected} != {found}"
+                    );
+                }
+            }
+            let total: f32 = sum_p.iter().sum();
+            for (expected, found) in sum_p.iter().zip(matcher.best_weight()) {
+                assert!((expected / total - found).abs() < 1e-5, "{update:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut left = RegretMatcher::new(2).unwrap();
+        left.update_regret(array![0.0, 3.0].view()).unwrap();
+        let mut right = RegretMatcher::new(2).unwrap();
+        right.update_regret(array![1.0, 0.0].view()).unwrap();
+
+        left.merge(&right).unwrap();
+        assert_eq!(vec![-1.0, 1.0], left.regrets());
+        assert_eq!(&[0.0, 1.0], left.current_weight());
+        assert_eq!(vec![0.5, 0.5], left.best_weight());
+        assert_eq!(2, left.num_updates());
+
+        assert_eq!(
+            Err(RegretMatcherError::DifferentNumberOfActions {
+                expected: 2,
+                found: 3
+            }),
+            left.merge(&RegretMatcher::new(3).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_subtract() {
+        let mut base = RegretMatcher::new(2).unwrap();
+        base.update_regret(array![0.0, 3.0].view()).unwrap();
+        let mut trained = base.clone();
+        trained.update_regret(array![2.0, 0.0].view()).unwrap();
+
+        // Only the second update is left, so adding it to the base gives
+        // back the trained matcher.
+        let mut update = trained.clone();
+        update.subtract(&base).unwrap();
+        assert_eq!(1, update.num_updates());
+        base.merge(&update).unwrap();
+        assert_eq!(trained, base);
+
+        assert_eq!(
+            Err(RegretMatcherError::DifferentNumberOfActions {
+                expected: 2,
+                found: 3
+            }),
+            update.subtract(&RegretMatcher::new(3).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_set_totals() {
+        let mut trained = RegretMatcher::new(2).unwrap();
+        trained.update_regret(array![0.0, 3.0].view()).unwrap();
+        let mut matcher = RegretMatcher::new(2).unwrap();
+        matcher
+            .set_totals(&trained.regrets(), trained.strategy_sum())
+            .unwrap();
+        assert_eq!(trained.regrets(), matcher.regrets());
+        assert_eq!(trained.current_weight(), matcher.current_weight());
+        assert_eq!(trained.best_weight(), matcher.best_weight());
+
+        assert_eq!(
+            Err(RegretMatcherError::DifferentNumberOfActions {
+                expected: 2,
+                found: 1
+            }),
+            matcher.set_totals(&[1.0], &[0.0, 1.0])
+        );
+    }
+
+    #[test]
+    fn test_resume_after_load() {
+        let mut matcher = RegretMatcher::new(3).unwrap();
+        matcher.update_regret(array![1.0, 0.0, 2.0].view()).unwrap();
+        let json = serde_json::to_string(&matcher).unwrap();
+        let mut loaded: RegretMatcher = serde_json::from_str(&json).unwrap();
+        assert_eq!(matcher, loaded);
+
+        // Training carries on the same from either.
+        matcher.update_regret(array![0.0, 4.0, 1.0].view()).unwrap();
+        loaded.update_regret(array![0.0, 4.0, 1.0].view()).unwrap();
+        assert_eq!(matcher, loaded);
+        let mut rng = crate::core::rng();
+        assert_ne!(0, loaded.next_action(&mut rng));
+
+        let json = r#"{"p":[1.0],"sum_p":[],"expert_reward":[0.0],"cumulative_reward":0.0,"num_updates":0}"#;
+        assert!(serde_json::from_str::<RegretMatcher>(json).is_err());
+        assert_eq!(
+            Err(RegretMatcherError::InvalidWeights),
+            RegretMatcher::new(0)
+        );
+    }
+}
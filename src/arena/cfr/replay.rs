@@ -0,0 +1,166 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::arena::action::{Action, AgentAction};
+use crate::arena::action_key::{ActionKey, ActionKeyEncoder, BetSizing};
+use crate::arena::{Agent, GameState, Historian, HistorianError, HoldemSimulationBuilder};
+
+use super::{ActionGenerator, CFRState, TraversalState};
+
+/// The betting a line of actions got to.
+pub(crate) struct Replayed {
+    pub(crate) key: ActionKey,
+    /// The decision it reached, with each action on offer and its index,
+    /// or `None` if the hand was over.
+    pub(crate) decision: Option<(GameState, Vec<(usize, AgentAction)>)>,
+}
+
+/// Plays lines of action indices, the child indices of the player nodes
+/// along a path through a tree, from the tree's starting game state. The
+/// cards are dealt at random, so only the betting is worth looking at.
+/// Every line is only played once.
+pub(crate) struct LineReplayer<T: ActionGenerator> {
+    game_state: GameState,
+    generator: T,
+    replays: HashMap<Vec<usize>, Rc<Replayed>>,
+}
+
+impl<T: ActionGenerator> LineReplayer<T> {
+    /// A replayer for the tree `player_idx` trained from `game_state`.
+    pub(crate) fn new(game_state: GameState, player_idx: usize) -> Self {
+        let generator = T::new(
+            CFRState::new(game_state.clone()),
+            TraversalState::new_root(player_idx),
+        );
+        Self {
+            game_state,
+            generator,
+            replays: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+
+    pub(crate) fn generator(&self) -> &T {
+        &self.generator
+    }
+
+    /// Each action on offer in `game_state` with its index, the first
+    /// action for indices several map to.
+    pub(crate) fn possible(&self, game_state: &GameState) -> Vec<(usize, AgentAction)> {
+        let mut possible: Vec<(usize, AgentAction)> = Vec::new();
+        for action in self.generator.gen_possible_actions(game_state) {
+            let idx = self.generator.action_to_idx(game_state, &action);
+            if possible.iter().all(|(other, _)| *other != idx) {
+                possible.push((idx, action));
+            }
+        }
+        possible
+    }
+
+    /// Play the hand along `line` to see where it gets to. `None` if the
+    /// line takes an action that wasn't on offer.
+    pub(crate) fn replay(&mut self, line: &[usize]) -> Option<Rc<Replayed>> {
+        if let Some(replayed) = self.replays.get(line) {
+            return Some(replayed.clone());
+        }
+        let mut actions = Vec::with_capacity(line.len());
+        for (step, action_idx) in line.iter().enumerate() {
+            let before = self.replay(&line[..step])?;
+            let (_, possible) = before.decision.as_ref()?;
+            let (_, action) = possible.iter().find(|(idx, _)| idx == action_idx)?;
+            actions.push(action.clone());
+        }
+
+        let script = Rc::new(RefCell::new(Script {
+            actions,
+            next_action: 0,
+            encoder: ActionKeyEncoder::new(BetSizing::Exact),
+            reached: None,
+        }));
+        let agents: Vec<Box<dyn Agent>> = (0..self.game_state.num_players)
+            .map(|_| {
+                Box::new(ScriptedAgent {
+                    script: script.clone(),
+                }) as Box<dyn Agent>
+            })
+            .collect();
+        let historians: Vec<Box<dyn Historian>> = vec![Box::new(ScriptedHistorian {
+            script: script.clone(),
+        })];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(self.game_state.clone())
+            .agents(agents)
+            .historians(historians)
+            .build()
+            .ok()?;
+        sim.run(&mut crate::core::rng());
+
+        let mut script = script.borrow_mut();
+        let decision = script.reached.take().map(|game_state| {
+            let possible = self.possible(&game_state);
+            (game_state, possible)
+        });
+        let replayed = Rc::new(Replayed {
+            key: script.encoder.key(),
+            decision,
+        });
+        self.replays.insert(line.to_vec(), replayed.clone());
+        Some(replayed)
+    }
+}
+
+/// The actions to play, and where playing them got to.
+struct Script {
+    actions: Vec<AgentAction>,
+    next_action: usize,
+    encoder: ActionKeyEncoder,
+    reached: Option<GameState>,
+}
+
+/// Plays the script, and folds once it's run out to get the hand over with.
+struct ScriptedAgent {
+    script: Rc<RefCell<Script>>,
+}
+
+impl Agent for ScriptedAgent {
+    fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+        let mut script = self.script.borrow_mut();
+        if script.reached.is_some() {
+            return AgentAction::Fold;
+        }
+        match script.actions.get(script.next_action).cloned() {
+            Some(action) => {
+                script.next_action += 1;
+                action
+            }
+            None => {
+                script.reached = Some(game_state.clone());
+                AgentAction::Fold
+            }
+        }
+    }
+}
+
+/// Keys the betting until the script runs out.
+struct ScriptedHistorian {
+    script: Rc<RefCell<Script>>,
+}
+
+impl Historian for ScriptedHistorian {
+    fn record_action(
+        &mut self,
+        _id: u128,
+        _game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        let mut script = self.script.borrow_mut();
+        if script.reached.is_none() {
+            script.encoder.push(&action);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use rand::Rng;
+
+/// A fixed size, uniformly random sample of everything pushed into it.
+///
+/// Once full, the `n`th item pushed replaces a random one with probability
+/// `capacity / n` (Vitter's algorithm R), so every item ever pushed is
+/// equally likely to be kept. Deep CFR trains on these rather than only
+/// the latest traversals, which is what lets the model approximate the
+/// regrets summed over every iteration.
+#[derive(Debug, Clone)]
+pub struct ReservoirBuffer<T> {
+    capacity: usize,
+    seen: u64,
+    items: Vec<T>,
+}
+
+impl<T> ReservoirBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Reservoir capacity must be positive");
+        Self {
+            capacity,
+            seen: 0,
+            items: Vec::new(),
+        }
+    }
+
+    /// Offer `item` to the sample. Returns whether it was kept.
+    pub fn push<R: Rng>(&mut self, item: T, rng: &mut R) -> bool {
+        self.seen += 1;
+        if self.items.len() < self.capacity {
+            self.items.push(item);
+            return true;
+        }
+        let idx = rng.random_range(0..self.seen);
+        if idx < self.capacity as u64 {
+            self.items[idx as usize] = item;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// How many items have been pushed, kept or not.
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The sample, in no particular order.
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn clear(&mut self) {
+        self.seen = 0;
+        self.items.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fills_then_samples() {
+        let mut rng = rand::rng();
+        let mut buffer = ReservoirBuffer::new(4);
+        for i in 0..4 {
+            assert!(buffer.push(i, &mut rng));
+        }
+        assert_eq!(&[0, 1, 2, 3], buffer.items());
+
+        for i in 4..100 {
+            buffer.push(i, &mut rng);
+        }
+        assert_eq!(4, buffer.len());
+        assert_eq!(100, buffer.seen());
+
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(0, buffer.seen());
+    }
+
+    #[test]
+    fn test_sample_is_uniform() {
+        let mut rng = rand::rng();
+        let mut buffer = ReservoirBuffer::new(1_000);
+        for i in 0..20_000 {
+            buffer.push(i as f64, &mut rng);
+        }
+        // A uniform sample of 0..20_000 has a mean near 10_000, where one
+        // biased towards either end wouldn't.
+        let mean = buffer.items().iter().sum::<f64>() / buffer.len() as f64;
+        assert!((9_000.0..11_000.0).contains(&mean), "mean was {mean}");
+    }
+}
@@ -0,0 +1,264 @@
+//! Solve a single decision without training a whole game.
+use rand::Rng;
+
+use crate::arena::action::AgentAction;
+use crate::arena::errors::SpotError;
+use crate::arena::{Agent, GameState, HoldemSimulationBuilder};
+use crate::core::{CardBitSet, Hand};
+use crate::holdem::Range;
+
+use super::{ActionGenerator, CFRAgent, FixedGameStateIteratorGen, StateStore, Subgame};
+
+/// How many times to try dealing every range a hand before giving up on
+/// ranges that keep wanting the same cards.
+const MAX_DEAL_ATTEMPTS: usize = 100;
+
+/// What [`solve_spot`] found for the player to act.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotSolution {
+    /// The player making the decision.
+    pub player_idx: usize,
+    /// The actions the action generator allows at the spot.
+    pub actions: Vec<AgentAction>,
+    /// How often to take each action, adding up to one.
+    pub strategy: Vec<f32>,
+    /// The average chips each action wins or loses from the spot on, not
+    /// counting what was put in the pot before it.
+    pub evs: Vec<f32>,
+}
+
+impl SpotSolution {
+    /// The expected value of playing the strategy.
+    pub fn ev(&self) -> f32 {
+        self.strategy
+            .iter()
+            .zip(self.evs.iter())
+            .map(|(frequency, ev)| frequency * ev)
+            .sum()
+    }
+
+    /// The action played most often.
+    pub fn best_action(&self) -> Option<&AgentAction> {
+        self.strategy
+            .iter()
+            .zip(self.actions.iter())
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, action)| action)
+    }
+}
+
+/// Run CFR on the decision of the player to act in `game_state`.
+///
+/// `ranges` has an entry for each player. A player with a range gets hole
+/// cards drawn from it for every iteration, and a player with `None` keeps
+/// the cards they hold in `game_state`. The action generator `T` is the
+/// action abstraction, it decides which actions are tried at the spot and
+/// later in the hand.
+///
+/// Each iteration deals hands from the ranges and plays every action at the
+/// spot out to the end of the hand, with CFR agents making the decisions
+/// after it. Only the spot's strategy and EVs are returned.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::action::AgentAction;
+/// use rs_poker::arena::cfr::{BasicCFRActionGenerator, solve_spot};
+/// use rs_poker::arena::game_state::{Round, RoundData};
+/// use rs_poker::core::{Hand, PlayerBitSet};
+/// use rs_poker::holdem::Range;
+///
+/// // On the river player 0 is all in and player 1 has top pair.
+/// let board = Hand::new_from_str("AcTh4d8d2s").unwrap();
+/// let hero = Hand::new_from_str("AsJsAcTh4d8d2s").unwrap();
+/// let round_data = RoundData::new_with_bets(100.0, PlayerBitSet::new(2), 1, vec![900.0, 0.0]);
+/// let game_state = GameState::new(
+///     Round::River,
+///     round_data,
+///     board.iter().collect(),
+///     vec![board, hero],
+///     vec![0.0, 900.0],
+///     vec![1000.0, 100.0],
+///     5.0,
+///     0.0,
+///     0.0,
+///     0,
+/// );
+///
+/// // Player 0 shoves a set of aces or a king queen bluff. Four bluffs for
+/// // each set once the hero's ace is removed, so calling wins.
+/// let villain: Range = "AA,KQs".parse().unwrap();
+/// let solution =
+///     solve_spot::<BasicCFRActionGenerator>(&game_state, &[Some(villain), None], 200).unwrap();
+///
+/// assert_eq!(1, solution.player_idx);
+/// assert_eq!(Some(&AgentAction::Bet(900.0)), solution.best_action());
+/// assert!(solution.ev() > 0.0);
+/// ```
+pub fn solve_spot<T: ActionGenerator + 'static>(
+    game_state: &GameState,
+    ranges: &[Option<Range>],
+    iterations: usize,
+) -> Result<SpotSolution, SpotError> {
+    Subgame::new(game_state.clone(), ranges.to_vec())?
+        .solve::<T, _>(iterations, &mut crate::core::rng())
+}
+
+/// The board and the hole cards of every player without a range, which no
+/// range can deal.
+pub(super) fn known_cards(game_state: &GameState, ranges: &[Option<Range>]) -> CardBitSet {
+    game_state
+        .hands
+        .iter()
+        .zip(ranges.iter())
+        .filter(|(_, range)| range.is_none())
+        .fold(
+            game_state.board.iter().copied().collect(),
+            |known, (hand, _)| known | CardBitSet::from(*hand),
+        )
+}
+
+/// A copy of the game state with hole cards drawn from each player's range,
+/// or `None` if the ranges kept colliding.
+pub(super) fn deal_from_ranges<R: Rng>(
+    game_state: &GameState,
+    ranges: &[Option<Range>],
+    known: CardBitSet,
+    rng: &mut R,
+) -> Option<GameState> {
+    'attempt: for _ in 0..MAX_DEAL_ATTEMPTS {
+        let mut dead = known;
+        let mut hole_cards = Vec::with_capacity(ranges.len());
+        for (idx, range) in ranges.iter().enumerate() {
+            let Some(range) = range else { continue };
+            let (first, second) = range.sample(known, rng)?;
+            if dead.contains(first) || dead.contains(second) {
+                continue 'attempt;
+            }
+            dead.insert(first);
+            dead.insert(second);
+            hole_cards.push((idx, first, second));
+        }
+
+        let mut sampled = game_state.clone();
+        for (idx, first, second) in hole_cards {
+            let mut hand = Hand::new_with_cards(game_state.board.to_vec());
+            hand.insert(first);
+            hand.insert(second);
+            sampled.hands_mut()[idx] = hand;
+        }
+        return Some(sampled);
+    }
+    None
+}
+
+/// Play `action` for the player to act then let CFR agents finish the hand,
+/// returning how much the player's stack changed from the spot.
+pub(super) fn play_out<T: ActionGenerator + 'static, R: Rng>(
+    state_store: &mut StateStore,
+    game_state: &GameState,
+    action: &AgentAction,
+    rng: &mut R,
+) -> f32 {
+    let player_idx = game_state.to_act_idx();
+    let num_players = game_state.num_players;
+    let gamestate_iterator_gen = FixedGameStateIteratorGen::new(1);
+
+    let agents: Vec<Box<dyn Agent>> = (0..num_players)
+        .map(|idx| {
+            let (cfr_state, traversal_state) = state_store.push_traversal(idx);
+            if idx == player_idx {
+                Box::new(CFRAgent::<T, _>::new_with_forced_action(
+                    state_store.clone(),
+                    cfr_state,
+                    traversal_state,
+                    gamestate_iterator_gen.clone(),
+                    action.clone(),
+                )) as Box<dyn Agent>
+            } else {
+                Box::new(CFRAgent::<T, _>::new(
+                    state_store.clone(),
+                    cfr_state,
+                    traversal_state,
+                    gamestate_iterator_gen.clone(),
+                )) as Box<dyn Agent>
+            }
+        })
+        .collect();
+
+    let mut sim = HoldemSimulationBuilder::default()
+        .game_state(game_state.clone())
+        .agents(agents)
+        .build()
+        .unwrap();
+    sim.run(rng);
+
+    for idx in 0..num_players {
+        state_store.pop_traversal(idx);
+    }
+    sim.game_state.stacks[player_idx] - game_state.stacks[player_idx]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::cfr::BasicCFRActionGenerator;
+    use crate::arena::game_state::{Round, RoundData};
+    use crate::core::PlayerBitSet;
+
+    /// Player 0 is all in on the river and player 1 has to decide.
+    fn river_all_in(hands: Vec<Hand>, board: &Hand) -> GameState {
+        let round_data = RoundData::new_with_bets(100.0, PlayerBitSet::new(2), 1, vec![900.0, 0.0]);
+        GameState::new(
+            Round::River,
+            round_data,
+            board.iter().collect(),
+            hands,
+            vec![0.0, 900.0],
+            vec![1000.0, 100.0],
+            5.0,
+            0.0,
+            0.0,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_fold_when_beaten() {
+        let board = Hand::new_from_str("AcTh4d8d2s").unwrap();
+        let hands = vec![
+            Hand::new_from_str("AsKsAcTh4d8d2s").unwrap(),
+            Hand::new_from_str("JdTcAcTh4d8d2s").unwrap(),
+        ];
+        let game_state = river_all_in(hands, &board);
+
+        let solution =
+            solve_spot::<BasicCFRActionGenerator>(&game_state, &[None, None], 20).unwrap();
+        assert_eq!(
+            vec![AgentAction::Fold, AgentAction::Bet(900.0)],
+            solution.actions
+        );
+        assert_eq!(vec![0.0, -900.0], solution.evs);
+        assert_eq!(Some(&AgentAction::Fold), solution.best_action());
+        assert!(solution.strategy[0] > 0.9);
+    }
+
+    #[test]
+    fn test_range_errors() {
+        let board = Hand::new_from_str("AcTh4d8d2s").unwrap();
+        let hero = Hand::new_from_str("AsAhAcTh4d8d2s").unwrap();
+        let game_state = river_all_in(vec![board, hero], &board);
+
+        assert_eq!(
+            Err(SpotError::WrongNumberOfRanges(2)),
+            solve_spot::<BasicCFRActionGenerator>(&game_state, &[None], 10)
+        );
+        // The only aces left are the hero's.
+        let aces: Range = "AA".parse().unwrap();
+        assert_eq!(
+            Err(SpotError::EmptyRange(0)),
+            solve_spot::<BasicCFRActionGenerator>(&game_state, &[Some(aces), None], 10)
+        );
+    }
+}
@@ -1,34 +1,89 @@
 use std::{
+    borrow::Cow,
     cell::{Ref, RefCell, RefMut},
+    collections::{BTreeMap, VecDeque},
+    path::Path,
     rc::Rc,
 };
+use anyhow::Result;
 use serde::{Deserialize, Serialize, Serializer, Deserializer};
 
 use crate::arena::GameState;
+use crate::arena::errors::{MergeError, QueryError, VersionedFileError};
+use crate::arena::storage::{StateStorage, load_versioned_from, save_versioned_to};
+use crate::arena::versioned::{Versioned, load_versioned, save_versioned};
+use crate::core::Hand;
 
-use super::{Node, NodeData};
+use super::{
+    ActionDistribution, ActionGenerator, Node, NodeData, NodeDataRef, NodeMut, NodeRef,
+    NodeStorage, NodeStore, NodeView, PlayerData, TerminalData, TreeIter,
+};
 
 /// The internal state for tracking CFR nodes.
 ///
-/// This uses a vector to store all the nodes in the game tree. Each node is
-/// identified by its index in this vector. This approach was chosen over a more
-/// traditional tree structure with heap allocations and pointers because:
+/// This uses a `NodeStorage`, a `NodeStore` unless it's given another, to
+/// store all the nodes in the game tree. Each node is identified by its
+/// index in the store. This approach was chosen over a more traditional
+/// tree structure with heap allocations and pointers because:
 ///
 /// 1. It avoids complex lifetime issues with rust's borrow checker that arise
 ///    from nodes referencing their parent/children
 /// 2. It provides better memory locality since nodes are stored contiguously
 /// 3. It makes serialization/deserialization simpler since we just need to
 ///    store indices rather than reconstruct pointer relationships
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CFRStateInternal {
-    /// Vector storing all nodes in the game tree. Nodes reference each other
-    /// using their indices into this vector rather than direct pointers.
-    pub nodes: Vec<Node>,
+    /// All the nodes in the game tree. Nodes reference each other using
+    /// their indices into the store rather than direct pointers.
+    pub nodes: Box<dyn NodeStorage>,
     pub starting_game_state: GameState,
     /// The next available index for inserting a new node
     next_node_idx: usize,
 }
 
+/// How big a CFR tree has grown, from `CFRState::stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TreeStats {
+    pub root_nodes: usize,
+    pub chance_nodes: usize,
+    pub player_nodes: usize,
+    pub terminal_nodes: usize,
+    /// The most edges from the root to any node.
+    pub max_depth: usize,
+    /// The average number of children of nodes that have any.
+    pub average_branching_factor: f32,
+    /// Every visit counted by every node.
+    pub total_visits: u64,
+    /// Roughly how many bytes of heap the tree holds, see
+    /// `NodeStore::memory_bytes`.
+    pub memory_bytes: usize,
+}
+
+impl TreeStats {
+    /// How many nodes there are in total.
+    pub fn num_nodes(&self) -> usize {
+        self.root_nodes + self.chance_nodes + self.player_nodes + self.terminal_nodes
+    }
+}
+
+impl std::fmt::Display for TreeStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} nodes ({} chance, {} player, {} terminal), depth {}, \
+             branching {:.2}, {} visits, {:.1} MiB",
+            self.num_nodes(),
+            self.chance_nodes,
+            self.player_nodes,
+            self.terminal_nodes,
+            self.max_depth,
+            self.average_branching_factor,
+            self.total_visits,
+            self.memory_bytes as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
+
 /// Counterfactual Regret Minimization (CFR) state tracker.
 ///
 /// This struct manages the game tree used for CFR algorithm calculations. The
@@ -84,17 +139,202 @@ impl<'de> Deserialize<'de> for CFRState {
     }
 }
 
+impl CFRStateInternal {
+    /// Check that `other` can be merged into this tree without changing
+    /// it, by merging into copies of the nodes both trees have.
+    pub(crate) fn check_merge(&self, other: &CFRStateInternal) -> Result<(), MergeError> {
+        if self.starting_game_state != other.starting_game_state {
+            return Err(MergeError::DifferentGameStates);
+        }
+
+        let mut queue = VecDeque::from([(0, 0)]);
+        while let Some((other_idx, idx)) = queue.pop_front() {
+            let other_node = other.nodes.get(other_idx).unwrap();
+            let node = self.nodes.get(idx).unwrap();
+            merge_node_data(&mut (*node.data).clone(), &other_node.data, other_idx)?;
+            // Nodes only `other` has are copied, which can't fail.
+            for (child_idx, other_child_idx) in other_node.iter_children() {
+                if let Some(child) = node.get_child(child_idx) {
+                    queue.push_back((other_child_idx, child));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge `other` into this tree where its nodes are kept, see
+    /// `CFRState::merge_from`. Nothing is changed if it fails.
+    pub(crate) fn merge_from(&mut self, other: &CFRStateInternal) -> Result<(), MergeError> {
+        self.check_merge(other)?;
+
+        let mut queue = VecDeque::from([(0, 0)]);
+        while let Some((other_idx, idx)) = queue.pop_front() {
+            let other_node = other.nodes.get(other_idx).unwrap();
+            let mut node = self.nodes.get_mut(idx).unwrap();
+            merge_node_data(node.data, &other_node.data, other_idx)?;
+            for (child_idx, count) in other_node.iter_counts() {
+                node.add_count(child_idx, count);
+            }
+
+            for (child_idx, other_child_idx) in other_node.iter_children() {
+                let child_idx = match self.nodes.get(idx).unwrap().get_child(child_idx) {
+                    Some(existing) => existing,
+                    None => {
+                        let other_child = other.nodes.get(other_child_idx).unwrap();
+                        self.nodes
+                            .add(Some(idx), child_idx, unvisited(&other_child.data))
+                    }
+                };
+                queue.push_back((other_child_idx, child_idx));
+            }
+        }
+        self.next_node_idx = self.nodes.len();
+        Ok(())
+    }
+
+    /// What this tree has learned since it was `base`, see
+    /// `CFRState::since`.
+    pub(crate) fn since(&self, base: &CFRStateInternal) -> Result<CFRStateInternal, MergeError> {
+        if self.starting_game_state != base.starting_game_state {
+            return Err(MergeError::DifferentGameStates);
+        }
+
+        let mut nodes = self.nodes.clone();
+        let mut queue = VecDeque::from([(0, 0)]);
+        while let Some((base_idx, idx)) = queue.pop_front() {
+            let base_node = base.nodes.get(base_idx).unwrap();
+            let mut node = nodes.get_mut(idx).unwrap();
+            subtract_node_data(node.data, &base_node.data, base_idx)?;
+            for (child_idx, count) in base_node.iter_counts() {
+                node.sub_count(child_idx, count);
+            }
+            for (child_idx, base_child_idx) in base_node.iter_children() {
+                let child = node
+                    .get_child(child_idx)
+                    .ok_or(MergeError::DifferentNodes(base_child_idx))?;
+                queue.push_back((base_child_idx, child));
+            }
+        }
+
+        Ok(CFRStateInternal {
+            next_node_idx: nodes.len(),
+            nodes,
+            starting_game_state: self.starting_game_state.clone(),
+        })
+    }
+
+    /// Check that every index in the tree points at a node that points
+    /// back.
+    fn validate(&self) -> Result<(), String> {
+        match self.nodes.get(0) {
+            Some(root) if root.data.is_root() => {}
+            _ => return Err("the first node isn't the root".to_string()),
+        }
+        if self.next_node_idx != self.nodes.len() {
+            return Err(format!(
+                "next node index {} doesn't match {} nodes",
+                self.next_node_idx,
+                self.nodes.len()
+            ));
+        }
+
+        for node in self.nodes.iter() {
+            let idx = node.idx;
+            if idx > 0 {
+                let points_back = match (node.parent, node.parent_child_idx) {
+                    (Some(parent), Some(child_idx)) => self
+                        .nodes
+                        .get(parent)
+                        .is_some_and(|p| p.get_child(child_idx) == Some(idx)),
+                    _ => false,
+                };
+                if !points_back {
+                    return Err(format!("node {idx} isn't a child of its parent"));
+                }
+            }
+            for (child_idx, child) in node.iter_children() {
+                let child_node = self.nodes.get(child).filter(|_| child != 0);
+                if !child_node.is_some_and(|c| {
+                    c.parent == Some(idx) && c.parent_child_idx == Some(child_idx)
+                }) {
+                    return Err(format!(
+                        "child {child_idx} of node {idx} points at node {child}, which has another parent"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Versioned for CFRState {
+    const KIND: &'static str = "cfr_state";
+    // Version 2 saves the regret matchers that version 1 wrote as null,
+    // which still loads.
+    const VERSION: u32 = 2;
+
+    fn validate(&self) -> Result<(), VersionedFileError> {
+        self.inner_state
+            .borrow()
+            .validate()
+            .map_err(|reason| VersionedFileError::Invalid {
+                kind: Self::KIND,
+                reason,
+            })
+    }
+}
+
 impl CFRState {
     pub fn new(game_state: GameState) -> Self {
+        Self::with_storage(game_state, NodeStore::new())
+    }
+
+    /// A tree kept in `nodes` rather than a `NodeStore`, such as a
+    /// `MmapNodeStore` for trees too big for memory. Empty storage gets a
+    /// root, and storage that already has a tree in it carries on from
+    /// there.
+    pub fn with_storage(game_state: GameState, mut nodes: impl NodeStorage + 'static) -> Self {
+        if nodes.is_empty() {
+            nodes.add(None, 0, NodeData::Root);
+        }
         CFRState {
             inner_state: Rc::new(RefCell::new(CFRStateInternal {
-                nodes: vec![Node::new_root()],
-                starting_game_state: game_state.clone(),
-                next_node_idx: 1,
+                next_node_idx: nodes.len(),
+                nodes: Box::new(nodes),
+                starting_game_state: game_state,
             })),
         }
     }
 
+    /// Wrap a tree that isn't shared with anything yet.
+    pub(crate) fn from_internal(internal: CFRStateInternal) -> Self {
+        CFRState {
+            inner_state: Rc::new(RefCell::new(internal)),
+        }
+    }
+
+    /// Save a single tree as a versioned JSON file, without the rest of
+    /// the `StateStore` it was trained in.
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        Ok(save_versioned(path, self)?)
+    }
+
+    /// Load a tree saved with `save_to_file`. Trees with nodes that don't
+    /// link up are rejected.
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        Ok(load_versioned(path)?)
+    }
+
+    /// Save the tree under `key` in any `StateStorage`.
+    pub fn save_to<S: StateStorage + ?Sized>(&self, storage: &S, key: &str) -> Result<()> {
+        Ok(save_versioned_to(storage, key, self)?)
+    }
+
+    /// Load a tree saved with `save_to`.
+    pub fn load_from<S: StateStorage + ?Sized>(storage: &S, key: &str) -> Result<Self> {
+        Ok(load_versioned_from(storage, key)?)
+    }
+
     pub fn starting_game_state(&self) -> GameState {
         self.inner_state.borrow().starting_game_state.clone()
     }
@@ -102,28 +342,266 @@ impl CFRState {
     pub fn add(&mut self, parent_idx: usize, child_idx: usize, data: NodeData) -> usize {
         let mut state = self.inner_state.borrow_mut();
 
-        let idx = state.next_node_idx;
         state.next_node_idx += 1;
 
-        let node = Node::new(idx, parent_idx, child_idx, data);
-        state.nodes.push(node);
-
-        // The parent node needs to be updated to point to the new child
-        state.nodes[parent_idx].set_child(child_idx, idx);
-
-        idx
+        // This also points the parent node at the new child
+        state.nodes.add(Some(parent_idx), child_idx, data)
     }
 
-    pub fn get(&self, idx: usize) -> Option<Ref<Node>> {
+    pub fn get(&self, idx: usize) -> Option<NodeRef<'_>> {
         let inner_ref = self.inner_state.borrow();
 
-        Ref::filter_map(inner_ref, |state| state.nodes.get(idx)).ok()
+        // Storage that doesn't keep the node in memory hands out a copy,
+        // and only the links are borrowed.
+        let copied = match inner_ref.nodes.data(idx)? {
+            Cow::Borrowed(_) => None,
+            Cow::Owned(data) => Some(data),
+        };
+        Some(match copied {
+            Some(data) => NodeView::new(
+                idx,
+                NodeDataRef::Copied(data),
+                Ref::map(inner_ref, |state| state.nodes.links()),
+            ),
+            None => {
+                let (data, links) = Ref::map_split(inner_ref, |state| {
+                    let Some(Cow::Borrowed(data)) = state.nodes.data(idx) else {
+                        unreachable!("Node {idx} was borrowed a moment ago");
+                    };
+                    (data, state.nodes.links())
+                });
+                NodeView::new(idx, NodeDataRef::Borrowed(data), links)
+            }
+        })
     }
 
-    pub fn get_mut(&mut self, idx: usize) -> Option<RefMut<Node>> {
+    pub fn get_mut(&mut self, idx: usize) -> Option<NodeMut<'_>> {
         let inner_ref = self.inner_state.borrow_mut();
+        if idx >= inner_ref.nodes.len() {
+            return None;
+        }
+
+        let (data, links) =
+            RefMut::map_split(inner_ref, |state| state.nodes.node_mut(idx).unwrap());
+        Some(NodeView::new(idx, data, links))
+    }
+
+    /// Make sure every change to the tree is stored, for `NodeStorage`
+    /// such as a `MmapNodeStore` that writes it out. Trees in memory have
+    /// nothing to do.
+    pub fn flush(&self) -> Result<()> {
+        Ok(self.inner_state.borrow_mut().nodes.flush()?)
+    }
+
+    /// Every node, depth first from the root. See `TreeIter`.
+    pub fn iter_dfs(&self) -> TreeIter<'_> {
+        TreeIter::depth_first(self)
+    }
+
+    /// Every node, breadth first from the root, so by depth. See
+    /// `TreeIter`.
+    pub fn iter_bfs(&self) -> TreeIter<'_> {
+        TreeIter::breadth_first(self)
+    }
+
+    /// The child indices followed from the root to reach `node_idx`, the
+    /// same paths `average_strategy` and `StrategyProfile` use. `None` if
+    /// there's no such node.
+    pub fn path_to(&self, node_idx: usize) -> Option<Vec<usize>> {
+        let mut path = Vec::new();
+        let mut idx = node_idx;
+        loop {
+            let node = self.get(idx)?;
+            let (Some(parent), Some(child_idx)) = (node.parent, node.parent_child_idx) else {
+                break;
+            };
+            path.push(child_idx);
+            idx = parent;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The trained strategy of this tree at the decision in `game_state`,
+    /// for the player to act holding `hole_cards`. The betting is matched
+    /// to the line of the tree with the same bets and raises on every
+    /// street, taking the closest amounts for bets `T` doesn't make, and
+    /// the cards go through `T`'s abstraction.
+    pub fn query<T: ActionGenerator>(
+        &self,
+        game_state: &GameState,
+        hole_cards: Hand,
+    ) -> Result<ActionDistribution, QueryError> {
+        super::query::query::<T>(self, game_state, hole_cards)
+    }
+
+    /// Copy the node at `node_idx` and everything below it into a new tree
+    /// starting from `game_state`, the state the node was reached in. The
+    /// node becomes the first child of the new root, where agents start,
+    /// and keeps its regrets and visit counts.
+    ///
+    /// Returns `None` if there's no such node or it's the root.
+    pub fn subtree(&self, node_idx: usize, game_state: GameState) -> Option<CFRState> {
+        let internal = self.inner_state.borrow();
+        internal
+            .nodes
+            .get(node_idx)
+            .filter(|node| !node.data.is_root())?;
+
+        let mut nodes = NodeStore::new();
+        let mut root = Node::new_root();
+        root.set_child(0, 1);
+        nodes.push_node(&root);
 
-        RefMut::filter_map(inner_ref, |state| state.nodes.get_mut(idx)).ok()
+        // New indices are handed out in the order nodes are queued, which
+        // is the order they're pushed.
+        let mut next_idx = 2;
+        let mut queue = VecDeque::from([(node_idx, 0, 0)]);
+        while let Some((old_idx, parent, parent_child_idx)) = queue.pop_front() {
+            let old = internal.nodes.get(old_idx)?;
+            let mut node = Node::new(nodes.len(), parent, parent_child_idx, (*old.data).clone());
+            for (child_idx, count) in old.iter_counts() {
+                node.set_count(child_idx, count);
+            }
+            for (child_idx, child) in old.iter_children() {
+                node.set_child(child_idx, next_idx);
+                queue.push_back((child, node.idx, child_idx));
+                next_idx += 1;
+            }
+            nodes.push_node(&node);
+        }
+
+        Some(CFRState::with_storage(game_state, nodes))
+    }
+
+    /// Add what `other` has learned to this tree. Nodes are matched by
+    /// their path from the root: visit counts and terminal utilities are
+    /// summed, regret matchers are combined with `RegretMatcher::merge`,
+    /// and anything only `other` reached is copied in. This is how trees
+    /// trained apart on the same game, such as parallel shards, are put
+    /// back together.
+    ///
+    /// Both trees have to start from the same game state. If they disagree
+    /// about a node nothing is changed. The tree stays in whichever
+    /// `NodeStorage` it's kept in.
+    pub fn merge_from(&mut self, other: &CFRState) -> Result<(), MergeError> {
+        if Rc::ptr_eq(&self.inner_state, &other.inner_state) {
+            // A tree merged into itself is merged from a copy.
+            let copy = CFRState::from_internal(other.inner_state.borrow().clone());
+            return self.merge_from(&copy);
+        }
+        self.inner_state
+            .borrow_mut()
+            .merge_from(&other.inner_state.borrow())
+    }
+
+    /// Check that `other` can be merged in with `merge_from`.
+    pub(crate) fn check_merge(&self, other: &CFRState) -> Result<(), MergeError> {
+        self.inner_state
+            .borrow()
+            .check_merge(&other.inner_state.borrow())
+    }
+
+    /// What this tree has learned since it was `base`, such as a copy of
+    /// `base` that's been trained on. Visit counts, terminal utilities and
+    /// regret matchers have `base`'s taken out, see
+    /// `RegretMatcher::subtract`, and nodes `base` didn't have are kept as
+    /// they are.
+    ///
+    /// Merging the result with `merge_from` adds just that training, so
+    /// copies of one tree trained apart can all be merged back into it
+    /// without counting the tree they started from more than once.
+    ///
+    /// Both trees have to start from the same game state, and this one has
+    /// to have every node `base` has.
+    pub fn since(&self, base: &CFRState) -> Result<CFRState, MergeError> {
+        let internal = self
+            .inner_state
+            .borrow()
+            .since(&base.inner_state.borrow())?;
+        Ok(CFRState::from_internal(internal))
+    }
+
+    /// Walk the tree and pull out the average strategy at every decision
+    /// point, keyed by the acting player and the path of child indices from
+    /// the root. Each strategy maps an action's child index to how often
+    /// it's played, and adds up to one.
+    ///
+    /// Decision points whose regret matcher was never updated have no
+    /// average yet and are skipped.
+    pub fn average_strategy(&self) -> BTreeMap<(usize, Vec<usize>), BTreeMap<usize, f32>> {
+        let mut strategies = BTreeMap::new();
+        let internal = self.inner_state.borrow();
+        let mut stack = vec![(0, Vec::new())];
+
+        while let Some((node_idx, path)) = stack.pop() {
+            let Some(node) = internal.nodes.get(node_idx) else {
+                continue;
+            };
+            if let NodeData::Player(player_data) = &*node.data
+                && let Some(weights) = player_data
+                    .regret_matcher
+                    .as_ref()
+                    .map(|matcher| matcher.best_weight())
+            {
+                let total: f32 = weights.iter().sum();
+                if total.is_finite() && total > 0.0 {
+                    let probabilities = weights
+                        .into_iter()
+                        .enumerate()
+                        .map(|(action_idx, weight)| (action_idx, weight / total))
+                        .collect();
+                    strategies.insert((player_data.player_idx, path.clone()), probabilities);
+                }
+            }
+
+            for (child_idx, child_node_idx) in node.iter_children() {
+                let mut child_path = path.clone();
+                child_path.push(child_idx);
+                stack.push((child_node_idx, child_path));
+            }
+        }
+        strategies
+    }
+
+    /// Count the nodes of the tree and how it's grown. This walks every
+    /// node, so it's meant for reporting now and then rather than inside
+    /// training.
+    pub fn stats(&self) -> TreeStats {
+        let internal = self.inner_state.borrow();
+        let mut stats = TreeStats {
+            memory_bytes: internal.nodes.memory_bytes(),
+            ..TreeStats::default()
+        };
+        let mut parents = 0;
+        let mut children = 0;
+        for node in internal.nodes.iter() {
+            match &*node.data {
+                NodeData::Root => stats.root_nodes += 1,
+                NodeData::Chance => stats.chance_nodes += 1,
+                NodeData::Player(_) => stats.player_nodes += 1,
+                NodeData::Terminal(_) => stats.terminal_nodes += 1,
+            }
+            stats.total_visits += node
+                .iter_counts()
+                .map(|(_, count)| u64::from(count))
+                .sum::<u64>();
+            let num_children = node.iter_children().count();
+            if num_children > 0 {
+                parents += 1;
+                children += num_children;
+            }
+        }
+        if parents > 0 {
+            stats.average_branching_factor = children as f32 / parents as f32;
+        }
+
+        stats.max_depth = self
+            .iter_dfs()
+            .map(|(_, depth, _)| depth)
+            .max()
+            .unwrap_or(0);
+        stats
     }
 
     /// Access the internal state of the CFR state structure.
@@ -139,6 +617,78 @@ impl CFRState {
     }
 }
 
+/// Add `other`'s data into `data`, the same node in another tree.
+fn merge_node_data(
+    data: &mut NodeData,
+    other: &NodeData,
+    other_idx: usize,
+) -> Result<(), MergeError> {
+    match (data, other) {
+        (NodeData::Root, NodeData::Root) | (NodeData::Chance, NodeData::Chance) => Ok(()),
+        (NodeData::Terminal(terminal), NodeData::Terminal(other)) => {
+            terminal.total_utility += other.total_utility;
+            Ok(())
+        }
+        (NodeData::Player(player_data), NodeData::Player(other))
+            if player_data.player_idx == other.player_idx =>
+        {
+            let Some(other_matcher) = &other.regret_matcher else {
+                return Ok(());
+            };
+            match &mut player_data.regret_matcher {
+                Some(matcher) => matcher
+                    .merge(other_matcher)
+                    .map_err(|e| MergeError::RegretMatcher(other_idx, e)),
+                None => {
+                    player_data.regret_matcher = Some(other_matcher.clone());
+                    Ok(())
+                }
+            }
+        }
+        _ => Err(MergeError::DifferentNodes(other_idx)),
+    }
+}
+
+/// Take `base`'s data out of `data`, the same node in a tree that's
+/// learned more since.
+fn subtract_node_data(
+    data: &mut NodeData,
+    base: &NodeData,
+    base_idx: usize,
+) -> Result<(), MergeError> {
+    match (data, base) {
+        (NodeData::Root, NodeData::Root) | (NodeData::Chance, NodeData::Chance) => Ok(()),
+        (NodeData::Terminal(terminal), NodeData::Terminal(base)) => {
+            terminal.total_utility -= base.total_utility;
+            Ok(())
+        }
+        (NodeData::Player(player_data), NodeData::Player(base))
+            if player_data.player_idx == base.player_idx =>
+        {
+            match (&mut player_data.regret_matcher, &base.regret_matcher) {
+                (_, None) => Ok(()),
+                (Some(matcher), Some(base_matcher)) => matcher
+                    .subtract(base_matcher)
+                    .map_err(|e| MergeError::RegretMatcher(base_idx, e)),
+                (None, Some(_)) => Err(MergeError::DifferentNodes(base_idx)),
+            }
+        }
+        _ => Err(MergeError::DifferentNodes(base_idx)),
+    }
+}
+
+/// The data for a node of the same kind that nothing has reached yet.
+fn unvisited(data: &NodeData) -> NodeData {
+    match data {
+        NodeData::Player(player_data) => NodeData::Player(PlayerData {
+            regret_matcher: None,
+            player_idx: player_data.player_idx,
+        }),
+        NodeData::Terminal(_) => NodeData::Terminal(TerminalData::default()),
+        other => other.clone(),
+    }
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct TraversalStateInternal {
     // What node are we at
@@ -222,9 +772,12 @@ impl TraversalState {
 
 #[cfg(test)]
 mod tests {
-    use crate::arena::cfr::{NodeData, PlayerData, TraversalState};
+    use ndarray::array;
+
+    use crate::arena::cfr::{NodeData, PlayerData, RegretMatcher, TerminalData, TraversalState};
 
     use crate::arena::GameState;
+    use crate::arena::errors::MergeError;
 
     use super::CFRState;
     use serde_json;
@@ -240,7 +793,7 @@ mod tests {
         let player_idx: usize = state.add(0, 0, new_data);
 
         let node = state.get(player_idx).unwrap();
-        match &node.data {
+        match &*node.data {
             NodeData::Player(pd) => assert!(pd.regret_matcher.is_none()),
             _ => panic!("Expected player data"),
         }
@@ -255,6 +808,211 @@ mod tests {
         assert_eq!(parent.get_child(0), Some(player_idx));
     }
 
+    #[test]
+    fn test_average_strategy() {
+        let mut state = CFRState::new(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0));
+        let mut matcher = RegretMatcher::new(3).unwrap();
+        matcher.update_regret(array![0.0, 1.0, 3.0].view()).unwrap();
+        let first = state.add(
+            0,
+            0,
+            NodeData::Player(PlayerData {
+                regret_matcher: Some(Box::new(matcher)),
+                player_idx: 0,
+            }),
+        );
+        let chance = state.add(first, 2, NodeData::Chance);
+        state.add(
+            chance,
+            7,
+            NodeData::Player(PlayerData {
+                regret_matcher: Some(Box::new(RegretMatcher::new(3).unwrap())),
+                player_idx: 1,
+            }),
+        );
+
+        let strategies = state.average_strategy();
+        // The second decision was never updated.
+        assert_eq!(1, strategies.len());
+        let probabilities = &strategies[&(0, vec![0])];
+        assert_eq!(
+            vec![0, 1, 2],
+            probabilities.keys().copied().collect::<Vec<_>>()
+        );
+        assert!((probabilities.values().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(probabilities[&2] > probabilities[&1]);
+    }
+
+    #[test]
+    fn test_merge_from() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let shard = |rewards: [f32; 2], card_idx: usize| {
+            let mut state = CFRState::new(game_state.clone());
+            let mut matcher = RegretMatcher::new(2).unwrap();
+            matcher
+                .update_regret(array![rewards[0], rewards[1]].view())
+                .unwrap();
+            let player = state.add(
+                0,
+                0,
+                NodeData::Player(PlayerData {
+                    regret_matcher: Some(Box::new(matcher)),
+                    player_idx: 0,
+                }),
+            );
+            state.get_mut(0).unwrap().increment_count(0);
+            let chance = state.add(player, 1, NodeData::Chance);
+            state.get_mut(player).unwrap().increment_count(1);
+            let terminal = state.add(chance, card_idx, NodeData::Terminal(TerminalData::new(5.0)));
+            state.get_mut(chance).unwrap().increment_count(card_idx);
+            state.get_mut(terminal).unwrap().increment_count(0);
+            state
+        };
+
+        let mut state = shard([0.0, 3.0], 4);
+        let other = shard([1.0, 0.0], 9);
+        state.merge_from(&other).unwrap();
+
+        let player = state.get(0).unwrap().get_child(0).unwrap();
+        assert_eq!(2, state.get(0).unwrap().get_count(0));
+        assert_eq!(2, state.get(player).unwrap().get_count(1));
+        match &*state.get(player).unwrap().data {
+            NodeData::Player(player_data) => {
+                let matcher = player_data.regret_matcher.as_ref().unwrap();
+                assert_eq!(vec![-1.0, 1.0], matcher.regrets());
+                assert_eq!(2, matcher.num_updates());
+            }
+            _ => panic!("Expected player data"),
+        }
+
+        // Each shard dealt a different card, so the chance node has both.
+        let chance = state.get(player).unwrap().get_child(1).unwrap();
+        let chance = state.get(chance).unwrap();
+        assert_eq!(
+            vec![(4, 1), (9, 1)],
+            chance.iter_counts().collect::<Vec<_>>()
+        );
+        let terminal = state.get(chance.get_child(9).unwrap()).unwrap();
+        assert_eq!(1, terminal.get_count(0));
+        match &*terminal.data {
+            NodeData::Terminal(terminal_data) => assert_eq!(5.0, terminal_data.total_utility),
+            _ => panic!("Expected terminal data"),
+        }
+    }
+
+    #[test]
+    fn test_since() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut base = CFRState::new(game_state.clone());
+        let chance = base.add(0, 0, NodeData::Chance);
+        base.get_mut(0).unwrap().increment_count(0);
+        let terminal = base.add(chance, 3, NodeData::Terminal(TerminalData::new(2.0)));
+        base.get_mut(chance).unwrap().increment_count(3);
+
+        // Two copies that each play another hand, one down a new branch.
+        let train = |card_idx: usize| {
+            let mut tree = CFRState::from_internal(base.internal_state().borrow().clone());
+            tree.get_mut(0).unwrap().increment_count(0);
+            let existing = tree.get(chance).unwrap().get_child(card_idx);
+            let node = match existing {
+                Some(node) => node,
+                None => tree.add(
+                    chance,
+                    card_idx,
+                    NodeData::Terminal(TerminalData::default()),
+                ),
+            };
+            tree.get_mut(chance).unwrap().increment_count(card_idx);
+            if let NodeData::Terminal(data) = &mut *tree.get_mut(node).unwrap().data {
+                data.total_utility += 1.0;
+            }
+            tree.since(&base).unwrap()
+        };
+        let (first, second) = (train(3), train(7));
+        base.merge_from(&first).unwrap();
+        base.merge_from(&second).unwrap();
+
+        // Three hands in all, not the base's counted once per copy.
+        assert_eq!(3, base.get(0).unwrap().get_count(0));
+        let chance_node = base.get(chance).unwrap();
+        assert_eq!(
+            vec![(3, 2), (7, 1)],
+            chance_node.iter_counts().collect::<Vec<_>>()
+        );
+        match &*base.get(terminal).unwrap().data {
+            NodeData::Terminal(data) => assert_eq!(3.0, data.total_utility),
+            _ => panic!("Expected terminal data"),
+        }
+
+        // A tree missing a node the base has didn't come from it.
+        let fresh = CFRState::new(game_state);
+        assert_eq!(
+            Err(MergeError::DifferentNodes(chance)),
+            fresh.since(&base).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_merge_from_mismatch() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut state = CFRState::new(game_state.clone());
+        state.add(0, 0, NodeData::Chance);
+        let mut other = CFRState::new(game_state);
+        let player = other.add(
+            0,
+            0,
+            NodeData::Player(PlayerData {
+                regret_matcher: None,
+                player_idx: 1,
+            }),
+        );
+        assert_eq!(
+            Err(MergeError::DifferentNodes(player)),
+            state.merge_from(&other)
+        );
+        // Nothing was merged in.
+        assert_eq!(2, state.internal_state().borrow().nodes.len());
+
+        let different_start =
+            CFRState::new(GameState::new_starting(vec![50.0; 2], 10.0, 5.0, 0.0, 0));
+        assert_eq!(
+            Err(MergeError::DifferentGameStates),
+            state.merge_from(&different_start)
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut state = CFRState::new(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0));
+        let chance = state.add(0, 0, NodeData::Chance);
+        for card_idx in [3, 9] {
+            let player = state.add(
+                chance,
+                card_idx,
+                NodeData::Player(PlayerData {
+                    regret_matcher: None,
+                    player_idx: 0,
+                }),
+            );
+            state.add(player, 1, NodeData::Terminal(TerminalData::default()));
+            state.get_mut(chance).unwrap().increment_count(card_idx);
+        }
+        state.get_mut(chance).unwrap().increment_count(3);
+
+        let stats = state.stats();
+        assert_eq!(1, stats.root_nodes);
+        assert_eq!(1, stats.chance_nodes);
+        assert_eq!(2, stats.player_nodes);
+        assert_eq!(2, stats.terminal_nodes);
+        assert_eq!(6, stats.num_nodes());
+        assert_eq!(3, stats.max_depth);
+        // The root and players have one child each, the chance node two.
+        assert_eq!(5.0 / 4.0, stats.average_branching_factor);
+        assert_eq!(3, stats.total_visits);
+        assert!(stats.memory_bytes > 0);
+        assert!(stats.to_string().starts_with("6 nodes"));
+    }
+
     #[test]
     fn test_node_get_not_exist() {
         let state = CFRState::new(GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0));
@@ -266,6 +1024,27 @@ mod tests {
         assert!(node.is_none());
     }
 
+    #[test]
+    fn test_save_load_file() {
+        let mut state = CFRState::new(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0));
+        let child = state.add(0, 0, NodeData::Chance);
+        state.add(child, 12, NodeData::Chance);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cfr_state.json");
+        state.save_to_file(&path).unwrap();
+        let loaded = CFRState::load_from_file(&path).unwrap();
+        assert_eq!(Some(2), loaded.get(child).unwrap().get_child(12));
+
+        // Point the root's child somewhere that doesn't exist.
+        let mut value: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        value["data"]["nodes"][0]["children"][0] = serde_json::json!(7);
+        std::fs::write(&path, value.to_string()).unwrap();
+        let err = CFRState::load_from_file(&path).unwrap_err();
+        assert!(err.to_string().contains("Invalid cfr_state"), "{err}");
+    }
+
     #[test]
     fn test_cloned_traversal_share_loc() {
         let mut traversal = TraversalState::new(0, 0, 0);
@@ -303,7 +1082,7 @@ mod tests {
         
         // Check that the deserialized state has the root node
         let root_node = deserialized_state.get(0).expect("No root node found");
-        assert!(matches!(root_node.data, NodeData::Root), "Root node data should be NodeData::Root");
+        assert!(matches!(*root_node.data, NodeData::Root), "Root node data should be NodeData::Root");
         
         // Check that the starting game state was properly serialized
         assert_eq!(deserialized_state.starting_game_state().big_blind, 10.0);
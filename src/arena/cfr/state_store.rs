@@ -1,11 +1,16 @@
-use std::fs;
 use std::path::Path;
 use std::rc::Rc;
 use serde::{Deserialize, Serialize, Serializer, Deserializer};
 use crate::arena::GameState;
+use crate::arena::errors::{MergeError, QueryError, VersionedFileError};
+use crate::arena::storage::{StateStorage, load_versioned_from, save_versioned_to};
+use crate::arena::versioned::{
+    SaveFormat, SaveOptions, Versioned, load_versioned, save_versioned_as,
+};
+use crate::core::Hand;
 use anyhow::Result;
 
-use super::{CFRState, TraversalState};
+use super::{ActionDistribution, ActionGenerator, CFRState, TraversalState};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct StateStoreInternal {
@@ -21,6 +26,7 @@ struct StateStoreInternal {
 /// enables reuse of the memory and regret matchers of all players.
 ///
 /// This state store is not thread safe so it has to be used in a single thread.
+/// `ConcurrentStateStore` keeps the trees where threads can share them.
 #[derive(Debug, Clone)]
 pub struct StateStore {
     inner: Rc<std::cell::RefCell<StateStoreInternal>>,
@@ -50,6 +56,45 @@ impl<'de> Deserialize<'de> for StateStore {
     }
 }
 
+impl Versioned for StateStore {
+    const KIND: &'static str = "state_store";
+    // Version 0 is the bare JSON written before files were versioned. The
+    // layout didn't change, only the envelope was added. Version 2 saves the
+    // regret matchers that version 1 wrote as null, which still loads.
+    const VERSION: u32 = 2;
+
+    fn validate(&self) -> Result<(), VersionedFileError> {
+        let inner = self.inner.borrow();
+        let invalid = |reason: String| VersionedFileError::Invalid {
+            kind: Self::KIND,
+            reason,
+        };
+        if inner.cfr_states.len() != inner.traversal_states.len() {
+            return Err(invalid(format!(
+                "{} trees but {} traversal stacks",
+                inner.cfr_states.len(),
+                inner.traversal_states.len()
+            )));
+        }
+        for (player_idx, (cfr_state, traversals)) in inner
+            .cfr_states
+            .iter()
+            .zip(&inner.traversal_states)
+            .enumerate()
+        {
+            cfr_state.validate()?;
+            let num_nodes = cfr_state.internal_state().borrow().nodes.len();
+            if let Some(traversal) = traversals.iter().find(|t| t.node_idx() >= num_nodes) {
+                return Err(invalid(format!(
+                    "player {player_idx} is traversing node {}, which isn't in their tree",
+                    traversal.node_idx()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
 impl StateStore {
     pub fn new() -> Self {
         StateStore {
@@ -60,30 +105,100 @@ impl StateStore {
         }
     }
 
-    /// Merges the contents of another StateStore into this one.
-    /// This appends all CFRStates and TraversalStates from `other` into `self`.
-    pub fn merge_from(&mut self, other: &StateStore) {
-        let mut self_inner = self.inner.borrow_mut();
-        let other_inner = other.inner.borrow();
+    /// A store of `cfr_states`, one for each player in order, with none of
+    /// them being traversed. Trees kept in other `NodeStorage`, from
+    /// `CFRState::with_storage`, are trained where they are.
+    pub fn from_states(cfr_states: Vec<CFRState>) -> Self {
+        let traversal_states = (0..cfr_states.len())
+            .map(|player_idx| vec![TraversalState::new_root(player_idx)])
+            .collect();
+        StateStore {
+            inner: Rc::new(std::cell::RefCell::new(StateStoreInternal {
+                cfr_states,
+                traversal_states,
+            })),
+        }
+    }
 
-        for state in &other_inner.cfr_states {
-            self_inner.cfr_states.push(state.clone());
+    /// Merges another store's trees into this one, player by player, see
+    /// `CFRState::merge_from`. Players this store has no tree for get a
+    /// copy of `other`'s. This combines stores trained apart on the same
+    /// game, such as shards trained in parallel.
+    ///
+    /// If any tree fails to merge nothing is changed.
+    pub fn merge_from(&mut self, other: &StateStore) -> Result<(), MergeError> {
+        for player_idx in 0..other.len() {
+            if let Some(state) = self.get_state(player_idx) {
+                state.check_merge(&other.get_state(player_idx).unwrap())?;
+            }
         }
 
-        for traversal in &other_inner.traversal_states {
-            self_inner.traversal_states.push(traversal.clone());
+        for player_idx in 0..other.len() {
+            let other_state = other.get_state(player_idx).unwrap();
+            match self.get_state(player_idx) {
+                Some(mut state) => state.merge_from(&other_state)?,
+                None => {
+                    let internal = other_state.internal_state().borrow().clone();
+                    let mut self_inner = self.inner.borrow_mut();
+                    self_inner
+                        .cfr_states
+                        .push(CFRState::from_internal(internal));
+                    self_inner
+                        .traversal_states
+                        .push(vec![TraversalState::new_root(player_idx)]);
+                }
+            }
         }
+        Ok(())
     }
 
+    /// What this store learned since it was a copy of `base`, player by
+    /// player, see `CFRState::since`. Players `base` has no tree for keep
+    /// their whole tree. Merging the result back into `base` with
+    /// `merge_from` gives this store's trees again.
+    pub fn since(&self, base: &StateStore) -> Result<StateStore, MergeError> {
+        let mut learned = Vec::with_capacity(self.len());
+        for player_idx in 0..self.len() {
+            let cfr_state = self.get_state(player_idx).unwrap();
+            learned.push(match base.get_state(player_idx) {
+                Some(base) => cfr_state.since(&base)?,
+                None => cfr_state,
+            });
+        }
+        Ok(StateStore::from_states(learned))
+    }
+
+    /// Save the store as a versioned binary file. Trained stores are big,
+    /// and JSON is many times the size and slow to load.
     pub fn save_to_file(&self, path: &Path) -> Result<()> {
-        let serialized = serde_json::to_string(self)?;
-        fs::write(path, serialized)?;
-        Ok(())
+        self.save_to_file_as(path, SaveFormat::Binary)
+    }
+
+    /// Save the store as a versioned file in a `SaveFormat`, such as JSON
+    /// to read it or to load it with later versions of the crate. With the
+    /// `compression` feature, `SaveOptions` can also compress it, which
+    /// makes checkpoints of big trees a lot smaller.
+    pub fn save_to_file_as(&self, path: &Path, options: impl Into<SaveOptions>) -> Result<()> {
+        Ok(save_versioned_as(path, self, options)?)
     }
 
+    /// Load a store saved with `save_to_file` or `save_to_file_as` in any
+    /// format. JSON files written by older versions of the crate are
+    /// migrated as they are loaded.
     pub fn load_from_file(path: &Path) -> Result<Self> {
-        let contents = fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&contents)?)
+        Ok(load_versioned(path)?)
+    }
+
+    /// Save the store under `key` in any `StateStorage` as versioned JSON,
+    /// which `load_from_file` can also read.
+    pub fn save_to<S: StateStorage + ?Sized>(&self, storage: &S, key: &str) -> Result<()> {
+        Ok(save_versioned_to(storage, key, self)?)
+    }
+
+    /// Load a store saved with `save_to`, migrating it like
+    /// `load_from_file` does.
+    pub fn load_from<S: StateStorage + ?Sized>(storage: &S, key: &str) -> Result<Self> {
+        Ok(load_versioned_from(storage, key)?)
     }
     pub fn len(&self) -> usize {
         self.inner.borrow().cfr_states.len()
@@ -93,6 +208,23 @@ impl StateStore {
         self.len() == 0
     }
 
+    /// The CFR tree trained by `player_idx`.
+    pub fn get_state(&self, player_idx: usize) -> Option<CFRState> {
+        self.inner.borrow().cfr_states.get(player_idx).cloned()
+    }
+
+    /// The strategy the tree of the player to act in `game_state` has
+    /// trained for them, see `CFRState::query`.
+    pub fn query<T: ActionGenerator>(
+        &self,
+        game_state: &GameState,
+        hole_cards: Hand,
+    ) -> Result<ActionDistribution, QueryError> {
+        self.get_state(game_state.to_act_idx())
+            .ok_or(QueryError::NotInTree)?
+            .query::<T>(game_state, hole_cards)
+    }
+
     pub fn traversal_len(&self, player_idx: usize) -> usize {
         self.inner
             .borrow()
@@ -199,7 +331,7 @@ impl Default for StateStore {
 mod tests {
 
     use super::*;
-    use std::path::PathBuf;
+    use crate::arena::cfr::NodeData;
     use tempfile::tempdir;
 
     #[test]
@@ -225,6 +357,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_merge_from() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut state_store = StateStore::new();
+        let (mut state, _) = state_store.new_state(game_state.clone(), 0);
+        state.add(0, 0, NodeData::Chance);
+        state.get_mut(0).unwrap().increment_count(0);
+        state_store.pop_traversal(0);
+
+        let mut other = StateStore::new();
+        for player_idx in 0..2 {
+            let (mut other_state, _) = other.new_state(game_state.clone(), player_idx);
+            other_state.add(0, 0, NodeData::Chance);
+            other_state.get_mut(0).unwrap().increment_count(0);
+            other.pop_traversal(player_idx);
+        }
+
+        state_store.merge_from(&other).unwrap();
+        assert_eq!(2, state_store.len());
+        // Agents already holding the tree see the merge.
+        assert_eq!(2, state.get(0).unwrap().get_count(0));
+        let other_state = state_store.get_state(1).unwrap();
+        assert_eq!(1, other_state.get(0).unwrap().get_count(0));
+        assert_eq!(1, state_store.traversal_len(1));
+    }
+
     #[test]
     fn test_push_len() {
         let mut state_store = StateStore::new();
@@ -290,4 +448,49 @@ mod tests {
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_load_unversioned_file() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("state_store.json");
+
+        let mut state_store = StateStore::new();
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let (_state, _traversal) = state_store.new_state(game_state, 0);
+
+        // Files were written without any version envelope before it existed.
+        std::fs::write(&file_path, serde_json::to_string(&state_store)?)?;
+
+        let loaded_store = StateStore::load_from_file(&file_path)?;
+        assert_eq!(1, loaded_store.len());
+        assert_eq!(2, loaded_store.traversal_len(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_load_formats() -> Result<()> {
+        let dir = tempdir()?;
+        let json_path = dir.path().join("state_store.json");
+        let binary_path = dir.path().join("state_store.bin");
+
+        let mut state_store = StateStore::new();
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let (_state, _traversal) = state_store.new_state(game_state.clone(), 0);
+        let (_state2, _traversal2) = state_store.new_state(game_state, 1);
+
+        state_store.save_to_file_as(&json_path, SaveFormat::Json)?;
+        state_store.save_to_file(&binary_path)?;
+        assert!(std::fs::metadata(&binary_path)?.len() < std::fs::metadata(&json_path)?.len());
+
+        for path in [&json_path, &binary_path] {
+            let loaded_store = StateStore::load_from_file(path)?;
+            assert_eq!(2, loaded_store.len());
+            assert_eq!(2, loaded_store.traversal_len(1));
+            assert_eq!(
+                state_store.get_state(1).unwrap().starting_game_state(),
+                loaded_store.get_state(1).unwrap().starting_game_state()
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::arena::errors::VersionedFileError;
+use crate::arena::storage::{StateStorage, load_versioned_from, save_versioned_to};
+use crate::arena::versioned::{Versioned, load_versioned, save_versioned};
+
+use super::{CFRState, StateStore};
+
+/// The strategy for a single decision point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyEntry {
+    /// The player that is acting.
+    pub player_idx: usize,
+    /// The child indices followed from the root of the tree to reach the
+    /// decision.
+    pub path: Vec<usize>,
+    /// The probability of taking each action, indexed the same way as the
+    /// children of the node. These add up to one.
+    pub probabilities: Vec<f32>,
+}
+
+/// A trained strategy that doesn't need the CFR tree to be kept around.
+///
+/// A saved `CFRState` carries everything needed to keep training, which is
+/// far more than playing needs. Once training is done the average strategy
+/// at every decision point is pulled out into a `StrategyProfile`. That can
+/// be saved, loaded and queried on its own, for example by a bot or a
+/// strategy server.
+///
+/// Decision points are identified by the acting player and the path of
+/// child indices from the root of the tree. The meaning of those indices
+/// comes from the `ActionGenerator` that was used while training.
+///
+/// # Examples
+///
+/// ```
+/// use rs_poker::arena::cfr::StrategyProfile;
+///
+/// let mut profile = StrategyProfile::new();
+/// profile.insert(0, vec![0, 3], vec![0.25, 0.75]);
+///
+/// assert_eq!(Some(&[0.25, 0.75][..]), profile.get(0, &[0, 3]));
+/// assert_eq!(None, profile.get(1, &[0, 3]));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<StrategyEntry>", into = "Vec<StrategyEntry>")]
+pub struct StrategyProfile {
+    strategies: BTreeMap<(usize, Vec<usize>), Vec<f32>>,
+}
+
+impl Versioned for StrategyProfile {
+    const KIND: &'static str = "strategy_profile";
+    const VERSION: u32 = 1;
+
+    fn validate(&self) -> Result<(), VersionedFileError> {
+        for (player_idx, path, probabilities) in self.iter() {
+            let total: f32 = probabilities.iter().sum();
+            let valid = probabilities.iter().all(|p| p.is_finite() && *p >= 0.0)
+                && (total - 1.0).abs() < 1e-3;
+            if !valid {
+                return Err(VersionedFileError::Invalid {
+                    kind: Self::KIND,
+                    reason: format!(
+                        "the strategy of player {player_idx} at {path:?} isn't a probability distribution"
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StrategyProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Extract the average strategy of `player_idx` from a trained tree.
+    ///
+    /// Decision points for other players, and ones that were never
+    /// updated, are skipped.
+    pub fn from_cfr_state(cfr_state: &CFRState, player_idx: usize) -> Self {
+        let mut profile = Self::new();
+        for ((player, path), probabilities) in cfr_state.average_strategy() {
+            if player == player_idx {
+                profile.insert(player, path, probabilities.into_values().collect());
+            }
+        }
+        profile
+    }
+
+    /// Extract the strategy of every player in a store. Each player's
+    /// strategy comes from the tree that player trained.
+    pub fn from_state_store(state_store: &StateStore) -> Self {
+        let mut profile = Self::new();
+        for player_idx in 0..state_store.len() {
+            if let Some(cfr_state) = state_store.get_state(player_idx) {
+                profile.extend(Self::from_cfr_state(&cfr_state, player_idx));
+            }
+        }
+        profile
+    }
+
+    /// Set the strategy of `player_idx` at the decision reached by `path`,
+    /// returning the strategy it replaced.
+    pub fn insert(
+        &mut self,
+        player_idx: usize,
+        path: Vec<usize>,
+        probabilities: Vec<f32>,
+    ) -> Option<Vec<f32>> {
+        self.strategies.insert((player_idx, path), probabilities)
+    }
+
+    /// The strategy of `player_idx` at the decision reached by `path`.
+    pub fn get(&self, player_idx: usize, path: &[usize]) -> Option<&[f32]> {
+        self.strategies
+            .get(&(player_idx, path.to_vec()))
+            .map(Vec::as_slice)
+    }
+
+    /// Add every decision from `other`, replacing any that are in both.
+    pub fn extend(&mut self, other: StrategyProfile) {
+        self.strategies.extend(other.strategies);
+    }
+
+    /// The number of decision points.
+    pub fn len(&self) -> usize {
+        self.strategies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strategies.is_empty()
+    }
+
+    /// Iterate over `(player_idx, path, probabilities)` ordered by player
+    /// and then path.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &[usize], &[f32])> + '_ {
+        self.strategies
+            .iter()
+            .map(|((player_idx, path), probabilities)| {
+                (*player_idx, path.as_slice(), probabilities.as_slice())
+            })
+    }
+
+    /// Save the profile as a versioned JSON file.
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        Ok(save_versioned(path, self)?)
+    }
+
+    /// Load a profile saved with `save_to_file`. Profiles with strategies
+    /// that aren't probability distributions are rejected.
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        Ok(load_versioned(path)?)
+    }
+
+    /// Save the profile under `key` in any `StateStorage`.
+    pub fn save_to<S: StateStorage + ?Sized>(&self, storage: &S, key: &str) -> Result<()> {
+        Ok(save_versioned_to(storage, key, self)?)
+    }
+
+    /// Load a profile saved with `save_to`.
+    pub fn load_from<S: StateStorage + ?Sized>(storage: &S, key: &str) -> Result<Self> {
+        Ok(load_versioned_from(storage, key)?)
+    }
+}
+
+impl From<Vec<StrategyEntry>> for StrategyProfile {
+    fn from(entries: Vec<StrategyEntry>) -> Self {
+        let mut profile = Self::new();
+        for entry in entries {
+            profile.insert(entry.player_idx, entry.path, entry.probabilities);
+        }
+        profile
+    }
+}
+
+impl From<StrategyProfile> for Vec<StrategyEntry> {
+    fn from(profile: StrategyProfile) -> Self {
+        profile
+            .strategies
+            .into_iter()
+            .map(|((player_idx, path), probabilities)| StrategyEntry {
+                player_idx,
+                path,
+                probabilities,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+    use tempfile::tempdir;
+
+    use crate::arena::GameState;
+    use crate::arena::cfr::{NodeData, PlayerData, RegretMatcher};
+    use crate::arena::storage::MemoryStorage;
+
+    use super::*;
+
+    fn player_node(cfr_state: &mut CFRState, parent: usize, child: usize, player: usize) -> usize {
+        let mut matcher = RegretMatcher::new(3).unwrap();
+        matcher.update_regret(array![0.0, 1.0, 3.0].view()).unwrap();
+        cfr_state.add(
+            parent,
+            child,
+            NodeData::Player(PlayerData {
+                regret_matcher: Some(Box::new(matcher)),
+                player_idx: player,
+            }),
+        )
+    }
+
+    #[test]
+    fn test_from_cfr_state() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut cfr_state = CFRState::new(game_state);
+        let first = player_node(&mut cfr_state, 0, 0, 0);
+        let second = player_node(&mut cfr_state, first, 2, 1);
+        player_node(&mut cfr_state, second, 1, 0);
+        // Never updated, so there's no strategy to extract.
+        cfr_state.add(
+            first,
+            1,
+            NodeData::Player(PlayerData {
+                regret_matcher: Some(Box::new(RegretMatcher::new(3).unwrap())),
+                player_idx: 1,
+            }),
+        );
+
+        let profile = StrategyProfile::from_cfr_state(&cfr_state, 0);
+        assert_eq!(2, profile.len());
+        let probabilities = profile.get(0, &[0]).unwrap();
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(profile.get(0, &[0, 2, 1]).is_some());
+        assert!(profile.get(1, &[0, 2]).is_none());
+
+        let other = StrategyProfile::from_cfr_state(&cfr_state, 1);
+        assert_eq!(1, other.len());
+        assert!(other.get(1, &[0, 2]).is_some());
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut profile = StrategyProfile::new();
+        profile.insert(0, vec![0], vec![0.5, 0.5]);
+        profile.insert(1, vec![0, 1], vec![0.1, 0.2, 0.7]);
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("profile.json");
+        profile.save_to_file(&path).unwrap();
+        assert_eq!(profile, StrategyProfile::load_from_file(&path).unwrap());
+
+        let entries: Vec<_> = profile.iter().map(|(player, _, _)| player).collect();
+        assert_eq!(vec![0, 1], entries);
+
+        let storage = MemoryStorage::new();
+        profile.save_to(&storage, "profile").unwrap();
+        assert_eq!(
+            profile,
+            StrategyProfile::load_from(&storage, "profile").unwrap()
+        );
+
+        profile.insert(1, vec![0, 2], vec![0.5, 0.6]);
+        profile.save_to_file(&path).unwrap();
+        let err = StrategyProfile::load_from_file(&path).unwrap_err();
+        assert!(err.to_string().contains("player 1 at [0, 2]"), "{err}");
+    }
+}
@@ -0,0 +1,408 @@
+//! Solve part of the game again from a spot reached while playing.
+use ndarray::ArrayView1;
+use rand::Rng;
+
+use crate::arena::GameState;
+use crate::arena::errors::SpotError;
+use crate::core::CardBitSet;
+use crate::holdem::Range;
+
+use super::spot::{deal_from_ranges, known_cards, play_out};
+use super::{
+    ActionGenerator, CFRState, NodeData, PlayerData, RegretMatcher, RegretUpdate, SpotSolution,
+    StateStore, TraversalState,
+};
+
+/// The rest of the hand from a spot reached while playing, to be solved on
+/// its own.
+///
+/// A blueprint trained on the whole game has to keep its abstraction coarse
+/// to stay a manageable size. Once play reaches a spot, the part of the game
+/// left is small enough to solve again with a finer action generator, with
+/// each player's hands weighted by how likely they are to have got there.
+///
+/// The decision of the player to act is kept at the first child of the
+/// root of every tree, and is updated every iteration. Decisions after it
+/// are learned the way `CFRAgent` learns them.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::action::AgentAction;
+/// use rs_poker::arena::cfr::{AbstractCFRActionGenerator, Subgame};
+/// use rs_poker::arena::game_state::{Round, RoundData};
+/// use rs_poker::core::{Hand, PlayerBitSet};
+/// use rs_poker::holdem::Range;
+///
+/// // On the river player 0 is all in and player 1 has top pair.
+/// let board = Hand::new_from_str("AcTh4d8d2s").unwrap();
+/// let hero = Hand::new_from_str("AsJsAcTh4d8d2s").unwrap();
+/// let round_data = RoundData::new_with_bets(100.0, PlayerBitSet::new(2), 1, vec![900.0, 0.0]);
+/// let game_state = GameState::new(
+///     Round::River,
+///     round_data,
+///     board.iter().collect(),
+///     vec![board, hero],
+///     vec![0.0, 900.0],
+///     vec![1000.0, 100.0],
+///     5.0,
+///     0.0,
+///     0.0,
+///     0,
+/// );
+///
+/// // Player 0 shoves a set of aces, and bluffs with king queen a tenth of
+/// // the time they have it. That's too few bluffs to call.
+/// let mut villain: Range = "AA,KQs".parse().unwrap();
+/// for (first, second, _) in "KQs".parse::<Range>().unwrap().combos() {
+///     villain.set_weight(first, second, 0.1);
+/// }
+///
+/// let mut subgame = Subgame::new(game_state, vec![Some(villain), None]).unwrap();
+/// let solution = subgame
+///     .solve::<AbstractCFRActionGenerator, _>(200, &mut rand::rng())
+///     .unwrap();
+/// assert_eq!(Some(&AgentAction::Fold), solution.best_action());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Subgame {
+    game_state: GameState,
+    ranges: Vec<Option<Range>>,
+    known: CardBitSet,
+    state_store: StateStore,
+    regret_update: RegretUpdate,
+}
+
+impl Subgame {
+    /// A subgame from `game_state` with fresh trees.
+    ///
+    /// `ranges` has an entry for each player, like `solve_spot`. A range's
+    /// weights are the reach probabilities of the player's hands, how
+    /// likely they are to have played each of them to the spot, and hands
+    /// are dealt in proportion to them. A player with `None` keeps the
+    /// cards they hold in `game_state`.
+    pub fn new(game_state: GameState, ranges: Vec<Option<Range>>) -> Result<Self, SpotError> {
+        let num_players = game_state.num_players;
+        let state_store = StateStore::from_states(
+            (0..num_players)
+                .map(|_| CFRState::new(game_state.clone()))
+                .collect(),
+        );
+        Self::with_state_store(game_state, ranges, state_store)
+    }
+
+    /// A subgame from `game_state` that starts from what `blueprint` has
+    /// learned below it. `node_indices` has the node each player's tree
+    /// reached at the spot, which is the chosen child of the node of the
+    /// player's `TraversalState`.
+    ///
+    /// Regrets are kept by child index, so this only helps when the
+    /// subgame is solved with the blueprint's action generator. A finer
+    /// one should start from `new`.
+    pub fn from_blueprint(
+        blueprint: &StateStore,
+        node_indices: &[usize],
+        game_state: GameState,
+        ranges: Vec<Option<Range>>,
+    ) -> Result<Self, SpotError> {
+        let to_act = game_state.to_act_idx();
+        let trees = (0..game_state.num_players)
+            .map(|player_idx| {
+                let cfr_state = blueprint.get_state(player_idx);
+                let node_idx = node_indices.get(player_idx).copied();
+                let starts_at_decision = match (&cfr_state, node_idx) {
+                    (Some(cfr_state), Some(node_idx)) => {
+                        cfr_state.get(node_idx).is_some_and(|node| {
+                            matches!(&*node.data, NodeData::Player(player_data) if player_data.player_idx == to_act)
+                        })
+                    }
+                    _ => false,
+                };
+                if !starts_at_decision {
+                    return Err(SpotError::NoSubtree(player_idx));
+                }
+                Ok(cfr_state
+                    .unwrap()
+                    .subtree(node_idx.unwrap(), game_state.clone())
+                    .unwrap())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::with_state_store(game_state, ranges, StateStore::from_states(trees))
+    }
+
+    fn with_state_store(
+        game_state: GameState,
+        ranges: Vec<Option<Range>>,
+        state_store: StateStore,
+    ) -> Result<Self, SpotError> {
+        if game_state.is_complete() || !game_state.player_active.get(game_state.to_act_idx()) {
+            return Err(SpotError::NoDecision);
+        }
+        if ranges.len() != game_state.num_players {
+            return Err(SpotError::WrongNumberOfRanges(game_state.num_players));
+        }
+        let known = known_cards(&game_state, &ranges);
+        for (idx, range) in ranges.iter().enumerate() {
+            if let Some(range) = range
+                && range.sample(known, &mut crate::core::rng()).is_none()
+            {
+                return Err(SpotError::EmptyRange(idx));
+            }
+        }
+        Ok(Self {
+            game_state,
+            ranges,
+            known,
+            state_store,
+            regret_update: RegretUpdate::default(),
+        })
+    }
+
+    /// Update regrets with `regret_update` instead of plain regret
+    /// matching.
+    pub fn with_regret_update(mut self, regret_update: RegretUpdate) -> Self {
+        self.regret_update = regret_update;
+        self
+    }
+
+    pub fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+
+    /// The subgame's trees, one for each player, rooted at the spot.
+    pub fn state_store(&self) -> &StateStore {
+        &self.state_store
+    }
+
+    /// Run `iterations` more iterations with the action generator `T`,
+    /// which is usually finer than the blueprint's.
+    ///
+    /// Each iteration deals hands from the ranges and plays every action
+    /// of the player to act out to the end of the hand. The strategy
+    /// returned is the average over every iteration so far, and the EVs
+    /// are over the iterations of this call.
+    pub fn solve<T: ActionGenerator + 'static, R: Rng>(
+        &mut self,
+        iterations: usize,
+        rng: &mut R,
+    ) -> Result<SpotSolution, SpotError> {
+        let game_state = &self.game_state;
+        let player_idx = game_state.to_act_idx();
+        let action_generator = {
+            let (cfr_state, _) = self.state_store.push_traversal(player_idx);
+            self.state_store.pop_traversal(player_idx);
+            T::new(cfr_state, TraversalState::new_root(player_idx))
+        };
+        let actions = action_generator.gen_possible_actions(game_state);
+        let num_potential = action_generator.num_potential_actions(game_state);
+        let mut ev_totals = vec![0.0; actions.len()];
+
+        // An action the generator has room for but can't be played here is
+        // treated as losing everything left, so it's never chosen.
+        let worst = -game_state.stacks[player_idx];
+
+        let mut dealt = 0;
+        for _ in 0..iterations {
+            let Some(sampled) = deal_from_ranges(game_state, &self.ranges, self.known, rng) else {
+                continue;
+            };
+            dealt += 1;
+
+            let mut rewards = vec![worst; num_potential];
+            for (action, total) in actions.iter().zip(ev_totals.iter_mut()) {
+                let reward = play_out::<T, _>(&mut self.state_store, &sampled, action, rng);
+                rewards[action_generator.action_to_idx(&sampled, action)] = reward;
+                *total += reward;
+            }
+            let regret_update = self.regret_update;
+            root_matcher(&self.state_store, player_idx, num_potential, |matcher| {
+                matcher
+                    .update_regret_with(ArrayView1::from(&rewards), regret_update)
+                    .unwrap();
+            });
+        }
+        if dealt == 0 && iterations > 0 {
+            return Err(SpotError::NoDeal);
+        }
+
+        let weights = root_matcher(&self.state_store, player_idx, num_potential, |matcher| {
+            if matcher.num_updates() > 0 {
+                matcher.best_weight()
+            } else {
+                vec![1.0; num_potential]
+            }
+        });
+        let mut strategy: Vec<f32> = actions
+            .iter()
+            .map(|action| weights[action_generator.action_to_idx(game_state, action)])
+            .collect();
+        let total: f32 = strategy.iter().sum();
+        if total > 0.0 {
+            strategy
+                .iter_mut()
+                .for_each(|frequency| *frequency /= total);
+        } else {
+            strategy.fill(1.0 / actions.len() as f32);
+        }
+
+        Ok(SpotSolution {
+            player_idx,
+            actions,
+            strategy,
+            evs: ev_totals
+                .into_iter()
+                .map(|total| total / dealt.max(1) as f32)
+                .collect(),
+        })
+    }
+}
+
+/// Run `f` on the regret matcher of the decision at the spot, giving it a
+/// fresh one if it has none or one for a different number of actions.
+fn root_matcher<O>(
+    state_store: &StateStore,
+    player_idx: usize,
+    num_potential: usize,
+    f: impl FnOnce(&mut RegretMatcher) -> O,
+) -> O {
+    let mut cfr_state = state_store.get_state(player_idx).unwrap();
+    let existing = cfr_state.get(0).unwrap().get_child(0);
+    let node_idx = existing.unwrap_or_else(|| {
+        cfr_state.add(
+            0,
+            0,
+            NodeData::Player(PlayerData {
+                regret_matcher: None,
+                player_idx,
+            }),
+        )
+    });
+    let mut node = cfr_state.get_mut(node_idx).unwrap();
+    let NodeData::Player(player_data) = &mut *node.data else {
+        panic!("Expected the subgame to start at a decision, found {node:?}");
+    };
+    let matcher = player_data
+        .regret_matcher
+        .get_or_insert_with(|| Box::new(RegretMatcher::new(num_potential).unwrap()));
+    if matcher.num_actions() != num_potential {
+        **matcher = RegretMatcher::new(num_potential).unwrap();
+    }
+    f(matcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::action::AgentAction;
+    use crate::arena::cfr::{AbstractCFRActionGenerator, BasicCFRActionGenerator};
+    use crate::arena::game_state::{Round, RoundData};
+    use crate::core::{Hand, PlayerBitSet};
+
+    use super::*;
+
+    /// Player 0 is all in on the river and player 1 has to decide.
+    fn river_all_in(hands: Vec<Hand>) -> GameState {
+        let board = Hand::new_from_str("AcTh4d8d2s").unwrap();
+        let round_data = RoundData::new_with_bets(100.0, PlayerBitSet::new(2), 1, vec![900.0, 0.0]);
+        GameState::new(
+            Round::River,
+            round_data,
+            board.iter().collect(),
+            hands,
+            vec![0.0, 900.0],
+            vec![1000.0, 100.0],
+            5.0,
+            0.0,
+            0.0,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_reach_changes_the_answer() {
+        let board = Hand::new_from_str("AcTh4d8d2s").unwrap();
+        let hero = Hand::new_from_str("AsJsAcTh4d8d2s").unwrap();
+        let game_state = river_all_in(vec![board, hero]);
+
+        let bluffs: Range = "KQs".parse().unwrap();
+        let mut villain: Range = "AA".parse().unwrap();
+        for (first, second, _) in bluffs.combos() {
+            villain.set_weight(first, second, 1.0);
+        }
+        let mut subgame =
+            Subgame::new(game_state.clone(), vec![Some(villain.clone()), None]).unwrap();
+        let solution = subgame
+            .solve::<BasicCFRActionGenerator, _>(100, &mut rand::rng())
+            .unwrap();
+        assert_eq!(Some(&AgentAction::Bet(900.0)), solution.best_action());
+
+        // Hardly ever bluffing makes calling a mistake.
+        for (first, second, _) in bluffs.combos() {
+            villain.set_weight(first, second, 0.01);
+        }
+        let mut subgame = Subgame::new(game_state, vec![Some(villain), None]).unwrap();
+        let solution = subgame
+            .solve::<AbstractCFRActionGenerator, _>(100, &mut rand::rng())
+            .unwrap();
+        assert_eq!(Some(&AgentAction::Fold), solution.best_action());
+        assert_eq!(2, subgame.state_store().len());
+    }
+
+    #[test]
+    fn test_from_blueprint() {
+        let hands = vec![
+            Hand::new_from_str("AsKsAcTh4d8d2s").unwrap(),
+            Hand::new_from_str("JdTcAcTh4d8d2s").unwrap(),
+        ];
+        let game_state = river_all_in(hands);
+
+        // A blueprint that's already learned to fold at the spot, under a
+        // chance node.
+        let mut blueprint = StateStore::new();
+        let mut node_indices = Vec::new();
+        for player_idx in 0..2 {
+            let (mut cfr_state, _) = blueprint.new_state(game_state.clone(), player_idx);
+            let chance = cfr_state.add(0, 0, NodeData::Chance);
+            let mut matcher = RegretMatcher::new(3).unwrap();
+            matcher
+                .update_regret(ndarray::array![0.0, -900.0, -900.0].view())
+                .unwrap();
+            let decision = cfr_state.add(
+                chance,
+                7,
+                NodeData::Player(PlayerData {
+                    regret_matcher: Some(Box::new(matcher)),
+                    player_idx: 1,
+                }),
+            );
+            cfr_state.add(decision, 0, NodeData::Chance);
+            node_indices.push(decision);
+        }
+
+        let subgame = Subgame::from_blueprint(
+            &blueprint,
+            &node_indices,
+            game_state.clone(),
+            vec![None, None],
+        )
+        .unwrap();
+        let cfr_state = subgame.state_store().get_state(1).unwrap();
+        assert_eq!(Some(1), cfr_state.get(0).unwrap().get_child(0));
+        assert_eq!(Some(2), cfr_state.get(1).unwrap().get_child(0));
+        assert_eq!(3, cfr_state.internal_state().borrow().nodes.len());
+
+        let mut subgame = subgame;
+        let solution = subgame
+            .solve::<BasicCFRActionGenerator, _>(0, &mut rand::rng())
+            .unwrap();
+        assert_eq!(Some(&AgentAction::Fold), solution.best_action());
+
+        // The chance node isn't a decision to start from.
+        let chance_indices = vec![1, 1];
+        assert_eq!(
+            Err(SpotError::NoSubtree(0)),
+            Subgame::from_blueprint(&blueprint, &chance_indices, game_state, vec![None, None])
+                .map(|_| ())
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+
+use super::{CFRState, NodeRef};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Order {
+    DepthFirst,
+    BreadthFirst,
+}
+
+/// Walks every node of a `CFRState` reachable from the root, giving the
+/// index, depth and node of each. The root is at depth zero, and children
+/// are visited in order of their child index.
+///
+/// Made by `CFRState::iter_dfs` and `CFRState::iter_bfs`. Each node is
+/// borrowed from the tree, so it can't be changed while any are held.
+pub struct TreeIter<'a> {
+    cfr_state: &'a CFRState,
+    pending: VecDeque<(usize, usize)>,
+    order: Order,
+}
+
+impl<'a> TreeIter<'a> {
+    pub(crate) fn depth_first(cfr_state: &'a CFRState) -> Self {
+        Self::new(cfr_state, Order::DepthFirst)
+    }
+
+    pub(crate) fn breadth_first(cfr_state: &'a CFRState) -> Self {
+        Self::new(cfr_state, Order::BreadthFirst)
+    }
+
+    fn new(cfr_state: &'a CFRState, order: Order) -> Self {
+        Self {
+            cfr_state,
+            pending: VecDeque::from([(0, 0)]),
+            order,
+        }
+    }
+}
+
+impl<'a> Iterator for TreeIter<'a> {
+    type Item = (usize, usize, NodeRef<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node_idx, depth) = match self.order {
+                Order::DepthFirst => self.pending.pop_back()?,
+                Order::BreadthFirst => self.pending.pop_front()?,
+            };
+            let Some(node) = self.cfr_state.get(node_idx) else {
+                continue;
+            };
+            let children = node.iter_children().map(|(_, child)| (child, depth + 1));
+            match self.order {
+                // Pushed backwards so the first child is popped first.
+                Order::DepthFirst => {
+                    let children: Vec<_> = children.collect();
+                    self.pending.extend(children.into_iter().rev());
+                }
+                Order::BreadthFirst => self.pending.extend(children),
+            }
+            return Some((node_idx, depth, node));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::GameState;
+    use crate::arena::cfr::{NodeData, PlayerData, TerminalData};
+
+    use super::*;
+
+    /// root -> chance, which deals 3 to one player node and 1 to another,
+    /// each with a terminal below.
+    fn tree() -> CFRState {
+        let mut cfr_state =
+            CFRState::new(GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0));
+        let chance = cfr_state.add(0, 0, NodeData::Chance);
+        let player = |player_idx| {
+            NodeData::Player(PlayerData {
+                regret_matcher: None,
+                player_idx,
+            })
+        };
+        let third = cfr_state.add(chance, 3, player(0));
+        let first = cfr_state.add(chance, 1, player(1));
+        cfr_state.add(third, 0, NodeData::Terminal(TerminalData::new(1.0)));
+        cfr_state.add(first, 2, NodeData::Terminal(TerminalData::new(-1.0)));
+        cfr_state
+    }
+
+    #[test]
+    fn test_depth_first() {
+        let cfr_state = tree();
+        let visited: Vec<(usize, usize)> = cfr_state
+            .iter_dfs()
+            .map(|(node_idx, depth, _)| (node_idx, depth))
+            .collect();
+        assert_eq!(
+            vec![(0, 0), (1, 1), (3, 2), (5, 3), (2, 2), (4, 3)],
+            visited
+        );
+    }
+
+    #[test]
+    fn test_breadth_first() {
+        let cfr_state = tree();
+        let visited: Vec<(usize, usize)> = cfr_state
+            .iter_bfs()
+            .map(|(node_idx, depth, _)| (node_idx, depth))
+            .collect();
+        assert_eq!(
+            vec![(0, 0), (1, 1), (3, 2), (2, 2), (5, 3), (4, 3)],
+            visited
+        );
+
+        // The nodes are the ones at those indices.
+        for (node_idx, _, node) in cfr_state.iter_bfs() {
+            assert_eq!(node_idx, node.idx);
+        }
+        assert!(cfr_state.iter_bfs().next().unwrap().2.data.is_root());
+    }
+
+    #[test]
+    fn test_path_to() {
+        let cfr_state = tree();
+        assert_eq!(Some(vec![]), cfr_state.path_to(0));
+        assert_eq!(Some(vec![0, 3, 0]), cfr_state.path_to(4));
+        assert_eq!(Some(vec![0, 1, 2]), cfr_state.path_to(5));
+        assert_eq!(None, cfr_state.path_to(6));
+
+        // Following the path gets back to the node.
+        for (node_idx, depth, _) in cfr_state.iter_dfs() {
+            let path = cfr_state.path_to(node_idx).unwrap();
+            assert_eq!(depth, path.len());
+            let reached = path.iter().fold(0, |idx, child_idx| {
+                cfr_state.get(idx).unwrap().get_child(*child_idx).unwrap()
+            });
+            assert_eq!(node_idx, reached);
+        }
+    }
+}
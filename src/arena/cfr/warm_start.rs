@@ -0,0 +1,334 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::arena::action::{Action, AgentAction, PlayedActionPayload};
+use crate::arena::game_state::Round;
+use crate::arena::{GameState, Historian, HistorianError};
+use crate::core::{Card, CardSet};
+
+use super::{ActionGenerator, CFRState, NodeData, RegretMatcher, StateStore, TraversalState};
+
+/// Where a player is in the blueprint: the node and the child being gone
+/// down, or `None` once the hand has gone somewhere the blueprint never
+/// went.
+pub(crate) type PriorPosition = Rc<Cell<Option<(usize, usize)>>>;
+
+/// The parts of the blueprint's `ActionGenerator` that map the hand into
+/// its tree.
+trait PriorAbstraction {
+    fn action_to_idx(&self, game_state: &GameState, action: &AgentAction) -> usize;
+    fn played_action_to_idx(&self, game_state: &GameState, payload: &PlayedActionPayload) -> usize;
+    fn card_to_idx(&self, game_state: &GameState, known: CardSet, card: Card) -> usize;
+}
+
+impl<B: ActionGenerator> PriorAbstraction for B {
+    fn action_to_idx(&self, game_state: &GameState, action: &AgentAction) -> usize {
+        ActionGenerator::action_to_idx(self, game_state, action)
+    }
+
+    fn played_action_to_idx(&self, game_state: &GameState, payload: &PlayedActionPayload) -> usize {
+        ActionGenerator::played_action_to_idx(self, game_state, payload)
+    }
+
+    fn card_to_idx(&self, game_state: &GameState, known: CardSet, card: Card) -> usize {
+        ActionGenerator::card_to_idx(self, game_state, known, card)
+    }
+}
+
+/// A blueprint for a new training run to start from, rather than every
+/// decision starting out playing each action as often.
+///
+/// The blueprint is a `StateStore` trained with a coarser action generator
+/// `B`, such as one with fewer bet sizes or a lossy `CardAbstraction`. Every
+/// hand is followed through the blueprint's trees as well, with the cards
+/// and actions mapped through `B`, so when training reaches a decision for
+/// the first time the blueprint decision it falls in is known. The new
+/// regret matcher starts from that one's regrets and average strategy, see
+/// `RegretMatcher::warm_started`, with each action taking after the
+/// blueprint action `B` maps it to. Decisions the blueprint never reached
+/// start out as usual.
+///
+/// The blueprint is only read. Agents get a place in it with
+/// `CFRAgent::with_warm_start`, which is given back with `pop_traversal`
+/// once the hand is over, the same as a `StateStore`'s.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::cfr::{
+///     HeadsUpLimitConfig, LimitCFRActionGenerator, StateStore, SuitIsomorphism,
+///     ValueOnlyAbstraction, WarmStart,
+/// };
+///
+/// // A quick blueprint that ignores suits.
+/// let mut config = HeadsUpLimitConfig::default();
+/// let mut blueprint = StateStore::new();
+/// config.train::<ValueOnlyAbstraction, _>(&mut blueprint, 2, &mut rand::rng());
+///
+/// // Then a run that tells suits apart, starting from it.
+/// config.warm_start = Some(WarmStart::new::<
+///     LimitCFRActionGenerator<ValueOnlyAbstraction>,
+/// >(&blueprint));
+/// let mut state_store = StateStore::new();
+/// config.train::<SuitIsomorphism, _>(&mut state_store, 2, &mut rand::rng());
+/// ```
+#[derive(Clone)]
+pub struct WarmStart {
+    trees: Vec<CFRState>,
+    abstractions: Rc<[Box<dyn PriorAbstraction>]>,
+    positions: Rc<RefCell<Vec<Vec<PriorPosition>>>>,
+    weight: f32,
+}
+
+impl fmt::Debug for WarmStart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WarmStart")
+            .field("trees", &self.trees.len())
+            .field("weight", &self.weight)
+            .finish()
+    }
+}
+
+impl WarmStart {
+    /// Start from `blueprint`, whose trees were built with `B`.
+    pub fn new<B: ActionGenerator + 'static>(blueprint: &StateStore) -> Self {
+        let trees: Vec<CFRState> = (0..blueprint.len())
+            .filter_map(|player_idx| blueprint.get_state(player_idx))
+            .collect();
+        let abstractions = trees
+            .iter()
+            .enumerate()
+            .map(|(player_idx, tree)| {
+                Box::new(B::new(tree.clone(), TraversalState::new_root(player_idx)))
+                    as Box<dyn PriorAbstraction>
+            })
+            .collect();
+        let positions = (0..trees.len())
+            .map(|_| vec![Rc::new(Cell::new(Some((0, 0))))])
+            .collect();
+        Self {
+            trees,
+            abstractions,
+            positions: Rc::new(RefCell::new(positions)),
+            weight: 1.0,
+        }
+    }
+
+    /// How much the blueprint's regrets and average strategy count, one by
+    /// default. Less lets training move away from it sooner.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Copy `player_idx`'s place in the blueprint for a new agent to move
+    /// from. Players the blueprint has no tree for never get anywhere.
+    pub(crate) fn push_traversal(&self, player_idx: usize) -> PriorPosition {
+        let mut positions = self.positions.borrow_mut();
+        let position = positions
+            .get(player_idx)
+            .and_then(|stack| stack.last())
+            .and_then(|last| last.get());
+        let position = Rc::new(Cell::new(position));
+        if let Some(stack) = positions.get_mut(player_idx) {
+            stack.push(position.clone());
+        }
+        position
+    }
+
+    /// Drop the place in the blueprint given to the last agent for
+    /// `player_idx`.
+    pub fn pop_traversal(&self, player_idx: usize) {
+        if let Some(stack) = self.positions.borrow_mut().get_mut(player_idx) {
+            assert!(
+                stack.len() > 1,
+                "No blueprint traversal to pop for player {player_idx}"
+            );
+            stack.pop();
+        }
+    }
+
+    /// A historian that moves `position` through `player_idx`'s tree as
+    /// the hand is played.
+    pub(crate) fn historian(&self, player_idx: usize, position: PriorPosition) -> PriorHistorian {
+        PriorHistorian {
+            warm_start: self.clone(),
+            player_idx,
+            position,
+            dealt_cards: CardSet::new(),
+        }
+    }
+
+    /// A regret matcher for the decision reached at `position`, taken from
+    /// the blueprint's. `actions` are the actions offered with the index
+    /// each has in the new matcher.
+    pub(crate) fn regret_matcher(
+        &self,
+        player_idx: usize,
+        position: &PriorPosition,
+        game_state: &GameState,
+        actions: &[(usize, AgentAction)],
+        num_actions: usize,
+    ) -> Option<RegretMatcher> {
+        let (node_idx, child_idx) = position.get()?;
+        let tree = self.trees.get(player_idx)?;
+        let target_idx = tree.get(node_idx)?.get_child(child_idx)?;
+        let target = tree.get(target_idx)?;
+        let NodeData::Player(player_data) = &*target.data else {
+            return None;
+        };
+        let prior = player_data.regret_matcher.as_ref()?;
+
+        let abstraction = &self.abstractions[player_idx];
+        let mut mapping = vec![None; num_actions];
+        for (idx, action) in actions {
+            mapping[*idx] = Some(abstraction.action_to_idx(game_state, action));
+        }
+        RegretMatcher::warm_started(prior, &mapping, self.weight).ok()
+    }
+}
+
+/// Follows a hand through a player's blueprint tree, without changing it.
+pub(crate) struct PriorHistorian {
+    warm_start: WarmStart,
+    player_idx: usize,
+    position: PriorPosition,
+    dealt_cards: CardSet,
+}
+
+impl PriorHistorian {
+    /// Go down to the current child and on towards `child_idx`, or get lost
+    /// if the blueprint never went there.
+    fn advance(&mut self, child_idx: usize) {
+        let tree = &self.warm_start.trees[self.player_idx];
+        let next = self
+            .position
+            .get()
+            .and_then(|(node_idx, chosen)| tree.get(node_idx)?.get_child(chosen));
+        self.position
+            .set(next.map(|node_idx| (node_idx, child_idx)));
+    }
+
+    fn record_card(&mut self, game_state: &GameState, card: Card) {
+        let known = CardSet::from(game_state.hands[self.player_idx]) | self.dealt_cards;
+        let card_idx =
+            self.warm_start.abstractions[self.player_idx].card_to_idx(game_state, known, card);
+        self.dealt_cards.insert(card);
+        self.advance(card_idx);
+    }
+
+    fn record(&mut self, game_state: &GameState, action: &Action) {
+        let Some(abstraction) = self.warm_start.abstractions.get(self.player_idx) else {
+            return;
+        };
+        let action_idx = match action {
+            Action::PlayedAction(payload) => abstraction.played_action_to_idx(game_state, payload),
+            Action::FailedAction(payload) => {
+                abstraction.played_action_to_idx(game_state, &payload.result)
+            }
+            Action::DealStartingHand(payload) if payload.idx == self.player_idx => {
+                return self.record_card(game_state, payload.card);
+            }
+            Action::DealCommunity(card) => return self.record_card(game_state, *card),
+            Action::RoundAdvance(Round::Complete) => 0,
+            _ => return,
+        };
+        self.advance(action_idx);
+    }
+}
+
+/// An agent's historian along with the one following the blueprint.
+pub(crate) struct WarmStartHistorian<H> {
+    pub(crate) historian: H,
+    pub(crate) prior: PriorHistorian,
+}
+
+impl<H: Historian> Historian for WarmStartHistorian<H> {
+    fn record_action(
+        &mut self,
+        id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        self.prior.record(game_state, &action);
+        self.historian.record_action(id, game_state, action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use crate::arena::cfr::{BasicCFRActionGenerator, PlayerData};
+    use crate::core::PlayerBitSet;
+
+    use super::*;
+
+    #[test]
+    fn test_follows_blueprint() {
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut blueprint = StateStore::new();
+        let (mut cfr_state, _) = blueprint.new_state(game_state.clone(), 0);
+        blueprint.new_state(game_state.clone(), 1);
+        let mut matcher = RegretMatcher::new(3).unwrap();
+        matcher
+            .update_regret(array![-30.0, 0.0, 30.0].view())
+            .unwrap();
+        let decision = cfr_state.add(
+            0,
+            0,
+            NodeData::Player(PlayerData {
+                regret_matcher: Some(Box::new(matcher)),
+                player_idx: 0,
+            }),
+        );
+
+        let warm_start = WarmStart::new::<BasicCFRActionGenerator>(&blueprint).with_weight(0.5);
+        let position = warm_start.push_traversal(0);
+        // A finer generator offering two bets, which the blueprint only
+        // knows as a call.
+        let actions = vec![
+            (0, AgentAction::Fold),
+            (1, AgentAction::Bet(10.0)),
+            (2, AgentAction::Bet(30.0)),
+            (3, AgentAction::AllIn),
+        ];
+        let matcher = warm_start
+            .regret_matcher(0, &position, &game_state, &actions, 5)
+            .unwrap();
+        assert_eq!(vec![-15.0, 0.0, 0.0, 15.0, 0.0], matcher.regrets());
+        assert!(
+            warm_start
+                .regret_matcher(1, &warm_start.push_traversal(1), &game_state, &actions, 5)
+                .is_none()
+        );
+
+        // Going all in goes past what the blueprint knows.
+        let mut historian = warm_start.historian(0, position.clone());
+        let payload = PlayedActionPayload {
+            action: AgentAction::AllIn,
+            idx: 0,
+            round: Round::Preflop,
+            player_stack: 0.0,
+            starting_pot: 15.0,
+            final_pot: 115.0,
+            starting_bet: 10.0,
+            final_bet: 100.0,
+            starting_min_raise: 10.0,
+            final_min_raise: 90.0,
+            starting_player_bet: 5.0,
+            final_player_bet: 100.0,
+            players_active: PlayerBitSet::new(2),
+            players_all_in: PlayerBitSet::new(2),
+        };
+        historian.record(&game_state, &Action::PlayedAction(payload));
+        assert_eq!(Some((decision, 2)), position.get());
+        historian.record(&game_state, &Action::RoundAdvance(Round::Complete));
+        assert_eq!(None, position.get());
+
+        warm_start.pop_traversal(0);
+        warm_start.pop_traversal(1);
+        assert_eq!(Some((0, 0)), warm_start.push_traversal(0).get());
+    }
+}
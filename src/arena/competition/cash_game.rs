@@ -0,0 +1,376 @@
+use rand::Rng;
+use tracing::{event, trace_span};
+
+use crate::arena::{
+    GameState, HoldemSimulationBuilder, agent::AgentGenerator, errors::HoldemSimulationError,
+    historian::HistorianGenerator,
+};
+use crate::stats::ResultStats;
+
+/// Builds a [`CashGame`].
+///
+/// Every player sits down with one buy-in, 100 big blinds unless set with
+/// `buy_in`. By default a player who busts buys in again as often as they
+/// need to and stacks are never topped up.
+#[derive(Default)]
+pub struct CashGameBuilder {
+    agent_generators: Option<Vec<Box<dyn AgentGenerator>>>,
+    historian_generators: Option<Vec<Box<dyn HistorianGenerator>>>,
+    starting_game_state: Option<GameState>,
+    buy_in: Option<f32>,
+    top_up_to: Option<f32>,
+    max_buy_ins: Option<usize>,
+    panic_on_historian_error: bool,
+}
+
+/// Money in and out of a [`CashGame`] for every seat.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CashGameResults {
+    /// How many hands have been played.
+    pub hands: usize,
+    /// How many times each player bought in, counting the first.
+    pub buy_ins: Vec<usize>,
+    /// Everything each player has brought to the table, buy-ins and top-ups.
+    pub money_in: Vec<f32>,
+    /// What each player would leave with if they cashed out now.
+    pub money_out: Vec<f32>,
+    /// Each player's result for every hand they were dealt into, in big
+    /// blinds.
+    pub results: Vec<ResultStats>,
+}
+
+impl CashGameResults {
+    fn new(num_players: usize) -> Self {
+        CashGameResults {
+            hands: 0,
+            buy_ins: vec![0; num_players],
+            money_in: vec![0.0; num_players],
+            money_out: vec![0.0; num_players],
+            results: vec![ResultStats::default(); num_players],
+        }
+    }
+
+    /// How much the player is up or down.
+    pub fn net(&self, idx: usize) -> f32 {
+        self.money_out[idx] - self.money_in[idx]
+    }
+}
+
+/// A cash game where the same agents play hand after hand, buying in when
+/// they sit down or bust and topping up between hands.
+///
+/// Blinds, antes and the first dealer come from the starting game state,
+/// its stacks are replaced by the buy-in. Each call to `run` is a session
+/// that picks up where the last one left off, so money in and out are
+/// tracked across all of them. A player who busts with no buy-ins left sits
+/// out for the rest of the game.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::agent::{CallingAgentGenerator, RandomAgentGenerator};
+/// use rs_poker::arena::competition::CashGameBuilder;
+/// use rs_poker::arena::{AgentGenerator, GameState};
+///
+/// let agent_gens: Vec<Box<dyn AgentGenerator>> = vec![
+///     Box::<RandomAgentGenerator>::default(),
+///     Box::<CallingAgentGenerator>::default(),
+/// ];
+/// let mut cash_game = CashGameBuilder::default()
+///     .agent_generators(agent_gens)
+///     .starting_game_state(GameState::new_starting(vec![0.0; 2], 2.0, 1.0, 0.0, 0))
+///     // Reload to 100 big blinds whenever a stack drops below it.
+///     .top_up_to(200.0)
+///     .max_buy_ins(3)
+///     .build()
+///     .unwrap();
+///
+/// let results = cash_game.run(50).unwrap();
+/// // Chips are never created or lost.
+/// let net: f32 = (0..2).map(|idx| results.net(idx)).sum();
+/// assert!(net.abs() < 1e-3);
+/// ```
+pub struct CashGame {
+    agent_generators: Vec<Box<dyn AgentGenerator>>,
+    historian_generators: Vec<Box<dyn HistorianGenerator>>,
+    game_state: GameState,
+    buy_in: f32,
+    top_up_to: Option<f32>,
+    max_buy_ins: Option<usize>,
+    panic_on_historian_error: bool,
+    results: CashGameResults,
+}
+
+impl CashGameBuilder {
+    /// Sets the agent generators, one for each seat.
+    pub fn agent_generators(mut self, agent_generators: Vec<Box<dyn AgentGenerator>>) -> Self {
+        self.agent_generators = Some(agent_generators);
+        self
+    }
+
+    /// Sets the historian generators, called before every hand.
+    pub fn historian_generators(
+        mut self,
+        historian_generators: Vec<Box<dyn HistorianGenerator>>,
+    ) -> Self {
+        self.historian_generators = Some(historian_generators);
+        self
+    }
+
+    /// Sets the blinds, ante, number of seats and the first dealer.
+    pub fn starting_game_state(mut self, starting_game_state: GameState) -> Self {
+        self.starting_game_state = Some(starting_game_state);
+        self
+    }
+
+    /// The chips a player gets each time they buy in.
+    pub fn buy_in(mut self, buy_in: f32) -> Self {
+        self.buy_in = Some(buy_in);
+        self
+    }
+
+    /// Between hands, bring any stack below `top_up_to` back up to it. A
+    /// player who busted has to buy in first.
+    pub fn top_up_to(mut self, top_up_to: f32) -> Self {
+        self.top_up_to = Some(top_up_to);
+        self
+    }
+
+    /// The most times a player can buy in, counting the first.
+    pub fn max_buy_ins(mut self, max_buy_ins: usize) -> Self {
+        self.max_buy_ins = Some(max_buy_ins);
+        self
+    }
+
+    /// Sets whether the underlying `HoldemSimulation` should panic if a
+    /// historian errors.
+    pub fn panic_on_historian_error(mut self, panic_on_historian_error: bool) -> Self {
+        self.panic_on_historian_error = panic_on_historian_error;
+        self
+    }
+
+    /// Builds the `CashGame` and buys every player in.
+    pub fn build(self) -> Result<CashGame, HoldemSimulationError> {
+        let agent_generators = self
+            .agent_generators
+            .ok_or(HoldemSimulationError::NeedAgents)?;
+        let starting_game_state = self
+            .starting_game_state
+            .ok_or(HoldemSimulationError::NeedGameState)?;
+        let buy_in = self.buy_in.unwrap_or(100.0 * starting_game_state.big_blind);
+
+        let mut cash_game = CashGame {
+            agent_generators,
+            historian_generators: self.historian_generators.unwrap_or_default(),
+            results: CashGameResults::new(starting_game_state.num_players),
+            game_state: starting_game_state,
+            buy_in,
+            top_up_to: self.top_up_to,
+            max_buy_ins: self.max_buy_ins,
+            panic_on_historian_error: self.panic_on_historian_error,
+        };
+        let mut stacks = vec![0.0; cash_game.game_state.num_players];
+        cash_game.reload(&mut stacks);
+        cash_game.game_state = GameState::new_starting(
+            stacks,
+            cash_game.game_state.big_blind,
+            cash_game.game_state.small_blind,
+            cash_game.game_state.ante,
+            cash_game.game_state.dealer_idx,
+        );
+        Ok(cash_game)
+    }
+}
+
+impl CashGame {
+    /// Play a session of up to `num_hands` hands. It ends early if fewer
+    /// than two players have chips.
+    pub fn run(&mut self, num_hands: usize) -> Result<&CashGameResults, HoldemSimulationError> {
+        self.run_with_rng(num_hands, &mut crate::core::rng())
+    }
+
+    /// Like `run`, but the cards are dealt using `rng`. A seeded rng makes
+    /// the deals reproducible.
+    pub fn run_with_rng<R: Rng>(
+        &mut self,
+        num_hands: usize,
+        rand: &mut R,
+    ) -> Result<&CashGameResults, HoldemSimulationError> {
+        let span = trace_span!("CashGame::run");
+        let _enter = span.enter();
+
+        for _ in 0..num_hands {
+            if self.game_state.stacks.iter().filter(|s| **s > 0.0).count() < 2 {
+                break;
+            }
+            let agents = self
+                .agent_generators
+                .iter()
+                .map(|builder| builder.generate(&self.game_state))
+                .collect::<Vec<_>>();
+            let historians = self
+                .historian_generators
+                .iter()
+                .map(|builder| builder.generate(&self.game_state))
+                .collect::<Vec<_>>();
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(self.game_state.clone())
+                .agents(agents)
+                .historians(historians)
+                .panic_on_historian_error(self.panic_on_historian_error)
+                .build()?;
+            sim.run(rand);
+
+            let game_state = sim.game_state;
+            self.results.hands += 1;
+            for (idx, (starting, ending)) in game_state
+                .starting_stacks
+                .iter()
+                .zip(game_state.stacks.iter())
+                .enumerate()
+            {
+                if *starting > 0.0 {
+                    let change = (*ending - *starting) / game_state.big_blind;
+                    self.results.results[idx].push(change as f64);
+                }
+            }
+
+            let mut stacks = game_state.stacks;
+            self.reload(&mut stacks);
+
+            // Move the button to the next player with chips.
+            let num_players = stacks.len();
+            let mut dealer_idx = (game_state.dealer_idx + 1) % num_players;
+            while stacks[dealer_idx] == 0.0 && dealer_idx != game_state.dealer_idx {
+                dealer_idx = (dealer_idx + 1) % num_players;
+            }
+            self.game_state = GameState::new_starting(
+                stacks,
+                game_state.big_blind,
+                game_state.small_blind,
+                game_state.ante,
+                dealer_idx,
+            );
+        }
+        Ok(&self.results)
+    }
+
+    /// The money in and out so far.
+    pub fn results(&self) -> &CashGameResults {
+        &self.results
+    }
+
+    /// The stacks the next hand starts with.
+    pub fn stacks(&self) -> &[f32] {
+        &self.game_state.stacks
+    }
+
+    /// Buy in everyone who is out of chips and has buy-ins left, then top
+    /// up the short stacks.
+    fn reload(&mut self, stacks: &mut [f32]) {
+        for (idx, stack) in stacks.iter_mut().enumerate() {
+            let buy_ins = self.results.buy_ins[idx];
+            if *stack <= 0.0 && self.max_buy_ins.is_none_or(|max| buy_ins < max) {
+                event!(tracing::Level::DEBUG, idx, buy_ins, "buy_in");
+                *stack = self.buy_in;
+                self.results.buy_ins[idx] += 1;
+                self.results.money_in[idx] += self.buy_in;
+            }
+            if let Some(top_up_to) = self.top_up_to
+                && *stack > 0.0
+                && *stack < top_up_to
+            {
+                event!(tracing::Level::DEBUG, idx, stack, top_up_to, "top_up");
+                self.results.money_in[idx] += top_up_to - *stack;
+                *stack = top_up_to;
+            }
+            self.results.money_out[idx] = *stack;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::agent::{AllInAgentGenerator, CallingAgentGenerator, RandomAgentGenerator};
+
+    use super::*;
+
+    fn all_in_game(max_buy_ins: Option<usize>) -> CashGame {
+        let gens: Vec<Box<dyn AgentGenerator>> = vec![
+            Box::<AllInAgentGenerator>::default(),
+            Box::<AllInAgentGenerator>::default(),
+            Box::<AllInAgentGenerator>::default(),
+        ];
+        let mut builder = CashGameBuilder::default()
+            .agent_generators(gens)
+            .starting_game_state(GameState::new_starting(vec![0.0; 3], 2.0, 1.0, 0.0, 0));
+        if let Some(max_buy_ins) = max_buy_ins {
+            builder = builder.max_buy_ins(max_buy_ins);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_buy_in_defaults_to_100_big_blinds() {
+        let cash_game = all_in_game(None);
+        assert_eq!(&[200.0; 3], cash_game.stacks());
+        assert_eq!(vec![1; 3], cash_game.results().buy_ins);
+        assert_eq!(vec![200.0; 3], cash_game.results().money_in);
+    }
+
+    #[test]
+    fn test_rebuy_until_out_of_buy_ins() {
+        let mut cash_game = all_in_game(Some(2));
+        let results = cash_game.run(1000).unwrap().clone();
+
+        // Someone busts every hand, so everyone runs out of buy-ins but the
+        // last player standing.
+        assert!(results.hands < 1000);
+        assert_eq!(1, cash_game.stacks().iter().filter(|s| **s > 0.0).count());
+        assert!(results.buy_ins.iter().all(|b| *b <= 2));
+
+        let money_in: f32 = results.money_in.iter().sum();
+        let money_out: f32 = results.money_out.iter().sum();
+        assert_eq!(money_in, money_out);
+        assert_eq!(money_in, cash_game.stacks().iter().sum::<f32>());
+        assert_eq!(
+            money_in,
+            200.0 * results.buy_ins.iter().sum::<usize>() as f32
+        );
+    }
+
+    #[test]
+    fn test_top_up_across_sessions() {
+        let gens: Vec<Box<dyn AgentGenerator>> = vec![
+            Box::<RandomAgentGenerator>::default(),
+            Box::<CallingAgentGenerator>::default(),
+            Box::<RandomAgentGenerator>::default(),
+        ];
+        let mut cash_game = CashGameBuilder::default()
+            .agent_generators(gens)
+            .starting_game_state(GameState::new_starting(vec![0.0; 3], 2.0, 1.0, 0.0, 0))
+            .buy_in(100.0)
+            .top_up_to(200.0)
+            .build()
+            .unwrap();
+        // Topped up as soon as they sit down.
+        assert_eq!(&[200.0; 3], cash_game.stacks());
+
+        crate::core::with_seed(42, || {
+            cash_game.run(20).unwrap();
+            cash_game.run(20).unwrap();
+        });
+        let results = cash_game.results();
+        assert_eq!(40, results.hands);
+        // Nobody ever starts a hand short.
+        assert!(cash_game.stacks().iter().all(|s| *s >= 200.0));
+        assert_eq!(cash_game.stacks(), &results.money_out[..]);
+
+        let net: f32 = (0..3).map(|idx| results.net(idx)).sum();
+        assert!(net.abs() < 1e-3);
+        let won: f64 = results.results.iter().map(|r| r.total()).sum();
+        assert!(won.abs() < 1e-3);
+        assert!((results.results[0].total() * 2.0 - results.net(0) as f64).abs() < 1e-2);
+    }
+}
@@ -3,12 +3,20 @@ use std::{
     fmt::Debug,
 };
 
-use crate::arena::{HoldemSimulation, errors::HoldemSimulationError, game_state::Round};
+use rand::Rng;
+
+use crate::arena::{
+    HoldemSimulation, errors::HoldemSimulationError, game_state::Round, timing::TimingStats,
+};
+use crate::stats::ResultStats;
 
 /// A  struct to help seeing which agent is likely to do well
 ///
 /// Each competition is a series of `HoldemSimulations`
 /// from the `HoldemSimulationGenerator` passed in.
+///
+/// The stats are kept by agent, following each simulation's `seat_agents`,
+/// so they stay with an agent that moves between seats.
 pub struct HoldemCompetition<T: Iterator<Item = HoldemSimulation>> {
     simulation_iterator: T,
     /// The number of rounds that have been run.
@@ -27,14 +35,83 @@ pub struct HoldemCompetition<T: Iterator<Item = HoldemSimulation>> {
     pub zero_count: Vec<usize>,
     // Count of the round before the simulation stopped
     pub before_count: HashMap<Round, usize>,
+    /// Each agent's results in big blinds, for win rates and downswings.
+    pub results: Vec<ResultStats>,
+    /// The timings of every simulation that recorded them.
+    pub timings: TimingStats,
 
     /// Maximum number of HoldemSimulation's to
     /// keep in a long call to `run`
     max_sim_history: usize,
+    record_timings: bool,
 }
 
 const MAX_PLAYERS: usize = 12;
 
+/// The stats of a `HoldemCompetition` without the simulations, so they can
+/// be sent between threads and merged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompetitionStats {
+    /// The number of rounds that have been run.
+    pub num_rounds: usize,
+
+    /// stack size change normalized in big blinds
+    pub total_change: Vec<f32>,
+    pub max_change: Vec<f32>,
+    pub min_change: Vec<f32>,
+
+    /// How many hands each agent has made some profit
+    pub win_count: Vec<usize>,
+    /// How many hands the agents have lost money
+    pub loss_count: Vec<usize>,
+    // How many times the agent has lost no money
+    pub zero_count: Vec<usize>,
+    // Count of the round before the simulation stopped
+    pub before_count: HashMap<Round, usize>,
+    /// Each agent's results in big blinds, for win rates and downswings.
+    pub results: Vec<ResultStats>,
+    /// The timings of every simulation that recorded them.
+    pub timings: TimingStats,
+}
+
+impl Default for CompetitionStats {
+    fn default() -> Self {
+        CompetitionStats {
+            num_rounds: 0,
+            total_change: vec![0.0; MAX_PLAYERS],
+            max_change: vec![0.0; MAX_PLAYERS],
+            min_change: vec![0.0; MAX_PLAYERS],
+            win_count: vec![0; MAX_PLAYERS],
+            loss_count: vec![0; MAX_PLAYERS],
+            zero_count: vec![0; MAX_PLAYERS],
+            before_count: HashMap::new(),
+            results: vec![ResultStats::default(); MAX_PLAYERS],
+            timings: TimingStats::default(),
+        }
+    }
+}
+
+impl CompetitionStats {
+    /// Add the stats of another competition with the same agents in the
+    /// same seats.
+    pub fn merge(&mut self, other: &CompetitionStats) {
+        self.num_rounds += other.num_rounds;
+        for idx in 0..MAX_PLAYERS {
+            self.total_change[idx] += other.total_change[idx];
+            self.max_change[idx] = self.max_change[idx].max(other.max_change[idx]);
+            self.min_change[idx] = self.min_change[idx].min(other.min_change[idx]);
+            self.win_count[idx] += other.win_count[idx];
+            self.loss_count[idx] += other.loss_count[idx];
+            self.zero_count[idx] += other.zero_count[idx];
+            self.results[idx].merge(&other.results[idx]);
+        }
+        self.timings.merge(&other.timings);
+        for (round, count) in &other.before_count {
+            *self.before_count.entry(*round).or_default() += count;
+        }
+    }
+}
+
 impl<T: Iterator<Item = HoldemSimulation>> HoldemCompetition<T> {
     /// Creates a new HoldemHandCompetition instance with the provided
     /// HoldemSimulation.
@@ -55,21 +132,42 @@ impl<T: Iterator<Item = HoldemSimulation>> HoldemCompetition<T> {
             zero_count: vec![0; MAX_PLAYERS],
             // Round before stopping
             before_count: HashMap::new(),
+            results: vec![ResultStats::default(); MAX_PLAYERS],
+            timings: TimingStats::default(),
+            record_timings: false,
         }
     }
 
+    /// Time every simulation run from now on, whether or not it was built
+    /// to. The timings are gathered in `timings`.
+    pub fn record_timings(&mut self, record_timings: bool) {
+        self.record_timings = record_timings;
+    }
+
     pub fn run(
         &mut self,
         num_rounds: usize,
+    ) -> Result<Vec<HoldemSimulation>, HoldemSimulationError> {
+        self.run_with_rng(num_rounds, &mut crate::core::rng())
+    }
+
+    /// Like `run`, but the cards are dealt using `rng`. A seeded rng makes
+    /// the deals reproducible.
+    pub fn run_with_rng<R: Rng>(
+        &mut self,
+        num_rounds: usize,
+        rng: &mut R,
     ) -> Result<Vec<HoldemSimulation>, HoldemSimulationError> {
         let mut sims = VecDeque::with_capacity(self.max_sim_history);
-        let mut rand = rand::rng();
 
         for _round in 0..num_rounds {
             // Createa a new holdem simulation
             let mut running_sim = self.simulation_iterator.next().unwrap();
+            if self.record_timings {
+                running_sim.timings.get_or_insert_with(TimingStats::default);
+            }
             // Run the sim
-            running_sim.run(&mut rand);
+            running_sim.run(rng);
             // Update the stack change stats
             self.update_metrics(&running_sim);
             // Update the counter
@@ -86,6 +184,22 @@ impl<T: Iterator<Item = HoldemSimulation>> HoldemCompetition<T> {
         Ok(sims.into_iter().collect())
     }
 
+    /// A copy of the stats gathered so far.
+    pub fn stats(&self) -> CompetitionStats {
+        CompetitionStats {
+            num_rounds: self.num_rounds,
+            total_change: self.total_change.clone(),
+            max_change: self.max_change.clone(),
+            min_change: self.min_change.clone(),
+            win_count: self.win_count.clone(),
+            loss_count: self.loss_count.clone(),
+            zero_count: self.zero_count.clone(),
+            before_count: self.before_count.clone(),
+            results: self.results.clone(),
+            timings: self.timings.clone(),
+        }
+    }
+
     fn update_metrics(&mut self, running_sim: &HoldemSimulation) {
         // Calculates the change in each player's winnings for the round,
         // normalized by the big blind amount.
@@ -96,10 +210,10 @@ impl<T: Iterator<Item = HoldemSimulation>> HoldemCompetition<T> {
             .starting_stacks
             .iter()
             .zip(running_sim.game_state.stacks.iter())
-            .enumerate()
-            .map(|(idx, (starting, ending))| {
+            .zip(running_sim.seat_agents.iter())
+            .map(|((starting, ending), agent)| {
                 (
-                    idx,
+                    *agent,
                     (*ending - *starting) / running_sim.game_state.big_blind,
                 )
             });
@@ -111,6 +225,7 @@ impl<T: Iterator<Item = HoldemSimulation>> HoldemCompetition<T> {
             self.min_change[idx] = self.min_change[idx].min(norm_change);
             // What's the most we win
             self.max_change[idx] = self.max_change[idx].max(norm_change);
+            self.results[idx].push(norm_change as f64);
 
             // Count how many times the agent wins or loses
             if norm_change > 0.0 {
@@ -127,6 +242,10 @@ impl<T: Iterator<Item = HoldemSimulation>> HoldemCompetition<T> {
             .entry(running_sim.game_state.round_before)
             .or_default();
         *count += 1;
+
+        if let Some(timings) = &running_sim.timings {
+            self.timings.merge(timings);
+        }
     }
 }
 
@@ -141,6 +260,8 @@ impl<T: Iterator<Item = HoldemSimulation>> Debug for HoldemCompetition<T> {
             .field("zero_count", &self.zero_count)
             .field("loss_count", &self.loss_count)
             .field("round_before", &self.before_count)
+            .field("results", &self.results)
+            .field("timings", &self.timings)
             .finish()
     }
 }
@@ -149,8 +270,10 @@ impl<T: Iterator<Item = HoldemSimulation>> Debug for HoldemCompetition<T> {
 mod tests {
     use crate::arena::{
         AgentGenerator, CloneGameStateGenerator, GameState,
-        agent::{CallingAgentGenerator, RandomAgentGenerator},
-        competition::StandardSimulationIterator,
+        agent::{
+            AllInAgentGenerator, CallingAgentGenerator, FoldingAgentGenerator, RandomAgentGenerator,
+        },
+        competition::{Seating, StandardSimulationIterator},
     };
 
     use super::*;
@@ -173,4 +296,58 @@ mod tests {
 
         let _first_results = competition.run(100).unwrap();
     }
+
+    #[test]
+    fn test_stats_follow_agents_between_seats() {
+        // A folder on the button never loses anything, but moved around the
+        // table it pays the big blind and then the small blind.
+        let run = |seating| {
+            let agent_gens: Vec<Box<dyn AgentGenerator>> = vec![
+                Box::<FoldingAgentGenerator>::default(),
+                Box::<AllInAgentGenerator>::default(),
+                Box::<AllInAgentGenerator>::default(),
+            ];
+            let game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0);
+            let sim_gen = StandardSimulationIterator::new(
+                agent_gens,
+                vec![],
+                CloneGameStateGenerator::new(game_state),
+            )
+            .seating(seating);
+            let mut competition = HoldemCompetition::new(sim_gen);
+            competition.run(3).unwrap();
+            competition.total_change[0]
+        };
+        assert_eq!(0.0, run(Seating::Fixed));
+        assert_eq!(-1.5, run(Seating::Rotate));
+    }
+
+    #[test]
+    fn test_merge_stats() {
+        let agent_gens: Vec<Box<dyn AgentGenerator>> = vec![
+            Box::<CallingAgentGenerator>::default(),
+            Box::<CallingAgentGenerator>::default(),
+        ];
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let sim_gen = StandardSimulationIterator::new(
+            agent_gens,
+            vec![],
+            CloneGameStateGenerator::new(game_state),
+        );
+        let mut competition = HoldemCompetition::new(sim_gen);
+        competition.run(10).unwrap();
+
+        let mut merged = CompetitionStats::default();
+        merged.merge(&competition.stats());
+        merged.merge(&competition.stats());
+        assert_eq!(20, merged.num_rounds);
+        assert_eq!(2 * competition.win_count[0], merged.win_count[0]);
+        assert_eq!(competition.max_change, merged.max_change);
+        assert_eq!(20, merged.before_count.values().sum::<usize>());
+        assert_eq!(20, merged.results[0].hands());
+        assert_eq!(
+            2.0 * competition.total_change[0] as f64,
+            merged.results[0].total()
+        );
+    }
 }
@@ -0,0 +1,101 @@
+use crate::arena::betting::BettingStructure;
+use crate::arena::errors::HoldemSimulationError;
+use crate::arena::variant::GameVariant;
+
+/// One game of a rotation and how it's bet. The default is no limit
+/// hold'em.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MixedGame {
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+}
+
+impl MixedGame {
+    pub fn new(variant: GameVariant, betting: BettingStructure) -> Self {
+        Self { variant, betting }
+    }
+
+    /// The HORSE rotation, every game fixed limit with the same stakes.
+    pub fn horse(small_bet: f32) -> Vec<MixedGame> {
+        let betting = BettingStructure::fixed_limit(small_bet);
+        [
+            GameVariant::Holdem,
+            GameVariant::OmahaHiLo,
+            GameVariant::Razz,
+            GameVariant::Stud,
+            GameVariant::StudHiLo,
+        ]
+        .into_iter()
+        .map(|variant| MixedGame::new(variant, betting))
+        .collect()
+    }
+}
+
+/// Which game each hand of a session is, playing `hands_per_game` hands of
+/// each game in turn and starting over after the last.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::betting::BettingStructure;
+/// use rs_poker::arena::competition::{GameVariant, MixedGame, MixedGameSchedule};
+/// use rs_poker::arena::errors::HoldemSimulationError;
+///
+/// let schedule = MixedGameSchedule::new(
+///     vec![
+///         MixedGame::new(GameVariant::Holdem, BettingStructure::fixed_limit(2.0)),
+///         MixedGame::new(GameVariant::Holdem, BettingStructure::NoLimit),
+///     ],
+///     3,
+/// )
+/// .unwrap();
+/// assert_eq!(BettingStructure::NoLimit, schedule.game_for_hand(4).betting);
+/// assert_eq!(
+///     BettingStructure::fixed_limit(2.0),
+///     schedule.game_for_hand(6).betting
+/// );
+///
+/// let horse = MixedGameSchedule::new(MixedGame::horse(2.0), 8).unwrap();
+/// assert_eq!(GameVariant::Razz, horse.game_for_hand(16).variant);
+///
+/// assert_eq!(
+///     Err(HoldemSimulationError::EmptySchedule),
+///     MixedGameSchedule::new(vec![], 8)
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MixedGameSchedule {
+    games: Vec<MixedGame>,
+    hands_per_game: usize,
+}
+
+impl MixedGameSchedule {
+    /// A schedule of `games`, which can't be empty.
+    pub fn new(
+        games: Vec<MixedGame>,
+        hands_per_game: usize,
+    ) -> Result<Self, HoldemSimulationError> {
+        if games.is_empty() || hands_per_game == 0 {
+            return Err(HoldemSimulationError::EmptySchedule);
+        }
+        Ok(Self {
+            games,
+            hands_per_game,
+        })
+    }
+
+    pub fn games(&self) -> &[MixedGame] {
+        &self.games
+    }
+
+    pub fn hands_per_game(&self) -> usize {
+        self.hands_per_game
+    }
+
+    /// The game for hand `hand_num` of the session, counting from zero.
+    pub fn game_for_hand(&self, hand_num: usize) -> &MixedGame {
+        &self.games[(hand_num / self.hands_per_game) % self.games.len()]
+    }
+}
@@ -1,7 +1,16 @@
+mod cash_game;
 mod holdem_competition;
+mod mixed_game;
+#[cfg(feature = "rayon")]
+mod parallel;
 mod sim_iterator;
 mod tournament;
 
-pub use holdem_competition::HoldemCompetition;
-pub use sim_iterator::StandardSimulationIterator;
-pub use tournament::{SingleTableTournament, SingleTableTournamentBuilder};
+pub use crate::arena::variant::GameVariant;
+pub use cash_game::{CashGame, CashGameBuilder, CashGameResults};
+pub use holdem_competition::{CompetitionStats, HoldemCompetition};
+pub use mixed_game::{MixedGame, MixedGameSchedule};
+#[cfg(feature = "rayon")]
+pub use parallel::ParallelRunner;
+pub use sim_iterator::{Seating, StandardSimulationIterator};
+pub use tournament::{SingleTableTournament, SingleTableTournamentBuilder, TournamentResults};
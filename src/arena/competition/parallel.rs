@@ -0,0 +1,206 @@
+use rayon::prelude::*;
+
+use crate::arena::{HoldemSimulation, errors::HoldemSimulationError};
+use crate::core::{rng, with_seed};
+
+use super::{CompetitionStats, HoldemCompetition, SingleTableTournament, TournamentResults};
+
+/// Runs many independent tables at once on the rayon thread pool.
+///
+/// Every table is built and run with the crate rng seeded from the runner's
+/// seed and the table index, so the deal and anything agents draw from
+/// `rs_poker::core::rng` are covered. The results are the same however the
+/// tables end up scheduled, and any single table can be replayed on its
+/// own by running it under `with_seed(table_seed)`.
+///
+/// Agents, historians and simulations aren't `Send`, so each table is built
+/// by a closure on the thread that runs it. The closure gets the table
+/// index.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::agent::{AllInAgentGenerator, CallingAgentGenerator};
+/// use rs_poker::arena::competition::{ParallelRunner, SingleTableTournamentBuilder};
+///
+/// let runner = ParallelRunner::new(8, 42);
+/// let results = runner
+///     .run_tournaments(|_table_idx| {
+///         SingleTableTournamentBuilder::default()
+///             .agent_generators(vec![
+///                 Box::<AllInAgentGenerator>::default(),
+///                 Box::<CallingAgentGenerator>::default(),
+///             ])
+///             .starting_game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+///             .build()
+///     })
+///     .unwrap();
+/// assert_eq!(8, results.len());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ParallelRunner {
+    num_tables: usize,
+    seed: u64,
+}
+
+impl ParallelRunner {
+    pub fn new(num_tables: usize, seed: u64) -> Self {
+        Self { num_tables, seed }
+    }
+
+    pub fn num_tables(&self) -> usize {
+        self.num_tables
+    }
+
+    /// The seed of the crate rng while the table at `table_idx` runs.
+    pub fn table_seed(&self, table_idx: usize) -> u64 {
+        self.seed.wrapping_add(table_idx as u64)
+    }
+
+    /// Run one tournament per table, built by `build`.
+    ///
+    /// Returns the results of every tournament in table order, or the
+    /// first error.
+    pub fn run_tournaments<F>(
+        &self,
+        build: F,
+    ) -> Result<Vec<TournamentResults>, HoldemSimulationError>
+    where
+        F: Fn(usize) -> Result<SingleTableTournament, HoldemSimulationError> + Sync,
+    {
+        (0..self.num_tables)
+            .into_par_iter()
+            .map(|table_idx| {
+                with_seed(self.table_seed(table_idx), || {
+                    build(table_idx)?.run_with_rng(&mut rng())
+                })
+            })
+            .collect()
+    }
+
+    /// Play `num_rounds` hands at every table, using the competitions
+    /// built by `build`, and merge their stats.
+    ///
+    /// Every table should seat the same agents in the same order, otherwise
+    /// the merged stats don't mean much.
+    pub fn run_sessions<F, T>(
+        &self,
+        num_rounds: usize,
+        build: F,
+    ) -> Result<CompetitionStats, HoldemSimulationError>
+    where
+        F: Fn(usize) -> HoldemCompetition<T> + Sync,
+        T: Iterator<Item = HoldemSimulation>,
+    {
+        let tables = (0..self.num_tables)
+            .into_par_iter()
+            .map(|table_idx| {
+                with_seed(self.table_seed(table_idx), || {
+                    let mut competition = build(table_idx);
+                    competition.run_with_rng(num_rounds, &mut rng())?;
+                    Ok(competition.stats())
+                })
+            })
+            .collect::<Result<Vec<_>, HoldemSimulationError>>()?;
+
+        // Merge in table order so that float sums don't depend on
+        // scheduling.
+        let mut stats = CompetitionStats::default();
+        for table in &tables {
+            stats.merge(table);
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::{
+        AgentGenerator, CloneGameStateGenerator, GameState,
+        agent::{
+            AllInAgentGenerator, CallingAgentGenerator, FoldingAgentGenerator, RandomAgentGenerator,
+        },
+        competition::{SingleTableTournamentBuilder, StandardSimulationIterator},
+    };
+
+    use super::*;
+
+    fn tournament(_table_idx: usize) -> Result<SingleTableTournament, HoldemSimulationError> {
+        let gens: Vec<Box<dyn AgentGenerator>> = vec![
+            Box::<AllInAgentGenerator>::default(),
+            Box::<AllInAgentGenerator>::default(),
+            Box::<CallingAgentGenerator>::default(),
+        ];
+        SingleTableTournamentBuilder::default()
+            .agent_generators(gens)
+            .starting_game_state(GameState::new_starting(vec![50.0; 3], 10.0, 5.0, 0.0, 0))
+            .build()
+    }
+
+    #[test]
+    fn test_tournaments_are_deterministic() {
+        let runner = ParallelRunner::new(16, 7);
+        let first = runner.run_tournaments(tournament).unwrap();
+        let second = runner.run_tournaments(tournament).unwrap();
+        assert_eq!(16, first.len());
+
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!(a.places(), b.places());
+            assert_eq!(a.rounds(), b.rounds());
+        }
+
+        // Any table can be replayed on its own.
+        let replay = with_seed(runner.table_seed(5), || {
+            tournament(5).unwrap().run_with_rng(&mut rng()).unwrap()
+        });
+        assert_eq!(first[5].places(), replay.places());
+    }
+
+    #[test]
+    fn test_sessions_merge() {
+        let build = |_table_idx| {
+            let gens: Vec<Box<dyn AgentGenerator>> = vec![
+                Box::<CallingAgentGenerator>::default(),
+                Box::<FoldingAgentGenerator>::default(),
+            ];
+            let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+            HoldemCompetition::new(StandardSimulationIterator::new(
+                gens,
+                vec![],
+                CloneGameStateGenerator::new(game_state),
+            ))
+        };
+        let runner = ParallelRunner::new(4, 3);
+        let stats = runner.run_sessions(25, build).unwrap();
+        assert_eq!(100, stats.num_rounds);
+        assert_eq!(stats, runner.run_sessions(25, build).unwrap());
+
+        for idx in 0..2 {
+            let hands = stats.win_count[idx] + stats.loss_count[idx] + stats.zero_count[idx];
+            assert_eq!(100, hands);
+        }
+    }
+
+    #[test]
+    fn test_seed_covers_random_agents() {
+        let build = |_table_idx| {
+            let gens: Vec<Box<dyn AgentGenerator>> = vec![
+                Box::<RandomAgentGenerator>::default(),
+                Box::<RandomAgentGenerator>::default(),
+                Box::<RandomAgentGenerator>::default(),
+            ];
+            let game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0);
+            HoldemCompetition::new(StandardSimulationIterator::new(
+                gens,
+                vec![],
+                CloneGameStateGenerator::new(game_state),
+            ))
+        };
+        let runner = ParallelRunner::new(4, 11);
+        assert_eq!(
+            runner.run_sessions(20, build).unwrap(),
+            runner.run_sessions(20, build).unwrap()
+        );
+    }
+}
@@ -1,8 +1,26 @@
+use rand::seq::SliceRandom;
+
 use crate::arena::{
     AgentGenerator, GameState, HoldemSimulation, HoldemSimulationBuilder,
     historian::HistorianGenerator,
 };
 
+use super::MixedGameSchedule;
+
+/// Where the agents sit from one hand to the next. Moving them around
+/// keeps a strong seat from flattering whoever always sits in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Seating {
+    /// Agent `i` always sits in seat `i`.
+    #[default]
+    Fixed,
+    /// Every agent moves one seat to the right each hand, so over as many
+    /// hands as there are seats each agent sits in each seat once.
+    Rotate,
+    /// A random seat for each agent each hand.
+    Shuffle,
+}
+
 pub struct StandardSimulationIterator<G>
 where
     G: Iterator<Item = GameState>,
@@ -10,6 +28,9 @@ where
     agent_generators: Vec<Box<dyn AgentGenerator>>,
     historian_generators: Vec<Box<dyn HistorianGenerator>>,
     game_state_iterator: G,
+    seating: Seating,
+    schedule: Option<MixedGameSchedule>,
+    num_hands: usize,
 }
 
 impl<G> StandardSimulationIterator<G>
@@ -25,19 +46,54 @@ where
             agent_generators,
             historian_generators,
             game_state_iterator,
+            seating: Seating::default(),
+            schedule: None,
+            num_hands: 0,
         }
     }
+
+    /// Move the agents between seats from hand to hand. Each simulation's
+    /// `seat_agents` says which agent sat where.
+    pub fn seating(mut self, seating: Seating) -> Self {
+        self.seating = seating;
+        self
+    }
+
+    /// Rotate through the games of a mixed game, each simulation dealing
+    /// and bet the way the schedule says for its hand.
+    pub fn schedule(mut self, schedule: MixedGameSchedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
 }
 
 impl<G> StandardSimulationIterator<G>
 where
     G: Iterator<Item = GameState>,
 {
+    /// The agent in each seat for the next hand.
+    fn seat_agents(&mut self) -> Vec<usize> {
+        let num_agents = self.agent_generators.len();
+        let mut seat_agents: Vec<usize> = (0..num_agents).collect();
+        match self.seating {
+            Seating::Fixed => {}
+            Seating::Rotate => seat_agents.rotate_left(self.num_hands % num_agents.max(1)),
+            Seating::Shuffle => seat_agents.shuffle(&mut crate::core::rng()),
+        }
+        self.num_hands += 1;
+        seat_agents
+    }
+
     fn generate(&mut self, game_state: GameState) -> Option<HoldemSimulation> {
-        let agents = self
-            .agent_generators
+        let game = self
+            .schedule
+            .as_ref()
+            .map(|schedule| *schedule.game_for_hand(self.num_hands))
+            .unwrap_or_default();
+        let seat_agents = self.seat_agents();
+        let agents = seat_agents
             .iter()
-            .map(|g| g.generate(&game_state))
+            .map(|agent| self.agent_generators[*agent].generate(&game_state))
             .collect();
         let historians = self
             .historian_generators
@@ -49,6 +105,9 @@ where
             .agents(agents)
             .historians(historians)
             .game_state(game_state)
+            .seat_agents(seat_agents)
+            .betting(game.betting)
+            .variant(game.variant)
             .build()
             .ok()
     }
@@ -72,7 +131,11 @@ where
 #[cfg(test)]
 mod tests {
     use crate::arena::{
-        GameState, agent::FoldingAgentGenerator, game_state::CloneGameStateGenerator,
+        GameState,
+        agent::{AllInAgentGenerator, CallingAgentGenerator, FoldingAgentGenerator},
+        betting::BettingStructure,
+        competition::{GameVariant, MixedGame},
+        game_state::{CloneGameStateGenerator, Round},
     };
 
     use super::*;
@@ -96,4 +159,102 @@ mod tests {
             .next()
             .expect("There should always be a first simulation");
     }
+
+    #[test]
+    fn test_seating() {
+        let sim_gen = |seating| {
+            let generators: Vec<Box<dyn AgentGenerator>> = (0..3)
+                .map(|_| Box::<FoldingAgentGenerator>::default() as Box<dyn AgentGenerator>)
+                .collect();
+            let game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0);
+            StandardSimulationIterator::new(
+                generators,
+                vec![],
+                CloneGameStateGenerator::new(game_state),
+            )
+            .seating(seating)
+        };
+
+        let rotated: Vec<Vec<usize>> = sim_gen(Seating::Rotate)
+            .take(4)
+            .map(|sim| sim.seat_agents)
+            .collect();
+        assert_eq!(
+            vec![vec![0, 1, 2], vec![1, 2, 0], vec![2, 0, 1], vec![0, 1, 2]],
+            rotated
+        );
+
+        for sim in sim_gen(Seating::Shuffle).take(10) {
+            let mut seat_agents = sim.seat_agents;
+            seat_agents.sort_unstable();
+            assert_eq!(vec![0, 1, 2], seat_agents);
+        }
+    }
+
+    #[test]
+    fn test_schedule_sets_betting() {
+        let generators: Vec<Box<dyn AgentGenerator>> = (0..2)
+            .map(|_| Box::<AllInAgentGenerator>::default() as Box<dyn AgentGenerator>)
+            .collect();
+        let game_state = GameState::new_starting(vec![1000.0; 2], 10.0, 5.0, 0.0, 0);
+        let schedule = MixedGameSchedule::new(
+            vec![
+                MixedGame::new(GameVariant::Holdem, BettingStructure::fixed_limit(10.0)),
+                MixedGame::new(GameVariant::Holdem, BettingStructure::NoLimit),
+            ],
+            1,
+        )
+        .unwrap();
+        let mut sim_gen = StandardSimulationIterator::new(
+            generators,
+            vec![],
+            CloneGameStateGenerator::new(game_state),
+        )
+        .schedule(schedule);
+
+        // Capped at four bets a street: 40 before the turn, 80 from it.
+        let mut limit = sim_gen.next().unwrap();
+        limit.run(&mut rand::rng());
+        assert_eq!(vec![240.0, 240.0], limit.game_state.player_bet);
+
+        let mut no_limit = sim_gen.next().unwrap();
+        no_limit.run(&mut rand::rng());
+        assert_eq!(vec![1000.0, 1000.0], no_limit.game_state.player_bet);
+    }
+
+    #[test]
+    fn test_horse_runs_every_game() {
+        let generators: Vec<Box<dyn AgentGenerator>> = (0..3)
+            .map(|_| Box::<CallingAgentGenerator>::default() as Box<dyn AgentGenerator>)
+            .collect();
+        let game_state = GameState::new_starting(vec![1000.0; 3], 10.0, 5.0, 1.0, 0);
+        let schedule = MixedGameSchedule::new(MixedGame::horse(10.0), 1).unwrap();
+        let sim_gen = StandardSimulationIterator::new(
+            generators,
+            vec![],
+            CloneGameStateGenerator::new(game_state),
+        )
+        .schedule(schedule);
+
+        let variants: Vec<GameVariant> = sim_gen
+            .take(5)
+            .map(|mut sim| {
+                sim.run(&mut rand::rng());
+                assert_eq!(Round::Complete, sim.game_state.round);
+                let total: f32 = sim.game_state.stacks.iter().sum();
+                assert!((total - 3000.0).abs() < 1e-3);
+                sim.game_state.variant
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                GameVariant::Holdem,
+                GameVariant::OmahaHiLo,
+                GameVariant::Razz,
+                GameVariant::Stud,
+                GameVariant::StudHiLo,
+            ],
+            variants
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use rand::Rng;
 use tracing::{event, trace_span};
 
 use crate::arena::{
@@ -22,6 +23,7 @@ pub struct SingleTableTournamentBuilder {
 /// The max stack that each agent had at any point in the tournament.
 /// And the number of rounds that the tournament took to complete.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TournamentResults {
     places: Vec<usize>,
     max_stacks: Vec<f32>,
@@ -135,10 +137,18 @@ impl SingleTableTournament {
     /// finished in second place, the second agent won, the third agent got
     /// third and the fourth agent finished in last.
     pub fn run(self) -> Result<TournamentResults, HoldemSimulationError> {
+        self.run_with_rng(&mut crate::core::rng())
+    }
+
+    /// Like `run`, but the cards are dealt using `rng`. A seeded rng makes
+    /// the deals reproducible.
+    pub fn run_with_rng<R: Rng>(
+        self,
+        rand: &mut R,
+    ) -> Result<TournamentResults, HoldemSimulationError> {
         let span = trace_span!("SingleTableTournament::run");
         let _enter = span.enter();
 
-        let mut rand = rand::rng();
         // The place that we are about to assign to the next agent to bust out.
         let mut place = self.agent_generators.len();
         // Holds the results of the tournament.
@@ -165,7 +175,7 @@ impl SingleTableTournament {
                 .build()?;
 
             // Run the simulation
-            sim.run(&mut rand);
+            sim.run(rand);
 
             // Update the results
             results.update_max(&sim.game_state.stacks);
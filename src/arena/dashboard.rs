@@ -0,0 +1,405 @@
+//! A terminal dashboard for watching training and simulations as they run.
+//!
+//! The dashboard reads the same counters and gauges as the `metrics` module:
+//! a `DashboardRecorder` keeps their current values in memory, and a
+//! `Dashboard` samples them on every refresh to draw the tree size, memory,
+//! hands and iterations per second, and how the exploitability estimate has
+//! moved. A leaderboard of seats can be shown next to them by handing
+//! results to `DashboardHandle::set_leaderboard`.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use rs_poker::arena::cfr::{HeadsUpLimitConfig, NoCardAbstraction, StateStore};
+//! use rs_poker::arena::dashboard::{Dashboard, DashboardHandle};
+//!
+//! let handle = DashboardHandle::new("Heads up limit");
+//! metrics::set_global_recorder(handle.recorder()).unwrap();
+//!
+//! let training = std::thread::spawn(|| {
+//!     let mut state_store = StateStore::new();
+//!     for _ in 0..100 {
+//!         HeadsUpLimitConfig::default().train::<NoCardAbstraction, _>(
+//!             &mut state_store,
+//!             100,
+//!             &mut rand::rng(),
+//!         );
+//!     }
+//! });
+//!
+//! // Draws until `q` is pressed or training is done.
+//! Dashboard::new(handle)
+//!     .run(Duration::from_millis(250), || training.is_finished())
+//!     .unwrap();
+//! ```
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::text::Line;
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph, Row, Table};
+
+use super::metrics::{CFR_EXPLOITABILITY, CFR_ITERATIONS, CFR_MEMORY, CFR_NODES, HANDS_SIMULATED};
+use crate::stats::ResultStats;
+
+/// How many samples the rates are averaged over.
+const RATE_WINDOW: usize = 8;
+
+/// One row of the leaderboard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub results: ResultStats,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    title: String,
+    counters: HashMap<Key, Arc<AtomicU64>>,
+    gauges: HashMap<Key, Arc<AtomicU64>>,
+    leaderboard: Vec<LeaderboardEntry>,
+}
+
+/// The values the dashboard shows, shared between the recorder, whatever
+/// is running and the dashboard itself.
+#[derive(Debug, Clone, Default)]
+pub struct DashboardHandle {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl DashboardHandle {
+    pub fn new(title: impl Into<String>) -> Self {
+        let handle = Self::default();
+        handle.shared.lock().unwrap().title = title.into();
+        handle
+    }
+
+    /// A `metrics` recorder that keeps values for this dashboard. Install
+    /// it globally or with `metrics::with_local_recorder`.
+    pub fn recorder(&self) -> DashboardRecorder {
+        DashboardRecorder {
+            handle: self.clone(),
+        }
+    }
+
+    /// Replace the leaderboard. It's shown best win rate first.
+    pub fn set_leaderboard(&self, leaderboard: Vec<LeaderboardEntry>) {
+        self.shared.lock().unwrap().leaderboard = leaderboard;
+    }
+
+    /// The total of a counter across all of its labels.
+    pub fn counter(&self, name: &str) -> u64 {
+        let shared = self.shared.lock().unwrap();
+        shared
+            .counters
+            .iter()
+            .filter(|(key, _)| key.name() == name)
+            .map(|(_, value)| value.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// The sum of a gauge across all of its labels, or `None` if it has
+    /// never been set.
+    pub fn gauge(&self, name: &str) -> Option<f64> {
+        let shared = self.shared.lock().unwrap();
+        shared
+            .gauges
+            .iter()
+            .filter(|(key, _)| key.name() == name)
+            .map(|(_, value)| f64::from_bits(value.load(Ordering::Relaxed)))
+            .reduce(|a, b| a + b)
+    }
+
+    fn register(
+        map: impl FnOnce(&mut Shared) -> &mut HashMap<Key, Arc<AtomicU64>>,
+        shared: &Mutex<Shared>,
+        key: &Key,
+    ) -> Arc<AtomicU64> {
+        let mut shared = shared.lock().unwrap();
+        map(&mut shared).entry(key.clone()).or_default().clone()
+    }
+}
+
+/// Records counters and gauges for a `DashboardHandle`. Histograms aren't
+/// shown, so they're dropped.
+#[derive(Debug, Clone)]
+pub struct DashboardRecorder {
+    handle: DashboardHandle,
+}
+
+impl Recorder for DashboardRecorder {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        Counter::from_arc(DashboardHandle::register(
+            |shared| &mut shared.counters,
+            &self.handle.shared,
+            key,
+        ))
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(DashboardHandle::register(
+            |shared| &mut shared.gauges,
+            &self.handle.shared,
+            key,
+        ))
+    }
+
+    fn register_histogram(&self, _key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        Histogram::noop()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: Instant,
+    hands: u64,
+    iterations: u64,
+}
+
+/// Draws a `DashboardHandle` in the terminal.
+#[derive(Debug)]
+pub struct Dashboard {
+    handle: DashboardHandle,
+    samples: VecDeque<Sample>,
+    /// Every change in the exploitability estimate, by iteration.
+    exploitability: Vec<(f64, f64)>,
+}
+
+impl Dashboard {
+    pub fn new(handle: DashboardHandle) -> Self {
+        Self {
+            handle,
+            samples: VecDeque::with_capacity(RATE_WINDOW),
+            exploitability: Vec::new(),
+        }
+    }
+
+    /// Take a sample of the current values. `run` does this before every
+    /// draw.
+    pub fn sample(&mut self, at: Instant) {
+        let iterations = self.handle.counter(CFR_ITERATIONS);
+        if self.samples.len() == RATE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample {
+            at,
+            hands: self.handle.counter(HANDS_SIMULATED),
+            iterations,
+        });
+        if let Some(exploitability) = self.handle.gauge(CFR_EXPLOITABILITY)
+            && self.exploitability.last().map(|(_, last)| *last) != Some(exploitability)
+        {
+            self.exploitability
+                .push((iterations as f64, exploitability));
+        }
+    }
+
+    fn rate(&self, count: impl Fn(&Sample) -> u64) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(first), Some(last)) if last.at > first.at => {
+                (count(last) - count(first)) as f64 / (last.at - first.at).as_secs_f64()
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Hands simulated per second over the last few samples.
+    pub fn hands_per_second(&self) -> f64 {
+        self.rate(|sample| sample.hands)
+    }
+
+    /// Training iterations per second over the last few samples.
+    pub fn iterations_per_second(&self) -> f64 {
+        self.rate(|sample| sample.iterations)
+    }
+
+    pub fn render(&self, frame: &mut Frame) {
+        let shared = self.handle.shared.lock().unwrap();
+        let title = shared.title.clone();
+        let mut leaderboard = shared.leaderboard.clone();
+        drop(shared);
+
+        let [summary_area, chart_area, leaderboard_area] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Fill(1),
+            Constraint::Length(leaderboard.len() as u16 + 3),
+        ])
+        .areas(frame.area());
+
+        let last = self.samples.back();
+        let summary = vec![
+            Line::from(format!(
+                "hands {:>12}   {:>10.0} hands/s   iterations {:>10}   {:>8.1} iterations/s",
+                last.map_or(0, |sample| sample.hands),
+                self.hands_per_second(),
+                last.map_or(0, |sample| sample.iterations),
+                self.iterations_per_second(),
+            )),
+            Line::from(format!(
+                "nodes {:>12}   memory {:>10}   exploitability {}",
+                self.handle.gauge(CFR_NODES).unwrap_or(0.0),
+                format_bytes(self.handle.gauge(CFR_MEMORY).unwrap_or(0.0)),
+                self.exploitability
+                    .last()
+                    .map_or("-".to_string(), |(_, value)| format!("{value:.4}")),
+            )),
+        ];
+        frame.render_widget(
+            Paragraph::new(summary).block(Block::bordered().title(title.bold())),
+            summary_area,
+        );
+
+        let (max_x, max_y) = self
+            .exploitability
+            .iter()
+            .fold((1.0_f64, 0.0_f64), |(x, y), (ix, iy)| {
+                (x.max(*ix), y.max(*iy))
+            });
+        let dataset = Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .data(&self.exploitability);
+        let chart = Chart::new(vec![dataset])
+            .block(Block::bordered().title("exploitability"))
+            .x_axis(
+                Axis::default()
+                    .bounds([0.0, max_x])
+                    .labels(["0".to_string(), format!("{max_x:.0}")]),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds([0.0, max_y.max(f64::EPSILON)])
+                    .labels(["0".to_string(), format!("{max_y:.3}")]),
+            );
+        frame.render_widget(chart, chart_area);
+
+        leaderboard.sort_by(|a, b| b.results.bb_per_100().total_cmp(&a.results.bb_per_100()));
+        let rows = leaderboard.iter().map(|entry| {
+            Row::new([
+                entry.name.clone(),
+                entry.results.hands().to_string(),
+                format!("{:.2}", entry.results.bb_per_100()),
+                format!("{:.2}", entry.results.std_dev_per_100()),
+                format!("{:.2}", entry.results.max_downswing()),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+            ],
+        )
+        .header(
+            Row::new(["name", "hands", "bb/100", "sd/100", "downswing"]).style(Style::new().bold()),
+        )
+        .block(Block::bordered().title("leaderboard"));
+        frame.render_widget(table, leaderboard_area);
+    }
+
+    /// Take over the terminal and redraw every `refresh` until `q` or
+    /// escape is pressed or `done` returns true.
+    pub fn run(&mut self, refresh: Duration, done: impl Fn() -> bool) -> io::Result<()> {
+        let mut terminal = ratatui::try_init()?;
+        let result = (|| {
+            while !done() {
+                self.sample(Instant::now());
+                terminal.draw(|frame| self.render(frame))?;
+                if event::poll(refresh)?
+                    && let Event::Key(key) = event::read()?
+                    && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                {
+                    break;
+                }
+            }
+            Ok(())
+        })();
+        ratatui::restore();
+        result
+    }
+}
+
+fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics::{counter, gauge};
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let handle = DashboardHandle::new("training");
+        let mut dashboard = Dashboard::new(handle.clone());
+        let start = Instant::now();
+        dashboard.sample(start);
+
+        metrics::with_local_recorder(&handle.recorder(), || {
+            counter!(HANDS_SIMULATED).increment(500);
+            counter!(CFR_ITERATIONS, "trainer" => "test").increment(10);
+            gauge!(CFR_NODES, "player" => "0").set(100.0);
+            gauge!(CFR_NODES, "player" => "1").set(50.0);
+            gauge!(CFR_MEMORY, "player" => "0").set(3.0 * 1024.0 * 1024.0);
+            gauge!(CFR_EXPLOITABILITY).set(0.25);
+        });
+        handle.set_leaderboard(vec![
+            LeaderboardEntry {
+                name: "folding".to_string(),
+                results: [-1.0, -1.0].into_iter().collect(),
+            },
+            LeaderboardEntry {
+                name: "calling".to_string(),
+                results: [1.0, 1.0].into_iter().collect(),
+            },
+        ]);
+        dashboard.sample(start + Duration::from_secs(2));
+
+        assert_eq!(Some(150.0), handle.gauge(CFR_NODES));
+        assert_eq!(250.0, dashboard.hands_per_second());
+        assert_eq!(5.0, dashboard.iterations_per_second());
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| dashboard.render(frame)).unwrap();
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(text.contains("training"));
+        assert!(text.contains("250 hands/s"));
+        assert!(text.contains("3.0 MiB"));
+        assert!(text.contains("0.2500"));
+        // Best win rate first.
+        assert!(text.find("calling").unwrap() < text.find("folding").unwrap());
+    }
+}
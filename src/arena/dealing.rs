@@ -0,0 +1,181 @@
+//! How a simulation takes cards off the deck, and a record of every card it
+//! took.
+//!
+//! Every card a [`HoldemSimulation`](super::HoldemSimulation) deals, burns
+//! included, goes into its [`DeckAudit`] in the order it came off the deck.
+//! The audit can be checked against the finished hand, and handed to
+//! [`HoldemSimulationBuilder::replay_deck`](super::HoldemSimulationBuilder::replay_deck)
+//! to deal the same cards again in the same order.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::agent::CallingAgent;
+//! use rs_poker::arena::dealing::{DealingProcedure, DealtTo};
+//! use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+//!
+//! let game_state = GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.0, 0);
+//! let agents = || -> Vec<Box<dyn Agent>> {
+//!     (0..3)
+//!         .map(|_| Box::<CallingAgent>::default() as Box<dyn Agent>)
+//!         .collect()
+//! };
+//! let mut sim = HoldemSimulationBuilder::default()
+//!     .game_state(game_state.clone())
+//!     .agents(agents())
+//!     .dealing(DealingProcedure::casino())
+//!     .build()
+//!     .unwrap();
+//! sim.run(&mut rand::rng());
+//!
+//! let audit = &sim.deck_audit;
+//! audit.verify(&sim.game_state).unwrap();
+//! // Six hole cards, a burn before each street and five on the board.
+//! assert_eq!(14, audit.cards.len());
+//! assert_eq!(3, audit.burns().count());
+//! assert_eq!(DealtTo::Burn, audit.cards[6].to);
+//!
+//! // Dealing the same cards again gives the same hand.
+//! let mut replay = HoldemSimulationBuilder::default()
+//!     .game_state(game_state)
+//!     .agents(agents())
+//!     .replay_deck(audit)
+//!     .build()
+//!     .unwrap();
+//! replay.run(&mut rand::rng());
+//! assert_eq!(sim.game_state.hands, replay.game_state.hands);
+//! assert_eq!(sim.deck_audit, replay.deck_audit);
+//! ```
+use crate::core::{Card, CardBitSet};
+
+use super::GameState;
+use super::errors::DeckAuditError;
+
+/// The order hole cards are dealt in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HoleCardOrder {
+    /// Each player gets both their cards before the next player gets any.
+    #[default]
+    PlayerAtATime,
+    /// One card to each player around the table, then a second card.
+    RoundRobin,
+}
+
+/// How cards are dealt. The default is what the simulation has always done,
+/// no burns and each player's hole cards together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DealingProcedure {
+    /// Burn a card before the flop, the turn and the river.
+    pub burn_cards: bool,
+    pub hole_cards: HoleCardOrder,
+}
+
+impl DealingProcedure {
+    /// Dealt the way a casino dealer would, round robin with burns.
+    pub fn casino() -> Self {
+        Self {
+            burn_cards: true,
+            hole_cards: HoleCardOrder::RoundRobin,
+        }
+    }
+}
+
+/// Where a dealt card went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DealtTo {
+    /// A hole card for the player in this seat.
+    Player(usize),
+    Board,
+    Burn,
+    /// A board card for a run of the board after the first, when the board
+    /// is run more than once. Runs count from one, the game state's board
+    /// being run zero.
+    RunIt(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DealtCard {
+    pub card: Card,
+    pub to: DealtTo,
+}
+
+/// Every card dealt in a hand, in the order it came off the deck.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeckAudit {
+    /// How the cards were dealt.
+    pub procedure: DealingProcedure,
+    pub cards: Vec<DealtCard>,
+    /// Cards thrown away in a draw game. They can be shuffled back into the
+    /// deck and dealt again.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub discards: CardBitSet,
+}
+
+impl DeckAudit {
+    pub fn new(procedure: DealingProcedure) -> Self {
+        Self {
+            procedure,
+            cards: vec![],
+            discards: CardBitSet::new(),
+        }
+    }
+
+    pub fn record(&mut self, card: Card, to: DealtTo) {
+        self.cards.push(DealtCard { card, to });
+    }
+
+    /// The cards in the order they were dealt, which is the stacked deck
+    /// that deals the hand again.
+    pub fn order(&self) -> Vec<Card> {
+        self.cards.iter().map(|dealt| dealt.card).collect()
+    }
+
+    pub fn burns(&self) -> impl Iterator<Item = Card> + '_ {
+        self.cards
+            .iter()
+            .filter(|dealt| dealt.to == DealtTo::Burn)
+            .map(|dealt| dealt.card)
+    }
+
+    /// Check the audit against the hand it was dealt for: no card was dealt
+    /// twice, every hole card is in its player's hand, and the board is
+    /// exactly the cards dealt to it. A discard may have been dealt twice and
+    /// needn't still be in a hand.
+    pub fn verify(&self, game_state: &GameState) -> Result<(), DeckAuditError> {
+        let mut seen = CardBitSet::new();
+        let mut board = CardBitSet::new();
+        for dealt in &self.cards {
+            let discarded = self.discards.contains(dealt.card);
+            if seen.contains(dealt.card) && !discarded {
+                return Err(DeckAuditError::RepeatedCard(dealt.card));
+            }
+            seen.insert(dealt.card);
+
+            match dealt.to {
+                DealtTo::Player(idx) => {
+                    let in_hand = discarded
+                        || game_state
+                            .hands
+                            .get(idx)
+                            .is_some_and(|hand| hand.contains(&dealt.card));
+                    if !in_hand {
+                        return Err(DeckAuditError::NotInHand(dealt.card, idx));
+                    }
+                }
+                DealtTo::Board => board.insert(dealt.card),
+                DealtTo::Burn | DealtTo::RunIt(_) => {}
+            }
+        }
+
+        let expected: CardBitSet = game_state.board.iter().copied().collect();
+        if board != expected {
+            return Err(DeckAuditError::BoardMismatch);
+        }
+        Ok(())
+    }
+}
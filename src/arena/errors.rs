@@ -24,6 +24,116 @@ pub enum HoldemSimulationError {
 
     #[error("Expected GameState to contain a winner (agent with all the money)")]
     NoWinner,
+
+    #[error("Seat agents must give each agent one seat")]
+    InvalidSeatAgents,
+
+    #[error("Stacked card {0} isn't in the deck or is stacked twice")]
+    StackedCardNotInDeck(crate::core::Card),
+
+    #[error("A mixed game schedule needs at least one game and one hand of each")]
+    EmptySchedule,
+
+    #[error("The game can be dealt to at most {0} players")]
+    TooManyPlayers(usize),
+
+    #[error("A draw game has one to three draws, not {0}")]
+    InvalidDraws(usize),
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SpotError {
+    #[error("Nobody is left to act in the game state")]
+    NoDecision,
+    #[error("Expected a range or None for each of the {0} players")]
+    WrongNumberOfRanges(usize),
+    #[error("Player {0}'s range has no hands left once dealt cards are removed")]
+    EmptyRange(usize),
+    #[error("Couldn't deal hands from the ranges without two players sharing a card")]
+    NoDeal,
+    #[error("Player {0}'s tree has no decision of the player to act to start the subgame from")]
+    NoSubtree(usize),
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum QueryError {
+    #[error("Nobody is left to act in the game state")]
+    NoDecision,
+    #[error("Expected two hole cards, not {0}")]
+    WrongHoleCards(usize),
+    #[error("The game state isn't from the game the tree was trained on")]
+    DifferentGame,
+    #[error("The tree never reached the spot")]
+    NotInTree,
+    #[error("The decision at the spot was never trained")]
+    Untrained,
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum RegretMatcherError {
+    #[error(
+        "The strategy can't be sampled from, it needs at least one action and a positive weight"
+    )]
+    InvalidWeights,
+    #[error("The saved strategy, strategy sums and rewards are different lengths")]
+    MismatchedLengths,
+    #[error("Can't combine a matcher for {expected} actions with one for {found}")]
+    DifferentNumberOfActions { expected: usize, found: usize },
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum MergeError {
+    #[error("The trees start from different game states")]
+    DifferentGameStates,
+    #[error(
+        "Node {0} of the tree being merged in is a different kind of node, or for a different player"
+    )]
+    DifferentNodes(usize),
+    #[error("Unable to merge the regrets at node {0}: {1}")]
+    RegretMatcher(usize, #[source] RegretMatcherError),
+    #[error("There's no tree for player {0} to merge into")]
+    MissingPlayer(usize),
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum LbrError {
+    #[error("Local best response needs two players, not {0}")]
+    NotHeadsUp(usize),
+    #[error("The game state has no hand left to play")]
+    Complete,
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ExploitabilityError {
+    #[error("A best response needs two players, not {0}")]
+    NotHeadsUp(usize),
+    #[error("The game state has no hand left to play")]
+    Complete,
+    #[error("Player {0} hasn't been dealt a hand, try a local best response instead")]
+    HandNotDealt(usize),
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone, Hash)]
+pub enum StrategyComparisonError {
+    #[error(
+        "Player {player_idx} has {first} actions at {path:?} in the first strategy and {second} in the second"
+    )]
+    DifferentActions {
+        player_idx: usize,
+        path: Vec<usize>,
+        first: usize,
+        second: usize,
+    },
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum DeckAuditError {
+    #[error("{0} was dealt more than once")]
+    RepeatedCard(crate::core::Card),
+    #[error("{0} was dealt to seat {1} but isn't in their hand")]
+    NotInHand(crate::core::Card, usize),
+    #[error("The board doesn't match the cards dealt to it")]
+    BoardMismatch,
 }
 
 #[derive(Error, Debug)]
@@ -36,4 +146,195 @@ pub enum ExportError {
 
     #[error("Failed to run dot")]
     FailedToRunDot(std::process::ExitStatus),
+
+    #[error("Error writing JSON")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum HandHistoryError {
+    #[error("Unable to parse hand history line {0}: {1}")]
+    Parse(usize, String),
+
+    #[error("Unknown player {0}")]
+    UnknownPlayer(String),
+
+    #[error("Hand history is missing {0}")]
+    MissingField(&'static str),
+
+    #[error("Unsupported hand history feature: {0}")]
+    Unsupported(String),
+
+    #[error("Unable to parse card")]
+    InvalidCard(#[from] crate::core::RSPokerError),
+
+    #[error("Expected player {expected} to act but found player {found}")]
+    OutOfTurn { expected: usize, found: usize },
+
+    #[error("No player is left to act in {0} but more actions were recorded")]
+    NoActionExpected(super::game_state::Round),
+
+    #[error("Betting in {0} ended before every player acted")]
+    IncompleteRound(super::game_state::Round),
+
+    #[error("Player {0} can't check facing a bet")]
+    IllegalCheck(usize),
+
+    #[error("Illegal action for player {0}")]
+    IllegalAction(usize, #[source] GameStateError),
+
+    #[cfg(feature = "serde")]
+    #[error("Unable to read or write JSON hand history")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum HandLogError {
+    #[error("Error reading hand log caused by IO error")]
+    Io(#[from] std::io::Error),
+
+    #[error("Not a hand log file")]
+    BadMagic,
+
+    #[error("Unsupported hand log version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Corrupt hand log: {0}")]
+    Corrupt(&'static str),
+}
+
+#[cfg(feature = "flatbuffers")]
+#[derive(Error, Debug)]
+pub enum FlatHandLogError {
+    #[error("Not a FlatBuffers hand log file")]
+    BadMagic,
+
+    #[error("Unsupported FlatBuffers hand log version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Corrupt FlatBuffers hand log: {0}")]
+    Corrupt(&'static str),
+
+    #[error("No hand at index {0}")]
+    OutOfBounds(usize),
+
+    #[error("Invalid hand record: {0}")]
+    Invalid(#[from] flatbuffers::InvalidFlatbuffer),
+}
+
+#[cfg(feature = "mmap")]
+#[derive(Error, Debug)]
+pub enum MmapNodeStoreError {
+    #[error("Error reading node store file caused by IO error")]
+    Io(#[from] std::io::Error),
+
+    #[error("Not a node store file")]
+    BadMagic,
+
+    #[error("Unsupported node store version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Node store records are {found} bytes where {expected} were expected")]
+    RecordSize { expected: usize, found: usize },
+
+    #[error("Node store file is too short for its {0} nodes")]
+    Truncated(usize),
+
+    #[error("Node {idx} has unknown kind {kind}")]
+    BadKind { idx: usize, kind: u8 },
+
+    #[error("A regret matcher with {0} actions doesn't fit in a record")]
+    TooManyActions(usize),
+
+    #[error("A node with {0} children doesn't fit in a record")]
+    TooManyChildren(usize),
+
+    #[error("Invalid regret matcher: {0}")]
+    RegretMatcher(#[from] RegretMatcherError),
+}
+
+#[derive(Error, Debug)]
+pub enum VersionedFileError {
+    #[error("Error reading versioned file caused by IO error")]
+    Io(#[from] std::io::Error),
+
+    #[error("Unable to parse versioned file: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Unable to decode binary file: {0}")]
+    Binary(#[from] bincode::Error),
+
+    #[error("File is compressed, which needs the compression feature")]
+    Compressed,
+
+    #[error("Expected a {expected} file but found a {found} file")]
+    WrongKind {
+        expected: &'static str,
+        found: String,
+    },
+
+    #[error("File version {found} is newer than the supported version {supported}")]
+    TooNew { found: u32, supported: u32 },
+
+    #[error("Unable to migrate from version {from}: {reason}")]
+    Migration { from: u32, reason: String },
+
+    #[error("Invalid {kind}: {reason}")]
+    Invalid { kind: &'static str, reason: String },
+}
+
+#[derive(Error, Debug)]
+pub enum CheckpointError {
+    #[error("Unable to save or load checkpoint: {0}")]
+    Versioned(#[from] VersionedFileError),
+
+    #[error("Checkpoint was trained with {field} {found} but {expected} was given")]
+    Mismatch {
+        field: &'static str,
+        expected: String,
+        found: String,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Nothing is stored under {0}")]
+    NotFound(String),
+
+    #[error("Invalid storage key {0}")]
+    InvalidKey(String),
+
+    #[error("Storage IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Unable to encode or decode stored data: {0}")]
+    Versioned(#[from] VersionedFileError),
+
+    #[cfg(feature = "redis")]
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+
+    #[cfg(feature = "s3")]
+    #[error("S3 error: {0}")]
+    S3(#[from] s3::error::S3Error),
+
+    #[cfg(feature = "s3")]
+    #[error("S3 request for {key} failed with status {status}")]
+    S3Status { key: String, status: u16 },
+}
+
+#[derive(Error, Debug)]
+pub enum ModelError {
+    #[error("Model expects {expected} features but was given {found}")]
+    WrongFeatureCount { expected: usize, found: usize },
+
+    #[error("Model has {found} action outputs where {expected} were expected")]
+    WrongActionCount { expected: usize, found: usize },
+
+    #[error("Model output {0} is missing or malformed")]
+    BadOutput(String),
+
+    #[cfg(feature = "onnx")]
+    #[error("ONNX runtime error: {0}")]
+    Onnx(#[from] ort::Error),
 }
@@ -0,0 +1,323 @@
+//! Turn game states into fixed length vectors of numbers for training
+//! models outside the crate.
+//!
+//! A `FeatureEncoder` encodes a hold'em `GameState` from one player's point
+//! of view, holding whichever hole cards it's given. Every vector it makes
+//! has the same length and layout, which `names` spells out, so the output
+//! of many simulations can go straight into a table or a tensor. The
+//! layout only changes with `FeatureEncoder::SCHEMA_VERSION`.
+//!
+//! In order, the features are:
+//!
+//! - The cards. With `CardEncoding::OneHot` that's 52 for the hole cards and 52
+//!   for the board, 1.0 for each card, indexed by `u8::from(card)`. With
+//!   `CardEncoding::Canonical` it's 2 for the hole cards, lower first, and 5
+//!   for the board in the order it was dealt, each the card's branch under
+//!   `SuitIsomorphism` or -1.0 for a card not dealt yet. Hands that only differ
+//!   by suits get the same features that way.
+//! - 4 for the round, preflop, flop, turn or river.
+//! - The pot, the amount to call and the player's stack in big blinds, the pot
+//!   odds and the stack to pot ratio.
+//! - The fraction of the players still in the hand, the player's seat after the
+//!   dealer divided by the number of seats, and the number of seats.
+//! - For each of the four streets, what each seat put in, in big blinds,
+//!   starting from the player and going round the table for `max_players`
+//!   seats, then the number of bets and whether the player made the last one.
+//!
+//! `FeatureEncoder` is also a `Featurizer`, encoding for the player to act,
+//! so models trained on its output can be used by `ModelAgent`.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::agent::CallingAgent;
+//! use rs_poker::arena::features::FeatureEncoder;
+//! use rs_poker::arena::historian::VecHistorian;
+//! use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+//! use rs_poker::core::CardSet;
+//!
+//! let historian = VecHistorian::default();
+//! let records = historian.get_storage();
+//! let agents: Vec<Box<dyn Agent>> = vec![
+//!     Box::<CallingAgent>::default(),
+//!     Box::<CallingAgent>::default(),
+//! ];
+//! let mut sim = HoldemSimulationBuilder::default()
+//!     .game_state(GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0))
+//!     .agents(agents)
+//!     .historians(vec![Box::new(historian)])
+//!     .build()
+//!     .unwrap();
+//! sim.run(&mut rand::rng());
+//!
+//! // A row for the player at every decision, before they acted.
+//! let encoder = FeatureEncoder::default();
+//! let rows: Vec<Vec<f32>> = records
+//!     .borrow()
+//!     .iter()
+//!     .filter_map(|record| record.before_game_state.as_ref())
+//!     .map(|game_state| {
+//!         let idx = game_state.to_act_idx();
+//!         let hole = CardSet::from(game_state.hands[idx]) - game_state.board_set();
+//!         encoder.encode(game_state, idx, hole)
+//!     })
+//!     .collect();
+//! assert!(!rows.is_empty());
+//! assert!(rows.iter().all(|row| row.len() == encoder.num_features()));
+//! assert_eq!(encoder.num_features(), encoder.names().len());
+//! ```
+use crate::arena::GameState;
+use crate::arena::cfr::{CardAbstraction, SuitIsomorphism};
+use crate::arena::game_state::Round;
+use crate::arena::model::Featurizer;
+use crate::core::{Card, CardSet};
+
+/// The streets with a slot in the features.
+const STREETS: [(Round, &str); 4] = [
+    (Round::Preflop, "preflop"),
+    (Round::Flop, "flop"),
+    (Round::Turn, "turn"),
+    (Round::River, "river"),
+];
+
+/// How many scalar features come after the rounds.
+const NUM_SCALARS: usize = 8;
+
+/// How cards are turned into features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CardEncoding {
+    /// A feature for every card, set when it's held or on the board.
+    #[default]
+    OneHot,
+    /// A feature for each card slot, holding the card's branch under
+    /// `SuitIsomorphism`.
+    Canonical,
+}
+
+/// Encodes game states as fixed length vectors. See the module docs.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FeatureEncoder {
+    pub cards: CardEncoding,
+    /// How many seats the action history has room for. Seats past this are
+    /// left out, and tables with fewer are padded with zeros.
+    pub max_players: usize,
+}
+
+impl Default for FeatureEncoder {
+    /// One hot cards and room for a full ring of nine.
+    fn default() -> Self {
+        Self {
+            cards: CardEncoding::OneHot,
+            max_players: 9,
+        }
+    }
+}
+
+impl FeatureEncoder {
+    /// Bumped whenever the layout of the features changes.
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    pub fn new(cards: CardEncoding, max_players: usize) -> Self {
+        Self { cards, max_players }
+    }
+
+    fn num_card_features(&self) -> usize {
+        match self.cards {
+            CardEncoding::OneHot => 104,
+            CardEncoding::Canonical => 7,
+        }
+    }
+
+    pub fn num_features(&self) -> usize {
+        self.num_card_features() + STREETS.len() * (self.max_players + 3) + NUM_SCALARS
+    }
+
+    /// The name of every feature, in order.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = Vec::with_capacity(self.num_features());
+        match self.cards {
+            CardEncoding::OneHot => {
+                for prefix in ["hole", "board"] {
+                    names.extend((0..52).map(|idx| format!("{prefix}_{}", Card::from(idx))));
+                }
+            }
+            CardEncoding::Canonical => {
+                names.extend((0..2).map(|slot| format!("hole_{slot}")));
+                names.extend((0..5).map(|slot| format!("board_{slot}")));
+            }
+        }
+        names.extend(STREETS.iter().map(|(_, street)| format!("round_{street}")));
+        names.extend(
+            [
+                "pot_bb",
+                "to_call_bb",
+                "stack_bb",
+                "pot_odds",
+                "stack_to_pot",
+                "active_players",
+                "position",
+                "num_players",
+            ]
+            .map(String::from),
+        );
+        for (_, street) in STREETS {
+            names.extend((0..self.max_players).map(|seat| format!("{street}_bet_seat_{seat}")));
+            names.push(format!("{street}_num_bets"));
+            names.push(format!("{street}_player_aggressor"));
+        }
+        names
+    }
+
+    /// Encode `game_state` for the player in seat `idx` holding `hole`.
+    pub fn encode(&self, game_state: &GameState, idx: usize, hole: CardSet) -> Vec<f32> {
+        let mut out = Vec::with_capacity(self.num_features());
+        self.encode_into(game_state, idx, hole, &mut out);
+        out
+    }
+
+    /// Clear `out` and write the features into it, so a buffer can be
+    /// reused between states.
+    pub fn encode_into(
+        &self,
+        game_state: &GameState,
+        idx: usize,
+        hole: CardSet,
+        out: &mut Vec<f32>,
+    ) {
+        out.clear();
+        match self.cards {
+            CardEncoding::OneHot => {
+                out.resize(104, 0.0);
+                for card in hole {
+                    out[u8::from(card) as usize] = 1.0;
+                }
+                for card in game_state.board.iter() {
+                    out[52 + u8::from(*card) as usize] = 1.0;
+                }
+            }
+            CardEncoding::Canonical => {
+                let mut known = CardSet::new();
+                let mut slot = |card: Option<Card>| {
+                    out.push(card.map_or(-1.0, |card| {
+                        let branch = SuitIsomorphism.card_to_idx(game_state, known, card);
+                        known.insert(card);
+                        branch as f32
+                    }))
+                };
+                let mut hole = hole.into_iter();
+                for _ in 0..2 {
+                    slot(hole.next());
+                }
+                for i in 0..5 {
+                    slot(game_state.board.get(i).copied());
+                }
+            }
+        }
+
+        out.extend(
+            STREETS
+                .iter()
+                .map(|(round, _)| f32::from(u8::from(game_state.round == *round))),
+        );
+
+        let big_blind = game_state.big_blind.max(f32::MIN_POSITIVE);
+        let stack = game_state.stacks[idx];
+        let pot = game_state.total_pot;
+        let to_call = (game_state.current_round_bet() - game_state.current_round_player_bet(idx))
+            .clamp(0.0, stack);
+        let num_players = game_state.num_players;
+        out.extend([
+            pot / big_blind,
+            to_call / big_blind,
+            stack / big_blind,
+            if to_call > 0.0 {
+                to_call / (pot + to_call)
+            } else {
+                0.0
+            },
+            if pot > 0.0 { stack / pot } else { 0.0 },
+            game_state.num_active_players() as f32 / num_players as f32,
+            ((idx + num_players - game_state.dealer_idx) % num_players) as f32 / num_players as f32,
+            num_players as f32,
+        ]);
+
+        for (round, _) in STREETS {
+            let street = game_state.street(round);
+            out.extend((0..self.max_players).map(|seat| {
+                let player_bet = street
+                    .filter(|_| seat < num_players)
+                    .and_then(|street| street.player_bet.get((idx + seat) % num_players));
+                player_bet.map_or(0.0, |bet| bet / big_blind)
+            }));
+            out.push(street.map_or(0.0, |street| f32::from(street.num_bets)));
+            out.push(f32::from(u8::from(
+                street.is_some_and(|street| street.last_aggressor == Some(idx)),
+            )));
+        }
+    }
+}
+
+impl Featurizer for FeatureEncoder {
+    fn num_features(&self) -> usize {
+        FeatureEncoder::num_features(self)
+    }
+
+    /// Encode for the player to act, holding the cards they hold.
+    fn featurize(&self, game_state: &GameState, out: &mut Vec<f32>) {
+        let idx = game_state.to_act_idx();
+        let hole = CardSet::from(game_state.hands[idx]) - game_state.board_set();
+        self.encode_into(game_state, idx, hole, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Hand;
+
+    #[test]
+    fn test_encode() {
+        let mut game_state = GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.0, 0);
+        game_state.advance_to_round(Round::Preflop);
+        game_state.do_bet(1.0, true).unwrap();
+        game_state.do_bet(2.0, true).unwrap();
+        game_state.do_bet(6.0, false).unwrap();
+        // The small blind, facing a raise from the dealer.
+        let idx = game_state.to_act_idx();
+        assert_eq!(1, idx);
+        let hole = CardSet::from(Hand::new_from_str("AsKs").unwrap());
+
+        let encoder = FeatureEncoder::new(CardEncoding::OneHot, 4);
+        let features = encoder.encode(&game_state, idx, hole);
+        let names = encoder.names();
+        assert_eq!(encoder.num_features(), features.len());
+        assert_eq!(features.len(), names.len());
+        let feature = |name: &str| features[names.iter().position(|n| n == name).unwrap()];
+
+        assert_eq!(1.0, feature("hole_As"));
+        assert_eq!(0.0, feature("hole_Ah"));
+        assert_eq!(1.0, feature("round_preflop"));
+        assert_eq!(4.5, feature("pot_bb"));
+        assert_eq!(2.5, feature("to_call_bb"));
+        assert_eq!(49.5, feature("stack_bb"));
+        assert_eq!(5.0 / 14.0, feature("pot_odds"));
+        assert_eq!(1.0 / 3.0, feature("position"));
+        assert_eq!(0.5, feature("preflop_bet_seat_0"));
+        assert_eq!(1.0, feature("preflop_bet_seat_1"));
+        assert_eq!(3.0, feature("preflop_bet_seat_2"));
+        // Only three seats at the table.
+        assert_eq!(0.0, feature("preflop_bet_seat_3"));
+        assert_eq!(0.0, feature("preflop_player_aggressor"));
+        assert_eq!(0.0, feature("flop_num_bets"));
+
+        // Swapping spades for hearts changes nothing canonically.
+        let canonical = FeatureEncoder::new(CardEncoding::Canonical, 4);
+        let hearts = CardSet::from(Hand::new_from_str("AhKh").unwrap());
+        let features = canonical.encode(&game_state, idx, hole);
+        assert_eq!(canonical.num_features(), features.len());
+        assert_eq!(features, canonical.encode(&game_state, idx, hearts));
+        assert_eq!(&[-1.0; 5], &features[2..7]);
+    }
+}
@@ -0,0 +1,329 @@
+//! Accessors for `schema/hand_record.fbs`.
+//!
+//! These are written in the same shape that `flatc --rust` generates, but
+//! are checked in so that building the crate doesn't need `flatc`. Keep
+//! them in sync with the schema.
+
+use flatbuffers::{
+    FlatBufferBuilder, Follow, ForwardsUOffset, InvalidFlatbuffer, Table, VOffsetT, Vector,
+    Verifiable, Verifier, WIPOffset,
+};
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct HandRecord<'a> {
+    pub _tab: Table<'a>,
+}
+
+impl<'a> Follow<'a> for HandRecord<'a> {
+    type Inner = HandRecord<'a>;
+    #[inline]
+    unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: unsafe { Table::new(buf, loc) },
+        }
+    }
+}
+
+impl<'a> HandRecord<'a> {
+    pub const VT_ID_HI: VOffsetT = 4;
+    pub const VT_ID_LO: VOffsetT = 6;
+    pub const VT_NUM_PLAYERS: VOffsetT = 8;
+    pub const VT_DEALER_IDX: VOffsetT = 10;
+    pub const VT_SMALL_BLIND: VOffsetT = 12;
+    pub const VT_BIG_BLIND: VOffsetT = 14;
+    pub const VT_ANTE: VOffsetT = 16;
+    pub const VT_STARTING_STACKS: VOffsetT = 18;
+    pub const VT_FINAL_STACKS: VOffsetT = 20;
+    pub const VT_HOLE_CARDS: VOffsetT = 22;
+    pub const VT_BOARD: VOffsetT = 24;
+    pub const VT_ACTION_PLAYERS: VOffsetT = 26;
+    pub const VT_ACTION_ROUNDS: VOffsetT = 28;
+    pub const VT_ACTION_KINDS: VOffsetT = 30;
+    pub const VT_ACTION_AMOUNTS: VOffsetT = 32;
+    pub const VT_PLAYER_WINNINGS: VOffsetT = 34;
+    pub const VT_HOLE_CARD_COUNTS: VOffsetT = 36;
+
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+        fbb: &'mut_bldr mut FlatBufferBuilder<'bldr, A>,
+        args: &'args HandRecordArgs<'args>,
+    ) -> WIPOffset<HandRecord<'bldr>> {
+        // Largest fields first, the same order flatc emits.
+        let start = fbb.start_table();
+        fbb.push_slot::<u64>(Self::VT_ID_LO, args.id_lo, 0);
+        fbb.push_slot::<u64>(Self::VT_ID_HI, args.id_hi, 0);
+        if let Some(x) = args.hole_card_counts {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_HOLE_CARD_COUNTS, x);
+        }
+        if let Some(x) = args.player_winnings {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_PLAYER_WINNINGS, x);
+        }
+        if let Some(x) = args.action_amounts {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_ACTION_AMOUNTS, x);
+        }
+        if let Some(x) = args.action_kinds {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_ACTION_KINDS, x);
+        }
+        if let Some(x) = args.action_rounds {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_ACTION_ROUNDS, x);
+        }
+        if let Some(x) = args.action_players {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_ACTION_PLAYERS, x);
+        }
+        if let Some(x) = args.board {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_BOARD, x);
+        }
+        if let Some(x) = args.hole_cards {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_HOLE_CARDS, x);
+        }
+        if let Some(x) = args.final_stacks {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_FINAL_STACKS, x);
+        }
+        if let Some(x) = args.starting_stacks {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_STARTING_STACKS, x);
+        }
+        fbb.push_slot::<f32>(Self::VT_ANTE, args.ante, 0.0);
+        fbb.push_slot::<f32>(Self::VT_BIG_BLIND, args.big_blind, 0.0);
+        fbb.push_slot::<f32>(Self::VT_SMALL_BLIND, args.small_blind, 0.0);
+        fbb.push_slot::<u8>(Self::VT_DEALER_IDX, args.dealer_idx, 0);
+        fbb.push_slot::<u8>(Self::VT_NUM_PLAYERS, args.num_players, 0);
+        let o = fbb.end_table(start);
+        WIPOffset::new(o.value())
+    }
+
+    #[inline]
+    pub fn id_hi(&self) -> u64 {
+        // Safety:
+        // Created from valid Table for this object
+        // which contains a valid value in this slot
+        unsafe { self._tab.get::<u64>(Self::VT_ID_HI, Some(0)).unwrap() }
+    }
+    #[inline]
+    pub fn id_lo(&self) -> u64 {
+        unsafe { self._tab.get::<u64>(Self::VT_ID_LO, Some(0)).unwrap() }
+    }
+    #[inline]
+    pub fn num_players(&self) -> u8 {
+        unsafe { self._tab.get::<u8>(Self::VT_NUM_PLAYERS, Some(0)).unwrap() }
+    }
+    #[inline]
+    pub fn dealer_idx(&self) -> u8 {
+        unsafe { self._tab.get::<u8>(Self::VT_DEALER_IDX, Some(0)).unwrap() }
+    }
+    #[inline]
+    pub fn small_blind(&self) -> f32 {
+        unsafe {
+            self._tab
+                .get::<f32>(Self::VT_SMALL_BLIND, Some(0.0))
+                .unwrap()
+        }
+    }
+    #[inline]
+    pub fn big_blind(&self) -> f32 {
+        unsafe { self._tab.get::<f32>(Self::VT_BIG_BLIND, Some(0.0)).unwrap() }
+    }
+    #[inline]
+    pub fn ante(&self) -> f32 {
+        unsafe { self._tab.get::<f32>(Self::VT_ANTE, Some(0.0)).unwrap() }
+    }
+    #[inline]
+    pub fn starting_stacks(&self) -> Option<Vector<'a, f32>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, f32>>>(Self::VT_STARTING_STACKS, None)
+        }
+    }
+    #[inline]
+    pub fn final_stacks(&self) -> Option<Vector<'a, f32>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, f32>>>(Self::VT_FINAL_STACKS, None)
+        }
+    }
+    #[inline]
+    pub fn hole_cards(&self) -> Option<Vector<'a, u8>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, u8>>>(Self::VT_HOLE_CARDS, None)
+        }
+    }
+    #[inline]
+    pub fn board(&self) -> Option<Vector<'a, u8>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, u8>>>(Self::VT_BOARD, None)
+        }
+    }
+    #[inline]
+    pub fn action_players(&self) -> Option<Vector<'a, u8>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, u8>>>(Self::VT_ACTION_PLAYERS, None)
+        }
+    }
+    #[inline]
+    pub fn action_rounds(&self) -> Option<Vector<'a, u8>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, u8>>>(Self::VT_ACTION_ROUNDS, None)
+        }
+    }
+    #[inline]
+    pub fn action_kinds(&self) -> Option<Vector<'a, u8>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, u8>>>(Self::VT_ACTION_KINDS, None)
+        }
+    }
+    #[inline]
+    pub fn action_amounts(&self) -> Option<Vector<'a, f32>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, f32>>>(Self::VT_ACTION_AMOUNTS, None)
+        }
+    }
+    #[inline]
+    pub fn player_winnings(&self) -> Option<Vector<'a, f32>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, f32>>>(Self::VT_PLAYER_WINNINGS, None)
+        }
+    }
+    #[inline]
+    pub fn hole_card_counts(&self) -> Option<Vector<'a, u8>> {
+        unsafe {
+            self._tab
+                .get::<ForwardsUOffset<Vector<'a, u8>>>(Self::VT_HOLE_CARD_COUNTS, None)
+        }
+    }
+}
+
+impl Verifiable for HandRecord<'_> {
+    #[inline]
+    fn run_verifier(v: &mut Verifier, pos: usize) -> Result<(), InvalidFlatbuffer> {
+        v.visit_table(pos)?
+            .visit_field::<u64>("id_hi", Self::VT_ID_HI, false)?
+            .visit_field::<u64>("id_lo", Self::VT_ID_LO, false)?
+            .visit_field::<u8>("num_players", Self::VT_NUM_PLAYERS, false)?
+            .visit_field::<u8>("dealer_idx", Self::VT_DEALER_IDX, false)?
+            .visit_field::<f32>("small_blind", Self::VT_SMALL_BLIND, false)?
+            .visit_field::<f32>("big_blind", Self::VT_BIG_BLIND, false)?
+            .visit_field::<f32>("ante", Self::VT_ANTE, false)?
+            .visit_field::<ForwardsUOffset<Vector<'_, f32>>>(
+                "starting_stacks",
+                Self::VT_STARTING_STACKS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Vector<'_, f32>>>(
+                "final_stacks",
+                Self::VT_FINAL_STACKS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Vector<'_, u8>>>(
+                "hole_cards",
+                Self::VT_HOLE_CARDS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Vector<'_, u8>>>("board", Self::VT_BOARD, false)?
+            .visit_field::<ForwardsUOffset<Vector<'_, u8>>>(
+                "action_players",
+                Self::VT_ACTION_PLAYERS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Vector<'_, u8>>>(
+                "action_rounds",
+                Self::VT_ACTION_ROUNDS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Vector<'_, u8>>>(
+                "action_kinds",
+                Self::VT_ACTION_KINDS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Vector<'_, f32>>>(
+                "action_amounts",
+                Self::VT_ACTION_AMOUNTS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Vector<'_, f32>>>(
+                "player_winnings",
+                Self::VT_PLAYER_WINNINGS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Vector<'_, u8>>>(
+                "hole_card_counts",
+                Self::VT_HOLE_CARD_COUNTS,
+                false,
+            )?
+            .finish();
+        Ok(())
+    }
+}
+
+pub struct HandRecordArgs<'a> {
+    pub id_hi: u64,
+    pub id_lo: u64,
+    pub num_players: u8,
+    pub dealer_idx: u8,
+    pub small_blind: f32,
+    pub big_blind: f32,
+    pub ante: f32,
+    pub starting_stacks: Option<WIPOffset<Vector<'a, f32>>>,
+    pub final_stacks: Option<WIPOffset<Vector<'a, f32>>>,
+    pub hole_cards: Option<WIPOffset<Vector<'a, u8>>>,
+    pub board: Option<WIPOffset<Vector<'a, u8>>>,
+    pub action_players: Option<WIPOffset<Vector<'a, u8>>>,
+    pub action_rounds: Option<WIPOffset<Vector<'a, u8>>>,
+    pub action_kinds: Option<WIPOffset<Vector<'a, u8>>>,
+    pub action_amounts: Option<WIPOffset<Vector<'a, f32>>>,
+    pub player_winnings: Option<WIPOffset<Vector<'a, f32>>>,
+    pub hole_card_counts: Option<WIPOffset<Vector<'a, u8>>>,
+}
+
+impl Default for HandRecordArgs<'_> {
+    #[inline]
+    fn default() -> Self {
+        HandRecordArgs {
+            id_hi: 0,
+            id_lo: 0,
+            num_players: 0,
+            dealer_idx: 0,
+            small_blind: 0.0,
+            big_blind: 0.0,
+            ante: 0.0,
+            starting_stacks: None,
+            final_stacks: None,
+            hole_cards: None,
+            board: None,
+            action_players: None,
+            action_rounds: None,
+            action_kinds: None,
+            action_amounts: None,
+            player_winnings: None,
+            hole_card_counts: None,
+        }
+    }
+}
+
+impl core::fmt::Debug for HandRecord<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut ds = f.debug_struct("HandRecord");
+        ds.field("id_hi", &self.id_hi());
+        ds.field("id_lo", &self.id_lo());
+        ds.field("num_players", &self.num_players());
+        ds.field("dealer_idx", &self.dealer_idx());
+        ds.field("small_blind", &self.small_blind());
+        ds.field("big_blind", &self.big_blind());
+        ds.field("ante", &self.ante());
+        ds.field("starting_stacks", &self.starting_stacks());
+        ds.field("final_stacks", &self.final_stacks());
+        ds.field("hole_cards", &self.hole_cards());
+        ds.field("board", &self.board());
+        ds.field("action_players", &self.action_players());
+        ds.field("action_rounds", &self.action_rounds());
+        ds.field("action_kinds", &self.action_kinds());
+        ds.field("action_amounts", &self.action_amounts());
+        ds.field("player_winnings", &self.player_winnings());
+        ds.field("hole_card_counts", &self.hole_card_counts());
+        ds.finish()
+    }
+}
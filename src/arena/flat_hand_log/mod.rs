@@ -0,0 +1,523 @@
+//! A FlatBuffers hand log with zero-copy random access.
+//!
+//! The compact `hand_log` format is the smallest way to archive hands, but
+//! it has to be decompressed and replayed from the start every time it's
+//! read. Training pipelines that scan the same logs over and over want the
+//! opposite trade off: one `HandRecord` per hand, stored as a FlatBuffer
+//! that can be read in place without parsing or copying, plus an index so
+//! any hand can be found in constant time.
+//!
+//! The layout of a file is:
+//!
+//! - The magic bytes `RSFB`, a version byte and padding to 8 bytes.
+//! - One finished FlatBuffer per hand (see `schema/hand_record.fbs`), each
+//!   padded to 8 bytes.
+//! - The index, a little endian `u64` offset for the start of each record.
+//! - A little endian `u64` offset of the index and a `u64` record count.
+//!
+//! The index is written by `FlatHandLogWriter::finish`, so a log that was
+//! cut short can't be opened. `FlatHandLog` works on any `&[u8]`; for very
+//! large logs memory map the file and only the records that are touched
+//! will be paged in.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::GameState;
+//! use rs_poker::arena::flat_hand_log::{FlatHandLog, FlatHandLogWriter};
+//!
+//! let game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0);
+//! let mut writer = FlatHandLogWriter::new(Vec::new()).unwrap();
+//! writer.write_hand(1, &game_state, &[]).unwrap();
+//! writer.write_hand(2, &game_state, &[]).unwrap();
+//! let bytes = writer.finish().unwrap();
+//!
+//! let log = FlatHandLog::new(&bytes).unwrap();
+//! assert_eq!(2, log.len());
+//! let record = log.get(1).unwrap();
+//! assert_eq!(2, record.id());
+//! assert_eq!(3, record.num_players());
+//! ```
+mod hand_record_generated;
+
+use std::io::{self, Write};
+
+use flatbuffers::FlatBufferBuilder;
+
+use crate::core::{Card, CardSet};
+
+use super::GameState;
+use super::action::{AgentAction, PlayedActionPayload};
+use super::errors::FlatHandLogError;
+
+pub use hand_record_generated::{HandRecord, HandRecordArgs};
+
+const MAGIC: &[u8; 4] = b"RSFB";
+const VERSION: u8 = 1;
+const ALIGN: usize = 8;
+const HEADER_LEN: usize = 8;
+const TRAILER_LEN: usize = 16;
+
+/// `HandRecord::action_kinds` values.
+pub const ACTION_FOLD: u8 = 0;
+pub const ACTION_BET: u8 = 1;
+pub const ACTION_ALL_IN: u8 = 2;
+
+impl HandRecord<'_> {
+    /// The id of the simulation that played this hand.
+    pub fn id(&self) -> u128 {
+        (u128::from(self.id_hi()) << 64) | u128::from(self.id_lo())
+    }
+
+    /// The hole cards dealt to the player in seat `idx`. Two for hold'em,
+    /// four for Omaha and so on.
+    pub fn hole_cards_for(&self, idx: usize) -> Option<Vec<Card>> {
+        let cards = self.hole_cards()?;
+        // Logs written before the counts were stored only had hold'em hands.
+        let (start, count) = match self.hole_card_counts() {
+            Some(counts) => {
+                if idx >= counts.len() {
+                    return None;
+                }
+                let start: usize = counts.iter().take(idx).map(usize::from).sum();
+                (start, usize::from(counts.get(idx)))
+            }
+            None => (idx * 2, 2),
+        };
+        if cards.len() < start + count {
+            return None;
+        }
+        Some(
+            (start..start + count)
+                .map(|i| Card::from(cards.get(i)))
+                .collect(),
+        )
+    }
+
+    /// The community cards, in the order they were dealt.
+    pub fn board_cards(&self) -> Vec<Card> {
+        self.board()
+            .map(|board| board.iter().map(Card::from).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Writes one `HandRecord` per hand and the index needed to read them back
+/// in any order.
+pub struct FlatHandLogWriter<W: Write> {
+    writer: Option<W>,
+    builder: FlatBufferBuilder<'static>,
+    offsets: Vec<u64>,
+    position: u64,
+}
+
+impl<W: Write> FlatHandLogWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let mut header = [0_u8; HEADER_LEN];
+        header[..4].copy_from_slice(MAGIC);
+        header[4] = VERSION;
+        writer.write_all(&header)?;
+        Ok(Self {
+            writer: Some(writer),
+            builder: FlatBufferBuilder::with_capacity(1024),
+            offsets: vec![],
+            position: HEADER_LEN as u64,
+        })
+    }
+
+    /// The number of hands written so far.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Append a hand. `game_state` should be the state once the hand is
+    /// complete, and `actions` the voluntary actions taken in it.
+    pub fn write_hand(
+        &mut self,
+        id: u128,
+        game_state: &GameState,
+        actions: &[PlayedActionPayload],
+    ) -> io::Result<()> {
+        let fbb = &mut self.builder;
+        fbb.reset();
+
+        let board_set = game_state.board_set();
+        let holes: Vec<CardSet> = game_state
+            .hands
+            .iter()
+            .map(|hand| CardSet::from(*hand).difference(board_set))
+            .collect();
+        let hole_cards: Vec<u8> = holes
+            .iter()
+            .flat_map(|hole| hole.iter().map(u8::from))
+            .collect();
+        let hole_card_counts: Vec<u8> = holes.iter().map(|hole| hole.count() as u8).collect();
+        let board: Vec<u8> = game_state.board.iter().copied().map(u8::from).collect();
+        let action_players: Vec<u8> = actions.iter().map(|a| a.idx as u8).collect();
+        let action_rounds: Vec<u8> = actions.iter().map(|a| a.round.into()).collect();
+        let action_kinds: Vec<u8> = actions
+            .iter()
+            .map(|a| match a.action {
+                AgentAction::Fold => ACTION_FOLD,
+                AgentAction::Bet(_) => ACTION_BET,
+                AgentAction::AllIn => ACTION_ALL_IN,
+            })
+            .collect();
+        let action_amounts: Vec<f32> = actions.iter().map(|a| a.final_player_bet).collect();
+
+        let args = HandRecordArgs {
+            id_hi: (id >> 64) as u64,
+            id_lo: id as u64,
+            num_players: game_state.num_players as u8,
+            dealer_idx: game_state.dealer_idx as u8,
+            small_blind: game_state.small_blind,
+            big_blind: game_state.big_blind,
+            ante: game_state.ante,
+            starting_stacks: Some(fbb.create_vector(&game_state.starting_stacks)),
+            final_stacks: Some(fbb.create_vector(&game_state.stacks)),
+            hole_cards: Some(fbb.create_vector(&hole_cards)),
+            board: Some(fbb.create_vector(&board)),
+            action_players: Some(fbb.create_vector(&action_players)),
+            action_rounds: Some(fbb.create_vector(&action_rounds)),
+            action_kinds: Some(fbb.create_vector(&action_kinds)),
+            action_amounts: Some(fbb.create_vector(&action_amounts)),
+            player_winnings: Some(fbb.create_vector(&game_state.player_winnings)),
+            hole_card_counts: Some(fbb.create_vector(&hole_card_counts)),
+        };
+        let record = HandRecord::create(fbb, &args);
+        fbb.finish_minimal(record);
+
+        let data = fbb.finished_data();
+        let padding = (ALIGN - data.len() % ALIGN) % ALIGN;
+        let writer = self
+            .writer
+            .as_mut()
+            .expect("writer is only taken on finish");
+        writer.write_all(data)?;
+        writer.write_all(&[0; ALIGN][..padding])?;
+
+        self.offsets.push(self.position);
+        self.position += (data.len() + padding) as u64;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer
+            .as_mut()
+            .expect("writer is only taken on finish")
+            .flush()
+    }
+
+    /// Write the index, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut writer = self.writer.take().expect("writer is only taken on finish");
+        write_index(&mut writer, &self.offsets, self.position)?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Drop for FlatHandLogWriter<W> {
+    fn drop(&mut self) {
+        if let Some(mut writer) = self.writer.take() {
+            let _ = write_index(&mut writer, &self.offsets, self.position);
+        }
+    }
+}
+
+fn write_index<W: Write>(writer: &mut W, offsets: &[u64], index_start: u64) -> io::Result<()> {
+    for offset in offsets {
+        writer.write_all(&offset.to_le_bytes())?;
+    }
+    writer.write_all(&index_start.to_le_bytes())?;
+    writer.write_all(&(offsets.len() as u64).to_le_bytes())?;
+    writer.flush()
+}
+
+/// A read only view of a FlatBuffers hand log. Records are read in place,
+/// borrowing from the underlying bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct FlatHandLog<'a> {
+    data: &'a [u8],
+    index: &'a [u8],
+    index_start: usize,
+}
+
+impl<'a> FlatHandLog<'a> {
+    /// Check the header and find the index. No records are read until
+    /// they're asked for.
+    pub fn new(data: &'a [u8]) -> Result<Self, FlatHandLogError> {
+        if data.len() < HEADER_LEN || &data[..4] != MAGIC {
+            return Err(FlatHandLogError::BadMagic);
+        }
+        if data[4] != VERSION {
+            return Err(FlatHandLogError::UnsupportedVersion(data[4]));
+        }
+        if data.len() < HEADER_LEN + TRAILER_LEN {
+            return Err(FlatHandLogError::Corrupt("missing index"));
+        }
+
+        let trailer = data.len() - TRAILER_LEN;
+        let index_start = read_u64(data, trailer);
+        let count = read_u64(data, trailer + 8);
+        // An unfinished log won't have a trailer that points back at a
+        // matching index.
+        let index_len = count.checked_mul(8);
+        if index_len.and_then(|len| index_start.checked_add(len)) != Some(trailer as u64)
+            || index_start < HEADER_LEN as u64
+        {
+            return Err(FlatHandLogError::Corrupt("missing index"));
+        }
+        let index_start = index_start as usize;
+
+        Ok(Self {
+            data,
+            index: &data[index_start..trailer],
+            index_start,
+        })
+    }
+
+    /// The number of hands in the log.
+    pub fn len(&self) -> usize {
+        self.index.len() / 8
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Read the hand at `idx`. The record is verified before it's
+    /// returned, so a corrupt file can't cause out of bounds reads.
+    pub fn get(&self, idx: usize) -> Result<HandRecord<'a>, FlatHandLogError> {
+        if idx >= self.len() {
+            return Err(FlatHandLogError::OutOfBounds(idx));
+        }
+        let start = read_u64(self.index, idx * 8) as usize;
+        let end = if idx + 1 < self.len() {
+            read_u64(self.index, (idx + 1) * 8) as usize
+        } else {
+            self.index_start
+        };
+        if start < HEADER_LEN || start > end || end > self.index_start {
+            return Err(FlatHandLogError::Corrupt("bad record offset"));
+        }
+        Ok(flatbuffers::root::<HandRecord>(&self.data[start..end])?)
+    }
+
+    /// Iterate over every hand in the order they were written.
+    pub fn iter(&self) -> impl Iterator<Item = Result<HandRecord<'a>, FlatHandLogError>> + '_ {
+        (0..self.len()).map(|idx| self.get(idx))
+    }
+}
+
+fn read_u64(data: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(data[at..at + 8].try_into().expect("slice is 8 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::action::Action;
+    use crate::arena::agent::{CallingAgent, RandomAgent};
+    use crate::arena::game_state::Round;
+    use crate::arena::historian::VecHistorian;
+    use crate::arena::variant::GameVariant;
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::*;
+
+    fn simulate(num_players: usize) -> (u128, GameState, Vec<PlayedActionPayload>) {
+        simulate_variant(num_players, GameVariant::Holdem)
+    }
+
+    fn simulate_variant(
+        num_players: usize,
+        variant: GameVariant,
+    ) -> (u128, GameState, Vec<PlayedActionPayload>) {
+        let historian = VecHistorian::default();
+        let storage = historian.get_storage();
+        let agents: Vec<Box<dyn Agent>> = (0..num_players)
+            .map(|i| -> Box<dyn Agent> {
+                if i == 0 {
+                    Box::<CallingAgent>::default()
+                } else {
+                    Box::<RandomAgent>::default()
+                }
+            })
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(
+                vec![100.0; num_players],
+                10.0,
+                5.0,
+                1.0,
+                1,
+            ))
+            .agents(agents)
+            .variant(variant)
+            .historians(vec![Box::new(historian)])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let actions = storage
+            .borrow()
+            .iter()
+            .filter_map(|r| match &r.action {
+                Action::PlayedAction(payload) => Some(payload.clone()),
+                _ => None,
+            })
+            .collect();
+        (sim.id, sim.game_state, actions)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (id, game_state, actions) = simulate(4);
+        let mut writer = FlatHandLogWriter::new(Vec::new()).unwrap();
+        writer.write_hand(id, &game_state, &actions).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let log = FlatHandLog::new(&bytes).unwrap();
+        assert_eq!(1, log.len());
+        let record = log.get(0).unwrap();
+
+        assert_eq!(id, record.id());
+        assert_eq!(4, record.num_players());
+        assert_eq!(1, record.dealer_idx());
+        assert_eq!(1.0, record.ante());
+        assert_eq!(
+            game_state.stacks,
+            record.final_stacks().unwrap().iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            game_state.player_winnings,
+            record.player_winnings().unwrap().iter().collect::<Vec<_>>()
+        );
+        assert_eq!(game_state.board.to_vec(), record.board_cards());
+        for idx in 0..4 {
+            let hole = record.hole_cards_for(idx).unwrap();
+            assert_eq!(2, hole.len());
+            assert!(hole.iter().all(|c| game_state.hands[idx].contains(c)));
+            assert!(hole.iter().all(|c| !game_state.board.contains(c)));
+        }
+        assert_eq!(None, record.hole_cards_for(4));
+
+        assert_eq!(actions.len(), record.action_kinds().unwrap().len());
+        for (action, round) in actions.iter().zip(record.action_rounds().unwrap()) {
+            assert_eq!(Ok(action.round), Round::try_from(round));
+        }
+    }
+
+    #[test]
+    fn test_round_trip_omaha() {
+        let (id, game_state, actions) = simulate_variant(3, GameVariant::Omaha);
+        let mut writer = FlatHandLogWriter::new(Vec::new()).unwrap();
+        writer.write_hand(id, &game_state, &actions).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let log = FlatHandLog::new(&bytes).unwrap();
+        let record = log.get(0).unwrap();
+        assert_eq!(12, record.hole_cards().unwrap().len());
+        for idx in 0..3 {
+            let hole = record.hole_cards_for(idx).unwrap();
+            let expected: Vec<Card> = CardSet::from(game_state.hands[idx])
+                .difference(game_state.board_set())
+                .iter()
+                .collect();
+            assert_eq!(4, hole.len());
+            assert_eq!(expected, hole);
+        }
+        assert_eq!(None, record.hole_cards_for(3));
+    }
+
+    #[test]
+    fn test_old_records_have_two_cards_per_seat() {
+        let mut fbb = FlatBufferBuilder::new();
+        let hole_cards = fbb.create_vector(&[0_u8, 1, 2, 3]);
+        let args = HandRecordArgs {
+            num_players: 2,
+            hole_cards: Some(hole_cards),
+            ..Default::default()
+        };
+        let record = HandRecord::create(&mut fbb, &args);
+        fbb.finish_minimal(record);
+        let record = flatbuffers::root::<HandRecord>(fbb.finished_data()).unwrap();
+
+        assert_eq!(
+            Some(vec![Card::from(2_u8), Card::from(3_u8)]),
+            record.hole_cards_for(1)
+        );
+        assert_eq!(None, record.hole_cards_for(2));
+    }
+
+    #[test]
+    fn test_random_access() {
+        let mut writer = FlatHandLogWriter::new(Vec::new()).unwrap();
+        let mut ids = vec![];
+        for i in 0..20 {
+            let (id, game_state, actions) = simulate(2 + i % 5);
+            writer.write_hand(id, &game_state, &actions).unwrap();
+            ids.push(id);
+        }
+        assert_eq!(20, writer.len());
+        let bytes = writer.finish().unwrap();
+
+        let log = FlatHandLog::new(&bytes).unwrap();
+        assert_eq!(20, log.len());
+        for idx in [13, 0, 19, 7] {
+            let record = log.get(idx).unwrap();
+            assert_eq!(ids[idx], record.id());
+            assert_eq!(2 + idx % 5, record.num_players() as usize);
+        }
+        let read_ids: Vec<u128> = log.iter().map(|r| r.unwrap().id()).collect();
+        assert_eq!(ids, read_ids);
+        assert!(matches!(
+            log.get(20),
+            Err(FlatHandLogError::OutOfBounds(20))
+        ));
+    }
+
+    #[test]
+    fn test_empty_log() {
+        let bytes = FlatHandLogWriter::new(Vec::new())
+            .unwrap()
+            .finish()
+            .unwrap();
+        let log = FlatHandLog::new(&bytes).unwrap();
+        assert!(log.is_empty());
+        assert_eq!(0, log.iter().count());
+    }
+
+    #[test]
+    fn test_bad_files() {
+        assert!(matches!(
+            FlatHandLog::new(b"nope"),
+            Err(FlatHandLogError::BadMagic)
+        ));
+        assert!(matches!(
+            FlatHandLog::new(b"RSFB\x09\0\0\0"),
+            Err(FlatHandLogError::UnsupportedVersion(9))
+        ));
+
+        let (id, game_state, actions) = simulate(2);
+        let mut writer = FlatHandLogWriter::new(Vec::new()).unwrap();
+        writer.write_hand(id, &game_state, &actions).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        // Cut off the index, as if the writer never finished.
+        let truncated = &bytes[..bytes.len() - 24];
+        assert!(matches!(
+            FlatHandLog::new(truncated),
+            Err(FlatHandLogError::Corrupt(_))
+        ));
+
+        // Garbage in a record is caught by the verifier.
+        let mut garbled = bytes.clone();
+        garbled[HEADER_LEN..HEADER_LEN + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let log = FlatHandLog::new(&garbled).unwrap();
+        assert!(matches!(log.get(0), Err(FlatHandLogError::Invalid(_))));
+    }
+}
@@ -0,0 +1,321 @@
+//! A harness for fuzzing the arena engine.
+//!
+//! `fuzz_engine` turns raw bytes into a table and a script of actions for
+//! every seat, plays the hand out, and panics if the engine breaks one of its
+//! invariants at any point along the way. The invariants are checked after
+//! every action, not only at the end, so a bad intermediate state is caught
+//! where it happens.
+//!
+//! Scripted actions are relative to the state of the hand when they're
+//! played: call, the minimum raise, just under it, a bet of the whole stack
+//! and so on. Random bytes then hit the edges of the betting rules far more
+//! often than random bet sizes would.
+//!
+//! It's meant to be driven by cargo-fuzz:
+//!
+//! ```ignore
+//! #![no_main]
+//! use libfuzzer_sys::fuzz_target;
+//!
+//! fuzz_target!(|data: &[u8]| rs_poker::arena::fuzz::fuzz_engine(data));
+//! ```
+//!
+//! or with the input as the fuzz target's argument, so that cargo-fuzz can
+//! print failing inputs in a readable form:
+//!
+//! ```
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use rs_poker::arena::fuzz::EngineInput;
+//!
+//! let bytes: Vec<u8> = (0..255).collect();
+//! let input = EngineInput::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+//! let game_state = input.run();
+//! assert!(game_state.is_complete());
+//! ```
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use super::action::{Action, AgentAction};
+use super::game_state::Round;
+use super::historian::{HistorianError, VecHistorian};
+use super::reachable::MAX_REACHABLE_PLAYERS;
+use super::test_util::{
+    assert_valid_game_state, assert_valid_history, assert_valid_partial_game_state,
+    assert_valid_round_data,
+};
+use super::{Agent, GameState, Historian, HoldemSimulationBuilder};
+use crate::core::PlayerBitSet;
+
+/// One action in a seat's script, worked out against the hand when it's
+/// played.
+#[derive(Debug, Clone, Copy, PartialEq, Arbitrary)]
+pub enum ScriptedAction {
+    Fold,
+    /// Check or call.
+    Call,
+    MinRaise,
+    /// Half way between a call and the minimum raise, which isn't a legal
+    /// bet unless it puts the player all in.
+    UnderMinRaise,
+    /// Raise the size of the pot.
+    PotRaise,
+    /// Bet everything by amount rather than with `AgentAction::AllIn`.
+    BetStack,
+    /// Bet more than the player has.
+    OverBetStack,
+    AllIn,
+    /// A total bet of this many small blinds.
+    SmallBlinds(u16),
+    /// Any bet at all, including negative, infinite and NaN amounts.
+    Raw(f32),
+}
+
+impl ScriptedAction {
+    /// The action an agent sends to the engine for this in `game_state`.
+    pub fn to_agent_action(self, game_state: &GameState) -> AgentAction {
+        let call = game_state.current_round_bet();
+        let min_raise = call + game_state.current_round_min_raise();
+        let all_in =
+            game_state.current_round_current_player_bet() + game_state.current_player_stack();
+        match self {
+            ScriptedAction::Fold => AgentAction::Fold,
+            ScriptedAction::Call => AgentAction::Bet(call),
+            ScriptedAction::MinRaise => AgentAction::Bet(min_raise),
+            ScriptedAction::UnderMinRaise => AgentAction::Bet((call + min_raise) / 2.0),
+            ScriptedAction::PotRaise => AgentAction::Bet(call + game_state.total_pot),
+            ScriptedAction::BetStack => AgentAction::Bet(all_in),
+            ScriptedAction::OverBetStack => AgentAction::Bet(all_in + game_state.big_blind),
+            ScriptedAction::AllIn => AgentAction::AllIn,
+            ScriptedAction::SmallBlinds(n) => AgentAction::Bet(n as f32 * game_state.small_blind),
+            ScriptedAction::Raw(bet) => AgentAction::Bet(bet),
+        }
+    }
+}
+
+/// Plays a seat's script, then folds once it runs out.
+#[derive(Debug, Clone)]
+pub struct ScriptedAgent {
+    actions: Vec<ScriptedAction>,
+    idx: usize,
+}
+
+impl ScriptedAgent {
+    pub fn new(actions: Vec<ScriptedAction>) -> Self {
+        Self { actions, idx: 0 }
+    }
+}
+
+impl Agent for ScriptedAgent {
+    fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+        let action = self.actions.get(self.idx).copied();
+        self.idx += 1;
+        action.map_or(AgentAction::Fold, |action| {
+            action.to_agent_action(game_state)
+        })
+    }
+}
+
+/// A historian that checks the game state after every action and panics as
+/// soon as something is wrong.
+#[derive(Debug, Clone, Default)]
+pub struct InvariantHistorian {
+    folded: PlayerBitSet,
+    last_round: Option<Round>,
+}
+
+impl Historian for InvariantHistorian {
+    fn record_action(
+        &mut self,
+        _id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> std::result::Result<(), HistorianError> {
+        assert_ne!(
+            Some(Round::Complete),
+            self.last_round,
+            "{action:?} after the hand was complete"
+        );
+        assert_valid_partial_game_state(game_state);
+
+        let total_bet: f32 = game_state.player_bet.iter().sum();
+        assert!(
+            (total_bet - game_state.total_pot).abs() <= total_bet / 100_000.0,
+            "pot of {} with {total_bet} bet",
+            game_state.total_pot
+        );
+
+        match action {
+            Action::RoundAdvance(round) => {
+                if let Some(last) = self.last_round {
+                    assert!(last as usize <= round as usize, "{round} after {last}");
+                }
+                self.last_round = Some(round);
+            }
+            Action::PlayedAction(payload) => {
+                assert!(
+                    !self.folded.get(payload.idx),
+                    "{} acted after folding",
+                    payload.idx
+                );
+                assert!(payload.final_bet >= payload.starting_bet);
+                assert!(payload.final_player_bet >= payload.starting_player_bet);
+                if payload.action == AgentAction::Fold {
+                    self.folded.enable(payload.idx);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// A table and what every seat at it does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineInput {
+    pub stacks: Vec<f32>,
+    pub big_blind: f32,
+    pub small_blind: f32,
+    pub ante: f32,
+    pub dealer_idx: usize,
+    pub actions: Vec<Vec<ScriptedAction>>,
+    /// Seeds the deal.
+    pub seed: u64,
+}
+
+impl<'a> Arbitrary<'a> for EngineInput {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let num_players = u.int_in_range(2..=MAX_REACHABLE_PLAYERS)?;
+        let small_blind = u.int_in_range(1_u16..=10)? as f32;
+        let big_blind = small_blind * u.int_in_range(1_u16..=2)? as f32;
+        let ante = u.int_in_range(0_u16..=small_blind as u16)? as f32;
+        // Short stacks that can't cover the blinds are the interesting ones.
+        let stacks = (0..num_players)
+            .map(|_| Ok(u.int_in_range(1_u16..=1000)? as f32))
+            .collect::<Result<Vec<_>>>()?;
+        let dealer_idx = u.choose_index(num_players)?;
+        let actions = (0..num_players)
+            .map(|_| {
+                let len = u.int_in_range(0..=32)?;
+                (0..len).map(|_| u.arbitrary()).collect()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            stacks,
+            big_blind,
+            small_blind,
+            ante,
+            dealer_idx,
+            actions,
+            seed: u.arbitrary()?,
+        })
+    }
+}
+
+impl EngineInput {
+    /// Play the hand with every invariant checked, panicking on the first
+    /// one that's broken. Returns the finished game state.
+    pub fn run(&self) -> GameState {
+        let agents: Vec<Box<dyn Agent>> = (0..self.stacks.len())
+            .map(|idx| {
+                let actions = self.actions.get(idx).cloned().unwrap_or_default();
+                Box::new(ScriptedAgent::new(actions)) as Box<dyn Agent>
+            })
+            .collect();
+        let vec_historian = VecHistorian::new();
+        let storage = vec_historian.get_storage();
+        let historians: Vec<Box<dyn Historian>> = vec![
+            Box::new(vec_historian),
+            Box::<InvariantHistorian>::default(),
+        ];
+
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(
+                self.stacks.clone(),
+                self.big_blind,
+                self.small_blind,
+                self.ante,
+                self.dealer_idx,
+            ))
+            .agents(agents)
+            .historians(historians)
+            .panic_on_historian_error(true)
+            .with_seed(self.seed)
+            .build()
+            .expect("game state is always set");
+        sim.run(&mut crate::core::rng());
+
+        assert_eq!(2, sim.historians.len(), "a historian was dropped");
+        assert_valid_game_state(&sim.game_state);
+        assert_valid_round_data(&sim.game_state.round_data);
+        assert_valid_history(&storage.borrow());
+        sim.game_state
+    }
+}
+
+/// Play whatever hand `data` describes, panicking if the engine breaks an
+/// invariant. Data too short to describe a hand is ignored.
+pub fn fuzz_engine(data: &[u8]) {
+    if let Ok(input) = EngineInput::arbitrary_take_rest(Unstructured::new(data)) {
+        input.run();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripted_actions() {
+        let game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0);
+        // Before the blinds there's nothing to call.
+        assert_eq!(
+            AgentAction::Bet(0.0),
+            ScriptedAction::Call.to_agent_action(&game_state)
+        );
+        assert_eq!(
+            AgentAction::Bet(100.0),
+            ScriptedAction::BetStack.to_agent_action(&game_state)
+        );
+        assert_eq!(
+            AgentAction::Bet(15.0),
+            ScriptedAction::SmallBlinds(3).to_agent_action(&game_state)
+        );
+    }
+
+    #[test]
+    fn test_fuzz_engine() {
+        // Deterministic noise standing in for a fuzzer's corpus.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for len in (0..2048).step_by(7) {
+            let data: Vec<u8> = (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+            fuzz_engine(&data);
+        }
+    }
+
+    #[test]
+    fn test_short_stacks_play_out() {
+        let input = EngineInput {
+            stacks: vec![1.0, 3.0, 100.0, 7.0],
+            big_blind: 4.0,
+            small_blind: 2.0,
+            ante: 1.0,
+            dealer_idx: 3,
+            actions: vec![
+                vec![ScriptedAction::AllIn],
+                vec![ScriptedAction::UnderMinRaise, ScriptedAction::Call],
+                vec![ScriptedAction::MinRaise; 4],
+                vec![ScriptedAction::OverBetStack],
+            ],
+            seed: 3,
+        };
+        let game_state = input.run();
+        assert_eq!(Round::Complete, game_state.round);
+        assert_eq!(input.run(), game_state);
+    }
+}
@@ -1,16 +1,22 @@
 use core::fmt;
+use std::cell::RefCell;
 use std::fmt::Display;
+use std::sync::Arc;
 
-use rand::{Rng, rng};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 
-use crate::core::{Card, Hand, PlayerBitSet};
+use crate::core::{Card, CardSet, Hand, PlayerBitSet, rng};
 
+use super::action::AgentAction;
 use super::errors::GameStateError;
+use super::variant::GameVariant;
 
 /// The round of the game.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Round {
     #[default]
     Starting,
@@ -30,6 +36,20 @@ pub enum Round {
 
     Showdown,
     Complete,
+
+    /// The streets of seven card stud, each dealt and then bet. They come
+    /// after the hold'em rounds to keep the encoding of those the same.
+    ThirdStreet,
+    FourthStreet,
+    FifthStreet,
+    SixthStreet,
+    SeventhStreet,
+
+    /// The draws of a draw game, each followed by betting. The betting
+    /// before the first draw is `Preflop`.
+    FirstDraw,
+    SecondDraw,
+    ThirdDraw,
 }
 
 impl Display for Round {
@@ -53,6 +73,16 @@ impl Display for Round {
 
             Round::Showdown => write!(f, "Showdown"),
             Round::Complete => write!(f, "Complete"),
+
+            Round::ThirdStreet => write!(f, "Third Street"),
+            Round::FourthStreet => write!(f, "Fourth Street"),
+            Round::FifthStreet => write!(f, "Fifth Street"),
+            Round::SixthStreet => write!(f, "Sixth Street"),
+            Round::SeventhStreet => write!(f, "Seventh Street"),
+
+            Round::FirstDraw => write!(f, "First Draw"),
+            Round::SecondDraw => write!(f, "Second Draw"),
+            Round::ThirdDraw => write!(f, "Third Draw"),
         }
     }
 }
@@ -73,11 +103,135 @@ impl Round {
             Round::Showdown => Round::Complete,
 
             Round::Complete => Round::Complete,
+
+            Round::ThirdStreet => Round::FourthStreet,
+            Round::FourthStreet => Round::FifthStreet,
+            Round::FifthStreet => Round::SixthStreet,
+            Round::SixthStreet => Round::SeventhStreet,
+            Round::SeventhStreet => Round::Showdown,
+
+            Round::FirstDraw => Round::SecondDraw,
+            Round::SecondDraw => Round::ThirdDraw,
+            Round::ThirdDraw => Round::Showdown,
         }
     }
+
+    /// Is this a round where players bet, rather than one where cards are
+    /// dealt or the hand is settled.
+    pub fn is_betting(&self) -> bool {
+        matches!(
+            self,
+            Round::Preflop
+                | Round::Flop
+                | Round::Turn
+                | Round::River
+                | Round::ThirdStreet
+                | Round::FourthStreet
+                | Round::FifthStreet
+                | Round::SixthStreet
+                | Round::SeventhStreet
+                | Round::FirstDraw
+                | Round::SecondDraw
+                | Round::ThirdDraw
+        )
+    }
 }
 
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+/// A compact encoding of the round. The hold'em rounds are in the order
+/// they're played, followed by the stud streets and then the draws.
+impl From<Round> for u8 {
+    fn from(round: Round) -> Self {
+        round as u8
+    }
+}
+
+impl TryFrom<u8> for Round {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Round::Starting),
+            1 => Ok(Round::Ante),
+            2 => Ok(Round::DealPreflop),
+            3 => Ok(Round::Preflop),
+            4 => Ok(Round::DealFlop),
+            5 => Ok(Round::Flop),
+            6 => Ok(Round::DealTurn),
+            7 => Ok(Round::Turn),
+            8 => Ok(Round::DealRiver),
+            9 => Ok(Round::River),
+            10 => Ok(Round::Showdown),
+            11 => Ok(Round::Complete),
+            12 => Ok(Round::ThirdStreet),
+            13 => Ok(Round::FourthStreet),
+            14 => Ok(Round::FifthStreet),
+            15 => Ok(Round::SixthStreet),
+            16 => Ok(Round::SeventhStreet),
+            17 => Ok(Round::FirstDraw),
+            18 => Ok(Round::SecondDraw),
+            19 => Ok(Round::ThirdDraw),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Where a seat sits relative to the button for a hand.
+///
+/// The first player to act before the flop is always under the gun. The
+/// cutoff, hijack and lojack are taken from the seats right of the button
+/// before any more early positions are named. Heads up the button posts
+/// the small blind, so the two seats are `Button` and `BigBlind`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Position {
+    /// First to act before the flop.
+    UnderTheGun,
+    /// This many seats left of under the gun.
+    UnderTheGunPlus(u8),
+    Lojack,
+    Hijack,
+    Cutoff,
+    Button,
+    SmallBlind,
+    BigBlind,
+}
+
+impl Position {
+    /// The position of the seat `offset` places into the `len` seats
+    /// between the big blind and the button.
+    fn middle(offset: usize, len: usize) -> Self {
+        if offset == 0 {
+            return Position::UnderTheGun;
+        }
+        match len - 1 - offset {
+            0 => Position::Cutoff,
+            1 => Position::Hijack,
+            2 => Position::Lojack,
+            _ => Position::UnderTheGunPlus(offset as u8),
+        }
+    }
+
+    pub fn is_blind(&self) -> bool {
+        matches!(self, Position::SmallBlind | Position::BigBlind)
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Position::UnderTheGun => write!(f, "UTG"),
+            Position::UnderTheGunPlus(offset) => write!(f, "UTG+{offset}"),
+            Position::Lojack => write!(f, "LJ"),
+            Position::Hijack => write!(f, "HJ"),
+            Position::Cutoff => write!(f, "CO"),
+            Position::Button => write!(f, "BTN"),
+            Position::SmallBlind => write!(f, "SB"),
+            Position::BigBlind => write!(f, "BB"),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct RoundData {
     // Which players were active starting this round.
     pub starting_player_active: PlayerBitSet,
@@ -96,6 +250,34 @@ pub struct RoundData {
     pub to_act_idx: usize,
 }
 
+// Clone is written out so that `clone_from` reuses the vectors that are
+// already allocated, which `GameStatePool` relies on.
+impl Clone for RoundData {
+    fn clone(&self) -> Self {
+        RoundData {
+            starting_player_active: self.starting_player_active,
+            needs_action: self.needs_action,
+            min_raise: self.min_raise,
+            bet: self.bet,
+            player_bet: self.player_bet.clone(),
+            total_bet_count: self.total_bet_count,
+            total_raise_count: self.total_raise_count,
+            to_act_idx: self.to_act_idx,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.starting_player_active = source.starting_player_active;
+        self.needs_action = source.needs_action;
+        self.min_raise = source.min_raise;
+        self.bet = source.bet;
+        self.player_bet.clone_from(&source.player_bet);
+        self.total_bet_count = source.total_bet_count;
+        self.total_raise_count = source.total_raise_count;
+        self.to_act_idx = source.to_act_idx;
+    }
+}
+
 impl RoundData {
     pub fn new(num_players: usize, min_raise: f32, active: PlayerBitSet, to_act: usize) -> Self {
         RoundData {
@@ -212,7 +394,70 @@ impl RoundData {
     }
 }
 
+/// The community cards. There are never more than five so they're kept
+/// inline rather than on the heap.
+pub type Board = SmallVec<[Card; 5]>;
+
+/// The betting on one street, kept for the whole hand so agents can see
+/// what happened earlier without replaying the actions.
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct StreetSummary {
+    pub round: Round,
+    /// How many times a player chose to make the bet bigger, the first bet
+    /// of the street included. Checks, calls and blinds don't count.
+    pub num_bets: u8,
+    /// The last player to bet or raise.
+    pub last_aggressor: Option<usize>,
+    /// How much each seat put in on this street, blinds included.
+    pub player_bet: Vec<f32>,
+}
+
+impl StreetSummary {
+    fn new(round: Round, num_players: usize) -> Self {
+        StreetSummary {
+            round,
+            num_bets: 0,
+            last_aggressor: None,
+            player_bet: vec![0.0; num_players],
+        }
+    }
+
+    /// How many bets the street got to. Before the flop the big blind is
+    /// the first, so an open raise is the 2-bet and a re-raise of it the
+    /// 3-bet. After the flop the first bet is the 1-bet.
+    ///
+    /// ```
+    /// use rs_poker::arena::GameState;
+    /// use rs_poker::arena::game_state::Round;
+    ///
+    /// let mut game_state = GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.0, 0);
+    /// game_state.advance_round(); // Ante
+    /// game_state.advance_round(); // Deal preflop
+    /// game_state.advance_round(); // Preflop
+    /// game_state.do_bet(1.0, true).unwrap();
+    /// game_state.do_bet(2.0, true).unwrap();
+    /// game_state.do_bet(6.0, false).unwrap(); // open
+    /// game_state.do_bet(18.0, false).unwrap(); // 3-bet
+    ///
+    /// let preflop = game_state.street(Round::Preflop).unwrap();
+    /// assert_eq!(3, preflop.bet_level());
+    /// assert_eq!(Some(1), preflop.last_aggressor);
+    /// assert_eq!(18.0, preflop.player_bet[1]);
+    /// ```
+    pub fn bet_level(&self) -> u8 {
+        match self.round {
+            Round::Preflop => self.num_bets + 1,
+            _ => self.num_bets,
+        }
+    }
+
+    /// Everything put in on this street.
+    pub fn total_bet(&self) -> f32 {
+        self.player_bet.iter().sum()
+    }
+}
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct GameState {
     /// The number of players that started
     pub num_players: usize,
@@ -224,7 +469,8 @@ pub struct GameState {
     /// How much is left in each player's stack
     pub stacks: Vec<f32>,
     // The amount at the start of the game (or creation of the gamestate).
-    pub starting_stacks: Vec<f32>,
+    // Shared between clones since it never changes during a hand.
+    pub starting_stacks: Arc<Vec<f32>>,
     pub player_bet: Vec<f32>,
     pub player_winnings: Vec<f32>,
     /// The big blind size
@@ -235,7 +481,9 @@ pub struct GameState {
     pub ante: f32,
     /// The hands for each player. We keep hands
     /// even if the player is not currently active.
-    pub hands: Vec<Hand>,
+    ///
+    /// Shared between clones until cards are dealt, see `hands_mut`.
+    pub hands: Arc<Vec<Hand>>,
     /// The index of the player who's the dealer
     pub dealer_idx: usize,
     // What round this is currently
@@ -246,13 +494,89 @@ pub struct GameState {
     pub round_before: Round,
     // ALl the current state of the round.
     pub round_data: RoundData,
-    // The community cards.
-    pub board: Vec<Card>,
+    // The community cards. Shared between clones until cards are
+    // dealt, see `board_mut`.
+    pub board: Arc<Board>,
     // Have the blinds been posted.
     // This is used to not double post blinds
     // on sim restarts.
     pub bb_posted: bool,
     pub sb_posted: bool,
+    /// The betting on each street so far, in order. A street where no one
+    /// acted, because everyone left was all in, isn't here.
+    #[serde(default)]
+    pub streets: Vec<StreetSummary>,
+    /// The cards each player has face up for everyone to see, in stud. They
+    /// are in `hands` too. Empty in hold'em, where no player's cards are up.
+    #[serde(default)]
+    pub up_cards: Arc<Vec<Hand>>,
+    /// The game being played, set by the simulation. Hold'em unless the
+    /// simulation was built for another variant.
+    #[serde(default)]
+    pub variant: GameVariant,
+    /// The cards the hand is dealt from. The whole deck unless it's been
+    /// cut down, which makes a game small enough to solve exactly.
+    #[serde(default)]
+    pub deck: CardSet,
+}
+
+// The board, hands and starting stacks are shared between clones, so a
+// branch of a tree search only copies the betting state. Dealing cards
+// through `hands_mut` or `board_mut` copies them for that branch alone.
+impl Clone for GameState {
+    fn clone(&self) -> Self {
+        GameState {
+            num_players: self.num_players,
+            player_active: self.player_active,
+            player_all_in: self.player_all_in,
+            total_pot: self.total_pot,
+            stacks: self.stacks.clone(),
+            starting_stacks: Arc::clone(&self.starting_stacks),
+            player_bet: self.player_bet.clone(),
+            player_winnings: self.player_winnings.clone(),
+            big_blind: self.big_blind,
+            small_blind: self.small_blind,
+            ante: self.ante,
+            hands: Arc::clone(&self.hands),
+            dealer_idx: self.dealer_idx,
+            round: self.round,
+            round_before: self.round_before,
+            round_data: self.round_data.clone(),
+            board: Arc::clone(&self.board),
+            bb_posted: self.bb_posted,
+            sb_posted: self.sb_posted,
+            streets: self.streets.clone(),
+            up_cards: Arc::clone(&self.up_cards),
+            variant: self.variant,
+            deck: self.deck,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.num_players = source.num_players;
+        self.player_active = source.player_active;
+        self.player_all_in = source.player_all_in;
+        self.total_pot = source.total_pot;
+        self.stacks.clone_from(&source.stacks);
+        self.starting_stacks.clone_from(&source.starting_stacks);
+        self.player_bet.clone_from(&source.player_bet);
+        self.player_winnings.clone_from(&source.player_winnings);
+        self.big_blind = source.big_blind;
+        self.small_blind = source.small_blind;
+        self.ante = source.ante;
+        self.hands.clone_from(&source.hands);
+        self.dealer_idx = source.dealer_idx;
+        self.round = source.round;
+        self.round_before = source.round_before;
+        self.round_data.clone_from(&source.round_data);
+        self.board.clone_from(&source.board);
+        self.bb_posted = source.bb_posted;
+        self.sb_posted = source.sb_posted;
+        self.streets.clone_from(&source.streets);
+        self.up_cards.clone_from(&source.up_cards);
+        self.variant = source.variant;
+        self.deck = source.deck;
+    }
 }
 
 impl GameState {
@@ -300,7 +624,7 @@ impl GameState {
 
         GameState {
             num_players,
-            starting_stacks: stacks.clone(),
+            starting_stacks: Arc::new(stacks.clone()),
             stacks,
             big_blind,
             small_blind,
@@ -311,15 +635,19 @@ impl GameState {
             player_winnings: vec![0.0; num_players],
             dealer_idx,
             total_pot,
-            hands,
+            hands: Arc::new(hands),
             round,
             round_before: round,
             round_data,
-            board,
+            board: Arc::new(Board::from_vec(board)),
             // Assume that the blinds have not been posted
             // if the game is just starting.
             bb_posted: round != Round::Starting,
             sb_posted: round != Round::Starting,
+            streets: vec![],
+            up_cards: Arc::default(),
+            variant: GameVariant::default(),
+            deck: CardSet::default(),
         }
     }
 
@@ -357,6 +685,50 @@ impl GameState {
         )
     }
 
+    /// Mutable access to the hands. If the hands are shared with another
+    /// clone they're copied first, so only this game state sees the change.
+    pub fn hands_mut(&mut self) -> &mut Vec<Hand> {
+        Arc::make_mut(&mut self.hands)
+    }
+
+    /// Mutable access to the face up cards, copying them first if they're
+    /// shared with another clone. Empty until the first card is dealt face up.
+    pub fn up_cards_mut(&mut self) -> &mut Vec<Hand> {
+        let num_players = self.num_players;
+        let up_cards = Arc::make_mut(&mut self.up_cards);
+        if up_cards.is_empty() {
+            up_cards.resize(num_players, Hand::default());
+        }
+        up_cards
+    }
+
+    /// A player's face up cards, empty if they have none.
+    pub fn player_up_cards(&self, idx: usize) -> Hand {
+        self.up_cards.get(idx).copied().unwrap_or_default()
+    }
+
+    /// Mutable access to the board, copying it first if it's shared with
+    /// another clone.
+    pub fn board_mut(&mut self) -> &mut Board {
+        Arc::make_mut(&mut self.board)
+    }
+
+    /// The community cards as a set.
+    pub fn board_set(&self) -> CardSet {
+        CardSet::from(&self.board[..])
+    }
+
+    /// Every card that's been dealt, to the board or to any player
+    /// including those that folded, and every card left out of the `deck`.
+    /// None of them can come out of the deck.
+    pub fn dead_cards(&self) -> CardSet {
+        self.hands
+            .iter()
+            .fold(self.board_set() | !self.deck, |dead, hand| {
+                dead | CardSet::from(*hand)
+            })
+    }
+
     pub fn num_active_players(&self) -> usize {
         self.player_active.count()
     }
@@ -365,6 +737,77 @@ impl GameState {
         self.player_all_in.count()
     }
 
+    /// Every seat dealt into the hand with its position, in the order they
+    /// act before the flop. Seats that started the hand without chips sit
+    /// out and have no position.
+    pub fn positions(&self) -> Vec<(usize, Position)> {
+        let n = self.num_players;
+        // Clockwise from the dealer's left, ending with the dealer.
+        let mut seated: Vec<usize> = (1..=n)
+            .map(|offset| (self.dealer_idx + offset) % n)
+            .filter(|idx| self.starting_stacks[*idx] > 0.0)
+            .collect();
+        if seated.len() < 2 {
+            return vec![];
+        }
+        if n == 2 {
+            return vec![
+                (seated[1], Position::Button),
+                (seated[0], Position::BigBlind),
+            ];
+        }
+
+        let blinds = [
+            (seated[0], Position::SmallBlind),
+            (seated[1], Position::BigBlind),
+        ];
+        let mut rest = seated.split_off(2);
+        let button = (rest.last() == Some(&self.dealer_idx))
+            .then(|| rest.pop())
+            .flatten();
+        let len = rest.len();
+        rest.into_iter()
+            .enumerate()
+            .map(|(offset, idx)| (idx, Position::middle(offset, len)))
+            .chain(button.map(|idx| (idx, Position::Button)))
+            .chain(blinds)
+            .collect()
+    }
+
+    /// The position of `seat` this hand, if it was dealt in.
+    pub fn position_of(&self, seat: usize) -> Option<Position> {
+        self.positions()
+            .into_iter()
+            .find(|(idx, _)| *idx == seat)
+            .map(|(_, position)| position)
+    }
+
+    /// The seat in `position` this hand, if there is one.
+    pub fn seat_of(&self, position: Position) -> Option<usize> {
+        self.positions()
+            .into_iter()
+            .find(|(_, p)| *p == position)
+            .map(|(idx, _)| idx)
+    }
+
+    /// How many of the players still in the hand act after `seat` from the
+    /// flop on. Zero means `seat` is last to act, in position on everyone.
+    /// `None` if `seat` isn't in the hand.
+    pub fn relative_position(&self, seat: usize) -> Option<usize> {
+        let in_hand = self.player_active | self.player_all_in;
+        if !in_hand.get(seat) {
+            return None;
+        }
+        let n = self.num_players;
+        let offset = |idx: usize| (idx + n - self.dealer_idx - 1) % n;
+        Some(
+            in_hand
+                .ones()
+                .filter(|idx| offset(*idx) > offset(seat))
+                .count(),
+        )
+    }
+
     pub fn is_complete(&self) -> bool {
         self.num_active_players() == 1 || self.round == Round::Complete
     }
@@ -405,6 +848,24 @@ impl GameState {
         self.round_data.min_raise
     }
 
+    /// What the player to act is allowed to do. This doesn't allocate, so
+    /// it's cheap enough to call at every node of a tree search.
+    pub fn legal_actions(&self) -> LegalActions {
+        let bet = self.current_round_bet();
+        let all_in = self.current_round_current_player_bet() + self.current_player_stack();
+        let raise = if all_in > bet {
+            // A raise has to be at least the min raise unless it's all in.
+            Some(((bet + self.current_round_min_raise()).min(all_in), all_in))
+        } else {
+            None
+        };
+        LegalActions {
+            can_fold: bet > self.current_round_current_player_bet(),
+            call: bet,
+            raise,
+        }
+    }
+
     pub fn advance_round(&mut self) {
         match self.round {
             Round::Complete => (),
@@ -413,11 +874,17 @@ impl GameState {
     }
 
     fn advance_normal(&mut self) {
+        self.advance_to_round(self.round.advance());
+    }
+
+    /// Move on to `round` with fresh betting, for games like stud where the
+    /// round after the antes isn't hold'em's.
+    pub fn advance_to_round(&mut self, round: Round) {
         // We're advancing (not completing) so
         // keep advanding the round_before field as well.
         self.round_before = self.round;
 
-        self.round = self.round.advance();
+        self.round = round;
 
         let mut round_data = RoundData::new(
             self.num_players,
@@ -492,6 +959,7 @@ impl GameState {
         self.total_pot += extra_amount;
 
         let is_betting_reopened = prev_bet < self.round_data.bet;
+        self.record_street(idx, extra_amount, is_forced, is_betting_reopened);
 
         if is_betting_reopened {
             // This is a new max bet. We need to reset who can act in the round
@@ -521,6 +989,27 @@ impl GameState {
         Ok(extra_amount)
     }
 
+    /// The betting on `round`, if anyone has acted on it.
+    pub fn street(&self, round: Round) -> Option<&StreetSummary> {
+        self.streets.iter().find(|street| street.round == round)
+    }
+
+    fn record_street(&mut self, idx: usize, extra_amount: f32, is_forced: bool, raised: bool) {
+        if !self.round.is_betting() {
+            return;
+        }
+        if self.streets.last().is_none_or(|s| s.round != self.round) {
+            self.streets
+                .push(StreetSummary::new(self.round, self.num_players));
+        }
+        let street = self.streets.last_mut().expect("just pushed");
+        street.player_bet[idx] += extra_amount;
+        if raised && !is_forced {
+            street.num_bets += 1;
+            street.last_aggressor = Some(idx);
+        }
+    }
+
     pub fn award(&mut self, player_idx: usize, amount: f32) {
         self.stacks[player_idx] += amount;
         self.player_winnings[player_idx] += amount;
@@ -583,6 +1072,173 @@ impl GameState {
     }
 }
 
+/// The actions open to the player to act, from `GameState::legal_actions`.
+///
+/// Bets are totals for the round, the same as `AgentAction::Bet`. This
+/// holds no vectors, and `write_to` fills a buffer the caller owns, so
+/// solvers and agents can enumerate actions in their inner loops without
+/// allocating.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::GameState;
+/// use rs_poker::arena::action::AgentAction;
+/// use rs_poker::arena::game_state::{LegalActions, MAX_LEGAL_ACTIONS};
+///
+/// let mut game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+/// game_state.advance_round();
+/// game_state.advance_round();
+/// game_state.do_bet(20.0, false).unwrap();
+///
+/// let legal = game_state.legal_actions();
+/// assert_eq!(Some((40.0, 100.0)), legal.raise);
+///
+/// let mut buf = [const { AgentAction::Fold }; MAX_LEGAL_ACTIONS];
+/// let actions = legal.write_to(&mut buf);
+/// assert_eq!(
+///     &[
+///         AgentAction::Fold,
+///         AgentAction::Bet(20.0),
+///         AgentAction::Bet(40.0),
+///         AgentAction::AllIn,
+///     ],
+///     actions
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LegalActions {
+    /// Folding is only offered when there's a bet to call.
+    pub can_fold: bool,
+    /// The bet that calls, or checks when nothing has been bet. If it's
+    /// more than the player has the bet is capped and they're all in.
+    pub call: f32,
+    /// The smallest and largest bets that raise, if the player has
+    /// more than it takes to call. The largest is all in, and so is the
+    /// smallest when the player can't make a full raise.
+    pub raise: Option<(f32, f32)>,
+}
+
+/// The most actions `LegalActions::write_to` will write.
+pub const MAX_LEGAL_ACTIONS: usize = 4;
+
+impl LegalActions {
+    /// Write fold, call, the smallest raise and all in, whichever of them
+    /// are legal, into `buf` and return the part that was written.
+    pub fn write_to<'a>(&self, buf: &'a mut [AgentAction; MAX_LEGAL_ACTIONS]) -> &'a [AgentAction] {
+        self.write(buf, true)
+    }
+
+    /// Write fold, call and all in, whichever of them are legal, into `buf`
+    /// and return the part that was written. This is `write_to` without the
+    /// smallest raise, for callers that only raise all in.
+    pub fn write_without_min_raise_to<'a>(
+        &self,
+        buf: &'a mut [AgentAction; MAX_LEGAL_ACTIONS],
+    ) -> &'a [AgentAction] {
+        self.write(buf, false)
+    }
+
+    fn write<'a>(
+        &self,
+        buf: &'a mut [AgentAction; MAX_LEGAL_ACTIONS],
+        min_raise: bool,
+    ) -> &'a [AgentAction] {
+        let mut len = 0;
+        let mut push = |action| {
+            buf[len] = action;
+            len += 1;
+        };
+        if self.can_fold {
+            push(AgentAction::Fold);
+        }
+        push(AgentAction::Bet(self.call));
+        if let Some((min, max)) = self.raise {
+            if min_raise && min < max {
+                push(AgentAction::Bet(min));
+            }
+            push(AgentAction::AllIn);
+        }
+        &buf[..len]
+    }
+
+    /// Is `bet`, a total for the round, one that the player can make?
+    pub fn is_legal_bet(&self, bet: f32) -> bool {
+        bet == self.call
+            || self
+                .raise
+                .is_some_and(|(min, max)| (min..=max).contains(&bet))
+    }
+}
+
+/// Recycles game states so that copying one doesn't allocate.
+///
+/// Rollouts copy the game state they start from many times, and every copy
+/// allocates a handful of per-player vectors. A state that's no longer
+/// needed can be handed back with `recycle`, and the next `copy_of` copies
+/// into its vectors instead of allocating new ones. The pool holds at most
+/// `capacity` states.
+///
+/// The pool isn't `Sync`. `CFRAgent` keeps one per thread for its
+/// rollouts.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::{GameState, GameStatePool};
+///
+/// let pool = GameStatePool::new(8);
+/// let game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0);
+///
+/// let copy = pool.copy_of(&game_state);
+/// assert_eq!(game_state, copy);
+/// pool.recycle(copy);
+/// assert_eq!(1, pool.len());
+/// ```
+#[derive(Debug)]
+pub struct GameStatePool {
+    free: RefCell<Vec<GameState>>,
+    capacity: usize,
+}
+
+impl GameStatePool {
+    pub fn new(capacity: usize) -> Self {
+        GameStatePool {
+            free: RefCell::new(Vec::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// A copy of `game_state`, made in a recycled state if there is one.
+    pub fn copy_of(&self, game_state: &GameState) -> GameState {
+        match self.free.borrow_mut().pop() {
+            Some(mut recycled) => {
+                recycled.clone_from(game_state);
+                recycled
+            }
+            None => game_state.clone(),
+        }
+    }
+
+    /// Hand back a state that's no longer needed. It's dropped if the pool
+    /// is full.
+    pub fn recycle(&self, game_state: GameState) {
+        let mut free = self.free.borrow_mut();
+        if free.len() < self.capacity {
+            free.push(game_state);
+        }
+    }
+
+    /// The number of states waiting to be reused.
+    pub fn len(&self) -> usize {
+        self.free.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 pub trait GameStateGenerator: Iterator<Item = GameState> {}
 
 /// This is a simple generator that just clones the game state
@@ -844,4 +1500,291 @@ mod tests {
 
         assert_eq!(round_data.total_raise_count, 2);
     }
+
+    #[test]
+    fn test_round_u8_round_trip() {
+        let mut round = Round::Starting;
+        loop {
+            assert_eq!(Ok(round), Round::try_from(u8::from(round)));
+            if round == Round::Complete {
+                break;
+            }
+            round = round.advance();
+        }
+        assert_eq!(11, u8::from(Round::Complete));
+
+        let mut round = Round::ThirdStreet;
+        while round != Round::Showdown {
+            assert_eq!(Ok(round), Round::try_from(u8::from(round)));
+            round = round.advance();
+        }
+        assert_eq!(16, u8::from(Round::SeventhStreet));
+
+        let mut round = Round::FirstDraw;
+        while round != Round::Showdown {
+            assert_eq!(Ok(round), Round::try_from(u8::from(round)));
+            round = round.advance();
+        }
+        assert_eq!(19, u8::from(Round::ThirdDraw));
+        assert_eq!(Err(20), Round::try_from(20));
+    }
+
+    #[test]
+    fn test_legal_actions() {
+        let mut game_state = GameState::new_starting(vec![100.0, 30.0], 10.0, 5.0, 0.0, 0);
+        game_state.advance_round();
+        game_state.advance_round();
+        game_state.advance_round();
+
+        // Nothing bet yet, so check or bet from the big blind up.
+        let legal = game_state.legal_actions();
+        assert!(!legal.can_fold);
+        assert_eq!(0.0, legal.call);
+        assert_eq!(Some((10.0, 100.0)), legal.raise);
+        assert!(legal.is_legal_bet(0.0));
+        assert!(legal.is_legal_bet(55.0));
+        assert!(!legal.is_legal_bet(5.0));
+        assert!(!legal.is_legal_bet(150.0));
+
+        // Facing a bet the short stack can only make part of a raise,
+        // which is all in.
+        game_state.do_bet(25.0, false).unwrap();
+        let legal = game_state.legal_actions();
+        assert!(legal.can_fold);
+        assert_eq!(25.0, legal.call);
+        assert_eq!(Some((30.0, 30.0)), legal.raise);
+        let mut buf = [const { AgentAction::Fold }; MAX_LEGAL_ACTIONS];
+        assert_eq!(
+            &[
+                AgentAction::Fold,
+                AgentAction::Bet(25.0),
+                AgentAction::AllIn
+            ],
+            legal.write_to(&mut buf)
+        );
+
+        // Facing the all in, the raise is still sized off the full raise
+        // before it.
+        game_state.do_bet(30.0, false).unwrap();
+        let legal = game_state.legal_actions();
+        assert_eq!(30.0, legal.call);
+        assert_eq!(Some((55.0, 100.0)), legal.raise);
+        assert_eq!(
+            &[
+                AgentAction::Fold,
+                AgentAction::Bet(30.0),
+                AgentAction::Bet(55.0),
+                AgentAction::AllIn
+            ],
+            legal.write_to(&mut buf)
+        );
+        assert_eq!(
+            &[
+                AgentAction::Fold,
+                AgentAction::Bet(30.0),
+                AgentAction::AllIn
+            ],
+            legal.write_without_min_raise_to(&mut buf)
+        );
+    }
+
+    #[test]
+    fn test_pool_reuses_vectors() {
+        let pool = GameStatePool::new(1);
+        let mut played = GameState::new_starting(vec![100.0; 4], 10.0, 5.0, 0.0, 1);
+        played.advance_round();
+        played
+            .board_mut()
+            .push(Card::new(crate::core::Value::Ace, crate::core::Suit::Heart));
+        let stacks = played.stacks.as_ptr();
+        let bets = played.round_data.player_bet.as_ptr();
+        pool.recycle(played);
+        pool.recycle(GameState::new_starting(vec![100.0; 4], 10.0, 5.0, 0.0, 1));
+        assert_eq!(1, pool.len());
+
+        let game_state = GameState::new_starting(vec![50.0, 60.0, 70.0], 10.0, 5.0, 1.0, 2);
+        let copy = pool.copy_of(&game_state);
+        assert_eq!(game_state, copy);
+        assert_eq!(stacks, copy.stacks.as_ptr());
+        assert_eq!(bets, copy.round_data.player_bet.as_ptr());
+        assert!(pool.is_empty());
+
+        // Without anything to recycle it's a plain clone.
+        assert_eq!(game_state, pool.copy_of(&game_state));
+    }
+
+    #[test]
+    fn test_clone_shares_cards_until_dealt() {
+        use crate::core::{Suit, Value};
+
+        let mut game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0);
+        game_state
+            .board_mut()
+            .push(Card::new(Value::Ace, Suit::Heart));
+        let mut branch = game_state.clone();
+        assert!(Arc::ptr_eq(&game_state.board, &branch.board));
+        assert!(Arc::ptr_eq(&game_state.hands, &branch.hands));
+        assert!(Arc::ptr_eq(
+            &game_state.starting_stacks,
+            &branch.starting_stacks
+        ));
+
+        // Betting state is copied right away.
+        branch.do_bet(10.0, false).unwrap();
+        assert_ne!(game_state.stacks, branch.stacks);
+
+        // Dealing to the branch copies its cards, leaving the original alone.
+        branch.board_mut().push(Card::new(Value::King, Suit::Heart));
+        branch.hands_mut()[0].insert(Card::new(Value::Two, Suit::Club));
+        assert!(!Arc::ptr_eq(&game_state.board, &branch.board));
+        assert_eq!(1, game_state.board.len());
+        assert_eq!(2, branch.board.len());
+        // The board is small enough to never leave the stack.
+        assert!(!branch.board.spilled());
+        assert_eq!(0, game_state.hands[0].count());
+        assert_eq!(1, branch.hands[0].count());
+    }
+
+    #[test]
+    fn test_positions() {
+        use Position::*;
+
+        let names = |num_players: usize| -> Vec<String> {
+            GameState::new_starting(vec![100.0; num_players], 10.0, 5.0, 0.0, 0)
+                .positions()
+                .iter()
+                .map(|(_, position)| position.to_string())
+                .collect()
+        };
+        assert_eq!(vec!["BTN", "BB"], names(2));
+        assert_eq!(vec!["BTN", "SB", "BB"], names(3));
+        assert_eq!(vec!["UTG", "HJ", "CO", "BTN", "SB", "BB"], names(6));
+        assert_eq!(
+            vec!["UTG", "UTG+1", "UTG+2", "LJ", "HJ", "CO", "BTN", "SB", "BB"],
+            names(9)
+        );
+
+        let game_state = GameState::new_starting(vec![100.0; 6], 10.0, 5.0, 0.0, 4);
+        assert_eq!(Some(Button), game_state.position_of(4));
+        assert_eq!(Some(SmallBlind), game_state.position_of(5));
+        assert_eq!(Some(BigBlind), game_state.position_of(0));
+        assert_eq!(Some(1), game_state.seat_of(UnderTheGun));
+        assert_eq!(None, game_state.seat_of(Lojack));
+
+        // Seats without chips are skipped.
+        let game_state = GameState::new_starting(vec![100.0, 0.0, 100.0, 100.0], 10.0, 5.0, 0.0, 0);
+        assert_eq!(None, game_state.position_of(1));
+        assert_eq!(Some(SmallBlind), game_state.position_of(2));
+        assert_eq!(Some(Button), game_state.position_of(0));
+    }
+
+    #[test]
+    fn test_positions_match_simulation() {
+        use crate::arena::HoldemSimulationBuilder;
+        use crate::arena::action::{Action, ForcedBetType};
+        use crate::arena::agent::CallingAgent;
+        use crate::arena::historian::VecHistorian;
+
+        for num_players in 2..=9 {
+            for dealer_idx in 0..num_players {
+                let mut stacks = vec![100.0; num_players];
+                if num_players > 3 {
+                    stacks[(dealer_idx + 2) % num_players] = 0.0;
+                }
+                let game_state = GameState::new_starting(stacks, 10.0, 5.0, 0.0, dealer_idx);
+                let historian = VecHistorian::default();
+                let records = historian.get_storage();
+                let agents: Vec<Box<dyn crate::arena::Agent>> = (0..num_players)
+                    .map(|_| Box::<CallingAgent>::default() as Box<dyn crate::arena::Agent>)
+                    .collect();
+                let mut sim = HoldemSimulationBuilder::default()
+                    .game_state(game_state.clone())
+                    .agents(agents)
+                    .historians(vec![Box::new(historian)])
+                    .build()
+                    .unwrap();
+                sim.run(&mut rand::rng());
+
+                let records = records.borrow();
+                let mut preflop = records.iter().filter_map(|record| match &record.action {
+                    Action::ForcedBet(payload) => {
+                        let position = match payload.forced_bet_type {
+                            // Heads up the button posts the small blind.
+                            ForcedBetType::SmallBlind if num_players == 2 => Position::Button,
+                            ForcedBetType::SmallBlind => Position::SmallBlind,
+                            _ => Position::BigBlind,
+                        };
+                        Some((payload.idx, position))
+                    }
+                    Action::PlayedAction(payload) if payload.round == Round::Preflop => {
+                        Some((payload.idx, game_state.position_of(payload.idx).unwrap()))
+                    }
+                    _ => None,
+                });
+                let positions = game_state.positions();
+                // The blinds come first, then everyone acts in order.
+                let mut expected: Vec<_> = positions.iter().rev().take(2).rev().copied().collect();
+                expected.extend(positions.iter().take(positions.len() - 2).copied());
+                for position in expected {
+                    assert_eq!(Some(position), preflop.next());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_street_summaries() {
+        let mut game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 1.0, 0);
+        game_state.advance_round(); // Ante
+        for _ in 0..3 {
+            game_state.do_bet(1.0, true).unwrap();
+        }
+        game_state.advance_round(); // Deal preflop
+        game_state.advance_round(); // Preflop
+        game_state.do_bet(5.0, true).unwrap();
+        game_state.do_bet(10.0, true).unwrap();
+        game_state.do_bet(10.0, false).unwrap(); // idx 0 limps
+        game_state.do_bet(10.0, false).unwrap(); // idx 1 completes
+        game_state.do_bet(10.0, false).unwrap(); // idx 2 checks
+        game_state.advance_round(); // Deal flop
+        game_state.advance_round(); // Flop
+        game_state.do_bet(0.0, false).unwrap(); // idx 1 checks
+        game_state.do_bet(20.0, false).unwrap(); // idx 2 bets
+        game_state.do_bet(60.0, false).unwrap(); // idx 0 raises
+        game_state.fold(); // idx 1
+        game_state.do_bet(60.0, false).unwrap(); // idx 2 calls
+
+        // Antes aren't a street.
+        let rounds: Vec<Round> = game_state.streets.iter().map(|s| s.round).collect();
+        assert_eq!(vec![Round::Preflop, Round::Flop], rounds);
+
+        let preflop = game_state.street(Round::Preflop).unwrap();
+        assert_eq!(0, preflop.num_bets);
+        assert_eq!(1, preflop.bet_level());
+        assert_eq!(None, preflop.last_aggressor);
+        assert_eq!(30.0, preflop.total_bet());
+
+        let flop = game_state.street(Round::Flop).unwrap();
+        assert_eq!(2, flop.bet_level());
+        assert_eq!(Some(0), flop.last_aggressor);
+        assert_eq!(vec![60.0, 0.0, 60.0], flop.player_bet);
+        assert_eq!(None, game_state.street(Round::Turn));
+
+        let mut copy = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 1.0, 0);
+        copy.clone_from(&game_state);
+        assert_eq!(game_state.streets, copy.streets);
+    }
+
+    #[test]
+    fn test_relative_position() {
+        let mut game_state = GameState::new_starting(vec![100.0; 4], 10.0, 5.0, 0.0, 1);
+        // The button acts last after the flop and the small blind first.
+        assert_eq!(Some(0), game_state.relative_position(1));
+        assert_eq!(Some(3), game_state.relative_position(2));
+        assert_eq!(Some(1), game_state.relative_position(0));
+
+        game_state.player_active.disable(1);
+        assert_eq!(None, game_state.relative_position(1));
+        assert_eq!(Some(0), game_state.relative_position(0));
+    }
 }
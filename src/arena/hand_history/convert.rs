@@ -0,0 +1,214 @@
+use std::path::Path;
+
+use crate::arena::errors::HandHistoryError;
+
+use super::{
+    HandHistory, parse_phh_many, parse_pokerstars_many, write_phh, write_phh_many,
+    write_pokerstars_many,
+};
+
+/// The hand history formats that can be read and written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum HandHistoryFormat {
+    /// PokerStars style text hand histories.
+    #[cfg_attr(feature = "cli", value(name = "pokerstars"))]
+    PokerStars,
+    /// Poker Hand History files, `.phh` for one hand or `.phhs` for many.
+    Phh,
+    /// The crate's own [`HandHistory`] serialized as a JSON array.
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+impl HandHistoryFormat {
+    /// Guess the format from a file's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "txt" => Some(Self::PokerStars),
+            "phh" | "phhs" => Some(Self::Phh),
+            #[cfg(feature = "serde")]
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Read every hand in `text`.
+    pub fn parse(self, text: &str) -> Result<Vec<HandHistory>, HandHistoryError> {
+        match self {
+            Self::PokerStars => parse_pokerstars_many(text),
+            Self::Phh => parse_phh_many(text),
+            #[cfg(feature = "serde")]
+            Self::Json => {
+                // A single hand is accepted as well as an array of them.
+                let value: serde_json::Value = serde_json::from_str(text)?;
+                if value.is_array() {
+                    Ok(serde_json::from_value(value)?)
+                } else {
+                    Ok(vec![serde_json::from_value(value)?])
+                }
+            }
+        }
+    }
+
+    /// Write the hands in this format. A lone hand is written as a plain
+    /// `.phh` file rather than with a table header.
+    pub fn write(self, histories: &[HandHistory]) -> Result<String, HandHistoryError> {
+        match self {
+            Self::PokerStars => write_pokerstars_many(histories),
+            Self::Phh if histories.len() == 1 => write_phh(&histories[0]),
+            Self::Phh => write_phh_many(histories),
+            #[cfg(feature = "serde")]
+            Self::Json => Ok(serde_json::to_string_pretty(histories)?),
+        }
+    }
+}
+
+/// Convert hand histories from one format to another.
+///
+/// Hands are read into [`HandHistory`] and written back out, so whatever a
+/// format can't express is dropped: PHH has no button other than the last
+/// seat, so seats are rotated, and neither text format keeps seats that sat
+/// out. Writing replays every hand, so a hand that the arena can't play out
+/// is an error rather than a bad file.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::hand_history::{HandHistoryFormat, convert};
+///
+/// let phh = "variant = 'NT'
+/// blinds_or_straddles = [1, 2]
+/// starting_stacks = [100, 100]
+/// actions = ['d dh p1 AcAs', 'd dh p2 7h6h', 'p2 f']
+/// ";
+/// let text = convert(phh, HandHistoryFormat::Phh, HandHistoryFormat::PokerStars).unwrap();
+/// assert!(text.contains("p2: folds"));
+/// ```
+pub fn convert(
+    text: &str,
+    from: HandHistoryFormat,
+    to: HandHistoryFormat,
+) -> Result<String, HandHistoryError> {
+    to.write(&from.parse(text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::hand_history::replay_hand;
+
+    use super::*;
+
+    const POKERSTARS: &str =
+        "PokerStars Hand #2001: Hold'em No Limit ($0.50/$1.00 USD) - 2020/01/01 12:00:00 ET
+Table 'Alpha' 6-max Seat #3 is the button
+Seat 1: alice ($100 in chips)
+Seat 3: carol ($100 in chips)
+Seat 4: dave ($150 in chips)
+alice: posts the ante $0.10
+carol: posts the ante $0.10
+dave: posts the ante $0.10
+dave: posts small blind $0.50
+alice: posts big blind $1
+*** HOLE CARDS ***
+carol: raises $2 to $3
+dave: folds
+alice: calls $2
+*** FLOP *** [Kd Qs 3c]
+alice: checks
+carol: bets $4
+alice: raises $92.90 to $96.90 and is all-in
+carol: calls $92.90 and is all-in
+*** TURN *** [Kd Qs 3c] [9h]
+*** RIVER *** [Kd Qs 3c 9h] [4s]
+*** SHOW DOWN ***
+alice: shows [7c 2d]
+carol: shows [As Ah]
+*** SUMMARY ***
+";
+
+    /// What happened in the hand, ignoring how it was written down: every
+    /// bet, and each player's final stack by name.
+    fn outcome(history: &HandHistory) -> (Vec<Option<f32>>, Vec<(String, f32)>) {
+        let replayed = replay_hand(history).unwrap();
+        let bets = replayed
+            .decision_points
+            .iter()
+            .map(|point| point.round_total())
+            .collect();
+        let mut stacks: Vec<(String, f32)> = history
+            .players
+            .iter()
+            .cloned()
+            .zip(replayed.game_state.stacks)
+            .collect();
+        stacks.sort_by(|a, b| a.0.cmp(&b.0));
+        (bets, stacks)
+    }
+
+    fn all_formats() -> Vec<HandHistoryFormat> {
+        vec![
+            HandHistoryFormat::PokerStars,
+            HandHistoryFormat::Phh,
+            #[cfg(feature = "serde")]
+            HandHistoryFormat::Json,
+        ]
+    }
+
+    #[test]
+    fn test_round_trip_every_format() {
+        let original = parse_pokerstars_many(POKERSTARS).unwrap();
+        let expected = outcome(&original[0]);
+        // alice's all in loses to carol's aces
+        assert_eq!(("alice".to_string(), 0.0), expected.1[0]);
+
+        for from in all_formats() {
+            let text = from.write(&original).unwrap();
+            for to in all_formats() {
+                let converted = to.parse(&convert(&text, from, to).unwrap()).unwrap();
+                assert_eq!(1, converted.len(), "{from:?} to {to:?}");
+                let hand = &converted[0];
+                assert_eq!(Some("2001"), hand.id.as_deref());
+                assert_eq!("carol", hand.players[hand.dealer_idx]);
+                assert_eq!(0.1, hand.ante);
+                assert_eq!(expected, outcome(hand), "{from:?} to {to:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_many_hands() {
+        let two = format!("{POKERSTARS}\n{}", POKERSTARS.replace("#2001", "#2002"));
+        let phh = convert(&two, HandHistoryFormat::PokerStars, HandHistoryFormat::Phh).unwrap();
+        assert!(phh.starts_with("[1]\n"));
+        assert!(phh.contains("\n[2]\n"));
+
+        let hands = HandHistoryFormat::Phh.parse(&phh).unwrap();
+        assert_eq!(2, hands.len());
+        assert_eq!(Some("2002"), hands[1].id.as_deref());
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            Some(HandHistoryFormat::Phh),
+            HandHistoryFormat::from_path(Path::new("hands/1.phhs"))
+        );
+        assert_eq!(
+            Some(HandHistoryFormat::PokerStars),
+            HandHistoryFormat::from_path(Path::new("stars.txt"))
+        );
+        assert_eq!(None, HandHistoryFormat::from_path(Path::new("hands")));
+    }
+
+    #[test]
+    fn test_unplayable_hand() {
+        // alice acts before dave has
+        let text = POKERSTARS.replace("dave: folds\n", "");
+        let hands = HandHistoryFormat::PokerStars.parse(&text).unwrap();
+        assert!(matches!(
+            HandHistoryFormat::Phh.write(&hands),
+            Err(HandHistoryError::OutOfTurn { .. })
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+//! Import hand histories written by other poker software and replay them
+//! through the arena.
+//!
+//! Parsers turn the text of a hand history into a [`HandHistory`], which
+//! holds the starting stacks, blinds, known cards and every voluntary action.
+//! [`replay_hand`] then steps a [`GameState`](crate::arena::GameState)
+//! through those actions using the same rules as the
+//! [`HoldemSimulation`](crate::arena::HoldemSimulation). Every action is
+//! checked for legality and the game state at each decision point is kept.
+//!
+//! Supported formats:
+//!
+//! - PokerStars style text hand histories: [`parse_pokerstars`] and
+//!   [`write_pokerstars`]
+//! - Poker Hand History (PHH) files for no limit hold'em: [`parse_phh`] and
+//!   [`write_phh`]
+//! - The crate's own JSON, the serialized [`HandHistory`]
+//!
+//! [`convert`] reads hands in one of these formats and writes them in
+//! another, so archives from different sites can be kept in one format.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::hand_history::{parse_phh, replay_hand};
+//!
+//! let phh = r#"
+//! variant = 'NT'
+//! antes = [0, 0, 0]
+//! blinds_or_straddles = [1, 2, 0]
+//! min_bet = 2
+//! starting_stacks = [100, 100, 100]
+//! actions = [
+//!   'd dh p1 AcAs',
+//!   'd dh p2 7h6h',
+//!   'd dh p3 ????',
+//!   'p3 f',
+//!   'p1 cbr 6',
+//!   'p2 f',
+//! ]
+//! "#;
+//!
+//! let history = parse_phh(phh).unwrap();
+//! let replayed = replay_hand(&history).unwrap();
+//!
+//! assert_eq!(3, replayed.decision_points.len());
+//! assert!(replayed.game_state.is_complete());
+//! assert_eq!(102.0, replayed.game_state.stacks[0]);
+//! ```
+mod convert;
+mod phh;
+mod pokerstars;
+mod replay;
+
+use crate::core::{Card, Hand};
+
+use super::game_state::Round;
+
+pub use convert::{HandHistoryFormat, convert};
+pub use phh::{parse_phh, parse_phh_many, write_phh, write_phh_many};
+pub use pokerstars::{
+    parse_pokerstars, parse_pokerstars_many, write_pokerstars, write_pokerstars_many,
+};
+pub use replay::{DecisionPoint, ReplayedHand, replay_hand};
+
+/// A voluntary action as it was written down in a hand history.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RecordedAction {
+    Fold,
+    /// Check with no outstanding bet.
+    Check,
+    /// Match the outstanding bet. With no outstanding bet this is a check.
+    Call,
+    /// Bet or raise so that the player's total for the round is the amount.
+    BetTo(f32),
+    /// Put every remaining chip in the pot.
+    AllIn,
+}
+
+/// A single recorded action along with who made it and when.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandHistoryAction {
+    pub idx: usize,
+    pub round: Round,
+    pub action: RecordedAction,
+}
+
+/// Everything needed to recreate a single hand of no limit hold'em.
+///
+/// Players are indexed in seat order, skipping any seat that wasn't dealt
+/// in. `hole_cards` holds only the two private cards for each player and is
+/// empty for players whose cards were never shown.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandHistory {
+    /// The identifier the source site gave the hand, if any.
+    pub id: Option<String>,
+    pub players: Vec<String>,
+    pub starting_stacks: Vec<f32>,
+    pub dealer_idx: usize,
+    pub small_blind: f32,
+    pub big_blind: f32,
+    pub ante: f32,
+    pub hole_cards: Vec<Hand>,
+    pub board: Vec<Card>,
+    pub actions: Vec<HandHistoryAction>,
+}
+
+impl HandHistory {
+    pub fn num_players(&self) -> usize {
+        self.starting_stacks.len()
+    }
+
+    /// Find the index of a player by name.
+    pub fn player_idx(&self, name: &str) -> Option<usize> {
+        self.players.iter().position(|p| p == name)
+    }
+}
+
+/// Parse a run of cards like `AhKd`, `Ah Kd`, or `[Ah Kd]`.
+pub(crate) fn parse_cards(cards: &str) -> Result<Vec<Card>, crate::core::RSPokerError> {
+    let cleaned: Vec<char> = cards
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '[' && *c != ']')
+        .collect();
+
+    cleaned
+        .chunks(2)
+        .map(|chunk| {
+            let s: String = chunk.iter().collect();
+            Card::try_from(s.as_str())
+        })
+        .collect()
+}
+
+/// Parse a chip amount, ignoring currency symbols and thousands separators.
+pub(crate) fn parse_amount(amount: &str) -> Option<f32> {
+    let cleaned: String = amount
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, '$' | '€' | '£' | ','))
+        .collect();
+    cleaned.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{Suit, Value};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_cards() {
+        let cards = parse_cards("[Ah Kd]").unwrap();
+        assert_eq!(
+            vec![
+                Card::new(Value::Ace, Suit::Heart),
+                Card::new(Value::King, Suit::Diamond)
+            ],
+            cards
+        );
+        assert_eq!(cards, parse_cards("AhKd").unwrap());
+        assert!(parse_cards("Ah K").is_err());
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(Some(1234.5), parse_amount("$1,234.50"));
+        assert_eq!(Some(20.0), parse_amount(" 20 "));
+        assert_eq!(None, parse_amount("lots"));
+    }
+}
@@ -0,0 +1,596 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::arena::errors::HandHistoryError;
+use crate::arena::game_state::Round;
+use crate::core::{Card, Hand};
+
+use super::replay::{blind_posters, replay_hand};
+use super::{HandHistory, HandHistoryAction, RecordedAction, parse_cards};
+
+/// Parse a no limit hold'em hand written in the Poker Hand History (PHH)
+/// format.
+///
+/// PHH files are a small subset of TOML. Only the fields needed to replay
+/// the hand are read: `variant`, `antes`, `blinds_or_straddles`,
+/// `starting_stacks`, `actions` and optionally `players` and `hand`. As in
+/// PHH the last player is on the button.
+pub fn parse_phh(text: &str) -> Result<HandHistory, HandHistoryError> {
+    let fields = TomlParser::new(text).parse()?;
+
+    match fields.get("variant") {
+        Some(TomlValue::Str(variant)) if variant == "NT" => {}
+        Some(TomlValue::Str(variant)) => {
+            return Err(HandHistoryError::Unsupported(format!("variant {variant}")));
+        }
+        _ => return Err(HandHistoryError::MissingField("variant")),
+    }
+
+    let starting_stacks = number_array(&fields, "starting_stacks")?;
+    let num_players = starting_stacks.len();
+
+    let ante = match number_array(&fields, "antes") {
+        Ok(antes) => uniform_ante(&antes)?,
+        Err(HandHistoryError::MissingField(_)) => 0.0,
+        Err(e) => return Err(e),
+    };
+
+    let mut blinds: Vec<f32> = number_array(&fields, "blinds_or_straddles")?
+        .into_iter()
+        .filter(|b| *b > 0.0)
+        .collect();
+    if blinds.len() > 2 {
+        return Err(HandHistoryError::Unsupported("straddles".to_string()));
+    }
+    blinds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let big_blind = blinds
+        .pop()
+        .ok_or(HandHistoryError::MissingField("blinds_or_straddles"))?;
+    let small_blind = blinds.pop().unwrap_or_default();
+
+    let players = match fields.get("players") {
+        Some(TomlValue::Array(names)) => names
+            .iter()
+            .map(|n| match n {
+                TomlValue::Str(s) => Ok(s.clone()),
+                _ => Err(HandHistoryError::MissingField("players")),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => (1..=num_players).map(|i| format!("p{i}")).collect(),
+    };
+
+    let id = match fields.get("hand") {
+        Some(TomlValue::Number(n)) => Some(n.to_string()),
+        Some(TomlValue::Str(s)) => Some(s.clone()),
+        _ => None,
+    };
+
+    let mut history = HandHistory {
+        id,
+        players,
+        starting_stacks,
+        dealer_idx: num_players.saturating_sub(1),
+        small_blind,
+        big_blind,
+        ante,
+        hole_cards: vec![Hand::default(); num_players],
+        ..Default::default()
+    };
+
+    let actions = match fields.get("actions") {
+        Some(TomlValue::Array(actions)) => actions,
+        _ => return Err(HandHistoryError::MissingField("actions")),
+    };
+    let mut round = Round::Preflop;
+    for (action_num, action) in actions.iter().enumerate() {
+        let TomlValue::Str(action) = action else {
+            return Err(HandHistoryError::MissingField("actions"));
+        };
+        parse_action(&mut history, &mut round, action_num, action)?;
+    }
+    Ok(history)
+}
+
+/// Parse many PHH hands, each under a `[n]` table header as in `.phhs`
+/// files. Text without any headers is read as a single hand.
+pub fn parse_phh_many(text: &str) -> Result<Vec<HandHistory>, HandHistoryError> {
+    let mut hands = vec![];
+    let mut current = String::new();
+    for line in text.lines() {
+        if is_table_header(line) {
+            if !current.trim().is_empty() {
+                hands.push(parse_phh(&current)?);
+            }
+            current.clear();
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    if !current.trim().is_empty() {
+        hands.push(parse_phh(&current)?);
+    }
+    Ok(hands)
+}
+
+fn is_table_header(line: &str) -> bool {
+    line.trim()
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Write a hand in the PHH format.
+///
+/// The hand is replayed first, so only hands the arena can play out are
+/// written and all in bets can be given as amounts. PHH puts the button
+/// last, so the players are rotated to start just after the dealer.
+pub fn write_phh(history: &HandHistory) -> Result<String, HandHistoryError> {
+    let replayed = replay_hand(history)?;
+    let num_players = history.num_players();
+    // The players in the order they're written.
+    let order: Vec<usize> = (1..=num_players)
+        .map(|offset| (history.dealer_idx + offset) % num_players)
+        .collect();
+    let player = |idx: usize| {
+        let position = order.iter().position(|o| *o == idx).unwrap_or_default();
+        format!("p{}", position + 1)
+    };
+
+    let (small_blind_idx, big_blind_idx) = blind_posters(history);
+    let blinds: Vec<f32> = order
+        .iter()
+        .map(|idx| {
+            if Some(*idx) == big_blind_idx {
+                history.big_blind
+            } else if Some(*idx) == small_blind_idx {
+                history.small_blind
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    let mut actions = vec![];
+    for idx in &order {
+        let cards = match history.hole_cards.get(*idx) {
+            Some(hand) if hand.count() == 2 => cards_str(hand.iter()),
+            _ => "????".to_string(),
+        };
+        actions.push(format!("d dh {} {cards}", player(*idx)));
+    }
+
+    let board = &replayed.game_state.board;
+    let mut dealt = 0;
+    let mut deal_to = |actions: &mut Vec<String>, num_cards: usize| {
+        for street_end in [3, 4, 5] {
+            if street_end > dealt && street_end <= num_cards.min(board.len()) {
+                actions.push(format!(
+                    "d db {}",
+                    cards_str(board[dealt..street_end].iter().copied())
+                ));
+                dealt = street_end;
+            }
+        }
+    };
+    for point in &replayed.decision_points {
+        let num_cards = match point.game_state.round {
+            Round::Flop => 3,
+            Round::Turn => 4,
+            Round::River => 5,
+            _ => 0,
+        };
+        deal_to(&mut actions, num_cards);
+        let verb = match point.round_total() {
+            None => "f".to_string(),
+            Some(total) if total > point.game_state.current_round_bet() => format!("cbr {total}"),
+            Some(_) => "cc".to_string(),
+        };
+        actions.push(format!("{} {verb}", player(point.idx)));
+    }
+    deal_to(&mut actions, board.len());
+
+    let final_state = &replayed.game_state;
+    let contenders = final_state.player_active | final_state.player_all_in;
+    if contenders.count() > 1 {
+        for idx in order.iter().filter(|idx| contenders.get(**idx)) {
+            if let Some(hand) = history.hole_cards.get(*idx).filter(|h| h.count() == 2) {
+                actions.push(format!("{} sm {}", player(*idx), cards_str(hand.iter())));
+            }
+        }
+    }
+
+    let numbers = |values: Vec<f32>| {
+        let values: Vec<String> = values.iter().map(f32::to_string).collect();
+        format!("[{}]", values.join(", "))
+    };
+    let mut text = String::from("variant = 'NT'\n");
+    text.push_str(&format!(
+        "antes = {}\n",
+        numbers(vec![history.ante; num_players])
+    ));
+    text.push_str(&format!("blinds_or_straddles = {}\n", numbers(blinds)));
+    text.push_str(&format!("min_bet = {}\n", history.big_blind));
+    text.push_str(&format!(
+        "starting_stacks = {}\n",
+        numbers(
+            order
+                .iter()
+                .map(|idx| history.starting_stacks[*idx])
+                .collect()
+        )
+    ));
+    text.push_str("actions = [\n");
+    for action in &actions {
+        text.push_str(&format!("  '{action}',\n"));
+    }
+    text.push_str("]\n");
+    if let Some(id) = &history.id {
+        text.push_str(&format!("hand = {}\n", quote(id)?));
+    }
+    if !history.players.is_empty() {
+        let players = order
+            .iter()
+            .map(|idx| quote(history.players.get(*idx).map_or("", String::as_str)))
+            .collect::<Result<Vec<_>, _>>()?;
+        text.push_str(&format!("players = [{}]\n", players.join(", ")));
+    }
+    Ok(text)
+}
+
+/// Write many hands as a `.phhs` file, each under its own table header.
+pub fn write_phh_many(histories: &[HandHistory]) -> Result<String, HandHistoryError> {
+    let mut text = String::new();
+    for (num, history) in histories.iter().enumerate() {
+        if num > 0 {
+            text.push('\n');
+        }
+        text.push_str(&format!("[{}]\n", num + 1));
+        text.push_str(&write_phh(history)?);
+    }
+    Ok(text)
+}
+
+fn cards_str(cards: impl Iterator<Item = Card>) -> String {
+    cards.map(|card| card.to_string()).collect()
+}
+
+/// Quote a string for TOML. The parser doesn't handle escapes, so strings
+/// holding both kinds of quote can't be written.
+fn quote(s: &str) -> Result<String, HandHistoryError> {
+    if !s.contains('\'') {
+        Ok(format!("'{s}'"))
+    } else if !s.contains('"') {
+        Ok(format!("\"{s}\""))
+    } else {
+        Err(HandHistoryError::Unsupported(format!("quotes in {s}")))
+    }
+}
+
+fn uniform_ante(antes: &[f32]) -> Result<f32, HandHistoryError> {
+    let mut posted = antes.iter().filter(|a| **a > 0.0);
+    let ante = posted.next().copied().unwrap_or_default();
+    if posted.any(|a| *a != ante) {
+        return Err(HandHistoryError::Unsupported(
+            "antes of different sizes".to_string(),
+        ));
+    }
+    Ok(ante)
+}
+
+fn parse_action(
+    history: &mut HandHistory,
+    round: &mut Round,
+    action_num: usize,
+    action: &str,
+) -> Result<(), HandHistoryError> {
+    let parse_err = || HandHistoryError::Parse(action_num, action.to_string());
+    // Actions can carry trailing comments
+    let action_text = action.split(" #").next().unwrap_or_default();
+    let words: Vec<&str> = action_text.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["d", "dh", player, cards] => {
+            let idx = player_idx(history, player).ok_or_else(parse_err)?;
+            if !cards.contains('?') {
+                history.hole_cards[idx] = Hand::new_with_cards(parse_cards(cards)?);
+            }
+        }
+        ["d", "db", cards] => {
+            history.board.extend(parse_cards(cards)?);
+            *round = match history.board.len() {
+                3 => Round::Flop,
+                4 => Round::Turn,
+                5 => Round::River,
+                _ => return Err(parse_err()),
+            };
+        }
+        [player, "sm", rest @ ..] => {
+            let idx = player_idx(history, player).ok_or_else(parse_err)?;
+            if let Some(cards) = rest.first().filter(|c| !c.contains('?') && **c != "-") {
+                history.hole_cards[idx] = Hand::new_with_cards(parse_cards(cards)?);
+            }
+        }
+        [player, verb, rest @ ..] => {
+            let idx = player_idx(history, player).ok_or_else(parse_err)?;
+            let recorded = match (*verb, rest) {
+                ("f", []) => RecordedAction::Fold,
+                ("cc", []) => RecordedAction::Call,
+                ("cbr", [amount]) => {
+                    RecordedAction::BetTo(amount.parse().map_err(|_| parse_err())?)
+                }
+                _ => return Err(parse_err()),
+            };
+            history.actions.push(HandHistoryAction {
+                idx,
+                round: *round,
+                action: recorded,
+            });
+        }
+        _ => return Err(parse_err()),
+    }
+    Ok(())
+}
+
+fn player_idx(history: &HandHistory, player: &str) -> Option<usize> {
+    let num: usize = player.strip_prefix('p')?.parse().ok()?;
+    (1..=history.num_players()).contains(&num).then(|| num - 1)
+}
+
+fn number_array(
+    fields: &HashMap<String, TomlValue>,
+    name: &'static str,
+) -> Result<Vec<f32>, HandHistoryError> {
+    match fields.get(name) {
+        Some(TomlValue::Array(values)) => values
+            .iter()
+            .map(|v| match v {
+                TomlValue::Number(n) => Ok(*n),
+                _ => Err(HandHistoryError::MissingField(name)),
+            })
+            .collect(),
+        _ => Err(HandHistoryError::MissingField(name)),
+    }
+}
+
+/// The values that can show up in a PHH file.
+#[derive(Debug, Clone, PartialEq)]
+enum TomlValue {
+    Number(f32),
+    Str(String),
+    Bool(bool),
+    Array(Vec<TomlValue>),
+}
+
+/// Just enough of a TOML parser for PHH files: top level `key = value`
+/// pairs where values are numbers, strings, booleans or arrays of them.
+struct TomlParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> TomlParser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn parse(mut self) -> Result<HashMap<String, TomlValue>, HandHistoryError> {
+        let mut fields = HashMap::new();
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek().is_none() {
+                return Ok(fields);
+            }
+            let key = self.parse_key()?;
+            self.skip_whitespace();
+            if self.chars.next() != Some('=') {
+                return Err(self.error("expected ="));
+            }
+            let value = self.parse_value()?;
+            fields.insert(key, value);
+        }
+    }
+
+    fn error(&self, reason: &str) -> HandHistoryError {
+        HandHistoryError::Parse(self.line, reason.to_string())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    self.chars.next();
+                }
+                '#' => while self.chars.next_if(|c| *c != '\n').is_some() {},
+                c if c.is_whitespace() => {
+                    self.chars.next();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String, HandHistoryError> {
+        let mut key = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        {
+            key.push(c);
+        }
+        if key.is_empty() {
+            Err(self.error("expected a key"))
+        } else {
+            Ok(key)
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<TomlValue, HandHistoryError> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('[') => {
+                self.chars.next();
+                let mut values = vec![];
+                loop {
+                    self.skip_whitespace();
+                    if self.chars.next_if_eq(&']').is_some() {
+                        return Ok(TomlValue::Array(values));
+                    }
+                    values.push(self.parse_value()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(TomlValue::Array(values)),
+                        _ => return Err(self.error("expected , or ]")),
+                    }
+                }
+            }
+            Some(&quote @ ('\'' | '"')) => {
+                self.chars.next();
+                let mut s = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some(c) if c == quote => return Ok(TomlValue::Str(s)),
+                        Some('\n') | None => return Err(self.error("unterminated string")),
+                        Some(c) => s.push(c),
+                    }
+                }
+            }
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '_'))
+                {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "true" => Ok(TomlValue::Bool(true)),
+                    "false" => Ok(TomlValue::Bool(false)),
+                    _ => word
+                        .replace('_', "")
+                        .parse()
+                        .map(TomlValue::Number)
+                        .map_err(|_| self.error("expected a value")),
+                }
+            }
+            None => Err(self.error("expected a value")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::hand_history::replay_hand;
+
+    use super::*;
+
+    const HAND: &str = r#"
+# A hand between three players
+variant = 'NT'
+ante_trimming_status = true
+antes = [0, 0, 0]
+blinds_or_straddles = [1, 2, 0]
+min_bet = 2
+starting_stacks = [200, 200, 200]
+actions = [
+  'd dh p1 AcAs',
+  'd dh p2 7h6h',
+  'd dh p3 ????',
+  'p3 f',
+  'p1 cbr 6',
+  'p2 cc',
+  'd db Jc3d5c',
+  'p1 cbr 10',
+  'p2 cc # Hoping for a straight',
+  'd db 4h',
+  'p1 cc',
+  'p2 cc',
+  'd db 2s',
+  'p1 cc',
+  'p2 cbr 50',
+  'p1 cc',
+  'p2 sm 7h6h',
+  'p1 sm AcAs',
+]
+hand = 42
+players = ["Alice", "Bob", "Carol"]
+"#;
+
+    #[test]
+    fn test_parse_hand() {
+        let history = parse_phh(HAND).unwrap();
+        assert_eq!(Some("42".to_string()), history.id);
+        assert_eq!(vec!["Alice", "Bob", "Carol"], history.players);
+        assert_eq!(2, history.dealer_idx);
+        assert_eq!(1.0, history.small_blind);
+        assert_eq!(2.0, history.big_blind);
+        assert_eq!(0.0, history.ante);
+        assert_eq!(5, history.board.len());
+        assert_eq!(Hand::default(), history.hole_cards[2]);
+        assert_eq!(10, history.actions.len());
+        assert_eq!(
+            HandHistoryAction {
+                idx: 1,
+                round: Round::River,
+                action: RecordedAction::BetTo(50.0)
+            },
+            history.actions[8]
+        );
+    }
+
+    #[test]
+    fn test_replay_hand() {
+        let history = parse_phh(HAND).unwrap();
+        let replayed = replay_hand(&history).unwrap();
+        assert_eq!(Round::Complete, replayed.game_state.round);
+        // Bob makes the straight and takes 6 + 10 + 50 from Alice
+        assert_eq!(134.0, replayed.game_state.stacks[0]);
+        assert_eq!(266.0, replayed.game_state.stacks[1]);
+        assert_eq!(200.0, replayed.game_state.stacks[2]);
+    }
+
+    #[test]
+    fn test_unsupported_variant() {
+        let text = "variant = 'FT'\nstarting_stacks = [1, 1]\n";
+        assert!(matches!(
+            parse_phh(text),
+            Err(HandHistoryError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_bad_action() {
+        let text = "variant = 'NT'
+starting_stacks = [100, 100]
+blinds_or_straddles = [1, 2]
+actions = ['p3 f']
+";
+        assert!(matches!(
+            parse_phh(text),
+            Err(HandHistoryError::Parse(0, _))
+        ));
+    }
+
+    #[test]
+    fn test_toml_values() {
+        let fields = TomlParser::new("a = [1.5, 'x', true]\nb = \"y\" # comment\n")
+            .parse()
+            .unwrap();
+        assert_eq!(
+            Some(&TomlValue::Array(vec![
+                TomlValue::Number(1.5),
+                TomlValue::Str("x".to_string()),
+                TomlValue::Bool(true)
+            ])),
+            fields.get("a")
+        );
+        assert_eq!(Some(&TomlValue::Str("y".to_string())), fields.get("b"));
+        assert!(TomlParser::new("a = [1, 2").parse().is_err());
+    }
+}
@@ -0,0 +1,586 @@
+use crate::arena::errors::HandHistoryError;
+use crate::arena::game_state::Round;
+use crate::core::{Card, Hand};
+
+use super::replay::{blind_posters, replay_hand};
+use super::{HandHistory, HandHistoryAction, RecordedAction, parse_amount, parse_cards};
+
+/// Parse a single PokerStars style no limit hold'em hand history.
+///
+/// Players that are sitting out are skipped so indices are contiguous in
+/// seat order. Lines that don't change the game (chat, uncalled bets being
+/// returned, pots being collected) are ignored.
+pub fn parse_pokerstars(text: &str) -> Result<HandHistory, HandHistoryError> {
+    let mut parser = PokerStarsParser::default();
+    for (line_idx, line) in text.lines().enumerate() {
+        parser.parse_line(line_idx + 1, line.trim())?;
+    }
+    parser.finish()
+}
+
+/// Parse a file containing many PokerStars hand histories, one after
+/// another.
+pub fn parse_pokerstars_many(text: &str) -> Result<Vec<HandHistory>, HandHistoryError> {
+    let mut hands = vec![];
+    let mut current = String::new();
+    for line in text.lines() {
+        if line.trim_start().starts_with("PokerStars ") && !current.trim().is_empty() {
+            hands.push(parse_pokerstars(&current)?);
+            current.clear();
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.trim().is_empty() {
+        hands.push(parse_pokerstars(&current)?);
+    }
+    Ok(hands)
+}
+
+/// Write a hand as a PokerStars style hand history.
+///
+/// The hand is replayed first to work out how much each call and raise
+/// puts in and who wins the pot. Every known hole card is shown in the
+/// summary, which is where `parse_pokerstars` picks them up again.
+pub fn write_pokerstars(history: &HandHistory) -> Result<String, HandHistoryError> {
+    let replayed = replay_hand(history)?;
+    let final_state = &replayed.game_state;
+    let name = |idx: usize| history.players.get(idx).map_or("", String::as_str);
+    let hole_cards = |idx: usize| history.hole_cards.get(idx).filter(|h| h.count() == 2);
+    let mut lines = vec![];
+
+    let stakes = format!(
+        "Hold'em No Limit ({}/{})",
+        history.small_blind, history.big_blind
+    );
+    lines.push(match &history.id {
+        Some(id) => format!("PokerStars Hand #{id}: {stakes}"),
+        None => format!("PokerStars Hand: {stakes}"),
+    });
+    lines.push(format!(
+        "Table 'rs_poker' {}-max Seat #{} is the button",
+        history.num_players(),
+        history.dealer_idx + 1
+    ));
+    for (idx, stack) in history.starting_stacks.iter().enumerate() {
+        lines.push(format!(
+            "Seat {}: {} ({stack} in chips)",
+            idx + 1,
+            name(idx)
+        ));
+    }
+    if history.ante > 0.0 {
+        for idx in 0..history.num_players() {
+            lines.push(format!("{}: posts the ante {}", name(idx), history.ante));
+        }
+    }
+    let (small_blind_idx, big_blind_idx) = blind_posters(history);
+    if let Some(idx) = small_blind_idx.filter(|_| history.small_blind > 0.0) {
+        lines.push(format!(
+            "{}: posts small blind {}",
+            name(idx),
+            history.small_blind
+        ));
+    }
+    if let Some(idx) = big_blind_idx {
+        lines.push(format!(
+            "{}: posts big blind {}",
+            name(idx),
+            history.big_blind
+        ));
+    }
+    lines.push("*** HOLE CARDS ***".to_string());
+
+    let board = &final_state.board;
+    let mut dealt = 0;
+    let mut deal_to = |lines: &mut Vec<String>, num_cards: usize| {
+        for (street, street_end) in [("FLOP", 3), ("TURN", 4), ("RIVER", 5)] {
+            if street_end > dealt && street_end <= num_cards.min(board.len()) {
+                let mut line = format!("*** {street} ***");
+                if dealt > 0 {
+                    line.push_str(&format!(" [{}]", cards_str(&board[..dealt])));
+                }
+                line.push_str(&format!(" [{}]", cards_str(&board[dealt..street_end])));
+                lines.push(line);
+                dealt = street_end;
+            }
+        }
+    };
+    for point in &replayed.decision_points {
+        let num_cards = match point.game_state.round {
+            Round::Flop => 3,
+            Round::Turn => 4,
+            Round::River => 5,
+            _ => 0,
+        };
+        deal_to(&mut lines, num_cards);
+
+        let player_bet = point.game_state.current_round_current_player_bet();
+        let bet = point.game_state.current_round_bet();
+        let mut line = match point.round_total() {
+            None => "folds".to_string(),
+            Some(total) if total <= player_bet => "checks".to_string(),
+            Some(total) if total <= bet => format!("calls {}", chips(total - player_bet)),
+            Some(total) if bet <= 0.0 => format!("bets {total}"),
+            Some(total) => format!("raises {} to {total}", chips(total - bet)),
+        };
+        if point.is_all_in() {
+            line.push_str(" and is all-in");
+        }
+        lines.push(format!("{}: {line}", name(point.idx)));
+    }
+    deal_to(&mut lines, board.len());
+
+    let contenders = final_state.player_active | final_state.player_all_in;
+    if contenders.count() > 1 {
+        lines.push("*** SHOW DOWN ***".to_string());
+        for idx in contenders.ones() {
+            if let Some(hand) = hole_cards(idx) {
+                let cards: Vec<Card> = hand.iter().collect();
+                lines.push(format!("{}: shows [{}]", name(idx), cards_str(&cards)));
+            }
+        }
+    }
+    for (idx, won) in final_state.player_winnings.iter().enumerate() {
+        if *won > 0.0 {
+            lines.push(format!("{} collected {won} from pot", name(idx)));
+        }
+    }
+
+    lines.push("*** SUMMARY ***".to_string());
+    lines.push(format!("Total pot {} | Rake 0", final_state.total_pot));
+    if !board.is_empty() {
+        lines.push(format!("Board [{}]", cards_str(board)));
+    }
+    for idx in 0..history.num_players() {
+        let mut line = format!("Seat {}: {}", idx + 1, name(idx));
+        match hole_cards(idx) {
+            Some(hand) => {
+                let cards: Vec<Card> = hand.iter().collect();
+                let verb = if contenders.get(idx) {
+                    "showed"
+                } else {
+                    "mucked"
+                };
+                line.push_str(&format!(" {verb} [{}]", cards_str(&cards)));
+            }
+            None if !contenders.get(idx) => line.push_str(" folded"),
+            None => {}
+        }
+        lines.push(line);
+    }
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+    Ok(text)
+}
+
+/// Write many hands one after another, separated by blank lines.
+pub fn write_pokerstars_many(histories: &[HandHistory]) -> Result<String, HandHistoryError> {
+    let hands = histories
+        .iter()
+        .map(write_pokerstars)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(hands.join("\n\n"))
+}
+
+fn cards_str(cards: &[Card]) -> String {
+    let cards: Vec<String> = cards.iter().map(Card::to_string).collect();
+    cards.join(" ")
+}
+
+/// Amounts worked out by subtraction, rounded to drop float noise.
+fn chips(amount: f32) -> f32 {
+    (amount * 100.0).round() / 100.0
+}
+
+#[derive(Debug, Default)]
+struct PokerStarsParser {
+    history: HandHistory,
+    seats: Vec<usize>,
+    button_seat: Option<usize>,
+    round: Option<Round>,
+    in_summary: bool,
+    small_blind: Option<f32>,
+    big_blind: Option<f32>,
+}
+
+impl PokerStarsParser {
+    fn parse_line(&mut self, line_num: usize, line: &str) -> Result<(), HandHistoryError> {
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(marker) = line.strip_prefix("*** ") {
+            return self.parse_street(line_num, marker);
+        }
+
+        if line.starts_with("PokerStars ") {
+            self.history.id = line
+                .split_once('#')
+                .and_then(|(_, rest)| rest.split(':').next())
+                .map(|id| id.trim().to_string());
+            return Ok(());
+        }
+
+        if line.starts_with("Table ") {
+            let seat = line
+                .split_once("Seat #")
+                .and_then(|(_, rest)| rest.split_whitespace().next())
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| HandHistoryError::Parse(line_num, "missing button".to_string()))?;
+            self.button_seat = Some(seat);
+            return Ok(());
+        }
+
+        if let Some(rest) = line.strip_prefix("Seat ") {
+            return self.parse_seat(line_num, rest);
+        }
+
+        if let Some(rest) = line.strip_prefix("Dealt to ") {
+            let (name, cards) = rest
+                .split_once(" [")
+                .ok_or_else(|| HandHistoryError::Parse(line_num, "missing cards".to_string()))?;
+            let idx = self
+                .history
+                .player_idx(name)
+                .ok_or_else(|| HandHistoryError::UnknownPlayer(name.to_string()))?;
+            return self.set_hole_cards(idx, cards);
+        }
+
+        if let Some((idx, rest)) = self.split_player(line) {
+            return self.parse_player_line(line_num, idx, rest);
+        }
+
+        Ok(())
+    }
+
+    fn parse_street(&mut self, line_num: usize, marker: &str) -> Result<(), HandHistoryError> {
+        let (name, cards) = marker.split_once(" ***").unwrap_or((marker, ""));
+        self.round = match name {
+            "HOLE CARDS" => Some(Round::Preflop),
+            "FLOP" => Some(Round::Flop),
+            "TURN" => Some(Round::Turn),
+            "RIVER" => Some(Round::River),
+            "SHOW DOWN" => Some(Round::Showdown),
+            "SUMMARY" => {
+                self.in_summary = true;
+                Some(Round::Complete)
+            }
+            // Run it twice boards and similar are not something we can replay.
+            _ => {
+                return Err(HandHistoryError::Unsupported(format!(
+                    "street marker {name} on line {line_num}"
+                )));
+            }
+        };
+
+        // Each new street shows the new cards in the last bracket.
+        if let Some((_, new_cards)) = cards.rsplit_once('[') {
+            self.history.board.extend(parse_cards(new_cards)?);
+        }
+        Ok(())
+    }
+
+    fn parse_seat(&mut self, line_num: usize, rest: &str) -> Result<(), HandHistoryError> {
+        let parse_err = || HandHistoryError::Parse(line_num, "bad seat".to_string());
+        let (seat, rest) = rest.split_once(": ").ok_or_else(parse_err)?;
+        let seat: usize = seat.parse().map_err(|_| parse_err())?;
+
+        if self.in_summary {
+            // The summary repeats the seats along with any cards shown.
+            let cards = rest
+                .split_once("showed [")
+                .or_else(|| rest.split_once("mucked ["))
+                .map(|(_, cards)| cards);
+            if let (Some(cards), Some(idx)) = (cards, self.seats.iter().position(|s| *s == seat)) {
+                self.set_hole_cards(idx, cards)?;
+            }
+            return Ok(());
+        }
+
+        if rest.contains(" is sitting out") || rest.contains(" out of hand") {
+            return Ok(());
+        }
+
+        let (name, chips) = rest.rsplit_once(" (").ok_or_else(parse_err)?;
+        let chips = chips
+            .split(" in chips")
+            .next()
+            .and_then(parse_amount)
+            .ok_or_else(parse_err)?;
+
+        self.seats.push(seat);
+        self.history.players.push(name.to_string());
+        self.history.starting_stacks.push(chips);
+        Ok(())
+    }
+
+    /// Find the player that this line is about. The longest name wins so
+    /// that players whose names are prefixes of each other don't collide.
+    fn split_player<'a>(&self, line: &'a str) -> Option<(usize, &'a str)> {
+        self.history
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| {
+                line.len() > name.len() + 1
+                    && line.starts_with(name.as_str())
+                    && line[name.len()..].starts_with(": ")
+            })
+            .max_by_key(|(_, name)| name.len())
+            .map(|(idx, name)| (idx, &line[name.len() + 2..]))
+    }
+
+    fn parse_player_line(
+        &mut self,
+        line_num: usize,
+        idx: usize,
+        rest: &str,
+    ) -> Result<(), HandHistoryError> {
+        let parse_err = || HandHistoryError::Parse(line_num, rest.to_string());
+        let is_all_in = rest.ends_with("and is all-in");
+        let mut words = rest.split_whitespace();
+
+        let action = match words.next() {
+            Some("posts") => return self.parse_post(line_num, rest),
+            Some("shows") => {
+                let (_, cards) = rest.split_once('[').ok_or_else(parse_err)?;
+                return self.set_hole_cards(idx, cards);
+            }
+            Some("folds") => RecordedAction::Fold,
+            Some("checks") => RecordedAction::Check,
+            Some("calls") => RecordedAction::Call,
+            Some("bets") if is_all_in => RecordedAction::AllIn,
+            Some("bets") => {
+                let amount = words.next().and_then(parse_amount).ok_or_else(parse_err)?;
+                RecordedAction::BetTo(amount)
+            }
+            Some("raises") if is_all_in => RecordedAction::AllIn,
+            Some("raises") => {
+                // raises X to Y
+                let amount = words.nth(2).and_then(parse_amount).ok_or_else(parse_err)?;
+                RecordedAction::BetTo(amount)
+            }
+            // Mucks, timeouts and the like don't change anything.
+            _ => return Ok(()),
+        };
+
+        let round = match self.round {
+            Some(round @ (Round::Preflop | Round::Flop | Round::Turn | Round::River)) => round,
+            _ => return Err(parse_err()),
+        };
+        self.history
+            .actions
+            .push(HandHistoryAction { idx, round, action });
+        Ok(())
+    }
+
+    fn parse_post(&mut self, line_num: usize, rest: &str) -> Result<(), HandHistoryError> {
+        let amount = rest
+            .split_whitespace()
+            .last()
+            .and_then(parse_amount)
+            .ok_or_else(|| HandHistoryError::Parse(line_num, rest.to_string()))?;
+
+        let blind = if rest.starts_with("posts the ante") {
+            if self.history.ante > 0.0 && self.history.ante != amount {
+                return Err(HandHistoryError::Unsupported(
+                    "antes of different sizes".to_string(),
+                ));
+            }
+            self.history.ante = amount;
+            return Ok(());
+        } else if rest.starts_with("posts small blind") {
+            &mut self.small_blind
+        } else if rest.starts_with("posts big blind") {
+            &mut self.big_blind
+        } else {
+            return Err(HandHistoryError::Unsupported(format!(
+                "dead or straddle blind on line {line_num}"
+            )));
+        };
+
+        if blind.is_some() {
+            return Err(HandHistoryError::Unsupported(format!(
+                "extra blind on line {line_num}"
+            )));
+        }
+        *blind = Some(amount);
+        Ok(())
+    }
+
+    fn set_hole_cards(&mut self, idx: usize, cards: &str) -> Result<(), HandHistoryError> {
+        let cards = cards.split(']').next().unwrap_or_default();
+        let hand = Hand::new_with_cards(parse_cards(cards)?);
+        if self.history.hole_cards.len() < self.history.num_players() {
+            self.history
+                .hole_cards
+                .resize(self.history.num_players(), Hand::default());
+        }
+        self.history.hole_cards[idx] = hand;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<HandHistory, HandHistoryError> {
+        if self.history.players.is_empty() {
+            return Err(HandHistoryError::MissingField("seats"));
+        }
+        let button_seat = self
+            .button_seat
+            .ok_or(HandHistoryError::MissingField("button"))?;
+        self.history.dealer_idx = self
+            .seats
+            .iter()
+            .position(|s| *s == button_seat)
+            .ok_or_else(|| HandHistoryError::Unsupported("button on an empty seat".to_string()))?;
+        self.history.big_blind = self
+            .big_blind
+            .ok_or(HandHistoryError::MissingField("big blind"))?;
+        self.history.small_blind = self.small_blind.unwrap_or_default();
+        Ok(self.history)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::hand_history::replay_hand;
+
+    use super::*;
+
+    const HAND: &str =
+        "PokerStars Hand #2001: Hold'em No Limit ($0.50/$1.00 USD) - 2020/01/01 12:00:00 ET
+Table 'Alpha' 6-max Seat #3 is the button
+Seat 1: alice ($100 in chips)
+Seat 2: bob ($80.50 in chips) is sitting out
+Seat 3: carol ($100 in chips)
+Seat 4: dave ($150 in chips)
+dave: posts small blind $0.50
+alice: posts big blind $1
+*** HOLE CARDS ***
+Dealt to carol [As Ah]
+carol: raises $2 to $3
+dave: folds
+alice: calls $2
+*** FLOP *** [Kd Qs 3c]
+alice: checks
+carol: bets $4
+alice: calls $4
+*** TURN *** [Kd Qs 3c] [9h]
+alice: checks
+carol: checks
+*** RIVER *** [Kd Qs 3c 9h] [4s]
+alice: bets $10
+carol: raises $20 to $30
+alice: calls $20
+*** SHOW DOWN ***
+carol: shows [As Ah] (a pair of Aces)
+alice: mucks hand
+carol collected $74.50 from pot
+*** SUMMARY ***
+Total pot $74.50 | Rake $0
+Board [Kd Qs 3c 9h 4s]
+Seat 1: alice (big blind) mucked [7c 2d]
+Seat 3: carol (button) showed [As Ah] and won ($74.50)
+Seat 4: dave (small blind) folded before Flop
+";
+
+    #[test]
+    fn test_parse_hand() {
+        let history = parse_pokerstars(HAND).unwrap();
+        assert_eq!(Some("2001".to_string()), history.id);
+        assert_eq!(vec!["alice", "carol", "dave"], history.players);
+        assert_eq!(vec![100.0, 100.0, 150.0], history.starting_stacks);
+        assert_eq!(1, history.dealer_idx);
+        assert_eq!(0.5, history.small_blind);
+        assert_eq!(1.0, history.big_blind);
+        assert_eq!(5, history.board.len());
+        assert_eq!(11, history.actions.len());
+        assert_eq!(Hand::new_from_str("AsAh").unwrap(), history.hole_cards[1]);
+        assert_eq!(Hand::new_from_str("7c2d").unwrap(), history.hole_cards[0]);
+        assert_eq!(
+            HandHistoryAction {
+                idx: 1,
+                round: Round::River,
+                action: RecordedAction::BetTo(30.0)
+            },
+            history.actions[9]
+        );
+    }
+
+    #[test]
+    fn test_replay_hand() {
+        let history = parse_pokerstars(HAND).unwrap();
+        let replayed = replay_hand(&history).unwrap();
+
+        assert_eq!(11, replayed.decision_points.len());
+        assert_eq!(Round::Complete, replayed.game_state.round);
+        // alice loses 3 + 4 + 30 to carol, dave loses the small blind.
+        assert_eq!(63.0, replayed.game_state.stacks[0]);
+        assert_eq!(137.5, replayed.game_state.stacks[1]);
+        assert_eq!(149.5, replayed.game_state.stacks[2]);
+    }
+
+    #[test]
+    fn test_parse_many() {
+        let text = format!("{HAND}\n\n{}", HAND.replace("#2001", "#2002"));
+        let hands = parse_pokerstars_many(&text).unwrap();
+        assert_eq!(2, hands.len());
+        assert_eq!(Some("2002".to_string()), hands[1].id);
+    }
+
+    #[test]
+    fn test_all_in_and_antes() {
+        let text = "PokerStars Hand #7: Tournament
+Table 'T' 9-max Seat #1 is the button
+Seat 1: a (500 in chips)
+Seat 2: b (1,000 in chips)
+a: posts the ante 10
+b: posts the ante 10
+a: posts small blind 25
+b: posts big blind 50
+*** HOLE CARDS ***
+a: raises 440 to 490 and is all-in
+b: calls 440
+*** FLOP *** [2c 3d 4h]
+*** TURN *** [2c 3d 4h] [5s]
+*** RIVER *** [2c 3d 4h 5s] [6s]
+*** SHOW DOWN ***
+a: shows [Ac Ad]
+b: shows [Kc Kd]
+*** SUMMARY ***
+";
+        let history = parse_pokerstars(text).unwrap();
+        assert_eq!(10.0, history.ante);
+        assert_eq!(1000.0, history.starting_stacks[1]);
+        assert_eq!(RecordedAction::AllIn, history.actions[0].action);
+
+        let replayed = replay_hand(&history).unwrap();
+        assert_eq!(Round::Complete, replayed.game_state.round);
+        // The straight on the board means they split.
+        assert_eq!(500.0, replayed.game_state.stacks[0]);
+        assert_eq!(1000.0, replayed.game_state.stacks[1]);
+    }
+
+    #[test]
+    fn test_missing_button() {
+        let text = "Seat 1: a (500 in chips)\nSeat 2: b (500 in chips)\nb: posts big blind 50\n";
+        assert!(matches!(
+            parse_pokerstars(text),
+            Err(HandHistoryError::MissingField("button"))
+        ));
+    }
+
+    #[test]
+    fn test_straddle_unsupported() {
+        let text = "Table 'T' Seat #1 is the button
+Seat 1: a (500 in chips)
+Seat 2: b (500 in chips)
+a: posts small & big blinds 75
+";
+        assert!(matches!(
+            parse_pokerstars(text),
+            Err(HandHistoryError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,491 @@
+use std::iter::Peekable;
+
+use crate::arena::action::AgentAction;
+use crate::arena::agent::FoldingAgent;
+use crate::arena::errors::HandHistoryError;
+use crate::arena::game_state::Round;
+use crate::arena::{Agent, GameState, HoldemSimulationBuilder};
+use crate::core::{Card, rng};
+
+use super::{HandHistory, HandHistoryAction, RecordedAction};
+
+/// The state of the game right before a player acted, along with the action
+/// they took.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecisionPoint {
+    pub game_state: GameState,
+    pub idx: usize,
+    pub action: AgentAction,
+}
+
+impl DecisionPoint {
+    /// The player's total bet for the round once the action is taken, or
+    /// `None` if they folded. Bets are capped at what the player has left.
+    pub fn round_total(&self) -> Option<f32> {
+        let all_in = self.game_state.current_round_current_player_bet()
+            + self.game_state.current_player_stack();
+        match self.action {
+            AgentAction::Fold => None,
+            AgentAction::Bet(amount) => Some(amount.min(all_in)),
+            AgentAction::AllIn => Some(all_in),
+        }
+    }
+
+    /// Whether the action put the player all in.
+    pub fn is_all_in(&self) -> bool {
+        self.round_total().is_some_and(|total| {
+            total
+                >= self.game_state.current_round_current_player_bet()
+                    + self.game_state.current_player_stack()
+        })
+    }
+}
+
+/// The result of replaying a hand history.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayedHand {
+    /// Every voluntary action in the order it was taken.
+    pub decision_points: Vec<DecisionPoint>,
+    /// The game state after the last action.
+    ///
+    /// If the hand went to showdown and every remaining player's cards and
+    /// the full board are known the pot will have been awarded and the round
+    /// will be `Complete`. Otherwise the game state is left at the first
+    /// round that couldn't be played out.
+    pub game_state: GameState,
+}
+
+/// Replay a hand history through a `GameState` using the same rules as the
+/// `HoldemSimulation`.
+///
+/// Antes and blinds are posted by the players the arena expects to post them
+/// and each recorded action must come from the player whose turn it is and
+/// be legal according to `GameState::do_bet`.
+pub fn replay_hand(history: &HandHistory) -> Result<ReplayedHand, HandHistoryError> {
+    let num_players = history.num_players();
+    if num_players < 2 {
+        return Err(HandHistoryError::Unsupported(
+            "hands need at least two players".to_string(),
+        ));
+    }
+    if history.dealer_idx >= num_players {
+        return Err(HandHistoryError::MissingField("dealer"));
+    }
+
+    let mut game_state = starting_game_state(history);
+    let mut actions = history.actions.iter().peekable();
+    let mut decision_points = vec![];
+
+    loop {
+        match game_state.round {
+            // Nothing but sitting down happens here.
+            Round::Starting => game_state.advance_round(),
+            Round::Ante => {
+                post_antes(&mut game_state);
+                game_state.advance_round();
+            }
+            Round::DealPreflop => {
+                for (hand, hole_cards) in game_state.hands_mut().iter_mut().zip(&history.hole_cards)
+                {
+                    hand.extend(hole_cards.iter());
+                }
+                game_state.advance_round();
+            }
+            Round::Preflop => {
+                post_blinds(&mut game_state);
+                run_betting_round(&mut game_state, &mut actions, &mut decision_points)?;
+                game_state.advance_round();
+            }
+            Round::Flop | Round::Turn | Round::River => {
+                run_betting_round(&mut game_state, &mut actions, &mut decision_points)?;
+                game_state.advance_round();
+            }
+            Round::DealFlop => {
+                deal_community_cards(&mut game_state, &history.board, 3);
+                game_state.advance_round();
+            }
+            Round::DealTurn | Round::DealRiver => {
+                deal_community_cards(&mut game_state, &history.board, 1);
+                game_state.advance_round();
+            }
+            Round::Showdown => {
+                if can_showdown(&game_state, history) {
+                    game_state = showdown(game_state);
+                }
+                break;
+            }
+            Round::Complete => break,
+            // Hand histories are hold'em, whose rounds never lead here.
+            Round::ThirdStreet
+            | Round::FourthStreet
+            | Round::FifthStreet
+            | Round::SixthStreet
+            | Round::SeventhStreet => {
+                return Err(HandHistoryError::Unsupported("stud streets".to_string()));
+            }
+            Round::FirstDraw | Round::SecondDraw | Round::ThirdDraw => {
+                return Err(HandHistoryError::Unsupported("draws".to_string()));
+            }
+        }
+    }
+
+    if let Some(action) = actions.next() {
+        return Err(HandHistoryError::NoActionExpected(action.round));
+    }
+
+    Ok(ReplayedHand {
+        decision_points,
+        game_state,
+    })
+}
+
+fn starting_game_state(history: &HandHistory) -> GameState {
+    GameState::new_starting(
+        history.starting_stacks.clone(),
+        history.big_blind,
+        history.small_blind,
+        history.ante,
+        history.dealer_idx,
+    )
+}
+
+/// The players that post the small and big blinds, found by posting them
+/// the same way `replay_hand` does.
+pub(super) fn blind_posters(history: &HandHistory) -> (Option<usize>, Option<usize>) {
+    let mut game_state = starting_game_state(history);
+    while game_state.round != Round::Preflop {
+        if game_state.round == Round::Ante {
+            post_antes(&mut game_state);
+        }
+        game_state.advance_round();
+    }
+    post_blinds(&mut game_state)
+}
+
+fn post_antes(game_state: &mut GameState) {
+    let ante = game_state.ante;
+    if ante > 0.0 {
+        while game_state.current_round_num_active_players() > 0 {
+            let idx = game_state.to_act_idx();
+            // Forced bets are capped at the stack so they can't fail.
+            game_state.do_bet(ante, true).unwrap();
+            game_state.round_data.needs_action.disable(idx);
+        }
+    }
+}
+
+/// Post the blinds, returning who posted each of them.
+fn post_blinds(game_state: &mut GameState) -> (Option<usize>, Option<usize>) {
+    let mut posters = (None, None);
+    if !game_state.sb_posted {
+        posters.0 = Some(game_state.to_act_idx());
+        game_state.do_bet(game_state.small_blind, true).unwrap();
+        game_state.sb_posted = true;
+    }
+    if !game_state.bb_posted {
+        posters.1 = Some(game_state.to_act_idx());
+        game_state.do_bet(game_state.big_blind, true).unwrap();
+        game_state.bb_posted = true;
+    }
+    posters
+}
+
+fn run_betting_round<'a, I>(
+    game_state: &mut GameState,
+    actions: &mut Peekable<I>,
+    decision_points: &mut Vec<DecisionPoint>,
+) -> Result<(), HandHistoryError>
+where
+    I: Iterator<Item = &'a HandHistoryAction>,
+{
+    let round = game_state.round;
+    while game_state.round == round {
+        let needs_action = !(game_state.player_active & game_state.round_data.needs_action).empty();
+
+        let Some(action) = actions.next_if(|a| a.round == round) else {
+            // A lone player that isn't facing a bet has no one to bet
+            // against, so hand histories never record their check.
+            let lone_player_done = game_state.num_active_players() <= 1
+                && game_state.current_round_current_player_bet() >= game_state.current_round_bet();
+            return if needs_action && !lone_player_done {
+                Err(HandHistoryError::IncompleteRound(round))
+            } else {
+                Ok(())
+            };
+        };
+
+        if !needs_action {
+            return Err(HandHistoryError::NoActionExpected(round));
+        }
+
+        let idx = game_state.to_act_idx();
+        if action.idx != idx {
+            return Err(HandHistoryError::OutOfTurn {
+                expected: idx,
+                found: action.idx,
+            });
+        }
+
+        let agent_action = match action.action {
+            RecordedAction::Fold => AgentAction::Fold,
+            RecordedAction::Check => {
+                if game_state.current_round_current_player_bet() < game_state.current_round_bet() {
+                    return Err(HandHistoryError::IllegalCheck(idx));
+                }
+                AgentAction::Bet(game_state.current_round_bet())
+            }
+            RecordedAction::Call => AgentAction::Bet(game_state.current_round_bet()),
+            RecordedAction::BetTo(amount) => AgentAction::Bet(amount),
+            RecordedAction::AllIn => AgentAction::AllIn,
+        };
+
+        decision_points.push(DecisionPoint {
+            game_state: game_state.clone(),
+            idx,
+            action: agent_action.clone(),
+        });
+
+        match agent_action {
+            AgentAction::Fold => fold(game_state),
+            AgentAction::Bet(amount) => {
+                game_state
+                    .do_bet(amount, false)
+                    .map_err(|e| HandHistoryError::IllegalAction(idx, e))?;
+            }
+            AgentAction::AllIn => {
+                let all_in_amount = game_state.current_round_current_player_bet()
+                    + game_state.current_player_stack();
+                game_state
+                    .do_bet(all_in_amount, false)
+                    .map_err(|e| HandHistoryError::IllegalAction(idx, e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn fold(game_state: &mut GameState) {
+    game_state.fold();
+    let left = game_state.player_active | game_state.player_all_in;
+
+    // Just like the simulation, the last player standing takes the pot.
+    if left.count() <= 1 {
+        if let Some(winning_idx) = left.ones().next() {
+            let total_pot = game_state.total_pot;
+            game_state.award(winning_idx, total_pot);
+        }
+        game_state.complete();
+    }
+}
+
+fn deal_community_cards(game_state: &mut GameState, board: &[Card], num_cards: usize) {
+    let start = game_state.board.len();
+    if let Some(cards) = board.get(start..start + num_cards) {
+        for hand in game_state.hands_mut() {
+            hand.extend(cards.iter().copied());
+        }
+        game_state.board_mut().extend_from_slice(cards);
+    }
+}
+
+fn can_showdown(game_state: &GameState, history: &HandHistory) -> bool {
+    let contenders = game_state.player_active | game_state.player_all_in;
+    game_state.board.len() == 5
+        && contenders.ones().all(|idx| {
+            history
+                .hole_cards
+                .get(idx)
+                .is_some_and(|hand| hand.count() == 2)
+        })
+}
+
+/// Let the simulation split the pots so that side pots and ties are handled
+/// exactly the way the arena would.
+fn showdown(game_state: GameState) -> GameState {
+    let agents: Vec<Box<dyn Agent>> = (0..game_state.num_players)
+        .map(|_| Box::<FoldingAgent>::default() as Box<dyn Agent>)
+        .collect();
+    let mut sim = HoldemSimulationBuilder::default()
+        .game_state(game_state)
+        .agents(agents)
+        .build()
+        .unwrap();
+    // Only the showdown is left so there's no dealing and no randomness.
+    sim.run(&mut rng());
+    sim.game_state
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::errors::GameStateError;
+    use crate::core::Hand;
+
+    use super::*;
+
+    fn action(idx: usize, round: Round, action: RecordedAction) -> HandHistoryAction {
+        HandHistoryAction { idx, round, action }
+    }
+
+    fn heads_up() -> HandHistory {
+        HandHistory {
+            players: vec!["a".to_string(), "b".to_string()],
+            starting_stacks: vec![100.0, 100.0],
+            dealer_idx: 0,
+            small_blind: 1.0,
+            big_blind: 2.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fold_preflop() {
+        let mut history = heads_up();
+        // Heads up the dealer posts the small blind and acts first
+        history.actions = vec![action(0, Round::Preflop, RecordedAction::Fold)];
+
+        let replayed = replay_hand(&history).unwrap();
+        assert_eq!(1, replayed.decision_points.len());
+        assert_eq!(Round::Preflop, replayed.decision_points[0].game_state.round);
+        assert_eq!(Round::Complete, replayed.game_state.round);
+        assert_eq!(99.0, replayed.game_state.stacks[0]);
+        assert_eq!(101.0, replayed.game_state.stacks[1]);
+    }
+
+    #[test]
+    fn test_showdown_awards_pot() {
+        let mut history = heads_up();
+        history.hole_cards = vec![
+            Hand::new_from_str("AsAh").unwrap(),
+            Hand::new_from_str("7c2d").unwrap(),
+        ];
+        history.board = super::super::parse_cards("KdQs3c9h4s").unwrap();
+        history.actions = vec![
+            action(0, Round::Preflop, RecordedAction::Call),
+            action(1, Round::Preflop, RecordedAction::Check),
+            action(1, Round::Flop, RecordedAction::BetTo(4.0)),
+            action(0, Round::Flop, RecordedAction::Call),
+            action(1, Round::Turn, RecordedAction::Check),
+            action(0, Round::Turn, RecordedAction::Check),
+            action(1, Round::River, RecordedAction::Check),
+            action(0, Round::River, RecordedAction::Check),
+        ];
+
+        let replayed = replay_hand(&history).unwrap();
+        assert_eq!(8, replayed.decision_points.len());
+        assert_eq!(Round::Complete, replayed.game_state.round);
+        assert_eq!(106.0, replayed.game_state.stacks[0]);
+        assert_eq!(94.0, replayed.game_state.stacks[1]);
+        assert_eq!(5, replayed.game_state.board.len());
+    }
+
+    #[test]
+    fn test_all_in_runs_out_board() {
+        let mut history = heads_up();
+        history.hole_cards = vec![
+            Hand::new_from_str("AsAh").unwrap(),
+            Hand::new_from_str("7c2d").unwrap(),
+        ];
+        history.board = super::super::parse_cards("KdQs3c9h4s").unwrap();
+        history.actions = vec![
+            action(0, Round::Preflop, RecordedAction::AllIn),
+            action(1, Round::Preflop, RecordedAction::Call),
+        ];
+
+        let replayed = replay_hand(&history).unwrap();
+        assert_eq!(Round::Complete, replayed.game_state.round);
+        assert_eq!(200.0, replayed.game_state.stacks[0]);
+        assert_eq!(0.0, replayed.game_state.stacks[1]);
+    }
+
+    #[test]
+    fn test_unknown_cards_stop_at_showdown() {
+        let mut history = heads_up();
+        history.actions = vec![
+            action(0, Round::Preflop, RecordedAction::AllIn),
+            action(1, Round::Preflop, RecordedAction::Call),
+        ];
+
+        let replayed = replay_hand(&history).unwrap();
+        assert_eq!(Round::Showdown, replayed.game_state.round);
+        assert_eq!(200.0, replayed.game_state.total_pot);
+    }
+
+    #[test]
+    fn test_out_of_turn() {
+        let mut history = heads_up();
+        history.actions = vec![action(1, Round::Preflop, RecordedAction::Fold)];
+
+        let result = replay_hand(&history);
+        assert!(matches!(
+            result,
+            Err(HandHistoryError::OutOfTurn {
+                expected: 0,
+                found: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_illegal_check() {
+        let mut history = heads_up();
+        history.actions = vec![action(0, Round::Preflop, RecordedAction::Check)];
+
+        let result = replay_hand(&history);
+        assert!(matches!(result, Err(HandHistoryError::IllegalCheck(0))));
+    }
+
+    #[test]
+    fn test_raise_too_small() {
+        let mut history = heads_up();
+        history.actions = vec![action(0, Round::Preflop, RecordedAction::BetTo(3.0))];
+
+        let result = replay_hand(&history);
+        assert!(matches!(
+            result,
+            Err(HandHistoryError::IllegalAction(
+                0,
+                GameStateError::RaiseSizeTooSmall
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_incomplete_round() {
+        let mut history = heads_up();
+        history.actions = vec![action(0, Round::Preflop, RecordedAction::Call)];
+
+        let result = replay_hand(&history);
+        assert!(matches!(
+            result,
+            Err(HandHistoryError::IncompleteRound(Round::Preflop))
+        ));
+    }
+
+    #[test]
+    fn test_extra_actions() {
+        let mut history = heads_up();
+        history.actions = vec![
+            action(0, Round::Preflop, RecordedAction::Fold),
+            action(1, Round::Flop, RecordedAction::Check),
+        ];
+
+        let result = replay_hand(&history);
+        assert!(matches!(
+            result,
+            Err(HandHistoryError::NoActionExpected(Round::Flop))
+        ));
+    }
+
+    #[test]
+    fn test_antes_posted() {
+        let mut history = heads_up();
+        history.ante = 1.0;
+        history.actions = vec![action(0, Round::Preflop, RecordedAction::Fold)];
+
+        let replayed = replay_hand(&history).unwrap();
+        assert_eq!(98.0, replayed.game_state.stacks[0]);
+        assert_eq!(102.0, replayed.game_state.stacks[1]);
+    }
+}
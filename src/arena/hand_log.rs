@@ -0,0 +1,715 @@
+//! A compact binary log of simulated hands.
+//!
+//! JSON is handy for looking at a few hands but it's far too large for
+//! archiving millions or billions of them. This format stores the same
+//! `Action` stream that historians see, at a small fraction of the size:
+//!
+//! - Integers are LEB128 varints.
+//! - Chip amounts that are whole numbers (nearly all of them) are stored as
+//!   varints, anything else keeps the exact `f32` bits.
+//! - Cards are a single byte, hands and player sets are bitsets.
+//! - The simulation id is only written once per hand.
+//! - The whole stream is compressed with zstd. A new zstd frame is started
+//!   every `hands_per_frame` hands, so a log that was cut short (for example by
+//!   a crash) can still be read up to the last finished frame.
+//!
+//! A file starts with the magic bytes `RSPL` and a version byte, followed by
+//! the zstd frames. The game state isn't stored, it can be rebuilt by
+//! replaying the actions.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::action::Action;
+//! use rs_poker::arena::game_state::Round;
+//! use rs_poker::arena::hand_log::{HandLogReader, HandLogWriter};
+//!
+//! let mut writer = HandLogWriter::new(Vec::new()).unwrap();
+//! writer
+//!     .write(7, &Action::RoundAdvance(Round::Preflop))
+//!     .unwrap();
+//! let bytes = writer.finish().unwrap();
+//!
+//! let records: Vec<_> = HandLogReader::new(&bytes[..])
+//!     .unwrap()
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! assert_eq!(1, records.len());
+//! assert_eq!(7, records[0].id);
+//! ```
+use std::io::{self, BufReader, ErrorKind, Read, Write};
+
+use crate::core::{Card, Hand, PlayerBitSet, Rank};
+
+use super::action::{
+    Action, AgentAction, AwardPayload, DealStartingHandPayload, DrawPayload, FailedActionPayload,
+    ForcedBetPayload, ForcedBetType, GameStartPayload, PlayedActionPayload, PlayerSitPayload,
+    ShowHandPayload,
+};
+use super::errors::HandLogError;
+use super::game_state::Round;
+
+const MAGIC: &[u8; 4] = b"RSPL";
+const VERSION: u8 = 1;
+
+/// Marks the start of a new simulation id. Every other tag is an action.
+const TAG_HAND: u8 = 0xFF;
+const TAG_GAME_START: u8 = 0;
+const TAG_PLAYER_SIT: u8 = 1;
+const TAG_DEAL_STARTING_HAND: u8 = 2;
+const TAG_ROUND_ADVANCE: u8 = 3;
+const TAG_PLAYED_ACTION: u8 = 4;
+const TAG_FAILED_ACTION: u8 = 5;
+const TAG_FORCED_BET: u8 = 6;
+const TAG_DEAL_COMMUNITY: u8 = 7;
+const TAG_AWARD: u8 = 8;
+const TAG_SHOW_HAND: u8 = 9;
+const TAG_DRAW: u8 = 10;
+
+/// A single action read back from a hand log.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandLogRecord {
+    /// The id of the simulation the action happened in.
+    pub id: u128,
+    pub action: Action,
+}
+
+/// Writes actions into the binary hand log format.
+///
+/// Dropping the writer finishes the last zstd frame, but any error doing
+/// so is lost. Call `finish` to see it.
+pub struct HandLogWriter<W: Write> {
+    encoder: Option<zstd::Encoder<'static, W>>,
+    level: i32,
+    hands_per_frame: usize,
+    hands_in_frame: usize,
+    current_id: Option<u128>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> HandLogWriter<W> {
+    /// The zstd compression level used by `new`.
+    pub const DEFAULT_LEVEL: i32 = 3;
+    /// How many hands are put in each zstd frame by `new`.
+    pub const DEFAULT_HANDS_PER_FRAME: usize = 4096;
+
+    pub fn new(writer: W) -> io::Result<Self> {
+        Self::new_with_options(writer, Self::DEFAULT_LEVEL, Self::DEFAULT_HANDS_PER_FRAME)
+    }
+
+    /// Create a writer with an explicit zstd compression level and number
+    /// of hands per frame. Smaller frames lose less when a log is cut
+    /// short, larger frames compress better.
+    pub fn new_with_options(mut writer: W, level: i32, hands_per_frame: usize) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        Ok(Self {
+            encoder: Some(zstd::Encoder::new(writer, level)?),
+            level,
+            hands_per_frame: hands_per_frame.max(1),
+            hands_in_frame: 0,
+            current_id: None,
+            buf: Vec::with_capacity(128),
+        })
+    }
+
+    /// Append an action that happened in the simulation `id`.
+    pub fn write(&mut self, id: u128, action: &Action) -> io::Result<()> {
+        if self.current_id != Some(id) {
+            if self.hands_in_frame >= self.hands_per_frame {
+                self.next_frame()?;
+            }
+            self.hands_in_frame += 1;
+            self.current_id = Some(id);
+            self.buf.push(TAG_HAND);
+            self.buf.extend_from_slice(&id.to_le_bytes());
+        }
+        encode_action(&mut self.buf, action);
+
+        let encoder = self
+            .encoder
+            .as_mut()
+            .expect("encoder is only taken on finish");
+        encoder.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+
+    /// Finish the current frame and flush it to the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.hands_in_frame > 0 {
+            self.next_frame()?;
+        }
+        self.encoder
+            .as_mut()
+            .expect("encoder is only taken on finish")
+            .get_mut()
+            .flush()
+    }
+
+    /// Finish the log, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let encoder = self
+            .encoder
+            .take()
+            .expect("encoder is only taken on finish");
+        let mut writer = encoder.finish()?;
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    fn next_frame(&mut self) -> io::Result<()> {
+        let encoder = self
+            .encoder
+            .take()
+            .expect("encoder is only taken on finish");
+        let writer = encoder.finish()?;
+        self.encoder = Some(zstd::Encoder::new(writer, self.level)?);
+        self.hands_in_frame = 0;
+        // Readers need the id again at the start of every frame.
+        self.current_id = None;
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for HandLogWriter<W> {
+    fn drop(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            let _ = encoder.finish().and_then(|mut w| w.flush());
+        }
+    }
+}
+
+/// A streaming reader for the binary hand log format. It yields one
+/// `HandLogRecord` per action, decompressing as it goes, so logs of any
+/// size can be read in constant memory.
+pub struct HandLogReader<R: Read> {
+    decoder: zstd::Decoder<'static, BufReader<R>>,
+    current_id: Option<u128>,
+    done: bool,
+}
+
+impl<R: Read> HandLogReader<R> {
+    pub fn new(reader: R) -> Result<Self, HandLogError> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0_u8; 5];
+        reader.read_exact(&mut header).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => HandLogError::BadMagic,
+            _ => HandLogError::Io(e),
+        })?;
+        if &header[..4] != MAGIC {
+            return Err(HandLogError::BadMagic);
+        }
+        if header[4] != VERSION {
+            return Err(HandLogError::UnsupportedVersion(header[4]));
+        }
+        Ok(Self {
+            decoder: zstd::Decoder::with_buffer(reader)?,
+            current_id: None,
+            done: false,
+        })
+    }
+
+    fn read_record(&mut self) -> Result<Option<HandLogRecord>, HandLogError> {
+        loop {
+            let mut tag = [0_u8; 1];
+            match self.decoder.read(&mut tag) {
+                Ok(0) => return Ok(None),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+
+            if tag[0] == TAG_HAND {
+                let mut id = [0_u8; 16];
+                self.decoder.read_exact(&mut id)?;
+                self.current_id = Some(u128::from_le_bytes(id));
+                continue;
+            }
+
+            let id = self
+                .current_id
+                .ok_or(HandLogError::Corrupt("action before hand id"))?;
+            let action = decode_action(&mut self.decoder, tag[0])?;
+            return Ok(Some(HandLogRecord { id, action }));
+        }
+    }
+}
+
+impl<R: Read> Iterator for HandLogReader<R> {
+    type Item = Result<HandLogRecord, HandLogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_record().transpose();
+        // Stop after the end of the log or the first error; there's no way
+        // to resynchronize in the middle of a compressed stream.
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+fn encode_action(buf: &mut Vec<u8>, action: &Action) {
+    match action {
+        Action::GameStart(payload) => {
+            buf.push(TAG_GAME_START);
+            write_amount(buf, payload.ante);
+            write_amount(buf, payload.small_blind);
+            write_amount(buf, payload.big_blind);
+        }
+        Action::PlayerSit(payload) => {
+            buf.push(TAG_PLAYER_SIT);
+            write_varint(buf, payload.idx as u64);
+            write_amount(buf, payload.player_stack);
+        }
+        Action::DealStartingHand(payload) => {
+            buf.push(TAG_DEAL_STARTING_HAND);
+            buf.push(payload.card.into());
+            write_varint(buf, payload.idx as u64);
+        }
+        Action::RoundAdvance(round) => {
+            buf.push(TAG_ROUND_ADVANCE);
+            buf.push((*round).into());
+        }
+        Action::PlayedAction(payload) => {
+            buf.push(TAG_PLAYED_ACTION);
+            write_played(buf, payload);
+        }
+        Action::FailedAction(payload) => {
+            buf.push(TAG_FAILED_ACTION);
+            write_agent_action(buf, &payload.action);
+            write_played(buf, &payload.result);
+        }
+        Action::ForcedBet(payload) => {
+            buf.push(TAG_FORCED_BET);
+            write_amount(buf, payload.bet);
+            write_amount(buf, payload.player_stack);
+            write_varint(buf, payload.idx as u64);
+            buf.push(match payload.forced_bet_type {
+                ForcedBetType::Ante => 0,
+                ForcedBetType::SmallBlind => 1,
+                ForcedBetType::BigBlind => 2,
+                ForcedBetType::BringIn => 3,
+            });
+        }
+        Action::DealCommunity(card) => {
+            buf.push(TAG_DEAL_COMMUNITY);
+            buf.push((*card).into());
+        }
+        Action::Award(payload) => {
+            buf.push(TAG_AWARD);
+            write_amount(buf, payload.total_pot);
+            write_amount(buf, payload.award_amount);
+            write_varint(buf, payload.idx as u64);
+            match payload.rank {
+                None => buf.push(0),
+                Some(rank) => {
+                    let (kind, value) = rank_parts(rank);
+                    buf.push(kind + 1);
+                    write_varint(buf, value as u64);
+                }
+            }
+            write_hand(buf, payload.hand);
+        }
+        Action::ShowHand(payload) => {
+            buf.push(TAG_SHOW_HAND);
+            write_varint(buf, payload.idx as u64);
+            write_hand(buf, payload.hand);
+        }
+        Action::Draw(payload) => {
+            buf.push(TAG_DRAW);
+            write_varint(buf, payload.idx as u64);
+            write_varint(buf, payload.discarded.to_u64());
+            write_varint(buf, payload.drawn.to_u64());
+        }
+    }
+}
+
+fn decode_action<R: Read>(reader: &mut R, tag: u8) -> Result<Action, HandLogError> {
+    let action = match tag {
+        TAG_GAME_START => Action::GameStart(GameStartPayload {
+            ante: read_amount(reader)?,
+            small_blind: read_amount(reader)?,
+            big_blind: read_amount(reader)?,
+        }),
+        TAG_PLAYER_SIT => Action::PlayerSit(PlayerSitPayload {
+            idx: read_idx(reader)?,
+            player_stack: read_amount(reader)?,
+        }),
+        TAG_DEAL_STARTING_HAND => Action::DealStartingHand(DealStartingHandPayload {
+            card: read_card(reader)?,
+            idx: read_idx(reader)?,
+        }),
+        TAG_ROUND_ADVANCE => Action::RoundAdvance(read_round(reader)?),
+        TAG_PLAYED_ACTION => Action::PlayedAction(read_played(reader)?),
+        TAG_FAILED_ACTION => Action::FailedAction(FailedActionPayload {
+            action: read_agent_action(reader)?,
+            result: read_played(reader)?,
+        }),
+        TAG_FORCED_BET => Action::ForcedBet(ForcedBetPayload {
+            bet: read_amount(reader)?,
+            player_stack: read_amount(reader)?,
+            idx: read_idx(reader)?,
+            forced_bet_type: match read_u8(reader)? {
+                0 => ForcedBetType::Ante,
+                1 => ForcedBetType::SmallBlind,
+                2 => ForcedBetType::BigBlind,
+                3 => ForcedBetType::BringIn,
+                _ => return Err(HandLogError::Corrupt("unknown forced bet type")),
+            },
+        }),
+        TAG_DEAL_COMMUNITY => Action::DealCommunity(read_card(reader)?),
+        TAG_AWARD => {
+            let total_pot = read_amount(reader)?;
+            let award_amount = read_amount(reader)?;
+            let idx = read_idx(reader)?;
+            let rank = match read_u8(reader)? {
+                0 => None,
+                kind => {
+                    let value = u32::try_from(read_varint(reader)?)
+                        .map_err(|_| HandLogError::Corrupt("rank value out of range"))?;
+                    Some(rank_from_parts(kind - 1, value)?)
+                }
+            };
+            let hand = read_hand(reader)?;
+            Action::Award(AwardPayload {
+                total_pot,
+                award_amount,
+                rank,
+                hand,
+                idx,
+            })
+        }
+        TAG_SHOW_HAND => Action::ShowHand(ShowHandPayload {
+            idx: read_idx(reader)?,
+            hand: read_hand(reader)?,
+        }),
+        TAG_DRAW => Action::Draw(DrawPayload {
+            idx: read_idx(reader)?,
+            discarded: read_cards(reader)?,
+            drawn: read_cards(reader)?,
+        }),
+        _ => return Err(HandLogError::Corrupt("unknown action tag")),
+    };
+    Ok(action)
+}
+
+fn write_hand(buf: &mut Vec<u8>, hand: Option<Hand>) {
+    match hand {
+        None => buf.push(0),
+        Some(hand) => {
+            buf.push(1);
+            write_varint(buf, hand.to_u64());
+        }
+    }
+}
+
+fn read_hand<R: Read>(reader: &mut R) -> Result<Option<Hand>, HandLogError> {
+    match read_u8(reader)? {
+        0 => Ok(None),
+        1 => read_cards(reader).map(Some),
+        _ => Err(HandLogError::Corrupt("invalid hand marker")),
+    }
+}
+
+fn read_cards<R: Read>(reader: &mut R) -> Result<Hand, HandLogError> {
+    let bits = read_varint(reader)?;
+    Hand::try_from_u64(bits).map_err(|_| HandLogError::Corrupt("invalid hand"))
+}
+
+fn write_played(buf: &mut Vec<u8>, payload: &PlayedActionPayload) {
+    write_agent_action(buf, &payload.action);
+    write_varint(buf, payload.idx as u64);
+    buf.push(payload.round.into());
+    for amount in [
+        payload.player_stack,
+        payload.starting_pot,
+        payload.final_pot,
+        payload.starting_bet,
+        payload.final_bet,
+        payload.starting_min_raise,
+        payload.final_min_raise,
+        payload.starting_player_bet,
+        payload.final_player_bet,
+    ] {
+        write_amount(buf, amount);
+    }
+    write_player_set(buf, payload.players_active);
+    write_player_set(buf, payload.players_all_in);
+}
+
+fn read_played<R: Read>(reader: &mut R) -> Result<PlayedActionPayload, HandLogError> {
+    Ok(PlayedActionPayload {
+        action: read_agent_action(reader)?,
+        idx: read_idx(reader)?,
+        round: read_round(reader)?,
+        player_stack: read_amount(reader)?,
+        starting_pot: read_amount(reader)?,
+        final_pot: read_amount(reader)?,
+        starting_bet: read_amount(reader)?,
+        final_bet: read_amount(reader)?,
+        starting_min_raise: read_amount(reader)?,
+        final_min_raise: read_amount(reader)?,
+        starting_player_bet: read_amount(reader)?,
+        final_player_bet: read_amount(reader)?,
+        players_active: read_player_set(reader)?,
+        players_all_in: read_player_set(reader)?,
+    })
+}
+
+fn write_agent_action(buf: &mut Vec<u8>, action: &AgentAction) {
+    match action {
+        AgentAction::Fold => buf.push(0),
+        AgentAction::Bet(amount) => {
+            buf.push(1);
+            write_amount(buf, *amount);
+        }
+        AgentAction::AllIn => buf.push(2),
+    }
+}
+
+fn read_agent_action<R: Read>(reader: &mut R) -> Result<AgentAction, HandLogError> {
+    match read_u8(reader)? {
+        0 => Ok(AgentAction::Fold),
+        1 => Ok(AgentAction::Bet(read_amount(reader)?)),
+        2 => Ok(AgentAction::AllIn),
+        _ => Err(HandLogError::Corrupt("unknown agent action")),
+    }
+}
+
+fn write_player_set(buf: &mut Vec<u8>, set: PlayerBitSet) {
+    let bits = set.ones().fold(0_u64, |bits, idx| bits | 1 << idx);
+    write_varint(buf, bits);
+}
+
+fn read_player_set<R: Read>(reader: &mut R) -> Result<PlayerBitSet, HandLogError> {
+    let bits = read_varint(reader)?;
+    if bits >> 16 != 0 {
+        return Err(HandLogError::Corrupt("invalid player set"));
+    }
+    let mut set = PlayerBitSet::default();
+    for idx in (0..16).filter(|i| bits & (1 << i) != 0) {
+        set.enable(idx);
+    }
+    Ok(set)
+}
+
+fn read_round<R: Read>(reader: &mut R) -> Result<Round, HandLogError> {
+    Round::try_from(read_u8(reader)?).map_err(|_| HandLogError::Corrupt("unknown round"))
+}
+
+fn rank_parts(rank: Rank) -> (u8, u32) {
+    match rank {
+        Rank::HighCard(v) => (0, v),
+        Rank::OnePair(v) => (1, v),
+        Rank::TwoPair(v) => (2, v),
+        Rank::ThreeOfAKind(v) => (3, v),
+        Rank::Straight(v) => (4, v),
+        Rank::Flush(v) => (5, v),
+        Rank::FullHouse(v) => (6, v),
+        Rank::FourOfAKind(v) => (7, v),
+        Rank::StraightFlush(v) => (8, v),
+    }
+}
+
+fn rank_from_parts(kind: u8, value: u32) -> Result<Rank, HandLogError> {
+    Ok(match kind {
+        0 => Rank::HighCard(value),
+        1 => Rank::OnePair(value),
+        2 => Rank::TwoPair(value),
+        3 => Rank::ThreeOfAKind(value),
+        4 => Rank::Straight(value),
+        5 => Rank::Flush(value),
+        6 => Rank::FullHouse(value),
+        7 => Rank::FourOfAKind(value),
+        8 => Rank::StraightFlush(value),
+        _ => return Err(HandLogError::Corrupt("unknown rank")),
+    })
+}
+
+fn read_card<R: Read>(reader: &mut R) -> Result<Card, HandLogError> {
+    Card::try_from_u8(read_u8(reader)?).map_err(|_| HandLogError::Corrupt("invalid card"))
+}
+
+fn read_idx<R: Read>(reader: &mut R) -> Result<usize, HandLogError> {
+    usize::try_from(read_varint(reader)?).map_err(|_| HandLogError::Corrupt("index out of range"))
+}
+
+/// Whole, non negative amounts below 2^31 are stored as a varint with the
+/// low bit clear. Everything else is the raw `f32` bits with the low bit
+/// set.
+fn write_amount(buf: &mut Vec<u8>, amount: f32) {
+    if amount.is_sign_positive() && amount.fract() == 0.0 && amount < 2_147_483_648.0 {
+        write_varint(buf, (amount as u64) << 1);
+    } else {
+        write_varint(buf, (u64::from(amount.to_bits()) << 1) | 1);
+    }
+}
+
+fn read_amount<R: Read>(reader: &mut R) -> Result<f32, HandLogError> {
+    let value = read_varint(reader)?;
+    if value & 1 == 0 {
+        Ok((value >> 1) as f32)
+    } else {
+        let bits =
+            u32::try_from(value >> 1).map_err(|_| HandLogError::Corrupt("amount out of range"))?;
+        Ok(f32::from_bits(bits))
+    }
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> Result<u64, HandLogError> {
+    let mut value = 0_u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(reader)?;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(HandLogError::Corrupt("varint too long"))
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8, HandLogError> {
+    let mut byte = [0_u8; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::agent::{CallingAgent, RandomAgent};
+    use crate::arena::historian::VecHistorian;
+    use crate::arena::{Agent, GameState, HoldemSimulationBuilder};
+
+    use super::*;
+
+    /// Run a few hands and return every action with its simulation id.
+    fn simulated_actions(num_hands: usize) -> Vec<HandLogRecord> {
+        let mut records = vec![];
+        for _ in 0..num_hands {
+            let historian = VecHistorian::default();
+            let storage = historian.get_storage();
+            let agents: Vec<Box<dyn Agent>> = vec![
+                Box::<RandomAgent>::default(),
+                Box::<RandomAgent>::default(),
+                Box::<CallingAgent>::default(),
+            ];
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 1.0, 0))
+                .agents(agents)
+                .historians(vec![Box::new(historian)])
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+            records.extend(storage.borrow().iter().map(|r| HandLogRecord {
+                id: sim.id,
+                action: r.action.clone(),
+            }));
+        }
+        records
+    }
+
+    fn write_all(records: &[HandLogRecord], hands_per_frame: usize) -> Vec<u8> {
+        let mut writer = HandLogWriter::new_with_options(vec![], 3, hands_per_frame).unwrap();
+        for record in records {
+            writer.write(record.id, &record.action).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn read_all(bytes: &[u8]) -> Result<Vec<HandLogRecord>, HandLogError> {
+        HandLogReader::new(bytes)?.collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let records = simulated_actions(20);
+        let bytes = write_all(&records, 4);
+        assert_eq!(records, read_all(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_draw_round_trip() {
+        let records: Vec<HandLogRecord> = [
+            Action::RoundAdvance(Round::ThirdDraw),
+            Action::Draw(DrawPayload {
+                idx: 2,
+                discarded: Hand::new_from_str("KsQd").unwrap(),
+                drawn: Hand::new_from_str("7c3h").unwrap(),
+            }),
+            Action::Draw(DrawPayload {
+                idx: 0,
+                discarded: Hand::default(),
+                drawn: Hand::default(),
+            }),
+        ]
+        .into_iter()
+        .map(|action| HandLogRecord { id: 3, action })
+        .collect();
+        let bytes = write_all(&records, 4);
+        assert_eq!(records, read_all(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_smaller_than_json() {
+        let records = simulated_actions(50);
+        let actions: Vec<&Action> = records.iter().map(|r| &r.action).collect();
+        let json = serde_json::to_vec(&actions).unwrap();
+        let bytes = write_all(&records, HandLogWriter::<Vec<u8>>::DEFAULT_HANDS_PER_FRAME);
+        assert!(bytes.len() * 10 < json.len());
+    }
+
+    #[test]
+    fn test_amounts() {
+        for amount in [0.0, 1.0, 0.5, 123456.0, -1.0, -0.0, 1e12, f32::MAX] {
+            let mut buf = vec![];
+            write_amount(&mut buf, amount);
+            let read = read_amount(&mut &buf[..]).unwrap();
+            assert_eq!(amount.to_bits(), read.to_bits());
+        }
+    }
+
+    #[test]
+    fn test_truncated_log_keeps_finished_frames() {
+        let records = simulated_actions(6);
+        let bytes = write_all(&records, 2);
+        // Chop off part of the last frame.
+        let truncated = &bytes[..bytes.len() - 4];
+
+        let read: Vec<_> = HandLogReader::new(truncated).unwrap().collect();
+        assert!(read.last().unwrap().is_err());
+        let read: Vec<HandLogRecord> = read.into_iter().filter_map(Result::ok).collect();
+        let ids: Vec<u128> = read.iter().map(|r| r.id).collect();
+        assert!(ids.contains(&records[0].id));
+        assert_eq!(&records[..read.len()], &read[..]);
+    }
+
+    #[test]
+    fn test_bad_header() {
+        assert!(matches!(read_all(b"nope"), Err(HandLogError::BadMagic)));
+        assert!(matches!(read_all(b"JSON!"), Err(HandLogError::BadMagic)));
+        assert!(matches!(
+            read_all(b"RSPL\x09"),
+            Err(HandLogError::UnsupportedVersion(9))
+        ));
+    }
+
+    #[test]
+    fn test_empty_log() {
+        let bytes = HandLogWriter::new(vec![]).unwrap().finish().unwrap();
+        assert!(read_all(&bytes).unwrap().is_empty());
+    }
+}
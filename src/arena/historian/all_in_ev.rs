@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use std::{cell::RefCell, rc::Rc};
+
+use super::{Historian, HistorianError};
+
+use crate::arena::GameState;
+use crate::arena::action::Action;
+use crate::arena::game_state::Round;
+use crate::core::{Card, CardIter, Hand};
+
+/// Actual and all-in adjusted results for a single seat.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AllInEvSeat {
+    /// How many hands this seat played.
+    pub hands: usize,
+    /// How many hands this seat was in an all-in before the river.
+    pub all_in_hands: usize,
+    /// The sum of the change in stack over every hand.
+    pub winnings: f32,
+    /// The sum of the change in stack over every hand, with the result of
+    /// each all-in replaced by its expected value.
+    pub all_in_ev_winnings: f32,
+}
+
+impl AllInEvSeat {
+    /// How much better (positive) or worse (negative) the seat did than
+    /// expected because of the cards dealt after all-ins.
+    pub fn luck(&self) -> f32 {
+        self.winnings - self.all_in_ev_winnings
+    }
+}
+
+/// The results for every seat accumulated by an `AllInEvHistorian`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AllInEvReport {
+    pub seats: Vec<AllInEvSeat>,
+}
+
+/// Storage for `AllInEvHistorian`.
+#[derive(Debug, Clone, Default)]
+pub struct AllInEvStorage {
+    report: AllInEvReport,
+    // The expected award for each player if there was
+    // an all-in during the current hand.
+    expected_awards: Option<Vec<f32>>,
+}
+
+impl AllInEvStorage {
+    pub fn report(&self) -> AllInEvReport {
+        self.report.clone()
+    }
+
+    fn ensure_players(&mut self, num_players: usize) {
+        if self.report.seats.len() < num_players {
+            self.report
+                .seats
+                .resize(num_players, AllInEvSeat::default());
+        }
+    }
+
+    fn record_all_in(&mut self, game_state: &GameState) -> Result<(), HistorianError> {
+        if self.expected_awards.is_some() {
+            return Ok(());
+        }
+        let expected = expected_awards(game_state)?;
+        let contenders = game_state.player_active | game_state.player_all_in;
+        for idx in contenders.ones() {
+            self.report.seats[idx].all_in_hands += 1;
+        }
+        self.expected_awards = Some(expected);
+        Ok(())
+    }
+
+    fn record_complete(&mut self, game_state: &GameState) {
+        let expected = self.expected_awards.take();
+        for idx in 0..game_state.num_players {
+            let reward = game_state.player_reward(idx);
+            let seat = &mut self.report.seats[idx];
+            seat.hands += 1;
+            seat.winnings += reward;
+            seat.all_in_ev_winnings += match &expected {
+                // Swap what was actually awarded for what was expected.
+                Some(expected) => reward - game_state.player_winnings[idx] + expected[idx],
+                None => reward,
+            };
+        }
+    }
+}
+
+/// A historian that separates luck from skill by tracking all-in expected
+/// value.
+///
+/// Whenever betting ends before the river because all but at most one of
+/// the remaining players are all-in, the exact equity of every remaining
+/// player is computed by enumerating every possible runout. When the hand
+/// completes the seat's actual result and the result with the all-in pot
+/// replaced by its expected value are both recorded.
+///
+/// Equity is enumerated exactly, so a preflop all-in evaluates every one of
+/// the ~1.7 million possible boards. Hands are ranked by the rules of the
+/// game, so only hold'em and Omaha can be tracked; other games return
+/// `HistorianError::UnsupportedVariant`.
+///
+/// Clones share the same storage so one historian can collect results over
+/// many simulations.
+#[derive(Debug, Clone, Default)]
+pub struct AllInEvHistorian {
+    storage: Rc<RefCell<AllInEvStorage>>,
+}
+
+impl AllInEvHistorian {
+    pub fn get_storage(&self) -> Rc<RefCell<AllInEvStorage>> {
+        self.storage.clone()
+    }
+
+    /// A snapshot of the results accumulated so far.
+    pub fn report(&self) -> AllInEvReport {
+        self.storage.borrow().report()
+    }
+}
+
+impl Historian for AllInEvHistorian {
+    fn record_action(
+        &mut self,
+        _id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        if !game_state.variant.has_board_equity() {
+            return Err(HistorianError::UnsupportedVariant(game_state.variant));
+        }
+        let mut storage = self.storage.try_borrow_mut()?;
+        storage.ensure_players(game_state.num_players);
+
+        match action {
+            Action::GameStart(_) => storage.expected_awards = None,
+            Action::RoundAdvance(Round::DealFlop | Round::DealTurn | Round::DealRiver)
+                if is_all_in(game_state) =>
+            {
+                storage.record_all_in(game_state)?
+            }
+            Action::RoundAdvance(Round::Complete) => storage.record_complete(game_state),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Betting is over when at least two players are left and no more than one
+/// of them can still bet.
+fn is_all_in(game_state: &GameState) -> bool {
+    let contenders = game_state.player_active | game_state.player_all_in;
+    contenders.count() >= 2 && game_state.player_active.count() <= 1
+}
+
+/// Compute the exact expected amount each player will be awarded from the
+/// pot given the cards dealt so far.
+///
+/// Every possible runout of the remaining board cards is enumerated and the
+/// pot is split the same way the simulation splits it at showdown,
+/// including side pots. Players that have folded get nothing.
+///
+/// Hands are ranked with `GameVariant::showdown_rank` for the game state's
+/// variant, which has to be one where the best high hand with the board
+/// takes the pot, see `GameVariant::has_board_equity`.
+pub fn expected_awards(game_state: &GameState) -> Result<Vec<f32>, HistorianError> {
+    let variant = game_state.variant;
+    if !variant.has_board_equity() {
+        return Err(HistorianError::UnsupportedVariant(variant));
+    }
+    let contenders: Vec<usize> = (game_state.player_active | game_state.player_all_in)
+        .ones()
+        .collect();
+
+    // Every card that has been dealt, including to players that folded,
+    // can't come on the board.
+    let remaining: Vec<Card> = (!game_state.dead_cards()).iter().collect();
+    let num_cards = 5_usize.saturating_sub(game_state.board.len());
+
+    // The split only depends on the order the hands finish in. Count how
+    // often each order happens rather than splitting the pot every time.
+    let mut outcomes: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut count_outcome = |runout: &[Card]| {
+        let mut board = game_state.board.to_vec();
+        board.extend_from_slice(runout);
+        let ranks: Vec<_> = contenders
+            .iter()
+            .map(|idx| {
+                let mut hand: Hand = game_state.hands[*idx];
+                hand.extend(runout.iter().copied());
+                variant.showdown_rank(&hand, &board)
+            })
+            .collect();
+        let tiers = ranks
+            .iter()
+            .map(|r| ranks.iter().filter(|other| *other > r).count() as u8)
+            .collect();
+        *outcomes.entry(tiers).or_default() += 1;
+    };
+
+    if num_cards == 0 {
+        count_outcome(&[]);
+    } else {
+        for runout in CardIter::new(&remaining, num_cards) {
+            count_outcome(&runout);
+        }
+    }
+
+    let total: usize = outcomes.values().sum();
+    let mut expected = vec![0.0_f64; game_state.num_players];
+    for (tiers, count) in outcomes {
+        let awards = split_pot(&game_state.player_bet, &contenders, &tiers);
+        let weight = count as f64 / total as f64;
+        for (e, a) in expected.iter_mut().zip(awards) {
+            *e += a * weight;
+        }
+    }
+    Ok(expected.into_iter().map(|e| e as f32).collect())
+}
+
+/// Split the pot between the contenders where `tiers[i]` is how many
+/// contenders have a better hand than `contenders[i]`.
+fn split_pot(player_bet: &[f32], contenders: &[usize], tiers: &[u8]) -> Vec<f64> {
+    let mut bets: Vec<f64> = player_bet.iter().map(|b| f64::from(*b)).collect();
+    let mut awards = vec![0.0; bets.len()];
+
+    // Money from players that folded goes to the best hand.
+    let mut folded_pot = 0.0;
+    for (idx, bet) in bets.iter_mut().enumerate() {
+        if !contenders.contains(&idx) {
+            folded_pot += *bet;
+            *bet = 0.0;
+        }
+    }
+
+    let mut tier_values: Vec<u8> = tiers.to_vec();
+    tier_values.sort();
+    tier_values.dedup();
+
+    for tier in tier_values {
+        let mut players: Vec<usize> = contenders
+            .iter()
+            .zip(tiers)
+            .filter(|(_, t)| **t == tier)
+            .map(|(idx, _)| *idx)
+            .collect();
+        players.sort_by(|a, b| bets[*a].partial_cmp(&bets[*b]).unwrap());
+
+        // Like the simulation, take side pots from smallest bet to largest.
+        for start_idx in 0..players.len() {
+            let max_wager = bets[players[start_idx]];
+            if max_wager <= 0.0 {
+                continue;
+            }
+            let mut pot = folded_pot;
+            folded_pot = 0.0;
+            for b in bets.iter_mut() {
+                let w = b.min(max_wager);
+                *b -= w;
+                pot += w;
+            }
+            let split = pot / (players.len() - start_idx) as f64;
+            for idx in &players[start_idx..] {
+                awards[*idx] += split;
+            }
+        }
+    }
+    awards
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use crate::arena::action::AgentAction;
+    use crate::arena::agent::VecReplayAgent;
+    use crate::arena::game_state::RoundData;
+    use crate::arena::variant::GameVariant;
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+    use crate::core::PlayerBitSet;
+
+    use super::*;
+
+    fn turn_all_in(board: &str, hands: [&str; 2]) -> GameState {
+        let board = crate::arena::hand_history::parse_cards(board).unwrap();
+        let hands = hands
+            .iter()
+            .map(|hole| {
+                let mut hand = Hand::new_from_str(hole).unwrap();
+                hand.extend(board.iter().copied());
+                hand
+            })
+            .collect();
+        let round_data = RoundData::new(2, 10.0, PlayerBitSet::new(2), 0);
+        let mut game_state = GameState::new(
+            Round::DealRiver,
+            round_data,
+            board,
+            hands,
+            vec![0.0, 0.0],
+            vec![100.0, 100.0],
+            10.0,
+            5.0,
+            0.0,
+            0,
+        );
+        // Both players are all in.
+        game_state.player_active = PlayerBitSet::default();
+        game_state
+    }
+
+    #[test]
+    fn test_expected_awards_turn() {
+        let game_state = turn_all_in("2c7d9h3s", ["AsAh", "KdKc"]);
+        assert!(is_all_in(&game_state));
+
+        let expected = expected_awards(&game_state).unwrap();
+        // Kings have two outs from 44 cards.
+        assert_relative_eq!(200.0 * 2.0 / 44.0, expected[1], epsilon = 1e-3);
+        assert_relative_eq!(200.0 * 42.0 / 44.0, expected[0], epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_expected_awards_omaha() {
+        // In hold'em the ace of hearts has a flush that only loses to a
+        // straight flush on the 8h or Kh. In Omaha it's a straight against
+        // a flush with two hearts in the hand.
+        let mut game_state = turn_all_in("JhTh9h3h", ["AhKsQd2c", "Qh2h4c5c"]);
+        let expected = expected_awards(&game_state).unwrap();
+        assert_relative_eq!(200.0 * 2.0 / 40.0, expected[1], epsilon = 1e-3);
+
+        game_state.variant = GameVariant::Omaha;
+        let expected = expected_awards(&game_state).unwrap();
+        assert_relative_eq!(0.0, expected[0]);
+        assert_relative_eq!(200.0, expected[1]);
+
+        game_state.variant = GameVariant::Stud;
+        assert!(matches!(
+            expected_awards(&game_state),
+            Err(HistorianError::UnsupportedVariant(GameVariant::Stud))
+        ));
+    }
+
+    #[test]
+    fn test_split_pot_side_pots() {
+        // Player 0 is all in for less and has the best hand.
+        let awards = split_pot(&[50.0, 100.0, 100.0, 20.0], &[0, 1, 2], &[0, 1, 2]);
+        assert_eq!(vec![170.0, 100.0, 0.0, 0.0], awards);
+
+        // Players 1 and 2 tie and split everything
+        let awards = split_pot(&[50.0, 100.0, 100.0], &[1, 2], &[0, 0]);
+        assert_eq!(vec![0.0, 125.0, 125.0], awards);
+    }
+
+    #[test]
+    fn test_historian_flop_all_in() {
+        let historian = AllInEvHistorian::default();
+        for _ in 0..3 {
+            // Both players call preflop then go all in on the flop.
+            let agents: Vec<Box<dyn Agent>> = (0..2)
+                .map(|_| {
+                    Box::new(VecReplayAgent::new_with_default(
+                        vec![AgentAction::Bet(10.0), AgentAction::AllIn],
+                        AgentAction::AllIn,
+                    )) as Box<dyn Agent>
+                })
+                .collect();
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+                .agents(agents)
+                .historians(vec![Box::new(historian.clone())])
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+        }
+
+        let report = historian.report();
+        assert_eq!(2, report.seats.len());
+        for seat in &report.seats {
+            assert_eq!(3, seat.hands);
+            assert_eq!(3, seat.all_in_hands);
+            assert_relative_eq!(seat.luck(), seat.winnings - seat.all_in_ev_winnings);
+        }
+        // Poker is zero sum with or without the luck.
+        let total: f32 = report.seats.iter().map(|s| s.winnings).sum();
+        let total_ev: f32 = report.seats.iter().map(|s| s.all_in_ev_winnings).sum();
+        assert_relative_eq!(0.0, total, epsilon = 1e-3);
+        assert_relative_eq!(0.0, total_ev, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_historian_omaha() {
+        let historian = AllInEvHistorian::default();
+        // Called preflop, then all in on the flop.
+        let agents: Vec<Box<dyn Agent>> = (0..2)
+            .map(|_| {
+                Box::new(VecReplayAgent::new_with_default(
+                    vec![AgentAction::Bet(10.0), AgentAction::AllIn],
+                    AgentAction::AllIn,
+                )) as Box<dyn Agent>
+            })
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .variant(GameVariant::Omaha)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let report = historian.report();
+        let total_ev: f32 = report.seats.iter().map(|s| s.all_in_ev_winnings).sum();
+        assert_relative_eq!(0.0, total_ev, epsilon = 1e-3);
+        for seat in &report.seats {
+            assert_eq!(1, seat.all_in_hands);
+        }
+    }
+
+    #[test]
+    fn test_historian_unsupported_variant() {
+        let historian = AllInEvHistorian::default();
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<crate::arena::agent::FoldingAgent>::default(),
+            Box::<crate::arena::agent::CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .variant(GameVariant::DeuceToSevenTripleDraw)
+            .historians(vec![Box::new(historian.clone())])
+            .panic_on_historian_error(false)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        assert!(sim.historians.is_empty());
+        assert!(historian.report().seats.is_empty());
+    }
+
+    #[test]
+    fn test_historian_no_all_in() {
+        let historian = AllInEvHistorian::default();
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<crate::arena::agent::FoldingAgent>::default(),
+            Box::<crate::arena::agent::CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let report = historian.report();
+        assert_eq!(0, report.seats[0].all_in_hands);
+        assert_eq!(-5.0, report.seats[0].winnings);
+        assert_eq!(-5.0, report.seats[0].all_in_ev_winnings);
+        assert_eq!(5.0, report.seats[1].all_in_ev_winnings);
+    }
+}
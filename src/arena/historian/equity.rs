@@ -0,0 +1,402 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rand::{Rng, seq::index::sample};
+
+use super::{Historian, HistorianError};
+
+use crate::arena::GameState;
+use crate::arena::action::Action;
+use crate::arena::game_state::Round;
+use crate::core::{Card, CardIter, Hand, rng};
+
+/// The equity of every player at the start of a single street.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreetEquity {
+    pub round: Round,
+    pub board: Vec<Card>,
+    /// The share of the pot each player would win on average if the hand
+    /// was checked down from here. Players that have folded have zero.
+    pub equity: Vec<f32>,
+}
+
+/// All the street equities for a single simulation.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandEquityRecord {
+    pub id: u128,
+    pub streets: Vec<StreetEquity>,
+}
+
+/// A historian that records the showdown equity of every player still in
+/// the hand at the start of each betting round.
+///
+/// Equity is calculated from everyone's hole cards. When there are few
+/// enough runouts left they are all enumerated and the result is exact,
+/// otherwise (usually only preflop) a random sample of runouts is used.
+/// Hands are ranked by the rules of the game, so only hold'em and Omaha
+/// can be recorded; other games return `HistorianError::UnsupportedVariant`.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::agent::CallingAgent;
+/// use rs_poker::arena::historian::ShowdownEquityHistorian;
+/// use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+///
+/// let historian = ShowdownEquityHistorian::new_with_max_runouts(1_000);
+/// let agents: Vec<Box<dyn Agent>> = vec![
+///     Box::<CallingAgent>::default(),
+///     Box::<CallingAgent>::default(),
+/// ];
+/// let mut sim = HoldemSimulationBuilder::default()
+///     .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+///     .agents(agents)
+///     .historians(vec![Box::new(historian.clone())])
+///     .build()
+///     .unwrap();
+/// sim.run(&mut rand::rng());
+///
+/// let records = historian.get_storage();
+/// // Preflop, flop, turn and river
+/// assert_eq!(4, records.borrow()[0].streets.len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShowdownEquityHistorian {
+    max_runouts: usize,
+    records: Rc<RefCell<Vec<HandEquityRecord>>>,
+}
+
+impl ShowdownEquityHistorian {
+    /// By default up to this many runouts are enumerated before switching
+    /// to sampling.
+    pub const DEFAULT_MAX_RUNOUTS: usize = 10_000;
+
+    pub fn new_with_max_runouts(max_runouts: usize) -> Self {
+        Self {
+            max_runouts,
+            records: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    pub fn get_storage(&self) -> Rc<RefCell<Vec<HandEquityRecord>>> {
+        self.records.clone()
+    }
+}
+
+impl Default for ShowdownEquityHistorian {
+    fn default() -> Self {
+        Self::new_with_max_runouts(Self::DEFAULT_MAX_RUNOUTS)
+    }
+}
+
+impl Historian for ShowdownEquityHistorian {
+    fn record_action(
+        &mut self,
+        id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        if let Action::RoundAdvance(
+            round @ (Round::Preflop | Round::Flop | Round::Turn | Round::River),
+        ) = action
+        {
+            let equity = showdown_equity(game_state, self.max_runouts, &mut rng())?;
+            let street = StreetEquity {
+                round,
+                board: game_state.board.to_vec(),
+                equity,
+            };
+
+            let mut records = self.records.try_borrow_mut()?;
+            match records.last_mut() {
+                Some(record) if record.id == id => record.streets.push(street),
+                _ => records.push(HandEquityRecord {
+                    id,
+                    streets: vec![street],
+                }),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Calculate the share of the pot each player still in the hand would win
+/// if every remaining board card was dealt and the hand was shown down.
+///
+/// Ties split the share equally. If there are more than `max_runouts`
+/// possible runouts then `max_runouts` of them are sampled instead.
+///
+/// Hands are ranked with `GameVariant::showdown_rank` for the game state's
+/// variant, which has to be one where the best high hand with the board
+/// takes the pot, see `GameVariant::has_board_equity`.
+pub fn showdown_equity<R: Rng>(
+    game_state: &GameState,
+    max_runouts: usize,
+    rng: &mut R,
+) -> Result<Vec<f32>, HistorianError> {
+    let variant = game_state.variant;
+    if !variant.has_board_equity() {
+        return Err(HistorianError::UnsupportedVariant(variant));
+    }
+    let contenders: Vec<usize> = (game_state.player_active | game_state.player_all_in)
+        .ones()
+        .collect();
+    let mut equity = vec![0.0_f64; game_state.num_players];
+    if contenders.len() == 1 {
+        equity[contenders[0]] = 1.0;
+    }
+    if contenders.len() <= 1 {
+        return Ok(equity.into_iter().map(|e| e as f32).collect());
+    }
+
+    let remaining: Vec<Card> = (!game_state.dead_cards()).iter().collect();
+    let num_cards = 5_usize.saturating_sub(game_state.board.len());
+
+    let mut total = 0_usize;
+    let mut count_runout = |runout: &[Card]| {
+        let mut board = game_state.board.to_vec();
+        board.extend_from_slice(runout);
+        let ranks: Vec<_> = contenders
+            .iter()
+            .map(|idx| {
+                let mut hand: Hand = game_state.hands[*idx];
+                hand.extend(runout.iter().copied());
+                variant.showdown_rank(&hand, &board)
+            })
+            .collect();
+        let best = ranks.iter().max().unwrap();
+        let winners = ranks.iter().filter(|r| *r == best).count() as f64;
+        for (idx, rank) in contenders.iter().zip(&ranks) {
+            if rank == best {
+                equity[*idx] += 1.0 / winners;
+            }
+        }
+        total += 1;
+    };
+
+    if num_cards == 0 {
+        count_runout(&[]);
+    } else if num_combinations(remaining.len(), num_cards) <= max_runouts as u64 {
+        for runout in CardIter::new(&remaining, num_cards) {
+            count_runout(&runout);
+        }
+    } else {
+        for _ in 0..max_runouts.max(1) {
+            let runout: Vec<Card> = sample(rng, remaining.len(), num_cards)
+                .into_iter()
+                .map(|i| remaining[i])
+                .collect();
+            count_runout(&runout);
+        }
+    }
+
+    Ok(equity
+        .into_iter()
+        .map(|e| (e / total as f64) as f32)
+        .collect())
+}
+
+fn num_combinations(n: usize, k: usize) -> u64 {
+    (0..k as u64).fold(1, |acc, i| acc * (n as u64 - i) / (i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use crate::arena::agent::{AllInAgent, FoldingAgent};
+    use crate::arena::game_state::RoundData;
+    use crate::arena::variant::GameVariant;
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+    use crate::core::PlayerBitSet;
+
+    use super::*;
+
+    fn game_state_with(board: &str, hands: &[&str]) -> GameState {
+        let board = crate::arena::hand_history::parse_cards(board).unwrap();
+        let hands: Vec<Hand> = hands
+            .iter()
+            .map(|h| {
+                let mut hand = Hand::new_from_str(h).unwrap();
+                hand.extend(board.iter().copied());
+                hand
+            })
+            .collect();
+        let num_players = hands.len();
+        GameState::new(
+            Round::Flop,
+            RoundData::new(num_players, 10.0, PlayerBitSet::new(num_players), 0),
+            board,
+            hands,
+            vec![100.0; num_players],
+            vec![10.0; num_players],
+            10.0,
+            5.0,
+            0.0,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_num_combinations() {
+        assert_eq!(990, num_combinations(45, 2));
+        assert_eq!(1_712_304, num_combinations(48, 5));
+    }
+
+    #[test]
+    fn test_turn_equity() {
+        let game_state = game_state_with("2c7d9h3s", &["AsAh", "KdKc"]);
+        let equity = showdown_equity(&game_state, 10_000, &mut rng()).unwrap();
+        assert_relative_eq!(42.0 / 44.0, equity[0], epsilon = 1e-5);
+        assert_relative_eq!(2.0 / 44.0, equity[1], epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_river_tie() {
+        let game_state = game_state_with("2c3d4h5s6s", &["AsAh", "KdKc"]);
+        let equity = showdown_equity(&game_state, 10_000, &mut rng()).unwrap();
+        assert_eq!(vec![0.5, 0.5], equity);
+    }
+
+    #[test]
+    fn test_folded_player_has_no_equity() {
+        let mut game_state = game_state_with("2c7d9h", &["AsAh", "KdKc", "QsQh"]);
+        game_state.player_active.disable(2);
+        let equity = showdown_equity(&game_state, 10_000, &mut rng()).unwrap();
+        assert_eq!(0.0, equity[2]);
+        assert_relative_eq!(1.0, equity.iter().sum::<f32>(), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_omaha_equity() {
+        // The ace of hearts makes a flush in hold'em, but Omaha needs two
+        // hearts in the hand.
+        let mut game_state = game_state_with("JhTh9h3h8s", &["AhKsQd2c", "Qh2h4c5c"]);
+        let equity = showdown_equity(&game_state, 10_000, &mut rng()).unwrap();
+        assert_eq!(vec![1.0, 0.0], equity);
+
+        game_state.variant = GameVariant::Omaha;
+        let equity = showdown_equity(&game_state, 10_000, &mut rng()).unwrap();
+        assert_eq!(vec![0.0, 1.0], equity);
+
+        game_state.variant = GameVariant::DeuceToSevenTripleDraw;
+        assert!(matches!(
+            showdown_equity(&game_state, 10_000, &mut rng()),
+            Err(HistorianError::UnsupportedVariant(
+                GameVariant::DeuceToSevenTripleDraw
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_sampled_preflop() {
+        let game_state = game_state_with("", &["AsAh", "7c2d"]);
+        let equity = showdown_equity(&game_state, 2_000, &mut rng()).unwrap();
+        // Aces are about an 87% favorite
+        assert!(equity[0] > 0.8);
+        assert_relative_eq!(1.0, equity.iter().sum::<f32>(), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_historian_records_streets() {
+        let historian = ShowdownEquityHistorian::new_with_max_runouts(500);
+        for _ in 0..2 {
+            let agents: Vec<Box<dyn Agent>> =
+                vec![Box::<AllInAgent>::default(), Box::<AllInAgent>::default()];
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+                .agents(agents)
+                .historians(vec![Box::new(historian.clone())])
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+        }
+
+        let records = historian.get_storage();
+        let records = records.borrow();
+        assert_eq!(2, records.len());
+        for record in records.iter() {
+            let rounds: Vec<Round> = record.streets.iter().map(|s| s.round).collect();
+            assert_eq!(
+                vec![Round::Preflop, Round::Flop, Round::Turn, Round::River],
+                rounds
+            );
+            for street in &record.streets {
+                assert_relative_eq!(1.0, street.equity.iter().sum::<f32>(), epsilon = 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_historian_omaha() {
+        let historian = ShowdownEquityHistorian::new_with_max_runouts(500);
+        let agents: Vec<Box<dyn Agent>> =
+            vec![Box::<AllInAgent>::default(), Box::<AllInAgent>::default()];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .variant(GameVariant::Omaha)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let records = historian.get_storage();
+        let records = records.borrow();
+        assert_eq!(4, records[0].streets.len());
+        let river = records[0].streets.last().unwrap();
+        let board: Vec<Card> = sim.game_state.board.to_vec();
+        let ranks: Vec<_> = sim
+            .game_state
+            .hands
+            .iter()
+            .map(|hand| GameVariant::Omaha.showdown_rank(hand, &board))
+            .collect();
+        let expected = match ranks[0].cmp(&ranks[1]) {
+            std::cmp::Ordering::Greater => vec![1.0, 0.0],
+            std::cmp::Ordering::Less => vec![0.0, 1.0],
+            std::cmp::Ordering::Equal => vec![0.5, 0.5],
+        };
+        assert_eq!(expected, river.equity);
+    }
+
+    #[test]
+    fn test_historian_lowball_unsupported() {
+        let historian = ShowdownEquityHistorian::default();
+        let agents: Vec<Box<dyn Agent>> =
+            vec![Box::<AllInAgent>::default(), Box::<AllInAgent>::default()];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .variant(GameVariant::DeuceToSevenTripleDraw)
+            .historians(vec![Box::new(historian.clone())])
+            .panic_on_historian_error(false)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        // The historian errored and was dropped before recording anything.
+        assert!(sim.historians.is_empty());
+        assert!(historian.get_storage().borrow().is_empty());
+    }
+
+    #[test]
+    fn test_historian_fold_preflop() {
+        let historian = ShowdownEquityHistorian::default();
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<FoldingAgent>::default(),
+            Box::<FoldingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let records = historian.get_storage();
+        assert_eq!(1, records.borrow()[0].streets.len());
+    }
+}
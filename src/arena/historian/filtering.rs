@@ -0,0 +1,259 @@
+use crate::arena::GameState;
+use crate::arena::action::Action;
+use crate::arena::game_state::Round;
+
+use super::{Historian, HistorianError};
+
+type HandPredicate = Box<dyn Fn(&GameState) -> bool>;
+
+/// A historian that wraps another historian and only passes along some of
+/// the hands it sees. This keeps the cost of logging bounded on very large
+/// runs.
+///
+/// Hands can be sampled (only 1 in every N hands is considered) and
+/// filtered with predicates on the game state at the end of the hand.
+/// Every action of a hand that passes is forwarded in order, and nothing is
+/// forwarded for hands that don't.
+///
+/// Predicates can only be checked once the hand is over, so while they are
+/// set the actions of the current hand are buffered. Sampling is decided at
+/// the start of a hand so hands that aren't sampled are never buffered.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::historian::{FilteringHistorian, VecHistorian};
+///
+/// let inner = VecHistorian::default();
+/// let storage = inner.get_storage();
+///
+/// // Keep one in ten hands, and only if they went to showdown with a pot
+/// // of at least 20 big blinds.
+/// let historian = FilteringHistorian::new(Box::new(inner))
+///     .sample_one_in(10)
+///     .only_showdowns()
+///     .min_pot_big_blinds(20.0);
+/// ```
+pub struct FilteringHistorian {
+    inner: Box<dyn Historian>,
+    sample_rate: usize,
+    predicates: Vec<HandPredicate>,
+
+    hands_seen: usize,
+    current_id: Option<u128>,
+    current_sampled: bool,
+    buffer: Vec<(GameState, Action)>,
+}
+
+impl FilteringHistorian {
+    /// Wrap a historian. With no sampling or predicates every action is
+    /// passed straight through.
+    pub fn new(inner: Box<dyn Historian>) -> Self {
+        Self {
+            inner,
+            sample_rate: 1,
+            predicates: vec![],
+            hands_seen: 0,
+            current_id: None,
+            current_sampled: true,
+            buffer: vec![],
+        }
+    }
+
+    /// Only consider the first hand out of every `n` hands.
+    pub fn sample_one_in(mut self, n: usize) -> Self {
+        self.sample_rate = n.max(1);
+        self
+    }
+
+    /// Only keep hands that pass `predicate`. It's given the game state at
+    /// the end of the hand.
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&GameState) -> bool + 'static,
+    {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// Only keep hands that went to showdown.
+    pub fn only_showdowns(self) -> Self {
+        self.filter(|game_state| game_state.round_before == Round::Showdown)
+    }
+
+    /// Only keep hands where the pot was at least `big_blinds` big blinds.
+    pub fn min_pot_big_blinds(self, big_blinds: f32) -> Self {
+        self.filter(move |game_state| game_state.total_pot >= big_blinds * game_state.big_blind)
+    }
+
+    fn start_hand(&mut self, id: u128) {
+        self.current_sampled = self.hands_seen.is_multiple_of(self.sample_rate);
+        self.hands_seen += 1;
+        self.current_id = Some(id);
+        self.buffer.clear();
+    }
+
+    fn finish_hand(&mut self, id: u128, game_state: &GameState) -> Result<(), HistorianError> {
+        let buffer = std::mem::take(&mut self.buffer);
+        if self.predicates.iter().all(|p| p(game_state)) {
+            for (game_state, action) in buffer {
+                self.inner.record_action(id, &game_state, action)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Historian for FilteringHistorian {
+    fn record_action(
+        &mut self,
+        id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        if self.current_id != Some(id) || matches!(action, Action::GameStart(_)) {
+            self.start_hand(id);
+        }
+
+        if !self.current_sampled {
+            return Ok(());
+        }
+
+        if self.predicates.is_empty() {
+            return self.inner.record_action(id, game_state, action);
+        }
+
+        let is_complete = action == Action::RoundAdvance(Round::Complete);
+        self.buffer.push((game_state.clone(), action));
+        if is_complete {
+            self.finish_hand(id, game_state)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::action::GameStartPayload;
+    use crate::arena::agent::{CallingAgent, FoldingAgent};
+    use crate::arena::historian::{HistoryRecord, VecHistorian};
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::*;
+
+    /// Run a single heads up hand and hand back the historian so it can see
+    /// more hands.
+    fn run_hand(historian: Box<dyn Historian>, calling: bool) -> Box<dyn Historian> {
+        let agents: Vec<Box<dyn Agent>> = if calling {
+            vec![
+                Box::<CallingAgent>::default(),
+                Box::<CallingAgent>::default(),
+            ]
+        } else {
+            vec![
+                Box::<FoldingAgent>::default(),
+                Box::<FoldingAgent>::default(),
+            ]
+        };
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state)
+            .agents(agents)
+            .historians(vec![historian])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        sim.historians.pop().unwrap()
+    }
+
+    fn num_hands(records: &[HistoryRecord]) -> usize {
+        records
+            .iter()
+            .filter(|r| matches!(r.action, Action::GameStart(_)))
+            .count()
+    }
+
+    #[test]
+    fn test_pass_through() {
+        let inner = VecHistorian::default();
+        let storage = inner.get_storage();
+        run_hand(Box::new(FilteringHistorian::new(Box::new(inner))), false);
+
+        let records = storage.borrow();
+        assert_eq!(1, num_hands(&records));
+        assert_eq!(
+            Some(&Action::RoundAdvance(Round::Complete)),
+            records.last().map(|r| &r.action)
+        );
+    }
+
+    #[test]
+    fn test_only_showdowns() {
+        let inner = VecHistorian::default();
+        let storage = inner.get_storage();
+        let historian = FilteringHistorian::new(Box::new(inner)).only_showdowns();
+
+        let historian = run_hand(Box::new(historian), false);
+        assert!(storage.borrow().is_empty());
+
+        run_hand(historian, true);
+        let records = storage.borrow();
+        assert_eq!(1, num_hands(&records));
+        assert_eq!(
+            Some(&Action::RoundAdvance(Round::Complete)),
+            records.last().map(|r| &r.action)
+        );
+    }
+
+    #[test]
+    fn test_min_pot() {
+        let inner = VecHistorian::default();
+        let storage = inner.get_storage();
+        // Calling agents see a pot of 2 big blinds.
+        let historian = FilteringHistorian::new(Box::new(inner)).min_pot_big_blinds(3.0);
+        run_hand(Box::new(historian), true);
+        assert!(storage.borrow().is_empty());
+
+        let inner = VecHistorian::default();
+        let storage = inner.get_storage();
+        let historian = FilteringHistorian::new(Box::new(inner)).min_pot_big_blinds(2.0);
+        run_hand(Box::new(historian), true);
+        assert_eq!(1, num_hands(&storage.borrow()));
+    }
+
+    #[test]
+    fn test_sampling() {
+        let inner = VecHistorian::default();
+        let storage = inner.get_storage();
+        let mut historian: Box<dyn Historian> =
+            Box::new(FilteringHistorian::new(Box::new(inner)).sample_one_in(3));
+        for _ in 0..10 {
+            historian = run_hand(historian, false);
+        }
+
+        // Hands 0, 3, 6 and 9
+        assert_eq!(4, num_hands(&storage.borrow()));
+    }
+
+    #[test]
+    fn test_new_id_starts_hand() {
+        let inner = VecHistorian::default();
+        let storage = inner.get_storage();
+        let mut historian = FilteringHistorian::new(Box::new(inner)).sample_one_in(2);
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        let start = Action::GameStart(GameStartPayload {
+            ante: 0.0,
+            small_blind: 5.0,
+            big_blind: 10.0,
+        });
+        for id in 0..4 {
+            historian
+                .record_action(id, &game_state, start.clone())
+                .unwrap();
+            historian
+                .record_action(id, &game_state, Action::RoundAdvance(Round::Ante))
+                .unwrap();
+        }
+        assert_eq!(4, storage.borrow().len());
+    }
+}
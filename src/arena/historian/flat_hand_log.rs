@@ -0,0 +1,114 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::arena::GameState;
+use crate::arena::action::{Action, PlayedActionPayload};
+use crate::arena::flat_hand_log::FlatHandLogWriter;
+use crate::arena::game_state::Round;
+
+use super::{Historian, HistorianError};
+
+/// A historian that writes one FlatBuffers `HandRecord` per completed hand.
+/// See `rs_poker::arena::flat_hand_log` for the format and for reading it
+/// back.
+///
+/// # Example
+///
+/// ```no_run
+/// use rs_poker::arena::historian::FlatHandLogHistorian;
+///
+/// let historian = FlatHandLogHistorian::create("hands.rsfb").unwrap();
+/// ```
+pub struct FlatHandLogHistorian<W: Write = BufWriter<File>> {
+    writer: FlatHandLogWriter<W>,
+    actions: Vec<PlayedActionPayload>,
+}
+
+impl FlatHandLogHistorian {
+    /// Create (or truncate) a log file at `path`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, HistorianError> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(Self::new(FlatHandLogWriter::new(file)?))
+    }
+}
+
+impl<W: Write> FlatHandLogHistorian<W> {
+    pub fn new(writer: FlatHandLogWriter<W>) -> Self {
+        Self {
+            writer,
+            actions: vec![],
+        }
+    }
+
+    /// Write the index, returning the underlying writer.
+    pub fn finish(self) -> Result<W, HistorianError> {
+        Ok(self.writer.finish()?)
+    }
+}
+
+impl<W: Write> Historian for FlatHandLogHistorian<W> {
+    fn record_action(
+        &mut self,
+        id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        match action {
+            Action::GameStart(_) => self.actions.clear(),
+            Action::PlayedAction(payload) => self.actions.push(payload),
+            Action::RoundAdvance(Round::Complete) => {
+                self.writer.write_hand(id, game_state, &self.actions)?;
+                self.actions.clear();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::agent::CallingAgent;
+    use crate::arena::flat_hand_log::FlatHandLog;
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::*;
+
+    #[test]
+    fn test_log_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("hands.rsfb");
+        let mut historian: Box<dyn Historian> =
+            Box::new(FlatHandLogHistorian::create(&path).unwrap());
+
+        let mut ids = vec![];
+        for _ in 0..3 {
+            let agents: Vec<Box<dyn Agent>> = vec![
+                Box::<CallingAgent>::default(),
+                Box::<CallingAgent>::default(),
+            ];
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+                .agents(agents)
+                .historians(vec![historian])
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+            ids.push(sim.id);
+            historian = sim.historians.pop().unwrap();
+        }
+        // Dropping the historian writes the index.
+        drop(historian);
+
+        let bytes = std::fs::read(&path).unwrap();
+        let log = FlatHandLog::new(&bytes).unwrap();
+        let read_ids: Vec<u128> = log.iter().map(|r| r.unwrap().id()).collect();
+        assert_eq!(ids, read_ids);
+
+        let record = log.get(2).unwrap();
+        assert_eq!(5, record.board().unwrap().len());
+        // Both players call down, so every street has actions.
+        assert!(record.action_kinds().unwrap().len() >= 4);
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::arena::GameState;
+use crate::arena::action::Action;
+use crate::arena::hand_log::HandLogWriter;
+
+use super::{Historian, HistorianError};
+
+/// A historian that appends every action to a compact binary hand log.
+/// See `rs_poker::arena::hand_log` for the format and for reading it back.
+///
+/// # Example
+///
+/// ```no_run
+/// use rs_poker::arena::historian::HandLogHistorian;
+///
+/// let historian = HandLogHistorian::create("hands.rspl").unwrap();
+/// ```
+pub struct HandLogHistorian<W: Write = BufWriter<File>> {
+    writer: HandLogWriter<W>,
+}
+
+impl HandLogHistorian {
+    /// Create (or truncate) a log file at `path`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, HistorianError> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(Self::new(HandLogWriter::new(file)?))
+    }
+}
+
+impl<W: Write> HandLogHistorian<W> {
+    pub fn new(writer: HandLogWriter<W>) -> Self {
+        Self { writer }
+    }
+
+    /// Finish the log, returning the underlying writer.
+    pub fn finish(self) -> Result<W, HistorianError> {
+        Ok(self.writer.finish()?)
+    }
+}
+
+impl<W: Write> Historian for HandLogHistorian<W> {
+    fn record_action(
+        &mut self,
+        id: u128,
+        _game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        Ok(self.writer.write(id, &action)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::agent::CallingAgent;
+    use crate::arena::hand_log::HandLogReader;
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::*;
+
+    #[test]
+    fn test_log_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("hands.rspl");
+        let mut historian: Box<dyn Historian> = Box::new(HandLogHistorian::create(&path).unwrap());
+
+        let mut ids = vec![];
+        for _ in 0..3 {
+            let agents: Vec<Box<dyn Agent>> = vec![
+                Box::<CallingAgent>::default(),
+                Box::<CallingAgent>::default(),
+            ];
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+                .agents(agents)
+                .historians(vec![historian])
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+            ids.push(sim.id);
+            historian = sim.historians.pop().unwrap();
+        }
+        // Dropping the historian finishes the log.
+        drop(historian);
+
+        let records: Vec<_> = HandLogReader::new(File::open(&path).unwrap())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let mut read_ids: Vec<u128> = records.iter().map(|r| r.id).collect();
+        read_ids.dedup();
+        assert_eq!(ids, read_ids);
+        assert!(matches!(records[0].action, Action::GameStart(_)));
+    }
+}
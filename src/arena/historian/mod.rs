@@ -1,4 +1,4 @@
-use super::{GameState, action::Action};
+use super::{GameState, action::Action, variant::GameVariant};
 use thiserror::Error;
 
 /// HistorianError is the error type for historian implementations.
@@ -19,6 +19,11 @@ pub enum HistorianError {
     CFRUnexpectedNode(String),
     #[error("Expected Node not found in tree")]
     CFRNodeNotFound,
+    #[error("Can't rank hands in {0}")]
+    UnsupportedVariant(GameVariant),
+    #[cfg(feature = "postgres")]
+    #[error("Database Error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
 }
 
 /// Historians are a way for the simulation to record or notify of
@@ -90,22 +95,67 @@ where
     }
 }
 
+mod all_in_ev;
+mod equity;
 mod failing;
+mod filtering;
 mod fn_historian;
+mod multi;
 mod null;
+mod player_stats;
+mod spot_finder;
 mod stats_tracking;
+mod strength_timeline;
 mod vec;
 
 #[cfg(any(test, feature = "serde"))]
 mod directory_historian;
 
+#[cfg(feature = "websocket")]
+mod websocket;
+
+#[cfg(feature = "hand-log")]
+mod hand_log;
+
+#[cfg(feature = "flatbuffers")]
+mod flat_hand_log;
+
+#[cfg(feature = "postgres")]
+mod postgres;
+
+pub use all_in_ev::{
+    AllInEvHistorian, AllInEvReport, AllInEvSeat, AllInEvStorage, expected_awards,
+};
+pub use equity::{HandEquityRecord, ShowdownEquityHistorian, StreetEquity, showdown_equity};
 pub use failing::FailingHistorian;
+pub use filtering::FilteringHistorian;
 pub use fn_historian::FnHistorian;
+pub use multi::{ChildHistorianStats, MultiHistorian};
 pub use null::NullHistorian;
+pub use player_stats::{PlayerStatsHistorian, PlayerStatsReport, PlayerStatsStorage, SeatStats};
+pub use spot_finder::{
+    InterestingSpot, InterestingSpotHistorian, RunoutKind, SpotCriteria, SpotReason,
+};
+pub use strength_timeline::{
+    HandCategory, HandStrengthTimeline, HandStrengthTimelineHistorian, StrengthPoint,
+    hand_percentile, omaha_percentile,
+};
 pub use vec::HistoryRecord;
 pub use vec::VecHistorian;
 
 #[cfg(any(test, feature = "serde"))]
 pub use directory_historian::DirectoryHistorian;
 
+#[cfg(feature = "websocket")]
+pub use websocket::WebSocketHistorian;
+
+#[cfg(feature = "hand-log")]
+pub use hand_log::HandLogHistorian;
+
+#[cfg(feature = "flatbuffers")]
+pub use flat_hand_log::FlatHandLogHistorian;
+
+#[cfg(feature = "postgres")]
+pub use postgres::{POSTGRES_SCHEMA, PostgresHistorian};
+
 pub use stats_tracking::StatsTrackingHistorian;
@@ -0,0 +1,198 @@
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::{cell::RefCell, rc::Rc};
+
+use tracing::{Level, event};
+
+use crate::arena::GameState;
+use crate::arena::action::Action;
+
+use super::{Historian, HistorianError};
+
+/// What happened to a single child of a `MultiHistorian`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChildHistorianStats {
+    /// Actions the child recorded without error.
+    pub recorded: usize,
+    /// Actions the child returned an error for.
+    pub errors: usize,
+    /// Actions the child panicked on.
+    pub panics: usize,
+    /// The most recent error or panic message.
+    pub last_error: Option<String>,
+    /// Disabled children no longer receive actions.
+    pub disabled: bool,
+}
+
+/// A historian that sends every action to many child historians while
+/// isolating them from each other and from the simulation.
+///
+/// An error or a panic from one child never reaches the simulation, so it
+/// can't stop the game or the other children. Instead it is counted in
+/// that child's `ChildHistorianStats`. A child that panics is disabled since
+/// its state can't be trusted afterwards. A child that returns errors keeps
+/// receiving actions unless `disable_after_errors` is set.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::historian::{FailingHistorian, MultiHistorian, VecHistorian};
+///
+/// let vec_historian = VecHistorian::default();
+/// let historian = MultiHistorian::new(vec![Box::new(vec_historian), Box::new(FailingHistorian)])
+///     .disable_after_errors(3);
+/// let stats = historian.get_stats();
+/// ```
+pub struct MultiHistorian {
+    children: Vec<Box<dyn Historian>>,
+    max_errors: Option<usize>,
+    stats: Rc<RefCell<Vec<ChildHistorianStats>>>,
+}
+
+impl MultiHistorian {
+    pub fn new(children: Vec<Box<dyn Historian>>) -> Self {
+        let stats = vec![ChildHistorianStats::default(); children.len()];
+        Self {
+            children,
+            max_errors: None,
+            stats: Rc::new(RefCell::new(stats)),
+        }
+    }
+
+    /// Stop sending actions to a child once it has returned `max_errors`
+    /// errors.
+    pub fn disable_after_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = Some(max_errors);
+        self
+    }
+
+    /// The per child stats, in the same order as the children were given.
+    pub fn get_stats(&self) -> Rc<RefCell<Vec<ChildHistorianStats>>> {
+        self.stats.clone()
+    }
+}
+
+impl Historian for MultiHistorian {
+    fn record_action(
+        &mut self,
+        id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        let mut stats = self.stats.try_borrow_mut()?;
+
+        for (idx, (child, child_stats)) in
+            self.children.iter_mut().zip(stats.iter_mut()).enumerate()
+        {
+            if child_stats.disabled {
+                continue;
+            }
+
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                child.record_action(id, game_state, action.clone())
+            }));
+
+            match result {
+                Ok(Ok(())) => child_stats.recorded += 1,
+                Ok(Err(error)) => {
+                    event!(Level::WARN, idx, ?error, "child_historian_error");
+                    child_stats.errors += 1;
+                    child_stats.last_error = Some(error.to_string());
+                    if self.max_errors.is_some_and(|max| child_stats.errors >= max) {
+                        child_stats.disabled = true;
+                    }
+                }
+                Err(panic) => {
+                    let message = panic
+                        .downcast_ref::<&str>()
+                        .map(|s| s.to_string())
+                        .or_else(|| panic.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "unknown panic".to_string());
+                    event!(Level::ERROR, idx, message, "child_historian_panic");
+                    child_stats.panics += 1;
+                    child_stats.last_error = Some(message);
+                    child_stats.disabled = true;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::agent::CallingAgent;
+    use crate::arena::historian::{FailingHistorian, FnHistorian, VecHistorian};
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::*;
+
+    fn run(historian: MultiHistorian) {
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<CallingAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .historians(vec![Box::new(historian)])
+            .panic_on_historian_error(true)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+    }
+
+    #[test]
+    fn test_failing_child_is_isolated() {
+        let vec_historian = VecHistorian::default();
+        let records = vec_historian.get_storage();
+        let historian =
+            MultiHistorian::new(vec![Box::new(FailingHistorian), Box::new(vec_historian)]);
+        let stats = historian.get_stats();
+
+        run(historian);
+
+        let stats = stats.borrow();
+        let num_records = records.borrow().len();
+        assert!(num_records > 0);
+        assert_eq!(num_records, stats[0].errors);
+        assert_eq!(0, stats[0].recorded);
+        assert!(!stats[0].disabled);
+        assert_eq!(num_records, stats[1].recorded);
+        assert_eq!(0, stats[1].errors);
+    }
+
+    #[test]
+    fn test_disable_after_errors() {
+        let historian =
+            MultiHistorian::new(vec![Box::new(FailingHistorian)]).disable_after_errors(2);
+        let stats = historian.get_stats();
+
+        run(historian);
+
+        let stats = stats.borrow();
+        assert_eq!(2, stats[0].errors);
+        assert!(stats[0].disabled);
+        assert_eq!(
+            Some(HistorianError::UnableToRecordAction.to_string()),
+            stats[0].last_error
+        );
+    }
+
+    #[test]
+    fn test_panicking_child_is_disabled() {
+        let vec_historian = VecHistorian::default();
+        let records = vec_historian.get_storage();
+        let panicking = FnHistorian::new(|_, _, _| panic!("historian blew up"));
+        let historian = MultiHistorian::new(vec![Box::new(panicking), Box::new(vec_historian)]);
+        let stats = historian.get_stats();
+
+        run(historian);
+
+        let stats = stats.borrow();
+        assert_eq!(1, stats[0].panics);
+        assert!(stats[0].disabled);
+        assert_eq!(Some("historian blew up".to_string()), stats[0].last_error);
+        assert_eq!(records.borrow().len(), stats[1].recorded);
+    }
+}
@@ -0,0 +1,354 @@
+use std::{cell::RefCell, rc::Rc};
+
+use super::{Historian, HistorianError};
+
+use crate::arena::GameState;
+use crate::arena::action::{Action, PlayedActionPayload};
+use crate::arena::game_state::Round;
+use crate::stats::ResultStats;
+
+/// The standard tracker stats for a single seat.
+///
+/// Counts are per hand for the preflop stats (a player that calls and then
+/// raises in the same hand has one VPIP hand and one PFR hand) and per
+/// action for the postflop aggression stats.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeatStats {
+    /// How many hands this seat was dealt into.
+    pub hands: usize,
+    /// Hands where the player voluntarily put money in preflop.
+    pub vpip_hands: usize,
+    /// Hands where the player raised preflop.
+    pub pfr_hands: usize,
+    /// Hands where the player acted facing exactly one preflop raise.
+    pub three_bet_opportunities: usize,
+    /// Hands where the player re-raised facing exactly one preflop raise.
+    pub three_bet_hands: usize,
+    /// Bets and raises after the flop.
+    pub postflop_aggressive_actions: usize,
+    /// Calls after the flop.
+    pub postflop_calls: usize,
+    /// The result of every finished hand in big blinds.
+    pub results: ResultStats,
+}
+
+impl SeatStats {
+    /// Voluntarily put money in pot. The fraction of hands where the player
+    /// called or raised preflop.
+    pub fn vpip(&self) -> f32 {
+        ratio(self.vpip_hands, self.hands)
+    }
+
+    /// Pre-flop raise. The fraction of hands where the player raised
+    /// preflop.
+    pub fn pfr(&self) -> f32 {
+        ratio(self.pfr_hands, self.hands)
+    }
+
+    /// The fraction of opportunities to 3-bet that the player took.
+    pub fn three_bet(&self) -> f32 {
+        ratio(self.three_bet_hands, self.three_bet_opportunities)
+    }
+
+    /// Aggression factor: postflop bets and raises divided by postflop
+    /// calls. `None` if the player has never called after the flop.
+    pub fn aggression_factor(&self) -> Option<f32> {
+        if self.postflop_calls == 0 {
+            None
+        } else {
+            Some(self.postflop_aggressive_actions as f32 / self.postflop_calls as f32)
+        }
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f32 / total as f32
+    }
+}
+
+/// The stats for every seat accumulated by a `PlayerStatsHistorian`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayerStatsReport {
+    pub seats: Vec<SeatStats>,
+}
+
+impl PlayerStatsReport {
+    pub fn seat(&self, idx: usize) -> Option<&SeatStats> {
+        self.seats.get(idx)
+    }
+}
+
+/// Storage for `PlayerStatsHistorian`. Holds the totals so far and the
+/// state of the hand currently being played.
+#[derive(Debug, Clone, Default)]
+pub struct PlayerStatsStorage {
+    report: PlayerStatsReport,
+    // Per hand flags so preflop stats count once per hand.
+    vpip: Vec<bool>,
+    pfr: Vec<bool>,
+    three_bet_opportunity: Vec<bool>,
+    preflop_raises: usize,
+}
+
+impl PlayerStatsStorage {
+    pub fn report(&self) -> PlayerStatsReport {
+        self.report.clone()
+    }
+
+    fn start_hand(&mut self, num_players: usize) {
+        self.vpip.clear();
+        self.pfr.clear();
+        self.three_bet_opportunity.clear();
+        self.preflop_raises = 0;
+        self.ensure_players(num_players);
+    }
+
+    /// Simulations don't have to start at the beginning of a hand so make
+    /// sure there's room for every seat before recording anything.
+    fn ensure_players(&mut self, num_players: usize) {
+        if self.report.seats.len() < num_players {
+            self.report.seats.resize(num_players, SeatStats::default());
+        }
+        if self.vpip.len() < num_players {
+            self.vpip.resize(num_players, false);
+            self.pfr.resize(num_players, false);
+            self.three_bet_opportunity.resize(num_players, false);
+        }
+    }
+
+    fn record_played_action(&mut self, payload: &PlayedActionPayload) {
+        let idx = payload.idx;
+        let put_in_money = payload.final_player_bet > payload.starting_player_bet;
+        let is_raise = payload.final_bet > payload.starting_bet;
+        let seat = &mut self.report.seats[idx];
+
+        if payload.round == Round::Preflop {
+            if self.preflop_raises == 1 && !self.three_bet_opportunity[idx] {
+                self.three_bet_opportunity[idx] = true;
+                seat.three_bet_opportunities += 1;
+                if is_raise {
+                    seat.three_bet_hands += 1;
+                }
+            }
+            if put_in_money && !self.vpip[idx] {
+                self.vpip[idx] = true;
+                seat.vpip_hands += 1;
+            }
+            if is_raise {
+                if !self.pfr[idx] {
+                    self.pfr[idx] = true;
+                    seat.pfr_hands += 1;
+                }
+                self.preflop_raises += 1;
+            }
+        } else if is_raise {
+            seat.postflop_aggressive_actions += 1;
+        } else if put_in_money {
+            seat.postflop_calls += 1;
+        }
+    }
+
+    fn finish_hand(&mut self, game_state: &GameState) {
+        for (seat, (starting, ending)) in self.report.seats.iter_mut().zip(
+            game_state
+                .starting_stacks
+                .iter()
+                .zip(game_state.stacks.iter()),
+        ) {
+            seat.results
+                .push(f64::from((ending - starting) / game_state.big_blind));
+        }
+    }
+}
+
+/// A historian that accumulates VPIP, PFR, 3-bet, aggression factor and
+/// win rate for every seat across all the hands it sees.
+///
+/// Clones share the same storage, so a single historian can be handed to
+/// many simulations (for example with a `CloneHistorianGenerator`) and the
+/// report will cover all of them.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::agent::CallingAgent;
+/// use rs_poker::arena::historian::PlayerStatsHistorian;
+/// use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+///
+/// let historian = PlayerStatsHistorian::default();
+/// for _ in 0..10 {
+///     let agents: Vec<Box<dyn Agent>> = vec![
+///         Box::<CallingAgent>::default(),
+///         Box::<CallingAgent>::default(),
+///     ];
+///     let mut sim = HoldemSimulationBuilder::default()
+///         .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+///         .agents(agents)
+///         .historians(vec![Box::new(historian.clone())])
+///         .build()
+///         .unwrap();
+///     sim.run(&mut rand::rng());
+/// }
+///
+/// let report = historian.report();
+/// assert_eq!(10, report.seats[0].hands);
+/// assert_eq!(0.0, report.seats[0].pfr());
+/// // Every chip one player won the other lost.
+/// assert_eq!(
+///     report.seats[0].results.total(),
+///     -report.seats[1].results.total()
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PlayerStatsHistorian {
+    storage: Rc<RefCell<PlayerStatsStorage>>,
+}
+
+impl PlayerStatsHistorian {
+    pub fn get_storage(&self) -> Rc<RefCell<PlayerStatsStorage>> {
+        self.storage.clone()
+    }
+
+    /// A snapshot of the stats accumulated so far.
+    pub fn report(&self) -> PlayerStatsReport {
+        self.storage.borrow().report()
+    }
+}
+
+impl Historian for PlayerStatsHistorian {
+    fn record_action(
+        &mut self,
+        _id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        let mut storage = self.storage.try_borrow_mut()?;
+        storage.ensure_players(game_state.num_players);
+        match action {
+            Action::GameStart(_) => storage.start_hand(game_state.num_players),
+            Action::PlayerSit(payload) => storage.report.seats[payload.idx].hands += 1,
+            Action::PlayedAction(payload) => storage.record_played_action(&payload),
+            Action::FailedAction(payload) => storage.record_played_action(&payload.result),
+            Action::RoundAdvance(Round::Complete) => storage.finish_hand(game_state),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::action::AgentAction;
+    use crate::arena::agent::{CallingAgent, FoldingAgent, VecReplayAgent};
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::*;
+
+    fn run(historian: &PlayerStatsHistorian, agents: Vec<Box<dyn Agent>>) {
+        let num_players = agents.len();
+        let game_state = GameState::new_starting(vec![100.0; num_players], 10.0, 5.0, 0.0, 0);
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state)
+            .agents(agents)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+    }
+
+    #[test]
+    fn test_calling_agents() {
+        let historian = PlayerStatsHistorian::default();
+        for _ in 0..5 {
+            run(
+                &historian,
+                vec![
+                    Box::<CallingAgent>::default(),
+                    Box::<CallingAgent>::default(),
+                ],
+            );
+        }
+
+        let report = historian.report();
+        assert_eq!(2, report.seats.len());
+        for seat in &report.seats {
+            assert_eq!(5, seat.hands);
+            assert_eq!(0, seat.pfr_hands);
+            assert_eq!(0, seat.postflop_aggressive_actions);
+            assert_eq!(None, seat.aggression_factor());
+        }
+        // The dealer posts the small blind and has to call to see a flop.
+        assert_eq!(1.0, report.seats[0].vpip());
+        // The big blind only ever checks.
+        assert_eq!(0.0, report.seats[1].vpip());
+    }
+
+    #[test]
+    fn test_folding_agents() {
+        let historian = PlayerStatsHistorian::default();
+        run(
+            &historian,
+            vec![
+                Box::<FoldingAgent>::default(),
+                Box::<FoldingAgent>::default(),
+                Box::<FoldingAgent>::default(),
+            ],
+        );
+
+        let report = historian.report();
+        assert!(report.seats.iter().all(|s| s.hands == 1));
+        assert!(report.seats.iter().all(|s| s.vpip_hands == 0));
+        // The big blind takes the small blind.
+        let totals: Vec<f64> = report.seats.iter().map(|s| s.results.total()).collect();
+        assert_eq!(vec![0.0, -0.5, 0.5], totals);
+        assert!(report.seats.iter().all(|s| s.results.hands() == 1));
+    }
+
+    #[test]
+    fn test_three_bet_and_aggression() {
+        let historian = PlayerStatsHistorian::default();
+        // Three players, dealer is 0, so 1 posts small, 2 posts big.
+        // 0 raises, 1 three bets, 2 folds, 0 calls.
+        // Then on the flop 1 bets and 0 calls, afterwards everyone checks.
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(VecReplayAgent::new_with_default(
+                vec![
+                    AgentAction::Bet(30.0),
+                    AgentAction::Bet(90.0),
+                    AgentAction::Bet(10.0),
+                ],
+                AgentAction::Bet(0.0),
+            )),
+            Box::new(VecReplayAgent::new_with_default(
+                vec![AgentAction::Bet(90.0), AgentAction::Bet(10.0)],
+                AgentAction::Bet(0.0),
+            )),
+            Box::<FoldingAgent>::default(),
+        ];
+        run(&historian, agents);
+
+        let report = historian.report();
+        let dealer = report.seat(0).unwrap();
+        let small_blind = report.seat(1).unwrap();
+        let big_blind = report.seat(2).unwrap();
+
+        assert_eq!(1, dealer.pfr_hands);
+        assert_eq!(1, dealer.vpip_hands);
+        assert_eq!(0, dealer.three_bet_opportunities);
+        assert_eq!(1, dealer.postflop_calls);
+        assert_eq!(Some(0.0), dealer.aggression_factor());
+
+        assert_eq!(1, small_blind.three_bet_opportunities);
+        assert_eq!(1, small_blind.three_bet_hands);
+        assert_eq!(1.0, small_blind.three_bet());
+        assert_eq!(1, small_blind.postflop_aggressive_actions);
+
+        assert_eq!(0, big_blind.three_bet_opportunities);
+        assert_eq!(0.0, big_blind.vpip());
+    }
+}
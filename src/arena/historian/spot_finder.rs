@@ -0,0 +1,542 @@
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+use super::{Historian, HistorianError, ShowdownEquityHistorian, showdown_equity};
+
+use crate::arena::GameState;
+use crate::arena::action::{Action, AgentAction};
+use crate::arena::action_key::{ActionKey, ActionKeyEncoder, BetSizing};
+use crate::arena::game_state::Round;
+use crate::core::{Rank, Suit, Value, rng};
+
+/// Something about a board card that makes the decisions after it worth a
+/// second look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RunoutKind {
+    /// All three flop cards are the same suit.
+    MonotoneFlop,
+    /// The board has a pair, or trips, that it didn't have before.
+    PairedBoard,
+    /// The turn or river puts a third card of a suit on the board.
+    FlushPossible,
+    /// A different player has the best hand now the card is out.
+    LeadChange,
+}
+
+/// Why a decision was kept.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpotReason {
+    /// Calling was close to breaking even against the hands actually held.
+    /// `ev_gap` is how many chips calling is worth over folding if the
+    /// hand was checked down after, which is close to zero.
+    CloseCall { equity: f32, ev_gap: f32 },
+    /// The pot was at least this many big blinds.
+    LargePot { big_blinds: f32 },
+    /// The decision came after an unusual card on this street.
+    Runout(RunoutKind),
+}
+
+/// A decision point worth studying, as a standalone snapshot.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterestingSpot {
+    /// The simulation the spot came from.
+    pub id: u128,
+    /// The game state just before the decision, with the player to act
+    /// being the one deciding. It can be handed straight to an agent or a
+    /// solver.
+    pub game_state: GameState,
+    /// What the player did.
+    pub action: AgentAction,
+    /// The betting in the hand before the decision.
+    pub line: ActionKey,
+    pub reasons: Vec<SpotReason>,
+}
+
+/// What makes a decision interesting. Any criterion that's `None` or
+/// `false` is not checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotCriteria {
+    /// Keep calls where the chip EV of calling over folding, against the
+    /// hands actually held, is within this fraction of the pot after
+    /// calling.
+    pub close_call_fraction: Option<f32>,
+    /// Keep decisions with at least this many big blinds in the pot.
+    pub large_pot_big_blinds: Option<f32>,
+    /// Keep the first decision after each unusual board card.
+    pub unusual_runouts: bool,
+    /// Equity for close calls is sampled rather than enumerated when there
+    /// are more runouts than this, as `ShowdownEquityHistorian` does.
+    pub max_runouts: usize,
+    /// How bet sizes are written in each spot's `line`.
+    pub line_sizing: BetSizing,
+    /// Keep only the first spot found for each line, so the same decision
+    /// isn't studied twice.
+    pub unique_lines: bool,
+}
+
+impl Default for SpotCriteria {
+    fn default() -> Self {
+        Self {
+            close_call_fraction: Some(0.05),
+            large_pot_big_blinds: Some(50.0),
+            unusual_runouts: true,
+            max_runouts: ShowdownEquityHistorian::DEFAULT_MAX_RUNOUTS,
+            line_sizing: BetSizing::default(),
+            unique_lines: false,
+        }
+    }
+}
+
+/// A historian that picks out interesting decision points from simulated
+/// hands: close calls, large pots and the decisions after unusual board
+/// cards.
+///
+/// Each is kept as a snapshot of the `GameState` just before the decision,
+/// so it can be used as a training quiz or as the starting point of a
+/// targeted solver run. Since the simulation knows every hand, close calls
+/// are judged against the cards the other players really held. Hands are
+/// ranked by the rules of the game, so only hold'em and Omaha can be
+/// searched; other games return `HistorianError::UnsupportedVariant`.
+///
+/// Clones share the same storage so one historian can collect spots over
+/// many simulations.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::agent::CallingAgent;
+/// use rs_poker::arena::historian::{InterestingSpotHistorian, SpotCriteria};
+/// use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+///
+/// let historian = InterestingSpotHistorian::new(SpotCriteria {
+///     close_call_fraction: None,
+///     large_pot_big_blinds: Some(2.0),
+///     unusual_runouts: false,
+///     ..Default::default()
+/// });
+/// let agents: Vec<Box<dyn Agent>> = vec![
+///     Box::<CallingAgent>::default(),
+///     Box::<CallingAgent>::default(),
+/// ];
+/// let mut sim = HoldemSimulationBuilder::default()
+///     .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+///     .agents(agents)
+///     .historians(vec![Box::new(historian.clone())])
+///     .build()
+///     .unwrap();
+/// sim.run(&mut rand::rng());
+///
+/// for spot in historian.get_storage().borrow().iter() {
+///     assert!(spot.game_state.total_pot >= 20.0);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct InterestingSpotHistorian {
+    criteria: SpotCriteria,
+    spots: Rc<RefCell<Vec<InterestingSpot>>>,
+    /// The lines of the spots kept, shared like the spots.
+    lines: Rc<RefCell<HashSet<ActionKey>>>,
+    line: ActionKeyEncoder,
+    /// The game state after the last action, which is the state the next
+    /// decision is made in.
+    last: Option<GameState>,
+    /// Unusual cards on this street, waiting for the next decision.
+    runouts: Vec<RunoutKind>,
+}
+
+impl InterestingSpotHistorian {
+    pub fn new(criteria: SpotCriteria) -> Self {
+        Self {
+            line: ActionKeyEncoder::new(criteria.line_sizing.clone()),
+            criteria,
+            spots: Rc::new(RefCell::new(vec![])),
+            lines: Rc::new(RefCell::new(HashSet::new())),
+            last: None,
+            runouts: vec![],
+        }
+    }
+
+    pub fn get_storage(&self) -> Rc<RefCell<Vec<InterestingSpot>>> {
+        self.spots.clone()
+    }
+
+    fn reasons(&mut self, game_state: &GameState) -> Result<Vec<SpotReason>, HistorianError> {
+        let mut reasons = vec![];
+        if let Some(fraction) = self.criteria.close_call_fraction {
+            let idx = game_state.to_act_idx();
+            let to_call = (game_state.current_round_bet()
+                - game_state.current_round_current_player_bet())
+            .min(game_state.stacks[idx]);
+            if to_call > 0.0 {
+                let equity =
+                    showdown_equity(game_state, self.criteria.max_runouts, &mut rng())?[idx];
+                let pot = game_state.total_pot + to_call;
+                let ev_gap = equity * pot - to_call;
+                if ev_gap.abs() <= fraction * pot {
+                    reasons.push(SpotReason::CloseCall { equity, ev_gap });
+                }
+            }
+        }
+        if let Some(min_big_blinds) = self.criteria.large_pot_big_blinds {
+            let big_blinds = game_state.total_pot / game_state.big_blind.max(f32::EPSILON);
+            if big_blinds >= min_big_blinds {
+                reasons.push(SpotReason::LargePot { big_blinds });
+            }
+        }
+        reasons.extend(self.runouts.drain(..).map(SpotReason::Runout));
+        Ok(reasons)
+    }
+}
+
+impl Default for InterestingSpotHistorian {
+    fn default() -> Self {
+        Self::new(SpotCriteria::default())
+    }
+}
+
+impl Historian for InterestingSpotHistorian {
+    fn record_action(
+        &mut self,
+        id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        if !game_state.variant.has_board_equity() {
+            return Err(HistorianError::UnsupportedVariant(game_state.variant));
+        }
+        // The line the decision was made on, before it's added.
+        let line = self.line.key();
+        self.line.push(&action);
+        let played = match action {
+            Action::GameStart(_) => {
+                self.runouts.clear();
+                None
+            }
+            Action::RoundAdvance(Round::Flop | Round::Turn | Round::River) => {
+                self.runouts.clear();
+                if self.criteria.unusual_runouts
+                    && let Some(before) = &self.last
+                {
+                    self.runouts = unusual_runouts(before, game_state);
+                }
+                None
+            }
+            Action::PlayedAction(payload) => Some(payload.action),
+            Action::FailedAction(payload) => Some(payload.action),
+            _ => None,
+        };
+
+        if let (Some(action), Some(before)) = (played, self.last.take()) {
+            let seen = self.criteria.unique_lines && self.lines.try_borrow()?.contains(&line);
+            let reasons = if seen { vec![] } else { self.reasons(&before)? };
+            if !reasons.is_empty() {
+                if self.criteria.unique_lines {
+                    self.lines.try_borrow_mut()?.insert(line.clone());
+                }
+                self.spots.try_borrow_mut()?.push(InterestingSpot {
+                    id,
+                    game_state: before,
+                    action,
+                    line,
+                    reasons,
+                });
+            }
+        }
+        self.last = Some(game_state.clone());
+        Ok(())
+    }
+}
+
+/// What's unusual about the cards dealt between `before` and `after`.
+fn unusual_runouts(before: &GameState, after: &GameState) -> Vec<RunoutKind> {
+    let mut kinds = vec![];
+    let suit_count = |game_state: &GameState, suit: Suit| {
+        game_state.board.iter().filter(|c| c.suit == suit).count()
+    };
+    let most_of_a_value = |game_state: &GameState| {
+        Value::values()
+            .iter()
+            .map(|v| game_state.board.iter().filter(|c| c.value == *v).count())
+            .max()
+            .unwrap_or(0)
+    };
+
+    if after.round == Round::Flop {
+        if Suit::suits()
+            .iter()
+            .any(|s| suit_count(after, *s) == after.board.len() && after.board.len() == 3)
+        {
+            kinds.push(RunoutKind::MonotoneFlop);
+        }
+    } else if Suit::suits()
+        .iter()
+        .any(|s| suit_count(before, *s) < 3 && suit_count(after, *s) >= 3)
+    {
+        kinds.push(RunoutKind::FlushPossible);
+    }
+    if most_of_a_value(after) > most_of_a_value(before).max(1) {
+        kinds.push(RunoutKind::PairedBoard);
+    }
+    if leaders(before) != leaders(after) {
+        kinds.push(RunoutKind::LeadChange);
+    }
+    kinds
+}
+
+/// The players still in the hand with the best hand so far.
+fn leaders(game_state: &GameState) -> Vec<usize> {
+    let ranks: Vec<(usize, Rank)> = (game_state.player_active | game_state.player_all_in)
+        .ones()
+        .map(|idx| {
+            let rank = game_state
+                .variant
+                .showdown_rank(&game_state.hands[idx], &game_state.board);
+            (idx, rank)
+        })
+        .collect();
+    let best = ranks.iter().map(|(_, rank)| *rank).max();
+    ranks
+        .into_iter()
+        .filter(|(_, rank)| Some(*rank) == best)
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::agent::{CallingAgent, VecReplayAgent};
+    use crate::arena::game_state::RoundData;
+    use crate::arena::hand_history::parse_cards;
+    use crate::arena::variant::GameVariant;
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+    use crate::core::{Hand, PlayerBitSet};
+
+    fn with_board(hole: &[&str], board: &str, round: Round) -> GameState {
+        let board = parse_cards(board).unwrap();
+        let hands = hole
+            .iter()
+            .map(|hole| {
+                let mut hand = Hand::new_from_str(hole).unwrap();
+                hand.extend(board.iter().copied());
+                hand
+            })
+            .collect();
+        GameState::new(
+            round,
+            RoundData::new(2, 10.0, PlayerBitSet::new(2), 0),
+            board,
+            hands,
+            vec![200.0, 200.0],
+            vec![5.0, 5.0],
+            10.0,
+            5.0,
+            0.0,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_close_call() {
+        // Both players play the royal flush on the board, so calling 100
+        // into 110 wins back half of 210, five more than it costs.
+        let game_state = with_board(&["2h3h", "2d3d"], "AsKsQsJsTs", Round::River);
+        let historian = InterestingSpotHistorian::new(SpotCriteria {
+            large_pot_big_blinds: None,
+            ..Default::default()
+        });
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(VecReplayAgent::new(vec![AgentAction::Bet(100.0)])),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state)
+            .agents(agents)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let spots = historian.get_storage();
+        let spots = spots.borrow();
+        assert_eq!(1, spots.len());
+        let spot = &spots[0];
+        assert_eq!(1, spot.game_state.to_act_idx());
+        assert_eq!(110.0, spot.game_state.total_pot);
+        assert_eq!(AgentAction::Bet(100.0), spot.action);
+        assert_eq!(
+            vec![SpotReason::CloseCall {
+                equity: 0.5,
+                ev_gap: 5.0
+            }],
+            spot.reasons
+        );
+    }
+
+    #[test]
+    fn test_large_pots() {
+        let historian = InterestingSpotHistorian::new(SpotCriteria {
+            close_call_fraction: None,
+            large_pot_big_blinds: Some(1.5),
+            unusual_runouts: false,
+            ..Default::default()
+        });
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<CallingAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        // Both blinds are in before anyone acts, so every decision counts,
+        // two preflop and two on each later street.
+        let spots = historian.get_storage();
+        let spots = spots.borrow();
+        assert_eq!(8, spots.len());
+        for spot in spots.iter() {
+            assert!(matches!(
+                spot.reasons[..],
+                [SpotReason::LargePot { big_blinds }] if big_blinds >= 1.5
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unique_lines() {
+        let historian = InterestingSpotHistorian::new(SpotCriteria {
+            close_call_fraction: None,
+            large_pot_big_blinds: Some(1.5),
+            unusual_runouts: false,
+            unique_lines: true,
+            ..Default::default()
+        });
+        // Two hands called down the same way only give spots once.
+        for _ in 0..2 {
+            let agents: Vec<Box<dyn Agent>> = vec![
+                Box::<CallingAgent>::default(),
+                Box::<CallingAgent>::default(),
+            ];
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+                .agents(agents)
+                .historians(vec![Box::new(historian.clone())])
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+        }
+
+        let spots = historian.get_storage();
+        let lines: Vec<_> = spots.borrow().iter().map(|s| s.line.to_string()).collect();
+        assert_eq!(
+            vec![
+                "",
+                "c",
+                "cx",
+                "cx/x",
+                "cx/xx",
+                "cx/xx/x",
+                "cx/xx/xx",
+                "cx/xx/xx/x"
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn test_unusual_runouts() {
+        let before = with_board(&["AhAd", "KsQs"], "", Round::Preflop);
+        let flop = with_board(&["AhAd", "KsQs"], "2s7s9s", Round::Flop);
+        assert_eq!(
+            vec![RunoutKind::MonotoneFlop, RunoutKind::LeadChange],
+            unusual_runouts(&before, &flop)
+        );
+
+        let turn = with_board(&["AhAd", "KsQs"], "2s7s9s9d", Round::Turn);
+        assert_eq!(vec![RunoutKind::PairedBoard], unusual_runouts(&flop, &turn));
+
+        let before = with_board(&["AhAd", "KsQs"], "2s7h9d", Round::Flop);
+        let turn = with_board(&["AhAd", "KsQs"], "2s7h9d3c", Round::Turn);
+        assert!(unusual_runouts(&before, &turn).is_empty());
+    }
+
+    #[test]
+    fn test_omaha_lead_change() {
+        // Three of the second hand make a straight in hold'em, but only two
+        // can play in Omaha so the aces stay ahead.
+        let hands = ["AhAd2c3c", "Ks5h8d6c"];
+        let mut before = with_board(&hands, "", Round::Preflop);
+        let mut flop = with_board(&hands, "2s7s9s", Round::Flop);
+        assert_eq!(
+            vec![RunoutKind::MonotoneFlop, RunoutKind::LeadChange],
+            unusual_runouts(&before, &flop)
+        );
+
+        before.variant = GameVariant::Omaha;
+        flop.variant = GameVariant::Omaha;
+        assert_eq!(
+            vec![RunoutKind::MonotoneFlop],
+            unusual_runouts(&before, &flop)
+        );
+    }
+
+    #[test]
+    fn test_omaha_hand() {
+        let historian = InterestingSpotHistorian::new(SpotCriteria {
+            close_call_fraction: Some(1.0),
+            large_pot_big_blinds: Some(1.5),
+            ..Default::default()
+        });
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<CallingAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .variant(GameVariant::Omaha)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        // The small blind completing is the only decision facing a bet.
+        let spots = historian.get_storage();
+        let spots = spots.borrow();
+        assert_eq!(8, spots.len());
+        assert!(matches!(
+            spots[0].reasons[0],
+            SpotReason::CloseCall { equity, .. } if (0.0..=1.0).contains(&equity)
+        ));
+        for spot in spots.iter() {
+            assert_eq!(GameVariant::Omaha, spot.game_state.variant);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_variant() {
+        let historian = InterestingSpotHistorian::default();
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<CallingAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .variant(GameVariant::FiveCardDraw)
+            .historians(vec![Box::new(historian.clone())])
+            .panic_on_historian_error(false)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        assert!(sim.historians.is_empty());
+        assert!(historian.get_storage().borrow().is_empty());
+    }
+}
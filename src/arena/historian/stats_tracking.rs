@@ -4,7 +4,6 @@ use super::Historian;
 
 use crate::arena::GameState;
 use crate::arena::action::{Action, AgentAction, PlayedActionPayload};
-use crate::core::Rankable;
 
 /// Storage for tracking various poker player statistics
 ///
@@ -24,6 +23,8 @@ use crate::core::Rankable;
 ///   each player
 /// * `raise_ahead_count` - Vector storing the count of raise actions when ahead
 ///   in hand for each player
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StatsStorage {
     // The total number of actions each player has taken
     pub actions_count: Vec<usize>,
@@ -69,6 +70,10 @@ impl Default for StatsStorage {
 
 /// A historian implementation that tracks and stores poker game statistics
 ///
+/// A player is ahead when they hold the best hand so far by the rules of
+/// the game being played: the lowest hand in 2-7 and the best high hand in
+/// the hi-lo games.
+///
 /// # Fields
 /// * `storage` - A reference-counted, mutable reference to the statistics
 ///   storage
@@ -86,14 +91,19 @@ impl StatsTrackingHistorian {
         games_state: &GameState,
         payload: PlayedActionPayload,
     ) -> Result<(), super::HistorianError> {
+        let variant = games_state.variant;
         let ranks = games_state
             .hands
             .iter()
-            .map(|hand| hand.rank())
+            .map(|hand| variant.showdown_rank(hand, &games_state.board))
             .collect::<Vec<_>>();
 
-        let max_hand = ranks.iter().max().unwrap();
-        let is_behind = ranks.get(payload.idx).unwrap() < max_hand;
+        let best_hand = if variant.is_lowball() {
+            ranks.iter().min()
+        } else {
+            ranks.iter().max()
+        };
+        let is_behind = ranks.get(payload.idx) != best_hand;
 
         let mut storage = self.storage.try_borrow_mut()?;
         storage.actions_count[payload.idx] += 1;
@@ -165,7 +175,9 @@ mod tests {
     use crate::arena::{
         Agent, HoldemSimulationBuilder,
         agent::{AllInAgent, CallingAgent, FoldingAgent, VecReplayAgent},
+        variant::GameVariant,
     };
+    use crate::core::{Card, Hand};
 
     use super::*;
 
@@ -265,6 +277,40 @@ mod tests {
         assert_eq!(actions_count.get(1), Some(&0));
     }
 
+    #[test]
+    fn test_ahead_by_the_variant() {
+        let vpip_ahead = |variant| {
+            let hist = Box::new(StatsTrackingHistorian::new_with_num_players(2));
+            let storage = hist.get_storage();
+            let agents: Vec<Box<dyn Agent>> = vec![
+                Box::<CallingAgent>::default() as Box<dyn Agent>,
+                Box::<CallingAgent>::default() as Box<dyn Agent>,
+            ];
+            let stacked = ["7s5d4c3h2s", "AsAdKsKdQs"]
+                .iter()
+                .flat_map(|cards| Hand::new_from_str(cards).unwrap().iter())
+                .collect();
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+                .agents(agents)
+                .historians(vec![hist])
+                .variant(variant)
+                .stacked_deck(stacked)
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+
+            // The only money put in voluntarily is the small blind
+            // completing with aces and kings, which is behind the seven
+            // low in 2-7.
+            assert!(sim.game_state.hands[0].contains(&Card::try_from("As").unwrap()));
+            assert_eq!(vec![1, 0], storage.borrow().vpip_count);
+            storage.borrow().vpip_ahead_count.clone()
+        };
+        assert_eq!(vec![1, 0], vpip_ahead(GameVariant::FiveCardDraw));
+        assert_eq!(vec![0, 0], vpip_ahead(GameVariant::DeuceToSevenTripleDraw));
+    }
+
     #[test]
     fn test_replay_agents_had_raises_counted() {
         let hist = Box::new(StatsTrackingHistorian::new_with_num_players(2));
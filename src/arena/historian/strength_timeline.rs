@@ -0,0 +1,373 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rand::{Rng, seq::index::sample};
+
+use super::{Historian, HistorianError};
+
+use crate::arena::GameState;
+use crate::arena::action::Action;
+use crate::arena::game_state::Round;
+use crate::arena::variant::GameVariant;
+use crate::core::{Card, CardIter, CardSet, Hand, HandEvaluator, Rank, Rankable, rng};
+
+/// The category of a made hand, without the kickers that `Rank` keeps to
+/// break ties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HandCategory {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+impl From<Rank> for HandCategory {
+    fn from(rank: Rank) -> Self {
+        match rank {
+            Rank::HighCard(_) => Self::HighCard,
+            Rank::OnePair(_) => Self::OnePair,
+            Rank::TwoPair(_) => Self::TwoPair,
+            Rank::ThreeOfAKind(_) => Self::ThreeOfAKind,
+            Rank::Straight(_) => Self::Straight,
+            Rank::Flush(_) => Self::Flush,
+            Rank::FullHouse(_) => Self::FullHouse,
+            Rank::FourOfAKind(_) => Self::FourOfAKind,
+            Rank::StraightFlush(_) => Self::StraightFlush,
+        }
+    }
+}
+
+/// How strong a single player's hand was on a single street.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StrengthPoint {
+    pub round: Round,
+    pub board: Vec<Card>,
+    pub category: HandCategory,
+    pub rank: Rank,
+    /// The fraction of all possible two card holdings that this hand beats
+    /// on the current board, counting ties as half. 1.0 is the nuts. In
+    /// Omaha it's a sample of four card holdings, see `omaha_percentile`.
+    pub percentile: f32,
+}
+
+/// The hand strength timeline of every player for a single simulation.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandStrengthTimeline {
+    pub id: u128,
+    /// One timeline per seat. A player's timeline stops on the street they
+    /// folded.
+    pub players: Vec<Vec<StrengthPoint>>,
+}
+
+/// A historian that records the made hand category and strength
+/// percentile of every player still in the hand once each street has been
+/// dealt.
+///
+/// Hands are ranked by the rules of the game, so only hold'em and Omaha
+/// can be recorded; other games return `HistorianError::UnsupportedVariant`.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::agent::CallingAgent;
+/// use rs_poker::arena::historian::HandStrengthTimelineHistorian;
+/// use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+///
+/// let historian = HandStrengthTimelineHistorian::default();
+/// let agents: Vec<Box<dyn Agent>> = vec![
+///     Box::<CallingAgent>::default(),
+///     Box::<CallingAgent>::default(),
+/// ];
+/// let mut sim = HoldemSimulationBuilder::default()
+///     .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+///     .agents(agents)
+///     .historians(vec![Box::new(historian.clone())])
+///     .build()
+///     .unwrap();
+/// sim.run(&mut rand::rng());
+///
+/// let timelines = historian.get_storage();
+/// // Preflop, flop, turn and river
+/// assert_eq!(4, timelines.borrow()[0].players[0].len());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HandStrengthTimelineHistorian {
+    timelines: Rc<RefCell<Vec<HandStrengthTimeline>>>,
+}
+
+impl HandStrengthTimelineHistorian {
+    pub fn get_storage(&self) -> Rc<RefCell<Vec<HandStrengthTimeline>>> {
+        self.timelines.clone()
+    }
+}
+
+impl Historian for HandStrengthTimelineHistorian {
+    fn record_action(
+        &mut self,
+        id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        if let Action::RoundAdvance(
+            round @ (Round::Preflop | Round::Flop | Round::Turn | Round::River),
+        ) = action
+        {
+            let variant = game_state.variant;
+            if !variant.has_board_equity() {
+                return Err(HistorianError::UnsupportedVariant(variant));
+            }
+            let mut timelines = self.timelines.try_borrow_mut()?;
+            if timelines.last().is_none_or(|t| t.id != id) {
+                timelines.push(HandStrengthTimeline {
+                    id,
+                    players: vec![vec![]; game_state.num_players],
+                });
+            }
+            let timeline = timelines.last_mut().unwrap();
+
+            for idx in (game_state.player_active | game_state.player_all_in).ones() {
+                let hand = &game_state.hands[idx];
+                let rank = variant.showdown_rank(hand, &game_state.board);
+                let percentile = if variant.is_omaha() {
+                    omaha_percentile(hand, &game_state.board, OMAHA_HOLDINGS, &mut rng())
+                } else {
+                    hand_percentile(hand, &game_state.board)
+                };
+                timeline.players[idx].push(StrengthPoint {
+                    round,
+                    board: game_state.board.to_vec(),
+                    category: rank.into(),
+                    rank,
+                    percentile,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fraction of all two card holdings an opponent could have that
+/// `hand` beats, counting ties as half. `hand` should include the board
+/// cards, as the hands in `GameState` do.
+pub fn hand_percentile(hand: &Hand, board: &[Card]) -> f32 {
+    let rank = hand.rank();
+    let remaining: Vec<Card> = (!CardSet::from(*hand)).iter().collect();
+
+    // Every holding shares the board so add it once.
+    let board = HandEvaluator::from(board);
+    let mut score = 0.0;
+    let mut total = 0.0;
+    for holding in CardIter::new(&remaining, 2) {
+        let mut other = board;
+        other.extend(holding);
+        let other_rank = other.rank();
+        if rank > other_rank {
+            score += 1.0;
+        } else if rank == other_rank {
+            score += 0.5;
+        }
+        total += 1.0;
+    }
+    score / total
+}
+
+/// How many holdings an Omaha hand is compared with on each street. There
+/// are too many four card holdings to try them all.
+const OMAHA_HOLDINGS: usize = 1_000;
+
+/// The fraction of `samples` random four card holdings that the Omaha
+/// `hand` beats, counting ties as half, as `hand_percentile` does for
+/// hold'em. Hands are ranked with `GameVariant::Omaha.showdown_rank`.
+pub fn omaha_percentile<R: Rng>(hand: &Hand, board: &[Card], samples: usize, rng: &mut R) -> f32 {
+    let omaha = GameVariant::Omaha;
+    let rank = omaha.showdown_rank(hand, board);
+    let remaining: Vec<Card> = (!CardSet::from(*hand)).iter().collect();
+
+    let samples = samples.max(1);
+    let mut score = 0.0;
+    for _ in 0..samples {
+        let mut other = Hand::new_with_cards(board.to_vec());
+        other.extend(
+            sample(rng, remaining.len(), 4)
+                .into_iter()
+                .map(|i| remaining[i]),
+        );
+        let other_rank = omaha.showdown_rank(&other, board);
+        if rank > other_rank {
+            score += 1.0;
+        } else if rank == other_rank {
+            score += 0.5;
+        }
+    }
+    score / samples as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use crate::arena::agent::{AllInAgent, CallingAgent, FoldingAgent};
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::*;
+
+    fn hand_with_board(hole: &str, board: &str) -> (Hand, Vec<Card>) {
+        let board = crate::arena::hand_history::parse_cards(board).unwrap();
+        let mut hand = Hand::new_from_str(hole).unwrap();
+        hand.extend(board.iter().copied());
+        (hand, board)
+    }
+
+    fn run(agents: Vec<Box<dyn Agent>>) -> HandStrengthTimeline {
+        let historian = HandStrengthTimelineHistorian::default();
+        let num_players = agents.len();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(
+                vec![100.0; num_players],
+                10.0,
+                5.0,
+                0.0,
+                0,
+            ))
+            .agents(agents)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let timelines = historian.get_storage();
+        let timelines = timelines.borrow();
+        assert_eq!(1, timelines.len());
+        assert_eq!(sim.id, timelines[0].id);
+        timelines[0].clone()
+    }
+
+    #[test]
+    fn test_category() {
+        let (hand, _) = hand_with_board("AsAh", "2c7d9h");
+        assert_eq!(HandCategory::OnePair, hand.rank().into());
+        let (hand, _) = hand_with_board("9s9d", "2c9c9h");
+        assert_eq!(HandCategory::FourOfAKind, hand.rank().into());
+    }
+
+    #[test]
+    fn test_nuts_percentile() {
+        // A royal flush can't be beaten or tied.
+        let (hand, board) = hand_with_board("AsKs", "QsJsTs2c3d");
+        assert_relative_eq!(1.0, hand_percentile(&hand, &board));
+    }
+
+    #[test]
+    fn test_board_plays_percentile() {
+        // Every holding plays the broadway straight on the board and no
+        // flush is possible, so everything ties.
+        let (hand, board) = hand_with_board("2c3d", "AsKdQhJcTs");
+        assert_relative_eq!(0.5, hand_percentile(&hand, &board));
+    }
+
+    #[test]
+    fn test_percentile_ordering() {
+        let (aces, board) = hand_with_board("AsAh", "2c7d9h");
+        let (junk, _) = hand_with_board("3s4h", "2c7d9h");
+        assert!(hand_percentile(&aces, &board) > 0.9);
+        assert!(hand_percentile(&junk, &board) < hand_percentile(&aces, &board));
+    }
+
+    #[test]
+    fn test_omaha_percentile() {
+        // The royal flush is the nuts in Omaha too.
+        let (royal, board) = hand_with_board("AsKs2d3d", "QsJsTs2c3h");
+        assert_relative_eq!(1.0, omaha_percentile(&royal, &board, 200, &mut rng()));
+        // One spade in the hand isn't a flush, so this is only ace high.
+        let (ace, board) = hand_with_board("As8d7c4h", "QsJsTs2s5c");
+        assert!(omaha_percentile(&ace, &board, 200, &mut rng()) < 0.5);
+        assert!(hand_percentile(&ace, &board) > 0.9);
+    }
+
+    #[test]
+    fn test_all_streets() {
+        let timeline = run(vec![
+            Box::<AllInAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ]);
+        for player in &timeline.players {
+            let rounds: Vec<Round> = player.iter().map(|p| p.round).collect();
+            assert_eq!(
+                vec![Round::Preflop, Round::Flop, Round::Turn, Round::River],
+                rounds
+            );
+            let board_sizes: Vec<usize> = player.iter().map(|p| p.board.len()).collect();
+            assert_eq!(vec![0, 3, 4, 5], board_sizes);
+            for point in player {
+                assert!((0.0..=1.0).contains(&point.percentile));
+                assert_eq!(point.category, point.rank.into());
+            }
+        }
+    }
+
+    #[test]
+    fn test_omaha_streets() {
+        let historian = HandStrengthTimelineHistorian::default();
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<CallingAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .variant(GameVariant::Omaha)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let timelines = historian.get_storage();
+        let timelines = timelines.borrow();
+        let board: Vec<Card> = sim.game_state.board.to_vec();
+        for (idx, player) in timelines[0].players.iter().enumerate() {
+            assert_eq!(4, player.len());
+            let river = player.last().unwrap();
+            let hand = &sim.game_state.hands[idx];
+            assert_eq!(GameVariant::Omaha.showdown_rank(hand, &board), river.rank);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_variant() {
+        let historian = HandStrengthTimelineHistorian::default();
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<CallingAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .variant(GameVariant::DeuceToSevenTripleDraw)
+            .historians(vec![Box::new(historian.clone())])
+            .panic_on_historian_error(false)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        assert!(sim.historians.is_empty());
+        assert!(historian.get_storage().borrow().is_empty());
+    }
+
+    #[test]
+    fn test_fold_preflop() {
+        let timeline = run(vec![
+            Box::<FoldingAgent>::default(),
+            Box::<FoldingAgent>::default(),
+        ]);
+        for player in &timeline.players {
+            assert_eq!(1, player.len());
+        }
+    }
+}
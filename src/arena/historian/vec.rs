@@ -5,6 +5,7 @@ use crate::arena::{GameState, action::Action};
 use super::{Historian, HistorianError};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HistoryRecord {
     pub before_game_state: Option<GameState>,
     pub action: Action,
@@ -0,0 +1,262 @@
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{Level, event};
+use tungstenite::{Message, Utf8Bytes};
+
+use crate::arena::GameState;
+use crate::arena::action::Action;
+
+use super::{Historian, HistorianError};
+
+/// How many events can wait to be broadcast before the simulation blocks.
+const BROADCAST_QUEUE: usize = 4096;
+/// How many events can wait to be written to one client. A client that
+/// falls further behind than this misses events.
+const CLIENT_QUEUE: usize = 1024;
+/// How long a client gets to finish the handshake, and to accept each
+/// write, before it's dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The queue of each connected client. Every client is written to by its
+/// own thread, so the lock is never held while talking to the network.
+type Clients = Arc<Mutex<Vec<SyncSender<Utf8Bytes>>>>;
+
+/// A single event as it's sent to WebSocket clients.
+#[derive(Debug, serde::Serialize)]
+struct WebSocketEvent<'a> {
+    /// Simulation ids are u128 which javascript numbers can't hold, so
+    /// they are sent as hex strings.
+    id: String,
+    action: &'a Action,
+    game_state: &'a GameState,
+}
+
+/// Everything that is shared between clones of the historian. When the last
+/// clone goes away the server stops accepting connections.
+#[derive(Debug)]
+struct Server {
+    local_addr: SocketAddr,
+    clients: Clients,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A historian that broadcasts every action, along with the game state
+/// after it, as a JSON text message to every connected WebSocket client.
+///
+/// This allows a browser front-end to render simulations while they run.
+/// Networking happens on background threads so a slow client never slows
+/// down the simulation or the other clients. Each client has a bounded
+/// queue; events that don't fit are dropped for that client, and clients
+/// that time out or can't be written to are disconnected. Clones share the same
+/// server, which shuts down when the last clone is dropped.
+///
+/// Each message looks like
+/// `{"id": "<hex simulation id>", "action": {...}, "game_state": {...}}`.
+///
+/// # Example
+///
+/// ```no_run
+/// use rs_poker::arena::historian::WebSocketHistorian;
+///
+/// let historian = WebSocketHistorian::bind("127.0.0.1:9001").unwrap();
+/// println!("Listening on ws://{}", historian.local_addr());
+/// ```
+#[derive(Debug, Clone)]
+pub struct WebSocketHistorian {
+    sender: SyncSender<String>,
+    server: Arc<Server>,
+}
+
+impl WebSocketHistorian {
+    /// Start listening for WebSocket connections on `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, HistorianError> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        // Non blocking so that the accept loop can notice it should stop.
+        listener.set_nonblocking(true)?;
+
+        let clients: Clients = Arc::new(Mutex::new(vec![]));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let accept_clients = clients.clone();
+        let accept_stop = stop.clone();
+        thread::spawn(move || accept_loop(listener, accept_clients, accept_stop));
+
+        let (sender, receiver) = sync_channel::<String>(BROADCAST_QUEUE);
+        let broadcast_clients = clients.clone();
+        thread::spawn(move || {
+            // This ends once every sender, and so every historian, is gone.
+            for text in receiver {
+                let text = Utf8Bytes::from(text);
+                let mut clients = broadcast_clients.lock().unwrap();
+                clients.retain(|queue| match queue.try_send(text.clone()) {
+                    Ok(()) | Err(TrySendError::Full(_)) => true,
+                    Err(TrySendError::Disconnected(_)) => false,
+                });
+            }
+        });
+
+        Ok(Self {
+            sender,
+            server: Arc::new(Server {
+                local_addr,
+                clients,
+                stop,
+            }),
+        })
+    }
+
+    /// The address the server is listening on. Useful when binding to port
+    /// 0 to let the OS pick a port.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.local_addr
+    }
+
+    /// The number of currently connected clients.
+    pub fn num_clients(&self) -> usize {
+        self.server.clients.lock().unwrap().len()
+    }
+}
+
+fn accept_loop(listener: TcpListener, clients: Clients, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, addr)) => {
+                let clients = clients.clone();
+                thread::spawn(move || serve_client(stream, addr, clients));
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(error) => {
+                event!(Level::ERROR, ?error, "websocket_accept_failed");
+                break;
+            }
+        }
+    }
+}
+
+/// Finish the handshake and then write this client's events until it goes
+/// away. Runs on its own thread so that a slow client can't hold up
+/// accepting others.
+fn serve_client(stream: TcpStream, addr: SocketAddr, clients: Clients) {
+    let configured = stream
+        .set_nonblocking(false)
+        .and_then(|_| stream.set_read_timeout(Some(CLIENT_TIMEOUT)))
+        .and_then(|_| stream.set_write_timeout(Some(CLIENT_TIMEOUT)));
+    if let Err(error) = configured {
+        event!(Level::WARN, ?error, ?addr, "websocket_configure_failed");
+        return;
+    }
+    let mut ws = match tungstenite::accept(stream) {
+        Ok(ws) => ws,
+        Err(error) => {
+            event!(Level::WARN, ?error, ?addr, "websocket_handshake_failed");
+            return;
+        }
+    };
+
+    let (queue, receiver) = sync_channel::<Utf8Bytes>(CLIENT_QUEUE);
+    clients.lock().unwrap().push(queue);
+    // Ends when the historian goes away or the client can't keep up with
+    // the write timeout. Either way dropping the receiver removes the
+    // client on the next broadcast.
+    for text in receiver {
+        if let Err(error) = ws.send(Message::Text(text)) {
+            event!(Level::DEBUG, ?error, ?addr, "websocket_client_dropped");
+            break;
+        }
+    }
+}
+
+impl Historian for WebSocketHistorian {
+    fn record_action(
+        &mut self,
+        id: u128,
+        game_state: &GameState,
+        action: Action,
+    ) -> Result<(), HistorianError> {
+        let text = serde_json::to_string(&WebSocketEvent {
+            id: format!("{id:x}"),
+            action: &action,
+            game_state,
+        })?;
+        self.sender
+            .send(text)
+            .map_err(|_| HistorianError::UnableToRecordAction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use std::net::TcpStream;
+
+    use crate::arena::agent::CallingAgent;
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::*;
+
+    #[test]
+    fn test_broadcast_to_client() {
+        let historian = WebSocketHistorian::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", historian.local_addr());
+        let (mut client, _) = tungstenite::connect(url).unwrap();
+
+        let start = Instant::now();
+        while historian.num_clients() == 0 {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<CallingAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .historians(vec![Box::new(historian.clone())])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let message = client.read().unwrap();
+        let value: serde_json::Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(format!("{:x}", sim.id), value["id"]);
+        assert!(value["action"]["GameStart"].is_object());
+        assert!(value["game_state"]["stacks"].is_array());
+    }
+
+    #[test]
+    fn test_stalled_handshake_does_not_block_others() {
+        let historian = WebSocketHistorian::bind("127.0.0.1:0").unwrap();
+
+        // Connect without ever sending the handshake.
+        let _stalled = TcpStream::connect(historian.local_addr()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        let url = format!("ws://{}", historian.local_addr());
+        let (_client, _) = tungstenite::connect(url).unwrap();
+
+        let start = Instant::now();
+        while historian.num_clients() == 0 {
+            assert!(start.elapsed() < CLIENT_TIMEOUT);
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(1, historian.num_clients());
+    }
+}
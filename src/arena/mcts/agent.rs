@@ -0,0 +1,463 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rand::Rng;
+
+use tracing::event;
+
+use crate::arena::{
+    Agent, AgentGenerator, GameState, HoldemSimulationBuilder, action::AgentAction,
+    agent::CallingAgentGenerator, model::ModelEvaluator,
+};
+use crate::core::{CardSet, Hand};
+
+use super::{ActionMenu, SearchTree, Spot};
+
+/// How hard and how wide `MctsAgent` searches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MctsConfig {
+    /// How many hands to play out for each decision.
+    pub iterations: usize,
+    /// The UCB1 exploration constant. Rewards are divided by the player's
+    /// starting stack before they're averaged, so this is on that scale.
+    pub exploration: f32,
+    /// The raises to try, as fractions of the pot after calling.
+    pub bet_fractions: Vec<f32>,
+    /// Deal the other players new hole cards for every playout, so the
+    /// search only uses what the player could know. With this off the
+    /// search sees everyone's cards, as `CFRAgent` does.
+    pub resample_hands: bool,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 500,
+            exploration: std::f32::consts::SQRT_2,
+            bet_fractions: vec![0.5, 1.0],
+            resample_hands: true,
+        }
+    }
+}
+
+/// An agent that decides by Monte Carlo tree search with UCB1.
+///
+/// For each decision it plays the hand out from the current `GameState`
+/// `iterations` times. Within the part of the tree it has already seen it
+/// picks its own actions by UCB1 from the `ActionMenu`. Once it steps off
+/// the tree it adds one node and plays the rest of the hand with the
+/// rollout policy. The other players are played by the opponent model the
+/// whole way. It then plays the action it tried most at the root.
+///
+/// Both the opponent model and the rollout policy are `AgentGenerator`s,
+/// so any agent in the arena can be either. They default to
+/// `CallingAgent`. With a leaf evaluator the value a model gives the new
+/// node is used in place of the playout's reward.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::arena::agent::RandomAgentGenerator;
+/// use rs_poker::arena::mcts::{MctsAgent, MctsConfig};
+/// use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+///
+/// let config = MctsConfig {
+///     iterations: 20,
+///     ..Default::default()
+/// };
+/// let agents: Vec<Box<dyn Agent>> = vec![
+///     Box::new(MctsAgent::new(config).with_opponent_model(RandomAgentGenerator::default())),
+///     Box::<rs_poker::arena::agent::CallingAgent>::default(),
+/// ];
+/// let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+/// let mut sim = HoldemSimulationBuilder::default()
+///     .game_state(game_state)
+///     .agents(agents)
+///     .build()
+///     .unwrap();
+/// sim.run(&mut rand::rng());
+/// ```
+#[derive(Clone)]
+pub struct MctsAgent {
+    config: MctsConfig,
+    menu: ActionMenu,
+    opponent_model: Rc<dyn AgentGenerator>,
+    rollout_policy: Rc<dyn AgentGenerator>,
+    leaf_evaluator: Option<Rc<RefCell<ModelEvaluator>>>,
+}
+
+impl MctsAgent {
+    pub fn new(config: MctsConfig) -> Self {
+        Self {
+            menu: ActionMenu::new(config.bet_fractions.clone()),
+            config,
+            opponent_model: Rc::new(CallingAgentGenerator),
+            rollout_policy: Rc::new(CallingAgentGenerator),
+            leaf_evaluator: None,
+        }
+    }
+
+    /// Play the other players with agents from `opponent_model`.
+    pub fn with_opponent_model(self, opponent_model: impl AgentGenerator + 'static) -> Self {
+        self.with_shared_opponent_model(Rc::new(opponent_model))
+    }
+
+    pub fn with_shared_opponent_model(mut self, opponent_model: Rc<dyn AgentGenerator>) -> Self {
+        self.opponent_model = opponent_model;
+        self
+    }
+
+    /// Finish playouts that leave the tree with agents from
+    /// `rollout_policy`.
+    pub fn with_rollout_policy(self, rollout_policy: impl AgentGenerator + 'static) -> Self {
+        self.with_shared_rollout_policy(Rc::new(rollout_policy))
+    }
+
+    pub fn with_shared_rollout_policy(mut self, rollout_policy: Rc<dyn AgentGenerator>) -> Self {
+        self.rollout_policy = rollout_policy;
+        self
+    }
+
+    /// Score the node added by each playout with the model's value rather
+    /// than by playing the hand out. The hand is still finished, by the
+    /// rollout policy, but only the model's value is counted.
+    pub fn with_leaf_evaluator(mut self, leaf_evaluator: Rc<RefCell<ModelEvaluator>>) -> Self {
+        self.leaf_evaluator = Some(leaf_evaluator);
+        self
+    }
+
+    pub fn config(&self) -> &MctsConfig {
+        &self.config
+    }
+
+    pub fn menu(&self) -> &ActionMenu {
+        &self.menu
+    }
+
+    /// Search from `game_state` for the player to act and return the tree.
+    /// Node 0 is this decision, with a slot of `menu()` for each action.
+    pub fn search(&self, game_state: &GameState) -> SearchTree {
+        let mut rng = crate::core::rng();
+        let player_idx = game_state.to_act_idx();
+        // Rewards are kept near the range UCB1 expects.
+        let scale = game_state.starting_stacks[player_idx].max(1.0);
+        let search = Rc::new(RefCell::new(Search {
+            tree: SearchTree::new(self.menu.num_slots()),
+            path: Vec::new(),
+            walk: Walk::Select(0),
+            leaf_value: None,
+        }));
+
+        for _ in 0..self.config.iterations {
+            let mut playout = game_state.clone();
+            if self.config.resample_hands {
+                resample_hands(&mut playout, player_idx, &mut rng);
+            }
+            {
+                let mut s = search.borrow_mut();
+                s.path.clear();
+                s.walk = Walk::Select(0);
+                s.leaf_value = None;
+            }
+
+            let agents: Vec<Box<dyn Agent>> = (0..playout.num_players)
+                .map(|idx| -> Box<dyn Agent> {
+                    if idx == player_idx {
+                        Box::new(TreeAgent {
+                            search: search.clone(),
+                            menu: self.menu.clone(),
+                            exploration: self.config.exploration,
+                            leaf_evaluator: self.leaf_evaluator.clone(),
+                            rollout: self.rollout_policy.generate(&playout),
+                        })
+                    } else {
+                        self.opponent_model.generate(&playout)
+                    }
+                })
+                .collect();
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(playout)
+                .agents(agents)
+                .build()
+                .unwrap();
+            sim.run(&mut rng);
+
+            let mut s = search.borrow_mut();
+            let reward = s
+                .leaf_value
+                .unwrap_or_else(|| sim.game_state.player_reward(player_idx) / scale);
+            let Search { tree, path, .. } = &mut *s;
+            tree.backpropagate(path, reward);
+        }
+
+        // The playouts are done so nothing else holds the search.
+        Rc::try_unwrap(search)
+            .map(|s| s.into_inner().tree)
+            .unwrap_or_else(|s| s.borrow().tree.clone())
+    }
+}
+
+impl Agent for MctsAgent {
+    fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+        let tree = self.search(game_state);
+        let legal = self.menu.legal_slots(game_state);
+        tree.best_slot(0, &legal)
+            .and_then(|slot| self.menu.action(game_state, slot))
+            .unwrap_or(AgentAction::Bet(game_state.current_round_bet()))
+    }
+}
+
+/// Builds an `MctsAgent` for every game, all sharing the same opponent
+/// model and rollout policy.
+#[derive(Clone)]
+pub struct MctsAgentGenerator {
+    agent: MctsAgent,
+}
+
+impl MctsAgentGenerator {
+    pub fn new(agent: MctsAgent) -> Self {
+        Self { agent }
+    }
+}
+
+impl AgentGenerator for MctsAgentGenerator {
+    fn generate(&self, _game_state: &GameState) -> Box<dyn Agent> {
+        Box::new(self.agent.clone())
+    }
+}
+
+/// Where the searching player is in the tree during a playout.
+#[derive(Debug, Clone, Copy)]
+enum Walk {
+    /// Choose an action at this node.
+    Select(usize),
+    /// This slot was played at this node. The next decision decides which
+    /// child that leads to.
+    Played(usize, usize),
+    /// Off the tree, so the rollout policy plays.
+    Rollout,
+}
+
+struct Search {
+    tree: SearchTree,
+    path: Vec<(usize, usize)>,
+    walk: Walk,
+    /// What the leaf evaluator made of the node this playout added.
+    leaf_value: Option<f32>,
+}
+
+/// Plays the searching player's seat during a playout.
+struct TreeAgent {
+    search: Rc<RefCell<Search>>,
+    menu: ActionMenu,
+    exploration: f32,
+    leaf_evaluator: Option<Rc<RefCell<ModelEvaluator>>>,
+    rollout: Box<dyn Agent>,
+}
+
+impl Agent for TreeAgent {
+    fn act(&mut self, id: u128, game_state: &GameState) -> AgentAction {
+        let mut search = self.search.borrow_mut();
+        let (node, leaving) = match search.walk {
+            Walk::Rollout => {
+                drop(search);
+                return self.rollout.act(id, game_state);
+            }
+            Walk::Select(node) => (node, false),
+            Walk::Played(node, slot) => {
+                let spot = Spot::of(game_state);
+                match search.tree.child(node, slot, spot) {
+                    Some(child) => (child, false),
+                    None => (search.tree.add_child(node, slot, spot), true),
+                }
+            }
+        };
+
+        if leaving && let Some(evaluator) = &self.leaf_evaluator {
+            match evaluator.borrow_mut().evaluate(game_state) {
+                Ok(result) => {
+                    search.leaf_value = Some(result.value);
+                    search.walk = Walk::Rollout;
+                    drop(search);
+                    return self.rollout.act(id, game_state);
+                }
+                Err(error) => event!(tracing::Level::WARN, ?error, "leaf_evaluator_error"),
+            }
+        }
+
+        let legal = self.menu.legal_slots(game_state);
+        let slot = search
+            .tree
+            .select(node, &legal, self.exploration, &mut crate::core::rng());
+        search.path.push((node, slot));
+        search.walk = if leaving {
+            Walk::Rollout
+        } else {
+            Walk::Played(node, slot)
+        };
+        self.menu
+            .action(game_state, slot)
+            .expect("Legal slots always have an action")
+    }
+}
+
+/// Deal every player other than `player_idx` new hole cards from the cards
+/// that player can't see.
+fn resample_hands<R: Rng>(game_state: &mut GameState, player_idx: usize, rng: &mut R) {
+    let board = game_state.board_set();
+    let mut unseen = !(board | CardSet::from(game_state.hands[player_idx]));
+    for (idx, hand) in game_state.hands_mut().iter_mut().enumerate() {
+        if idx == player_idx {
+            continue;
+        }
+        let num_hole = (CardSet::from(*hand) - board).count();
+        let mut cards = board;
+        for _ in 0..num_hole {
+            let card = unseen.sample_one(rng).expect("There are always cards left");
+            unseen.remove(card);
+            cards.insert(card);
+        }
+        *hand = Hand::from(cards);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::game_state::{Round, RoundData};
+    use crate::arena::hand_history::parse_cards;
+    use crate::core::PlayerBitSet;
+
+    /// A river where player 0 has a royal flush and it's checked to them.
+    fn nuts_on_the_river() -> GameState {
+        let board = parse_cards("QsJsTs2d3c").unwrap();
+        let hands = ["AsKs", "7h8h"]
+            .iter()
+            .map(|hole| {
+                let mut hand = Hand::new_from_str(hole).unwrap();
+                hand.extend(board.iter().copied());
+                hand
+            })
+            .collect();
+        GameState::new(
+            Round::River,
+            RoundData::new(2, 10.0, PlayerBitSet::new(2), 0),
+            board,
+            hands,
+            vec![100.0, 100.0],
+            vec![50.0, 50.0],
+            10.0,
+            5.0,
+            0.0,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_shoves_the_nuts_into_a_calling_station() {
+        let game_state = nuts_on_the_river();
+        let mut agent = MctsAgent::new(MctsConfig {
+            iterations: 200,
+            ..Default::default()
+        });
+        let tree = agent.search(&game_state);
+        let all_in = agent.menu().num_slots() - 1;
+        let legal = agent.menu().legal_slots(&game_state);
+        assert_eq!(Some(all_in), tree.best_slot(0, &legal));
+        // Every playout wins, and all in wins the whole of the other stack.
+        assert_eq!(Some(2.0), tree.mean_reward(0, all_in));
+        assert_eq!(AgentAction::AllIn, agent.act(0, &game_state));
+    }
+
+    #[test]
+    fn test_leaf_evaluator_replaces_rewards() {
+        use crate::arena::errors::ModelError;
+        use crate::arena::model::{BasicFeaturizer, PolicyValue, PolicyValueModel};
+
+        /// Thinks every spot is worth more than the whole table.
+        struct Optimist;
+
+        impl PolicyValueModel for Optimist {
+            fn num_features(&self) -> usize {
+                BasicFeaturizer::NUM_FEATURES
+            }
+
+            fn num_actions(&self) -> usize {
+                5
+            }
+
+            fn evaluate(&mut self, _features: &[f32]) -> Result<PolicyValue, ModelError> {
+                Ok(PolicyValue {
+                    priors: vec![0.2; 5],
+                    value: 7.0,
+                })
+            }
+        }
+
+        // Play up to the small blind's first decision.
+        let mut rng = rand::rng();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .build()
+            .unwrap();
+        while sim.game_state.round != Round::Preflop {
+            sim.run_round(&mut rng);
+        }
+
+        let evaluator = ModelEvaluator::new(Box::new(BasicFeaturizer), Box::new(Optimist))
+            .unwrap()
+            .shared();
+        let agent = MctsAgent::new(MctsConfig {
+            iterations: 30,
+            ..Default::default()
+        })
+        .with_leaf_evaluator(evaluator);
+        let tree = agent.search(&sim.game_state);
+
+        // Folding ends the hand so it's scored by what really happened, but
+        // calling leads to another decision and the model's value.
+        assert!(tree.mean_reward(0, 0).unwrap() < 0.0);
+        assert!(tree.mean_reward(0, 1).unwrap() > 1.0);
+    }
+
+    #[test]
+    fn test_resample_keeps_the_players_cards() {
+        let mut game_state = nuts_on_the_river();
+        let before = game_state.hands[0];
+        let mut rng = rand::rng();
+        for _ in 0..20 {
+            resample_hands(&mut game_state, 0, &mut rng);
+            assert_eq!(before, game_state.hands[0]);
+            let other = CardSet::from(game_state.hands[1]);
+            assert_eq!(7, other.count());
+            assert!(game_state.board_set().is_subset(other));
+            assert_eq!(
+                game_state.board_set(),
+                other.intersection(CardSet::from(before))
+            );
+        }
+    }
+
+    #[test]
+    fn test_plays_full_hands() {
+        let generator = MctsAgentGenerator::new(
+            MctsAgent::new(MctsConfig {
+                iterations: 10,
+                ..Default::default()
+            })
+            .with_opponent_model(crate::arena::agent::RandomAgentGenerator::default()),
+        );
+        for _ in 0..5 {
+            let game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0);
+            let agents = (0..3).map(|_| generator.generate(&game_state)).collect();
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(game_state)
+                .agents(agents)
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+            assert_eq!(Round::Complete, sim.game_state.round);
+            let total: f32 = (0..3).map(|idx| sim.game_state.player_reward(idx)).sum();
+            assert!(total.abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Monte Carlo tree search, as a decision engine that needs no training.
+//!
+//! Where `CFRAgent` learns a strategy over many hands, `MctsAgent` searches
+//! each decision as it comes. It plays the hand out from the current
+//! `GameState` many times, choosing its own actions with UCB1 while it's
+//! in the part of the tree it has explored and handing over to a rollout
+//! policy once it leaves it. The other players are played by an opponent
+//! model. Both are ordinary `AgentGenerator`s, so the search is only as
+//! good as the model of the opponents it's given.
+//!
+//! The tree only has the searching player's decisions in it. Each is
+//! reached by the action played at the one before and the `Spot` the
+//! player is in when it's their turn again. Cards aren't part of the
+//! tree, and by default the other players are dealt new hole cards for
+//! every playout so the search can't see them.
+//!
+//! ```
+//! use rs_poker::arena::agent::RandomAgentGenerator;
+//! use rs_poker::arena::mcts::{MctsAgent, MctsAgentGenerator, MctsConfig};
+//!
+//! let agent = MctsAgent::new(MctsConfig {
+//!     iterations: 1_000,
+//!     bet_fractions: vec![0.33, 0.75, 1.5],
+//!     ..Default::default()
+//! })
+//! .with_opponent_model(RandomAgentGenerator::default());
+//! let generator = MctsAgentGenerator::new(agent);
+//! ```
+mod agent;
+mod tree;
+
+pub use agent::{MctsAgent, MctsAgentGenerator, MctsConfig};
+pub use tree::{ActionMenu, SearchTree, Spot};
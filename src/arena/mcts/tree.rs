@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::arena::{GameState, action::AgentAction, game_state::Round};
+
+/// What the searching player can see about a decision without looking at
+/// the exact bets: the round and whether there's a bet to call.
+///
+/// Nodes in the search tree are reached by the action the player took last
+/// and the spot they're in when it's their turn again, so the same line
+/// followed by a check or a raise from an opponent leads to different
+/// nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Spot {
+    pub round: Round,
+    pub facing_bet: bool,
+}
+
+impl Spot {
+    pub fn of(game_state: &GameState) -> Self {
+        Self {
+            round: game_state.round,
+            facing_bet: game_state.legal_actions().can_fold,
+        }
+    }
+}
+
+/// The actions searched at each decision, as slots: fold, call, a raise
+/// for each fraction of the pot, then all in.
+///
+/// A slot can be empty in a given game state, when the action isn't legal
+/// or comes to the same bet as an earlier slot, so the search never tries
+/// the same bet twice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionMenu {
+    bet_fractions: Vec<f32>,
+}
+
+impl ActionMenu {
+    pub fn new(bet_fractions: Vec<f32>) -> Self {
+        Self { bet_fractions }
+    }
+
+    /// How many slots there are, empty or not.
+    pub fn num_slots(&self) -> usize {
+        self.bet_fractions.len() + 3
+    }
+
+    /// The bet that raises `fraction` of the pot, counting the call, or
+    /// `None` if that would be all in.
+    fn raise_to(game_state: &GameState, fraction: f32) -> Option<f32> {
+        let legal = game_state.legal_actions();
+        let (min, max) = legal.raise?;
+        let to_call = legal.call - game_state.current_round_current_player_bet();
+        let bet = (legal.call + fraction * (game_state.total_pot + to_call)).clamp(min, max);
+        (bet < max).then_some(bet)
+    }
+
+    /// The action in `slot` for this game state, if there is one.
+    pub fn action(&self, game_state: &GameState, slot: usize) -> Option<AgentAction> {
+        let legal = game_state.legal_actions();
+        match slot {
+            0 => legal.can_fold.then_some(AgentAction::Fold),
+            1 => Some(AgentAction::Bet(legal.call)),
+            s if s == self.num_slots() - 1 => legal.raise.map(|_| AgentAction::AllIn),
+            s => {
+                let bet = Self::raise_to(game_state, self.bet_fractions[s - 2])?;
+                let repeated = self.bet_fractions[..s - 2]
+                    .iter()
+                    .any(|f| Self::raise_to(game_state, *f) == Some(bet));
+                (!repeated).then_some(AgentAction::Bet(bet))
+            }
+        }
+    }
+
+    /// The slots that have an action in this game state.
+    pub fn legal_slots(&self, game_state: &GameState) -> Vec<usize> {
+        (0..self.num_slots())
+            .filter(|slot| self.action(game_state, *slot).is_some())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SearchNode {
+    action_visits: Vec<u32>,
+    action_rewards: Vec<f32>,
+    children: HashMap<(usize, Spot), usize>,
+}
+
+impl SearchNode {
+    fn new(num_slots: usize) -> Self {
+        Self {
+            action_visits: vec![0; num_slots],
+            action_rewards: vec![0.0; num_slots],
+            children: HashMap::new(),
+        }
+    }
+}
+
+/// Visit counts and total rewards for each action at each of the
+/// searching player's decisions. Node 0 is the decision being searched.
+#[derive(Debug, Clone)]
+pub struct SearchTree {
+    num_slots: usize,
+    nodes: Vec<SearchNode>,
+}
+
+impl SearchTree {
+    pub fn new(num_slots: usize) -> Self {
+        Self {
+            num_slots,
+            nodes: vec![SearchNode::new(num_slots)],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The node reached by playing `slot` at `node` and then being in
+    /// `spot` at the next decision, if it's been added.
+    pub fn child(&self, node: usize, slot: usize, spot: Spot) -> Option<usize> {
+        self.nodes[node].children.get(&(slot, spot)).copied()
+    }
+
+    pub fn add_child(&mut self, node: usize, slot: usize, spot: Spot) -> usize {
+        let child = self.nodes.len();
+        self.nodes.push(SearchNode::new(self.num_slots));
+        self.nodes[node].children.insert((slot, spot), child);
+        child
+    }
+
+    pub fn action_visits(&self, node: usize, slot: usize) -> u32 {
+        self.nodes[node].action_visits[slot]
+    }
+
+    /// The average reward after playing `slot` at `node`.
+    pub fn mean_reward(&self, node: usize, slot: usize) -> Option<f32> {
+        let n = &self.nodes[node];
+        (n.action_visits[slot] > 0).then(|| n.action_rewards[slot] / n.action_visits[slot] as f32)
+    }
+
+    /// Pick which of `legal` to play at `node` with UCB1: any that haven't
+    /// been tried yet first, at random, and then the one with the highest
+    /// mean reward plus `exploration * sqrt(ln(visits) / action_visits)`.
+    pub fn select<R: Rng>(
+        &self,
+        node: usize,
+        legal: &[usize],
+        exploration: f32,
+        rng: &mut R,
+    ) -> usize {
+        let n = &self.nodes[node];
+        let untried: Vec<usize> = legal
+            .iter()
+            .copied()
+            .filter(|s| n.action_visits[*s] == 0)
+            .collect();
+        if !untried.is_empty() {
+            return untried[rng.random_range(0..untried.len())];
+        }
+
+        let total: u32 = legal.iter().map(|s| n.action_visits[*s]).sum();
+        let ln_total = (total as f32).ln();
+        let ucb = |s: usize| {
+            let visits = n.action_visits[s] as f32;
+            n.action_rewards[s] / visits + exploration * (ln_total / visits).sqrt()
+        };
+        legal
+            .iter()
+            .copied()
+            .max_by(|a, b| ucb(*a).total_cmp(&ucb(*b)))
+            .expect("There should always be a legal action")
+    }
+
+    /// Add `reward` to every `(node, slot)` played on the way down.
+    pub fn backpropagate(&mut self, path: &[(usize, usize)], reward: f32) {
+        for &(node, slot) in path {
+            let n = &mut self.nodes[node];
+            n.action_visits[slot] += 1;
+            n.action_rewards[slot] += reward;
+        }
+    }
+
+    /// The most visited of `legal` at `node`, the one the search trusts
+    /// most.
+    pub fn best_slot(&self, node: usize, legal: &[usize]) -> Option<usize> {
+        legal
+            .iter()
+            .copied()
+            .max_by_key(|s| self.nodes[node].action_visits[*s])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::game_state::RoundData;
+    use crate::core::PlayerBitSet;
+
+    fn facing_bet(stacks: Vec<f32>) -> GameState {
+        // Player 1 has bet 10 into a pot of 30 and it's player 0's turn.
+        let round_data = RoundData::new_with_bets(10.0, PlayerBitSet::new(2), 0, vec![0.0, 10.0]);
+        GameState::new(
+            Round::Flop,
+            round_data,
+            vec![],
+            vec![Default::default(); 2],
+            stacks,
+            vec![10.0, 20.0],
+            10.0,
+            5.0,
+            0.0,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_menu() {
+        let menu = ActionMenu::new(vec![0.5, 1.0]);
+        let game_state = facing_bet(vec![1000.0, 1000.0]);
+        assert_eq!(0, game_state.to_act_idx());
+        assert!(Spot::of(&game_state).facing_bet);
+
+        // Calling is 10 more, making the pot 40. Half of that on top of the
+        // call is 30 and all of it is 50.
+        let actions: Vec<_> = (0..menu.num_slots())
+            .filter_map(|s| menu.action(&game_state, s))
+            .collect();
+        assert_eq!(
+            vec![
+                AgentAction::Fold,
+                AgentAction::Bet(10.0),
+                AgentAction::Bet(30.0),
+                AgentAction::Bet(50.0),
+                AgentAction::AllIn,
+            ],
+            actions
+        );
+    }
+
+    #[test]
+    fn test_menu_skips_repeated_raises() {
+        let menu = ActionMenu::new(vec![0.5, 1.0]);
+        // With 25 behind both raises would be all in.
+        let game_state = facing_bet(vec![25.0, 1000.0]);
+        assert_eq!(vec![0, 1, 4], menu.legal_slots(&game_state));
+    }
+
+    #[test]
+    fn test_select_and_backpropagate() {
+        let mut rng = rand::rng();
+        let mut tree = SearchTree::new(3);
+        let legal = [0, 2];
+
+        // Untried actions come first.
+        let first = tree.select(0, &legal, 1.0, &mut rng);
+        tree.backpropagate(&[(0, first)], 0.0);
+        let second = tree.select(0, &legal, 1.0, &mut rng);
+        assert_ne!(first, second);
+        tree.backpropagate(&[(0, second)], 0.0);
+
+        // Then the one that does better.
+        for _ in 0..10 {
+            tree.backpropagate(&[(0, 2)], 1.0);
+        }
+        assert_eq!(2, tree.select(0, &legal, 0.1, &mut rng));
+        assert_eq!(Some(2), tree.best_slot(0, &legal));
+        assert_eq!(Some(10.0 / 11.0), tree.mean_reward(0, 2));
+        assert_eq!(None, tree.mean_reward(0, 1));
+
+        let spot = Spot {
+            round: Round::Turn,
+            facing_bet: false,
+        };
+        assert_eq!(None, tree.child(0, 2, spot));
+        let child = tree.add_child(0, 2, spot);
+        assert_eq!(Some(child), tree.child(0, 2, spot));
+        assert_eq!(2, tree.len());
+    }
+}
@@ -0,0 +1,132 @@
+//! Counters and gauges for watching long simulations and CFR training runs,
+//! reported through the [`metrics`] facade.
+//!
+//! Nothing is recorded anywhere until a recorder is installed, so pick an
+//! exporter for whatever the dashboards read. For Prometheus that's
+//! `metrics-exporter-prometheus`:
+//!
+//! ```ignore
+//! metrics_exporter_prometheus::PrometheusBuilder::new()
+//!     .install()
+//!     .expect("failed to install the Prometheus exporter");
+//! rs_poker::arena::metrics::describe();
+//! ```
+//!
+//! Hands simulated and training iterations are counted as they happen. The
+//! size of the CFR trees is a gauge that's set by `record_state_store`,
+//! which `HeadsUpLimitConfig::train` calls when it's done; call it
+//! yourself every so often when driving the training any other way.
+//! Exploitability isn't measured during training, so report it with
+//! `record_exploitability`, for example the lower bound from
+//! `LocalBestResponse::estimate`.
+use metrics::{Unit, counter, describe_counter, describe_gauge, gauge};
+
+use super::cfr::StateStore;
+
+/// Hands played to completion by any simulation, including the ones CFR
+/// agents play to value their actions.
+pub const HANDS_SIMULATED: &str = "rs_poker_hands_simulated_total";
+/// Training iterations, labelled with the `trainer` that ran them.
+pub const CFR_ITERATIONS: &str = "rs_poker_cfr_iterations_total";
+/// Nodes in each player's CFR tree, labelled by `player`.
+pub const CFR_NODES: &str = "rs_poker_cfr_nodes";
+/// Approximate memory held by each player's CFR tree, labelled by `player`.
+pub const CFR_MEMORY: &str = "rs_poker_cfr_memory_bytes";
+/// The latest exploitability estimate.
+pub const CFR_EXPLOITABILITY: &str = "rs_poker_cfr_exploitability";
+
+/// Give the installed recorder the units and descriptions of every metric.
+pub fn describe() {
+    describe_counter!(
+        HANDS_SIMULATED,
+        Unit::Count,
+        "Hands played to completion by simulations"
+    );
+    describe_counter!(CFR_ITERATIONS, Unit::Count, "CFR training iterations");
+    describe_gauge!(CFR_NODES, Unit::Count, "Nodes in each player's CFR tree");
+    describe_gauge!(
+        CFR_MEMORY,
+        Unit::Bytes,
+        "Approximate memory held by each player's CFR tree"
+    );
+    describe_gauge!(
+        CFR_EXPLOITABILITY,
+        "The latest exploitability estimate of the trained strategy"
+    );
+}
+
+/// Set the node and memory gauges for every tree in `state_store`.
+pub fn record_state_store(state_store: &StateStore) {
+    for player in 0..state_store.len() {
+        let Some(cfr_state) = state_store.get_state(player) else {
+            continue;
+        };
+        let inner = cfr_state.internal_state().borrow();
+        let player = player.to_string();
+        gauge!(CFR_NODES, "player" => player.clone()).set(inner.nodes.len() as f64);
+        gauge!(CFR_MEMORY, "player" => player).set(inner.nodes.memory_bytes() as f64);
+    }
+}
+
+pub fn record_exploitability(exploitability: f64) {
+    gauge!(CFR_EXPLOITABILITY).set(exploitability);
+}
+
+pub(crate) fn record_hand_simulated() {
+    counter!(HANDS_SIMULATED).increment(1);
+}
+
+pub(crate) fn record_iteration(trainer: &'static str) {
+    counter!(CFR_ITERATIONS, "trainer" => trainer).increment(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    use super::*;
+    use crate::arena::cfr::{HeadsUpLimitConfig, NoCardAbstraction};
+
+    #[test]
+    fn test_training_metrics() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            let mut state_store = StateStore::new();
+            HeadsUpLimitConfig::default().train::<NoCardAbstraction, _>(
+                &mut state_store,
+                3,
+                &mut rand::rng(),
+            );
+            record_exploitability(12.5);
+        });
+
+        let values: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| (key.key().name().to_string(), value))
+            .collect();
+        let value = |name: &str| {
+            values
+                .iter()
+                .filter(|(key, _)| key == name)
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec![&DebugValue::Counter(3)], value(CFR_ITERATIONS));
+        // The three training hands and every hand played to value actions.
+        assert!(matches!(value(HANDS_SIMULATED)[..], [DebugValue::Counter(n)] if *n > 3));
+        assert_eq!(2, value(CFR_NODES).len());
+        assert!(
+            value(CFR_MEMORY)
+                .iter()
+                .all(|value| matches!(value, DebugValue::Gauge(bytes) if bytes.0 > 0.0))
+        );
+        assert_eq!(
+            vec![&DebugValue::Gauge(12.5.into())],
+            value(CFR_EXPLOITABILITY)
+        );
+    }
+}
@@ -121,20 +121,47 @@
 //! use rs_poker::arena::cfr::CFRAgent;
 //! ```
 pub mod action;
+pub mod action_key;
 pub mod agent;
+pub mod betting;
 pub mod cfr;
 pub mod competition;
+#[cfg(feature = "tui")]
+pub mod dashboard;
+pub mod dealing;
 pub mod errors;
+pub mod features;
+#[cfg(feature = "flatbuffers")]
+pub mod flat_hand_log;
+#[cfg(all(feature = "arbitrary", feature = "arena-test-util"))]
+pub mod fuzz;
 pub mod game_state;
+pub mod hand_history;
+#[cfg(feature = "hand-log")]
+pub mod hand_log;
 pub mod historian;
+pub mod mcts;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod model;
+pub mod range_inference;
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+pub mod reachable;
+pub mod review;
 pub mod sim_builder;
 pub mod simulation;
+pub mod storage;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod timing;
+pub mod variant;
+pub mod versioned;
 
 #[cfg(any(test, feature = "arena-test-util"))]
 pub mod test_util;
 
 pub use agent::{Agent, AgentGenerator, CloneAgentGenerator};
-pub use game_state::{CloneGameStateGenerator, GameState, GameStateGenerator};
+pub use game_state::{CloneGameStateGenerator, GameState, GameStateGenerator, GameStatePool};
 pub use historian::{CloneHistorianGenerator, Historian, HistorianError, HistorianGenerator};
 pub use sim_builder::HoldemSimulationBuilder;
-pub use simulation::HoldemSimulation;
+pub use simulation::{HoldemSimulation, RunItResult};
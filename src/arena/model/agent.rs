@@ -0,0 +1,135 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rand::Rng;
+use tracing::event;
+
+use crate::arena::{Agent, GameState, action::AgentAction, errors::ModelError, mcts::ActionMenu};
+
+use super::ModelEvaluator;
+
+/// An agent that plays straight from a model's policy.
+///
+/// The priors for the actions that are legal are normalized and one is
+/// picked at random in proportion, or the most likely one when
+/// `greedy` is set. If the model fails, or gives nothing to a legal
+/// action, the agent checks or calls.
+#[derive(Clone)]
+pub struct ModelAgent {
+    evaluator: Rc<RefCell<ModelEvaluator>>,
+    menu: ActionMenu,
+    greedy: bool,
+}
+
+impl ModelAgent {
+    /// Fails unless the model gives a prior for every slot of `menu`.
+    pub fn new(
+        evaluator: Rc<RefCell<ModelEvaluator>>,
+        menu: ActionMenu,
+    ) -> Result<Self, ModelError> {
+        let num_actions = evaluator.borrow().num_actions();
+        if num_actions != menu.num_slots() {
+            return Err(ModelError::WrongActionCount {
+                expected: menu.num_slots(),
+                found: num_actions,
+            });
+        }
+        Ok(Self {
+            evaluator,
+            menu,
+            greedy: false,
+        })
+    }
+
+    /// Always play the most likely action rather than sampling.
+    pub fn greedy(mut self, greedy: bool) -> Self {
+        self.greedy = greedy;
+        self
+    }
+
+    fn choose_slot(&self, game_state: &GameState) -> Result<Option<usize>, ModelError> {
+        let policy = self.evaluator.borrow_mut().evaluate(game_state)?;
+        let legal: Vec<(usize, f32)> = self
+            .menu
+            .legal_slots(game_state)
+            .into_iter()
+            .map(|slot| (slot, policy.priors[slot].max(0.0)))
+            .filter(|(_, p)| *p > 0.0)
+            .collect();
+        if self.greedy {
+            return Ok(legal
+                .iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(slot, _)| *slot));
+        }
+
+        let total: f32 = legal.iter().map(|(_, p)| p).sum();
+        if total <= 0.0 {
+            return Ok(None);
+        }
+        let mut target = crate::core::rng().random_range(0.0..total);
+        for (slot, p) in &legal {
+            if target < *p {
+                return Ok(Some(*slot));
+            }
+            target -= p;
+        }
+        Ok(legal.last().map(|(slot, _)| *slot))
+    }
+}
+
+impl Agent for ModelAgent {
+    fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+        let slot = self.choose_slot(game_state).unwrap_or_else(|error| {
+            event!(tracing::Level::WARN, ?error, "model_error");
+            None
+        });
+        slot.and_then(|slot| self.menu.action(game_state, slot))
+            .unwrap_or(AgentAction::Bet(game_state.current_round_bet()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::model::{BasicFeaturizer, tests::FixedModel};
+
+    fn agent(priors: Vec<f32>) -> Result<ModelAgent, ModelError> {
+        let model = FixedModel {
+            priors,
+            num_features: BasicFeaturizer::NUM_FEATURES,
+        };
+        let evaluator = ModelEvaluator::new(Box::new(BasicFeaturizer), Box::new(model))?;
+        ModelAgent::new(evaluator.shared(), ActionMenu::new(vec![0.5, 1.0]))
+    }
+
+    #[test]
+    fn test_needs_a_prior_per_slot() {
+        assert!(matches!(
+            agent(vec![1.0; 3]),
+            Err(ModelError::WrongActionCount {
+                expected: 5,
+                found: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_plays_legal_priors() {
+        let mut game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        game_state.round = crate::arena::game_state::Round::Flop;
+
+        // Fold has all the weight but isn't legal with nothing to call, so
+        // all in is the only choice left.
+        let mut shover = agent(vec![10.0, 0.0, 0.0, 0.0, 1.0]).unwrap();
+        for _ in 0..10 {
+            assert_eq!(AgentAction::AllIn, shover.act(0, &game_state));
+        }
+
+        // Nothing legal has any weight so check.
+        let mut folder = agent(vec![1.0, 0.0, 0.0, 0.0, 0.0]).unwrap();
+        assert_eq!(AgentAction::Bet(0.0), folder.act(0, &game_state));
+
+        let mut greedy = agent(vec![0.0, 0.4, 0.6, 0.0, 0.0]).unwrap().greedy(true);
+        assert!(matches!(greedy.act(0, &game_state), AgentAction::Bet(b) if b > 0.0));
+    }
+}
@@ -0,0 +1,283 @@
+//! Learned models as a source of decisions.
+//!
+//! A `PolicyValueModel` takes a game state that a `Featurizer` has turned
+//! into numbers and gives back a prior for each action and a value for the
+//! player to act. `ModelEvaluator` pairs a model with the featurizer it was
+//! trained with. From there the model can play on its own as a
+//! `ModelAgent`, or evaluate the leaves of an `MctsAgent` search in place of
+//! playing the hand out.
+//!
+//! The actions are the slots of an `ActionMenu`: fold, call, a raise for
+//! each fraction of the pot, then all in. Values are the expected reward as
+//! a fraction of the player's starting stack, the same scale the search
+//! uses.
+//!
+//! With the `onnx` feature `OnnxModel` runs a model exported to ONNX with
+//! ONNX Runtime, which is loaded when the first model is.
+//!
+//! ```
+//! use rs_poker::arena::errors::ModelError;
+//! use rs_poker::arena::mcts::{ActionMenu, MctsAgent, MctsConfig};
+//! use rs_poker::arena::model::{
+//!     BasicFeaturizer, ModelAgent, ModelEvaluator, PolicyValue, PolicyValueModel,
+//! };
+//!
+//! /// Always calls and expects to break even.
+//! struct CallModel;
+//!
+//! impl PolicyValueModel for CallModel {
+//!     fn num_features(&self) -> usize {
+//!         BasicFeaturizer::NUM_FEATURES
+//!     }
+//!
+//!     fn num_actions(&self) -> usize {
+//!         5
+//!     }
+//!
+//!     fn evaluate(&mut self, _features: &[f32]) -> Result<PolicyValue, ModelError> {
+//!         Ok(PolicyValue {
+//!             priors: vec![0.0, 1.0, 0.0, 0.0, 0.0],
+//!             value: 0.0,
+//!         })
+//!     }
+//! }
+//!
+//! let evaluator = ModelEvaluator::new(Box::new(BasicFeaturizer), Box::new(CallModel))
+//!     .unwrap()
+//!     .shared();
+//! let menu = ActionMenu::new(vec![0.5, 1.0]);
+//! let agent = ModelAgent::new(evaluator.clone(), menu).unwrap();
+//! let searcher = MctsAgent::new(MctsConfig::default()).with_leaf_evaluator(evaluator);
+//! ```
+mod agent;
+#[cfg(feature = "onnx")]
+mod onnx;
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::arena::{GameState, errors::ModelError, game_state::Round};
+use crate::core::CardSet;
+
+pub use agent::ModelAgent;
+#[cfg(feature = "onnx")]
+pub use onnx::OnnxModel;
+
+/// What a model thinks of a game state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyValue {
+    /// How likely the model is to play each action. Actions that aren't
+    /// legal are ignored, so these don't have to sum to one.
+    pub priors: Vec<f32>,
+    /// The expected reward for the player to act, as a fraction of their
+    /// starting stack.
+    pub value: f32,
+}
+
+/// A model that takes features and gives a policy and a value.
+pub trait PolicyValueModel {
+    /// How many features `evaluate` takes.
+    fn num_features(&self) -> usize;
+
+    /// How many priors `evaluate` gives.
+    fn num_actions(&self) -> usize;
+
+    fn evaluate(&mut self, features: &[f32]) -> Result<PolicyValue, ModelError>;
+}
+
+/// Turns a game state into the features a model takes, from the point of
+/// view of the player to act.
+pub trait Featurizer {
+    fn num_features(&self) -> usize;
+
+    /// Clear `out` and write the features into it.
+    fn featurize(&self, game_state: &GameState, out: &mut Vec<f32>);
+}
+
+/// A simple featurizer that only looks at the current state, not how it
+/// was reached.
+///
+/// In order, the features are:
+///
+/// - 52 for the player's hole cards, 1.0 for each card held, indexed by
+///   `u8::from(card)`.
+/// - 52 for the board in the same way.
+/// - 4 for the round, preflop, flop, turn or river.
+/// - The pot, the amount to call and the player's stack, each divided by the
+///   player's starting stack.
+/// - The fraction of the players still in the hand.
+/// - The player's seat after the dealer, divided by the number of seats.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BasicFeaturizer;
+
+impl BasicFeaturizer {
+    pub const NUM_FEATURES: usize = 52 + 52 + 4 + 5;
+}
+
+impl Featurizer for BasicFeaturizer {
+    fn num_features(&self) -> usize {
+        Self::NUM_FEATURES
+    }
+
+    fn featurize(&self, game_state: &GameState, out: &mut Vec<f32>) {
+        out.clear();
+        out.resize(Self::NUM_FEATURES, 0.0);
+
+        let idx = game_state.to_act_idx();
+        let board = game_state.board_set();
+        let hole = CardSet::from(game_state.hands[idx]) - board;
+        for card in hole {
+            out[u8::from(card) as usize] = 1.0;
+        }
+        for card in board {
+            out[52 + u8::from(card) as usize] = 1.0;
+        }
+        let round = match game_state.round {
+            Round::Preflop => Some(0),
+            Round::Flop => Some(1),
+            Round::Turn => Some(2),
+            Round::River => Some(3),
+            _ => None,
+        };
+        if let Some(round) = round {
+            out[104 + round] = 1.0;
+        }
+
+        let scale = game_state.starting_stacks[idx].max(1.0);
+        let to_call =
+            game_state.current_round_bet() - game_state.current_round_current_player_bet();
+        let num_players = game_state.num_players as f32;
+        out[108] = game_state.total_pot / scale;
+        out[109] = to_call / scale;
+        out[110] = game_state.stacks[idx] / scale;
+        out[111] = game_state.num_active_players() as f32 / num_players;
+        out[112] = ((idx + game_state.num_players - game_state.dealer_idx) % game_state.num_players)
+            as f32
+            / num_players;
+    }
+}
+
+/// A model and the featurizer it takes its features from.
+pub struct ModelEvaluator {
+    featurizer: Box<dyn Featurizer>,
+    model: Box<dyn PolicyValueModel>,
+    features: Vec<f32>,
+}
+
+impl ModelEvaluator {
+    pub fn new(
+        featurizer: Box<dyn Featurizer>,
+        model: Box<dyn PolicyValueModel>,
+    ) -> Result<Self, ModelError> {
+        if featurizer.num_features() != model.num_features() {
+            return Err(ModelError::WrongFeatureCount {
+                expected: model.num_features(),
+                found: featurizer.num_features(),
+            });
+        }
+        Ok(Self {
+            features: Vec::with_capacity(featurizer.num_features()),
+            featurizer,
+            model,
+        })
+    }
+
+    /// Wrap this up to be shared between agents, as `ModelAgent` and
+    /// `MctsAgent` take it.
+    pub fn shared(self) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(self))
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.model.num_actions()
+    }
+
+    /// Evaluate the game state for the player to act.
+    pub fn evaluate(&mut self, game_state: &GameState) -> Result<PolicyValue, ModelError> {
+        self.featurizer.featurize(game_state, &mut self.features);
+        let result = self.model.evaluate(&self.features)?;
+        if result.priors.len() != self.model.num_actions() {
+            return Err(ModelError::WrongActionCount {
+                expected: self.model.num_actions(),
+                found: result.priors.len(),
+            });
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Card;
+
+    /// Gives back the first feature as the value, and fixed priors.
+    pub(super) struct FixedModel {
+        pub priors: Vec<f32>,
+        pub num_features: usize,
+    }
+
+    impl PolicyValueModel for FixedModel {
+        fn num_features(&self) -> usize {
+            self.num_features
+        }
+
+        fn num_actions(&self) -> usize {
+            self.priors.len()
+        }
+
+        fn evaluate(&mut self, features: &[f32]) -> Result<PolicyValue, ModelError> {
+            Ok(PolicyValue {
+                priors: self.priors.clone(),
+                value: features[0],
+            })
+        }
+    }
+
+    #[test]
+    fn test_basic_features() {
+        let mut game_state = GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0);
+        game_state.hands_mut()[0] = crate::core::Hand::new_from_str("2sAd").unwrap();
+        game_state.round = Round::Preflop;
+        let mut features = vec![1.0; 3];
+        BasicFeaturizer.featurize(&game_state, &mut features);
+
+        assert_eq!(BasicFeaturizer::NUM_FEATURES, features.len());
+        assert_eq!(2.0, features[..52].iter().sum::<f32>());
+        assert_eq!(
+            1.0,
+            features[u8::from(Card::try_from("Ad").unwrap()) as usize]
+        );
+        assert_eq!(0.0, features[52..104].iter().sum::<f32>());
+        assert_eq!(&[1.0, 0.0, 0.0, 0.0], &features[104..108]);
+        assert_eq!(1.0, features[110]);
+        assert_eq!(1.0, features[111]);
+    }
+
+    #[test]
+    fn test_evaluator_checks_sizes() {
+        let model = FixedModel {
+            priors: vec![1.0; 5],
+            num_features: 3,
+        };
+        assert!(matches!(
+            ModelEvaluator::new(Box::new(BasicFeaturizer), Box::new(model)),
+            Err(ModelError::WrongFeatureCount {
+                expected: 3,
+                found: BasicFeaturizer::NUM_FEATURES
+            })
+        ));
+
+        let model = FixedModel {
+            priors: vec![1.0; 5],
+            num_features: BasicFeaturizer::NUM_FEATURES,
+        };
+        let mut evaluator =
+            ModelEvaluator::new(Box::new(BasicFeaturizer), Box::new(model)).unwrap();
+        let mut game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        game_state.hands_mut()[0] = crate::core::Hand::new_from_str("2s3d").unwrap();
+        let result = evaluator.evaluate(&game_state).unwrap();
+        assert_eq!(vec![1.0; 5], result.priors);
+        // The first feature is the two of spades.
+        assert_eq!(1.0, result.value);
+    }
+}
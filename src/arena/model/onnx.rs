@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use ort::{
+    session::{Session, builder::GraphOptimizationLevel},
+    value::Tensor,
+};
+
+use crate::arena::errors::ModelError;
+
+use super::{PolicyValue, PolicyValueModel};
+
+/// A `PolicyValueModel` exported to ONNX and run with ONNX Runtime.
+///
+/// The model takes one `float32` input of shape `[1, num_features]` and has
+/// two outputs: `policy` with a prior for each action, shape
+/// `[1, num_actions]`, and `value`, shape `[1, 1]` or `[1]`. Other output
+/// names can be given with `with_output_names`.
+///
+/// ONNX Runtime itself is loaded when the first session is created, from
+/// the path in `ORT_DYLIB_PATH` or the system library path, so nothing is
+/// linked at build time.
+pub struct OnnxModel {
+    session: Session,
+    input_name: String,
+    policy_output: String,
+    value_output: String,
+    num_features: usize,
+    num_actions: usize,
+}
+
+impl OnnxModel {
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        num_features: usize,
+        num_actions: usize,
+    ) -> Result<Self, ModelError> {
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .commit_from_file(path)?;
+        let input_name = session
+            .inputs
+            .first()
+            .map(|input| input.name.clone())
+            .ok_or_else(|| ModelError::BadOutput("input".to_string()))?;
+        Ok(Self {
+            session,
+            input_name,
+            policy_output: "policy".to_string(),
+            value_output: "value".to_string(),
+            num_features,
+            num_actions,
+        })
+    }
+
+    pub fn with_output_names(mut self, policy: &str, value: &str) -> Self {
+        self.policy_output = policy.to_string();
+        self.value_output = value.to_string();
+        self
+    }
+}
+
+impl PolicyValueModel for OnnxModel {
+    fn num_features(&self) -> usize {
+        self.num_features
+    }
+
+    fn num_actions(&self) -> usize {
+        self.num_actions
+    }
+
+    fn evaluate(&mut self, features: &[f32]) -> Result<PolicyValue, ModelError> {
+        if features.len() != self.num_features {
+            return Err(ModelError::WrongFeatureCount {
+                expected: self.num_features,
+                found: features.len(),
+            });
+        }
+        let input = Tensor::from_array(([1, features.len()], features.to_vec()))?;
+        let outputs = self
+            .session
+            .run(ort::inputs![self.input_name.as_str() => input])?;
+
+        let extract = |name: &str| -> Result<Vec<f32>, ModelError> {
+            let output = outputs
+                .get(name)
+                .ok_or_else(|| ModelError::BadOutput(name.to_string()))?;
+            let (_, data) = output.try_extract_tensor::<f32>()?;
+            Ok(data.to_vec())
+        };
+        let priors = extract(&self.policy_output)?;
+        let value = extract(&self.value_output)?
+            .first()
+            .copied()
+            .ok_or_else(|| ModelError::BadOutput(self.value_output.clone()))?;
+        Ok(PolicyValue { priors, value })
+    }
+}
@@ -0,0 +1,405 @@
+//! Estimate an opponent's range from how they've played.
+//!
+//! `RangeInference` starts from a prior, usually a preflop chart, and
+//! weights every hand by how likely the opponent would have been to take
+//! each action they took up to a decision, holding that hand. The
+//! likelihoods come from a `FrequencyModel`. Hands holding a card that's
+//! on the board, or that the caller knows is elsewhere, are taken out.
+//!
+//! `ObservedFrequencies` is a model built from a corpus of recorded hands:
+//! whenever a player's hole cards were shown it counts how often they
+//! folded, called or raised with hands like that, on each street. Shown
+//! hands are mostly hands that went to showdown, so the counts lean towards
+//! hands that don't fold. Where it has seen few hands like the one being
+//! weighted it falls back towards how often the player takes each action
+//! at all.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::game_state::Round;
+//! use rs_poker::arena::hand_history::{
+//!     HandHistory, HandHistoryAction, RecordedAction, replay_hand,
+//! };
+//! use rs_poker::arena::range_inference::{ObservedFrequencies, RangeInference};
+//! use rs_poker::core::{CardBitSet, Hand, Value};
+//!
+//! // Heads up, where the button acts first preflop. Villain raises the
+//! // button with aces, which hero folds to, and folds seven deuce.
+//! let hand = |hole: &str, raise: bool| {
+//!     let action = |idx, action| HandHistoryAction {
+//!         idx,
+//!         round: Round::Preflop,
+//!         action,
+//!     };
+//!     let actions = if raise {
+//!         vec![
+//!             action(0, RecordedAction::BetTo(6.0)),
+//!             action(1, RecordedAction::Fold),
+//!         ]
+//!     } else {
+//!         vec![action(0, RecordedAction::Fold)]
+//!     };
+//!     HandHistory {
+//!         players: vec!["villain".to_string(), "hero".to_string()],
+//!         starting_stacks: vec![100.0, 100.0],
+//!         small_blind: 1.0,
+//!         big_blind: 2.0,
+//!         hole_cards: vec![Hand::new_from_str(hole).unwrap(), Hand::new()],
+//!         actions,
+//!         ..Default::default()
+//!     }
+//! };
+//! let mut corpus = vec![];
+//! for _ in 0..10 {
+//!     corpus.push(hand("AdAc", true));
+//!     corpus.push(hand("7d2c", false));
+//! }
+//! let model = ObservedFrequencies::from_histories(&corpus, "villain").unwrap();
+//!
+//! // What does villain hold once they've raised?
+//! let raised = replay_hand(&hand("KdKc", true)).unwrap();
+//! let range = RangeInference::new(model).infer(&raised, 0, 1, CardBitSet::new());
+//! let aces = range.class_weight(Value::Ace, Value::Ace, false);
+//! let seven_deuce = range.class_weight(Value::Seven, Value::Two, false);
+//! assert!(aces > 5.0 * seven_deuce);
+//! ```
+use std::collections::HashMap;
+
+use crate::arena::errors::HandHistoryError;
+use crate::arena::game_state::Round;
+use crate::arena::hand_history::{DecisionPoint, HandHistory, ReplayedHand, replay_hand};
+use crate::core::{Card, CardBitSet, Hand, Rank, Rankable, Value};
+use crate::holdem::Range;
+
+/// What a player did at a decision, ignoring sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ActionKind {
+    Fold,
+    /// Check or call.
+    Passive,
+    /// Bet or raise.
+    Aggressive,
+}
+
+impl ActionKind {
+    pub fn of(decision: &DecisionPoint) -> Self {
+        match decision.round_total() {
+            None => ActionKind::Fold,
+            Some(total) if total > decision.game_state.current_round_bet() => {
+                ActionKind::Aggressive
+            }
+            Some(_) => ActionKind::Passive,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            ActionKind::Fold => 0,
+            ActionKind::Passive => 1,
+            ActionKind::Aggressive => 2,
+        }
+    }
+}
+
+/// How likely a player is to take an action holding a given hand.
+pub trait FrequencyModel {
+    /// How likely the player acting at `decision` would be to do what they
+    /// did there if they held `hole`. Only how the likelihoods of different
+    /// hands compare matters, so they don't have to add up to anything.
+    fn likelihood(&self, decision: &DecisionPoint, hole: (Card, Card)) -> f32;
+}
+
+/// Hands that are counted together: each starting hand preflop, and after
+/// the flop what the hand makes with the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum HandBucket {
+    Preflop(Value, Value, bool),
+    Made(u8),
+}
+
+impl HandBucket {
+    fn new(hole: (Card, Card), board: &[Card]) -> Self {
+        if board.is_empty() {
+            let (high, low) = if hole.0.value >= hole.1.value {
+                (hole.0.value, hole.1.value)
+            } else {
+                (hole.1.value, hole.0.value)
+            };
+            return HandBucket::Preflop(high, low, hole.0.suit == hole.1.suit);
+        }
+        let mut hand = Hand::new_with_cards(board.to_vec());
+        hand.insert(hole.0);
+        hand.insert(hole.1);
+        HandBucket::Made(match hand.rank() {
+            Rank::HighCard(_) => 0,
+            Rank::OnePair(_) => 1,
+            Rank::TwoPair(_) => 2,
+            Rank::ThreeOfAKind(_) => 3,
+            Rank::Straight(_) => 4,
+            Rank::Flush(_) => 5,
+            Rank::FullHouse(_) => 6,
+            Rank::FourOfAKind(_) => 7,
+            Rank::StraightFlush(_) => 8,
+        })
+    }
+}
+
+/// Action frequencies counted from recorded hands. See the module docs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservedFrequencies {
+    /// Folds, calls and raises with hands whose cards were shown.
+    by_hand: HashMap<(Round, HandBucket), [f32; 3]>,
+    /// Folds, calls and raises with any hand.
+    by_round: HashMap<Round, [f32; 3]>,
+    /// How many decisions with a kind of hand it takes before they count
+    /// for as much as the player's overall frequencies.
+    pub smoothing: f32,
+}
+
+impl Default for ObservedFrequencies {
+    fn default() -> Self {
+        Self {
+            by_hand: HashMap::new(),
+            by_round: HashMap::new(),
+            smoothing: 2.0,
+        }
+    }
+}
+
+impl ObservedFrequencies {
+    /// Count every decision `player` made in `histories`.
+    pub fn from_histories(
+        histories: &[HandHistory],
+        player: &str,
+    ) -> Result<Self, HandHistoryError> {
+        let mut frequencies = Self::default();
+        for history in histories {
+            let Some(idx) = history.player_idx(player) else {
+                continue;
+            };
+            let hole = history
+                .hole_cards
+                .get(idx)
+                .filter(|hand| hand.count() == 2)
+                .map(|hand| {
+                    let mut cards = hand.iter();
+                    (cards.next().unwrap(), cards.next().unwrap())
+                });
+            let replayed = replay_hand(history)?;
+            for decision in replayed.decision_points.iter().filter(|d| d.idx == idx) {
+                frequencies.observe(decision, hole);
+            }
+        }
+        Ok(frequencies)
+    }
+
+    /// Count one decision, with the player's hole cards if they're known.
+    pub fn observe(&mut self, decision: &DecisionPoint, hole: Option<(Card, Card)>) {
+        let kind = ActionKind::of(decision).index();
+        let round = decision.game_state.round;
+        self.by_round.entry(round).or_default()[kind] += 1.0;
+        if let Some(hole) = hole {
+            let bucket = HandBucket::new(hole, &decision.game_state.board);
+            self.by_hand.entry((round, bucket)).or_default()[kind] += 1.0;
+        }
+    }
+
+    /// How often the player takes `kind` of action in `round` holding
+    /// `hole` with `board`.
+    pub fn frequency(
+        &self,
+        round: Round,
+        hole: (Card, Card),
+        board: &[Card],
+        kind: ActionKind,
+    ) -> f32 {
+        let kind = kind.index();
+        // Every action is assumed to have been seen once, so nothing is
+        // ever ruled out entirely.
+        let overall = self.by_round.get(&round).copied().unwrap_or_default();
+        let overall = (overall[kind] + 1.0) / (overall.iter().sum::<f32>() + 3.0);
+
+        let seen = self
+            .by_hand
+            .get(&(round, HandBucket::new(hole, board)))
+            .copied()
+            .unwrap_or_default();
+        (seen[kind] + self.smoothing * overall) / (seen.iter().sum::<f32>() + self.smoothing)
+    }
+}
+
+impl FrequencyModel for ObservedFrequencies {
+    fn likelihood(&self, decision: &DecisionPoint, hole: (Card, Card)) -> f32 {
+        self.frequency(
+            decision.game_state.round,
+            hole,
+            &decision.game_state.board,
+            ActionKind::of(decision),
+        )
+    }
+}
+
+/// Works out an opponent's range from a prior and a `FrequencyModel`. See
+/// the module docs.
+#[derive(Debug, Clone)]
+pub struct RangeInference<M: FrequencyModel> {
+    model: M,
+    prior: Range,
+}
+
+impl<M: FrequencyModel> RangeInference<M> {
+    /// Infer ranges starting from every hand being as likely as any other.
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            prior: Range::full(),
+        }
+    }
+
+    /// What the player is thought to hold before they act, such as a
+    /// preflop chart for their position.
+    pub fn prior(mut self, prior: Range) -> Self {
+        self.prior = prior;
+        self
+    }
+
+    /// The range of `player` just before the hand's decision point number
+    /// `decision`, or at the end of the hand if that's past the last one,
+    /// normalized to add up to one. Hands holding a board card or one of
+    /// `dead`, such as the hero's hole cards, are left out.
+    pub fn infer(
+        &self,
+        hand: &ReplayedHand,
+        player: usize,
+        decision: usize,
+        dead: CardBitSet,
+    ) -> Range {
+        let decisions = &hand.decision_points[..decision.min(hand.decision_points.len())];
+        let board = match hand.decision_points.get(decision) {
+            Some(point) => &point.game_state.board,
+            None => &hand.game_state.board,
+        };
+        let dead = board.iter().fold(dead, |mut dead, card| {
+            dead.insert(*card);
+            dead
+        });
+
+        let mut range = self.prior.clone();
+        range.remove_cards(dead);
+        let combos: Vec<(Card, Card, f32)> = range.combos().collect();
+        for (first, second, weight) in combos {
+            let likelihood: f32 = decisions
+                .iter()
+                .filter(|point| point.idx == player)
+                .map(|point| self.model.likelihood(point, (first, second)))
+                .product();
+            range.set_weight(first, second, weight * likelihood);
+        }
+        range.normalize();
+        range
+    }
+}
+
+/// Lets any function of a decision and hole cards be used as a model.
+impl<F: Fn(&DecisionPoint, (Card, Card)) -> f32> FrequencyModel for F {
+    fn likelihood(&self, decision: &DecisionPoint, hole: (Card, Card)) -> f32 {
+        self(decision, hole)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::hand_history::{HandHistoryAction, RecordedAction};
+    use crate::core::Suit;
+
+    fn heads_up(hole: &str, actions: Vec<HandHistoryAction>) -> HandHistory {
+        HandHistory {
+            players: vec!["villain".to_string(), "hero".to_string()],
+            starting_stacks: vec![100.0, 100.0],
+            small_blind: 1.0,
+            big_blind: 2.0,
+            hole_cards: vec![Hand::new_from_str(hole).unwrap(), Hand::new()],
+            board: crate::arena::hand_history::parse_cards("2s7h9dTcJc").unwrap(),
+            actions,
+            ..Default::default()
+        }
+    }
+
+    fn action(idx: usize, round: Round, action: RecordedAction) -> HandHistoryAction {
+        HandHistoryAction { idx, round, action }
+    }
+
+    #[test]
+    fn test_frequencies_fall_back_to_overall() {
+        let raise = vec![
+            action(0, Round::Preflop, RecordedAction::BetTo(6.0)),
+            action(1, Round::Preflop, RecordedAction::Fold),
+        ];
+        let corpus: Vec<HandHistory> = (0..4).map(|_| heads_up("AdAc", raise.clone())).collect();
+        let model = ObservedFrequencies::from_histories(&corpus, "villain").unwrap();
+
+        let aces = (
+            Card::new(Value::Ace, Suit::Spade),
+            Card::new(Value::Ace, Suit::Heart),
+        );
+        let kings = (
+            Card::new(Value::King, Suit::Spade),
+            Card::new(Value::King, Suit::Heart),
+        );
+        // Four raises out of four, plus a made up one of each action.
+        let overall = 5.0 / 7.0;
+        let raise = model.frequency(Round::Preflop, aces, &[], ActionKind::Aggressive);
+        assert!((raise - (4.0 + 2.0 * overall) / 6.0).abs() < 1e-6);
+        let raise = model.frequency(Round::Preflop, kings, &[], ActionKind::Aggressive);
+        assert!((raise - overall).abs() < 1e-6);
+        // Unknown players have nothing counted.
+        assert_eq!(
+            ObservedFrequencies::default(),
+            ObservedFrequencies::from_histories(&corpus, "nobody").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_infer_removes_dead_cards_and_weighs_every_street() {
+        // Villain calls preflop then bets the flop.
+        let hand = heads_up(
+            "KsKh",
+            vec![
+                action(0, Round::Preflop, RecordedAction::Call),
+                action(1, Round::Preflop, RecordedAction::Check),
+                action(1, Round::Flop, RecordedAction::Check),
+                action(0, Round::Flop, RecordedAction::BetTo(4.0)),
+                action(1, Round::Flop, RecordedAction::Fold),
+            ],
+        );
+        let replayed = replay_hand(&hand).unwrap();
+        // Flop bets come only from sets.
+        let model = |point: &DecisionPoint, hole: (Card, Card)| {
+            let board = &point.game_state.board;
+            match HandBucket::new(hole, board) {
+                HandBucket::Made(3) if ActionKind::of(point) == ActionKind::Aggressive => 1.0,
+                _ if ActionKind::of(point) == ActionKind::Aggressive => 0.0,
+                _ => 1.0,
+            }
+        };
+        let inference = RangeInference::new(model).prior("22+".parse().unwrap());
+
+        // Before the flop bet every pair is still in, less those holding a
+        // board card or hero's queen.
+        let mut dead = CardBitSet::new();
+        dead.insert(Card::new(Value::Queen, Suit::Club));
+        let range = inference.infer(&replayed, 0, 3, dead);
+        assert_eq!(13 * 6 - 3 * 3 - 3, range.combos().count());
+
+        // After it only the nine sets are left, equally likely, so the three
+        // nines left average out over all six combinations of the pair.
+        let range = inference.infer(&replayed, 0, 4, dead);
+        assert_eq!(9, range.combos().count());
+        let nines = range.class_weight(Value::Nine, Value::Nine, false);
+        assert!((nines - 1.0 / 18.0).abs() < 1e-6);
+        assert_eq!(0.0, range.class_weight(Value::King, Value::King, false));
+    }
+}
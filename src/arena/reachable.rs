@@ -0,0 +1,165 @@
+//! Game states that can come up in a real hand, for property testing and
+//! fuzzing.
+//!
+//! A `ReachableGameState` is a recipe: the table, what each seat will do,
+//! a seed for the deal and how far to play. Following it runs a simulation,
+//! so every state it gives is one the engine itself got to. With the
+//! `arbitrary` feature `GameState` implements `Arbitrary` this way, and with
+//! the `proptest` feature so does `strategy::game_state`.
+use super::action::AgentAction;
+use super::agent::VecReplayAgent;
+use super::{Agent, GameState, HoldemSimulationBuilder};
+
+/// The most players a reachable game state seats.
+pub const MAX_REACHABLE_PLAYERS: usize = 9;
+
+/// How to get to a game state by playing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReachableGameState {
+    pub stacks: Vec<f32>,
+    pub big_blind: f32,
+    pub small_blind: f32,
+    pub ante: f32,
+    pub dealer_idx: usize,
+    /// What each seat does, in order. Seats fold once they run out.
+    pub actions: Vec<Vec<AgentAction>>,
+    /// Seeds the deal.
+    pub seed: u64,
+    /// How many rounds to play. Enough rounds plays the hand out.
+    pub rounds: usize,
+}
+
+impl ReachableGameState {
+    /// Play the hand as far as the recipe says.
+    pub fn game_state(&self) -> GameState {
+        let agents: Vec<Box<dyn Agent>> = (0..self.stacks.len())
+            .map(|idx| {
+                let actions = self.actions.get(idx).cloned().unwrap_or_default();
+                Box::new(VecReplayAgent::new(actions)) as Box<dyn Agent>
+            })
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(
+                self.stacks.clone(),
+                self.big_blind,
+                self.small_blind,
+                self.ante,
+                self.dealer_idx,
+            ))
+            .agents(agents)
+            .with_seed(self.seed)
+            .build()
+            .expect("game state is always set");
+
+        for _ in 0..self.rounds {
+            if !sim.more_rounds() {
+                break;
+            }
+            // Seeded simulations deal from their own rng.
+            sim.run_round(&mut crate::core::rng());
+        }
+        sim.game_state
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impl {
+    use arbitrary::{Arbitrary, Result, Unstructured};
+
+    use super::*;
+
+    /// Bets are whole numbers of small blinds so that they land on the
+    /// amounts real hands are played with.
+    fn action(u: &mut Unstructured, small_blind: f32) -> Result<AgentAction> {
+        Ok(match u.int_in_range(0..=3)? {
+            0 => AgentAction::Fold,
+            1 => AgentAction::AllIn,
+            _ => AgentAction::Bet(u.int_in_range(0_u16..=400)? as f32 * small_blind),
+        })
+    }
+
+    impl<'a> Arbitrary<'a> for ReachableGameState {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            let num_players = u.int_in_range(2..=MAX_REACHABLE_PLAYERS)?;
+            let small_blind = u.int_in_range(1_u16..=10)? as f32;
+            let big_blind = small_blind * 2.0;
+            let ante = u.int_in_range(0_u16..=small_blind as u16)? as f32;
+            let stacks = (0..num_players)
+                .map(|_| Ok(u.int_in_range(1_u16..=2000)? as f32))
+                .collect::<Result<Vec<_>>>()?;
+            let dealer_idx = u.choose_index(num_players)?;
+            let actions = (0..num_players)
+                .map(|_| {
+                    let len = u.int_in_range(0..=24)?;
+                    (0..len).map(|_| action(u, small_blind)).collect()
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Self {
+                stacks,
+                big_blind,
+                small_blind,
+                ante,
+                dealer_idx,
+                actions,
+                seed: u.arbitrary()?,
+                rounds: u.int_in_range(0..=12)?,
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for GameState {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(ReachableGameState::arbitrary(u)?.game_state())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::game_state::Round;
+    use crate::arena::test_util::{
+        assert_valid_game_state, assert_valid_partial_game_state, assert_valid_round_data,
+    };
+
+    #[test]
+    fn test_plays_to_the_end() {
+        let recipe = ReachableGameState {
+            stacks: vec![100.0; 3],
+            big_blind: 2.0,
+            small_blind: 1.0,
+            ante: 0.0,
+            dealer_idx: 0,
+            actions: vec![vec![AgentAction::Bet(2.0)], vec![AgentAction::Bet(2.0)]],
+            seed: 7,
+            rounds: 100,
+        };
+        let game_state = recipe.game_state();
+        assert_eq!(Round::Complete, game_state.round);
+        assert_valid_game_state(&game_state);
+        assert_valid_round_data(&game_state.round_data);
+        // The same recipe always gets to the same place.
+        assert_eq!(game_state, recipe.game_state());
+
+        let started = ReachableGameState {
+            rounds: 3,
+            ..recipe
+        }
+        .game_state();
+        assert_eq!(Round::Preflop, started.round);
+        assert!(started.hands.iter().all(|hand| hand.count() == 2));
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_game_states_are_valid() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let bytes: Vec<u8> = (0..8192_u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut u = Unstructured::new(&bytes);
+        while !u.is_empty() {
+            let game_state = GameState::arbitrary(&mut u).unwrap();
+            assert_valid_partial_game_state(&game_state);
+        }
+    }
+}
@@ -0,0 +1,650 @@
+//! Review played hands for mistakes.
+//!
+//! `HandReviewer` walks every decision in a hand and compares what was
+//! played with what a baseline would have played. The baseline is either
+//! a `StrategyProfile` from CFR training or, where there's no profile or it
+//! has nothing for a decision, equity thresholds: call when the player's
+//! equity beats the pot odds and fold when it doesn't. Equity thresholds
+//! only judge folding against carrying on, so they never flag the size of
+//! a bet.
+//!
+//! EVs come from the player's equity, assuming the hand is checked down
+//! after the decision and that any bet or raise is called. That's rough,
+//! but it's the same for every action so the difference between two of
+//! them, the EV lost, is a fair guide to how big a mistake was. Decisions
+//! that lose more than `ReviewConfig::max_ev_loss` big blinds are reported.
+//! Decisions by players whose hole cards aren't known are skipped, and so
+//! are decisions in games other than hold'em and Omaha, where equity can't
+//! be found by running out the board.
+//!
+//! Hands can come from the arena, recorded with a `VecHistorian`, or be
+//! imported with `hand_history` and replayed.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::agent::{CallingAgent, FoldingAgent};
+//! use rs_poker::arena::historian::VecHistorian;
+//! use rs_poker::arena::review::{HandReviewer, ReviewConfig};
+//! use rs_poker::arena::{Agent, GameState, HoldemSimulationBuilder};
+//!
+//! let historian = VecHistorian::default();
+//! let records = historian.get_storage();
+//! let agents: Vec<Box<dyn Agent>> = vec![
+//!     Box::<CallingAgent>::default(),
+//!     Box::<FoldingAgent>::default(),
+//! ];
+//! let mut sim = HoldemSimulationBuilder::default()
+//!     .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+//!     .agents(agents)
+//!     .historians(vec![Box::new(historian)])
+//!     .build()
+//!     .unwrap();
+//! sim.run(&mut rand::rng());
+//!
+//! let reviewer = HandReviewer::new(ReviewConfig::default());
+//! let reports = reviewer.review_records(&records.borrow());
+//! assert_eq!(1, reports.len());
+//! assert!(reports[0].decisions_reviewed > 0);
+//! ```
+use std::marker::PhantomData;
+
+use rand::{Rng, seq::index::sample};
+
+use crate::arena::GameState;
+use crate::arena::action::{Action, AgentAction, PlayedActionPayload};
+use crate::arena::cfr::{
+    ActionGenerator, BasicCFRActionGenerator, CFRState, StrategyProfile, TraversalState,
+};
+use crate::arena::game_state::Round;
+use crate::arena::hand_history::ReplayedHand;
+use crate::arena::historian::HistoryRecord;
+use crate::core::{Card, CardSet, Hand};
+
+/// How strict a review is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewConfig {
+    /// Report decisions that lose more than this many big blinds of EV
+    /// compared to the baseline.
+    pub max_ev_loss: f32,
+    /// How many runouts, and hands for the other players, to sample when
+    /// estimating equity.
+    pub equity_samples: usize,
+    /// Judge equity against the cards the other players really held, where
+    /// they're known, rather than against random hands. This is what the
+    /// result says in hindsight rather than what the player could know.
+    pub hindsight: bool,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self {
+            max_ev_loss: 1.0,
+            equity_samples: 2_000,
+            hindsight: false,
+        }
+    }
+}
+
+/// A decision that lost more than the config allows.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mistake {
+    pub idx: usize,
+    pub round: Round,
+    /// The game state the decision was made in.
+    pub game_state: GameState,
+    pub action: AgentAction,
+    /// What the baseline would have played.
+    pub recommended: AgentAction,
+    /// The player's estimated share of the pot at showdown.
+    pub equity: f32,
+    /// How many chips `action` lost compared to `recommended`.
+    pub ev_loss: f32,
+    /// How likely the strategy profile was to play `action`, if it had a
+    /// strategy for this decision.
+    pub probability: Option<f32>,
+}
+
+/// Every mistake found in one hand.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandReport {
+    /// The id of the simulation or hand history, if there was one.
+    pub id: Option<String>,
+    pub decisions_reviewed: usize,
+    pub mistakes: Vec<Mistake>,
+}
+
+impl HandReport {
+    /// The EV lost over every mistake, in chips.
+    pub fn total_ev_loss(&self) -> f32 {
+        self.mistakes.iter().map(|m| m.ev_loss).sum()
+    }
+}
+
+/// What happened in a hand, in the order the CFR tree sees it.
+enum Event {
+    /// A hole card dealt to a player.
+    HoleCard(usize, Card),
+    Community(Card),
+    Decision {
+        game_state: Box<GameState>,
+        idx: usize,
+        action: AgentAction,
+        /// False when only the state after the action was recorded, as
+        /// for the first action of a simulation that started mid hand. The
+        /// action still moves along the path but can't be judged.
+        reviewable: bool,
+    },
+}
+
+/// Finds the mistakes in played hands. See the module docs.
+///
+/// `T` is the `ActionGenerator` the strategy profile was trained with,
+/// which maps actions to the indices the profile uses. Decisions are found
+/// in the profile by following the same path the `CFRHistorian` builds: a
+/// step for each of the player's own hole cards, each community card and
+/// every player's action, from the start of the hand.
+pub struct HandReviewer<T: ActionGenerator = BasicCFRActionGenerator> {
+    config: ReviewConfig,
+    profile: Option<StrategyProfile>,
+    action_generator: PhantomData<T>,
+}
+
+impl HandReviewer {
+    /// A reviewer that only uses equity thresholds.
+    pub fn new(config: ReviewConfig) -> Self {
+        Self {
+            config,
+            profile: None,
+            action_generator: PhantomData,
+        }
+    }
+}
+
+impl<T: ActionGenerator> HandReviewer<T> {
+    /// A reviewer that plays what `profile` plays most, falling back to
+    /// equity thresholds where it has no strategy.
+    pub fn with_profile(config: ReviewConfig, profile: StrategyProfile) -> Self {
+        Self {
+            config,
+            profile: Some(profile),
+            action_generator: PhantomData,
+        }
+    }
+
+    /// Review hands recorded by a `VecHistorian`, one report per hand.
+    pub fn review_records(&self, records: &[HistoryRecord]) -> Vec<HandReport> {
+        let mut hands: Vec<Vec<Event>> = vec![];
+        for record in records {
+            let event = match &record.action {
+                Action::GameStart(_) => {
+                    hands.push(vec![]);
+                    None
+                }
+                Action::DealStartingHand(payload) => {
+                    Some(Event::HoleCard(payload.idx, payload.card))
+                }
+                Action::DealCommunity(card) => Some(Event::Community(*card)),
+                Action::PlayedAction(payload) => Some(decision(record, payload)),
+                Action::FailedAction(payload) => Some(decision(record, &payload.result)),
+                _ => None,
+            };
+            if let Some(event) = event {
+                if hands.is_empty() {
+                    hands.push(vec![]);
+                }
+                hands.last_mut().unwrap().push(event);
+            }
+        }
+        hands
+            .iter()
+            .map(|events| self.review(events, None))
+            .collect()
+    }
+
+    /// Review a hand imported from a hand history.
+    pub fn review_replayed(&self, hand: &ReplayedHand, id: Option<String>) -> HandReport {
+        let mut events = vec![];
+        if let Some(first) = hand.decision_points.first() {
+            // Hole cards are dealt in order, each player's sorted, as the
+            // simulation deals them.
+            for (idx, hand) in first.game_state.hands.iter().enumerate() {
+                let mut cards: Vec<Card> = hand.iter().collect();
+                cards.sort();
+                events.extend(cards.into_iter().map(|card| Event::HoleCard(idx, card)));
+            }
+        }
+        let mut board_len = 0;
+        for decision in &hand.decision_points {
+            let board = &decision.game_state.board;
+            let mut dealt: Vec<Card> = board[board_len.min(board.len())..].to_vec();
+            dealt.sort();
+            events.extend(dealt.into_iter().map(Event::Community));
+            board_len = board.len();
+            events.push(Event::Decision {
+                game_state: Box::new(decision.game_state.clone()),
+                idx: decision.idx,
+                action: decision.action.clone(),
+                reviewable: true,
+            });
+        }
+        self.review(&events, id)
+    }
+
+    fn review(&self, events: &[Event], id: Option<String>) -> HandReport {
+        let mut rng = crate::core::rng();
+        let mut report = HandReport {
+            id,
+            ..Default::default()
+        };
+        let Some(starting) = events.iter().find_map(|e| match e {
+            Event::Decision { game_state, .. } => Some(&**game_state),
+            _ => None,
+        }) else {
+            return report;
+        };
+        let num_players = starting.num_players;
+        let generators: Vec<T> = (0..num_players)
+            .map(|idx| {
+                T::new(
+                    CFRState::new(starting.clone()),
+                    TraversalState::new_root(idx),
+                )
+            })
+            .collect();
+        // Each player's path through their own tree.
+        let mut paths: Vec<Vec<usize>> = vec![vec![0]; num_players];
+
+        for event in events {
+            match event {
+                Event::HoleCard(idx, card) => {
+                    if let Some(path) = paths.get_mut(*idx) {
+                        path.push(u8::from(*card) as usize);
+                    }
+                }
+                Event::Community(card) => {
+                    for path in paths.iter_mut() {
+                        path.push(u8::from(*card) as usize);
+                    }
+                }
+                Event::Decision {
+                    game_state,
+                    idx,
+                    action,
+                    reviewable,
+                } => {
+                    let hole = CardSet::from(game_state.hands[*idx]) - game_state.board_set();
+                    let variant = game_state.variant;
+                    if *reviewable
+                        && variant.has_board_equity()
+                        && hole.count() >= variant.hole_cards()
+                    {
+                        report.decisions_reviewed += 1;
+                        report.mistakes.extend(self.review_decision(
+                            game_state,
+                            *idx,
+                            action,
+                            &generators[*idx],
+                            &paths[*idx],
+                            &mut rng,
+                        ));
+                    }
+                    for (path, generator) in paths.iter_mut().zip(&generators) {
+                        path.push(generator.action_to_idx(game_state, action));
+                    }
+                }
+            }
+        }
+        report
+    }
+
+    fn review_decision<R: Rng>(
+        &self,
+        game_state: &GameState,
+        idx: usize,
+        action: &AgentAction,
+        generator: &T,
+        path: &[usize],
+        rng: &mut R,
+    ) -> Option<Mistake> {
+        let equity = estimate_equity(game_state, idx, &self.config, rng);
+        let played_ev = action_ev(game_state, idx, action, equity);
+
+        let strategy = self.profile.as_ref().and_then(|p| p.get(idx, path));
+        let (recommended, probability) = match strategy {
+            Some(strategy) => {
+                let probability = |a: &AgentAction| {
+                    strategy
+                        .get(generator.action_to_idx(game_state, a))
+                        .copied()
+                        .unwrap_or(0.0)
+                };
+                let recommended = generator
+                    .gen_possible_actions(game_state)
+                    .into_iter()
+                    .max_by(|a, b| probability(a).total_cmp(&probability(b)))?;
+                (recommended, Some(probability(action)))
+            }
+            None => {
+                let legal = game_state.legal_actions();
+                let call = AgentAction::Bet(legal.call);
+                if legal.can_fold && action_ev(game_state, idx, &call, equity) < 0.0 {
+                    (AgentAction::Fold, None)
+                } else if *action == AgentAction::Fold {
+                    (call, None)
+                } else {
+                    // Pot odds only say whether to carry on, not how much
+                    // to bet, so any way of carrying on is fine.
+                    return None;
+                }
+            }
+        };
+
+        let ev_loss = action_ev(game_state, idx, &recommended, equity) - played_ev;
+        (ev_loss > self.config.max_ev_loss * game_state.big_blind).then(|| Mistake {
+            idx,
+            round: game_state.round,
+            game_state: game_state.clone(),
+            action: action.clone(),
+            recommended,
+            equity,
+            ev_loss,
+            probability,
+        })
+    }
+}
+
+fn decision(record: &HistoryRecord, payload: &PlayedActionPayload) -> Event {
+    Event::Decision {
+        game_state: Box::new(
+            record
+                .before_game_state
+                .clone()
+                .unwrap_or_else(|| record.after_game_state.clone()),
+        ),
+        idx: payload.idx,
+        action: payload.action.clone(),
+        reviewable: record.before_game_state.is_some(),
+    }
+}
+
+/// The chip EV of `action` over folding, if the hand is checked down after
+/// and any raise is called.
+fn action_ev(game_state: &GameState, idx: usize, action: &AgentAction, equity: f32) -> f32 {
+    let stack = game_state.stacks[idx];
+    let player_bet = game_state.current_round_player_bet(idx);
+    let to_call = (game_state.current_round_bet() - player_bet).clamp(0.0, stack);
+    let put_in = match action {
+        AgentAction::Fold => return 0.0,
+        AgentAction::Bet(amount) => (amount - player_bet).clamp(to_call, stack),
+        AgentAction::AllIn => stack,
+    };
+    // The most any other player still in can put in to call the raise.
+    let others_behind = (game_state.player_active | game_state.player_all_in)
+        .ones()
+        .filter(|other| *other != idx)
+        .map(|other| game_state.stacks[other])
+        .fold(0.0_f32, f32::max);
+    let called = (put_in - to_call).min(others_behind);
+    equity * (game_state.total_pot + put_in + called) - put_in
+}
+
+/// The share of the pot `idx` would win at showdown against the others
+/// still in the hand, sampling their hands unless they're known and
+/// `hindsight` is set. Hands are ranked by the rules of the game.
+fn estimate_equity<R: Rng>(
+    game_state: &GameState,
+    idx: usize,
+    config: &ReviewConfig,
+    rng: &mut R,
+) -> f32 {
+    let variant = game_state.variant;
+    let num_hole = variant.hole_cards();
+    let board = game_state.board_set();
+    let hole = |i: usize| CardSet::from(game_state.hands[i]) - board;
+    let others: Vec<usize> = (game_state.player_active | game_state.player_all_in)
+        .ones()
+        .filter(|other| *other != idx)
+        .collect();
+    if others.is_empty() {
+        return 1.0;
+    }
+    let known = |i: usize| config.hindsight && hole(i).count() >= num_hole;
+
+    let mut dead = board | hole(idx);
+    for &other in &others {
+        if known(other) {
+            dead |= hole(other);
+        }
+    }
+    let remaining: Vec<Card> = (!dead).into_iter().collect();
+    let num_board = 5_usize.saturating_sub(game_state.board.len());
+    let num_unknown = others.iter().filter(|o| !known(**o)).count();
+    let num_cards = num_board + num_hole * num_unknown;
+
+    let samples = if num_cards == 0 {
+        1
+    } else {
+        config.equity_samples.max(1)
+    };
+    let mut total = 0.0_f64;
+    for _ in 0..samples {
+        let mut dealt = sample(rng, remaining.len(), num_cards)
+            .into_iter()
+            .map(|i| remaining[i]);
+        let mut runout = board;
+        for card in dealt.by_ref().take(num_board) {
+            runout.insert(card);
+        }
+        let runout_cards: Vec<Card> = runout.into_iter().collect();
+        let rank_of = |cards: CardSet| variant.showdown_rank(&Hand::from(cards), &runout_cards);
+        let hero = rank_of(runout | hole(idx));
+        let mut best = hero;
+        let mut winners = 1;
+        for &other in &others {
+            let cards = if known(other) {
+                hole(other)
+            } else {
+                dealt.by_ref().take(num_hole).collect()
+            };
+            let rank = rank_of(runout | cards);
+            if rank > best {
+                best = rank;
+                winners = 0;
+            }
+            if rank == best {
+                winners += 1;
+            }
+        }
+        if best == hero {
+            total += 1.0 / winners as f64;
+        }
+    }
+    (total / samples as f64) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::agent::VecReplayAgent;
+    use crate::arena::game_state::RoundData;
+    use crate::arena::hand_history::{parse_cards, parse_phh, replay_hand};
+    use crate::arena::historian::VecHistorian;
+    use crate::arena::variant::GameVariant;
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+    use crate::core::PlayerBitSet;
+
+    fn river(hole: [&str; 2], board: &str) -> GameState {
+        let board = parse_cards(board).unwrap();
+        let hands = hole
+            .iter()
+            .map(|hole| {
+                let mut hand = Hand::new_from_str(hole).unwrap();
+                hand.extend(board.iter().copied());
+                hand
+            })
+            .collect();
+        GameState::new(
+            Round::River,
+            RoundData::new(2, 10.0, PlayerBitSet::new(2), 0),
+            board,
+            hands,
+            vec![100.0, 100.0],
+            vec![50.0, 50.0],
+            10.0,
+            5.0,
+            0.0,
+            1,
+        )
+    }
+
+    fn record(game_state: GameState, actions: [Vec<AgentAction>; 2]) -> Vec<HistoryRecord> {
+        let historian = VecHistorian::default();
+        let records = historian.get_storage();
+        let agents: Vec<Box<dyn Agent>> = actions
+            .into_iter()
+            .map(|a| Box::new(VecReplayAgent::new(a)) as Box<dyn Agent>)
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .variant(game_state.variant)
+            .game_state(game_state)
+            .agents(agents)
+            .historians(vec![Box::new(historian)])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        records.borrow().clone()
+    }
+
+    #[test]
+    fn test_folding_the_nuts_is_a_mistake() {
+        // Player 0 has a royal flush and folds to a bet.
+        let game_state = river(["AsKs", "7h8h"], "QsJsTs2d3c");
+        let records = record(
+            game_state,
+            [
+                vec![AgentAction::Bet(0.0), AgentAction::Fold],
+                vec![AgentAction::Bet(50.0)],
+            ],
+        );
+        let reports = HandReviewer::new(ReviewConfig::default()).review_records(&records);
+        assert_eq!(1, reports.len());
+        let report = &reports[0];
+        // The simulation starts on the river so the check is the first
+        // thing recorded, without the state before it.
+        assert_eq!(2, report.decisions_reviewed);
+
+        // Checking the nuts isn't a mistake by pot odds, but folding them
+        // to a bet gives up the 150 in the pot and the 50 bet.
+        assert_eq!(1, report.mistakes.len());
+        let mistake = &report.mistakes[0];
+        assert_eq!(0, mistake.idx);
+        assert_eq!(AgentAction::Fold, mistake.action);
+        assert_eq!(AgentAction::Bet(50.0), mistake.recommended);
+        assert_eq!(1.0, mistake.equity);
+        assert_eq!(150.0, mistake.ev_loss);
+        assert_eq!(report.total_ev_loss(), mistake.ev_loss);
+    }
+
+    #[test]
+    fn test_omaha() {
+        // The ace of hearts is the nut flush in hold'em, but in Omaha it's
+        // ace high against a flush with two hearts, so folding is right.
+        let mut game_state = river(["Ah2c3d4s", "8h7hTd6s"], "KhQhJh5h9c");
+        game_state.variant = GameVariant::Omaha;
+        let config = ReviewConfig {
+            hindsight: true,
+            ..Default::default()
+        };
+        let actions = || {
+            [
+                vec![AgentAction::Bet(0.0), AgentAction::Fold],
+                vec![AgentAction::Bet(50.0)],
+            ]
+        };
+
+        let records = record(game_state.clone(), actions());
+        let report = &HandReviewer::new(config.clone()).review_records(&records)[0];
+        assert_eq!(2, report.decisions_reviewed);
+        assert!(report.mistakes.is_empty());
+
+        game_state.variant = GameVariant::Holdem;
+        let records = record(game_state.clone(), actions());
+        let report = &HandReviewer::new(config.clone()).review_records(&records)[0];
+        assert_eq!(1, report.mistakes.len());
+        assert_eq!(1.0, report.mistakes[0].equity);
+
+        // Draw games can't be run out, so nothing is reviewed.
+        game_state.variant = GameVariant::FiveCardDraw;
+        let records = record(game_state, actions());
+        let report = &HandReviewer::new(config).review_records(&records)[0];
+        assert_eq!(0, report.decisions_reviewed);
+    }
+
+    #[test]
+    fn test_profile_baseline() {
+        // The profile always folds for player 1, so calling is a mistake
+        // when they're beaten.
+        let game_state = river(["AsKs", "7h8h"], "QsJsTs2d3c");
+        let records = record(
+            game_state,
+            [vec![AgentAction::AllIn], vec![AgentAction::Bet(100.0)]],
+        );
+        let mut profile = StrategyProfile::new();
+        // Root, then player 0's all in.
+        profile.insert(1, vec![0, 2], vec![1.0, 0.0, 0.0]);
+        // Judged against player 0's real hand.
+        let config = ReviewConfig {
+            hindsight: true,
+            ..Default::default()
+        };
+        let reviewer = HandReviewer::<BasicCFRActionGenerator>::with_profile(config, profile);
+        let report = &reviewer.review_records(&records)[0];
+
+        assert_eq!(1, report.decisions_reviewed);
+        assert_eq!(1, report.mistakes.len());
+        let mistake = &report.mistakes[0];
+        assert_eq!(1, mistake.idx);
+        assert_eq!(AgentAction::Fold, mistake.recommended);
+        assert_eq!(Some(0.0), mistake.probability);
+        assert_eq!(0.0, mistake.equity);
+        assert_eq!(100.0, mistake.ev_loss);
+    }
+
+    #[test]
+    fn test_review_replayed_hand() {
+        let phh = r#"
+variant = 'NT'
+antes = [0, 0, 0]
+blinds_or_straddles = [1, 2, 0]
+min_bet = 2
+starting_stacks = [100, 100, 100]
+actions = [
+  'd dh p1 AcAs',
+  'd dh p2 7h6h',
+  'd dh p3 ????',
+  'p3 f',
+  'p1 cbr 6',
+  'p2 f',
+]
+"#;
+        let history = parse_phh(phh).unwrap();
+        let replayed = replay_hand(&history).unwrap();
+        let reviewer = HandReviewer::new(ReviewConfig {
+            equity_samples: 500,
+            ..Default::default()
+        });
+        // Seeded so the sampled equity can't wander over the threshold.
+        let report = crate::core::with_seed(7, || {
+            reviewer.review_replayed(&replayed, Some("1".to_string()))
+        });
+        assert_eq!(Some("1".to_string()), report.id);
+        // Nothing is known about the third player's cards.
+        assert_eq!(2, report.decisions_reviewed);
+        // Folding suited connectors to a raise isn't worth a big blind
+        // either way.
+        assert!(report.mistakes.is_empty(), "{:?}", report.mistakes);
+    }
+}
@@ -1,27 +1,23 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
-use crate::core::{CardBitSet, Deck};
+use crate::core::{Card, CardBitSet, Deck};
 
 use super::{
-    Agent, GameState, HoldemSimulation, agent::FoldingAgent, errors::HoldemSimulationError,
+    Agent, GameState, HoldemSimulation,
+    agent::FoldingAgent,
+    betting::BettingStructure,
+    dealing::{DealingProcedure, DeckAudit},
+    errors::HoldemSimulationError,
     historian::Historian,
+    timing::TimingStats,
+    variant::GameVariant,
 };
 
 // Some builder methods to help with turning a builder struct into a ready
 // simulation
 fn build_deck(game_state: &GameState) -> Deck {
-    let mut d = CardBitSet::default();
-
-    for hand in game_state.hands.iter() {
-        let bitset: CardBitSet = (*hand).into();
-
-        d &= !bitset; // remove the cards in the hand from the deck
-    }
-    for card in game_state.board.iter() {
-        d.remove(*card); // remove the cards on the board from the deck
-    }
-
-    d.into() // convert the bitset into a deck
+    // The game state's deck, less the cards in the hands and on the board.
+    (!game_state.dead_cards()).into()
 }
 
 fn build_agents(num_agents: usize) -> Vec<Box<dyn Agent>> {
@@ -59,17 +55,18 @@ fn build_agents(num_agents: usize) -> Vec<Box<dyn Agent>> {
 ///     .unwrap();
 /// ```
 /// However sometimes you want to use a known but random simulation. In that
-/// case you can pass in the rng like this:
+/// case you can give it a seed like this:
 ///
 /// ```
-/// use rand::{SeedableRng, rngs::StdRng};
 /// use rs_poker::arena::{GameState, HoldemSimulationBuilder};
 ///
 /// let game_state = GameState::new_starting(vec![100.0; 5], 2.0, 1.0, 0.0, 3);
-/// let sim = HoldemSimulationBuilder::default()
+/// let mut sim = HoldemSimulationBuilder::default()
 ///     .game_state(game_state)
+///     .with_seed(42)
 ///     .build()
 ///     .unwrap();
+/// sim.run(&mut rand::rng());
 /// ```
 pub struct HoldemSimulationBuilder {
     agents: Option<Vec<Box<dyn Agent>>>,
@@ -77,6 +74,16 @@ pub struct HoldemSimulationBuilder {
     game_state: Option<GameState>,
     deck: Option<Deck>,
     panic_on_historian_error: bool,
+    seed: Option<u64>,
+    record_timings: bool,
+    run_it_times: usize,
+    muck_losing_hands: bool,
+    dealing: DealingProcedure,
+    stacked_deck: Vec<Card>,
+    seat_agents: Option<Vec<usize>>,
+    betting: BettingStructure,
+    variant: GameVariant,
+    draws: Option<usize>,
 }
 
 /// # Examples
@@ -128,19 +135,116 @@ impl HoldemSimulationBuilder {
         self
     }
 
+    /// Seed the simulation so that running it always plays out the same
+    /// way. The deal, the simulation id, and anything agents or historians
+    /// draw from `rs_poker::core::rng` all come from the seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Should the simulation time its rounds and agent decisions. Default is
+    /// false. See `rs_poker::arena::timing`.
+    pub fn record_timings(mut self, record_timings: bool) -> Self {
+        self.record_timings = record_timings;
+        self
+    }
+
+    /// How many times to run out the rest of the board when betting ends
+    /// with everyone left all in before the river. Default is once. See
+    /// [`RunItResult`](super::simulation::RunItResult).
+    pub fn run_it_times(mut self, run_it_times: usize) -> Self {
+        self.run_it_times = run_it_times;
+        self
+    }
+
+    /// Should players at showdown muck hands that can't beat one already
+    /// shown. Default is false, every hand is shown.
+    pub fn muck_losing_hands(mut self, muck_losing_hands: bool) -> Self {
+        self.muck_losing_hands = muck_losing_hands;
+        self
+    }
+
+    /// How cards are dealt. Default is no burns with each player's hole cards
+    /// dealt together.
+    pub fn dealing(mut self, dealing: DealingProcedure) -> Self {
+        self.dealing = dealing;
+        self
+    }
+
+    /// Deal these cards first, in order, before drawing any at random. Every
+    /// card must still be in the deck.
+    pub fn stacked_deck(mut self, stacked_deck: Vec<Card>) -> Self {
+        self.stacked_deck = stacked_deck;
+        self
+    }
+
+    /// Deal exactly the cards in `audit`, the same way they were dealt, so
+    /// the hand can be played again card for card.
+    pub fn replay_deck(self, audit: &DeckAudit) -> Self {
+        self.dealing(audit.procedure).stacked_deck(audit.order())
+    }
+
+    /// Which agent of a longer session sits in each seat, as a permutation
+    /// of the seats. If not set agent `i` is in seat `i`.
+    pub fn seat_agents(mut self, seat_agents: Vec<usize>) -> Self {
+        self.seat_agents = Some(seat_agents);
+        self
+    }
+
+    /// How much players may bet. Default is no limit.
+    pub fn betting(mut self, betting: BettingStructure) -> Self {
+        self.betting = betting;
+        self
+    }
+
+    /// Which game to deal. Default is hold'em. For stud the game state's
+    /// small blind is the bring-in and its big blind the small bet.
+    pub fn variant(mut self, variant: GameVariant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// How many times players draw in a draw game, from one to three. If
+    /// not set it's the variant's usual number, so 2-7 triple draw with one
+    /// draw is 2-7 single draw.
+    pub fn draws(mut self, draws: usize) -> Self {
+        self.draws = Some(draws);
+        self
+    }
+
     /// Given the fields already specified build any that are not specified and
     /// create a new HoldemSimulation.
     ///
     /// @returns HoldemSimulationError if no game_state was given.
     pub fn build(self) -> Result<HoldemSimulation, HoldemSimulationError> {
-        let game_state = self
+        let mut game_state = self
             .game_state
             .ok_or(HoldemSimulationError::NeedGameState)?;
+        game_state.variant = self.variant;
+
+        let max_players = self.variant.max_players(self.dealing.burn_cards);
+        if game_state.num_players > max_players {
+            return Err(HoldemSimulationError::TooManyPlayers(max_players));
+        }
+        let draws = self.draws.unwrap_or_else(|| self.variant.draws());
+        if self.variant.is_draw() && !(1..=3).contains(&draws) {
+            return Err(HoldemSimulationError::InvalidDraws(draws));
+        }
 
         let agents = self
             .agents
             .unwrap_or_else(|| build_agents(game_state.hands.len()));
 
+        let seat_agents = self
+            .seat_agents
+            .unwrap_or_else(|| (0..agents.len()).collect());
+        let mut sorted = seat_agents.clone();
+        sorted.sort_unstable();
+        if !sorted.into_iter().eq(0..agents.len()) {
+            return Err(HoldemSimulationError::InvalidSeatAgents);
+        }
+
         let agent_historians = agents.iter().filter_map(|a| a.historian());
 
         // Add the agent historians to the simulation
@@ -152,12 +256,24 @@ impl HoldemSimulationBuilder {
             .collect();
 
         let deck = self.deck.unwrap_or_else(|| build_deck(&game_state));
+        let mut stacked = CardBitSet::new();
+        // Discards in a draw game can be shuffled back in and dealt again.
+        let can_repeat = self.variant.is_draw();
+        for card in &self.stacked_deck {
+            if !deck.contains(card) || (stacked.contains(*card) && !can_repeat) {
+                return Err(HoldemSimulationError::StackedCardNotInDeck(*card));
+            }
+            stacked.insert(*card);
+        }
 
         // Create a new simulation id.
         // This will be used to track
         // this exact run of a simulation.
-        let mut rand = rand::rng();
-        let id = rand.random::<u128>();
+        let mut rng = self.seed.map(StdRng::seed_from_u64);
+        let id = match rng.as_mut() {
+            Some(rng) => rng.random::<u128>(),
+            None => crate::core::rng().random::<u128>(),
+        };
 
         Ok(HoldemSimulation {
             agents,
@@ -166,6 +282,17 @@ impl HoldemSimulationBuilder {
             id,
             historians,
             panic_on_historian_error: self.panic_on_historian_error,
+            rng,
+            timings: self.record_timings.then(TimingStats::default),
+            run_it_times: self.run_it_times,
+            run_it: None,
+            muck_losing_hands: self.muck_losing_hands,
+            stacked_deck: self.stacked_deck.into(),
+            deck_audit: DeckAudit::new(self.dealing),
+            seat_agents,
+            betting: self.betting,
+            variant: self.variant,
+            draws,
         })
     }
 }
@@ -178,6 +305,16 @@ impl Default for HoldemSimulationBuilder {
             game_state: None,
             deck: None,
             panic_on_historian_error: true,
+            seed: None,
+            record_timings: false,
+            run_it_times: 1,
+            muck_losing_hands: false,
+            dealing: DealingProcedure::default(),
+            stacked_deck: vec![],
+            seat_agents: None,
+            betting: BettingStructure::default(),
+            variant: GameVariant::default(),
+            draws: None,
         }
     }
 }
@@ -186,7 +323,17 @@ impl Default for HoldemSimulationBuilder {
 mod tests {
     use rand::{SeedableRng, rngs::StdRng};
 
-    use crate::{arena::game_state::Round, core::Card};
+    use crate::{
+        arena::{
+            action::{Action, AgentAction, DrawPayload, ForcedBetType, ShowHandPayload},
+            agent::{AllInAgent, CallingAgent, RandomAgent, VecReplayAgent},
+            dealing::DealtTo,
+            game_state::Round,
+            historian::VecHistorian,
+            test_util::assert_valid_game_state,
+        },
+        core::{Card, Hand, Rankable, Value},
+    };
 
     use super::*;
 
@@ -222,6 +369,680 @@ mod tests {
         assert_eq!(11.0, sim.game_state.player_bet[2]);
     }
 
+    fn run_seeded(seed: u64) -> HoldemSimulation {
+        let agents: Vec<Box<dyn Agent>> = (0..4)
+            .map(|_| Box::<RandomAgent>::default() as Box<dyn Agent>)
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 4], 2.0, 1.0, 0.0, 0))
+            .agents(agents)
+            .with_seed(seed)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        sim
+    }
+
+    #[test_log::test]
+    fn test_with_seed_is_reproducible() {
+        for seed in 0..20 {
+            let first = run_seeded(seed);
+            let second = run_seeded(seed);
+            assert_eq!(first.id, second.id);
+            assert_eq!(first.game_state, second.game_state);
+        }
+        assert_ne!(run_seeded(1).id, run_seeded(2).id);
+    }
+
+    #[test_log::test]
+    fn test_run_it_twice() {
+        for seed in 0..10 {
+            let agents: Vec<Box<dyn Agent>> = (0..3)
+                .map(|_| Box::<AllInAgent>::default() as Box<dyn Agent>)
+                .collect();
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(GameState::new_starting(
+                    vec![100.0, 50.0, 100.0],
+                    2.0,
+                    1.0,
+                    0.0,
+                    0,
+                ))
+                .agents(agents)
+                .with_seed(seed)
+                .run_it_times(2)
+                .build()
+                .unwrap();
+            sim.run(&mut rand::rng());
+            assert_valid_game_state(&sim.game_state);
+
+            let run_it = sim.run_it.as_ref().unwrap();
+            assert!(run_it.board.is_empty());
+            assert_eq!(vec![0, 1, 2], run_it.players);
+            assert_eq!(2, run_it.boards.len());
+            assert_eq!(sim.game_state.board.to_vec(), run_it.boards[0]);
+            let first: CardBitSet = run_it.boards[0].iter().copied().collect();
+            let second: CardBitSet = run_it.boards[1].iter().copied().collect();
+            assert!(first.is_disjoint(second));
+
+            // Half of every pot goes to each run.
+            for idx in 0..3 {
+                let won: f32 = run_it.winnings.iter().map(|run| run[idx]).sum();
+                assert!((won - sim.game_state.player_winnings[idx]).abs() < 1e-3);
+            }
+            for run in &run_it.winnings {
+                assert!((run.iter().sum::<f32>() - 125.0).abs() < 1e-3);
+            }
+            let equity: f64 = run_it.equity.equity.iter().sum();
+            assert!((equity - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test_log::test]
+    fn test_run_once_by_default() {
+        let agents: Vec<Box<dyn Agent>> = (0..2)
+            .map(|_| Box::<AllInAgent>::default() as Box<dyn Agent>)
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0))
+            .agents(agents)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        assert_eq!(None, sim.run_it);
+    }
+
+    #[test_log::test]
+    fn test_replay_deck() {
+        let all_in = || -> Vec<Box<dyn Agent>> {
+            (0..3)
+                .map(|_| Box::<AllInAgent>::default() as Box<dyn Agent>)
+                .collect()
+        };
+        let game_state = GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.0, 0);
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state.clone())
+            .agents(all_in())
+            .with_seed(7)
+            .run_it_times(2)
+            .dealing(DealingProcedure::casino())
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let audit = &sim.deck_audit;
+        audit.verify(&sim.game_state).unwrap();
+        // Round robin goes around the table twice in the same order.
+        let seats: Vec<DealtTo> = audit.cards[..6].iter().map(|dealt| dealt.to).collect();
+        assert_eq!(seats[..3], seats[3..]);
+        // Both runs burn before every street.
+        assert_eq!(6, audit.burns().count());
+        assert_eq!(
+            5,
+            audit
+                .cards
+                .iter()
+                .filter(|dealt| dealt.to == DealtTo::RunIt(1))
+                .count()
+        );
+        assert_eq!(22, audit.cards.len());
+
+        let mut replay = HoldemSimulationBuilder::default()
+            .game_state(game_state)
+            .agents(all_in())
+            .with_seed(8)
+            .run_it_times(2)
+            .replay_deck(audit)
+            .build()
+            .unwrap();
+        replay.run(&mut rand::rng());
+        assert_eq!(sim.game_state.hands, replay.game_state.hands);
+        // Preflop equity is sampled, but the runs are the same.
+        let (run_it, replayed) = (sim.run_it.unwrap(), replay.run_it.unwrap());
+        assert_eq!(run_it.boards, replayed.boards);
+        assert_eq!(run_it.winnings, replayed.winnings);
+        assert_eq!(sim.deck_audit, replay.deck_audit);
+        assert!(replay.stacked_deck.is_empty());
+    }
+
+    #[test]
+    fn test_stacked_deck_must_be_in_deck() {
+        let card = Card::try_from("As").unwrap();
+        let result = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0))
+            .stacked_deck(vec![card, card])
+            .build();
+        assert_eq!(
+            HoldemSimulationError::StackedCardNotInDeck(card),
+            result.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_cut_down_deck() {
+        // Nine cards are just enough for two hands and a board.
+        let deck: CardBitSet = Hand::new_from_str("AsKsQsJsTs9s8s7s6s").unwrap().into();
+        let mut game_state = GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0);
+        game_state.deck = deck;
+        let agents: Vec<Box<dyn Agent>> = (0..2)
+            .map(|_| Box::<CallingAgent>::default() as Box<dyn Agent>)
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(game_state)
+            .agents(agents)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        assert_eq!(5, sim.game_state.board.len());
+        let dealt = sim
+            .game_state
+            .hands
+            .iter()
+            .fold(CardBitSet::new(), |dealt, hand| {
+                dealt | CardBitSet::from(*hand)
+            });
+        assert_eq!(deck, dealt);
+        assert_eq!(CardBitSet::default(), sim.game_state.dead_cards());
+    }
+
+    fn stud(variant: GameVariant, stacked_deck: Vec<Card>) -> (HoldemSimulation, Vec<Action>) {
+        let agents: Vec<Box<dyn Agent>> = (0..3)
+            .map(|_| Box::<CallingAgent>::default() as Box<dyn Agent>)
+            .collect();
+        let historian = VecHistorian::default();
+        let records = historian.get_storage();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 3], 2.0, 1.0, 0.5, 0))
+            .agents(agents)
+            .historians(vec![Box::new(historian)])
+            .variant(variant)
+            .betting(BettingStructure::fixed_limit(2.0))
+            .stacked_deck(stacked_deck)
+            .with_seed(3)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        let actions = records
+            .borrow()
+            .iter()
+            .map(|record| record.action.clone())
+            .collect();
+        (sim, actions)
+    }
+
+    #[test_log::test]
+    fn test_stud_deals_seven_cards() {
+        let (sim, actions) = stud(GameVariant::Stud, vec![]);
+        let game_state = &sim.game_state;
+        assert_eq!(Round::Complete, game_state.round);
+        sim.deck_audit.verify(game_state).unwrap();
+        assert!(game_state.board.is_empty());
+        for idx in 0..3 {
+            assert_eq!(7, game_state.hands[idx].count());
+            assert_eq!(4, game_state.up_cards[idx].count());
+        }
+        let total: f32 = game_state.stacks.iter().sum();
+        assert!((total - 300.0).abs() < 1e-3);
+        assert!(actions.contains(&Action::RoundAdvance(Round::SeventhStreet)));
+        assert!(!actions.contains(&Action::RoundAdvance(Round::Flop)));
+    }
+
+    #[test_log::test]
+    fn test_stud_bring_in_by_door_card() {
+        // Each player gets two down and one up, starting left of the dealer,
+        // so the deuce showing goes to seat 2.
+        let stacked = ["As", "Ks", "Qs", "Ah", "Kh", "2c", "Ad", "Kd", "9c"]
+            .map(|card| Card::try_from(card).unwrap())
+            .to_vec();
+        let (sim, actions) = stud(GameVariant::Stud, stacked.clone());
+
+        let deuce = Card::try_from("2c").unwrap();
+        assert!(sim.game_state.player_up_cards(2).contains(&deuce));
+        let bring_in = |actions: &[Action]| {
+            actions
+                .iter()
+                .find_map(|action| match action {
+                    Action::ForcedBet(payload)
+                        if payload.forced_bet_type == ForcedBetType::BringIn =>
+                    {
+                        Some(payload.clone())
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(2, bring_in(&actions).idx);
+        assert_eq!(1.0, bring_in(&actions).bet);
+
+        // In razz the queen, the highest card showing, brings it in.
+        let (_, actions) = stud(GameVariant::Razz, stacked);
+        assert_eq!(1, bring_in(&actions).idx);
+    }
+
+    /// Check down a heads up hand of a stud `variant`, with the seven cards
+    /// of the first hand dealt `first` and of the second `second`, each in
+    /// the order they're dealt. There are no burns by default.
+    fn stud_showdown(variant: GameVariant, first: &str, second: &str) -> HoldemSimulation {
+        let cards = |cards: &str| -> Vec<Card> {
+            cards
+                .as_bytes()
+                .chunks(2)
+                .map(|card| Card::try_from(std::str::from_utf8(card).unwrap()).unwrap())
+                .collect()
+        };
+        let (first, second) = (cards(first), cards(second));
+        let mut stacked = [&first[..3], &second[..3]].concat();
+        for street in 3..7 {
+            stacked.extend([first[street], second[street]]);
+        }
+        let agents: Vec<Box<dyn Agent>> = (0..2)
+            .map(|_| Box::<CallingAgent>::default() as Box<dyn Agent>)
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.5, 0))
+            .agents(agents)
+            .variant(variant)
+            .betting(BettingStructure::fixed_limit(2.0))
+            .stacked_deck(stacked)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        assert_eq!(Round::Complete, sim.game_state.round);
+        sim.deck_audit.verify(&sim.game_state).unwrap();
+        sim
+    }
+
+    #[test_log::test]
+    fn test_razz_lowest_hand_wins() {
+        // The wheel, with kings paired, beats an eight low.
+        let sim = stud_showdown(GameVariant::Razz, "As2d3c4h5sKdKc", "8s7d6c4c3s2cQh");
+        let winnings = &sim.game_state.player_winnings;
+        let wheel = seat_with(&sim, "As");
+        assert!(winnings[wheel] > 0.0);
+        assert_eq!(0.0, winnings[1 - wheel]);
+    }
+
+    #[test_log::test]
+    fn test_stud_hi_lo_split() {
+        // Kings full takes the high and the wheel, a straight, the low.
+        let sim = stud_showdown(GameVariant::StudHiLo, "KsKhKd9c9d2h3h", "As2s3s4d5d8c7c");
+        let winnings = &sim.game_state.player_winnings;
+        assert!(winnings[0] > 0.0);
+        assert_eq!(winnings[0], winnings[1]);
+    }
+
+    #[test]
+    fn test_stud_player_limits() {
+        let build = |variant, num_players| {
+            HoldemSimulationBuilder::default()
+                .game_state(GameState::new_starting(
+                    vec![100.0; num_players],
+                    2.0,
+                    1.0,
+                    0.0,
+                    0,
+                ))
+                .variant(variant)
+                .build()
+        };
+        assert!(build(GameVariant::Stud, 7).is_ok());
+        assert_eq!(
+            HoldemSimulationError::TooManyPlayers(7),
+            build(GameVariant::Stud, 8).unwrap_err()
+        );
+        assert!(build(GameVariant::Razz, 7).is_ok());
+    }
+
+    /// Check down a heads up hand of `variant` with the first hand dealt
+    /// `first`, the second `second` and then `board`.
+    fn omaha(variant: GameVariant, first: &str, second: &str, board: &str) -> HoldemSimulation {
+        let stacked = [first, second, board]
+            .iter()
+            .flat_map(|cards| {
+                cards
+                    .as_bytes()
+                    .chunks(2)
+                    .map(|card| Card::try_from(std::str::from_utf8(card).unwrap()).unwrap())
+            })
+            .collect();
+        let agents: Vec<Box<dyn Agent>> = (0..2)
+            .map(|_| Box::<CallingAgent>::default() as Box<dyn Agent>)
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0))
+            .agents(agents)
+            .variant(variant)
+            .betting(BettingStructure::PotLimit)
+            .stacked_deck(stacked)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        assert_eq!(Round::Complete, sim.game_state.round);
+        sim.deck_audit.verify(&sim.game_state).unwrap();
+        sim
+    }
+
+    /// The seat dealt `card`.
+    fn seat_with(sim: &HoldemSimulation, card: &str) -> usize {
+        let card = Card::try_from(card).unwrap();
+        (0..2)
+            .find(|idx| sim.game_state.hands[*idx].contains(&card))
+            .unwrap()
+    }
+
+    #[test_log::test]
+    fn test_omaha_two_hole_cards_play() {
+        // With hold'em rules the ace of hearts makes a flush, but in Omaha
+        // it's a pair of kings against the other hand's straight.
+        let sim = omaha(GameVariant::Omaha, "AhKsKdQc", "6c4d8s8d", "Jh9h5h3h2s");
+        let flush = seat_with(&sim, "Ah");
+        for idx in 0..2 {
+            assert_eq!(9, sim.game_state.hands[idx].count());
+            assert_eq!(idx != flush, sim.game_state.player_winnings[idx] > 0.0);
+        }
+    }
+
+    #[test_log::test]
+    fn test_omaha_hi_lo_quarters() {
+        // Both make the nine high straight, and only the ace deuce makes
+        // the best low, so it gets half the pot and a quarter.
+        let sim = omaha(GameVariant::OmahaHiLo, "As2s8c9d", "8h9hAdKh", "5c6d7hKsKd");
+        let winnings = &sim.game_state.player_winnings;
+        let low = seat_with(&sim, "As");
+        assert_eq!(4.0, winnings.iter().sum::<f32>());
+        assert_eq!(3.0, winnings[low]);
+        assert_eq!(1.0, winnings[1 - low]);
+    }
+
+    #[test_log::test]
+    fn test_omaha_hi_lo_no_low() {
+        // Only two low cards on the board, so the high takes it all.
+        let sim = omaha(GameVariant::OmahaHiLo, "As2s3c4d", "QhQdJcTc", "5c6dQsKsKd");
+        let winnings = &sim.game_state.player_winnings;
+        let high = seat_with(&sim, "Qh");
+        assert_eq!(4.0, winnings[high]);
+        assert_eq!(0.0, winnings[1 - high]);
+    }
+
+    #[test_log::test]
+    fn test_omaha_hi_lo_split() {
+        // One hand has the high and the other the low.
+        let sim = omaha(GameVariant::OmahaHiLo, "As2s3c9d", "QhQdJcTc", "5c6d7hKsQs");
+        let winnings = &sim.game_state.player_winnings;
+        let high = seat_with(&sim, "Qh");
+        assert_eq!(2.0, winnings[high]);
+        assert_eq!(2.0, winnings[1 - high]);
+    }
+
+    #[test_log::test]
+    fn test_omaha_hi_lo_side_pot() {
+        // The dealer calls all in for one chip with the best high hand, so
+        // only gets half the main pot. The blinds split the side pot,
+        // the low taking half of both.
+        let stacked = ["As2s3c9d", "JhJd4d9c", "QhQdJcTc", "5c6d7hKsQs"]
+            .iter()
+            .flat_map(|cards| {
+                Hand::new_from_str(cards)
+                    .unwrap()
+                    .iter()
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let agents: Vec<Box<dyn Agent>> = (0..3)
+            .map(|_| Box::<CallingAgent>::default() as Box<dyn Agent>)
+            .collect();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(
+                vec![1.0, 100.0, 100.0],
+                2.0,
+                1.0,
+                0.0,
+                0,
+            ))
+            .agents(agents)
+            .variant(GameVariant::OmahaHiLo)
+            .stacked_deck(stacked)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let ace = Card::try_from("As").unwrap();
+        assert!(sim.game_state.hands[1].contains(&ace));
+        assert_eq!(vec![1.5, 2.5, 1.0], sim.game_state.player_winnings.to_vec());
+    }
+
+    /// Calls everything and throws away every card above `keep_up_to`.
+    struct DrawingAgent {
+        keep_up_to: Value,
+    }
+
+    impl Agent for DrawingAgent {
+        fn act(&mut self, _id: u128, game_state: &GameState) -> AgentAction {
+            AgentAction::Bet(game_state.current_round_bet())
+        }
+
+        fn discard(&mut self, _id: u128, game_state: &GameState) -> Hand {
+            let hand = game_state.hands[game_state.to_act_idx()];
+            Hand::new_with_cards(
+                hand.iter()
+                    .filter(|card| card.value > self.keep_up_to)
+                    .collect(),
+            )
+        }
+    }
+
+    fn draw_game(
+        variant: GameVariant,
+        num_players: usize,
+        keep_up_to: Value,
+        seed: u64,
+    ) -> (HoldemSimulation, Vec<Action>) {
+        let agents: Vec<Box<dyn Agent>> = (0..num_players)
+            .map(|_| Box::new(DrawingAgent { keep_up_to }) as Box<dyn Agent>)
+            .collect();
+        let historian = VecHistorian::default();
+        let records = historian.get_storage();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(
+                vec![100.0; num_players],
+                2.0,
+                1.0,
+                0.0,
+                0,
+            ))
+            .agents(agents)
+            .historians(vec![Box::new(historian)])
+            .variant(variant)
+            .betting(BettingStructure::fixed_limit(2.0))
+            .with_seed(seed)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+        let actions = records
+            .borrow()
+            .iter()
+            .map(|record| record.action.clone())
+            .collect();
+        (sim, actions)
+    }
+
+    #[test_log::test]
+    fn test_triple_draw() {
+        for seed in 0..10 {
+            let (sim, actions) =
+                draw_game(GameVariant::DeuceToSevenTripleDraw, 3, Value::Eight, seed);
+            let game_state = &sim.game_state;
+            assert_eq!(Round::Complete, game_state.round);
+            sim.deck_audit.verify(game_state).unwrap();
+            let total: f32 = game_state.stacks.iter().sum();
+            assert!((total - 300.0).abs() < 1e-3);
+
+            let draws: Vec<DrawPayload> = actions
+                .iter()
+                .filter_map(|action| match action {
+                    Action::Draw(payload) => Some(*payload),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(9, draws.len());
+            for draw in &draws {
+                assert_eq!(draw.discarded.count(), draw.drawn.count());
+                assert!(draw.discarded.iter().all(|card| card.value > Value::Eight));
+            }
+            assert!(actions.contains(&Action::RoundAdvance(Round::ThirdDraw)));
+
+            // Nobody folds, so the lowest hand takes the whole pot.
+            let variant = GameVariant::DeuceToSevenTripleDraw;
+            let best = (0..3)
+                .map(|idx| variant.rank(&game_state.hands[idx]))
+                .min()
+                .unwrap();
+            for idx in 0..3 {
+                assert_eq!(5, game_state.hands[idx].count());
+                let rank = variant.rank(&game_state.hands[idx]);
+                assert_eq!(rank == best, game_state.player_winnings[idx] > 0.0);
+            }
+        }
+    }
+
+    #[test_log::test]
+    fn test_draw_reshuffles_discards() {
+        // Six players throwing away everything but deuces need more cards
+        // than the deck has left.
+        let (sim, actions) = draw_game(GameVariant::FiveCardDraw, 6, Value::Two, 5);
+        let game_state = &sim.game_state;
+        sim.deck_audit.verify(game_state).unwrap();
+        assert!(sim.deck_audit.cards.len() > 52);
+
+        let mut held = CardBitSet::new();
+        for hand in game_state.hands.iter() {
+            assert_eq!(5, hand.count());
+            assert!(held.is_disjoint(CardBitSet::from(*hand)));
+            held |= CardBitSet::from(*hand);
+        }
+        assert!(actions.contains(&Action::RoundAdvance(Round::FirstDraw)));
+        assert!(!actions.contains(&Action::RoundAdvance(Round::SecondDraw)));
+    }
+
+    #[test]
+    fn test_invalid_draws() {
+        let result = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 2.0, 1.0, 0.0, 0))
+            .variant(GameVariant::FiveCardDraw)
+            .draws(4)
+            .build();
+        assert_eq!(HoldemSimulationError::InvalidDraws(4), result.unwrap_err());
+    }
+
+    /// Three players check every street, except that the big blind bets
+    /// the river if `river_bet` is set. Returns the hands shown in order.
+    fn showdown(
+        seed: u64,
+        river_bet: bool,
+        muck_losing_hands: bool,
+    ) -> (HoldemSimulation, Vec<ShowHandPayload>) {
+        let river = if river_bet { 20.0 } else { 0.0 };
+        let big_blind = vec![
+            AgentAction::Bet(10.0),
+            AgentAction::Bet(0.0),
+            AgentAction::Bet(0.0),
+            AgentAction::Bet(river),
+        ];
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<CallingAgent>::default(),
+            Box::<CallingAgent>::default(),
+            Box::new(VecReplayAgent::new(big_blind)),
+        ];
+        let historian = VecHistorian::default();
+        let records = historian.get_storage();
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 3], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .historians(vec![Box::new(historian)])
+            .with_seed(seed)
+            .muck_losing_hands(muck_losing_hands)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let records = records.borrow();
+        let shown: Vec<ShowHandPayload> = records
+            .iter()
+            .filter_map(|record| match record.action {
+                Action::ShowHand(payload) => Some(payload),
+                _ => None,
+            })
+            .collect();
+        // Every hand is shown or mucked before any pot is awarded.
+        let first_award = records
+            .iter()
+            .position(|record| matches!(record.action, Action::Award(_)))
+            .unwrap();
+        let last_show = records
+            .iter()
+            .rposition(|record| matches!(record.action, Action::ShowHand(_)))
+            .unwrap();
+        assert!(last_show < first_award);
+        (sim, shown)
+    }
+
+    #[test_log::test]
+    fn test_showdown_order() {
+        // With no river bet the first player left of the dealer shows first.
+        let (sim, shown) = showdown(1, false, false);
+        let river = sim.game_state.street(Round::River).unwrap();
+        assert_eq!(None, river.last_aggressor);
+        let order: Vec<usize> = shown.iter().map(|payload| payload.idx).collect();
+        assert_eq!(vec![1, 2, 0], order);
+        for payload in &shown {
+            let hand = payload.hand.unwrap();
+            assert_eq!(2, hand.count());
+            assert!(
+                hand.iter()
+                    .all(|card| !sim.game_state.board.contains(&card))
+            );
+        }
+
+        // The river bettor shows first.
+        let (sim, shown) = showdown(1, true, false);
+        let river = sim.game_state.street(Round::River).unwrap();
+        assert_eq!(Some(2), river.last_aggressor);
+        assert_eq!(1, river.num_bets);
+        assert_eq!(60.0, river.total_bet());
+        let order: Vec<usize> = shown.iter().map(|payload| payload.idx).collect();
+        assert_eq!(vec![2, 0, 1], order);
+        assert!(shown.iter().all(|payload| payload.hand.is_some()));
+    }
+
+    #[test_log::test]
+    fn test_muck_losing_hands() {
+        let mut mucked = 0;
+        for seed in 0..20 {
+            let (sim, shown) = showdown(seed, true, true);
+            assert_eq!(3, shown.len());
+            assert!(shown[0].hand.is_some());
+
+            let mut best = None;
+            for payload in &shown {
+                let rank = sim.game_state.hands[payload.idx].rank();
+                match payload.hand {
+                    Some(_) => {
+                        assert!(Some(rank) >= best);
+                        best = best.max(Some(rank));
+                    }
+                    None => {
+                        assert!(Some(rank) < best);
+                        // A mucked hand never wins anything.
+                        assert_eq!(0.0, sim.game_state.player_winnings[payload.idx]);
+                        mucked += 1;
+                    }
+                }
+            }
+        }
+        assert!(mucked > 0);
+    }
+
     // #[test_log::test]
     // fn test_flatdeck_order() {
     //     let stacks = vec![100.0; 2];
@@ -348,17 +1169,17 @@ mod tests {
         let card = Card::try_from(card_str).unwrap();
         assert!(deck.contains(card));
         deck.remove(card);
-        game_state.hands[idx].insert(card);
+        game_state.hands_mut()[idx].insert(card);
     }
 
     fn deal_community_card(card_str: &str, deck: &mut CardBitSet, game_state: &mut GameState) {
         let card = Card::try_from(card_str).unwrap();
         assert!(deck.contains(card));
         deck.remove(card);
-        for h in &mut game_state.hands {
+        for h in game_state.hands_mut() {
             h.insert(card);
         }
 
-        game_state.board.push(card);
+        game_state.board_mut().push(card);
     }
 }
@@ -1,21 +1,51 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt;
+use std::time::Instant;
 
 use rand::Rng;
+use rand::rngs::StdRng;
+use smallvec::{SmallVec, smallvec};
 use tracing::{Level, debug_span, event, instrument, trace_span};
 
 use crate::arena::action::{FailedActionPayload, PlayedActionPayload};
 use crate::arena::game_state::Round;
-use crate::core::{Card, Deck, Rank, Rankable};
+use crate::core::{Card, CardBitSet, Deck, Hand, HiLoRank, LowRank, Rank};
+use crate::holdem::AllInEquity;
 
 use super::action::{
-    Action, AgentAction, AwardPayload, DealStartingHandPayload, ForcedBetPayload, GameStartPayload,
-    PlayerSitPayload,
+    Action, AgentAction, AwardPayload, DealStartingHandPayload, DrawPayload, ForcedBetPayload,
+    GameStartPayload, PlayerSitPayload, ShowHandPayload,
 };
 
 use super::Agent;
 use super::GameState;
-use super::historian::Historian;
+use super::betting::BettingStructure;
+use super::dealing::{DealtTo, DeckAudit, HoleCardOrder};
+use super::historian::{Historian, ShowdownEquityHistorian};
+use super::timing::TimingStats;
+use super::variant::GameVariant;
+
+/// Per-player scratch space at showdown is kept on the stack for tables up
+/// to this size.
+const INLINE_PLAYERS: usize = 10;
+
+/// How the board was run out more than once after every player left in the
+/// hand was all in.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunItResult {
+    /// The board when the betting ended.
+    pub board: Vec<Card>,
+    /// The players still in the hand when the betting ended.
+    pub players: Vec<usize>,
+    /// Equity for `players`, in the same order, when the betting ended.
+    /// The pot is the total pot, side pots aren't split out.
+    pub equity: AllInEquity,
+    /// The whole board of every run. The first is the game state's board.
+    pub boards: Vec<Vec<Card>>,
+    /// What each player won from each run.
+    pub winnings: Vec<Vec<f32>>,
+}
 
 /// # Description
 ///
@@ -50,6 +80,47 @@ use super::historian::Historian;
 /// - It's expected that you have the same number of agents as you have chip
 ///   stacks in the game state. If players are not active, you can use the
 ///   `FoldingAgent` as a stand in and set the active bit to false.
+/// - A simulation built with a seed deals from its own rng, and the rng passed
+///   to `run` is ignored. Agents and historians that draw from
+///   `rs_poker::core::rng` get the seeded rng too, so the whole hand is
+///   reproducible.
+/// - With `run_it_times` above one, a hand where betting ends before the river
+///   with every player left all in has the rest of the board dealt that many
+///   times from the same deck, and each run is worth an equal share of every
+///   pot. Only the first run is dealt to the game state's board; the rest are
+///   in `run_it`. If the deck runs short there are fewer runs.
+/// - At showdown every player left records an [`Action::ShowHand`] before any
+///   pot is awarded. The last player to bet or raise on the river shows first,
+///   otherwise the first player left of the dealer does, and the rest follow
+///   clockwise. With `muck_losing_hands` a player who can't beat a hand already
+///   shown mucks instead, unless someone is all in, in which case every hand is
+///   shown.
+/// - Every card taken off the deck, burns included, is recorded in `deck_audit`
+///   in the order it was dealt. Cards in `stacked_deck` are dealt first, in
+///   order, before any are drawn at random.
+/// - Agents' actions are cut down to what `betting` allows before they're
+///   played, so an agent written for no limit can play limit.
+/// - With `variant` set to stud the hand is seven card stud: after the antes
+///   each player gets two cards down and one up on third street, then one up on
+///   each street to sixth and one down on seventh. The small blind is the
+///   bring-in, posted by the lowest card showing on third street, and the big
+///   blind is the small bet. From fourth street on the best hand showing acts
+///   first. Face up cards are in the game state's `up_cards` as well as its
+///   `hands`. Stud hi-lo splits each pot as Omaha hi-lo does. In razz the
+///   lowest hand wins, so the highest card showing brings it in and the best
+///   low showing acts first.
+/// - In Omaha each player is dealt four hole cards and the hand is played as
+///   hold'em, but at showdown only two of them play with three from the board.
+///   The board is only run out once. In Omaha hi-lo each pot is split between
+///   the best high hand and the best eight or better low, if anyone has one.
+/// - In a draw game each player is dealt five cards and the blinds are posted
+///   as in hold'em. After the first round of betting everyone left draws
+///   `draws` times, each draw followed by betting. When drawing, each player
+///   from the dealer's left throws away the cards their agent's
+///   [`Agent::discard`] picks and gets as many new ones, recorded as an
+///   [`Action::Draw`]. If the deck runs out the discards, other than the
+///   drawing player's, are shuffled back in. In a lowball game the lowest hand
+///   wins.
 pub struct HoldemSimulation {
     /// A randomly generated ID to represent the simulation.
     pub id: u128,
@@ -58,6 +129,32 @@ pub struct HoldemSimulation {
     pub deck: Deck,
     pub historians: Vec<Box<dyn Historian>>,
     pub panic_on_historian_error: bool,
+    /// The seeded rng everything in the simulation draws from, if it was
+    /// built with a seed.
+    pub rng: Option<StdRng>,
+    /// Wall clock timings of the rounds and decisions, if they're being
+    /// recorded.
+    pub timings: Option<TimingStats>,
+    /// How many times to run out the board after an all in.
+    pub run_it_times: usize,
+    /// How the board was run out, if it was run more than once.
+    pub run_it: Option<RunItResult>,
+    /// Should players muck hands at showdown that can't win.
+    pub muck_losing_hands: bool,
+    /// Cards to deal before drawing from the deck at random, next card
+    /// first.
+    pub stacked_deck: VecDeque<Card>,
+    /// How the cards are dealt, and every card dealt so far.
+    pub deck_audit: DeckAudit,
+    /// Which agent of a longer session sits in each seat, for keeping
+    /// results by agent when agents change seats between hands.
+    pub seat_agents: Vec<usize>,
+    /// How much players may bet.
+    pub betting: BettingStructure,
+    /// Which game is dealt.
+    pub variant: GameVariant,
+    /// How many times players draw in a draw game.
+    pub draws: usize,
 }
 
 impl HoldemSimulation {
@@ -86,6 +183,31 @@ impl HoldemSimulation {
         let span = trace_span!("run_round");
         let _enter = span.enter();
 
+        let round = self.game_state.round;
+        let start = self.timings.is_some().then(Instant::now);
+
+        match self.rng.take() {
+            Some(mut seeded) => {
+                crate::core::with_rng(&mut seeded, || self.play_round(&mut crate::core::rng()));
+                self.rng = Some(seeded);
+            }
+            None => self.play_round(rand),
+        }
+
+        #[cfg(feature = "metrics")]
+        if round != Round::Complete && self.game_state.round == Round::Complete {
+            super::metrics::record_hand_simulated();
+        }
+
+        if let (Some(timings), Some(start)) = (self.timings.as_mut(), start) {
+            timings.record_round(round, start.elapsed());
+            if round != Round::Complete && self.game_state.round == Round::Complete {
+                timings.record_hand();
+            }
+        }
+    }
+
+    fn play_round<R: Rng>(&mut self, rand: &mut R) {
         match self.game_state.round {
             // Dealing the user hand is dealt with as its own round
             // in order to use the per round active bit set
@@ -105,10 +227,18 @@ impl HoldemSimulation {
             Round::DealRiver => self.deal_river(rand),
             Round::River => self.river(),
 
-            Round::Showdown => self.showdown(),
+            Round::Showdown => self.showdown(rand),
 
             // There's nothing left to do to this.
             Round::Complete => (),
+
+            Round::ThirdStreet => self.third_street(rand),
+            Round::FourthStreet | Round::FifthStreet | Round::SixthStreet => {
+                self.stud_street(true, rand)
+            }
+            Round::SeventhStreet => self.stud_street(false, rand),
+
+            Round::FirstDraw | Round::SecondDraw | Round::ThirdDraw => self.draw_round(rand),
         }
     }
 
@@ -176,12 +306,17 @@ impl HoldemSimulation {
     fn deal_preflop<R: Rng>(&mut self, rand: &mut R) {
         let span = trace_span!("deal_preflop");
         let _enter = span.enter();
+        let num_cards = self.variant.hole_cards();
         // We deal the cards before advancing the round
         // This allows us to use the round active bitset
+        let mut seats: SmallVec<[usize; INLINE_PLAYERS]> = smallvec![];
         while self.game_state.current_round_num_active_players() > 0 {
             let idx = self.game_state.to_act_idx();
 
-            self.deal_player_cards(2, rand);
+            match self.deck_audit.procedure.hole_cards {
+                HoleCardOrder::PlayerAtATime => self.deal_player_cards(idx, num_cards, rand),
+                HoleCardOrder::RoundRobin => seats.push(idx),
+            }
 
             // This allows us to not deal to players that
             // are sitting out, while also going in the same
@@ -190,6 +325,12 @@ impl HoldemSimulation {
 
             self.game_state.round_data.advance_action();
         }
+        // Round robin goes around the table once for each card.
+        for _ in 0..num_cards {
+            for idx in &seats {
+                self.deal_player_cards(*idx, 1, rand);
+            }
+        }
         self.advance_round()
     }
 
@@ -233,6 +374,8 @@ impl HoldemSimulation {
         let span = trace_span!("deal_flop");
         let _enter = span.enter();
 
+        self.lock_all_in();
+        self.burn(rand);
         self.deal_comunity_cards(3, rand);
         self.advance_round();
     }
@@ -249,6 +392,8 @@ impl HoldemSimulation {
         let span = trace_span!("turn");
         let _enter = span.enter();
 
+        self.lock_all_in();
+        self.burn(rand);
         self.deal_comunity_cards(1, rand);
         self.advance_round();
     }
@@ -265,6 +410,8 @@ impl HoldemSimulation {
         let span = trace_span!("river");
         let _enter = span.enter();
 
+        self.lock_all_in();
+        self.burn(rand);
         self.deal_comunity_cards(1, rand);
         self.advance_round();
     }
@@ -276,29 +423,380 @@ impl HoldemSimulation {
         self.advance_round();
     }
 
-    fn showdown(&mut self) {
+    fn third_street<R: Rng>(&mut self, rand: &mut R) {
+        let span = trace_span!("third_street");
+        let _enter = span.enter();
+
+        let seats = self.seats_in_hand();
+        match self.deck_audit.procedure.hole_cards {
+            HoleCardOrder::PlayerAtATime => {
+                for idx in &seats {
+                    self.deal_player_cards(*idx, 2, rand);
+                    self.deal_up_card(*idx, rand);
+                }
+            }
+            HoleCardOrder::RoundRobin => {
+                for _ in 0..2 {
+                    for idx in &seats {
+                        self.deal_player_cards(*idx, 1, rand);
+                    }
+                }
+                for idx in &seats {
+                    self.deal_up_card(*idx, rand);
+                }
+            }
+        }
+
+        self.bring_in();
+        self.run_betting_round();
+        self.advance_round();
+    }
+
+    /// Deal fourth to seventh street, face up unless it's seventh, and bet.
+    fn stud_street<R: Rng>(&mut self, face_up: bool, rand: &mut R) {
+        let span = trace_span!("stud_street", face_up);
+        let _enter = span.enter();
+
+        self.burn(rand);
+        for idx in self.seats_in_hand() {
+            if face_up {
+                self.deal_up_card(idx, rand);
+            } else {
+                self.deal_player_cards(idx, 1, rand);
+            }
+        }
+
+        // The best hand showing acts first, the first from the dealer's left
+        // if there's a tie.
+        let mut first: Option<(usize, Hand)> = None;
+        for idx in self.seats_in_hand() {
+            if !self.game_state.player_active.get(idx) {
+                continue;
+            }
+            let up_cards = self.game_state.player_up_cards(idx);
+            if first
+                .as_ref()
+                .is_none_or(|(_, best)| self.variant.shows_better(up_cards, *best))
+            {
+                first = Some((idx, up_cards));
+            }
+        }
+        if let Some((idx, _)) = first {
+            self.game_state.round_data.to_act_idx = idx;
+        }
+
+        self.run_betting_round();
+        self.advance_round();
+    }
+
+    /// The lowest door card, or the highest in razz, posts the bring-in, and
+    /// the action starts to their left. The bring-in is forced, so whoever
+    /// posts it only acts again if someone completes. Without a bring-in
+    /// that card acts first.
+    fn bring_in(&mut self) {
+        let Some(idx) = self
+            .seats_in_hand()
+            .into_iter()
+            .filter(|idx| self.game_state.player_active.get(*idx))
+            .min_by_key(|idx| {
+                let door_card = self.game_state.player_up_cards(*idx).iter().next();
+                door_card.map(|card| self.variant.door_card_order(card))
+            })
+        else {
+            return;
+        };
+
+        self.game_state.round_data.to_act_idx = idx;
+        let bring_in = self.game_state.small_blind;
+        if bring_in > 0.0 {
+            self.game_state.do_bet(bring_in, true).unwrap();
+            self.game_state.round_data.needs_action.disable(idx);
+            self.record_action(Action::ForcedBet(ForcedBetPayload {
+                bet: bring_in,
+                idx,
+                forced_bet_type: super::action::ForcedBetType::BringIn,
+                player_stack: self.game_state.stacks[idx],
+            }));
+        }
+        // Completing the bring-in to the small bet is a full raise.
+        let complete = self.game_state.big_blind - self.game_state.current_round_bet();
+        if complete > 0.0 {
+            self.game_state.round_data.min_raise = complete;
+        }
+    }
+
+    /// Everyone left draws, from the dealer's left, then there's a round of
+    /// betting.
+    fn draw_round<R: Rng>(&mut self, rand: &mut R) {
+        let span = trace_span!("draw_round");
+        let _enter = span.enter();
+
+        self.burn(rand);
+        for idx in self.seats_in_hand() {
+            // The drawing player is the one to act, as when betting.
+            self.game_state.round_data.to_act_idx = idx;
+            let hand = self.game_state.hands[idx];
+            let discarded = self.agents[idx].discard(self.id, &self.game_state) & hand;
+            if self.deck.len() < discarded.count() {
+                self.reshuffle_discards();
+            }
+
+            let mut drawn = Hand::default();
+            for card in discarded.iter() {
+                self.game_state.hands_mut()[idx].remove(&card);
+                self.deck_audit.discards.insert(card);
+                if !self.deck.is_empty() {
+                    drawn.insert(self.draw(DealtTo::Player(idx), rand));
+                }
+            }
+            self.game_state.hands_mut()[idx].extend(drawn.iter());
+            event!(Level::TRACE, idx, ?discarded, ?drawn, "draw");
+            self.record_action(Action::Draw(DrawPayload {
+                idx,
+                discarded,
+                drawn,
+            }));
+        }
+
+        self.run_betting_round();
+        self.advance_round();
+    }
+
+    /// Shuffle every discard that isn't in someone's hand back into the
+    /// deck. The player about to draw still holds theirs, so they can't get
+    /// them back.
+    fn reshuffle_discards(&mut self) {
+        let held: CardBitSet = self
+            .game_state
+            .hands
+            .iter()
+            .fold(CardBitSet::new(), |held, hand| {
+                held | CardBitSet::from(*hand)
+            });
+        for card in (self.deck_audit.discards - held).iter() {
+            self.deck.insert(card);
+        }
+    }
+
+    /// The last round of betting before the showdown.
+    fn last_street(&self) -> Round {
+        match self.draws {
+            _ if !self.variant.is_draw() => self.variant.last_street(),
+            1 => Round::FirstDraw,
+            2 => Round::SecondDraw,
+            _ => Round::ThirdDraw,
+        }
+    }
+
+    /// Everyone still in the hand, all in or not, from the dealer's left.
+    fn seats_in_hand(&self) -> SmallVec<[usize; INLINE_PLAYERS]> {
+        let in_hand = self.game_state.player_active | self.game_state.player_all_in;
+        let num_players = self.game_state.num_players;
+        (1..=num_players)
+            .map(|offset| (self.game_state.dealer_idx + offset) % num_players)
+            .filter(|idx| in_hand.get(*idx))
+            .collect()
+    }
+
+    /// The first time betting ends with two or more players left and at most
+    /// one of them able to bet, note the board and everyone's equity so the
+    /// rest of the board can be run out more than once.
+    fn lock_all_in(&mut self) {
+        // The equity is worked out with hold'em rules, so Omaha is only run
+        // once.
+        if self.run_it_times <= 1 || self.run_it.is_some() || self.variant.is_omaha() {
+            return;
+        }
+        let contenders = self.game_state.player_active | self.game_state.player_all_in;
+        if contenders.count() < 2 || self.game_state.player_active.count() > 1 {
+            return;
+        }
+
+        let board = self.game_state.board.to_vec();
+        let players: Vec<usize> = contenders.ones().collect();
+        let hands: Vec<Hand> = players
+            .iter()
+            .map(|idx| hole_cards(self.game_state.hands[*idx], &board))
+            .collect();
+        // Exact from the flop on, sampled before it.
+        let equity = AllInEquity::estimate(
+            &hands,
+            &board,
+            self.game_state.total_pot,
+            ShowdownEquityHistorian::DEFAULT_MAX_RUNOUTS,
+            &mut crate::core::rng(),
+        )
+        .expect("dealt cards are never repeated");
+        self.run_it = Some(RunItResult {
+            board,
+            players,
+            equity,
+            boards: vec![],
+            winnings: vec![],
+        });
+    }
+
+    fn showdown<R: Rng>(&mut self, rand: &mut R) {
         let span = trace_span!("showdown");
         let _enter = span.enter();
 
+        self.show_hands();
+
+        let Some(mut run_it) = self.run_it.take() else {
+            let hands = self.game_state.hands.clone();
+            let board = self.game_state.board.clone();
+            let bets = SmallVec::from_slice(&self.game_state.player_bet);
+            self.award_pots(&hands, &board, bets);
+            self.end_game();
+            return;
+        };
+
+        // Every run comes from the same deck, so stop early if it runs out.
+        // With burns each street of a run is burned and dealt on its own.
+        let board = self.game_state.board.to_vec();
+        let num_cards = 5 - run_it.board.len();
+        let burn_cards = self.deck_audit.procedure.burn_cards;
+        let streets: &[usize] = match (burn_cards, num_cards) {
+            (_, 0) => &[],
+            (true, 5) => &[3, 1, 1],
+            (true, 2) => &[1, 1],
+            _ => &[num_cards],
+        };
+        let needed = num_cards + streets.len() * usize::from(burn_cards);
+        run_it.boards = vec![board.clone()];
+        while run_it.boards.len() < self.run_it_times && self.deck.len() >= needed {
+            let run = run_it.boards.len();
+            let mut run_board = run_it.board.clone();
+            for num_cards in streets {
+                self.burn(rand);
+                run_board.extend(self.deal_cards(*num_cards, DealtTo::RunIt(run), rand));
+            }
+            run_it.boards.push(run_board);
+        }
+
+        let num_runs = run_it.boards.len() as f32;
+        for run_board in &run_it.boards {
+            let hands: Vec<Hand> = self
+                .game_state
+                .hands
+                .iter()
+                .map(|hand| {
+                    let mut hand = hole_cards(*hand, &board);
+                    hand.extend(run_board.iter().copied());
+                    hand
+                })
+                .collect();
+            let bets = self
+                .game_state
+                .player_bet
+                .iter()
+                .map(|bet| bet / num_runs)
+                .collect();
+
+            let before = self.game_state.player_winnings.clone();
+            self.award_pots(&hands, run_board, bets);
+            run_it.winnings.push(
+                self.game_state
+                    .player_winnings
+                    .iter()
+                    .zip(before)
+                    .map(|(after, before)| after - before)
+                    .collect(),
+            );
+        }
+        self.run_it = Some(run_it);
+        self.end_game();
+    }
+
+    /// Everyone left in the hand shows or mucks, in showdown order.
+    fn show_hands(&mut self) {
+        let contenders = self.game_state.player_active | self.game_state.player_all_in;
+        if contenders.count() < 2 {
+            return;
+        }
+        let num_players = self.game_state.num_players;
+        let river_aggressor = self
+            .game_state
+            .street(self.last_street())
+            .and_then(|street| street.last_aggressor);
+        let first = match river_aggressor {
+            Some(idx) if contenders.get(idx) => idx,
+            _ => (1..=num_players)
+                .map(|offset| (self.game_state.dealer_idx + offset) % num_players)
+                .find(|idx| contenders.get(*idx))
+                .expect("there are players left"),
+        };
+        // Nobody can hide a hand once they're all in.
+        let must_show = !self.muck_losing_hands || !self.game_state.player_all_in.empty();
+
+        let lowball = self.variant.is_lowball();
+        let mut best_shown: Option<Rank> = None;
+        let mut best_low_shown: Option<LowRank> = None;
+        for offset in 0..num_players {
+            let idx = (first + offset) % num_players;
+            if !contenders.get(idx) {
+                continue;
+            }
+            let HiLoRank { high: rank, low } = self
+                .variant
+                .showdown_hi_lo(&self.game_state.hands[idx], &self.game_state.board);
+            let can_win = |best: Rank| if lowball { rank <= best } else { rank >= best };
+            // In a hi-lo game a hand that can't win the high can still show
+            // for the low.
+            let can_win_low = low.is_some_and(|low| best_low_shown.is_none_or(|best| low <= best));
+            let shows = must_show || best_shown.is_none_or(can_win) || can_win_low;
+            let hand = if shows {
+                if best_shown.is_none_or(can_win) {
+                    best_shown = Some(rank);
+                }
+                if can_win_low {
+                    best_low_shown = low;
+                }
+                Some(hole_cards(
+                    self.game_state.hands[idx],
+                    &self.game_state.board,
+                ))
+            } else {
+                None
+            };
+            event!(Level::TRACE, idx, ?hand, "show_hand");
+            self.record_action(Action::ShowHand(ShowHandPayload { idx, hand }));
+        }
+    }
+
+    /// Award the pots to the best of `hands`, with `board` dealt, where each
+    /// player put `bets` into them, splitting side pots and ties.
+    fn award_pots(
+        &mut self,
+        hands: &[Hand],
+        board: &[Card],
+        mut bets: SmallVec<[f32; INLINE_PLAYERS]>,
+    ) {
+        if self.variant.is_hi_lo() {
+            self.award_hi_lo_pots(hands, board, &bets);
+            return;
+        }
+
+        let span = trace_span!("award_pots");
+        let _enter = span.enter();
+
         // Rank each player that still has a chance.
         let active = self.game_state.player_active | self.game_state.player_all_in;
 
-        let mut bets = self.game_state.player_bet.clone();
-
         // Create a map where the keys are the ranks of hands and
         // the values are vectors of player index, for players that had that hand
         let ranks = active
             .ones()
-            .map(|idx| (idx, self.game_state.hands[idx].rank()))
+            .map(|idx| (idx, self.variant.showdown_rank(&hands[idx], board)))
             .fold(
                 BTreeMap::new(),
-                |mut map: BTreeMap<Rank, Vec<usize>>, (idx, rank)| {
+                |mut map: BTreeMap<Rank, SmallVec<[usize; INLINE_PLAYERS]>>, (idx, rank)| {
                     map.entry(rank)
                         .and_modify(|m| {
                             m.push(idx);
                             m.sort_by(|a, b| bets[*a].partial_cmp(&bets[*b]).unwrap());
                         })
-                        .or_insert_with(|| vec![idx]);
+                        .or_insert_with(|| smallvec![idx]);
 
                     map
                 },
@@ -313,16 +811,21 @@ impl HoldemSimulation {
             .filter(|(idx, _)| !active.get(*idx))
             .map(|(_, bet)| *bet)
             .sum::<f32>();
-        bets = bets
-            .iter()
-            .enumerate()
-            .map(|(idx, v)| if active.get(idx) { *v } else { 0.0 })
-            .collect();
+        for (idx, bet) in bets.iter_mut().enumerate() {
+            if !active.get(idx) {
+                *bet = 0.0;
+            }
+        }
 
-        // By default the map gives keys in assending order. We want them descending.
+        // By default the map gives keys in assending order. We want them descending,
+        // unless the lowest hand wins.
         // The actual player vector is sorted in ascending order according to bet size.
+        let mut ranks: Vec<_> = ranks.into_iter().collect();
+        if !self.variant.is_lowball() {
+            ranks.reverse();
+        }
 
-        for (rank, players) in ranks.into_iter().rev() {
+        for (rank, players) in ranks {
             let mut start_idx = 0;
             let end_idx = players.len();
 
@@ -374,7 +877,7 @@ impl HoldemSimulation {
                         // Since we had a showdown we cen copy the hand
                         // and the resulting rank.
                         rank: Some(rank),
-                        hand: Some(self.game_state.hands[*idx]),
+                        hand: Some(hands[*idx]),
                     }));
                 }
 
@@ -383,41 +886,148 @@ impl HoldemSimulation {
                 start_idx += 1;
             }
         }
+    }
 
-        self.end_game();
+    /// Award the pots of a hi-lo game to `hands`, with `board` dealt, where
+    /// each player put `bets` into them. Each pot, main and side, is split
+    /// in half between the best high hand and the best low that qualifies,
+    /// or goes to the high hand if no one can make a low. Ties split their
+    /// half, so a hand tied for one half gets a quarter of the pot.
+    fn award_hi_lo_pots(&mut self, hands: &[Hand], board: &[Card], bets: &[f32]) {
+        let span = trace_span!("award_hi_lo_pots");
+        let _enter = span.enter();
+
+        let active = self.game_state.player_active | self.game_state.player_all_in;
+        let ranks: SmallVec<[(usize, HiLoRank); INLINE_PLAYERS]> = active
+            .ones()
+            .map(|idx| (idx, self.variant.showdown_hi_lo(&hands[idx], board)))
+            .collect();
+
+        // As in `award_pots` bets that didn't make it to showdown go to the
+        // winners of the main pot.
+        let mut folded_pot: f64 = bets
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !active.get(*idx))
+            .map(|(_, bet)| f64::from(*bet))
+            .sum();
+
+        // Every amount a player still in bet up to closes a pot, the
+        // smallest the main pot and the rest side pots.
+        let mut levels: SmallVec<[f32; INLINE_PLAYERS]> = ranks
+            .iter()
+            .map(|(idx, _)| bets[*idx])
+            .filter(|bet| *bet > 0.0)
+            .collect();
+        levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        levels.dedup();
+
+        let mut below = 0.0;
+        for level in levels {
+            let mut pot = folded_pot;
+            folded_pot = 0.0;
+            for idx in active.ones() {
+                pot += f64::from(bets[idx].min(level) - bets[idx].min(below));
+            }
+            below = level;
+
+            let eligible: SmallVec<[(usize, HiLoRank); INLINE_PLAYERS]> = ranks
+                .iter()
+                .filter(|(idx, _)| bets[*idx] >= level)
+                .copied()
+                .collect();
+            let best_high = eligible
+                .iter()
+                .map(|(_, rank)| rank.high)
+                .max()
+                .expect("someone bet up to every level");
+            let high: SmallVec<[usize; INLINE_PLAYERS]> = eligible
+                .iter()
+                .filter(|(_, rank)| rank.high == best_high)
+                .map(|(idx, _)| *idx)
+                .collect();
+            let best_low = eligible.iter().filter_map(|(_, rank)| rank.low).min();
+            let low: SmallVec<[usize; INLINE_PLAYERS]> = eligible
+                .iter()
+                .filter(|(_, rank)| best_low.is_some() && rank.low == best_low)
+                .map(|(idx, _)| *idx)
+                .collect();
+
+            let high_half = if low.is_empty() { pot } else { pot / 2.0 };
+            self.split_pot(&high, high_half, pot, Some(best_high), hands);
+            self.split_pot(&low, pot - high_half, pot, None, hands);
+        }
     }
 
-    fn deal_player_cards<R: Rng>(&mut self, num_cards: usize, rand: &mut R) {
-        let new_hand: Vec<Card> = self.deal_cards(num_cards, rand);
+    /// Split `amount` of `pot` evenly between `winners`, recording each
+    /// award with the rank that won it, if it's a high hand.
+    fn split_pot(
+        &mut self,
+        winners: &[usize],
+        amount: f64,
+        pot: f64,
+        rank: Option<Rank>,
+        hands: &[Hand],
+    ) {
+        let split = amount / winners.len() as f64;
+        for idx in winners {
+            event!(Level::INFO, idx, split, pot, ?rank, "pot_awarded");
+            self.game_state.award(*idx, split as f32);
+            self.record_action(Action::Award(AwardPayload {
+                idx: *idx,
+                total_pot: pot as f32,
+                award_amount: split as f32,
+                rank,
+                hand: Some(hands[*idx]),
+            }));
+        }
+    }
+
+    fn deal_player_cards<R: Rng>(&mut self, idx: usize, num_cards: usize, rand: &mut R) {
+        let new_hand = self.deal_cards(num_cards, DealtTo::Player(idx), rand);
         for c in &new_hand {
             self.record_action(Action::DealStartingHand(DealStartingHandPayload {
                 card: *c,
-                idx: self.game_state.to_act_idx(),
+                idx,
             }));
         }
 
-        let idx = self.game_state.to_act_idx();
-        self.game_state.hands[idx].extend(new_hand);
+        self.game_state.hands_mut()[idx].extend(new_hand);
+    }
+
+    /// Deal a card face up, so everyone can see it in `up_cards`.
+    fn deal_up_card<R: Rng>(&mut self, idx: usize, rand: &mut R) {
+        let card = self.draw(DealtTo::Player(idx), rand);
+        self.game_state.hands_mut()[idx].insert(card);
+        self.game_state.up_cards_mut()[idx].insert(card);
+        self.record_action(Action::DealStartingHand(DealStartingHandPayload {
+            card,
+            idx,
+        }));
     }
 
     fn deal_comunity_cards<R: Rng>(&mut self, num_cards: usize, rand: &mut R) {
-        let mut community_cards = self.deal_cards(num_cards, rand);
+        let mut community_cards = self.deal_cards(num_cards, DealtTo::Board, rand);
         for c in &community_cards {
             self.record_action(Action::DealCommunity(*c));
         }
         // Add all the cards to the hands as well.
-        for h in &mut self.game_state.hands {
-            h.extend(community_cards.to_owned());
+        for h in self.game_state.hands_mut() {
+            h.extend(community_cards.iter().copied());
         }
         // Drain the community_cards vec into the game_state board.
-        self.game_state.board.append(&mut community_cards);
+        self.game_state.board_mut().append(&mut community_cards);
     }
 
-    /// Pull num_cards from the deck and return them as a vector.
-    fn deal_cards<R: Rng>(&mut self, num_cards: usize, rand: &mut R) -> Vec<Card> {
-        let mut cards: Vec<Card> = (0..num_cards)
-            .map(|_| self.deck.deal(rand).unwrap())
-            .collect();
+    /// Pull num_cards from the deck. No more than five, a draw hand, are
+    /// dealt at once, so they're kept on the stack.
+    fn deal_cards<R: Rng>(
+        &mut self,
+        num_cards: usize,
+        to: DealtTo,
+        rand: &mut R,
+    ) -> SmallVec<[Card; 5]> {
+        let mut cards: SmallVec<[Card; 5]> = (0..num_cards).map(|_| self.draw(to, rand)).collect();
 
         // Keep the cards sorted in min to max order
         // this keeps the number of permutations down since
@@ -427,6 +1037,26 @@ impl HoldemSimulation {
         cards
     }
 
+    /// Take the next card off the deck, the top of the stacked deck if
+    /// there is one, and note where it went.
+    fn draw<R: Rng>(&mut self, to: DealtTo, rand: &mut R) -> Card {
+        let card = match self.stacked_deck.pop_front() {
+            Some(card) => {
+                self.deck.remove(&card);
+                card
+            }
+            None => self.deck.deal(rand).unwrap(),
+        };
+        self.deck_audit.record(card, to);
+        card
+    }
+
+    fn burn<R: Rng>(&mut self, rand: &mut R) {
+        if self.deck_audit.procedure.burn_cards && !self.deck.is_empty() {
+            self.draw(DealtTo::Burn, rand);
+        }
+    }
+
     /// This runs betting for the round to completion. It will run until
     /// everyone has acted or until the round has been completed because no one
     /// can act anymore.
@@ -451,7 +1081,13 @@ impl HoldemSimulation {
         let idx = self.game_state.to_act_idx();
         let span = trace_span!("run_agent", idx);
         let _enter = span.enter();
+        let start = self.timings.is_some().then(Instant::now);
         let action = self.agents[idx].act(self.id, &self.game_state);
+        if let (Some(timings), Some(start)) = (self.timings.as_mut(), start) {
+            timings.record_decision(idx, start.elapsed());
+        }
+
+        let action = self.betting.constrain(&self.game_state, action);
 
         event!(parent: &span, Level::TRACE, ?action, idx);
         self.run_agent_action(action);
@@ -704,7 +1340,18 @@ impl HoldemSimulation {
     #[instrument]
     fn advance_round(&mut self) {
         let current_round = self.game_state.round;
-        self.game_state.advance_round();
+        match current_round {
+            Round::Ante => self
+                .game_state
+                .advance_to_round(self.variant.first_street()),
+            Round::Preflop if self.variant.is_draw() => {
+                self.game_state.advance_to_round(Round::FirstDraw)
+            }
+            round if round == self.last_street() => {
+                self.game_state.advance_to_round(Round::Showdown)
+            }
+            _ => self.game_state.advance_round(),
+        }
         if self.game_state.round != current_round {
             self.record_action(Action::RoundAdvance(self.game_state.round));
         }
@@ -741,11 +1388,20 @@ impl HoldemSimulation {
     }
 }
 
+/// A player's hand without any of the board's cards.
+fn hole_cards(mut hand: Hand, board: &[Card]) -> Hand {
+    for card in board {
+        hand.remove(card);
+    }
+    hand
+}
+
 impl fmt::Debug for HoldemSimulation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("HoldemSimulation")
             .field("game_state", &self.game_state)
             .field("deck", &self.deck.len())
+            .field("stacked_deck", &self.stacked_deck.len())
             .field("historians", &self.historians.len())
             .field("agents", &self.agents.len())
             .field("panic_on_historian_error", &self.panic_on_historian_error)
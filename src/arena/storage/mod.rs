@@ -0,0 +1,263 @@
+//! Places to keep saved artifacts other than a local file.
+//!
+//! `StateStorage` is a small key value interface. Anything that is
+//! `Versioned`, like a `StateStore` or a `StrategyProfile`, can be saved to
+//! and loaded from any storage with `save_versioned_to` and
+//! `load_versioned_from`, in the same format that `save_to_file` writes.
+//! That lets long trainings checkpoint to whatever infrastructure is
+//! around.
+//!
+//! - `FileStorage` keeps each key as a file under a root directory.
+//! - `MemoryStorage` keeps everything in memory, which is useful for tests.
+//! - `RedisStorage` (feature `redis`) keeps each key as a Redis string.
+//! - `S3Storage` (feature `s3`) keeps each key as an object in a bucket.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::arena::GameState;
+//! use rs_poker::arena::cfr::StateStore;
+//! use rs_poker::arena::storage::MemoryStorage;
+//!
+//! let mut store = StateStore::new();
+//! store.new_state(
+//!     GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0),
+//!     0,
+//! );
+//!
+//! let storage = MemoryStorage::new();
+//! store.save_to(&storage, "checkpoints/latest").unwrap();
+//! let loaded = StateStore::load_from(&storage, "checkpoints/latest").unwrap();
+//! assert_eq!(1, loaded.len());
+//! ```
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tempfile::NamedTempFile;
+
+use super::errors::StorageError;
+use super::versioned::{Versioned, read_versioned, write_versioned};
+
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(feature = "s3")]
+mod s3;
+
+#[cfg(feature = "redis")]
+pub use self::redis::RedisStorage;
+#[cfg(feature = "s3")]
+pub use self::s3::S3Storage;
+
+/// A key value store for saved artifacts.
+///
+/// Keys are `/` separated names like `"run-1/checkpoint-40"`.
+/// Implementations use interior mutability where they need it so a
+/// storage can be shared.
+pub trait StateStorage {
+    /// Store `data` under `key`, replacing anything already there.
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), StorageError>;
+
+    /// The data stored under `key`, or `StorageError::NotFound`.
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+
+    /// Remove `key`. Removing a key that doesn't exist isn't an error.
+    fn delete(&self, key: &str) -> Result<(), StorageError>;
+
+    /// Whether anything is stored under `key`.
+    fn exists(&self, key: &str) -> Result<bool, StorageError>;
+}
+
+/// Save `value` under `key` as versioned JSON.
+pub fn save_versioned_to<T: Versioned, S: StateStorage + ?Sized>(
+    storage: &S,
+    key: &str,
+    value: &T,
+) -> Result<(), StorageError> {
+    let mut data = vec![];
+    write_versioned(&mut data, value)?;
+    storage.put(key, &data)
+}
+
+/// Load a value saved with `save_versioned_to`, migrating it if it was
+/// written by an older version.
+pub fn load_versioned_from<T: Versioned, S: StateStorage + ?Sized>(
+    storage: &S,
+    key: &str,
+) -> Result<T, StorageError> {
+    let data = storage.get(key)?;
+    Ok(read_versioned(&data[..])?)
+}
+
+/// Keeps each key as a file under a root directory. Writes go to a
+/// temporary file that is moved into place, so a crash never leaves a half
+/// written checkpoint behind.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    root: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The file `key` is stored in. Keys can't escape the root directory.
+    pub fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let relative = Path::new(key);
+        let is_normal = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if key.is_empty() || !is_normal {
+            return Err(StorageError::InvalidKey(key.to_string()));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+impl StateStorage for FileStorage {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        let dir = path.parent().unwrap_or(&self.root);
+        fs::create_dir_all(dir)?;
+        let mut file = NamedTempFile::new_in(dir)?;
+        file.write_all(data)?;
+        file.persist(&path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        fs::read(self.path(key)?).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => StorageError::NotFound(key.to_string()),
+            _ => e.into(),
+        })
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        Ok(self.path(key)?.is_file())
+    }
+}
+
+/// Keeps everything in memory. Clones share the same data.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    data: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every stored key, in order.
+    pub fn keys(&self) -> Vec<String> {
+        self.data.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+impl StateStorage for MemoryStorage {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.data
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        self.data
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.data.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        Ok(self.data.lock().unwrap().contains_key(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::arena::GameState;
+    use crate::arena::cfr::{StateStore, StrategyProfile};
+
+    use super::*;
+
+    fn check_storage(storage: &dyn StateStorage) {
+        assert!(!storage.exists("a/b").unwrap());
+        assert!(matches!(
+            storage.get("a/b"),
+            Err(StorageError::NotFound(key)) if key == "a/b"
+        ));
+
+        storage.put("a/b", b"one").unwrap();
+        storage.put("a/b", b"two").unwrap();
+        assert!(storage.exists("a/b").unwrap());
+        assert_eq!(b"two".to_vec(), storage.get("a/b").unwrap());
+
+        storage.delete("a/b").unwrap();
+        storage.delete("a/b").unwrap();
+        assert!(!storage.exists("a/b").unwrap());
+
+        let mut store = StateStore::new();
+        let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        store.new_state(game_state.clone(), 0);
+        store.new_state(game_state, 1);
+        store.save_to(storage, "store").unwrap();
+        let loaded = StateStore::load_from(storage, "store").unwrap();
+        assert_eq!(2, loaded.len());
+
+        // The key holds a state store, not a strategy profile.
+        assert!(matches!(
+            load_versioned_from::<StrategyProfile, _>(storage, "store"),
+            Err(StorageError::Versioned(_))
+        ));
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        let storage = MemoryStorage::new();
+        check_storage(&storage);
+        assert_eq!(vec!["store".to_string()], storage.keys());
+    }
+
+    #[test]
+    fn test_file_storage() {
+        let dir = tempdir().unwrap();
+        let storage = FileStorage::new(dir.path());
+        check_storage(&storage);
+
+        // Files are interchangeable with `save_to_file`.
+        let loaded = StateStore::load_from_file(&dir.path().join("store")).unwrap();
+        assert_eq!(2, loaded.len());
+
+        for key in ["", "../escape", "/abs", "a/../../b"] {
+            assert!(matches!(
+                storage.put(key, b""),
+                Err(StorageError::InvalidKey(_))
+            ));
+        }
+    }
+}
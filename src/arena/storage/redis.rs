@@ -0,0 +1,70 @@
+use redis::{Client, Commands};
+
+use crate::arena::errors::StorageError;
+
+use super::StateStorage;
+
+/// Keeps each key as a Redis string, optionally under a prefix so several
+/// trainings can share one server.
+///
+/// A connection is opened for each call, so the storage can be shared and
+/// survives the server restarting between checkpoints.
+///
+/// # Example
+///
+/// ```no_run
+/// use rs_poker::arena::cfr::StateStore;
+/// use rs_poker::arena::storage::RedisStorage;
+///
+/// let storage = RedisStorage::open("redis://127.0.0.1/", "training/").unwrap();
+/// let store = StateStore::new();
+/// store.save_to(&storage, "checkpoint").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RedisStorage {
+    client: Client,
+    prefix: String,
+}
+
+impl RedisStorage {
+    pub fn new(client: Client, prefix: impl Into<String>) -> Self {
+        Self {
+            client,
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Connect to the server at `url`, like `redis://127.0.0.1/`.
+    pub fn open(url: &str, prefix: impl Into<String>) -> Result<Self, StorageError> {
+        Ok(Self::new(Client::open(url)?, prefix))
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+}
+
+impl StateStorage for RedisStorage {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        let mut conn = self.client.get_connection()?;
+        let () = conn.set(self.key(key), data)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let mut conn = self.client.get_connection()?;
+        let data: Option<Vec<u8>> = conn.get(self.key(key))?;
+        data.ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let mut conn = self.client.get_connection()?;
+        let _: usize = conn.del(self.key(key))?;
+        Ok(())
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        let mut conn = self.client.get_connection()?;
+        Ok(conn.exists(self.key(key))?)
+    }
+}
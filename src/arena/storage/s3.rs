@@ -0,0 +1,86 @@
+use s3::Bucket;
+
+use crate::arena::errors::StorageError;
+
+use super::StateStorage;
+
+/// Keeps each key as an object in an S3 (or S3 compatible) bucket,
+/// optionally under a prefix.
+///
+/// # Example
+///
+/// ```no_run
+/// use rs_poker::arena::cfr::StateStore;
+/// use rs_poker::arena::storage::S3Storage;
+/// use s3::creds::Credentials;
+/// use s3::{Bucket, Region};
+///
+/// let bucket = Bucket::new(
+///     "poker-training",
+///     Region::UsEast1,
+///     Credentials::from_env().unwrap(),
+/// )
+/// .unwrap();
+/// let storage = S3Storage::new(bucket, "run-1/");
+/// let store = StateStore::new();
+/// store.save_to(&storage, "checkpoint").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct S3Storage {
+    bucket: Box<Bucket>,
+    prefix: String,
+}
+
+impl S3Storage {
+    pub fn new(bucket: Box<Bucket>, prefix: impl Into<String>) -> Self {
+        Self {
+            bucket,
+            prefix: prefix.into(),
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+
+    fn check(key: &str, status: u16) -> Result<(), StorageError> {
+        match status {
+            200..=299 => Ok(()),
+            404 => Err(StorageError::NotFound(key.to_string())),
+            _ => Err(StorageError::S3Status {
+                key: key.to_string(),
+                status,
+            }),
+        }
+    }
+}
+
+impl StateStorage for S3Storage {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        let response = self.bucket.put_object(self.key(key), data)?;
+        Self::check(key, response.status_code())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let response = self.bucket.get_object(self.key(key))?;
+        Self::check(key, response.status_code())?;
+        Ok(response.to_vec())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let response = self.bucket.delete_object(self.key(key))?;
+        match Self::check(key, response.status_code()) {
+            Err(StorageError::NotFound(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        let (_, status) = self.bucket.head_object(self.key(key))?;
+        match Self::check(key, status) {
+            Ok(()) => Ok(true),
+            Err(StorageError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! `proptest` strategies for actions and game states.
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use rs_poker::arena::strategy;
+//!
+//! proptest!(|(game_state in strategy::game_state())| {
+//!     // Chips are never made or lost.
+//!     let starting: f32 = game_state.starting_stacks.iter().sum();
+//!     let stacks: f32 = game_state.stacks.iter().sum();
+//!     let won: f32 = game_state.player_winnings.iter().sum();
+//!     prop_assert!((stacks + game_state.total_pot - won - starting).abs() < 0.01);
+//! });
+//! ```
+use proptest::prelude::*;
+
+use super::GameState;
+use super::action::AgentAction;
+use super::reachable::{MAX_REACHABLE_PLAYERS, ReachableGameState};
+
+/// Any action, with bets from nothing up to `max_bet`.
+pub fn agent_action(max_bet: f32) -> impl Strategy<Value = AgentAction> {
+    prop_oneof![
+        Just(AgentAction::Fold),
+        Just(AgentAction::AllIn),
+        (0.0..=max_bet).prop_map(AgentAction::Bet),
+    ]
+}
+
+pub fn reachable_game_state() -> impl Strategy<Value = ReachableGameState> {
+    (2..=MAX_REACHABLE_PLAYERS, 1_u16..=10)
+        .prop_flat_map(|(num_players, small_blind)| {
+            let small_blind = small_blind as f32;
+            // Bets are whole numbers of small blinds so that they land on
+            // the amounts real hands are played with.
+            let action = prop_oneof![
+                Just(AgentAction::Fold),
+                Just(AgentAction::AllIn),
+                (0_u16..=400).prop_map(move |n| AgentAction::Bet(n as f32 * small_blind)),
+            ];
+            (
+                proptest::collection::vec(1_u16..=2000, num_players),
+                Just(small_blind),
+                0..=small_blind as u16,
+                0..num_players,
+                proptest::collection::vec(proptest::collection::vec(action, 0..=24), num_players),
+                any::<u64>(),
+                0_usize..=12,
+            )
+        })
+        .prop_map(
+            |(stacks, small_blind, ante, dealer_idx, actions, seed, rounds)| ReachableGameState {
+                stacks: stacks.into_iter().map(f32::from).collect(),
+                big_blind: small_blind * 2.0,
+                small_blind,
+                ante: ante as f32,
+                dealer_idx,
+                actions,
+                seed,
+                rounds,
+            },
+        )
+}
+
+/// A game state reached by playing part or all of a hand.
+pub fn game_state() -> impl Strategy<Value = GameState> {
+    reachable_game_state().prop_map(|recipe| recipe.game_state())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::test_util::assert_valid_partial_game_state;
+
+    proptest! {
+        #[test]
+        fn test_game_states_are_valid(game_state in game_state()) {
+            assert_valid_partial_game_state(&game_state);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::assert_matches::assert_matches;
+use std::assert_matches;
 
 use approx::assert_relative_eq;
 
@@ -35,6 +35,29 @@ pub fn assert_valid_round_data(round_data: &RoundData) {
     }
 }
 
+/// Checks that hold at any point in a hand, not just at the end.
+pub fn assert_valid_partial_game_state(game_state: &GameState) {
+    assert!(game_state.dealer_idx < game_state.num_players);
+    assert!(game_state.board.len() <= 5);
+
+    // Every chip a player started with is either still in their stack or
+    // in the pot, and winnings come on top.
+    for idx in 0..game_state.num_players {
+        let accounted =
+            game_state.stacks[idx] + game_state.player_bet[idx] - game_state.player_winnings[idx];
+        assert_relative_eq!(
+            accounted,
+            game_state.starting_stacks[idx],
+            epsilon = game_state.starting_stacks[idx] / 100_000.0
+        );
+        assert!(game_state.stacks[idx] >= 0.0);
+    }
+
+    if game_state.round == Round::Complete {
+        assert_valid_game_state(game_state);
+    }
+}
+
 pub fn assert_valid_game_state(game_state: &GameState) {
     assert_eq!(Round::Complete, game_state.round);
 
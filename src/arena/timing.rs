@@ -0,0 +1,287 @@
+//! Wall clock timings of simulations: hands per second, the time spent in
+//! each round, and how long each agent takes to decide.
+//!
+//! Timing costs a couple of clock reads per round and per decision, so it's
+//! off unless asked for with `HoldemSimulationBuilder::record_timings` or
+//! `HoldemCompetition::record_timings`.
+//!
+//! ```
+//! use rs_poker::arena::agent::{CallingAgentGenerator, FoldingAgentGenerator};
+//! use rs_poker::arena::competition::{HoldemCompetition, StandardSimulationIterator};
+//! use rs_poker::arena::{AgentGenerator, CloneGameStateGenerator, GameState};
+//!
+//! let agent_gens: Vec<Box<dyn AgentGenerator>> = vec![
+//!     Box::<FoldingAgentGenerator>::default(),
+//!     Box::<CallingAgentGenerator>::default(),
+//! ];
+//! let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+//! let mut competition = HoldemCompetition::new(StandardSimulationIterator::new(
+//!     agent_gens,
+//!     vec![],
+//!     CloneGameStateGenerator::new(game_state),
+//! ));
+//! competition.record_timings(true);
+//! competition.run(100).unwrap();
+//!
+//! let timings = &competition.timings;
+//! assert_eq!(100, timings.hands());
+//! assert!(timings.hands_per_second() > 0.0);
+//! // The dealer posts the small blind, acts first and folds every hand.
+//! assert_eq!(100, timings.decisions(0).count);
+//! assert_eq!(0, timings.decisions(1).count);
+//! // Print a breakdown, or `timings.log()` to send it to tracing.
+//! println!("{timings}");
+//! ```
+use std::fmt;
+use std::time::Duration;
+
+use tracing::{Level, event};
+
+use super::game_state::Round;
+
+/// Every round in the order they're played.
+const ROUNDS: [Round; 20] = [
+    Round::Starting,
+    Round::Ante,
+    Round::DealPreflop,
+    Round::Preflop,
+    Round::DealFlop,
+    Round::Flop,
+    Round::DealTurn,
+    Round::Turn,
+    Round::DealRiver,
+    Round::River,
+    Round::Showdown,
+    Round::Complete,
+    Round::ThirdStreet,
+    Round::FourthStreet,
+    Round::FifthStreet,
+    Round::SixthStreet,
+    Round::SeventhStreet,
+    Round::FirstDraw,
+    Round::SecondDraw,
+    Round::ThirdDraw,
+];
+
+/// How long one agent has taken to decide.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecisionTimings {
+    /// The number of decisions.
+    pub count: usize,
+    pub total: Duration,
+    /// The slowest single decision.
+    pub max: Duration,
+}
+
+impl DecisionTimings {
+    pub fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    pub fn merge(&mut self, other: &DecisionTimings) {
+        self.count += other.count;
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+    }
+
+    /// The average time per decision, zero if there weren't any.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.count as f64)
+        }
+    }
+}
+
+/// Timings gathered over one or many simulations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimingStats {
+    hands: usize,
+    rounds: [Duration; ROUNDS.len()],
+    decisions: Vec<DecisionTimings>,
+}
+
+impl TimingStats {
+    /// Add the time spent playing `round`, including the decisions in it.
+    pub fn record_round(&mut self, round: Round, elapsed: Duration) {
+        self.rounds[round as usize] += elapsed;
+    }
+
+    /// Add one decision by the agent in seat `idx`.
+    pub fn record_decision(&mut self, idx: usize, elapsed: Duration) {
+        if self.decisions.len() <= idx {
+            self.decisions.resize(idx + 1, DecisionTimings::default());
+        }
+        self.decisions[idx].record(elapsed);
+    }
+
+    /// Count a finished hand.
+    pub fn record_hand(&mut self) {
+        self.hands += 1;
+    }
+
+    /// Add the timings of other simulations, with the same agents in the
+    /// same seats.
+    pub fn merge(&mut self, other: &TimingStats) {
+        self.hands += other.hands;
+        for (total, elapsed) in self.rounds.iter_mut().zip(other.rounds.iter()) {
+            *total += *elapsed;
+        }
+        for (idx, decisions) in other.decisions.iter().enumerate() {
+            if self.decisions.len() <= idx {
+                self.decisions.resize(idx + 1, DecisionTimings::default());
+            }
+            self.decisions[idx].merge(decisions);
+        }
+    }
+
+    /// The number of hands played to completion.
+    pub fn hands(&self) -> usize {
+        self.hands
+    }
+
+    /// The time spent in the simulations, summed over every round.
+    pub fn total(&self) -> Duration {
+        self.rounds.iter().sum()
+    }
+
+    /// Hands finished per second of simulation time. Time spent outside the
+    /// simulations, like building them, isn't counted.
+    pub fn hands_per_second(&self) -> f64 {
+        let seconds = self.total().as_secs_f64();
+        if seconds == 0.0 {
+            0.0
+        } else {
+            self.hands as f64 / seconds
+        }
+    }
+
+    /// The time spent in `round`.
+    pub fn round(&self, round: Round) -> Duration {
+        self.rounds[round as usize]
+    }
+
+    /// The decisions made by the agent in seat `idx`.
+    pub fn decisions(&self, idx: usize) -> DecisionTimings {
+        self.decisions.get(idx).copied().unwrap_or_default()
+    }
+
+    /// The decisions of every seat that has made one, in seat order.
+    pub fn all_decisions(&self) -> &[DecisionTimings] {
+        &self.decisions
+    }
+
+    /// Send the timings to `tracing` at info level, one event for the
+    /// totals, one per round and one per seat.
+    pub fn log(&self) {
+        event!(
+            Level::INFO,
+            hands = self.hands,
+            total = ?self.total(),
+            hands_per_second = self.hands_per_second(),
+            "simulation_timings"
+        );
+        for round in ROUNDS {
+            let elapsed = self.round(round);
+            if !elapsed.is_zero() {
+                event!(Level::INFO, %round, ?elapsed, "round_timings");
+            }
+        }
+        for (idx, decisions) in self.decisions.iter().enumerate() {
+            event!(
+                Level::INFO,
+                idx,
+                count = decisions.count,
+                mean = ?decisions.mean(),
+                max = ?decisions.max,
+                "decision_timings"
+            );
+        }
+    }
+}
+
+impl fmt::Display for TimingStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} hands in {:?} ({:.0} hands/s)",
+            self.hands,
+            self.total(),
+            self.hands_per_second()
+        )?;
+        for round in ROUNDS {
+            let elapsed = self.round(round);
+            if !elapsed.is_zero() {
+                writeln!(f, "  {round}: {elapsed:?}")?;
+            }
+        }
+        for (idx, decisions) in self.decisions.iter().enumerate() {
+            writeln!(
+                f,
+                "  seat {idx}: {} decisions, mean {:?}, max {:?}",
+                decisions.count,
+                decisions.mean(),
+                decisions.max
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::{Agent, GameState, HoldemSimulationBuilder, agent::CallingAgent};
+
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut first = TimingStats::default();
+        first.record_hand();
+        first.record_round(Round::Flop, Duration::from_millis(3));
+        first.record_decision(0, Duration::from_millis(1));
+
+        let mut second = TimingStats::default();
+        second.record_hand();
+        second.record_round(Round::Flop, Duration::from_millis(1));
+        second.record_decision(1, Duration::from_millis(4));
+        second.record_decision(1, Duration::from_millis(2));
+
+        first.merge(&second);
+        assert_eq!(2, first.hands());
+        assert_eq!(Duration::from_millis(4), first.round(Round::Flop));
+        assert_eq!(Duration::from_millis(4), first.total());
+        assert_eq!(500.0, first.hands_per_second());
+        assert_eq!(1, first.decisions(0).count);
+        assert_eq!(2, first.decisions(1).count);
+        assert_eq!(Duration::from_millis(3), first.decisions(1).mean());
+        assert_eq!(Duration::from_millis(4), first.decisions(1).max);
+        assert_eq!(0, first.decisions(2).count);
+    }
+
+    #[test]
+    fn test_simulation_timings() {
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<CallingAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0))
+            .agents(agents)
+            .record_timings(true)
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        let timings = sim.timings.unwrap();
+        assert_eq!(1, timings.hands());
+        // Calling down heads up is one decision each per street.
+        assert_eq!(4, timings.decisions(0).count);
+        assert_eq!(4, timings.decisions(1).count);
+        assert!(timings.total() >= timings.decisions(0).total + timings.decisions(1).total);
+        assert!(timings.to_string().starts_with("1 hands"));
+    }
+}
@@ -0,0 +1,455 @@
+//! The games a simulation can deal, and the rules that differ between them.
+use std::fmt::{self, Display};
+
+use crate::core::{
+    Card, Hand, HiLoRank, Rank, Rankable, Suit, Value, rank_eight_or_better, rank_omaha,
+    rank_omaha_low,
+};
+
+use super::game_state::Round;
+
+/// A poker game. Every game here can be simulated, on its own or in a mixed
+/// game schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameVariant {
+    #[default]
+    Holdem,
+    /// Four hole cards, of which exactly two play with exactly three from
+    /// the board. Usually pot limit.
+    Omaha,
+    /// Omaha with the pot split between the best high hand and the best
+    /// eight or better low.
+    OmahaHiLo,
+    /// Seven card stud where the lowest hand wins, aces low with straights
+    /// and flushes not counting against the hand. Pairs do.
+    Razz,
+    Stud,
+    /// Seven card stud with the pot split between the best high hand and
+    /// the best eight or better low.
+    StudHiLo,
+    /// Five cards and one draw, best high hand wins.
+    FiveCardDraw,
+    /// Five cards and three draws, lowest hand wins with aces high and
+    /// straights and flushes counting against the hand.
+    DeuceToSevenTripleDraw,
+}
+
+impl GameVariant {
+    /// Is the pot split between the best high hand and the best low.
+    pub fn is_hi_lo(&self) -> bool {
+        matches!(self, GameVariant::OmahaHiLo | GameVariant::StudHiLo)
+    }
+
+    /// Do exactly two hole cards play with three from the board.
+    pub fn is_omaha(&self) -> bool {
+        matches!(self, GameVariant::Omaha | GameVariant::OmahaHiLo)
+    }
+
+    /// Do players draw to their hands rather than share a board.
+    pub fn is_draw(&self) -> bool {
+        matches!(
+            self,
+            GameVariant::FiveCardDraw | GameVariant::DeuceToSevenTripleDraw
+        )
+    }
+
+    /// How many times players draw in the game as it's usually spread, zero
+    /// for games without a draw.
+    pub fn draws(&self) -> usize {
+        match self {
+            GameVariant::FiveCardDraw => 1,
+            GameVariant::DeuceToSevenTripleDraw => 3,
+            _ => 0,
+        }
+    }
+
+    /// The cards each player is dealt before the first betting round.
+    pub fn hole_cards(&self) -> usize {
+        match self {
+            GameVariant::FiveCardDraw | GameVariant::DeuceToSevenTripleDraw => 5,
+            GameVariant::Omaha | GameVariant::OmahaHiLo => 4,
+            GameVariant::Stud | GameVariant::StudHiLo | GameVariant::Razz => 3,
+            GameVariant::Holdem => 2,
+        }
+    }
+
+    /// Does the lowest hand win the whole pot.
+    pub fn is_lowball(&self) -> bool {
+        matches!(
+            self,
+            GameVariant::Razz | GameVariant::DeuceToSevenTripleDraw
+        )
+    }
+
+    /// Does the best high hand made with a shared board take the whole pot,
+    /// so a hand's equity can be worked out by running out the board. True
+    /// for hold'em and Omaha, which is what the equity historians and the
+    /// hand reviewer can rank.
+    pub fn has_board_equity(&self) -> bool {
+        matches!(self, GameVariant::Holdem | GameVariant::Omaha)
+    }
+
+    /// The rank of a hand at showdown, for games where any five of its
+    /// cards can play. In a lowball game the lowest rank wins.
+    ///
+    /// ```
+    /// use rs_poker::arena::variant::GameVariant;
+    /// use rs_poker::core::Hand;
+    ///
+    /// let triple_draw = GameVariant::DeuceToSevenTripleDraw;
+    /// let seven_five = Hand::new_from_str("7s5d4c3h2s").unwrap();
+    /// // Aces are high, so the wheel is just ace high.
+    /// let wheel = Hand::new_from_str("As5d4c3h2s").unwrap();
+    /// assert!(triple_draw.rank(&seven_five) < triple_draw.rank(&wheel));
+    ///
+    /// // In razz the wheel is the nuts, from any five of the seven cards.
+    /// let razz_wheel = Hand::new_from_str("As5d4c3h2sKdKc").unwrap();
+    /// assert!(GameVariant::Razz.rank(&razz_wheel) < GameVariant::Razz.rank(&seven_five));
+    /// ```
+    pub fn rank(&self, hand: &Hand) -> Rank {
+        if *self == GameVariant::Razz {
+            return razz_rank(hand);
+        }
+        match (self, hand.rank()) {
+            (GameVariant::DeuceToSevenTripleDraw, Rank::Straight(0)) => {
+                Rank::HighCard(value_set(hand))
+            }
+            (GameVariant::DeuceToSevenTripleDraw, Rank::StraightFlush(0)) => {
+                Rank::Flush(value_set(hand))
+            }
+            (_, rank) => rank,
+        }
+    }
+
+    /// The rank of a hand at showdown with `board` dealt, where the hand has
+    /// the board cards in it the way game states keep hands. In Omaha only
+    /// two of the hole cards and three of the board play, see `rank_omaha`,
+    /// and before the flop it's the best two hole cards with whatever board
+    /// there is. Other games rank the same as `rank`. This is the high hand
+    /// of a hi-lo game.
+    ///
+    /// ```
+    /// use rs_poker::arena::variant::GameVariant;
+    /// use rs_poker::core::{Card, Hand, Rank};
+    ///
+    /// let board: Vec<Card> = Hand::new_from_str("Jh9h5h3h2s").unwrap().iter().collect();
+    /// let hand = Hand::new_from_str("AhKsKdQcJh9h5h3h2s").unwrap();
+    /// assert!(matches!(
+    ///     GameVariant::Holdem.showdown_rank(&hand, &board),
+    ///     Rank::Flush(_)
+    /// ));
+    /// assert!(matches!(
+    ///     GameVariant::Omaha.showdown_rank(&hand, &board),
+    ///     Rank::OnePair(_)
+    /// ));
+    ///
+    /// // Preflop a pair in the hand is a pair, however many are dealt.
+    /// let preflop = Hand::new_from_str("AhAsKdKc").unwrap();
+    /// assert!(matches!(
+    ///     GameVariant::Omaha.showdown_rank(&preflop, &[]),
+    ///     Rank::OnePair(_)
+    /// ));
+    /// ```
+    pub fn showdown_rank(&self, hand: &Hand, board: &[Card]) -> Rank {
+        if !self.is_omaha() {
+            return self.rank(hand);
+        }
+        let hole = hole_cards(hand, board);
+        if let Some(rank) = rank_omaha(&hole, board) {
+            return rank;
+        }
+        let mut best = None;
+        for (i, first) in hole.iter().enumerate() {
+            for second in &hole[i + 1..] {
+                let mut cards = Hand::new_with_cards(board.to_vec());
+                cards.insert(*first);
+                cards.insert(*second);
+                best = best.max(Some(cards.rank()));
+            }
+        }
+        best.unwrap_or_else(|| self.rank(hand))
+    }
+
+    /// The high hand as `showdown_rank` and, in Omaha hi-lo, the eight or
+    /// better low if there is one, see `rank_omaha_low`. In stud hi-lo the
+    /// low is from any five cards, see `rank_eight_or_better`. Other games
+    /// have no low.
+    ///
+    /// ```
+    /// use rs_poker::arena::variant::GameVariant;
+    /// use rs_poker::core::{Card, Hand};
+    ///
+    /// let board: Vec<Card> = Hand::new_from_str("Ks7d5c4h9s").unwrap().iter().collect();
+    /// let hand = Hand::new_from_str("As2sKdKcKs7d5c4h9s").unwrap();
+    /// let rank = GameVariant::OmahaHiLo.showdown_hi_lo(&hand, &board);
+    /// assert_eq!([7, 5, 4, 2, 1], rank.low.unwrap().values());
+    /// assert_eq!(None, GameVariant::Omaha.showdown_hi_lo(&hand, &board).low);
+    /// ```
+    pub fn showdown_hi_lo(&self, hand: &Hand, board: &[Card]) -> HiLoRank {
+        let low = match self {
+            GameVariant::OmahaHiLo => rank_omaha_low(&hole_cards(hand, board), board),
+            GameVariant::StudHiLo => rank_eight_or_better(&hand.iter().collect::<Vec<_>>()),
+            _ => None,
+        };
+        HiLoRank {
+            high: self.showdown_rank(hand, board),
+            low,
+        }
+    }
+
+    /// The most players the deck has cards for, burning a card before each
+    /// street if `burn_cards`.
+    pub fn max_players(&self, burn_cards: bool) -> usize {
+        match self {
+            GameVariant::Stud | GameVariant::StudHiLo | GameVariant::Razz => {
+                // Seven cards each and four burns.
+                (52 - if burn_cards { 4 } else { 0 }) / 7
+            }
+            // Two or four cards each and five on the board, with three burns.
+            GameVariant::Holdem => (52 - 5 - if burn_cards { 3 } else { 0 }) / 2,
+            GameVariant::Omaha | GameVariant::OmahaHiLo => {
+                (52 - 5 - if burn_cards { 3 } else { 0 }) / 4
+            }
+            // Discards are shuffled back in when the deck runs out, so the
+            // limit is the usual table rather than the cards.
+            GameVariant::FiveCardDraw | GameVariant::DeuceToSevenTripleDraw => 6,
+        }
+    }
+
+    /// The round after the antes.
+    pub fn first_street(&self) -> Round {
+        match self {
+            GameVariant::Stud | GameVariant::StudHiLo | GameVariant::Razz => Round::ThirdStreet,
+            _ => Round::DealPreflop,
+        }
+    }
+
+    /// Orders door cards for the stud bring-in, the smallest brings it in.
+    /// That's the lowest card, or in razz the highest with aces low. Suits
+    /// break ties, clubs lowest then diamonds, hearts and spades, so in razz
+    /// the higher suit brings it in.
+    ///
+    /// ```
+    /// use rs_poker::arena::variant::GameVariant;
+    /// use rs_poker::core::Card;
+    ///
+    /// let king = Card::try_from("Kc").unwrap();
+    /// let ace = Card::try_from("As").unwrap();
+    /// assert!(GameVariant::Stud.door_card_order(king) < GameVariant::Stud.door_card_order(ace));
+    /// assert!(GameVariant::Razz.door_card_order(king) < GameVariant::Razz.door_card_order(ace));
+    /// ```
+    pub fn door_card_order(&self, card: Card) -> (u8, u8) {
+        if *self == GameVariant::Razz {
+            (12 - ace_low(card.value) as u8, 3 - suit_rank(card.suit))
+        } else {
+            (card.value as u8, suit_rank(card.suit))
+        }
+    }
+
+    /// Do the face up cards `up_cards` show a better hand than `other`, so
+    /// they act first from fourth street on. That's the best high hand
+    /// showing, see `showing_strength`, or in razz the best low.
+    ///
+    /// ```
+    /// use rs_poker::arena::variant::GameVariant;
+    /// use rs_poker::core::Hand;
+    ///
+    /// let pair = Hand::new_from_str("4s4d").unwrap();
+    /// let six_deuce = Hand::new_from_str("6s2d").unwrap();
+    /// assert!(GameVariant::Stud.shows_better(pair, six_deuce));
+    /// assert!(GameVariant::Razz.shows_better(six_deuce, pair));
+    /// ```
+    pub fn shows_better(&self, up_cards: Hand, other: Hand) -> bool {
+        if *self == GameVariant::Razz {
+            razz_rank(&up_cards) < razz_rank(&other)
+        } else {
+            showing_strength(up_cards) > showing_strength(other)
+        }
+    }
+
+    /// The last round of betting before showdown.
+    pub fn last_street(&self) -> Round {
+        match self {
+            GameVariant::Stud | GameVariant::StudHiLo | GameVariant::Razz => Round::SeventhStreet,
+            _ => Round::River,
+        }
+    }
+}
+
+impl Display for GameVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameVariant::Holdem => write!(f, "Hold'em"),
+            GameVariant::Omaha => write!(f, "Omaha"),
+            GameVariant::OmahaHiLo => write!(f, "Omaha Hi-Lo"),
+            GameVariant::Razz => write!(f, "Razz"),
+            GameVariant::Stud => write!(f, "Stud"),
+            GameVariant::StudHiLo => write!(f, "Stud Hi-Lo"),
+            GameVariant::FiveCardDraw => write!(f, "Five Card Draw"),
+            GameVariant::DeuceToSevenTripleDraw => write!(f, "2-7 Triple Draw"),
+        }
+    }
+}
+
+/// The cards of `hand` that aren't on the board.
+fn hole_cards(hand: &Hand, board: &[Card]) -> Vec<Card> {
+    hand.iter().filter(|card| !board.contains(card)).collect()
+}
+
+/// The values in a hand as a bit set, the way ranks store them.
+fn value_set(hand: &Hand) -> u32 {
+    hand.iter()
+        .fold(0, |set, card| set | (1 << card.value as u32))
+}
+
+/// Suits ranked for breaking ties between door cards, clubs lowest then
+/// diamonds, hearts and spades.
+fn suit_rank(suit: Suit) -> u8 {
+    match suit {
+        Suit::Club => 0,
+        Suit::Diamond => 1,
+        Suit::Heart => 2,
+        Suit::Spade => 3,
+    }
+}
+
+/// A value counted with aces the lowest, from zero for the ace to 12 for
+/// the king.
+fn ace_low(value: Value) -> u32 {
+    (value as u32 + 1) % 13
+}
+
+/// The best ace to five low of `hand` as a rank, where the lowest rank is
+/// the best hand. Values are counted with `ace_low` and straights and
+/// flushes don't count, but pairs and the rest do, ranked as usual. Out of
+/// more than five cards it's the best five.
+fn razz_rank(hand: &Hand) -> Rank {
+    let cards: Vec<Card> = hand.iter().collect();
+    if cards.len() <= 5 {
+        return ace_to_five_rank(cards.iter());
+    }
+    (0u32..1 << cards.len())
+        .filter(|picked| picked.count_ones() == 5)
+        .map(|picked| {
+            ace_to_five_rank(
+                cards
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| picked & (1 << i) != 0)
+                    .map(|(_, card)| card),
+            )
+        })
+        .min()
+        .expect("more than five cards have five to pick")
+}
+
+/// Rank at most five cards with aces low, ignoring straights and flushes.
+fn ace_to_five_rank<'a>(cards: impl Iterator<Item = &'a Card>) -> Rank {
+    let mut counts = [0usize; 13];
+    for card in cards {
+        counts[ace_low(card.value) as usize] += 1;
+    }
+    // As when ranking high hands, a set of the values seen each number of
+    // times.
+    let mut count_to_value = [0u32; 5];
+    for (value, count) in counts.iter().enumerate() {
+        count_to_value[*count] |= 1 << value;
+    }
+    let [_, singles, pairs, trips, quads] = count_to_value;
+    if quads != 0 {
+        Rank::FourOfAKind((quads << 13) | singles)
+    } else if trips != 0 && pairs != 0 {
+        Rank::FullHouse((trips << 13) | pairs)
+    } else if trips != 0 {
+        Rank::ThreeOfAKind((trips << 13) | singles)
+    } else if pairs.count_ones() >= 2 {
+        Rank::TwoPair((pairs << 13) | singles)
+    } else if pairs != 0 {
+        Rank::OnePair((pairs << 13) | singles)
+    } else {
+        Rank::HighCard(singles)
+    }
+}
+
+/// Orders the face up cards of a stud hand, so the best showing hand acts
+/// first from fourth street on. With at most four cards up the only hands
+/// are pairs, two pair, trips and quads, then high cards.
+///
+/// ```
+/// use rs_poker::arena::variant::showing_strength;
+/// use rs_poker::core::Hand;
+///
+/// let pair = Hand::new_from_str("4s4d").unwrap();
+/// let ace_king = Hand::new_from_str("AsKd").unwrap();
+/// assert!(showing_strength(pair) > showing_strength(ace_king));
+/// ```
+pub fn showing_strength(up_cards: Hand) -> (Vec<u8>, Vec<Value>) {
+    let mut groups: Vec<(u8, Value)> = vec![];
+    for card in up_cards.iter() {
+        match groups.iter_mut().find(|(_, value)| *value == card.value) {
+            Some((count, _)) => *count += 1,
+            None => groups.push((1, card.value)),
+        }
+    }
+    // Bigger groups first, then higher values. The group sizes alone decide
+    // the kind of hand, so they're compared before any value.
+    groups.sort_unstable_by(|a, b| b.cmp(a));
+    groups.into_iter().unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_showing_strength() {
+        let strength = |cards| showing_strength(Hand::new_from_str(cards).unwrap());
+        assert!(strength("2s2d2c") > strength("AsAdKc"));
+        assert!(strength("AsAdKc") > strength("AsAdQc"));
+        assert!(strength("KsKdQcQh") > strength("AsAd9c8h"));
+        assert!(strength("4s4d") > strength("AsKdQc"));
+        assert_eq!(strength("AsKd"), strength("AcKh"));
+
+        let two_clubs = Card::new(Value::Two, Suit::Club);
+        let two_spades = Card::new(Value::Two, Suit::Spade);
+        let stud = GameVariant::Stud;
+        assert!(stud.door_card_order(two_clubs) < stud.door_card_order(two_spades));
+        let razz = GameVariant::Razz;
+        assert!(razz.door_card_order(two_spades) < razz.door_card_order(two_clubs));
+    }
+
+    #[test]
+    fn test_razz_rank() {
+        let rank = |cards| GameVariant::Razz.rank(&Hand::new_from_str(cards).unwrap());
+        // The wheel is the nuts, suited or not, and a six beats a seven.
+        assert_eq!(rank("As5d4c3h2s"), rank("As5s4s3s2s"));
+        assert!(rank("As5d4c3h2s") < rank("6s4d3c2hAs"));
+        assert!(rank("6s4d3c2hAs") < rank("7s4d3c2hAs"));
+        // Any high card beats a pair, and a lower pair beats a higher one.
+        assert!(rank("KsQdJcTh9s") < rank("2s2d3c4h5s"));
+        assert!(rank("2s2d3c4h5s") < rank("3s3d2c4h5s"));
+        assert!(rank("2s2dKcQhJs") < rank("3s3d2c4h5s"));
+        // The best five of seven, dropping the pairs when it can.
+        assert_eq!(rank("As5d4c3h2s"), rank("As5d4c3h2sAdKc"));
+        assert_eq!(rank("8s8d7c7h2s"), rank("8s8d7c7h2s8c7d"));
+        assert!(matches!(rank("8s8d7c7h2s8c7d"), Rank::TwoPair(_)));
+    }
+
+    #[test]
+    fn test_deuce_to_seven_rank() {
+        let rank =
+            |cards| GameVariant::DeuceToSevenTripleDraw.rank(&Hand::new_from_str(cards).unwrap());
+        // The nuts, then a rough seven and an eight.
+        assert!(rank("7s5d4c3h2s") < rank("7s6d5c4h2s"));
+        assert!(rank("7s6d5c4h2s") < rank("8s5d4c3h2s"));
+        // Pairs, straights and flushes are all bad.
+        assert!(rank("KsQdJc9h8s") < rank("2s2d4c5h7s"));
+        assert!(rank("KsQdJc9h8s") < rank("6s5d4c3h2s"));
+        assert!(rank("AsKdQcJh9s") < rank("7s5s4s3s2s"));
+        // The wheel is ace high and the suited wheel an ace high flush.
+        assert_eq!(Rank::HighCard(0b1_0000_0000_1111), rank("As5d4c3h2s"));
+        assert_eq!(Rank::Flush(0b1_0000_0000_1111), rank("As5s4s3s2s"));
+        assert!(rank("As5d4c3h2s") > rank("KsQdJc9h8s"));
+    }
+}
@@ -0,0 +1,690 @@
+//! Versioned files for anything the arena saves to disk.
+//!
+//! Saved artifacts are wrapped in a small envelope that records what kind
+//! of data it is and the format version it was written with:
+//!
+//! ```json
+//! { "kind": "state_store", "version": 1, "data": { ... } }
+//! ```
+//!
+//! When loading, data written with an older version is passed through
+//! `Versioned::migrate` one version at a time until it reaches the current
+//! version, and only then deserialized. This lets files written by older
+//! crate versions keep loading after fields are added, renamed or
+//! restructured. Files written before versioning existed (a bare JSON
+//! value, no envelope) are treated as version 0. `detect_format` reports
+//! what a file was written with and `upgrade_versioned` rewrites old files
+//! in place. A file from every released format is kept in
+//! `tests/fixtures/compat` to make sure they keep loading.
+//!
+//! Saved stores can be many gigabytes, so files are streamed rather than
+//! built in memory. The envelope is written with `kind` and `version`
+//! before `data`, which lets `read_versioned` deserialize the data straight
+//! from the reader when it's already the current version. Only files that
+//! need migrating, or that were written with another field order, are
+//! buffered as a `serde_json::Value` first.
+//!
+//! Big stores can also be written as `SaveFormat::Binary`, which is the
+//! same envelope with a magic header in front and the kind, version and
+//! data encoded with bincode. It's a fraction of the size and much faster
+//! to load. Loading looks at the header to tell the two apart, so JSON
+//! files keep loading. Binary files can't be migrated since there's no
+//! `Value` to migrate, so they only load with the version that wrote them.
+//!
+//! With the `compression` feature either format can be compressed with
+//! zstd by saving with `SaveOptions`. Compressed files are recognised by
+//! the zstd magic number and decompressed as they're read.
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Read, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+use serde::de::value::SeqAccessDeserializer;
+use serde::de::{DeserializeOwned, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value, json};
+use tempfile::NamedTempFile;
+
+use super::errors::VersionedFileError;
+
+/// A type that can be saved as a versioned file.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// A short name for the kind of data. Loading a file of another kind
+    /// fails instead of producing confusing field errors.
+    const KIND: &'static str;
+
+    /// The version written by this build of the crate. Bump this and add a
+    /// step to `migrate` whenever the serialized form changes.
+    const VERSION: u32;
+
+    /// Upgrade `data` that was written with `version` to `version + 1`.
+    ///
+    /// The default does nothing, which is correct when the serialized form
+    /// didn't change between the two versions.
+    fn migrate(version: u32, data: Value) -> Result<Value, VersionedFileError> {
+        let _ = version;
+        Ok(data)
+    }
+
+    /// Check a loaded value for problems that deserializing can't catch,
+    /// like indexes that point nowhere. This runs after every load, so a
+    /// corrupt or hand edited file fails to load instead of panicking
+    /// later.
+    fn validate(&self) -> Result<(), VersionedFileError> {
+        Ok(())
+    }
+}
+
+/// Starts every binary file. JSON can't start with a zero byte, so it's
+/// never mistaken for one.
+const BINARY_MAGIC: &[u8; 8] = b"\0rspoker";
+
+/// How a versioned file is encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SaveFormat {
+    /// Human readable, and can be migrated when loaded by later versions.
+    #[default]
+    Json,
+    /// Compact bincode, for big stores.
+    Binary,
+}
+
+/// Starts every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// How to save a versioned file.
+///
+/// ```
+/// use rs_poker::arena::versioned::{SaveFormat, SaveOptions};
+///
+/// let options = SaveOptions::from(SaveFormat::Binary);
+/// # #[cfg(feature = "compression")]
+/// let options = options.compressed(SaveOptions::DEFAULT_ZSTD_LEVEL);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+    pub format: SaveFormat,
+    /// The zstd level to compress the file with, or `None` to leave it
+    /// uncompressed.
+    #[cfg(feature = "compression")]
+    pub zstd_level: Option<i32>,
+}
+
+impl SaveOptions {
+    /// A good trade off between size and speed for CFR trees.
+    #[cfg(feature = "compression")]
+    pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+    /// These options with the file compressed with zstd at `level`.
+    #[cfg(feature = "compression")]
+    pub fn compressed(self, level: i32) -> Self {
+        Self {
+            zstd_level: Some(level),
+            ..self
+        }
+    }
+}
+
+impl From<SaveFormat> for SaveOptions {
+    fn from(format: SaveFormat) -> Self {
+        Self {
+            format,
+            #[cfg(feature = "compression")]
+            zstd_level: None,
+        }
+    }
+}
+
+/// Wrap `value` in a versioned envelope.
+pub fn to_versioned_value<T: Versioned>(value: &T) -> Result<Value, VersionedFileError> {
+    Ok(json!({
+        "kind": T::KIND,
+        "version": T::VERSION,
+        "data": serde_json::to_value(value)?,
+    }))
+}
+
+/// Read a value from a versioned envelope (or a bare pre-versioning
+/// value), migrating it to the current version first.
+pub fn from_versioned_value<T: Versioned>(value: Value) -> Result<T, VersionedFileError> {
+    let (version, mut data) = match value {
+        Value::Object(mut map) if map.contains_key("version") && map.contains_key("data") => {
+            let kind = map.remove("kind").unwrap_or(Value::Null);
+            if kind.as_str() != Some(T::KIND) {
+                return Err(VersionedFileError::WrongKind {
+                    expected: T::KIND,
+                    found: kind.to_string(),
+                });
+            }
+            // Anything that isn't a valid version number can only have come
+            // from a newer (or foreign) writer.
+            let version = map
+                .remove("version")
+                .and_then(|v| v.as_u64())
+                .map_or(u32::MAX, |v| u32::try_from(v).unwrap_or(u32::MAX));
+            (version, map.remove("data").unwrap_or(Value::Null))
+        }
+        bare => (0, bare),
+    };
+
+    if version > T::VERSION {
+        return Err(VersionedFileError::TooNew {
+            found: version,
+            supported: T::VERSION,
+        });
+    }
+    for from in version..T::VERSION {
+        data = T::migrate(from, data)?;
+    }
+    let value: T = serde_json::from_value(data)?;
+    value.validate()?;
+    Ok(value)
+}
+
+/// The envelope in the order it's written.
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    kind: &'static str,
+    version: u32,
+    data: &'a T,
+}
+
+/// Write `value` in a versioned envelope to `writer` without building it
+/// in memory first.
+pub fn write_versioned<T: Versioned, W: Write>(
+    writer: W,
+    value: &T,
+) -> Result<(), VersionedFileError> {
+    let envelope = Envelope {
+        kind: T::KIND,
+        version: T::VERSION,
+        data: value,
+    };
+    Ok(serde_json::to_writer(writer, &envelope)?)
+}
+
+/// Write `value` to `writer` in `format`.
+pub fn write_versioned_as<T: Versioned, W: Write>(
+    mut writer: W,
+    value: &T,
+    format: SaveFormat,
+) -> Result<(), VersionedFileError> {
+    match format {
+        SaveFormat::Json => write_versioned(writer, value),
+        SaveFormat::Binary => {
+            writer.write_all(BINARY_MAGIC)?;
+            bincode::serialize_into(&mut writer, &(T::KIND, T::VERSION))?;
+            Ok(bincode::serialize_into(writer, value)?)
+        }
+    }
+}
+
+/// Write `value` to `writer` as set by `options`.
+pub fn write_versioned_with<T: Versioned, W: Write>(
+    writer: W,
+    value: &T,
+    options: SaveOptions,
+) -> Result<(), VersionedFileError> {
+    #[cfg(feature = "compression")]
+    if let Some(level) = options.zstd_level {
+        let mut encoder = zstd::Encoder::new(writer, level)?;
+        write_versioned_as(&mut encoder, value, options.format)?;
+        encoder.finish()?;
+        return Ok(());
+    }
+    write_versioned_as(writer, value, options.format)
+}
+
+/// Read a value written by `write_versioned`, `write_versioned_as` or
+/// `write_versioned_with` (or a bare pre-versioning value), migrating it to
+/// the current version if needed.
+pub fn read_versioned<T: Versioned, R: Read>(reader: R) -> Result<T, VersionedFileError> {
+    let (binary, mut reader) = open_reader(reader)?;
+    if !binary {
+        return read_json(reader);
+    }
+
+    let (kind, version): (String, u32) = bincode::deserialize_from(&mut reader)?;
+    if kind != T::KIND {
+        return Err(VersionedFileError::WrongKind {
+            expected: T::KIND,
+            found: kind,
+        });
+    }
+    if version > T::VERSION {
+        return Err(VersionedFileError::TooNew {
+            found: version,
+            supported: T::VERSION,
+        });
+    }
+    if version < T::VERSION {
+        return Err(VersionedFileError::Migration {
+            from: version,
+            reason:
+                "binary files can't be migrated, save it as JSON with the version that wrote it"
+                    .to_string(),
+        });
+    }
+    let value: T = bincode::deserialize_from(reader)?;
+    value.validate()?;
+    Ok(value)
+}
+
+/// Whether `reader` holds a binary file, and a reader for the rest of it:
+/// the data after the magic for binary files, or the whole file otherwise.
+/// Compressed files are decompressed first.
+fn open_reader<'a, R: Read + 'a>(
+    reader: R,
+) -> Result<(bool, Box<dyn Read + 'a>), VersionedFileError> {
+    let (start, reader) = peek(reader)?;
+    if start.starts_with(&ZSTD_MAGIC) {
+        #[cfg(feature = "compression")]
+        {
+            let decoder = zstd::Decoder::new(Cursor::new(start).chain(reader))?;
+            let (start, reader) = peek(decoder)?;
+            return Ok(skip_magic(start, reader));
+        }
+        #[cfg(not(feature = "compression"))]
+        return Err(VersionedFileError::Compressed);
+    }
+    Ok(skip_magic(start, reader))
+}
+
+/// Read enough of `reader` to check for a magic number.
+fn peek<R: Read>(mut reader: R) -> std::io::Result<(Vec<u8>, R)> {
+    let mut start = Vec::with_capacity(BINARY_MAGIC.len());
+    reader
+        .by_ref()
+        .take(BINARY_MAGIC.len() as u64)
+        .read_to_end(&mut start)?;
+    Ok((start, reader))
+}
+
+fn skip_magic<'a>(mut start: Vec<u8>, reader: impl Read + 'a) -> (bool, Box<dyn Read + 'a>) {
+    let binary = start == BINARY_MAGIC;
+    if binary {
+        start.clear();
+    }
+    (binary, Box::new(Cursor::new(start).chain(reader)))
+}
+
+fn read_json<T: Versioned, R: Read>(reader: R) -> Result<T, VersionedFileError> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let raw = deserializer.deserialize_any(RawVisitor::<T>(PhantomData))?;
+    deserializer.end()?;
+    match raw {
+        Raw::Current(value) => {
+            value.validate()?;
+            Ok(value)
+        }
+        Raw::Buffered(value) => from_versioned_value(value),
+    }
+}
+
+/// Save `value` as a versioned JSON file.
+pub fn save_versioned<T: Versioned>(path: &Path, value: &T) -> Result<(), VersionedFileError> {
+    save_versioned_as(path, value, SaveFormat::Json)
+}
+
+/// Save `value` as a versioned file in a `SaveFormat`, or as set by
+/// `SaveOptions`.
+pub fn save_versioned_as<T: Versioned>(
+    path: &Path,
+    value: &T,
+    options: impl Into<SaveOptions>,
+) -> Result<(), VersionedFileError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_versioned_with(&mut writer, value, options.into())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Load a versioned file in any format, migrating JSON files that were
+/// written by an older version.
+pub fn load_versioned<T: Versioned>(path: &Path) -> Result<T, VersionedFileError> {
+    read_versioned(BufReader::new(File::open(path)?))
+}
+
+/// The kind and version a saved file was written with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFormat {
+    /// `None` for files written before versioning, which don't say.
+    pub kind: Option<String>,
+    pub version: u32,
+}
+
+/// Work out which format a saved file was written with, skipping over the
+/// data rather than deserializing it.
+pub fn detect_format<R: Read>(reader: R) -> Result<FileFormat, VersionedFileError> {
+    let (binary, mut reader) = open_reader(reader)?;
+    if binary {
+        let (kind, version): (String, u32) = bincode::deserialize_from(&mut reader)?;
+        return Ok(FileFormat {
+            kind: Some(kind),
+            version,
+        });
+    }
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let format = deserializer.deserialize_any(FormatVisitor)?;
+    deserializer.end()?;
+    Ok(format)
+}
+
+/// Rewrite the file at `path` with the current format if it was written by
+/// an older version of the crate, returning the format it had.
+///
+/// The upgraded file is written next to the original and moved into place,
+/// so the original is untouched if anything fails.
+pub fn upgrade_versioned<T: Versioned>(path: &Path) -> Result<FileFormat, VersionedFileError> {
+    let format = detect_format(BufReader::new(File::open(path)?))?;
+    if format.kind.as_deref() == Some(T::KIND) && format.version == T::VERSION {
+        return Ok(format);
+    }
+
+    let value: T = load_versioned(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut file = NamedTempFile::new_in(dir)?;
+    {
+        let mut writer = BufWriter::new(file.as_file_mut());
+        write_versioned(&mut writer, &value)?;
+        writer.flush()?;
+    }
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(format)
+}
+
+struct FormatVisitor;
+
+impl<'de> Visitor<'de> for FormatVisitor {
+    type Value = FileFormat;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a saved file")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut kind, mut version, mut has_data) = (None, None, false);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "kind" => kind = map.next_value::<Value>()?.as_str().map(str::to_string),
+                "version" => version = Some(map.next_value::<Value>()?),
+                _ => {
+                    has_data |= key == "data";
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(match version {
+            // The same rules as `from_versioned_value`.
+            Some(version) if has_data => FileFormat {
+                kind,
+                version: version
+                    .as_u64()
+                    .map_or(u32::MAX, |v| u32::try_from(v).unwrap_or(u32::MAX)),
+            },
+            _ => FileFormat {
+                kind: None,
+                version: 0,
+            },
+        })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(FileFormat {
+            kind: None,
+            version: 0,
+        })
+    }
+}
+
+enum Raw<T> {
+    /// The data was the current version and was deserialized directly.
+    Current(T),
+    /// Everything else, to go through `from_versioned_value`.
+    Buffered(Value),
+}
+
+struct RawVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Versioned> Visitor<'de> for RawVisitor<T> {
+    type Value = Raw<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a versioned {} file", T::KIND)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut fields = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let is_current = fields.get("kind").and_then(Value::as_str) == Some(T::KIND)
+                && fields.get("version").and_then(Value::as_u64) == Some(T::VERSION.into());
+            if key == "data" && is_current {
+                let value = map.next_value::<T>()?;
+                // Drain anything after the data so the reader ends up at
+                // the end of the document.
+                while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+                return Ok(Raw::Current(value));
+            }
+            fields.insert(key, map.next_value()?);
+        }
+        Ok(Raw::Buffered(Value::Object(fields)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        Ok(Raw::Buffered(Value::deserialize(
+            SeqAccessDeserializer::new(seq),
+        )?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    /// Version 0 had `name`, version 1 renamed it to `label`, and version 2
+    /// added `size`.
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Widget {
+        label: String,
+        size: u32,
+    }
+
+    impl Versioned for Widget {
+        const KIND: &'static str = "widget";
+        const VERSION: u32 = 2;
+
+        fn migrate(version: u32, mut data: Value) -> Result<Value, VersionedFileError> {
+            let map = data
+                .as_object_mut()
+                .ok_or_else(|| VersionedFileError::Migration {
+                    from: version,
+                    reason: "expected an object".to_string(),
+                })?;
+            match version {
+                0 => {
+                    let name = map.remove("name").unwrap_or_default();
+                    map.insert("label".to_string(), name);
+                }
+                1 => {
+                    map.insert("size".to_string(), json!(1));
+                }
+                _ => {}
+            }
+            Ok(data)
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let widget = Widget {
+            label: "a".to_string(),
+            size: 3,
+        };
+        let value = to_versioned_value(&widget).unwrap();
+        assert_eq!(json!(2), value["version"]);
+        assert_eq!(widget, from_versioned_value(value).unwrap());
+    }
+
+    #[test]
+    fn test_migrate_unversioned() {
+        let widget: Widget = from_versioned_value(json!({"name": "old"})).unwrap();
+        assert_eq!(
+            Widget {
+                label: "old".to_string(),
+                size: 1
+            },
+            widget
+        );
+    }
+
+    #[test]
+    fn test_migrate_from_middle() {
+        let value = json!({"kind": "widget", "version": 1, "data": {"label": "b"}});
+        let widget: Widget = from_versioned_value(value).unwrap();
+        assert_eq!(1, widget.size);
+        assert_eq!("b", widget.label);
+    }
+
+    #[test]
+    fn test_too_new() {
+        let value = json!({"kind": "widget", "version": 3, "data": {}});
+        assert!(matches!(
+            from_versioned_value::<Widget>(value),
+            Err(VersionedFileError::TooNew {
+                found: 3,
+                supported: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_wrong_kind() {
+        let value = json!({"kind": "gadget", "version": 2, "data": {}});
+        assert!(matches!(
+            from_versioned_value::<Widget>(value),
+            Err(VersionedFileError::WrongKind { .. })
+        ));
+    }
+
+    #[test]
+    fn test_save_load_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("widget.json");
+        let widget = Widget {
+            label: "c".to_string(),
+            size: 9,
+        };
+        save_versioned(&path, &widget).unwrap();
+        assert_eq!(widget, load_versioned(&path).unwrap());
+
+        // Streamed files put the kind and version first.
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with(r#"{"kind":"widget","version":2,"data":"#));
+    }
+
+    #[test]
+    fn test_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("widget.bin");
+        let widget = Widget {
+            label: "g".to_string(),
+            size: 5,
+        };
+        save_versioned_as(&path, &widget, SaveFormat::Binary).unwrap();
+        assert!(std::fs::read(&path).unwrap().starts_with(BINARY_MAGIC));
+        assert_eq!(widget, load_versioned(&path).unwrap());
+        assert_eq!(
+            FileFormat {
+                kind: Some("widget".to_string()),
+                version: 2
+            },
+            detect_format(File::open(&path).unwrap()).unwrap()
+        );
+
+        let mut old = BINARY_MAGIC.to_vec();
+        bincode::serialize_into(&mut old, &("widget", 1u32)).unwrap();
+        assert!(matches!(
+            read_versioned::<Widget, _>(&old[..]),
+            Err(VersionedFileError::Migration { from: 1, .. })
+        ));
+
+        let mut gadget = BINARY_MAGIC.to_vec();
+        bincode::serialize_into(&mut gadget, &("gadget", 2u32)).unwrap();
+        assert!(matches!(
+            read_versioned::<Widget, _>(&gadget[..]),
+            Err(VersionedFileError::WrongKind { .. })
+        ));
+
+        // A truncated file is an error rather than a panic.
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(matches!(
+            read_versioned::<Widget, _>(&bytes[..bytes.len() - 1]),
+            Err(VersionedFileError::Binary(_))
+        ));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let widget = Widget {
+            label: "h".repeat(1000),
+            size: 6,
+        };
+        for format in [SaveFormat::Json, SaveFormat::Binary] {
+            let path = dir.path().join("widget.zst");
+            let options = SaveOptions::from(format).compressed(SaveOptions::DEFAULT_ZSTD_LEVEL);
+            save_versioned_as(&path, &widget, options).unwrap();
+            let bytes = std::fs::read(&path).unwrap();
+            assert!(bytes.starts_with(&ZSTD_MAGIC));
+            assert!(bytes.len() < 100);
+            assert_eq!(widget, load_versioned(&path).unwrap());
+            assert_eq!(
+                Some("widget"),
+                detect_format(&bytes[..]).unwrap().kind.as_deref()
+            );
+        }
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn test_compressed_needs_feature() {
+        let compressed = [0x28, 0xb5, 0x2f, 0xfd, 0, 0];
+        assert!(matches!(
+            read_versioned::<Widget, _>(&compressed[..]),
+            Err(VersionedFileError::Compressed)
+        ));
+    }
+
+    #[test]
+    fn test_read_other_layouts() {
+        let widget = Widget {
+            label: "d".to_string(),
+            size: 4,
+        };
+        // Files saved before streaming have their keys sorted, so the data
+        // comes first.
+        let sorted = serde_json::to_vec(&to_versioned_value(&widget).unwrap()).unwrap();
+        assert!(sorted.starts_with(br#"{"data":"#));
+        assert_eq!(widget, read_versioned(&sorted[..]).unwrap());
+
+        let old = br#"{"kind": "widget", "version": 1, "data": {"label": "e"}}"#;
+        let migrated: Widget = read_versioned(&old[..]).unwrap();
+        assert_eq!(1, migrated.size);
+
+        let bare = br#"{"name": "f"}"#;
+        assert_eq!("f", read_versioned::<Widget, _>(&bare[..]).unwrap().label);
+
+        let wrong_kind = br#"{"kind": "gadget", "version": 2, "data": {}}"#;
+        assert!(matches!(
+            read_versioned::<Widget, _>(&wrong_kind[..]),
+            Err(VersionedFileError::WrongKind { .. })
+        ));
+        assert!(read_versioned::<Widget, _>(&b"[1, 2]"[..]).is_err());
+        assert!(read_versioned::<Widget, _>(&b"{} {}"[..]).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+//! Command line access to the common workflows: equity of a hand against a
+//! range, parsing ranges, batch simulations, exporting their hands,
+//! converting hand histories between formats and comparing trained
+//! strategies.
+//!
+//! Run `rs-poker --help` for the subcommands and `rs-poker <command> --help`
+//! for their options.
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rs_poker::arena::agent::{
+    AllInAgentGenerator, CallingAgentGenerator, FoldingAgentGenerator, RandomAgentGenerator,
+};
+use rs_poker::arena::cfr::{StrategyComparison, StrategyProfile};
+use rs_poker::arena::competition::{HoldemCompetition, StandardSimulationIterator};
+use rs_poker::arena::hand_history::{HandHistoryFormat, convert};
+use rs_poker::arena::historian::{DirectoryHistorian, HistorianGenerator};
+use rs_poker::arena::{
+    AgentGenerator, CloneGameStateGenerator, CloneHistorianGenerator, GameState,
+};
+use rs_poker::core::{Card, CardBitSet, FlatHand, Hand, Value, with_seed};
+use rs_poker::holdem::{MonteCarloGame, RangeParser};
+
+#[derive(Parser)]
+#[command(name = "rs-poker", version, about = "Poker tools built on rs_poker")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Equity of a hand against a range of hands.
+    Equity {
+        /// The hero's hole cards, like `AsKd`.
+        hand: String,
+        /// The villain's range, like `TT+,AKs,KQo`.
+        range: String,
+        /// Board cards already dealt, like `Ah7c2d`.
+        #[arg(short, long, default_value = "")]
+        board: String,
+        /// How many runouts to simulate in total.
+        #[arg(short, long, default_value_t = 100_000)]
+        iterations: usize,
+    },
+    /// Parse a range and print it in normal form with its size.
+    Range {
+        /// The range, like `TT+,AKs,KQo`.
+        range: String,
+        /// Print every combination instead of the normal form.
+        #[arg(long)]
+        combos: bool,
+    },
+    /// Play a batch of hands and report how each seat did.
+    Simulate(TableArgs),
+    /// Play a batch of hands and write each one as JSON to a directory.
+    Export {
+        #[command(flatten)]
+        table: TableArgs,
+        /// The directory to write the hands to, one file per hand.
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Convert a file of hand histories to another format.
+    Convert {
+        /// The hand histories to read.
+        input: PathBuf,
+        /// The format to read, guessed from the file extension if not given.
+        #[arg(long)]
+        from: Option<HandHistoryFormat>,
+        /// The format to write.
+        #[arg(long)]
+        to: HandHistoryFormat,
+        /// Where to write the converted hands instead of standard out.
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Report how two strategy profiles over the same abstraction differ.
+    CompareStrategies {
+        /// The earlier profile.
+        first: PathBuf,
+        /// The later profile.
+        second: PathBuf,
+        /// How many of the most divergent decisions to list.
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+}
+
+#[derive(Args)]
+struct TableArgs {
+    /// The agent in each seat, starting with the dealer.
+    #[arg(short, long, value_delimiter = ',', default_value = "calling,random")]
+    agents: Vec<AgentKind>,
+    /// How many hands to play.
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    hands: usize,
+    /// Every seat's starting stack.
+    #[arg(long, default_value_t = 100.0)]
+    stack: f32,
+    #[arg(long, default_value_t = 2.0)]
+    big_blind: f32,
+    #[arg(long, default_value_t = 1.0)]
+    small_blind: f32,
+    #[arg(long, default_value_t = 0.0)]
+    ante: f32,
+    /// Seed the deals and the agents to make the run reproducible.
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum AgentKind {
+    Calling,
+    Folding,
+    AllIn,
+    Random,
+}
+
+impl AgentKind {
+    fn generator(self) -> Box<dyn AgentGenerator> {
+        match self {
+            AgentKind::Calling => Box::<CallingAgentGenerator>::default(),
+            AgentKind::Folding => Box::<FoldingAgentGenerator>::default(),
+            AgentKind::AllIn => Box::<AllInAgentGenerator>::default(),
+            AgentKind::Random => Box::<RandomAgentGenerator>::default(),
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Equity {
+            hand,
+            range,
+            board,
+            iterations,
+        } => equity(&hand, &range, &board, iterations),
+        Command::Range { range, combos } => print_range(&range, combos),
+        Command::Simulate(table) => simulate(&table, Vec::new()),
+        Command::Export { table, out } => {
+            let historian: Box<dyn HistorianGenerator> = Box::new(CloneHistorianGenerator::new(
+                DirectoryHistorian::new(out.clone()),
+            ));
+            simulate(&table, vec![historian])?;
+            println!("Wrote {} hands to {}", table.hands, out.display());
+            Ok(())
+        }
+        Command::Convert {
+            input,
+            from,
+            to,
+            out,
+        } => convert_file(&input, from, to, out.as_deref()),
+        Command::CompareStrategies { first, second, top } => {
+            compare_strategies(&first, &second, top)
+        }
+    }
+}
+
+fn compare_strategies(first: &Path, second: &Path, top: usize) -> anyhow::Result<()> {
+    let load = |path: &Path| {
+        StrategyProfile::load_from_file(path).with_context(|| format!("loading {}", path.display()))
+    };
+    let comparison = StrategyComparison::new(&load(first)?, &load(second)?)?;
+    println!("{comparison}");
+    for spot in comparison.top(top) {
+        println!("{spot}");
+    }
+    Ok(())
+}
+
+fn convert_file(
+    input: &Path,
+    from: Option<HandHistoryFormat>,
+    to: HandHistoryFormat,
+    out: Option<&Path>,
+) -> anyhow::Result<()> {
+    let Some(from) = from.or_else(|| HandHistoryFormat::from_path(input)) else {
+        bail!("can't tell the format of {}, pass --from", input.display());
+    };
+    let text =
+        std::fs::read_to_string(input).with_context(|| format!("reading {}", input.display()))?;
+    let converted = convert(&text, from, to).context("converting the hands")?;
+    match out {
+        Some(out) => {
+            std::fs::write(out, converted).with_context(|| format!("writing {}", out.display()))?
+        }
+        None => print!("{converted}"),
+    }
+    Ok(())
+}
+
+fn equity(hand: &str, range: &str, board: &str, iterations: usize) -> anyhow::Result<()> {
+    let hero = Hand::new_from_str(hand).context("parsing the hand")?;
+    let board = Hand::new_from_str(board).context("parsing the board")?;
+    if hero.count() != 2 {
+        bail!("the hand needs two cards, not {}", hero.count());
+    }
+    if !matches!(board.count(), 0 | 3 | 4 | 5) {
+        bail!("the board needs 0, 3, 4 or 5 cards, not {}", board.count());
+    }
+    let dead = CardBitSet::from(hero).union(CardBitSet::from(board));
+    if dead.count() != hero.count() + board.count() {
+        bail!("the hand and board share a card");
+    }
+
+    // Combinations holding a card that's already out can't be dealt.
+    let combos: Vec<FlatHand> = RangeParser::parse_many(range)
+        .context("parsing the range")?
+        .into_iter()
+        .filter(|combo| CardBitSet::from(&combo[..]).is_disjoint(dead))
+        .collect();
+    if combos.is_empty() {
+        bail!("every combination in the range is blocked by the hand or board");
+    }
+
+    // Every combination is equally likely, so split the runouts evenly.
+    let per_combo = iterations.div_ceil(combos.len());
+    let mut total = 0.0;
+    for combo in &combos {
+        let mut villain = board;
+        for card in combo.iter() {
+            villain.insert(*card);
+        }
+        let mut game = MonteCarloGame::new(vec![with_board(hero, board), villain])?;
+        total += game.estimate_equity(per_combo)[0] as f64;
+    }
+    let equity = total / combos.len() as f64;
+
+    println!(
+        "{} combinations, {} runouts",
+        combos.len(),
+        per_combo * combos.len()
+    );
+    println!("hand:  {:.2}%", equity * 100.0);
+    println!("range: {:.2}%", (1.0 - equity) * 100.0);
+    Ok(())
+}
+
+fn with_board(mut hand: Hand, board: Hand) -> Hand {
+    for card in board.iter() {
+        hand.insert(card);
+    }
+    hand
+}
+
+fn print_range(range: &str, combos: bool) -> anyhow::Result<()> {
+    let mut parsed = RangeParser::parse_many(range).context("parsing the range")?;
+    // Overlapping parts of a range give the same combination twice.
+    parsed.sort_by_key(|combo| Reverse(CardBitSet::from(&combo[..]).to_u64()));
+    parsed.dedup_by_key(|combo| CardBitSet::from(&combo[..]));
+
+    if combos {
+        for combo in &parsed {
+            println!("{}", cards_str(combo.iter().copied()));
+        }
+    } else {
+        println!("{}", normal_form(&parsed));
+    }
+    println!(
+        "{} combinations, {:.2}% of hands",
+        parsed.len(),
+        parsed.len() as f64 / 1326.0 * 100.0
+    );
+    Ok(())
+}
+
+/// Whole starting hands like `AKs` where every combination is in the range,
+/// and the combinations themselves where only some are. Pairs come first,
+/// then suited and offsuit hands, strongest first.
+fn normal_form(combos: &[FlatHand]) -> String {
+    // Keyed by pair, suited or offsuit, then the values strongest first.
+    type Key = (usize, Reverse<Value>, Reverse<Value>);
+    let mut groups: BTreeMap<Key, Vec<&FlatHand>> = BTreeMap::new();
+    for combo in combos {
+        let (high, low) = if combo[0].value >= combo[1].value {
+            (combo[0], combo[1])
+        } else {
+            (combo[1], combo[0])
+        };
+        let kind = if high.value == low.value {
+            0
+        } else if high.suit == low.suit {
+            1
+        } else {
+            2
+        };
+        groups
+            .entry((kind, Reverse(high.value), Reverse(low.value)))
+            .or_default()
+            .push(combo);
+    }
+
+    let parts: Vec<String> = groups
+        .into_iter()
+        .flat_map(|((kind, Reverse(high), Reverse(low)), members)| {
+            let full = [6, 4, 12][kind];
+            if members.len() == full {
+                let suffix = ["", "s", "o"][kind];
+                vec![format!("{}{}{suffix}", high.to_char(), low.to_char())]
+            } else {
+                members
+                    .into_iter()
+                    .map(|combo| cards_str(combo.iter().copied()))
+                    .collect()
+            }
+        })
+        .collect();
+    parts.join(",")
+}
+
+fn cards_str(cards: impl Iterator<Item = Card>) -> String {
+    let mut cards: Vec<Card> = cards.collect();
+    cards.sort_by_key(|card| Reverse(*card));
+    cards.iter().map(Card::to_string).collect()
+}
+
+fn simulate(table: &TableArgs, historians: Vec<Box<dyn HistorianGenerator>>) -> anyhow::Result<()> {
+    if table.agents.len() < 2 {
+        bail!("a table needs at least two agents");
+    }
+    let game_state = GameState::new_starting(
+        vec![table.stack; table.agents.len()],
+        table.big_blind,
+        table.small_blind,
+        table.ante,
+        0,
+    );
+    let agents = table.agents.iter().map(|kind| kind.generator()).collect();
+    let mut competition = HoldemCompetition::new(StandardSimulationIterator::new(
+        agents,
+        historians,
+        CloneGameStateGenerator::new(game_state),
+    ));
+    competition.record_timings(true);
+    match table.seed {
+        Some(seed) => with_seed(seed, || competition.run(table.hands))?,
+        None => competition.run(table.hands)?,
+    };
+
+    let timings = &competition.timings;
+    println!(
+        "{} hands in {:?} ({:.0} hands/s)",
+        timings.hands(),
+        timings.total(),
+        timings.hands_per_second()
+    );
+    println!(
+        "{:<6} {:<8} {:>10} {:>10} {:>22} {:>10}",
+        "seat", "agent", "bb/100", "sd/100", "95% interval", "downswing"
+    );
+    for (idx, kind) in table.agents.iter().enumerate() {
+        let results = &competition.results[idx];
+        let (low, high) = results.confidence_interval(1.96);
+        println!(
+            "{:<6} {:<8} {:>10.2} {:>10.2} {:>22} {:>10.2}",
+            idx,
+            kind.to_possible_value()
+                .expect("no skipped agents")
+                .get_name(),
+            results.bb_per_100(),
+            results.std_dev_per_100(),
+            format!("[{low:.2}, {high:.2}]"),
+            results.max_downswing()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! Serve a saved `StrategyProfile` over gRPC.
+//!
+//! Usage: `strategy_server <profile.json> [addr]`, where `addr` defaults to
+//! `127.0.0.1:50051`.
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use rs_poker::arena::cfr::StrategyProfile;
+use rs_poker::proto::strategy::StrategyService;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let Some(profile_path) = args.next().map(PathBuf::from) else {
+        anyhow::bail!("usage: strategy_server <profile.json> [addr]");
+    };
+    let addr: SocketAddr = args
+        .next()
+        .as_deref()
+        .unwrap_or("127.0.0.1:50051")
+        .parse()?;
+
+    let profile = StrategyProfile::load_from_file(&profile_path)?;
+    println!(
+        "Serving {} decision points from {} on {addr}",
+        profile.len(),
+        profile_path.display()
+    );
+    StrategyService::new(profile).serve(addr).await?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! `Arbitrary` implementations for fuzzing, and helpers for the things that
+//! aren't a type of their own.
+//!
+//! `Card`, `Value` and `Suit` derive `Arbitrary`. The sets of cards here
+//! never hold the same card twice, so they're always something that could
+//! have been dealt.
+//!
+//! ```
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use rs_poker::core::{Card, FlatHand, arbitrary::board};
+//!
+//! #[derive(Debug, Arbitrary)]
+//! struct Input {
+//!     hole_cards: [Card; 2],
+//!     #[arbitrary(with = board)]
+//!     board: Vec<Card>,
+//! }
+//!
+//! let bytes = [7; 64];
+//! let input = Input::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+//! assert!(matches!(input.board.len(), 0 | 3 | 4 | 5));
+//! ```
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use super::{Card, CardBitSet, Deck, FlatHand, Hand};
+
+/// Up to `max` different cards, in an arbitrary order.
+pub fn cards(u: &mut Unstructured, max: usize) -> Result<Vec<Card>> {
+    let mut seen = CardBitSet::new();
+    let mut cards = Vec::new();
+    while cards.len() < max && u.arbitrary()? {
+        let card = Card::from(u.int_in_range(0..=51)?);
+        if !seen.contains(card) {
+            seen.insert(card);
+            cards.push(card);
+        }
+    }
+    Ok(cards)
+}
+
+/// A board as it is between streets: no cards, the flop, the turn or the
+/// river.
+pub fn board(u: &mut Unstructured) -> Result<Vec<Card>> {
+    let len = *u.choose(&[0, 3, 4, 5])?;
+    let mut board = cards(u, len)?;
+    // Run out of data and the board comes out short, so fill it up.
+    let mut deck = !CardBitSet::from(&board[..]);
+    while board.len() < len {
+        let card = deck.iter().nth(u.choose_index(deck.count())?).unwrap();
+        deck.remove(card);
+        board.push(card);
+    }
+    Ok(board)
+}
+
+impl<'a> Arbitrary<'a> for CardBitSet {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let bits: u64 = u.arbitrary()?;
+        Ok(CardBitSet::try_from_u64(bits & (!CardBitSet::new()).to_u64()).unwrap())
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        u64::size_hint(depth)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Hand {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Hand::from(CardBitSet::arbitrary(u)?))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        CardBitSet::size_hint(depth)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Deck {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Deck::from(CardBitSet::arbitrary(u)?))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        CardBitSet::size_hint(depth)
+    }
+}
+
+impl<'a> Arbitrary<'a> for FlatHand {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(FlatHand::new_with_cards(cards(u, 52)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_repeated_cards() {
+        let bytes: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let mut u = Unstructured::new(&bytes);
+        while !u.is_empty() {
+            let hand = FlatHand::arbitrary(&mut u).unwrap();
+            assert_eq!(hand.len(), CardBitSet::from(&hand[..]).count());
+
+            let board = board(&mut u).unwrap();
+            assert!(matches!(board.len(), 0 | 3 | 4 | 5));
+            assert_eq!(board.len(), CardBitSet::from(&board[..]).count());
+        }
+        // Out of data gives empty sets rather than failing.
+        let mut empty = Unstructured::new(&[]);
+        assert!(Hand::arbitrary(&mut empty).unwrap().is_empty());
+    }
+}
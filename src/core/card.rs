@@ -7,6 +7,7 @@ use super::error::RSPokerError;
 /// Card rank or value.
 /// This is basically the face value - 2
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[derive(PartialEq, PartialOrd, Eq, Ord, Debug, Clone, Copy, Hash)]
 pub enum Value {
     /// 2
@@ -197,6 +198,7 @@ impl From<Value> for u8 {
 /// While this has support for ordering it's not
 /// sensical. The sorting is only there to allow sorting cards.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[derive(PartialEq, PartialOrd, Eq, Ord, Debug, Clone, Copy, Hash)]
 pub enum Suit {
     /// Spades
@@ -310,6 +312,7 @@ impl From<Suit> for char {
 /// The main struct of this library.
 /// This is a carrier for Suit and Value combined.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Hash)]
 pub struct Card {
     /// The face value of this card.
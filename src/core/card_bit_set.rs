@@ -1,6 +1,8 @@
-use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign,
+};
 
-use super::{Card, FlatDeck};
+use super::{Card, FlatDeck, RSPokerError};
 use std::fmt::Debug;
 
 use rand::Rng;
@@ -21,6 +23,24 @@ pub struct CardBitSet {
     cards: u64,
 }
 
+/// A set of cards, as one bit per card in a u64. Union, intersection,
+/// difference and membership are each a single instruction, so it's the
+/// type to reach for when tracking dead cards, what's on the board or
+/// what's left in the deck, rather than scanning a `Vec<Card>`.
+///
+/// ```
+/// use rs_poker::core::{CardSet, Hand};
+///
+/// let hero = Hand::new_from_str("AsKs").unwrap();
+/// let board: CardSet = Hand::new_from_str("Qs7d2c").unwrap().into();
+/// let dead = CardSet::from(hero).union(board);
+///
+/// assert_eq!(5, dead.count());
+/// assert_eq!(47, (!dead).count());
+/// assert!(board.is_subset(dead));
+/// ```
+pub type CardSet = CardBitSet;
+
 const FIFTY_TWO_ONES: u64 = (1 << 52) - 1;
 
 impl CardBitSet {
@@ -121,6 +141,64 @@ impl CardBitSet {
         self.cards = 0;
     }
 
+    /// The set packed into a u64, bit `u8::from(card)` for each card. See
+    /// `rs_poker::core::codec`.
+    pub fn to_u64(self) -> u64 {
+        self.cards
+    }
+
+    /// Unpack a set from `to_u64`. Fails if any bit above the 52 cards is
+    /// set.
+    pub fn try_from_u64(bits: u64) -> Result<Self, RSPokerError> {
+        if bits & !FIFTY_TWO_ONES != 0 {
+            return Err(RSPokerError::InvalidPackedHand(bits));
+        }
+        Ok(Self { cards: bits })
+    }
+
+    /// Iterate over the cards in the set, from lowest to highest.
+    pub fn iter(&self) -> CardBitSetIter {
+        CardBitSetIter(self.cards)
+    }
+
+    /// The cards in either set.
+    pub fn union(self, other: Self) -> Self {
+        self | other
+    }
+
+    /// The cards in both sets.
+    pub fn intersection(self, other: Self) -> Self {
+        self & other
+    }
+
+    /// The cards in this set but not in `other`.
+    ///
+    /// ```
+    /// use rs_poker::core::{CardSet, Hand};
+    ///
+    /// let hand: CardSet = Hand::new_from_str("AsKsQs7d2c").unwrap().into();
+    /// let board: CardSet = Hand::new_from_str("Qs7d2c").unwrap().into();
+    /// let hole: Vec<String> = hand
+    ///     .difference(board)
+    ///     .iter()
+    ///     .map(|c| c.to_string())
+    ///     .collect();
+    /// assert_eq!(vec!["Ks", "As"], hole);
+    /// ```
+    pub fn difference(self, other: Self) -> Self {
+        self - other
+    }
+
+    /// Are all the cards in this set also in `other`?
+    pub fn is_subset(self, other: Self) -> bool {
+        self.cards & !other.cards == 0
+    }
+
+    /// Do the sets have no cards in common?
+    pub fn is_disjoint(self, other: Self) -> bool {
+        self.cards & other.cards == 0
+    }
+
     /// Sample one card from the bitset
     ///
     /// Returns `None` if the bitset is empty
@@ -279,6 +357,44 @@ impl BitAndAssign for CardBitSet {
     }
 }
 
+impl Sub for CardBitSet {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            cards: self.cards & !rhs.cards,
+        }
+    }
+}
+
+impl SubAssign for CardBitSet {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.cards &= !rhs.cards;
+    }
+}
+
+impl FromIterator<Card> for CardBitSet {
+    fn from_iter<I: IntoIterator<Item = Card>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Card> for CardBitSet {
+    fn extend<I: IntoIterator<Item = Card>>(&mut self, iter: I) {
+        for card in iter {
+            self.insert(card);
+        }
+    }
+}
+
+impl From<&[Card]> for CardBitSet {
+    fn from(cards: &[Card]) -> Self {
+        cards.iter().copied().collect()
+    }
+}
+
 impl Not for CardBitSet {
     type Output = Self;
 
@@ -727,4 +843,32 @@ mod tests {
             "Picked cards two should be unique"
         );
     }
+
+    #[test]
+    fn test_set_operations() {
+        let deck = CardSet::default();
+        let clubs: CardSet = deck
+            .iter()
+            .filter(|card| card.suit == crate::core::Suit::Club)
+            .collect();
+        let aces: CardSet = deck
+            .iter()
+            .filter(|card| card.value == crate::core::Value::Ace)
+            .collect();
+
+        assert_eq!(16, clubs.union(aces).count());
+        assert_eq!(1, clubs.intersection(aces).count());
+        assert_eq!(12, clubs.difference(aces).count());
+        assert!(clubs.is_subset(deck));
+        assert!(!deck.is_subset(clubs));
+        assert!(clubs.difference(aces).is_disjoint(aces));
+
+        let mut rest = deck;
+        rest -= clubs;
+        assert_eq!(39, rest.count());
+        assert_eq!(!clubs, rest);
+
+        let cards: Vec<Card> = aces.iter().collect();
+        assert_eq!(aces, CardSet::from(&cards[..]));
+    }
 }
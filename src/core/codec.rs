@@ -0,0 +1,225 @@
+//! Packed integer encodings for cards and hands, for passing them across
+//! FFI boundaries, to GPU kernels or into binary logs without going
+//! through strings.
+//!
+//! These layouts are stable: changing any of them is a breaking change.
+//!
+//! # `u8`: one card
+//!
+//! `suit * 13 + value`, so 0 to 51, where value is 0 for a two up to 12 for
+//! an ace and suit is 0 spades, 1 clubs, 2 hearts, 3 diamonds. This is
+//! `u8::from(Card)`, and what the C bindings and the hand log use.
+//!
+//! # `u32`: one card, Cactus Kev layout
+//!
+//! ```text
+//! +--------+--------+--------+--------+
+//! |xxxbbbbb|bbbbbbbb|cdhsrrrr|xxpppppp|
+//! +--------+--------+--------+--------+
+//! ```
+//!
+//! - `b`, bits 16 to 28, has the bit for the value set, 1 << value.
+//! - `cdhs`, bits 12 to 15, has the bit for the suit set: clubs is 0x8000,
+//!   diamonds 0x4000, hearts 0x2000, spades 0x1000.
+//! - `r`, bits 8 to 11, is the value, 0 to 12.
+//! - `p`, bits 0 to 5, is the prime for the value, 2 for a two up to 41 for an
+//!   ace.
+//!
+//! This is the layout most table driven and GPU evaluators expect.
+//!
+//! # `u64`: a set of cards
+//!
+//! Bit `u8::from(card)` is set for each card, the other twelve bits are
+//! clear. This is `CardBitSet::to_u64`, and `Hand` converts through
+//! `CardBitSet`.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::core::Card;
+//! use rs_poker::core::codec::{decode_u8, decode_u32, encode_u8, encode_u32};
+//!
+//! let cards = vec![Card::try_from("As").unwrap(), Card::try_from("Td").unwrap()];
+//!
+//! let bytes = encode_u8(&cards);
+//! assert_eq!(vec![12, 47], bytes);
+//! assert_eq!(cards, decode_u8(&bytes).unwrap());
+//!
+//! let packed = encode_u32(&cards);
+//! assert_eq!(0x1000_1c29, packed[0]);
+//! assert_eq!(cards, decode_u32(&packed).unwrap());
+//!
+//! assert!(decode_u8(&[52]).is_err());
+//! ```
+
+use super::{Card, CardBitSet, Hand, RSPokerError, Suit, Value};
+
+const PRIMES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+fn suit_bit(suit: Suit) -> u32 {
+    match suit {
+        Suit::Club => 0x8000,
+        Suit::Diamond => 0x4000,
+        Suit::Heart => 0x2000,
+        Suit::Spade => 0x1000,
+    }
+}
+
+impl Card {
+    /// The card as `suit * 13 + value`.
+    pub fn to_u8(self) -> u8 {
+        u8::from(self)
+    }
+
+    /// The card from `to_u8`. Unlike `Card::from(u8)` this fails on
+    /// anything above 51 rather than clamping it.
+    pub fn try_from_u8(byte: u8) -> Result<Self, RSPokerError> {
+        if byte < 52 {
+            Ok(Self::from(byte))
+        } else {
+            Err(RSPokerError::InvalidPackedCard(byte.into()))
+        }
+    }
+
+    /// The card in the Cactus Kev `u32` layout.
+    pub fn to_u32(self) -> u32 {
+        let value = self.value as u32;
+        (1 << (16 + value)) | suit_bit(self.suit) | (value << 8) | PRIMES[value as usize]
+    }
+
+    /// The card from `to_u32`. Fails unless every field agrees with the
+    /// others, so a corrupt or differently packed card is caught.
+    pub fn try_from_u32(packed: u32) -> Result<Self, RSPokerError> {
+        let value = (packed >> 8) & 0xf;
+        let suit = match (packed >> 12) & 0xf {
+            0x8 => Suit::Club,
+            0x4 => Suit::Diamond,
+            0x2 => Suit::Heart,
+            0x1 => Suit::Spade,
+            _ => return Err(RSPokerError::InvalidPackedCard(packed)),
+        };
+        if value > Value::Ace as u32 {
+            return Err(RSPokerError::InvalidPackedCard(packed));
+        }
+        let card = Self::new(Value::from(value as u8), suit);
+        if card.to_u32() == packed {
+            Ok(card)
+        } else {
+            Err(RSPokerError::InvalidPackedCard(packed))
+        }
+    }
+}
+
+impl Hand {
+    /// The hand as a `u64` with a bit per card.
+    pub fn to_u64(self) -> u64 {
+        CardBitSet::from(self).to_u64()
+    }
+
+    /// The hand from `to_u64`.
+    pub fn try_from_u64(bits: u64) -> Result<Self, RSPokerError> {
+        CardBitSet::try_from_u64(bits).map(Self::from)
+    }
+}
+
+/// Pack each card into a byte.
+pub fn encode_u8(cards: &[Card]) -> Vec<u8> {
+    cards.iter().map(|c| c.to_u8()).collect()
+}
+
+/// Unpack bytes from `encode_u8`, failing on the first one that isn't a
+/// card.
+pub fn decode_u8(bytes: &[u8]) -> Result<Vec<Card>, RSPokerError> {
+    bytes.iter().map(|&b| Card::try_from_u8(b)).collect()
+}
+
+/// Pack each card into the Cactus Kev `u32` layout.
+pub fn encode_u32(cards: &[Card]) -> Vec<u32> {
+    cards.iter().map(|c| c.to_u32()).collect()
+}
+
+/// Unpack cards from `encode_u32`, failing on the first one that isn't a
+/// card.
+pub fn decode_u32(packed: &[u32]) -> Result<Vec<Card>, RSPokerError> {
+    packed.iter().map(|&p| Card::try_from_u32(p)).collect()
+}
+
+/// Pack `cards` into `out`, which must be the same length. For filling
+/// buffers owned by the caller, such as one mapped for a GPU, without
+/// allocating.
+///
+/// # Panics
+///
+/// If `out` is a different length to `cards`.
+pub fn encode_u8_into(cards: &[Card], out: &mut [u8]) {
+    assert_eq!(cards.len(), out.len(), "Output must hold every card");
+    for (o, c) in out.iter_mut().zip(cards) {
+        *o = c.to_u8();
+    }
+}
+
+/// Pack `cards` into `out` in the Cactus Kev `u32` layout. `out` must be
+/// the same length.
+///
+/// # Panics
+///
+/// If `out` is a different length to `cards`.
+pub fn encode_u32_into(cards: &[Card], out: &mut [u32]) {
+    assert_eq!(cards.len(), out.len(), "Output must hold every card");
+    for (o, c) in out.iter_mut().zip(cards) {
+        *o = c.to_u32();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Deck;
+
+    #[test]
+    fn test_round_trip_every_card() {
+        let cards: Vec<Card> = Deck::default().into_iter().collect();
+        assert_eq!(52, cards.len());
+        assert_eq!(cards, decode_u8(&encode_u8(&cards)).unwrap());
+        assert_eq!(cards, decode_u32(&encode_u32(&cards)).unwrap());
+
+        let mut bytes = vec![0; cards.len()];
+        encode_u8_into(&cards, &mut bytes);
+        assert_eq!(encode_u8(&cards), bytes);
+        let mut packed = vec![0; cards.len()];
+        encode_u32_into(&cards, &mut packed);
+        assert_eq!(encode_u32(&cards), packed);
+    }
+
+    #[test]
+    fn test_cactus_kev_layout() {
+        // The examples from the original description.
+        let king_diamonds = Card::new(Value::King, Suit::Diamond);
+        assert_eq!(0x0800_4b25, king_diamonds.to_u32());
+        let five_spades = Card::new(Value::Five, Suit::Spade);
+        assert_eq!(0x0008_1307, five_spades.to_u32());
+        let jack_clubs = Card::new(Value::Jack, Suit::Club);
+        assert_eq!(0x0200_891d, jack_clubs.to_u32());
+    }
+
+    #[test]
+    fn test_invalid_cards() {
+        assert!(Card::try_from_u8(52).is_err());
+        assert!(Card::try_from_u8(255).is_err());
+        assert!(Card::try_from_u32(0).is_err());
+        // Two suits set.
+        assert!(Card::try_from_u32(Card::from(0).to_u32() | 0x8000).is_err());
+        // The wrong prime.
+        assert!(Card::try_from_u32(Card::from(0).to_u32() ^ 1).is_err());
+        assert!(decode_u32(&[0x0800_4b25, 7]).is_err());
+    }
+
+    #[test]
+    fn test_hand_bits() {
+        let hand = Hand::new_from_str("AsKd2c").unwrap();
+        let bits = hand.to_u64();
+        assert_eq!(3, bits.count_ones());
+        assert_eq!(hand, Hand::try_from_u64(bits).unwrap());
+        assert!(Hand::try_from_u64(1 << 52).is_err());
+    }
+}
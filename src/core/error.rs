@@ -29,4 +29,8 @@ pub enum RSPokerError {
     InvalidGap,
     #[error("Pairs can't be suited.")]
     InvalidSuitedPairs,
+    #[error("{0:#x} isn't a packed card")]
+    InvalidPackedCard(u32),
+    #[error("{0:#x} isn't a packed hand")]
+    InvalidPackedHand(u64),
 }
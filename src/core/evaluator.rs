@@ -0,0 +1,163 @@
+use super::card::Card;
+use super::rank::{Rank, rank_five_from_value_sets, rank_from_value_sets};
+
+/// The most cards a `HandEvaluator` can rank. With more there can be a
+/// flush and a better hand at the same time.
+pub const MAX_EVALUATOR_CARDS: usize = 7;
+
+/// A hand that's ranked as it's dealt, card by card.
+///
+/// Each card added updates the sets of values held once, twice, three
+/// and four times and the values in each suit, which is all ranking needs.
+/// So ranking after the flop, turn and river costs a few bit operations
+/// each time rather than going over every card again. It's `Copy`, so the
+/// state after the hole cards or the flop can be kept and added to for each
+/// runout.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::core::{Card, HandEvaluator, Rank, Suit, Value};
+///
+/// let mut hole = HandEvaluator::new();
+/// hole.add(Card::new(Value::Ace, Suit::Spade));
+/// hole.add(Card::new(Value::Ace, Suit::Heart));
+/// assert!(matches!(hole.rank(), Rank::OnePair(_)));
+///
+/// let mut flop = hole;
+/// flop.extend([
+///     Card::new(Value::Ace, Suit::Club),
+///     Card::new(Value::Two, Suit::Heart),
+///     Card::new(Value::Two, Suit::Spade),
+/// ]);
+/// assert!(matches!(flop.rank(), Rank::FullHouse(_)));
+///
+/// // The hole cards are still there to deal a different flop to.
+/// assert_eq!(2, hole.len());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct HandEvaluator {
+    suit_value_sets: [u32; 4],
+    /// The values on at least one, two, three and four cards.
+    at_least: [u32; 4],
+    num_cards: u8,
+}
+
+impl HandEvaluator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a card. Returns false, leaving the hand as it was, if the card
+    /// is already in it.
+    ///
+    /// # Panics
+    ///
+    /// If the hand already has `MAX_EVALUATOR_CARDS` cards.
+    pub fn add(&mut self, card: Card) -> bool {
+        let bit = 1 << card.value as u32;
+        let suit = &mut self.suit_value_sets[card.suit as usize];
+        if *suit & bit != 0 {
+            return false;
+        }
+        assert!(
+            (self.num_cards as usize) < MAX_EVALUATOR_CARDS,
+            "Can't rank more than {MAX_EVALUATOR_CARDS} cards"
+        );
+        *suit |= bit;
+        // The value is now on one more card than it was.
+        if let Some(level) = self.at_least.iter_mut().find(|l| **l & bit == 0) {
+            *level |= bit;
+        }
+        self.num_cards += 1;
+        true
+    }
+
+    pub fn contains(&self, card: &Card) -> bool {
+        self.suit_value_sets[card.suit as usize] & (1 << card.value as u32) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.num_cards as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_cards == 0
+    }
+
+    /// The best five card hand out of the cards added so far. Fewer than
+    /// five cards are ranked on what's there, as `Rankable` does.
+    pub fn rank(&self) -> Rank {
+        let [one, two, three, four] = self.at_least;
+        let count_to_value = [0, one ^ two, two ^ three, three ^ four, four];
+        if self.num_cards == 5 {
+            rank_five_from_value_sets(&count_to_value, &self.suit_value_sets, one)
+        } else {
+            rank_from_value_sets(&count_to_value, &self.suit_value_sets, one)
+        }
+    }
+}
+
+impl Extend<Card> for HandEvaluator {
+    fn extend<T: IntoIterator<Item = Card>>(&mut self, iter: T) {
+        for card in iter {
+            self.add(card);
+        }
+    }
+}
+
+impl FromIterator<Card> for HandEvaluator {
+    fn from_iter<T: IntoIterator<Item = Card>>(iter: T) -> Self {
+        let mut evaluator = Self::new();
+        evaluator.extend(iter);
+        evaluator
+    }
+}
+
+impl From<&[Card]> for HandEvaluator {
+    fn from(cards: &[Card]) -> Self {
+        cards.iter().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+
+    use super::*;
+    use crate::core::{Deck, Rankable, Suit, Value};
+
+    #[test]
+    fn test_matches_rankable_on_every_street() {
+        let mut rng = rand::rng();
+        let mut cards: Vec<Card> = Deck::default().into_iter().collect();
+        for _ in 0..5_000 {
+            cards.shuffle(&mut rng);
+            let mut evaluator = HandEvaluator::new();
+            for (i, card) in cards[..MAX_EVALUATOR_CARDS].iter().enumerate() {
+                assert!(evaluator.add(*card));
+                assert_eq!(i + 1, evaluator.len());
+                let dealt = &cards[..=i];
+                assert_eq!(dealt.rank(), evaluator.rank(), "{dealt:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_duplicate_card_is_ignored() {
+        let ace = Card::new(Value::Ace, Suit::Spade);
+        let mut evaluator = HandEvaluator::new();
+        assert!(evaluator.add(ace));
+        assert!(!evaluator.add(ace));
+        assert_eq!(1, evaluator.len());
+        assert!(evaluator.contains(&ace));
+        assert!(!evaluator.contains(&Card::new(Value::Ace, Suit::Heart)));
+        assert_eq!(Rank::HighCard(1 << Value::Ace as u32), evaluator.rank());
+    }
+
+    #[test]
+    #[should_panic(expected = "more than 7 cards")]
+    fn test_too_many_cards() {
+        let _: HandEvaluator = Deck::default().into_iter().take(8).collect();
+    }
+}
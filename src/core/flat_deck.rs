@@ -1,16 +1,17 @@
 use crate::core::card::Card;
 use crate::core::deck::Deck;
+use crate::core::rng;
 use std::ops::{Index, Range, RangeFrom, RangeFull, RangeTo};
 
 extern crate rand;
 use rand::Rng;
-use rand::rng;
 use rand::seq::{IndexedRandom, SliceRandom};
 
 /// `FlatDeck` is a deck of cards that allows easy
 /// indexing into the cards. It does not provide
 /// contains methods.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FlatDeck {
     /// Card storage.
     cards: Vec<Card>,
@@ -166,6 +166,12 @@ impl From<Hand> for CardBitSet {
     }
 }
 
+impl From<CardBitSet> for Hand {
+    fn from(cards: CardBitSet) -> Self {
+        Self(cards)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
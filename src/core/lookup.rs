@@ -0,0 +1,270 @@
+//! Precomputed rank tables for 5, 6 and 7 card hands.
+//!
+//! Without a flush the rank only depends on how many cards there are of
+//! each value. Every value has a key, chosen so that adding up the keys of
+//! the cards gives a different sum for every possible set of values. A
+//! perfect hash maps those sums to slots in a table of ranks, so ranking a
+//! hand is one pass over the cards and a couple of lookups. Flushes only
+//! depend on the values in the flush suit, which index a second table
+//! directly.
+//!
+//! The tables take under a megabyte and are built the first time they're
+//! needed, or by calling `init_lookup_tables` up front to keep that out of
+//! a timed section.
+use std::sync::OnceLock;
+
+use super::Card;
+use super::rank::{Rank, Rankable, rank_from_counts};
+
+/// The fewest and most cards there are tables for.
+const MIN_CARDS: u32 = 5;
+const MAX_CARDS: u32 = 7;
+
+/// Added to the key of every card. It's larger than any sum of value keys
+/// so the number of cards can be read back from the top bits.
+const CARD_BIT: u32 = 23;
+
+/// The keys of each value. The sums of 5, 6 or 7 of them, with each value
+/// used at most four times, are all different.
+const VALUE_KEYS: [u32; 13] = [
+    0, 1, 5, 22, 98, 453, 2031, 8698, 22854, 83661, 262349, 636345, 1479181,
+];
+
+/// Sums are hashed into this many buckets, each with its own
+/// displacement, to find their slot.
+const BUCKET_BITS: u32 = 14;
+
+struct Tables {
+    /// Indexed by the set of values in the flush suit.
+    flush: Vec<Rank>,
+    /// Mixed into the sum before it's hashed to a slot, per bucket.
+    displacements: Vec<u32>,
+    no_flush: Vec<Rank>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+impl Tables {
+    fn new() -> Self {
+        let flush = (0..1 << 13)
+            .map(|values: u32| {
+                if values.count_ones() >= 5 {
+                    let mut counts = [0; 13];
+                    for (value, count) in counts.iter_mut().enumerate() {
+                        *count = ((values >> value) & 1) as u8;
+                    }
+                    rank_from_counts(&counts, &[values, 0, 0, 0], values)
+                } else {
+                    // There's no flush with fewer than five cards.
+                    Rank::HighCard(0)
+                }
+            })
+            .collect();
+
+        let mut entries = vec![];
+        for num_cards in MIN_CARDS..=MAX_CARDS {
+            fill(&mut [0; 13], 0, num_cards, num_cards, &mut entries);
+        }
+        let (displacements, no_flush) = perfect_hash(entries);
+        Self {
+            flush,
+            displacements,
+            no_flush,
+        }
+    }
+
+    fn get(&self, key: u32) -> Rank {
+        let displacement = self.displacements[bucket(key)];
+        self.no_flush[slot(key, displacement, self.no_flush.len())]
+    }
+}
+
+/// The key of `num_cards` cards whose value keys add up to `sum`, counted
+/// from the smallest hand in the tables.
+fn table_key(sum: u32, num_cards: u32) -> u32 {
+    sum + ((num_cards - MIN_CARDS) << CARD_BIT)
+}
+
+/// Visit every way to give the values from `value` on counts that add up
+/// to `remaining`, and record their key and rank.
+fn fill(
+    counts: &mut [u8; 13],
+    value: usize,
+    remaining: u32,
+    num_cards: u32,
+    entries: &mut Vec<(u32, Rank)>,
+) {
+    if value == 13 {
+        if remaining == 0 {
+            let (sum, value_set) =
+                counts
+                    .iter()
+                    .enumerate()
+                    .fold((0, 0), |(sum, value_set), (value, &count)| {
+                        let value_set = if count > 0 {
+                            value_set | 1 << value
+                        } else {
+                            value_set
+                        };
+                        (sum + count as u32 * VALUE_KEYS[value], value_set)
+                    });
+            let rank = rank_from_counts(counts, &[0; 4], value_set);
+            entries.push((table_key(sum, num_cards), rank));
+        }
+        return;
+    }
+    for count in 0..=remaining.min(4) {
+        counts[value] = count as u8;
+        fill(counts, value + 1, remaining - count, num_cards, entries);
+    }
+    counts[value] = 0;
+}
+
+/// Scramble the bits of `x`, so that sums that are close together end up
+/// far apart.
+fn mix(x: u32) -> u32 {
+    let x = (x ^ (x >> 16)).wrapping_mul(0x7feb_352d);
+    let x = (x ^ (x >> 15)).wrapping_mul(0x846c_a68b);
+    x ^ (x >> 16)
+}
+
+fn bucket(key: u32) -> usize {
+    (mix(key) >> (32 - BUCKET_BITS)) as usize
+}
+
+fn slot(key: u32, displacement: u32, len: usize) -> usize {
+    ((mix(key ^ displacement) as u64 * len as u64) >> 32) as usize
+}
+
+/// Give every bucket of keys a displacement so that no two keys share a
+/// slot. The fullest buckets are placed first, while there's the most
+/// room, each with the first displacement that fits.
+fn perfect_hash(entries: Vec<(u32, Rank)>) -> (Vec<u32>, Vec<Rank>) {
+    let len = entries.len() + entries.len() / 10;
+    let mut buckets: Vec<Vec<(u32, Rank)>> = vec![vec![]; 1 << BUCKET_BITS];
+    for (key, rank) in entries {
+        buckets[bucket(key)].push((key, rank));
+    }
+    let mut order: Vec<usize> = (0..buckets.len()).collect();
+    order.sort_by_key(|&bucket| std::cmp::Reverse(buckets[bucket].len()));
+
+    let mut displacements = vec![0; buckets.len()];
+    let mut table: Vec<Option<Rank>> = vec![None; len];
+    let mut slots = vec![];
+    for idx in order {
+        let bucket = &buckets[idx];
+        let displacement = (0..)
+            .find(|&displacement| {
+                slots.clear();
+                slots.extend(bucket.iter().map(|(key, _)| slot(*key, displacement, len)));
+                slots.sort_unstable();
+                slots.windows(2).all(|pair| pair[0] != pair[1])
+                    && slots.iter().all(|&slot| table[slot].is_none())
+            })
+            .expect("Some displacement fits");
+        for (key, rank) in bucket {
+            table[slot(*key, displacement, len)] = Some(*rank);
+        }
+        displacements[idx] = displacement;
+    }
+    let table = table
+        .into_iter()
+        .map(|rank| rank.unwrap_or(Rank::HighCard(0)))
+        .collect();
+    (displacements, table)
+}
+
+/// Build the lookup tables now rather than on the first rank.
+pub fn init_lookup_tables() {
+    TABLES.get_or_init(Tables::new);
+}
+
+/// The rank of 5 to 7 cards from the tables, or `None` for any other number
+/// of cards.
+pub(crate) fn lookup<R: Rankable + ?Sized>(hand: &R) -> Option<Rank> {
+    let mut key: u32 = 0;
+    // Four bits per suit, counting the cards of that suit.
+    let mut suit_counts: u32 = 0;
+    for card in hand.cards() {
+        key = key.wrapping_add(VALUE_KEYS[card.value as usize] + (1 << CARD_BIT));
+        suit_counts += 1 << (4 * card.suit as u32);
+    }
+
+    let num_cards = key >> CARD_BIT;
+    if !(MIN_CARDS..=MAX_CARDS).contains(&num_cards) {
+        return None;
+    }
+    let tables = TABLES.get_or_init(Tables::new);
+
+    // Adding three carries into the top bit of a suit's count when there
+    // are at least five cards of that suit.
+    let flush = (suit_counts + 0x3333) & 0x8888;
+    if flush != 0 {
+        let suit = flush.trailing_zeros() / 4;
+        let values = hand
+            .cards()
+            .filter(|card| card.suit as u32 == suit)
+            .fold(0, |values, card: Card| values | 1 << card.value as u32);
+        return Some(tables.flush[values as usize]);
+    }
+    Some(tables.get(key - (MIN_CARDS << CARD_BIT)))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+
+    use super::*;
+    use crate::core::{Deck, FlatHand, Suit, Value};
+
+    #[test]
+    fn test_matches_rank_from_counts() {
+        let mut rng = rand::rng();
+        let mut cards: Vec<Card> = Deck::default().into_iter().collect();
+        for _ in 0..20_000 {
+            cards.shuffle(&mut rng);
+            for num_cards in MIN_CARDS as usize..=MAX_CARDS as usize {
+                let hand = &cards[..num_cards];
+                let mut value_to_count = [0; 13];
+                let mut suit_value_sets = [0; 4];
+                let mut value_set = 0;
+                for card in hand {
+                    value_to_count[card.value as usize] += 1;
+                    suit_value_sets[card.suit as usize] |= 1 << card.value as u32;
+                    value_set |= 1 << card.value as u32;
+                }
+                assert_eq!(
+                    Some(rank_from_counts(
+                        &value_to_count,
+                        &suit_value_sets,
+                        value_set
+                    )),
+                    lookup(hand),
+                    "{hand:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let hand = FlatHand::new_from_str("AdKdQdJdTd9d8d").unwrap();
+        assert_eq!(Rank::StraightFlush(9), hand.rank());
+        let hand = FlatHand::new_from_str("2c2d2h2s3c3d").unwrap();
+        assert_eq!(Rank::FourOfAKind((1 << 13) | (1 << 1)), hand.rank());
+
+        // Too few cards for the tables.
+        let cards = [Card::new(Value::Ace, Suit::Heart)];
+        assert_eq!(None, lookup(&cards[..]));
+        assert_eq!(Rank::HighCard(1 << 12), cards.rank());
+    }
+
+    #[test]
+    fn test_table_size() {
+        init_lookup_tables();
+        let tables = TABLES.get().unwrap();
+        // Every hand without a flush has a slot, with little wasted space.
+        let num_hands = 6175 + 18395 + 49205;
+        assert_eq!(num_hands + num_hands / 10, tables.no_flush.len());
+    }
+}
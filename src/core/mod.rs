@@ -32,10 +32,41 @@ mod flat_deck;
 /// Export the trait and the result.
 pub use self::flat_deck::FlatDeck;
 
+/// The crate wide, seedable rng.
+mod rng;
+pub use self::rng::{CrateRng, rng, with_rng, with_seed};
+
 /// 5 Card hand ranking code.
 mod rank;
 /// Export the trait and the results.
-pub use self::rank::{Rank, Rankable};
+pub use self::rank::{Rank, Rankable, rank_five_cards, rank_seven_cards, rank_six_cards};
+
+/// Packed integer encodings for cards and hands.
+pub mod codec;
+
+/// `Arbitrary` for cards and sets of cards.
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+/// `proptest` strategies for cards and sets of cards.
+#[cfg(feature = "proptest")]
+pub mod strategy;
+
+/// Ranking hands as cards are dealt.
+mod evaluator;
+pub use self::evaluator::{HandEvaluator, MAX_EVALUATOR_CARDS};
+
+/// Omaha hand ranking, two cards from the hand and three from the board,
+/// and eight or better lows for the hi-lo games.
+mod omaha;
+pub use self::omaha::{
+    HiLoRank, LowRank, rank_eight_or_better, rank_omaha, rank_omaha_hi_lo, rank_omaha_low,
+};
+
+/// Precomputed rank tables.
+#[cfg(feature = "lookup-tables")]
+mod lookup;
+#[cfg(feature = "lookup-tables")]
+pub use self::lookup::init_lookup_tables;
 
 // u16 backed player set.
 mod player_bit_set;
@@ -44,4 +75,4 @@ mod card_bit_set;
 // Export the bit set and the iterator
 pub use self::player_bit_set::{ActivePlayerBitSetIter, PlayerBitSet};
 // Export the bit set and the iterator used for cards (52 cards so u64 backed)
-pub use self::card_bit_set::{CardBitSet, CardBitSetIter};
+pub use self::card_bit_set::{CardBitSet, CardBitSetIter, CardSet};
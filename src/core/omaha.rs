@@ -0,0 +1,266 @@
+use super::card::{Card, Value};
+use super::rank::{Rank, rank_five_cards};
+
+/// An eight or better low: five cards of different values, all eight or
+/// under, with aces low. Straights and flushes don't count against it.
+///
+/// Lower is better, so the wheel, 5-4-3-2-A, is the smallest `LowRank`.
+/// Hands are compared from their highest card down, so 7-5-4-3-2 beats
+/// 7-6-3-2-A.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Copy)]
+pub struct LowRank(u32);
+
+impl LowRank {
+    /// The five values of the low, highest first, with the ace as 1 and
+    /// the rest as their pip count.
+    pub fn values(&self) -> [u8; 5] {
+        let mut values = [0; 5];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = (self.0 >> (4 * (4 - i)) & 0xf) as u8;
+        }
+        values
+    }
+}
+
+/// A hi-lo hand: the high hand, and the low if it qualifies.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Copy)]
+pub struct HiLoRank {
+    pub high: Rank,
+    pub low: Option<LowRank>,
+}
+
+/// A value counted the low way, aces as 1.
+fn low_value(value: Value) -> Option<u32> {
+    match value {
+        Value::Ace => Some(1),
+        value if value <= Value::Eight => Some(value as u32 + 2),
+        _ => None,
+    }
+}
+
+/// The best eight or better low out of `cards`, any five of them, or
+/// `None` if there aren't five different values eight or under.
+///
+/// ```
+/// use rs_poker::core::{Card, Hand, rank_eight_or_better};
+///
+/// let cards = |cards| {
+///     Hand::new_from_str(cards)
+///         .unwrap()
+///         .iter()
+///         .collect::<Vec<Card>>()
+/// };
+/// let wheel = rank_eight_or_better(&cards("As2d3c4h5s")).unwrap();
+/// let seven = rank_eight_or_better(&cards("7s5d4c3h2sKd")).unwrap();
+/// assert!(wheel < seven);
+/// assert_eq!([7, 5, 4, 3, 2], seven.values());
+/// assert_eq!(None, rank_eight_or_better(&cards("As2d3c4h9s")));
+/// ```
+pub fn rank_eight_or_better(cards: &[Card]) -> Option<LowRank> {
+    let values = cards
+        .iter()
+        .filter_map(|card| low_value(card.value))
+        .fold(0u32, |set, value| set | (1 << value));
+    if values.count_ones() < 5 {
+        return None;
+    }
+    // The five lowest values, added highest first.
+    let lowest: Vec<u32> = (1..=8).filter(|v| values & (1 << v) != 0).take(5).collect();
+    Some(LowRank(
+        lowest.iter().rev().fold(0, |low, v| (low << 4) | v),
+    ))
+}
+
+/// Rank an Omaha hand: the best five card hand made from exactly two of
+/// the `hole` cards and exactly three of the `board` cards.
+///
+/// Any number of hole cards works, so five and six card Omaha too. `None`
+/// if there are fewer than two hole cards or three board cards to pick
+/// from.
+///
+/// Unlike hold'em the rest of the cards don't play, so four hearts on the
+/// board is no flush without two hearts in the hand, and a single ace of
+/// hearts in the hand doesn't make one either.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::core::{Hand, Rank, Rankable, rank_omaha};
+///
+/// let hole: Vec<_> = Hand::new_from_str("AhKsQd2c").unwrap().iter().collect();
+/// let board: Vec<_> = Hand::new_from_str("JhTh9h3h8s").unwrap().iter().collect();
+///
+/// // Hold'em rules would give a flush with the ace of hearts.
+/// let mut holdem = hole.clone();
+/// holdem.extend(&board);
+/// assert!(matches!(holdem.rank(), Rank::Flush(_)));
+///
+/// // In Omaha it's the king high straight using KQ and JT9.
+/// assert!(matches!(rank_omaha(&hole, &board), Some(Rank::Straight(_))));
+/// assert_eq!(None, rank_omaha(&hole, &board[..2]));
+/// ```
+pub fn rank_omaha(hole: &[Card], board: &[Card]) -> Option<Rank> {
+    let mut best = None;
+    for_each_omaha_hand(hole, board, |five| {
+        let rank = rank_five_cards(five);
+        if best.is_none_or(|best| rank > best) {
+            best = Some(rank);
+        }
+    });
+    best
+}
+
+/// The best eight or better Omaha low, from exactly two of the `hole`
+/// cards and exactly three of the `board` cards. `None` if no such five
+/// cards make a low, or there aren't enough cards.
+pub fn rank_omaha_low(hole: &[Card], board: &[Card]) -> Option<LowRank> {
+    let mut best = None;
+    for_each_omaha_hand(hole, board, |five| {
+        if let Some(low) = rank_eight_or_better(five)
+            && best.is_none_or(|best| low < best)
+        {
+            best = Some(low);
+        }
+    });
+    best
+}
+
+/// Rank an Omaha hi-lo hand, the high hand as `rank_omaha` and the low as
+/// `rank_omaha_low`. Each half can use different cards.
+///
+/// ```
+/// use rs_poker::core::{Card, Hand, Rank, rank_omaha_hi_lo};
+///
+/// let cards = |cards| {
+///     Hand::new_from_str(cards)
+///         .unwrap()
+///         .iter()
+///         .collect::<Vec<Card>>()
+/// };
+/// let rank = rank_omaha_hi_lo(&cards("As2sKdKc"), &cards("Ks7d5c4h9s")).unwrap();
+/// assert!(matches!(rank.high, Rank::ThreeOfAKind(_)));
+/// assert_eq!([7, 5, 4, 2, 1], rank.low.unwrap().values());
+///
+/// // No low when the board has fewer than three cards eight or under.
+/// let rank = rank_omaha_hi_lo(&cards("As2sKdKc"), &cards("Ks7dQc4h9s")).unwrap();
+/// assert_eq!(None, rank.low);
+/// ```
+pub fn rank_omaha_hi_lo(hole: &[Card], board: &[Card]) -> Option<HiLoRank> {
+    Some(HiLoRank {
+        high: rank_omaha(hole, board)?,
+        low: rank_omaha_low(hole, board),
+    })
+}
+
+/// Call `f` with every five cards made of two from `hole` and three from
+/// `board`.
+fn for_each_omaha_hand(hole: &[Card], board: &[Card], mut f: impl FnMut(&[Card; 5])) {
+    for (i, first) in hole.iter().enumerate() {
+        for second in &hole[i + 1..] {
+            for (j, a) in board.iter().enumerate() {
+                for (k, b) in board.iter().enumerate().skip(j + 1) {
+                    for c in &board[k + 1..] {
+                        f(&[*first, *second, *a, *b, *c]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+
+    use super::*;
+    use crate::core::{Deck, Hand, Rankable};
+
+    fn cards(cards: &str) -> Vec<Card> {
+        Hand::new_from_str(cards).unwrap().iter().collect()
+    }
+
+    fn rank(hole: &str, board: &str) -> Rank {
+        rank_omaha(&cards(hole), &cards(board)).unwrap()
+    }
+
+    #[test]
+    fn test_exactly_two_and_three() {
+        // Quads on the board only play as trips with a pair.
+        assert!(matches!(
+            rank("AsKd7c2h", "9s9d9c9h3s"),
+            Rank::ThreeOfAKind(_)
+        ));
+        assert!(matches!(rank("AsAd7c2h", "9s9d9c9h3s"), Rank::FullHouse(_)));
+        // One heart in the hand doesn't make a flush.
+        assert!(matches!(rank("AhKsQd2c", "JhTh9h3h2s"), Rank::Straight(_)));
+        // Four to a straight in the hand needs three on the board.
+        assert!(matches!(rank("JsTd9c8h", "2s3d7cKhAs"), Rank::HighCard(_)));
+        assert!(matches!(rank("JsTd9c8h", "2s5d7c6hAs"), Rank::Straight(_)));
+        // Trips in the hand are only a pair.
+        assert!(matches!(rank("KsKdKc2h", "AsQd7c5h3s"), Rank::OnePair(_)));
+    }
+
+    #[test]
+    fn test_best_of_every_pick() {
+        let mut rng = rand::rng();
+        let mut deck: Vec<Card> = Deck::default().into_iter().collect();
+        for _ in 0..500 {
+            deck.shuffle(&mut rng);
+            let (hole, board) = (&deck[..4], &deck[4..9]);
+            let mut best = None;
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    for a in 0..5 {
+                        for b in a + 1..5 {
+                            for c in b + 1..5 {
+                                let five = [hole[i], hole[j], board[a], board[b], board[c]];
+                                best = best.max(Some(five[..].rank()));
+                            }
+                        }
+                    }
+                }
+            }
+            assert_eq!(best, rank_omaha(hole, board));
+        }
+    }
+
+    #[test]
+    fn test_eight_or_better() {
+        let low = |cards_str| rank_eight_or_better(&cards(cards_str)).map(|low| low.values());
+        assert_eq!(Some([5, 4, 3, 2, 1]), low("As2d3c4h5s"));
+        // Pairs are skipped and the lowest five kept.
+        assert_eq!(Some([6, 4, 3, 2, 1]), low("As2d3c4h6sAd8c"));
+        assert_eq!(None, low("As2d3c4h9s"));
+        assert_eq!(None, low("As2d3c4hAd"));
+        // From the top card down.
+        let rank = |cards_str| rank_eight_or_better(&cards(cards_str)).unwrap();
+        assert!(rank("7s5d4c3h2s") < rank("7s6d3c2hAs"));
+        assert!(rank("8s4d3c2hAs") > rank("7s6d5c4h3s"));
+    }
+
+    #[test]
+    fn test_omaha_low() {
+        let low = |hole, board| rank_omaha_low(&cards(hole), &cards(board)).map(|low| low.values());
+        assert_eq!(Some([7, 6, 5, 2, 1]), low("As2s3d4d", "5c6h7hKsQs"));
+        // A pair in the hand is one low card.
+        assert_eq!(None, low("2s2dKcKh", "3c4d5h9sTs"));
+        // Two low cards on the board aren't enough.
+        assert_eq!(None, low("As2s3d4d", "5c6hJhKsQs"));
+        // The ace on the board doesn't count twice.
+        assert_eq!(Some([8, 4, 3, 2, 1]), low("Ad2sKcKh", "As3c4d8hKs"));
+
+        let rank = rank_omaha_hi_lo(&cards("As2s3d4d"), &cards("5c6h7hKsQs")).unwrap();
+        assert!(matches!(rank.high, Rank::Straight(_)));
+        assert!(rank.low.is_some());
+    }
+
+    #[test]
+    fn test_too_few_cards() {
+        assert_eq!(None, rank_omaha(&cards("As"), &cards("KsQsJs")));
+        assert_eq!(None, rank_omaha(&cards("AsAd"), &cards("KsQs")));
+        assert!(rank_omaha(&cards("AsAd"), &cards("KsQsJs")).is_some());
+        assert_eq!(None, rank_omaha_hi_lo(&cards("As2d"), &cards("3s4s")));
+    }
+}
@@ -75,9 +75,10 @@ fn rank_straight(value_set: u32) -> Option<u32> {
         None
     }
 }
-/// Keep only the most significant bit.
+/// Keep only the most significant bit, or nothing if there are no bits
+/// set, as with two pair or quads in four cards.
 fn keep_highest(rank: u32) -> u32 {
-    1 << (32 - rank.leading_zeros() - 1)
+    (1_u32 << 31).checked_shr(rank.leading_zeros()).unwrap_or(0)
 }
 /// Keep the N most significant bits.
 ///
@@ -94,6 +95,187 @@ fn keep_n(rank: u32, to_keep: u32) -> u32 {
 fn find_flush(suit_value_sets: &[u32]) -> Option<usize> {
     suit_value_sets.iter().position(|sv| sv.count_ones() >= 5)
 }
+/// Find the best 5 card hand from the counts of each value and the set of
+/// values in each suit. This is correct for up to 7 cards, with more there
+/// can be a flush and a better hand at the same time.
+pub(crate) fn rank_from_counts(
+    value_to_count: &[u8; 13],
+    suit_value_sets: &[u32; 4],
+    value_set: u32,
+) -> Rank {
+    let mut count_to_value: [u32; 5] = [0, 0, 0, 0, 0];
+    // Now rotate the value to count map.
+    for (value, &count) in value_to_count.iter().enumerate() {
+        count_to_value[count as usize] |= 1 << value;
+    }
+    rank_from_value_sets(&count_to_value, suit_value_sets, value_set)
+}
+
+/// Rank exactly `N` distinct cards, 5, 6 or 7 of them, from the set of
+/// values in each suit.
+///
+/// Rather than counting each value, the values that appear two, three and
+/// four times come straight out of the suit sets: a value is on at least
+/// two cards if it's in two of the suits, and so on. That's a handful of
+/// bit operations with no loop over the values, and for five cards the
+/// best hand is all of them so nothing has to be dropped.
+#[inline]
+fn rank_suits<const N: u32>(suit_value_sets: &[u32; 4]) -> Rank {
+    let [a, b, c, d] = *suit_value_sets;
+    let value_set = a | b | c | d;
+    let two_or_more = (a & b) | (c & d) | ((a | b) & (c | d));
+    let three_or_more = (a & b & (c | d)) | (c & d & (a | b));
+    let four = a & b & c & d;
+    let count_to_value = [
+        0,
+        value_set ^ two_or_more,
+        two_or_more ^ three_or_more,
+        three_or_more ^ four,
+        four,
+    ];
+    if N == 5 {
+        rank_five_from_value_sets(&count_to_value, suit_value_sets, value_set)
+    } else {
+        rank_from_value_sets(&count_to_value, suit_value_sets, value_set)
+    }
+}
+
+/// The rank of exactly five cards, where every card plays so there's
+/// nothing to choose between.
+#[inline]
+pub(super) fn rank_five_from_value_sets(
+    count_to_value: &[u32; 5],
+    suit_value_sets: &[u32; 4],
+    value_set: u32,
+) -> Rank {
+    match value_set.count_ones() {
+        5 => {
+            let is_flush = suit_value_sets.contains(&value_set);
+            match (rank_straight(value_set), is_flush) {
+                (None, false) => Rank::HighCard(value_set),
+                (Some(rank), false) => Rank::Straight(rank),
+                (None, true) => Rank::Flush(value_set),
+                (Some(rank), true) => Rank::StraightFlush(rank),
+            }
+        }
+        4 => Rank::OnePair((count_to_value[2] << 13) | count_to_value[1]),
+        3 if count_to_value[3] != 0 => {
+            Rank::ThreeOfAKind((count_to_value[3] << 13) | count_to_value[1])
+        }
+        3 => Rank::TwoPair((count_to_value[2] << 13) | count_to_value[1]),
+        _ if count_to_value[4] != 0 => {
+            Rank::FourOfAKind((count_to_value[4] << 13) | count_to_value[1])
+        }
+        _ => Rank::FullHouse((count_to_value[3] << 13) | count_to_value[2]),
+    }
+}
+
+/// Rank exactly five cards. The cards must all be different.
+///
+/// ```
+/// use rs_poker::core::{Card, Rank, Rankable, Suit, Value, rank_five_cards};
+///
+/// let cards = [
+///     Card::new(Value::Ace, Suit::Spade),
+///     Card::new(Value::Ace, Suit::Heart),
+///     Card::new(Value::King, Suit::Spade),
+///     Card::new(Value::King, Suit::Heart),
+///     Card::new(Value::King, Suit::Club),
+/// ];
+/// assert_eq!(cards[..].rank(), rank_five_cards(&cards));
+/// assert!(matches!(rank_five_cards(&cards), Rank::FullHouse(_)));
+/// ```
+pub fn rank_five_cards(cards: &[Card; 5]) -> Rank {
+    rank_suits::<5>(&suit_value_sets(cards))
+}
+
+/// Find the best five card hand out of exactly six different cards.
+pub fn rank_six_cards(cards: &[Card; 6]) -> Rank {
+    rank_suits::<6>(&suit_value_sets(cards))
+}
+
+/// Find the best five card hand out of exactly seven different cards, as
+/// in a hold'em hand with two hole cards and a full board.
+pub fn rank_seven_cards(cards: &[Card; 7]) -> Rank {
+    rank_suits::<7>(&suit_value_sets(cards))
+}
+
+#[inline]
+fn suit_value_sets(cards: &[Card]) -> [u32; 4] {
+    let mut suit_value_sets = [0; 4];
+    for card in cards {
+        suit_value_sets[card.suit as usize] |= 1 << card.value as u32;
+    }
+    suit_value_sets
+}
+
+/// The best 5 card hand given the set of values that appear once, twice,
+/// three times and four times (indexed by count) and the values in each
+/// suit.
+#[inline]
+pub(super) fn rank_from_value_sets(
+    count_to_value: &[u32; 5],
+    suit_value_sets: &[u32; 4],
+    value_set: u32,
+) -> Rank {
+    // Find out if there's a flush
+    let flush: Option<usize> = find_flush(suit_value_sets);
+
+    // If this is a flush then it could be a straight flush
+    // or a flush. So check only once.
+    if let Some(flush_idx) = flush {
+        // If we can find a straight in the flush then it's a straight flush
+        if let Some(rank) = rank_straight(suit_value_sets[flush_idx]) {
+            Rank::StraightFlush(rank)
+        } else {
+            // Else it's just a normal flush
+            let rank = keep_n(suit_value_sets[flush_idx], 5);
+            Rank::Flush(rank)
+        }
+    } else if count_to_value[4] != 0 {
+        // Four of a kind.
+        let high = keep_highest(value_set ^ count_to_value[4]);
+        Rank::FourOfAKind((count_to_value[4] << 13) | high)
+    } else if count_to_value[3] != 0 && count_to_value[3].count_ones() == 2 {
+        // There are two sets. So the best we can make is a full house.
+        let set = keep_highest(count_to_value[3]);
+        let pair = count_to_value[3] ^ set;
+        Rank::FullHouse((set << 13) | pair)
+    } else if count_to_value[3] != 0 && count_to_value[2] != 0 {
+        // there is a pair and a set.
+        let set = count_to_value[3];
+        let pair = keep_highest(count_to_value[2]);
+        Rank::FullHouse((set << 13) | pair)
+    } else if let Some(s_rank) = rank_straight(value_set) {
+        // If there's a straight return it now.
+        Rank::Straight(s_rank)
+    } else if count_to_value[3] != 0 {
+        // if there is a set then we need to keep 2 cards that
+        // aren't in the set.
+        let low = keep_n(value_set ^ count_to_value[3], 2);
+        Rank::ThreeOfAKind((count_to_value[3] << 13) | low)
+    } else if count_to_value[2].count_ones() >= 2 {
+        // Two pair
+        //
+        // That can be because we have 3 pairs and a high card.
+        // Or we could have two pair and two high cards.
+        let pairs = keep_n(count_to_value[2], 2);
+        let low = keep_highest(value_set ^ pairs);
+        Rank::TwoPair((pairs << 13) | low)
+    } else if count_to_value[2] == 0 {
+        // This means that there's no pair
+        // no sets, no straights, no flushes, so only a
+        // high card.
+        Rank::HighCard(keep_n(value_set, 5))
+    } else {
+        // Otherwise there's only one pair.
+        let pair = count_to_value[2];
+        // Keep the highest three cards not in the pair.
+        let low = keep_n(value_set ^ count_to_value[2], 3);
+        Rank::OnePair((pair << 13) | low)
+    }
+}
+
 /// Can this turn into a hand rank? There are default implementations for
 /// `Hand` and `Vec<Card>`.
 pub trait Rankable {
@@ -103,8 +285,9 @@ pub trait Rankable {
 
     /// Rank the cards to find the best 5 card hand.
     /// This will work on 5 cards or more (specifically on 7 card holdem
-    /// hands). If you know that the hand only contains 5 cards then
-    /// `rank_five` will be faster.
+    /// hands). Five, six and seven different cards are ranked by their own
+    /// faster paths, see `rank_five_cards`, `rank_six_cards` and
+    /// `rank_seven_cards`.
     ///
     /// # Examples
     /// ```
@@ -116,8 +299,36 @@ pub trait Rankable {
     /// assert!(Rank::TwoPair(u32::max_value()) >= rank);
     /// ```
     fn rank(&self) -> Rank {
+        #[cfg(feature = "lookup-tables")]
+        if let Some(rank) = super::lookup::lookup(self) {
+            return rank;
+        }
+
+        // Five, six and seven different cards have their own faster paths.
+        // Sixteen bits per suit hold the values in that suit, and the
+        // number of bits set is the number of different cards.
+        let mut cards: u64 = 0;
+        let mut num_cards = 0;
+        for c in self.cards() {
+            cards |= 1 << (16 * c.suit as u32 + c.value as u32);
+            num_cards += 1;
+        }
+        if cards.count_ones() == num_cards {
+            let suit_value_sets = [
+                cards as u32 & 0xffff,
+                (cards >> 16) as u32 & 0xffff,
+                (cards >> 32) as u32 & 0xffff,
+                (cards >> 48) as u32,
+            ];
+            match num_cards {
+                5 => return rank_suits::<5>(&suit_value_sets),
+                6 => return rank_suits::<6>(&suit_value_sets),
+                7 => return rank_suits::<7>(&suit_value_sets),
+                _ => {}
+            }
+        }
+
         let mut value_to_count: [u8; 13] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
-        let mut count_to_value: [u32; 5] = [0, 0, 0, 0, 0];
         let mut suit_value_sets: [u32; 4] = [0, 0, 0, 0];
         let mut value_set: u32 = 0;
 
@@ -129,67 +340,7 @@ pub trait Rankable {
             suit_value_sets[s as usize] |= 1 << v;
         }
 
-        // Now rotate the value to count map.
-        for (value, &count) in value_to_count.iter().enumerate() {
-            count_to_value[count as usize] |= 1 << value;
-        }
-
-        // Find out if there's a flush
-        let flush: Option<usize> = find_flush(&suit_value_sets);
-
-        // If this is a flush then it could be a straight flush
-        // or a flush. So check only once.
-        if let Some(flush_idx) = flush {
-            // If we can find a straight in the flush then it's a straight flush
-            if let Some(rank) = rank_straight(suit_value_sets[flush_idx]) {
-                Rank::StraightFlush(rank)
-            } else {
-                // Else it's just a normal flush
-                let rank = keep_n(suit_value_sets[flush_idx], 5);
-                Rank::Flush(rank)
-            }
-        } else if count_to_value[4] != 0 {
-            // Four of a kind.
-            let high = keep_highest(value_set ^ count_to_value[4]);
-            Rank::FourOfAKind((count_to_value[4] << 13) | high)
-        } else if count_to_value[3] != 0 && count_to_value[3].count_ones() == 2 {
-            // There are two sets. So the best we can make is a full house.
-            let set = keep_highest(count_to_value[3]);
-            let pair = count_to_value[3] ^ set;
-            Rank::FullHouse((set << 13) | pair)
-        } else if count_to_value[3] != 0 && count_to_value[2] != 0 {
-            // there is a pair and a set.
-            let set = count_to_value[3];
-            let pair = keep_highest(count_to_value[2]);
-            Rank::FullHouse((set << 13) | pair)
-        } else if let Some(s_rank) = rank_straight(value_set) {
-            // If there's a straight return it now.
-            Rank::Straight(s_rank)
-        } else if count_to_value[3] != 0 {
-            // if there is a set then we need to keep 2 cards that
-            // aren't in the set.
-            let low = keep_n(value_set ^ count_to_value[3], 2);
-            Rank::ThreeOfAKind((count_to_value[3] << 13) | low)
-        } else if count_to_value[2].count_ones() >= 2 {
-            // Two pair
-            //
-            // That can be because we have 3 pairs and a high card.
-            // Or we could have two pair and two high cards.
-            let pairs = keep_n(count_to_value[2], 2);
-            let low = keep_highest(value_set ^ pairs);
-            Rank::TwoPair((pairs << 13) | low)
-        } else if count_to_value[2] == 0 {
-            // This means that there's no pair
-            // no sets, no straights, no flushes, so only a
-            // high card.
-            Rank::HighCard(keep_n(value_set, 5))
-        } else {
-            // Otherwise there's only one pair.
-            let pair = count_to_value[2];
-            // Keep the highest three cards not in the pair.
-            let low = keep_n(value_set ^ count_to_value[2], 3);
-            Rank::OnePair((pair << 13) | low)
-        }
+        rank_from_counts(&value_to_count, &suit_value_sets, value_set)
     }
 
     /// Rank this hand. It doesn't do any caching so it's left up to the user
@@ -327,6 +478,24 @@ mod tests {
         assert_eq!(0b100, keep_highest(0b111));
     }
 
+    #[test]
+    fn test_keep_highest_empty() {
+        assert_eq!(0, keep_highest(0));
+    }
+
+    #[test]
+    fn test_four_card_hands() {
+        let hand = FlatHand::new_from_str("AdAhKdKh").unwrap();
+        let pairs = (1 << Value::Ace as u32) | (1 << Value::King as u32);
+        assert_eq!(Rank::TwoPair(pairs << 13), hand.rank());
+
+        let hand = FlatHand::new_from_str("AdAhAsAc").unwrap();
+        assert_eq!(
+            Rank::FourOfAKind(1 << (Value::Ace as u32 + 13)),
+            hand.rank()
+        );
+    }
+
     #[test]
     fn test_keep_n() {
         assert_eq!(3, keep_n(0b1111, 3).count_ones());
@@ -522,4 +691,46 @@ mod tests {
         let low_rank = 1 << Value::King as u32;
         assert_eq!(Rank::TwoPair(pair_rank | low_rank), h.rank());
     }
+
+    #[test]
+    fn test_fast_paths_match_counts() {
+        use rand::seq::SliceRandom;
+
+        let mut rng = rand::rng();
+        let mut cards: Vec<Card> = crate::core::Deck::default().into_iter().collect();
+        for _ in 0..20_000 {
+            cards.shuffle(&mut rng);
+            let by_counts = |hand: &[Card]| {
+                let mut value_to_count = [0; 13];
+                let mut suit_value_sets = [0; 4];
+                let mut value_set = 0;
+                for card in hand {
+                    value_to_count[card.value as usize] += 1;
+                    suit_value_sets[card.suit as usize] |= 1 << card.value as u32;
+                    value_set |= 1 << card.value as u32;
+                }
+                rank_from_counts(&value_to_count, &suit_value_sets, value_set)
+            };
+            let five: [Card; 5] = cards[..5].try_into().unwrap();
+            let six: [Card; 6] = cards[..6].try_into().unwrap();
+            let seven: [Card; 7] = cards[..7].try_into().unwrap();
+            assert_eq!(by_counts(&five), rank_five_cards(&five), "{five:?}");
+            assert_eq!(by_counts(&six), rank_six_cards(&six), "{six:?}");
+            assert_eq!(by_counts(&seven), rank_seven_cards(&seven), "{seven:?}");
+        }
+    }
+
+    #[test]
+    fn test_duplicate_cards_use_counts() {
+        // The same card twice isn't five different cards, so it's ranked by
+        // counting rather than by the five card path.
+        let cards = vec![
+            Card::new(Value::Ace, Suit::Spade),
+            Card::new(Value::Ace, Suit::Spade),
+            Card::new(Value::King, Suit::Spade),
+            Card::new(Value::Queen, Suit::Heart),
+            Card::new(Value::Two, Suit::Club),
+        ];
+        assert!(matches!(cards.rank(), Rank::OnePair(_)));
+    }
 }
@@ -0,0 +1,70 @@
+//! `proptest` strategies for cards and sets of cards.
+//!
+//! Sets of cards never hold the same card twice, and shrink towards fewer
+//! cards.
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use rs_poker::core::{FlatHand, Rankable, strategy};
+//!
+//! proptest!(|(hand in strategy::flat_hand(5..=7))| {
+//!     // A hand is worth at least as much as any five of its cards.
+//!     let five = FlatHand::new_with_cards(hand[..5].to_vec());
+//!     prop_assert!(hand.rank() >= five.rank());
+//! });
+//! ```
+use proptest::collection::SizeRange;
+use proptest::prelude::*;
+use proptest::sample::subsequence;
+
+use super::{Card, FlatHand, Hand, Suit, Value};
+
+pub fn value() -> impl Strategy<Value = Value> {
+    (0_u8..13).prop_map(Value::from)
+}
+
+pub fn suit() -> impl Strategy<Value = Suit> {
+    (0_u8..4).prop_map(Suit::from)
+}
+
+pub fn card() -> impl Strategy<Value = Card> {
+    (0_u8..52).prop_map(Card::from)
+}
+
+/// Different cards, as many as `size`, in any order.
+pub fn cards(size: impl Into<SizeRange>) -> impl Strategy<Value = Vec<Card>> {
+    let deck: Vec<Card> = (0_u8..52).map(Card::from).collect();
+    subsequence(deck, size).prop_shuffle()
+}
+
+pub fn hand(size: impl Into<SizeRange>) -> impl Strategy<Value = Hand> {
+    cards(size).prop_map(Hand::new_with_cards)
+}
+
+pub fn flat_hand(size: impl Into<SizeRange>) -> impl Strategy<Value = FlatHand> {
+    cards(size).prop_map(FlatHand::new_with_cards)
+}
+
+/// A board as it is between streets: no cards, the flop, the turn or the
+/// river.
+pub fn board() -> impl Strategy<Value = Vec<Card>> {
+    prop_oneof![cards(0), cards(3), cards(4), cards(5)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::CardBitSet;
+
+    proptest! {
+        #[test]
+        fn test_cards_are_different(cards in cards(0..=52)) {
+            prop_assert_eq!(cards.len(), CardBitSet::from(&cards[..]).count());
+        }
+
+        #[test]
+        fn test_board_sizes(board in board()) {
+            prop_assert!(matches!(board.len(), 0 | 3 | 4 | 5));
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use rand::Rng;
+use rand::seq::index::sample;
+
+use crate::core::{Card, CardBitSet, CardIter, Hand, RSPokerError, Rankable};
+
+/// The value of an all in to every player still in the pot, and what it
+/// costs them to run it more than once or to buy insurance.
+///
+/// Running it more than once doesn't change what anyone expects to win, so
+/// the fair price of running it `n` times is nothing: only the swings
+/// shrink. Insurance is fair when the premium is the payout times the
+/// chance of losing the whole pot.
+///
+/// ```
+/// use rs_poker::core::{Card, Hand};
+/// use rs_poker::holdem::AllInEquity;
+///
+/// let hands = [
+///     Hand::new_from_str("AsAh").unwrap(),
+///     Hand::new_from_str("KsKh").unwrap(),
+/// ];
+/// let board: Vec<Card> = Hand::new_from_str("2c7d9h3s").unwrap().iter().collect();
+/// let all_in = AllInEquity::new(&hands, &board, 200.0).unwrap();
+///
+/// // Only the two remaining kings save the underdog.
+/// assert!((all_in.equity[1] - 2.0 / 44.0).abs() < 1e-9);
+/// // Insuring the aces for the whole pot costs what they lose on average.
+/// let insurance = all_in.insurance(0, 200.0);
+/// assert!((insurance.premium - 200.0 * 2.0 / 44.0).abs() < 1e-4);
+/// // Running it twice is free and halves the variance.
+/// let twice = all_in.run_it(0, 2);
+/// assert_eq!(all_in.run_it(0, 1).expected_value, twice.expected_value);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AllInEquity {
+    pub pot: f32,
+    /// The share of the pot each player wins on average.
+    pub equity: Vec<f64>,
+    /// The average of the square of each player's share, which with the
+    /// equity gives how much the share swings.
+    pub share_squared: Vec<f64>,
+    /// How often each player wins nothing at all.
+    pub lose: Vec<f64>,
+    /// How many runouts were dealt.
+    pub runouts: usize,
+    /// Whether every possible runout was dealt rather than a sample.
+    pub exact: bool,
+}
+
+/// What one player can expect from running the board out some number of
+/// times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunItTimes {
+    pub times: usize,
+    /// The same however many times it's run.
+    pub expected_value: f32,
+    /// The standard deviation of what the player wins.
+    ///
+    /// Each run is treated as independent. Runs dealt from the same deck
+    /// can't share cards, which makes the real swings a little smaller.
+    pub std_dev: f32,
+}
+
+/// The fair price of insuring one player's stake in the pot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Insurance {
+    /// Paid to the player if they win nothing from the pot.
+    pub coverage: f32,
+    /// The premium that makes the insurance break even.
+    pub premium: f32,
+    /// The standard deviation of what the player wins once insured.
+    pub std_dev: f32,
+}
+
+impl AllInEquity {
+    /// Deal every possible runout to find each player's exact equity.
+    ///
+    /// `hands` are each player's hole cards and `board` the community cards
+    /// dealt so far. Before the flop this ranks every one of the 1.7 million
+    /// boards, so use [`AllInEquity::estimate`] when speed matters more.
+    pub fn new(hands: &[Hand], board: &[Card], pot: f32) -> Result<Self, RSPokerError> {
+        Self::estimate(hands, board, pot, usize::MAX, &mut crate::core::rng())
+    }
+
+    /// Like [`AllInEquity::new`] but when there are more than `max_runouts`
+    /// possible runouts only `max_runouts` of them are sampled.
+    pub fn estimate<R: Rng>(
+        hands: &[Hand],
+        board: &[Card],
+        pot: f32,
+        max_runouts: usize,
+        rng: &mut R,
+    ) -> Result<Self, RSPokerError> {
+        if board.len() > 5 {
+            return Err(RSPokerError::HoldemHandSize);
+        }
+        let mut dead = CardBitSet::new();
+        for card in hands
+            .iter()
+            .flat_map(|hand| hand.iter())
+            .chain(board.iter().copied())
+        {
+            if dead.contains(card) {
+                return Err(RSPokerError::DuplicateCardInHand(card));
+            }
+            dead.insert(card);
+        }
+        if hands.iter().any(|hand| hand.count() + board.len() > 7) {
+            return Err(RSPokerError::HoldemHandSize);
+        }
+
+        let remaining: Vec<Card> = (!dead).iter().collect();
+        let num_cards = 5 - board.len();
+        let mut all_in = Self {
+            pot,
+            equity: vec![0.0; hands.len()],
+            share_squared: vec![0.0; hands.len()],
+            lose: vec![0.0; hands.len()],
+            runouts: 0,
+            exact: true,
+        };
+
+        if num_cards == 0 {
+            all_in.add_runout(hands, board, &[]);
+        } else if num_combinations(remaining.len(), num_cards) <= max_runouts as u64 {
+            for runout in CardIter::new(&remaining, num_cards) {
+                all_in.add_runout(hands, board, &runout);
+            }
+        } else {
+            all_in.exact = false;
+            for _ in 0..max_runouts.max(1) {
+                let runout: Vec<Card> = sample(rng, remaining.len(), num_cards)
+                    .into_iter()
+                    .map(|i| remaining[i])
+                    .collect();
+                all_in.add_runout(hands, board, &runout);
+            }
+        }
+
+        let runouts = all_in.runouts.max(1) as f64;
+        for value in all_in
+            .equity
+            .iter_mut()
+            .chain(all_in.share_squared.iter_mut())
+            .chain(all_in.lose.iter_mut())
+        {
+            *value /= runouts;
+        }
+        Ok(all_in)
+    }
+
+    fn add_runout(&mut self, hands: &[Hand], board: &[Card], runout: &[Card]) {
+        let ranks: Vec<_> = hands
+            .iter()
+            .map(|hand| {
+                let mut hand = *hand;
+                hand.extend(board.iter().chain(runout).copied());
+                hand.rank()
+            })
+            .collect();
+        let Some(best) = ranks.iter().max() else {
+            return;
+        };
+        let winners = ranks.iter().filter(|rank| *rank == best).count() as f64;
+        for (idx, rank) in ranks.iter().enumerate() {
+            if rank == best {
+                self.equity[idx] += 1.0 / winners;
+                self.share_squared[idx] += 1.0 / (winners * winners);
+            } else {
+                self.lose[idx] += 1.0;
+            }
+        }
+        self.runouts += 1;
+    }
+
+    /// What the player wins from the pot on average.
+    pub fn expected_value(&self, idx: usize) -> f32 {
+        (self.equity[idx] * self.pot as f64) as f32
+    }
+
+    /// Run the rest of the board out `times` times, splitting the pot evenly
+    /// between the runs.
+    pub fn run_it(&self, idx: usize, times: usize) -> RunItTimes {
+        let times = times.max(1);
+        let variance = self.share_variance(idx) / times as f64;
+        RunItTimes {
+            times,
+            expected_value: self.expected_value(idx),
+            std_dev: (variance.sqrt() * self.pot as f64) as f32,
+        }
+    }
+
+    /// Insure the player for `coverage`, paid out if they win nothing. The
+    /// premium leaves their expected value where it was.
+    pub fn insurance(&self, idx: usize, coverage: f32) -> Insurance {
+        let pot = self.pot as f64;
+        let coverage_f64 = coverage as f64;
+        let lose = self.lose[idx];
+        // The share and the payout never both come in, so they move against
+        // each other.
+        let covariance = -self.equity[idx] * lose;
+        let variance = pot * pot * self.share_variance(idx)
+            + coverage_f64 * coverage_f64 * lose * (1.0 - lose)
+            + 2.0 * pot * coverage_f64 * covariance;
+        Insurance {
+            coverage,
+            premium: (coverage_f64 * lose) as f32,
+            std_dev: variance.max(0.0).sqrt() as f32,
+        }
+    }
+
+    fn share_variance(&self, idx: usize) -> f64 {
+        (self.share_squared[idx] - self.equity[idx] * self.equity[idx]).max(0.0)
+    }
+}
+
+fn num_combinations(n: usize, k: usize) -> u64 {
+    (0..k as u64).fold(1, |acc, i| acc * (n as u64 - i) / (i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(s: &str) -> Vec<Card> {
+        Hand::new_from_str(s).unwrap().iter().collect()
+    }
+
+    #[test]
+    fn test_river_is_certain() {
+        let hands = [
+            Hand::new_from_str("AsAh").unwrap(),
+            Hand::new_from_str("KsKh").unwrap(),
+        ];
+        let all_in = AllInEquity::new(&hands, &cards("2c7d9h3s4d"), 100.0).unwrap();
+        assert_eq!(1, all_in.runouts);
+        assert_eq!(vec![1.0, 0.0], all_in.equity);
+        assert_eq!(0.0, all_in.run_it(0, 1).std_dev);
+        assert_eq!(0.0, all_in.insurance(0, 100.0).premium);
+    }
+
+    #[test]
+    fn test_chop_has_no_swings() {
+        // Both play the board's broadway straight.
+        let hands = [
+            Hand::new_from_str("2s3h").unwrap(),
+            Hand::new_from_str("2c3d").unwrap(),
+        ];
+        let all_in = AllInEquity::new(&hands, &cards("AhKdQcJs"), 100.0).unwrap();
+        assert!(all_in.exact);
+        assert_eq!(44, all_in.runouts);
+        // Every river leaves them with the same hand.
+        assert_eq!(all_in.equity[0], all_in.equity[1]);
+        assert_eq!(50.0, all_in.expected_value(0));
+    }
+
+    #[test]
+    fn test_run_it_twice_halves_variance() {
+        let hands = [
+            Hand::new_from_str("AsAh").unwrap(),
+            Hand::new_from_str("7c8c").unwrap(),
+        ];
+        let all_in = AllInEquity::new(&hands, &cards("6c9dKs"), 100.0).unwrap();
+        assert_eq!(990, all_in.runouts);
+        let once = all_in.run_it(1, 1);
+        let twice = all_in.run_it(1, 2);
+        assert_eq!(once.expected_value, twice.expected_value);
+        assert!((once.std_dev / twice.std_dev - 2.0_f32.sqrt()).abs() < 1e-4);
+        // Equity and losing add up without ties.
+        assert!((all_in.equity[0] + all_in.lose[0] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_full_insurance_removes_variance() {
+        let hands = [
+            Hand::new_from_str("AsAh").unwrap(),
+            Hand::new_from_str("KsKh").unwrap(),
+        ];
+        let all_in = AllInEquity::new(&hands, &cards("2c7d9h"), 100.0).unwrap();
+        let insurance = all_in.insurance(0, 100.0);
+        assert!(insurance.std_dev < 1e-3, "{insurance:?}");
+        assert!((insurance.premium - 100.0 * all_in.lose[0] as f32).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_estimate_samples() {
+        let hands = [
+            Hand::new_from_str("AsAh").unwrap(),
+            Hand::new_from_str("KsKh").unwrap(),
+        ];
+        let all_in =
+            AllInEquity::estimate(&hands, &[], 10.0, 1000, &mut crate::core::rng()).unwrap();
+        assert!(!all_in.exact);
+        assert_eq!(1000, all_in.runouts);
+        assert!(all_in.equity[0] > 0.7);
+    }
+
+    #[test]
+    fn test_bad_cards() {
+        let hands = [
+            Hand::new_from_str("AsAh").unwrap(),
+            Hand::new_from_str("AsKh").unwrap(),
+        ];
+        assert!(matches!(
+            AllInEquity::new(&hands, &[], 1.0),
+            Err(RSPokerError::DuplicateCardInHand(_))
+        ));
+    }
+}
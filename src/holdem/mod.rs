@@ -13,3 +13,25 @@ pub use self::monte_carlo_game::MonteCarloGame;
 mod parse;
 /// Export `RangeParser`
 pub use self::parse::RangeParser;
+
+/// `proptest` strategies for starting hands and ranges.
+#[cfg(feature = "proptest")]
+pub mod strategy;
+
+/// Module with weighted ranges of hands.
+mod range;
+/// Export `Range`
+pub use self::range::Range;
+
+/// Module with exact all in equity, running it more than once and insurance.
+mod all_in;
+/// Export the all in calculator.
+pub use self::all_in::{AllInEquity, Insurance, RunItTimes};
+
+/// Module with a solver for preflop push/fold games.
+mod push_fold;
+/// Export the push/fold solver and its results.
+pub use self::push_fold::{
+    MAX_PUSH_FOLD_PLAYERS, PushFoldConfig, PushFoldRange, PushFoldSolution, PushFoldSolver,
+    icm_equity, push_fold_chart,
+};
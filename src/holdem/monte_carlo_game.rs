@@ -1,6 +1,4 @@
-use rand::rng;
-
-use crate::core::{CardBitSet, FlatDeck, Hand, PlayerBitSet, RSPokerError, Rank, Rankable};
+use crate::core::{CardBitSet, FlatDeck, Hand, PlayerBitSet, RSPokerError, Rank, Rankable, rng};
 
 /// Current state of a game.
 #[derive(Debug)]
@@ -40,7 +38,7 @@ impl MonteCarloGame {
             }
         }
 
-        let num_community_cards = (7 - max_hand_size).max(0);
+        let num_community_cards = 7 - max_hand_size;
 
         let flat_deck: FlatDeck = deck.into();
         // Grab the deck.len() so that any call to shuffle_if_needed
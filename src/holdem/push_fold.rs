@@ -0,0 +1,645 @@
+//! Solve preflop push/fold games.
+//!
+//! With short stacks the only actions that matter preflop are going all in
+//! and folding. Each player either folds or shoves when it's folded to
+//! them, and folds or calls once someone has shoved. That game is small
+//! enough to solve outright: the solver runs chance sampled CFR over the
+//! 169 starting hands for every spot, dealing real cards so card removal
+//! and side pots are exact.
+//!
+//! Results are judged in chips, or with tournament payouts in ICM equity of
+//! the stacks after the hand. ICM is what makes calling off a stack near the
+//! money so much worse than it looks in chips.
+//!
+//! # Example
+//!
+//! ```
+//! use rs_poker::core::Value;
+//! use rs_poker::holdem::{PushFoldConfig, PushFoldSolver};
+//!
+//! // Heads up with ten big blinds each.
+//! let mut solver = PushFoldSolver::new(PushFoldConfig::equal_stacks(2, 10.0));
+//! solver.train(10_000, &mut rand::rng());
+//! let solution = solver.solution();
+//!
+//! let jam = solution.jam_range(0).unwrap();
+//! assert!(jam.frequency(Value::Ace, Value::Ace, false) > 0.9);
+//! let call = solution.call_range(1, &[0]).unwrap();
+//! println!("Jam {jam}\nCall {call}");
+//! ```
+use std::fmt;
+
+use rand::{Rng, seq::index::sample};
+
+use crate::core::{Card, Rank, Value, rank_seven_cards};
+
+/// How many distinct starting hands there are.
+const NUM_HANDS: usize = 169;
+
+/// The most players at a table the solver will take.
+pub const MAX_PUSH_FOLD_PLAYERS: usize = 10;
+
+/// The index of a starting hand in a 13 by 13 grid, suited hands below the
+/// diagonal and offsuit hands above it.
+fn hand_index(first: Value, second: Value, suited: bool) -> usize {
+    let (high, low) = if first >= second {
+        (first as usize, second as usize)
+    } else {
+        (second as usize, first as usize)
+    };
+    if suited && high != low {
+        high * 13 + low
+    } else {
+        low * 13 + high
+    }
+}
+
+fn cards_index(first: Card, second: Card) -> usize {
+    hand_index(first.value, second.value, first.suit == second.suit)
+}
+
+/// The name, like `AKs`, and the number of combinations of a hand index.
+fn hand_name(idx: usize) -> (String, usize) {
+    let (row, col) = (idx / 13, idx % 13);
+    let value = |v: usize| Value::from_u8(v as u8).to_char();
+    if row == col {
+        (format!("{}{}", value(row), value(col)), 6)
+    } else if row > col {
+        (format!("{}{}s", value(row), value(col)), 4)
+    } else {
+        (format!("{}{}o", value(col), value(row)), 12)
+    }
+}
+
+/// The order hands are listed in, aces first and then down each row.
+fn hands_in_order() -> impl Iterator<Item = usize> {
+    (0..13).rev().flat_map(|high| {
+        (0..=high).rev().flat_map(move |low| {
+            if high == low {
+                vec![high * 13 + low]
+            } else {
+                vec![high * 13 + low, low * 13 + high]
+            }
+        })
+    })
+}
+
+/// How often each starting hand shoves, or calls a shove.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PushFoldRange {
+    frequencies: Vec<f32>,
+}
+
+impl PushFoldRange {
+    /// How often the hand with these two values goes all in. Pairs are
+    /// never suited, so `suited` is ignored for them.
+    pub fn frequency(&self, first: Value, second: Value, suited: bool) -> f32 {
+        self.frequencies[hand_index(first, second, suited)]
+    }
+
+    /// How often this exact hand goes all in.
+    pub fn frequency_for_cards(&self, first: Card, second: Card) -> f32 {
+        self.frequencies[cards_index(first, second)]
+    }
+
+    /// The fraction of all 1326 hands that go all in, counting mixed hands
+    /// by how often they do.
+    pub fn fraction_of_hands(&self) -> f32 {
+        let combos: f32 = (0..NUM_HANDS)
+            .map(|idx| self.frequencies[idx] * hand_name(idx).1 as f32)
+            .sum();
+        combos / 1326.0
+    }
+
+    /// The names of the hands that go all in at least `threshold` of the
+    /// time, strongest values first.
+    pub fn hands(&self, threshold: f32) -> Vec<String> {
+        hands_in_order()
+            .filter(|idx| self.frequencies[*idx] >= threshold)
+            .map(|idx| hand_name(idx).0)
+            .collect()
+    }
+}
+
+/// Lists the hands that go all in more often than not.
+impl fmt::Display for PushFoldRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.hands(0.5).join(", "))
+    }
+}
+
+/// The game to solve.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PushFoldConfig {
+    /// Every player's stack before the blinds and antes, in the order they
+    /// act preflop. The last two players post the small and big blind, so
+    /// heads up the small blind is first.
+    pub stacks: Vec<f32>,
+    pub small_blind: f32,
+    pub big_blind: f32,
+    /// Posted by every player.
+    pub ante: f32,
+    /// The tournament payouts, first place first. When set, hands are
+    /// judged by the ICM equity of the stacks after them instead of chips.
+    pub payouts: Option<Vec<f32>>,
+}
+
+impl PushFoldConfig {
+    /// Blinds of 0.5 and 1, so stacks are in big blinds, with no antes.
+    pub fn new(stacks: Vec<f32>) -> Self {
+        Self {
+            stacks,
+            small_blind: 0.5,
+            big_blind: 1.0,
+            ante: 0.0,
+            payouts: None,
+        }
+    }
+
+    /// `num_players` players with `big_blinds` each.
+    pub fn equal_stacks(num_players: usize, big_blinds: f32) -> Self {
+        Self::new(vec![big_blinds; num_players])
+    }
+
+    fn num_players(&self) -> usize {
+        self.stacks.len()
+    }
+
+    /// What each player puts in before acting.
+    fn posted(&self, idx: usize) -> f32 {
+        let n = self.num_players();
+        let blind = if idx + 2 == n {
+            self.small_blind
+        } else if idx + 1 == n {
+            self.big_blind
+        } else {
+            0.0
+        };
+        (blind + self.ante).min(self.stacks[idx])
+    }
+}
+
+/// The equity of each stack in a tournament paying `payouts`, by the
+/// Malmuth-Harville model: the chance of finishing first is the share of
+/// the chips, and the rest finish in the same way without the winner.
+///
+/// Players without chips share the payouts for the places behind everyone
+/// who still has some.
+pub fn icm_equity(stacks: &[f32], payouts: &[f32]) -> Vec<f32> {
+    let n = stacks.len();
+    assert!(n <= 16, "ICM takes at most 16 players");
+    let payout = |place: usize| payouts.get(place).copied().unwrap_or(0.0) as f64;
+    let alive: u32 = (0..n)
+        .filter(|i| stacks[*i] > 0.0)
+        .fold(0, |mask, i| mask | (1 << i));
+    let num_alive = alive.count_ones() as usize;
+
+    // The chance of each set of players being the ones left, filled in by
+    // walking down from everyone alive one finisher at a time.
+    let mut reach = vec![0.0_f64; 1 << n];
+    reach[alive as usize] = 1.0;
+    let mut equity = vec![0.0_f64; n];
+    let mut masks: Vec<u32> = (0..(1_u32 << n))
+        .filter(|mask| mask & !alive == 0 && *mask != 0)
+        .collect();
+    masks.sort_by_key(|mask| std::cmp::Reverse(mask.count_ones()));
+    for mask in masks {
+        let p = reach[mask as usize];
+        if p == 0.0 {
+            continue;
+        }
+        let place = num_alive - mask.count_ones() as usize;
+        let total: f64 = (0..n)
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| stacks[i] as f64)
+            .sum();
+        for i in (0..n).filter(|i| mask & (1 << i) != 0) {
+            let first = p * stacks[i] as f64 / total;
+            equity[i] += first * payout(place);
+            reach[(mask & !(1 << i)) as usize] += first;
+        }
+    }
+
+    let busted = n - num_alive;
+    if busted > 0 {
+        let share = (num_alive..n).map(payout).sum::<f64>() / busted as f64;
+        for i in (0..n).filter(|i| alive & (1 << i) == 0) {
+            equity[i] = share;
+        }
+    }
+    equity.into_iter().map(|e| e as f32).collect()
+}
+
+/// The cards for one iteration.
+struct Deal {
+    hands: [usize; MAX_PUSH_FOLD_PLAYERS],
+    ranks: [Rank; MAX_PUSH_FOLD_PLAYERS],
+}
+
+/// Solves a push/fold game with chance sampled CFR.
+///
+/// Each spot is a player to act and the set of players before them who are
+/// already all in. With none all in the player chooses whether to shove,
+/// otherwise whether to call. The big blind gets no choice when everyone
+/// folds to them.
+#[derive(Debug, Clone)]
+pub struct PushFoldSolver {
+    config: PushFoldConfig,
+    /// For every spot and hand, the regret of folding and of going all in.
+    regrets: Vec<[f64; 2]>,
+    /// For every spot and hand, how often each was played, summed with
+    /// later iterations weighted more as the early ones are mostly noise.
+    strategy_sums: Vec<[f64; 2]>,
+    /// What the players start with in the payoff's units.
+    starting_values: Vec<f64>,
+    iterations: u64,
+}
+
+impl PushFoldSolver {
+    /// # Panics
+    ///
+    /// If there are fewer than two players or more than
+    /// `MAX_PUSH_FOLD_PLAYERS`.
+    pub fn new(config: PushFoldConfig) -> Self {
+        let n = config.num_players();
+        assert!(
+            (2..=MAX_PUSH_FOLD_PLAYERS).contains(&n),
+            "Push/fold needs between 2 and {MAX_PUSH_FOLD_PLAYERS} players"
+        );
+        let starting_values = match &config.payouts {
+            Some(payouts) => icm_equity(&config.stacks, payouts)
+                .into_iter()
+                .map(f64::from)
+                .collect(),
+            None => config.stacks.iter().map(|s| *s as f64).collect(),
+        };
+        let num_slots = ((1 << n) - 1) * NUM_HANDS;
+        Self {
+            config,
+            regrets: vec![[0.0; 2]; num_slots],
+            strategy_sums: vec![[0.0; 2]; num_slots],
+            starting_values,
+            iterations: 0,
+        }
+    }
+
+    pub fn config(&self) -> &PushFoldConfig {
+        &self.config
+    }
+
+    /// How many iterations have been run.
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Run `iterations` more iterations, each dealing one set of cards and
+    /// updating every player's regrets.
+    pub fn train<R: Rng>(&mut self, iterations: usize, rng: &mut R) {
+        for _ in 0..iterations {
+            let deal = self.deal(rng);
+            for traverser in 0..self.config.num_players() {
+                self.traverse(traverser, 0, 0, &deal, rng);
+            }
+            self.iterations += 1;
+        }
+    }
+
+    /// The average strategy so far.
+    pub fn solution(&self) -> PushFoldSolution {
+        let ranges = self
+            .strategy_sums
+            .chunks(NUM_HANDS)
+            .map(|sums| PushFoldRange {
+                frequencies: sums
+                    .iter()
+                    .map(|[fold, jam]| {
+                        let total = fold + jam;
+                        if total > 0.0 {
+                            (jam / total) as f32
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        PushFoldSolution {
+            config: self.config.clone(),
+            ranges,
+        }
+    }
+
+    fn deal<R: Rng>(&self, rng: &mut R) -> Deal {
+        let n = self.config.num_players();
+        let cards: Vec<Card> = sample(rng, 52, 2 * n + 5)
+            .into_iter()
+            .map(|c| Card::from(c as u8))
+            .collect();
+        let board = &cards[2 * n..];
+        let mut deal = Deal {
+            hands: [0; MAX_PUSH_FOLD_PLAYERS],
+            ranks: [Rank::HighCard(0); MAX_PUSH_FOLD_PLAYERS],
+        };
+        for i in 0..n {
+            let (first, second) = (cards[2 * i], cards[2 * i + 1]);
+            deal.hands[i] = cards_index(first, second);
+            deal.ranks[i] = rank_seven_cards(&[
+                first, second, board[0], board[1], board[2], board[3], board[4],
+            ]);
+        }
+        deal
+    }
+
+    /// The strategy from regret matching, as fold and all in.
+    fn current_strategy(&self, slot: usize) -> [f64; 2] {
+        let [fold, jam] = self.regrets[slot].map(|r| r.max(0.0));
+        let total = fold + jam;
+        if total > 0.0 {
+            [fold / total, jam / total]
+        } else {
+            [0.5, 0.5]
+        }
+    }
+
+    /// The traverser's value from `player` on, with `all_in` the players
+    /// before them who are in.
+    fn traverse<R: Rng>(
+        &mut self,
+        traverser: usize,
+        player: usize,
+        all_in: u32,
+        deal: &Deal,
+        rng: &mut R,
+    ) -> f64 {
+        let n = self.config.num_players();
+        if player == n {
+            return self.payoff(traverser, all_in, deal);
+        }
+        if player + 1 == n && all_in == 0 {
+            // Folded round to the big blind.
+            return self.payoff(traverser, 1 << player, deal);
+        }
+
+        let slot = (((1 << player) - 1) + all_in as usize) * NUM_HANDS + deal.hands[player];
+        let strategy = self.current_strategy(slot);
+        if player == traverser {
+            let fold = self.traverse(traverser, player + 1, all_in, deal, rng);
+            let jam = self.traverse(traverser, player + 1, all_in | (1 << player), deal, rng);
+            let value = strategy[0] * fold + strategy[1] * jam;
+            let regrets = &mut self.regrets[slot];
+            regrets[0] += fold - value;
+            regrets[1] += jam - value;
+            value
+        } else {
+            let weight = (self.iterations + 1) as f64;
+            let sums = &mut self.strategy_sums[slot];
+            sums[0] += weight * strategy[0];
+            sums[1] += weight * strategy[1];
+            let next = if rng.random::<f64>() < strategy[1] {
+                all_in | (1 << player)
+            } else {
+                all_in
+            };
+            self.traverse(traverser, player + 1, next, deal, rng)
+        }
+    }
+
+    /// How much better off the traverser is once the players in `all_in`
+    /// have gone to showdown.
+    fn payoff(&self, traverser: usize, all_in: u32, deal: &Deal) -> f64 {
+        let n = self.config.num_players();
+        let is_in = |i: usize| all_in & (1 << i) != 0;
+        let put_in: Vec<f64> = (0..n)
+            .map(|i| {
+                if is_in(i) {
+                    self.config.stacks[i] as f64
+                } else {
+                    self.config.posted(i) as f64
+                }
+            })
+            .collect();
+
+        // Split the pot into side pots at each stack that's all in, each
+        // won by the best hand among the players who covered it.
+        let mut levels: Vec<f64> = (0..n).filter(|i| is_in(*i)).map(|i| put_in[i]).collect();
+        levels.sort_by(f64::total_cmp);
+        levels.dedup();
+        let mut stacks: Vec<f64> = (0..n)
+            .map(|i| self.config.stacks[i] as f64 - put_in[i])
+            .collect();
+        let mut previous = 0.0;
+        for (level_idx, level) in levels.iter().enumerate() {
+            // The last pot also takes any dead money above it.
+            let top = if level_idx + 1 == levels.len() {
+                f64::INFINITY
+            } else {
+                *level
+            };
+            let pot: f64 = put_in.iter().map(|p| p.min(top) - p.min(previous)).sum();
+            let eligible = || (0..n).filter(|i| is_in(*i) && put_in[*i] >= *level);
+            let best = eligible().map(|i| deal.ranks[i]).max().unwrap();
+            let winners: Vec<usize> = eligible().filter(|i| deal.ranks[*i] == best).collect();
+            for winner in &winners {
+                stacks[*winner] += pot / winners.len() as f64;
+            }
+            previous = *level;
+        }
+
+        let value = match &self.config.payouts {
+            Some(payouts) => {
+                let stacks: Vec<f32> = stacks.iter().map(|s| *s as f32).collect();
+                icm_equity(&stacks, payouts)[traverser] as f64
+            }
+            None => stacks[traverser],
+        };
+        value - self.starting_values[traverser]
+    }
+}
+
+/// The strategies found by a `PushFoldSolver`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PushFoldSolution {
+    config: PushFoldConfig,
+    /// One range per spot, indexed as the solver's are.
+    ranges: Vec<PushFoldRange>,
+}
+
+impl PushFoldSolution {
+    pub fn config(&self) -> &PushFoldConfig {
+        &self.config
+    }
+
+    /// The hands `player` shoves when it's folded to them. The big blind
+    /// doesn't get to, so has none.
+    pub fn jam_range(&self, player: usize) -> Option<&PushFoldRange> {
+        if player + 1 >= self.config.num_players() {
+            return None;
+        }
+        self.ranges.get((1 << player) - 1)
+    }
+
+    /// The hands `player` calls with when the players in `all_in`, who act
+    /// before them, have gone all in and everyone else before them has
+    /// folded.
+    pub fn call_range(&self, player: usize, all_in: &[usize]) -> Option<&PushFoldRange> {
+        if player >= self.config.num_players()
+            || all_in.is_empty()
+            || all_in.iter().any(|i| *i >= player)
+        {
+            return None;
+        }
+        let mask = all_in.iter().fold(0, |mask, i| mask | (1 << i));
+        self.ranges.get((1 << player) - 1 + mask)
+    }
+}
+
+/// Solve the same game at each depth in `big_blinds`, with every stack set
+/// to that many big blinds, for a chart of ranges by stack size.
+pub fn push_fold_chart<R: Rng>(
+    config: &PushFoldConfig,
+    big_blinds: &[f32],
+    iterations: usize,
+    rng: &mut R,
+) -> Vec<PushFoldSolution> {
+    big_blinds
+        .iter()
+        .map(|depth| {
+            let mut config = config.clone();
+            config.stacks.fill(depth * config.big_blind);
+            let mut solver = PushFoldSolver::new(config);
+            solver.train(iterations, rng);
+            solver.solution()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hand_names() {
+        let mut names: Vec<String> = (0..NUM_HANDS).map(|idx| hand_name(idx).0).collect();
+        assert_eq!(
+            1326,
+            (0..NUM_HANDS).map(|idx| hand_name(idx).1).sum::<usize>()
+        );
+        names.sort();
+        names.dedup();
+        assert_eq!(NUM_HANDS, names.len());
+
+        let ace_king = hand_index(Value::King, Value::Ace, true);
+        assert_eq!("AKs", hand_name(ace_king).0);
+        let ace_king = hand_index(Value::Ace, Value::King, false);
+        assert_eq!("AKo", hand_name(ace_king).0);
+        assert_eq!(
+            vec!["AA", "AKs", "AKo", "AQs"],
+            hands_in_order()
+                .take(4)
+                .map(|idx| hand_name(idx).0)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_icm_equity() {
+        let payouts = [50.0, 30.0, 20.0];
+        let equal = icm_equity(&[100.0, 100.0, 100.0], &payouts);
+        for equity in &equal {
+            assert!((equity - 100.0 / 3.0).abs() < 1e-3);
+        }
+
+        let equity = icm_equity(&[200.0, 50.0, 50.0], &payouts);
+        assert!((equity.iter().sum::<f32>() - 100.0).abs() < 1e-3);
+        // The chip leader has two thirds of the chips but well under two
+        // thirds of the prize pool.
+        assert!(equity[0] > equity[1] && equity[0] < 66.0);
+
+        // A busted player gets last place.
+        assert_eq!(20.0, icm_equity(&[150.0, 150.0, 0.0], &payouts)[2]);
+    }
+
+    #[test]
+    fn test_side_pots() {
+        let mut config = PushFoldConfig::new(vec![5.0, 20.0, 20.0]);
+        config.ante = 0.0;
+        let solver = PushFoldSolver::new(config);
+        let deal = Deal {
+            hands: [0; MAX_PUSH_FOLD_PLAYERS],
+            ranks: [
+                Rank::Straight(0),
+                Rank::OnePair(0),
+                Rank::HighCard(0),
+                Rank::HighCard(0),
+                Rank::HighCard(0),
+                Rank::HighCard(0),
+                Rank::HighCard(0),
+                Rank::HighCard(0),
+                Rank::HighCard(0),
+                Rank::HighCard(0),
+            ],
+        };
+        // Everyone's in. The short stack wins the main pot of 15 and the
+        // pair wins the side pot between the blinds.
+        assert_eq!(10.0, solver.payoff(0, 0b111, &deal));
+        assert_eq!(10.0, solver.payoff(1, 0b111, &deal));
+        assert_eq!(-20.0, solver.payoff(2, 0b111, &deal));
+        // The small blind folds to the big blind's call of the shove.
+        assert_eq!(5.5, solver.payoff(0, 0b101, &deal));
+        assert_eq!(-0.5, solver.payoff(1, 0b101, &deal));
+        // Folded to the big blind, who wins the small blind.
+        assert_eq!(0.5, solver.payoff(2, 0b100, &deal));
+    }
+
+    #[test]
+    fn test_heads_up_ranges_by_depth() {
+        let mut rng = rand::rng();
+        let chart = push_fold_chart(
+            &PushFoldConfig::equal_stacks(2, 1.0),
+            &[1.5, 20.0],
+            300_000,
+            &mut rng,
+        );
+
+        // With a big blind and a half almost anything is worth a shove.
+        let shallow = chart[0].jam_range(0).unwrap();
+        assert!(shallow.fraction_of_hands() > 0.8, "{shallow}");
+
+        let deep = &chart[1];
+        let jam = deep.jam_range(0).unwrap();
+        let call = deep.call_range(1, &[0]).unwrap();
+        assert!(jam.fraction_of_hands() < shallow.fraction_of_hands());
+        assert!(jam.frequency(Value::Ace, Value::Ace, false) > 0.9);
+        assert!(jam.frequency(Value::Three, Value::Two, false) < 0.2);
+        assert!(call.frequency(Value::Ace, Value::Ace, false) > 0.9);
+        assert!(call.frequency(Value::Seven, Value::Two, false) < 0.1);
+        assert!(jam.hands(0.5).contains(&"KK".to_string()));
+
+        assert!(deep.jam_range(1).is_none());
+        assert!(deep.call_range(0, &[1]).is_none());
+    }
+
+    #[test]
+    fn test_icm_calls_tighter() {
+        let mut rng = rand::rng();
+        // Three players on the bubble of a two place payout.
+        let chips = PushFoldConfig::equal_stacks(3, 10.0);
+        let icm = PushFoldConfig {
+            payouts: Some(vec![65.0, 35.0]),
+            ..chips.clone()
+        };
+
+        let call_fraction = |config: PushFoldConfig, rng: &mut rand::rngs::ThreadRng| {
+            let mut solver = PushFoldSolver::new(config);
+            solver.train(30_000, rng);
+            let solution = solver.solution();
+            solution.call_range(2, &[0]).unwrap().fraction_of_hands()
+        };
+        let chips = call_fraction(chips, &mut rng);
+        let icm = call_fraction(icm, &mut rng);
+        assert!(icm < chips, "ICM called {icm} of hands and chips {chips}");
+    }
+}
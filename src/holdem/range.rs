@@ -0,0 +1,236 @@
+//! A weighted range of two card hands.
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::core::{Card, CardBitSet, RSPokerError, Value};
+
+use super::{PushFoldRange, RangeParser};
+
+/// How many two card combinations there are.
+const NUM_COMBOS: usize = 1326;
+
+/// The index of a combination, counting pairs of distinct card indices
+/// with the higher one first.
+fn combo_index(first: Card, second: Card) -> Option<usize> {
+    let (a, b) = (u8::from(first) as usize, u8::from(second) as usize);
+    let (low, high) = if a < b { (a, b) } else { (b, a) };
+    (low != high).then(|| high * (high - 1) / 2 + low)
+}
+
+/// Every combination in index order.
+fn all_combos() -> impl Iterator<Item = (Card, Card)> {
+    (1..52u8).flat_map(|high| (0..high).map(move |low| (Card::from(low), Card::from(high))))
+}
+
+/// A weight for each of the 1326 two card hands a player could hold,
+/// usually how likely they are to hold it. Weights don't have to add up to
+/// anything, and hands that can't be held have no weight.
+///
+/// # Example
+///
+/// ```
+/// use rs_poker::core::{Card, CardBitSet, Suit, Value};
+/// use rs_poker::holdem::Range;
+///
+/// let mut range: Range = "QQ+,AKs".parse().unwrap();
+/// assert_eq!(22.0, range.total_weight());
+///
+/// // Holding the ace of spades takes out three aces and an ace king.
+/// let mut dead = CardBitSet::new();
+/// dead.insert(Card::new(Value::Ace, Suit::Spade));
+/// range.remove_cards(dead);
+/// assert_eq!(18.0, range.total_weight());
+/// assert_eq!(0.5, range.class_weight(Value::Ace, Value::Ace, false));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Range {
+    weights: Vec<f32>,
+}
+
+impl Default for Range {
+    /// A range with no hands in it.
+    fn default() -> Self {
+        Self {
+            weights: vec![0.0; NUM_COMBOS],
+        }
+    }
+}
+
+impl Range {
+    /// Every hand with a weight of one.
+    pub fn full() -> Self {
+        Self {
+            weights: vec![1.0; NUM_COMBOS],
+        }
+    }
+
+    /// The weight of the hand with these two cards, zero if they're the
+    /// same card.
+    pub fn weight(&self, first: Card, second: Card) -> f32 {
+        combo_index(first, second).map_or(0.0, |idx| self.weights[idx])
+    }
+
+    /// Set the weight of a hand. Setting one for a card paired with itself
+    /// does nothing.
+    pub fn set_weight(&mut self, first: Card, second: Card, weight: f32) {
+        if let Some(idx) = combo_index(first, second) {
+            self.weights[idx] = weight;
+        }
+    }
+
+    /// The hands with any weight, lower card first.
+    pub fn combos(&self) -> impl Iterator<Item = (Card, Card, f32)> + '_ {
+        all_combos()
+            .zip(self.weights.iter())
+            .filter(|(_, weight)| **weight > 0.0)
+            .map(|((first, second), weight)| (first, second, *weight))
+    }
+
+    pub fn total_weight(&self) -> f32 {
+        self.weights.iter().sum()
+    }
+
+    /// Take out every hand holding one of these cards, because they're on
+    /// the board or in someone else's hand.
+    pub fn remove_cards(&mut self, dead: CardBitSet) {
+        for ((first, second), weight) in all_combos().zip(self.weights.iter_mut()) {
+            if dead.contains(first) || dead.contains(second) {
+                *weight = 0.0;
+            }
+        }
+    }
+
+    /// Draw a hand with chances in proportion to the weights, leaving out
+    /// hands holding any of `dead`. `None` if no hand is left.
+    pub fn sample<R: Rng>(&self, dead: CardBitSet, rng: &mut R) -> Option<(Card, Card)> {
+        let live = || {
+            self.combos()
+                .filter(move |(first, second, _)| !dead.contains(*first) && !dead.contains(*second))
+        };
+        let total: f32 = live().map(|(_, _, weight)| weight).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut target = rng.random_range(0.0..total);
+        let mut last = None;
+        for (first, second, weight) in live() {
+            if target < weight {
+                return Some((first, second));
+            }
+            target -= weight;
+            last = Some((first, second));
+        }
+        // Rounding can leave a sliver past the last hand.
+        last
+    }
+
+    /// Scale the weights to add up to one, so each is the chance of holding
+    /// that hand. An empty range stays empty.
+    pub fn normalize(&mut self) {
+        let total = self.total_weight();
+        if total > 0.0 {
+            self.weights.iter_mut().for_each(|weight| *weight /= total);
+        }
+    }
+
+    /// The average weight of the combinations of a starting hand like
+    /// `AKs`. Pairs are never suited, so `suited` is ignored for them.
+    pub fn class_weight(&self, first: Value, second: Value, suited: bool) -> f32 {
+        let suited = suited && first != second;
+        let (total, count) = all_combos()
+            .zip(self.weights.iter())
+            .filter(|((a, b), _)| {
+                let values = (a.value == first && b.value == second)
+                    || (a.value == second && b.value == first);
+                values && (a.suit == b.suit) == suited
+            })
+            .fold((0.0, 0), |(total, count), (_, weight)| {
+                (total + weight, count + 1)
+            });
+        if count == 0 {
+            0.0
+        } else {
+            total / count as f32
+        }
+    }
+}
+
+/// Every hand in a range like `TT+,AJs+`, with a weight of one.
+impl FromStr for Range {
+    type Err = RSPokerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut range = Range::default();
+        for hand in RangeParser::parse_many(s)? {
+            range.set_weight(hand[0], hand[1], 1.0);
+        }
+        Ok(range)
+    }
+}
+
+/// Each hand weighted by how often the chart plays it.
+impl From<&PushFoldRange> for Range {
+    fn from(chart: &PushFoldRange) -> Self {
+        Self {
+            weights: all_combos()
+                .map(|(first, second)| chart.frequency_for_cards(first, second))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Suit;
+
+    #[test]
+    fn test_combo_index() {
+        let indices: Vec<usize> = all_combos()
+            .map(|(first, second)| combo_index(second, first).unwrap())
+            .collect();
+        assert_eq!((0..NUM_COMBOS).collect::<Vec<_>>(), indices);
+
+        let ace = Card::new(Value::Ace, Suit::Spade);
+        assert_eq!(None, combo_index(ace, ace));
+        assert_eq!(0.0, Range::full().weight(ace, ace));
+    }
+
+    #[test]
+    fn test_sample() {
+        let range: Range = "AA,KK".parse().unwrap();
+        let mut dead = CardBitSet::new();
+        for suit in [Suit::Spade, Suit::Heart, Suit::Diamond] {
+            dead.insert(Card::new(Value::Ace, suit));
+        }
+        dead.insert(Card::new(Value::King, Suit::Spade));
+        let mut rng = rand::rng();
+        for _ in 0..20 {
+            let (first, second) = range.sample(dead, &mut rng).unwrap();
+            assert_eq!(Value::King, first.value);
+            assert_eq!(Value::King, second.value);
+            assert!(!dead.contains(first) && !dead.contains(second));
+        }
+        for suit in [Suit::Heart, Suit::Diamond] {
+            dead.insert(Card::new(Value::King, suit));
+        }
+        assert_eq!(None, range.sample(dead, &mut rng));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut range: Range = "AA,KK".parse().unwrap();
+        range.set_weight(
+            Card::new(Value::King, Suit::Spade),
+            Card::new(Value::King, Suit::Heart),
+            4.0,
+        );
+        assert_eq!(15.0, range.total_weight());
+        range.normalize();
+        assert!((range.total_weight() - 1.0).abs() < 1e-6);
+        assert_eq!(12, range.combos().count());
+        assert!((range.class_weight(Value::King, Value::King, false) - 1.5 / 15.0).abs() < 1e-6);
+    }
+}
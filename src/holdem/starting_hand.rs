@@ -5,6 +5,8 @@ use crate::core::{Card, FlatHand, Suit, Value};
 /// `Suitedness::OffSuit` will mean that all cards have the different suit
 /// `Suitedness::Any` makes no promises.
 #[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Suitedness {
     /// All of the cards are the same suit
     Suited,
@@ -19,6 +21,8 @@ pub enum Suitedness {
 ///
 /// Give two values and if you only want suited variants.
 #[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Default {
     /// The first value.
     value_one: Value,
@@ -116,6 +120,8 @@ impl Default {
 /// Starting hand struct to represent where it's one
 /// static card and a range for the other.
 #[derive(Debug, PartialEq, Eq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct SingleCardRange {
     /// First value; this one will not change.
     value_one: Value,
@@ -150,6 +156,8 @@ impl SingleCardRange {
 
 /// Enum to represent all the possible ways to specify a starting hand.
 #[derive(Debug, PartialEq, Eq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum StartingHand {
     /// Default starting hand type. This means that we
     /// specify two cards and their suitedness.
@@ -0,0 +1,100 @@
+//! `proptest` strategies for starting hands and range strings.
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use rs_poker::holdem::{RangeParser, strategy};
+//!
+//! proptest!(|(range in strategy::range())| {
+//!     prop_assert!(!RangeParser::parse_many(&range).unwrap().is_empty());
+//! });
+//! ```
+use proptest::prelude::*;
+
+use crate::core::{Value, strategy::value};
+
+use super::{StartingHand, Suitedness};
+
+pub fn suitedness() -> impl Strategy<Value = Suitedness> {
+    prop_oneof![
+        Just(Suitedness::Suited),
+        Just(Suitedness::OffSuit),
+        Just(Suitedness::Any)
+    ]
+}
+
+pub fn starting_hand() -> impl Strategy<Value = StartingHand> {
+    (value(), value(), suitedness()).prop_map(|(value_one, value_two, suited)| {
+        StartingHand::default(value_one, value_two, suited)
+    })
+}
+
+/// The suffix for `suited`, with pairs left as they are since they can't
+/// be suited.
+fn suffix(suited: Suitedness, is_pair: bool) -> &'static str {
+    match suited {
+        _ if is_pair => "",
+        Suitedness::Suited => "s",
+        Suitedness::OffSuit => "o",
+        Suitedness::Any => "",
+    }
+}
+
+/// One part of a range, like `AKs`, `TT+`, `A5o+` or `JT-87s`.
+pub fn range_part() -> impl Strategy<Value = String> {
+    let single = (value(), value(), suitedness(), any::<bool>()).prop_map(
+        |(first, second, suited, plus)| {
+            let (high, low) = (first.max(second), first.min(second));
+            format!(
+                "{}{}{}{}",
+                high.to_char(),
+                low.to_char(),
+                suffix(suited, high == low),
+                if plus { "+" } else { "" }
+            )
+        },
+    );
+    // Hands with the same gap, from the top pair of values down.
+    let dashed = (1_u8..13, 0_u8..12, 1_u8..12, suitedness()).prop_filter_map(
+        "the bottom of the range has to be a hand",
+        |(top, gap, steps, suited)| {
+            let bottom = top.checked_sub(steps)?.checked_sub(gap)?;
+            let name = |high: u8| {
+                let low = Value::from(high - gap);
+                format!("{}{}", Value::from(high).to_char(), low.to_char())
+            };
+            Some(format!(
+                "{}-{}{}",
+                name(top),
+                name(bottom + gap),
+                suffix(suited, gap == 0)
+            ))
+        },
+    );
+    prop_oneof![3 => single, 1 => dashed]
+}
+
+/// A range string for `RangeParser::parse_many`: up to four parts joined
+/// by commas.
+pub fn range() -> impl Strategy<Value = String> {
+    proptest::collection::vec(range_part(), 1..=4).prop_map(|parts| parts.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::holdem::RangeParser;
+
+    proptest! {
+        #[test]
+        fn test_range_parts_parse(part in range_part()) {
+            prop_assert!(!RangeParser::parse_one(&part).unwrap().is_empty());
+        }
+
+        #[test]
+        fn test_starting_hands_have_two_cards(hand in starting_hand()) {
+            for cards in hand.possible_hands() {
+                prop_assert_eq!(2, cards.len());
+            }
+        }
+    }
+}
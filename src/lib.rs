@@ -175,7 +175,6 @@
 //! let mut competition = HoldemCompetition::new(sim_gen);
 //! let _first_results = competition.run(100).unwrap();
 //! ```
-#![feature(assert_matches)]
 #![deny(clippy::all)]
 
 extern crate rand;
@@ -192,5 +191,23 @@ pub mod holdem;
 /// equity in the total tournament.
 pub mod simulated_icm;
 
+/// Simulate bankrolls from a win rate or past results to find downswings,
+/// risk of ruin and the bankroll needed.
+pub mod variance;
+
+/// Win rates, standard deviations, confidence intervals and downswings
+/// from per-hand results.
+pub mod stats;
+
 #[cfg(feature = "arena")]
 pub mod arena;
+
+/// Protobuf messages for cards, actions, game states and results so that
+/// simulation output can be consumed from other languages.
+#[cfg(feature = "proto")]
+pub mod proto;
+
+/// An HTTP service for starting simulations and following them as they
+/// run, for use as the backend of poker web tools.
+#[cfg(feature = "server")]
+pub mod server;
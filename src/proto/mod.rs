@@ -0,0 +1,752 @@
+//! Protobuf messages for simulation output.
+//!
+//! The schema is in `proto/rs_poker.proto` at the root of the repository,
+//! other languages can generate their bindings from it. The Rust messages
+//! are generated from it with `prost-build` when the crate is built. They
+//! are encoded and decoded with `prost::Message`, and convert to and from
+//! the crate's own types with `From` (to protobuf) and `TryFrom` (from
+//! protobuf, since decoded messages can contain values that are out of
+//! range).
+//!
+//! # Example
+//!
+//! ```
+//! use prost::Message;
+//! use rs_poker::arena::GameState;
+//! use rs_poker::arena::action::Action;
+//! use rs_poker::arena::game_state::Round;
+//! use rs_poker::proto;
+//!
+//! let game_state = GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+//! let action = Action::RoundAdvance(Round::Ante);
+//! let bytes = proto::GameEvent::new(42, &game_state, &action).encode_to_vec();
+//!
+//! let event = proto::GameEvent::decode(&bytes[..]).unwrap();
+//! assert_eq!(42, event.simulation_id().unwrap());
+//! let decoded: GameState = event.game_state.unwrap().try_into().unwrap();
+//! assert_eq!(game_state, decoded);
+//! ```
+mod rs_poker {
+    include!(concat!(env!("OUT_DIR"), "/rs_poker.rs"));
+}
+#[cfg(feature = "grpc")]
+pub mod strategy;
+
+pub use self::rs_poker::*;
+
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::arena;
+use crate::arena::action as arena_action;
+use crate::core;
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum ProtoError {
+    #[error("Invalid value {value} for {field}")]
+    InvalidEnum { field: &'static str, value: i32 },
+    #[error("Missing field {0}")]
+    MissingField(&'static str),
+    #[error("Invalid player index {0}")]
+    InvalidPlayer(u32),
+    #[error("Simulation ids must be 16 bytes, found {0}")]
+    InvalidId(usize),
+}
+
+fn enumeration<E: TryFrom<i32>>(field: &'static str, value: i32) -> Result<E, ProtoError> {
+    E::try_from(value).map_err(|_| ProtoError::InvalidEnum { field, value })
+}
+
+fn player_indexes(set: core::PlayerBitSet) -> Vec<u32> {
+    set.ones().map(|idx| idx as u32).collect()
+}
+
+fn player_set(indexes: &[u32]) -> Result<core::PlayerBitSet, ProtoError> {
+    let mut set = core::PlayerBitSet::default();
+    for &idx in indexes {
+        if idx >= 16 {
+            return Err(ProtoError::InvalidPlayer(idx));
+        }
+        set.enable(idx as usize);
+    }
+    Ok(set)
+}
+
+impl From<core::Card> for Card {
+    fn from(card: core::Card) -> Self {
+        Self {
+            value: card.value as i32,
+            suit: card.suit as i32,
+        }
+    }
+}
+
+impl TryFrom<Card> for core::Card {
+    type Error = ProtoError;
+
+    fn try_from(card: Card) -> Result<Self, Self::Error> {
+        let value: Value = enumeration("value", card.value)?;
+        let suit: Suit = enumeration("suit", card.suit)?;
+        Ok(core::Card::new(
+            core::Value::from(value as u8),
+            core::Suit::from(suit as u8),
+        ))
+    }
+}
+
+fn cards(cards: Vec<Card>) -> Result<Vec<core::Card>, ProtoError> {
+    cards.into_iter().map(core::Card::try_from).collect()
+}
+
+impl From<core::Hand> for Hand {
+    fn from(hand: core::Hand) -> Self {
+        Self {
+            cards: hand.iter().map(Card::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<Hand> for core::Hand {
+    type Error = ProtoError;
+
+    fn try_from(hand: Hand) -> Result<Self, Self::Error> {
+        Ok(core::Hand::new_with_cards(cards(hand.cards)?))
+    }
+}
+
+impl From<core::Rank> for Rank {
+    fn from(rank: core::Rank) -> Self {
+        let (category, value) = match rank {
+            core::Rank::HighCard(v) => (RankCategory::HighCard, v),
+            core::Rank::OnePair(v) => (RankCategory::OnePair, v),
+            core::Rank::TwoPair(v) => (RankCategory::TwoPair, v),
+            core::Rank::ThreeOfAKind(v) => (RankCategory::ThreeOfAKind, v),
+            core::Rank::Straight(v) => (RankCategory::Straight, v),
+            core::Rank::Flush(v) => (RankCategory::Flush, v),
+            core::Rank::FullHouse(v) => (RankCategory::FullHouse, v),
+            core::Rank::FourOfAKind(v) => (RankCategory::FourOfAKind, v),
+            core::Rank::StraightFlush(v) => (RankCategory::StraightFlush, v),
+        };
+        Self {
+            category: category as i32,
+            value,
+        }
+    }
+}
+
+impl TryFrom<Rank> for core::Rank {
+    type Error = ProtoError;
+
+    fn try_from(rank: Rank) -> Result<Self, Self::Error> {
+        let v = rank.value;
+        Ok(match enumeration("category", rank.category)? {
+            RankCategory::HighCard => core::Rank::HighCard(v),
+            RankCategory::OnePair => core::Rank::OnePair(v),
+            RankCategory::TwoPair => core::Rank::TwoPair(v),
+            RankCategory::ThreeOfAKind => core::Rank::ThreeOfAKind(v),
+            RankCategory::Straight => core::Rank::Straight(v),
+            RankCategory::Flush => core::Rank::Flush(v),
+            RankCategory::FullHouse => core::Rank::FullHouse(v),
+            RankCategory::FourOfAKind => core::Rank::FourOfAKind(v),
+            RankCategory::StraightFlush => core::Rank::StraightFlush(v),
+        })
+    }
+}
+
+impl From<arena::game_state::Round> for Round {
+    fn from(round: arena::game_state::Round) -> Self {
+        use arena::game_state::Round as R;
+        match round {
+            R::Starting => Self::Starting,
+            R::Ante => Self::Ante,
+            R::DealPreflop => Self::DealPreflop,
+            R::Preflop => Self::Preflop,
+            R::DealFlop => Self::DealFlop,
+            R::Flop => Self::Flop,
+            R::DealTurn => Self::DealTurn,
+            R::Turn => Self::Turn,
+            R::DealRiver => Self::DealRiver,
+            R::River => Self::River,
+            R::Showdown => Self::Showdown,
+            R::Complete => Self::Complete,
+            R::ThirdStreet => Self::ThirdStreet,
+            R::FourthStreet => Self::FourthStreet,
+            R::FifthStreet => Self::FifthStreet,
+            R::SixthStreet => Self::SixthStreet,
+            R::SeventhStreet => Self::SeventhStreet,
+            R::FirstDraw => Self::FirstDraw,
+            R::SecondDraw => Self::SecondDraw,
+            R::ThirdDraw => Self::ThirdDraw,
+        }
+    }
+}
+
+impl From<Round> for arena::game_state::Round {
+    fn from(round: Round) -> Self {
+        match round {
+            Round::Starting => Self::Starting,
+            Round::Ante => Self::Ante,
+            Round::DealPreflop => Self::DealPreflop,
+            Round::Preflop => Self::Preflop,
+            Round::DealFlop => Self::DealFlop,
+            Round::Flop => Self::Flop,
+            Round::DealTurn => Self::DealTurn,
+            Round::Turn => Self::Turn,
+            Round::DealRiver => Self::DealRiver,
+            Round::River => Self::River,
+            Round::Showdown => Self::Showdown,
+            Round::Complete => Self::Complete,
+            Round::ThirdStreet => Self::ThirdStreet,
+            Round::FourthStreet => Self::FourthStreet,
+            Round::FifthStreet => Self::FifthStreet,
+            Round::SixthStreet => Self::SixthStreet,
+            Round::SeventhStreet => Self::SeventhStreet,
+            Round::FirstDraw => Self::FirstDraw,
+            Round::SecondDraw => Self::SecondDraw,
+            Round::ThirdDraw => Self::ThirdDraw,
+        }
+    }
+}
+
+fn round(field: &'static str, value: i32) -> Result<arena::game_state::Round, ProtoError> {
+    Ok(enumeration::<Round>(field, value)?.into())
+}
+
+impl From<arena::variant::GameVariant> for GameVariant {
+    fn from(variant: arena::variant::GameVariant) -> Self {
+        use arena::variant::GameVariant as V;
+        match variant {
+            V::Holdem => Self::Holdem,
+            V::Omaha => Self::Omaha,
+            V::OmahaHiLo => Self::OmahaHiLo,
+            V::Razz => Self::Razz,
+            V::Stud => Self::Stud,
+            V::StudHiLo => Self::StudHiLo,
+            V::FiveCardDraw => Self::FiveCardDraw,
+            V::DeuceToSevenTripleDraw => Self::DeuceToSevenTripleDraw,
+        }
+    }
+}
+
+impl From<GameVariant> for arena::variant::GameVariant {
+    fn from(variant: GameVariant) -> Self {
+        match variant {
+            GameVariant::Holdem => Self::Holdem,
+            GameVariant::Omaha => Self::Omaha,
+            GameVariant::OmahaHiLo => Self::OmahaHiLo,
+            GameVariant::Razz => Self::Razz,
+            GameVariant::Stud => Self::Stud,
+            GameVariant::StudHiLo => Self::StudHiLo,
+            GameVariant::FiveCardDraw => Self::FiveCardDraw,
+            GameVariant::DeuceToSevenTripleDraw => Self::DeuceToSevenTripleDraw,
+        }
+    }
+}
+
+impl From<&arena_action::AgentAction> for AgentAction {
+    fn from(action: &arena_action::AgentAction) -> Self {
+        let (kind, amount) = match action {
+            arena_action::AgentAction::Fold => (agent_action::Kind::Fold, 0.0),
+            arena_action::AgentAction::Bet(amount) => (agent_action::Kind::Bet, *amount),
+            arena_action::AgentAction::AllIn => (agent_action::Kind::AllIn, 0.0),
+        };
+        Self {
+            kind: kind as i32,
+            amount,
+        }
+    }
+}
+
+impl TryFrom<AgentAction> for arena_action::AgentAction {
+    type Error = ProtoError;
+
+    fn try_from(action: AgentAction) -> Result<Self, Self::Error> {
+        Ok(match enumeration("kind", action.kind)? {
+            agent_action::Kind::Fold => Self::Fold,
+            agent_action::Kind::Bet => Self::Bet(action.amount),
+            agent_action::Kind::AllIn => Self::AllIn,
+        })
+    }
+}
+
+impl From<&arena::game_state::RoundData> for RoundData {
+    fn from(round_data: &arena::game_state::RoundData) -> Self {
+        Self {
+            starting_player_active: player_indexes(round_data.starting_player_active),
+            needs_action: player_indexes(round_data.needs_action),
+            min_raise: round_data.min_raise,
+            bet: round_data.bet,
+            player_bet: round_data.player_bet.clone(),
+            total_bet_count: round_data.total_bet_count.into(),
+            total_raise_count: round_data.total_raise_count.into(),
+            to_act_idx: round_data.to_act_idx as u32,
+        }
+    }
+}
+
+impl TryFrom<RoundData> for arena::game_state::RoundData {
+    type Error = ProtoError;
+
+    fn try_from(round_data: RoundData) -> Result<Self, Self::Error> {
+        Ok(Self {
+            starting_player_active: player_set(&round_data.starting_player_active)?,
+            needs_action: player_set(&round_data.needs_action)?,
+            min_raise: round_data.min_raise,
+            bet: round_data.bet,
+            player_bet: round_data.player_bet,
+            total_bet_count: round_data.total_bet_count.min(u8::MAX.into()) as u8,
+            total_raise_count: round_data.total_raise_count.min(u8::MAX.into()) as u8,
+            to_act_idx: round_data.to_act_idx as usize,
+        })
+    }
+}
+
+impl From<&arena::game_state::StreetSummary> for StreetSummary {
+    fn from(street: &arena::game_state::StreetSummary) -> Self {
+        Self {
+            round: Round::from(street.round) as i32,
+            num_bets: street.num_bets as u32,
+            last_aggressor: street.last_aggressor.map(|idx| idx as u32),
+            player_bet: street.player_bet.clone(),
+        }
+    }
+}
+
+impl TryFrom<StreetSummary> for arena::game_state::StreetSummary {
+    type Error = ProtoError;
+
+    fn try_from(street: StreetSummary) -> Result<Self, Self::Error> {
+        Ok(Self {
+            round: round("round", street.round)?,
+            num_bets: street.num_bets.min(u8::MAX.into()) as u8,
+            last_aggressor: street.last_aggressor.map(|idx| idx as usize),
+            player_bet: street.player_bet,
+        })
+    }
+}
+
+impl From<&arena::GameState> for GameState {
+    fn from(game_state: &arena::GameState) -> Self {
+        Self {
+            num_players: game_state.num_players as u32,
+            player_active: player_indexes(game_state.player_active),
+            player_all_in: player_indexes(game_state.player_all_in),
+            total_pot: game_state.total_pot,
+            stacks: game_state.stacks.clone(),
+            starting_stacks: game_state.starting_stacks.to_vec(),
+            player_bet: game_state.player_bet.clone(),
+            player_winnings: game_state.player_winnings.clone(),
+            big_blind: game_state.big_blind,
+            small_blind: game_state.small_blind,
+            ante: game_state.ante,
+            hands: game_state.hands.iter().copied().map(Hand::from).collect(),
+            dealer_idx: game_state.dealer_idx as u32,
+            round: Round::from(game_state.round) as i32,
+            round_before: Round::from(game_state.round_before) as i32,
+            round_data: Some((&game_state.round_data).into()),
+            board: game_state.board.iter().copied().map(Card::from).collect(),
+            bb_posted: game_state.bb_posted,
+            sb_posted: game_state.sb_posted,
+            streets: game_state.streets.iter().map(StreetSummary::from).collect(),
+            up_cards: game_state
+                .up_cards
+                .iter()
+                .copied()
+                .map(Hand::from)
+                .collect(),
+            variant: GameVariant::from(game_state.variant) as i32,
+            removed_cards: (!game_state.deck).into_iter().map(Card::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<GameState> for arena::GameState {
+    type Error = ProtoError;
+
+    fn try_from(game_state: GameState) -> Result<Self, Self::Error> {
+        Ok(Self {
+            num_players: game_state.num_players as usize,
+            player_active: player_set(&game_state.player_active)?,
+            player_all_in: player_set(&game_state.player_all_in)?,
+            total_pot: game_state.total_pot,
+            stacks: game_state.stacks,
+            starting_stacks: Arc::new(game_state.starting_stacks),
+            player_bet: game_state.player_bet,
+            player_winnings: game_state.player_winnings,
+            big_blind: game_state.big_blind,
+            small_blind: game_state.small_blind,
+            ante: game_state.ante,
+            hands: Arc::new(
+                game_state
+                    .hands
+                    .into_iter()
+                    .map(core::Hand::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            dealer_idx: game_state.dealer_idx as usize,
+            round: round("round", game_state.round)?,
+            round_before: round("round_before", game_state.round_before)?,
+            round_data: game_state
+                .round_data
+                .ok_or(ProtoError::MissingField("round_data"))?
+                .try_into()?,
+            board: Arc::new(arena::game_state::Board::from_vec(cards(game_state.board)?)),
+            bb_posted: game_state.bb_posted,
+            sb_posted: game_state.sb_posted,
+            streets: game_state
+                .streets
+                .into_iter()
+                .map(arena::game_state::StreetSummary::try_from)
+                .collect::<Result<_, _>>()?,
+            up_cards: Arc::new(
+                game_state
+                    .up_cards
+                    .into_iter()
+                    .map(core::Hand::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            variant: enumeration::<GameVariant>("variant", game_state.variant)?.into(),
+            deck: !cards(game_state.removed_cards)?
+                .into_iter()
+                .collect::<core::CardSet>(),
+        })
+    }
+}
+
+impl From<&arena_action::PlayedActionPayload> for PlayedAction {
+    fn from(payload: &arena_action::PlayedActionPayload) -> Self {
+        Self {
+            action: Some((&payload.action).into()),
+            idx: payload.idx as u32,
+            round: Round::from(payload.round) as i32,
+            player_stack: payload.player_stack,
+            starting_pot: payload.starting_pot,
+            final_pot: payload.final_pot,
+            starting_bet: payload.starting_bet,
+            final_bet: payload.final_bet,
+            starting_min_raise: payload.starting_min_raise,
+            final_min_raise: payload.final_min_raise,
+            starting_player_bet: payload.starting_player_bet,
+            final_player_bet: payload.final_player_bet,
+            players_active: player_indexes(payload.players_active),
+            players_all_in: player_indexes(payload.players_all_in),
+        }
+    }
+}
+
+impl TryFrom<PlayedAction> for arena_action::PlayedActionPayload {
+    type Error = ProtoError;
+
+    fn try_from(payload: PlayedAction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            action: payload
+                .action
+                .ok_or(ProtoError::MissingField("action"))?
+                .try_into()?,
+            idx: payload.idx as usize,
+            round: round("round", payload.round)?,
+            player_stack: payload.player_stack,
+            starting_pot: payload.starting_pot,
+            final_pot: payload.final_pot,
+            starting_bet: payload.starting_bet,
+            final_bet: payload.final_bet,
+            starting_min_raise: payload.starting_min_raise,
+            final_min_raise: payload.final_min_raise,
+            starting_player_bet: payload.starting_player_bet,
+            final_player_bet: payload.final_player_bet,
+            players_active: player_set(&payload.players_active)?,
+            players_all_in: player_set(&payload.players_all_in)?,
+        })
+    }
+}
+
+impl From<&arena_action::Action> for Action {
+    fn from(action: &arena_action::Action) -> Self {
+        use arena_action::Action as A;
+        let event = match action {
+            A::GameStart(payload) => action::Event::GameStart(GameStart {
+                ante: payload.ante,
+                small_blind: payload.small_blind,
+                big_blind: payload.big_blind,
+            }),
+            A::PlayerSit(payload) => action::Event::PlayerSit(PlayerSit {
+                idx: payload.idx as u32,
+                player_stack: payload.player_stack,
+            }),
+            A::DealStartingHand(payload) => action::Event::DealStartingHand(DealStartingHand {
+                card: Some(payload.card.into()),
+                idx: payload.idx as u32,
+            }),
+            A::RoundAdvance(round) => action::Event::RoundAdvance(Round::from(*round) as i32),
+            A::PlayedAction(payload) => action::Event::PlayedAction(payload.into()),
+            A::FailedAction(payload) => action::Event::FailedAction(FailedAction {
+                action: Some((&payload.action).into()),
+                result: Some((&payload.result).into()),
+            }),
+            A::ForcedBet(payload) => action::Event::ForcedBet(ForcedBet {
+                bet: payload.bet,
+                player_stack: payload.player_stack,
+                idx: payload.idx as u32,
+                forced_bet_type: match payload.forced_bet_type {
+                    arena_action::ForcedBetType::Ante => ForcedBetType::Ante,
+                    arena_action::ForcedBetType::SmallBlind => ForcedBetType::SmallBlind,
+                    arena_action::ForcedBetType::BigBlind => ForcedBetType::BigBlind,
+                    arena_action::ForcedBetType::BringIn => ForcedBetType::BringIn,
+                } as i32,
+            }),
+            A::DealCommunity(card) => action::Event::DealCommunity((*card).into()),
+            A::Award(payload) => action::Event::Award(Award {
+                total_pot: payload.total_pot,
+                award_amount: payload.award_amount,
+                rank: payload.rank.map(Rank::from),
+                hand: payload.hand.map(Hand::from),
+                idx: payload.idx as u32,
+            }),
+            A::ShowHand(payload) => action::Event::ShowHand(ShowHand {
+                idx: payload.idx as u32,
+                hand: payload.hand.map(Hand::from),
+            }),
+            A::Draw(payload) => action::Event::Draw(Draw {
+                idx: payload.idx as u32,
+                discarded: Some(payload.discarded.into()),
+                drawn: Some(payload.drawn.into()),
+            }),
+        };
+        Self { event: Some(event) }
+    }
+}
+
+impl TryFrom<Action> for arena_action::Action {
+    type Error = ProtoError;
+
+    fn try_from(action: Action) -> Result<Self, Self::Error> {
+        use arena_action::Action as A;
+        Ok(
+            match action.event.ok_or(ProtoError::MissingField("event"))? {
+                action::Event::GameStart(payload) => A::GameStart(arena_action::GameStartPayload {
+                    ante: payload.ante,
+                    small_blind: payload.small_blind,
+                    big_blind: payload.big_blind,
+                }),
+                action::Event::PlayerSit(payload) => A::PlayerSit(arena_action::PlayerSitPayload {
+                    idx: payload.idx as usize,
+                    player_stack: payload.player_stack,
+                }),
+                action::Event::DealStartingHand(payload) => {
+                    A::DealStartingHand(arena_action::DealStartingHandPayload {
+                        card: payload
+                            .card
+                            .ok_or(ProtoError::MissingField("card"))?
+                            .try_into()?,
+                        idx: payload.idx as usize,
+                    })
+                }
+                action::Event::RoundAdvance(value) => {
+                    A::RoundAdvance(round("round_advance", value)?)
+                }
+                action::Event::PlayedAction(payload) => A::PlayedAction(payload.try_into()?),
+                action::Event::FailedAction(payload) => {
+                    A::FailedAction(arena_action::FailedActionPayload {
+                        action: payload
+                            .action
+                            .ok_or(ProtoError::MissingField("action"))?
+                            .try_into()?,
+                        result: payload
+                            .result
+                            .ok_or(ProtoError::MissingField("result"))?
+                            .try_into()?,
+                    })
+                }
+                action::Event::ForcedBet(payload) => A::ForcedBet(arena_action::ForcedBetPayload {
+                    bet: payload.bet,
+                    player_stack: payload.player_stack,
+                    idx: payload.idx as usize,
+                    forced_bet_type: match enumeration("forced_bet_type", payload.forced_bet_type)?
+                    {
+                        ForcedBetType::Ante => arena_action::ForcedBetType::Ante,
+                        ForcedBetType::SmallBlind => arena_action::ForcedBetType::SmallBlind,
+                        ForcedBetType::BigBlind => arena_action::ForcedBetType::BigBlind,
+                        ForcedBetType::BringIn => arena_action::ForcedBetType::BringIn,
+                    },
+                }),
+                action::Event::DealCommunity(card) => A::DealCommunity(card.try_into()?),
+                action::Event::Award(payload) => A::Award(arena_action::AwardPayload {
+                    total_pot: payload.total_pot,
+                    award_amount: payload.award_amount,
+                    rank: payload.rank.map(core::Rank::try_from).transpose()?,
+                    hand: payload.hand.map(core::Hand::try_from).transpose()?,
+                    idx: payload.idx as usize,
+                }),
+                action::Event::ShowHand(payload) => A::ShowHand(arena_action::ShowHandPayload {
+                    idx: payload.idx as usize,
+                    hand: payload.hand.map(core::Hand::try_from).transpose()?,
+                }),
+                action::Event::Draw(payload) => A::Draw(arena_action::DrawPayload {
+                    idx: payload.idx as usize,
+                    discarded: payload
+                        .discarded
+                        .ok_or(ProtoError::MissingField("discarded"))?
+                        .try_into()?,
+                    drawn: payload
+                        .drawn
+                        .ok_or(ProtoError::MissingField("drawn"))?
+                        .try_into()?,
+                }),
+            },
+        )
+    }
+}
+
+impl GameEvent {
+    pub fn new(id: u128, game_state: &arena::GameState, action: &arena_action::Action) -> Self {
+        Self {
+            id: id.to_be_bytes().to_vec(),
+            action: Some(action.into()),
+            game_state: Some(game_state.into()),
+        }
+    }
+
+    /// The id of the simulation this event came from.
+    pub fn simulation_id(&self) -> Result<u128, ProtoError> {
+        let bytes: [u8; 16] = self
+            .id
+            .as_slice()
+            .try_into()
+            .map_err(|_| ProtoError::InvalidId(self.id.len()))?;
+        Ok(u128::from_be_bytes(bytes))
+    }
+}
+
+impl From<&arena::competition::TournamentResults> for TournamentResults {
+    fn from(results: &arena::competition::TournamentResults) -> Self {
+        Self {
+            places: results.places().iter().map(|p| *p as u32).collect(),
+            max_stacks: results.max_stacks().to_vec(),
+            rounds: results.rounds() as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use crate::arena::agent::{CallingAgent, RandomAgent};
+    use crate::arena::historian::VecHistorian;
+    use crate::arena::{Agent, HoldemSimulationBuilder};
+
+    use super::*;
+
+    #[test]
+    fn test_all_cards() {
+        for card in core::Deck::default().iter() {
+            let encoded = Card::from(card).encode_to_vec();
+            let decoded = Card::decode(&encoded[..]).unwrap();
+            assert_eq!(card, core::Card::try_from(decoded).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_simulation_round_trip() {
+        let historian = VecHistorian::default();
+        let records = historian.get_storage();
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::<RandomAgent>::default(),
+            Box::<RandomAgent>::default(),
+            Box::<CallingAgent>::default(),
+        ];
+        let mut sim = HoldemSimulationBuilder::default()
+            .game_state(arena::GameState::new_starting(
+                vec![100.0; 3],
+                10.0,
+                5.0,
+                1.0,
+                0,
+            ))
+            .agents(agents)
+            .historians(vec![Box::new(historian)])
+            .build()
+            .unwrap();
+        sim.run(&mut rand::rng());
+
+        for record in records.borrow().iter() {
+            let event = GameEvent::new(sim.id, &record.after_game_state, &record.action);
+            let decoded = GameEvent::decode(&event.encode_to_vec()[..]).unwrap();
+            assert_eq!(sim.id, decoded.simulation_id().unwrap());
+
+            let action: arena_action::Action = decoded.action.unwrap().try_into().unwrap();
+            assert_eq!(record.action, action);
+            let game_state: arena::GameState = decoded.game_state.unwrap().try_into().unwrap();
+            assert_eq!(record.after_game_state, game_state);
+        }
+    }
+
+    #[test]
+    fn test_variant_round_trip() {
+        let mut game_state = arena::GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        game_state.variant = arena::variant::GameVariant::Omaha;
+        let encoded = GameState::from(&game_state).encode_to_vec();
+        let decoded: arena::GameState =
+            GameState::decode(&encoded[..]).unwrap().try_into().unwrap();
+        assert_eq!(arena::variant::GameVariant::Omaha, decoded.variant);
+    }
+
+    #[test]
+    fn test_deck_round_trip() {
+        let mut game_state = arena::GameState::new_starting(vec![100.0; 2], 10.0, 5.0, 0.0, 0);
+        game_state.deck = core::Hand::new_from_str("AsKsQsJsTs9s8s7s6s")
+            .unwrap()
+            .into();
+        let encoded = GameState::from(&game_state).encode_to_vec();
+        let decoded: arena::GameState =
+            GameState::decode(&encoded[..]).unwrap().try_into().unwrap();
+        assert_eq!(game_state.deck, decoded.deck);
+
+        // Nothing removed is the whole deck.
+        let decoded: arena::GameState = GameState::from(&arena::GameState::new_starting(
+            vec![100.0; 2],
+            10.0,
+            5.0,
+            0.0,
+            0,
+        ))
+        .try_into()
+        .unwrap();
+        assert_eq!(core::CardSet::default(), decoded.deck);
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let card = Card { value: 13, suit: 0 };
+        assert_eq!(
+            Err(ProtoError::InvalidEnum {
+                field: "value",
+                value: 13
+            }),
+            core::Card::try_from(card)
+        );
+
+        let action = Action { event: None };
+        assert_eq!(
+            Err(ProtoError::MissingField("event")),
+            arena_action::Action::try_from(action)
+        );
+
+        let event = GameEvent {
+            id: vec![1, 2, 3],
+            ..Default::default()
+        };
+        assert_eq!(Err(ProtoError::InvalidId(3)), event.simulation_id());
+    }
+
+    #[test]
+    fn test_tournament_results() {
+        let results = arena::competition::TournamentResults::new(&[100.0, 50.0]);
+        let decoded =
+            TournamentResults::decode(&TournamentResults::from(&results).encode_to_vec()[..])
+                .unwrap();
+        assert_eq!(vec![100.0, 50.0], decoded.max_stacks);
+        assert_eq!(2, decoded.places.len());
+    }
+}
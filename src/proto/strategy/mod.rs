@@ -0,0 +1,225 @@
+//! A gRPC server that answers strategy queries from a trained
+//! `StrategyProfile`, so bots and analysis tools can use a strategy without
+//! linking the solver.
+//!
+//! The schema is in `proto/strategy.proto`. `BatchQuery` answers many
+//! decision points in one round trip, which is what a bot evaluating a
+//! whole hand or a UI rendering a tree should use.
+//!
+//! The `strategy_server` binary serves a profile saved with
+//! `StrategyProfile::save_to_file`:
+//!
+//! ```text
+//! cargo run --release --features grpc --bin strategy_server -- profile.json 127.0.0.1:50051
+//! ```
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rs_poker::arena::cfr::StrategyProfile;
+//! use rs_poker::proto::strategy::{
+//!     StrategyQuery, StrategyService, strategy_client::StrategyClient,
+//! };
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let profile = StrategyProfile::load_from_file("profile.json".as_ref())?;
+//! let addr = "127.0.0.1:50051".parse()?;
+//! tokio::spawn(StrategyService::new(profile).serve(addr));
+//!
+//! let mut client = StrategyClient::connect("http://127.0.0.1:50051").await?;
+//! let query = StrategyQuery {
+//!     player_idx: 0,
+//!     path: vec![0],
+//! };
+//! let reply = client.query(query).await?.into_inner();
+//! println!("{:?}", reply.probabilities);
+//! # Ok(())
+//! # }
+//! ```
+mod rs_poker_strategy {
+    include!(concat!(env!("OUT_DIR"), "/rs_poker.strategy.rs"));
+}
+
+pub use self::rs_poker_strategy::*;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::arena::cfr::StrategyProfile;
+
+use self::strategy_server::{Strategy, StrategyServer};
+
+/// The most queries accepted in a single `BatchQuery` call by default.
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 4096;
+
+/// Serves a `StrategyProfile` over gRPC.
+#[derive(Debug, Clone)]
+pub struct StrategyService {
+    profile: Arc<StrategyProfile>,
+    max_batch_size: usize,
+}
+
+impl StrategyService {
+    pub fn new(profile: StrategyProfile) -> Self {
+        Self {
+            profile: Arc::new(profile),
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+        }
+    }
+
+    /// Reject batches with more than `max_batch_size` queries.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    pub fn profile(&self) -> &StrategyProfile {
+        &self.profile
+    }
+
+    /// Wrap the service so it can be added to a `tonic` server.
+    pub fn into_server(self) -> StrategyServer<Self> {
+        StrategyServer::new(self)
+    }
+
+    /// Serve on `addr` until the server fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        Server::builder()
+            .add_service(self.into_server())
+            .serve(addr)
+            .await
+    }
+
+    fn answer(&self, query: &StrategyQuery) -> StrategyReply {
+        let path: Vec<usize> = query.path.iter().map(|&idx| idx as usize).collect();
+        match self.profile.get(query.player_idx as usize, &path) {
+            Some(probabilities) => StrategyReply {
+                found: true,
+                probabilities: probabilities.to_vec(),
+            },
+            None => StrategyReply::default(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Strategy for StrategyService {
+    async fn query(
+        &self,
+        request: Request<StrategyQuery>,
+    ) -> Result<Response<StrategyReply>, Status> {
+        Ok(Response::new(self.answer(request.get_ref())))
+    }
+
+    async fn batch_query(
+        &self,
+        request: Request<BatchQueryRequest>,
+    ) -> Result<Response<BatchQueryReply>, Status> {
+        let queries = &request.get_ref().queries;
+        if queries.len() > self.max_batch_size {
+            return Err(Status::invalid_argument(format!(
+                "batch of {} queries is larger than the limit of {}",
+                queries.len(),
+                self.max_batch_size
+            )));
+        }
+        let replies = queries.iter().map(|query| self.answer(query)).collect();
+        Ok(Response::new(BatchQueryReply { replies }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::transport::server::TcpIncoming;
+
+    use super::strategy_client::StrategyClient;
+    use super::*;
+
+    async fn start(service: StrategyService) -> StrategyClient<tonic::transport::Channel> {
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = incoming.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(service.into_server())
+                .serve_with_incoming(incoming),
+        );
+        StrategyClient::connect(format!("http://{addr}"))
+            .await
+            .unwrap()
+    }
+
+    fn profile() -> StrategyProfile {
+        let mut profile = StrategyProfile::new();
+        profile.insert(0, vec![0], vec![0.25, 0.75]);
+        profile.insert(1, vec![0, 1], vec![1.0, 0.0, 0.0]);
+        profile
+    }
+
+    #[tokio::test]
+    async fn test_query() {
+        let mut client = start(StrategyService::new(profile())).await;
+
+        let reply = client
+            .query(StrategyQuery {
+                player_idx: 0,
+                path: vec![0],
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(reply.found);
+        assert_eq!(vec![0.25, 0.75], reply.probabilities);
+
+        let missing = client
+            .query(StrategyQuery {
+                player_idx: 1,
+                path: vec![0],
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(StrategyReply::default(), missing);
+    }
+
+    #[tokio::test]
+    async fn test_batch_query() {
+        let mut client = start(StrategyService::new(profile()).with_max_batch_size(3)).await;
+
+        let queries = vec![
+            StrategyQuery {
+                player_idx: 1,
+                path: vec![0, 1],
+            },
+            StrategyQuery {
+                player_idx: 2,
+                path: vec![],
+            },
+            StrategyQuery {
+                player_idx: 0,
+                path: vec![0],
+            },
+        ];
+        let replies = client
+            .batch_query(BatchQueryRequest {
+                queries: queries.clone(),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .replies;
+        let found: Vec<_> = replies.iter().map(|reply| reply.found).collect();
+        assert_eq!(vec![true, false, true], found);
+        assert_eq!(vec![1.0, 0.0, 0.0], replies[0].probabilities);
+
+        let mut too_many = queries.clone();
+        too_many.push(queries[0].clone());
+        let status = client
+            .batch_query(BatchQueryRequest { queries: too_many })
+            .await
+            .unwrap_err();
+        assert_eq!(tonic::Code::InvalidArgument, status.code());
+    }
+}
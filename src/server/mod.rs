@@ -0,0 +1,487 @@
+//! An HTTP service, built on axum, that runs holdem simulations and lets
+//! clients follow them as they play out.
+//!
+//! | Method   | Path                       | Description                                    |
+//! |----------|----------------------------|------------------------------------------------|
+//! | `POST`   | `/simulations`             | Start a simulation from a `SimulationRequest`. |
+//! | `GET`    | `/simulations`             | Summaries of every simulation.                 |
+//! | `GET`    | `/simulations/{id}`        | Status, and the final game state once done.    |
+//! | `GET`    | `/simulations/{id}/events` | Server sent events, one per action.            |
+//! | `DELETE` | `/simulations/{id}`        | Forget a simulation.                           |
+//!
+//! The events stream starts with every action that has already happened,
+//! then follows the simulation live and ends when it's done. Each event's
+//! data is a `SimulationEvent` as JSON.
+//!
+//! `SimulationServer::router` returns a plain `axum::Router`, so the
+//! service can be served on its own or nested into a bigger application.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rs_poker::server::SimulationServer;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
+//! SimulationServer::new().serve(listener).await
+//! # }
+//! ```
+//!
+//! ```text
+//! curl -X POST localhost:8080/simulations -H 'content-type: application/json' \
+//!     -d '{"stacks": [100, 100], "big_blind": 10, "small_blind": 5}'
+//! curl -N localhost:8080/simulations/0/events
+//! ```
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::arena::action::Action;
+use crate::arena::agent::{AllInAgent, CallingAgent, FoldingAgent, RandomAgent};
+use crate::arena::historian::FnHistorian;
+use crate::arena::{Agent, GameState, HoldemSimulationBuilder};
+
+/// The built in agents that can be seated through the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentKind {
+    Calling,
+    Folding,
+    AllIn,
+    Random,
+}
+
+impl AgentKind {
+    fn build(self) -> Box<dyn Agent> {
+        match self {
+            AgentKind::Calling => Box::<CallingAgent>::default(),
+            AgentKind::Folding => Box::<FoldingAgent>::default(),
+            AgentKind::AllIn => Box::<AllInAgent>::default(),
+            AgentKind::Random => Box::<RandomAgent>::default(),
+        }
+    }
+}
+
+/// The body of `POST /simulations`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationRequest {
+    pub stacks: Vec<f32>,
+    pub big_blind: f32,
+    pub small_blind: f32,
+    #[serde(default)]
+    pub ante: f32,
+    #[serde(default)]
+    pub dealer_idx: usize,
+    /// One agent per stack. Every seat gets a random agent when this is
+    /// left out.
+    #[serde(default)]
+    pub agents: Option<Vec<AgentKind>>,
+}
+
+impl SimulationRequest {
+    fn validate(&self) -> Result<(), String> {
+        let num_players = self.stacks.len();
+        if !(2..=9).contains(&num_players) {
+            return Err(format!("expected 2 to 9 stacks, found {num_players}"));
+        }
+        if self.dealer_idx >= num_players {
+            return Err(format!("dealer_idx {} is out of range", self.dealer_idx));
+        }
+        let amounts = [self.big_blind, self.small_blind, self.ante];
+        if self
+            .stacks
+            .iter()
+            .chain(&amounts)
+            .any(|amount| !amount.is_finite() || *amount < 0.0)
+        {
+            return Err("stacks, blinds and ante must be non-negative numbers".to_string());
+        }
+        if let Some(agents) = &self.agents
+            && agents.len() != num_players
+        {
+            return Err(format!(
+                "expected {num_players} agents, found {}",
+                agents.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A single action, and the game state after it, in a simulation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimulationEvent {
+    /// The position of the event in the simulation, starting at zero.
+    pub index: usize,
+    pub action: Action,
+    pub game_state: GameState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulationStatus {
+    Running,
+    Complete,
+    /// The simulation panicked. The events up to that point are kept.
+    Failed,
+}
+
+/// The body returned when starting, listing or fetching simulations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimulationSummary {
+    pub id: u64,
+    pub status: SimulationStatus,
+    pub num_events: usize,
+    /// The final game state, once the simulation is complete.
+    pub result: Option<GameState>,
+}
+
+#[derive(Debug)]
+struct SimulationEntry {
+    status: SimulationStatus,
+    events: Vec<SimulationEvent>,
+    result: Option<GameState>,
+    // Open event streams. These are dropped when the simulation ends,
+    // which ends the streams.
+    subscribers: Vec<UnboundedSender<SimulationEvent>>,
+}
+
+impl SimulationEntry {
+    fn summary(&self, id: u64) -> SimulationSummary {
+        SimulationSummary {
+            id,
+            status: self.status,
+            num_events: self.events.len(),
+            result: self.result.clone(),
+        }
+    }
+
+    fn push(&mut self, action: Action, game_state: &GameState) {
+        let event = SimulationEvent {
+            index: self.events.len(),
+            action,
+            game_state: game_state.clone(),
+        };
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        self.events.push(event);
+    }
+
+    fn finish(&mut self, status: SimulationStatus, result: Option<GameState>) {
+        self.status = status;
+        self.result = result;
+        self.subscribers.clear();
+    }
+}
+
+type SharedEntry = Arc<Mutex<SimulationEntry>>;
+
+/// Runs simulations for HTTP clients and keeps them until they are
+/// deleted.
+///
+/// Clones share the same simulations.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationServer {
+    simulations: Arc<Mutex<BTreeMap<u64, SharedEntry>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl SimulationServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The routes of the service, ready to be served or nested.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/simulations", get(list_simulations).post(start_simulation))
+            .route(
+                "/simulations/{id}",
+                get(get_simulation).delete(delete_simulation),
+            )
+            .route("/simulations/{id}/events", get(simulation_events))
+            .with_state(self.clone())
+    }
+
+    /// Serve the routes on `listener` until the server fails.
+    pub async fn serve(self, listener: tokio::net::TcpListener) -> std::io::Result<()> {
+        axum::serve(listener, self.router()).await
+    }
+
+    /// Start a simulation on the blocking thread pool and return its id.
+    pub fn start(&self, request: SimulationRequest) -> Result<u64, String> {
+        request.validate()?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let entry = Arc::new(Mutex::new(SimulationEntry {
+            status: SimulationStatus::Running,
+            events: vec![],
+            result: None,
+            subscribers: vec![],
+        }));
+        self.simulations.lock().unwrap().insert(id, entry.clone());
+
+        let sim_entry = entry.clone();
+        let run = tokio::task::spawn_blocking(move || {
+            let num_players = request.stacks.len();
+            let agents = request
+                .agents
+                .unwrap_or_else(|| vec![AgentKind::Random; num_players])
+                .into_iter()
+                .map(AgentKind::build)
+                .collect();
+            let game_state = GameState::new_starting(
+                request.stacks,
+                request.big_blind,
+                request.small_blind,
+                request.ante,
+                request.dealer_idx,
+            );
+            let historian = FnHistorian::new(move |_id, game_state: &GameState, action| {
+                sim_entry.lock().unwrap().push(action, game_state);
+                Ok(())
+            });
+            let mut sim = HoldemSimulationBuilder::default()
+                .game_state(game_state)
+                .agents(agents)
+                .historians(vec![Box::new(historian)])
+                .build()
+                .expect("game state is always set");
+            sim.run(&mut rand::rng());
+            sim.game_state
+        });
+        tokio::spawn(async move {
+            let outcome = run.await;
+            let mut entry = entry.lock().unwrap();
+            match outcome {
+                Ok(game_state) => entry.finish(SimulationStatus::Complete, Some(game_state)),
+                Err(_) => entry.finish(SimulationStatus::Failed, None),
+            }
+        });
+        Ok(id)
+    }
+
+    /// The current state of a simulation.
+    pub fn summary(&self, id: u64) -> Option<SimulationSummary> {
+        self.entry(id)
+            .map(|entry| entry.lock().unwrap().summary(id))
+    }
+
+    fn entry(&self, id: u64) -> Option<SharedEntry> {
+        self.simulations.lock().unwrap().get(&id).cloned()
+    }
+}
+
+/// An error returned to HTTP clients as `{"error": "..."}`.
+#[derive(Debug)]
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn not_found(id: u64) -> Self {
+        Self(StatusCode::NOT_FOUND, format!("no simulation with id {id}"))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+async fn start_simulation(
+    State(server): State<SimulationServer>,
+    Json(request): Json<SimulationRequest>,
+) -> Result<(StatusCode, Json<SimulationSummary>), ApiError> {
+    let id = server
+        .start(request)
+        .map_err(|message| ApiError(StatusCode::BAD_REQUEST, message))?;
+    let summary = server.summary(id).ok_or(ApiError::not_found(id))?;
+    Ok((StatusCode::CREATED, Json(summary)))
+}
+
+async fn list_simulations(State(server): State<SimulationServer>) -> Json<Vec<SimulationSummary>> {
+    let simulations = server.simulations.lock().unwrap();
+    Json(
+        simulations
+            .iter()
+            .map(|(id, entry)| entry.lock().unwrap().summary(*id))
+            .collect(),
+    )
+}
+
+async fn get_simulation(
+    State(server): State<SimulationServer>,
+    Path(id): Path<u64>,
+) -> Result<Json<SimulationSummary>, ApiError> {
+    server.summary(id).map(Json).ok_or(ApiError::not_found(id))
+}
+
+async fn delete_simulation(
+    State(server): State<SimulationServer>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    server
+        .simulations
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .map(|_| StatusCode::NO_CONTENT)
+        .ok_or(ApiError::not_found(id))
+}
+
+async fn simulation_events(
+    State(server): State<SimulationServer>,
+    Path(id): Path<u64>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let entry = server.entry(id).ok_or(ApiError::not_found(id))?;
+    let (sender, receiver) = unbounded_channel();
+    {
+        // Replaying and subscribing under the same lock means no event is
+        // missed or sent twice.
+        let mut entry = entry.lock().unwrap();
+        for event in &entry.events {
+            let _ = sender.send(event.clone());
+        }
+        if entry.status == SimulationStatus::Running {
+            entry.subscribers.push(sender);
+        }
+    }
+
+    let stream = UnboundedReceiverStream::new(receiver).map(|event| {
+        Ok(Event::default()
+            .event("action")
+            .json_data(&event)
+            .expect("events always serialize"))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn call(
+        server: &SimulationServer,
+        method: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, String) {
+        let mut request = Request::builder().method(method).uri(uri);
+        let body = match body {
+            Some(body) => {
+                request = request.header("content-type", "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+        let response = server
+            .router()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_run_simulation() {
+        let server = SimulationServer::new();
+        let request = serde_json::json!({
+            "stacks": [100.0, 100.0, 100.0],
+            "big_blind": 10.0,
+            "small_blind": 5.0,
+            "agents": ["calling", "calling", "all_in"],
+        });
+        let (status, body) = call(&server, "POST", "/simulations", Some(request)).await;
+        assert_eq!(StatusCode::CREATED, status);
+        let id = serde_json::from_str::<Value>(&body).unwrap()["id"]
+            .as_u64()
+            .unwrap();
+
+        // The stream ends once the simulation is done.
+        let (status, events) =
+            call(&server, "GET", &format!("/simulations/{id}/events"), None).await;
+        assert_eq!(StatusCode::OK, status);
+        let data: Vec<Value> = events
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        assert!(!data.is_empty());
+        for (idx, event) in data.iter().enumerate() {
+            assert_eq!(idx as u64, event["index"].as_u64().unwrap());
+        }
+
+        let summary = server.summary(id).unwrap();
+        assert_eq!(SimulationStatus::Complete, summary.status);
+        assert_eq!(data.len(), summary.num_events);
+        let result = summary.result.unwrap();
+        let total: f32 = result.stacks.iter().sum();
+        assert!((300.0 - total).abs() < 1e-3);
+
+        // Following a finished simulation replays it.
+        let (_, replay) = call(&server, "GET", &format!("/simulations/{id}/events"), None).await;
+        assert_eq!(events, replay);
+
+        let (status, list) = call(&server, "GET", "/simulations", None).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(1, serde_json::from_str::<Vec<Value>>(&list).unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let server = SimulationServer::new();
+        let request = serde_json::json!({
+            "stacks": [100.0, 100.0],
+            "big_blind": 10.0,
+            "small_blind": 5.0,
+            "agents": ["calling"],
+        });
+        let (status, body) = call(&server, "POST", "/simulations", Some(request)).await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        assert!(body.contains("expected 2 agents"));
+
+        let (status, _) = call(&server, "GET", "/simulations/7", None).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        let (status, _) = call(&server, "GET", "/simulations/7/events", None).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+
+    #[tokio::test]
+    async fn test_delete() {
+        let server = SimulationServer::new();
+        let request = SimulationRequest {
+            stacks: vec![50.0; 4],
+            big_blind: 2.0,
+            small_blind: 1.0,
+            ante: 0.0,
+            dealer_idx: 3,
+            agents: None,
+        };
+        let id = server.start(request).unwrap();
+
+        let (status, _) = call(&server, "DELETE", &format!("/simulations/{id}"), None).await;
+        assert_eq!(StatusCode::NO_CONTENT, status);
+        assert_eq!(None, server.summary(id));
+        let (status, _) = call(&server, "DELETE", &format!("/simulations/{id}"), None).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+}
@@ -19,7 +19,9 @@
 //! oeverhead needed.
 //! - We can change the players skill easily. Since ICM just looks at the
 //!   percentage or outstanding chips
-use rand::{Rng, rng, seq::SliceRandom};
+use rand::{Rng, seq::SliceRandom};
+
+use crate::core::rng;
 
 /// Simulate a tournament by running a series of all
 /// in showdowns. This helps deterimine the value of each
@@ -33,7 +35,7 @@ use rand::{Rng, rng, seq::SliceRandom};
 pub fn simulate_icm_tournament(chip_stacks: &[i32], payments: &[i32]) -> Vec<i32> {
     // We're going to mutate in place so move the chip stacks into a mutable vector.
     let mut remaining_stacks: Vec<i32> = chip_stacks.into();
-    // Thread local rng.
+    // The crate rng, so a seed makes this reproducible.
     let mut rng = rng();
     // Which place in the next player to bust will get.
     let mut next_place = remaining_stacks.len() - 1;
@@ -0,0 +1,236 @@
+//! Summaries of per-hand results in big blinds: the win rate in bb/100, its
+//! standard deviation and confidence interval, and the biggest downswing.
+//!
+//! `ResultStats` is a running summary, so it doesn't keep the results
+//! themselves and two summaries can be merged. That makes it cheap to keep
+//! one per seat for a long batch of simulations, or one per table when
+//! running them in parallel.
+//!
+//! ```
+//! use rs_poker::stats::ResultStats;
+//!
+//! let stats: ResultStats = [2.0, -1.0, -1.0, 3.0, -1.0].into_iter().collect();
+//! assert_eq!(5, stats.hands());
+//! assert!((stats.bb_per_100() - 40.0).abs() < 1e-9);
+//! // From 2 after the first hand down to 0 after the third.
+//! assert_eq!(2.0, stats.max_downswing());
+//!
+//! let (low, high) = stats.confidence_interval(1.96);
+//! assert!(low < 40.0 && 40.0 < high);
+//! ```
+use crate::variance::WinRate;
+
+/// A running summary of per-hand results in big blinds, in the order they
+/// were played.
+///
+/// The mean and variance are kept with Welford's method so they stay
+/// accurate over millions of hands.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResultStats {
+    hands: usize,
+    mean: f64,
+    // Sum of squared differences from the mean.
+    m2: f64,
+    total: f64,
+    // Highest and lowest running totals, counting the start at zero.
+    peak: f64,
+    trough: f64,
+    max_downswing: f64,
+    biggest_win: f64,
+    biggest_loss: f64,
+}
+
+impl ResultStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the result of the next hand.
+    pub fn push(&mut self, result: f64) {
+        self.hands += 1;
+        let delta = result - self.mean;
+        self.mean += delta / self.hands as f64;
+        self.m2 += delta * (result - self.mean);
+
+        self.total += result;
+        self.peak = self.peak.max(self.total);
+        self.trough = self.trough.min(self.total);
+        self.max_downswing = self.max_downswing.max(self.peak - self.total);
+        self.biggest_win = self.biggest_win.max(result);
+        self.biggest_loss = self.biggest_loss.min(result);
+    }
+
+    /// Add the results of `other` as if they were played after these.
+    ///
+    /// The win rate and standard deviation don't depend on the order, but
+    /// downswings can run from the end of these results into `other`.
+    pub fn merge(&mut self, other: &ResultStats) {
+        if other.hands == 0 {
+            return;
+        }
+        let hands = self.hands + other.hands;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.hands as f64 / hands as f64;
+        self.m2 += other.m2 + delta * delta * self.hands as f64 * other.hands as f64 / hands as f64;
+        self.hands = hands;
+
+        self.max_downswing = self
+            .max_downswing
+            .max(other.max_downswing)
+            .max(self.peak - (self.total + other.trough));
+        self.peak = self.peak.max(self.total + other.peak);
+        self.trough = self.trough.min(self.total + other.trough);
+        self.total += other.total;
+        self.biggest_win = self.biggest_win.max(other.biggest_win);
+        self.biggest_loss = self.biggest_loss.min(other.biggest_loss);
+    }
+
+    pub fn hands(&self) -> usize {
+        self.hands
+    }
+
+    /// The sum of all results.
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+
+    /// The average result per hand.
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// The standard deviation of a single hand's result.
+    pub fn std_dev(&self) -> f64 {
+        if self.hands == 0 {
+            0.0
+        } else {
+            (self.m2 / self.hands as f64).sqrt()
+        }
+    }
+
+    pub fn bb_per_100(&self) -> f64 {
+        self.mean * 100.0
+    }
+
+    /// The standard deviation of the result of 100 hands. Hands are
+    /// independent so that's ten times the standard deviation of one.
+    pub fn std_dev_per_100(&self) -> f64 {
+        self.std_dev() * 10.0
+    }
+
+    /// The win rate, for answering bankroll questions with the `variance`
+    /// module.
+    pub fn win_rate(&self) -> WinRate {
+        WinRate::new(self.bb_per_100(), self.std_dev_per_100())
+    }
+
+    /// The range in bb/100 the real win rate is in, with the given z score
+    /// confidence (1.96 for 95%).
+    pub fn confidence_interval(&self, z: f64) -> (f64, f64) {
+        if self.hands == 0 {
+            return (f64::NEG_INFINITY, f64::INFINITY);
+        }
+        self.win_rate().confidence_interval(self.hands, z)
+    }
+
+    /// The most lost from a high point to a later low point.
+    pub fn max_downswing(&self) -> f64 {
+        self.max_downswing
+    }
+
+    /// How far below the high point the results are now.
+    pub fn current_downswing(&self) -> f64 {
+        self.peak - self.total
+    }
+
+    /// The best single hand, or zero if there were no winning hands.
+    pub fn biggest_win(&self) -> f64 {
+        self.biggest_win
+    }
+
+    /// The worst single hand, as a negative number, or zero if there were no
+    /// losing hands.
+    pub fn biggest_loss(&self) -> f64 {
+        self.biggest_loss
+    }
+}
+
+impl Extend<f64> for ResultStats {
+    fn extend<T: IntoIterator<Item = f64>>(&mut self, iter: T) {
+        for result in iter {
+            self.push(result);
+        }
+    }
+}
+
+impl FromIterator<f64> for ResultStats {
+    fn from_iter<T: IntoIterator<Item = f64>>(iter: T) -> Self {
+        let mut stats = Self::new();
+        stats.extend(iter);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expected: f64, actual: f64) {
+        assert!(
+            (expected - actual).abs() < 1e-9,
+            "expected {expected} got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_win_rate() {
+        let stats: ResultStats = (0..1000)
+            .map(|i| if i % 2 == 0 { 2.0 } else { -1.0 })
+            .collect();
+        assert_eq!(1000, stats.hands());
+        assert_close(500.0, stats.total());
+        assert_close(50.0, stats.bb_per_100());
+        assert_close(15.0, stats.std_dev_per_100());
+        assert_eq!(2.0, stats.biggest_win());
+        assert_eq!(-1.0, stats.biggest_loss());
+        assert_eq!(1.0, stats.max_downswing());
+        let win_rate = WinRate::from_results(&[2.0, -1.0]);
+        assert_close(win_rate.bb_per_100, stats.win_rate().bb_per_100);
+        assert_close(win_rate.std_dev_per_100, stats.win_rate().std_dev_per_100);
+
+        let (low, high) = stats.confidence_interval(1.96);
+        assert_close(2.0 * 1.96 * 15.0 / 10.0_f64.sqrt(), high - low);
+    }
+
+    #[test]
+    fn test_downswing() {
+        let stats: ResultStats = [1.0, 2.0, -4.0, 1.0, -3.0, 10.0, -1.0]
+            .into_iter()
+            .collect();
+        // From 3 down to -3.
+        assert_eq!(6.0, stats.max_downswing());
+        assert_eq!(1.0, stats.current_downswing());
+
+        // Losing from the first hand counts from zero.
+        let losing: ResultStats = [-1.0, -1.0].into_iter().collect();
+        assert_eq!(2.0, losing.max_downswing());
+    }
+
+    #[test]
+    fn test_merge_matches_sequential() {
+        let results = [1.0, 2.0, -4.0, 1.0, -3.0, 10.0, -1.0, -6.0, 0.5];
+        let all: ResultStats = results.into_iter().collect();
+        for split in 0..=results.len() {
+            let mut merged: ResultStats = results[..split].iter().copied().collect();
+            merged.merge(&results[split..].iter().copied().collect());
+            assert_eq!(all.hands(), merged.hands());
+            assert_close(all.mean(), merged.mean());
+            assert_close(all.std_dev(), merged.std_dev());
+            assert_close(all.total(), merged.total());
+            assert_close(all.max_downswing(), merged.max_downswing());
+            assert_close(all.current_downswing(), merged.current_downswing());
+            assert_eq!(all.biggest_loss(), merged.biggest_loss());
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! This module answers the bankroll questions every winning player asks
+//! sooner or later: how bad can the downswings get, how likely am I to go
+//! broke, and how much do I need to play comfortably.
+//!
+//! Results are modeled either as normally distributed with a win rate and
+//! standard deviation, the numbers every tracker reports in big blinds per
+//! 100 hands, or by resampling a player's own per-hand results, which keeps
+//! the fat tails of real results.
+//!
+//! There are two ways to get answers:
+//!
+//! - `WinRate::risk_of_ruin` and `WinRate::required_bankroll` use the closed
+//!   form for playing forever, which is the usual rule of thumb.
+//! - `BankrollSimulation` plays out many trajectories of a fixed number of
+//!   hands, giving the spread of results, downswings and breakeven stretches,
+//!   and the risk of ruin over just those hands.
+//!
+//! ```
+//! use rs_poker::variance::{BankrollSimulation, HandResults, WinRate};
+//!
+//! // A 5bb/100 winner with a standard deviation of 90bb/100.
+//! let win_rate = WinRate::new(5.0, 90.0);
+//! let forever = win_rate.risk_of_ruin(3_000.0);
+//!
+//! let report =
+//!     BankrollSimulation::new(10_000, 200).run(&HandResults::Normal(win_rate), &mut rand::rng());
+//! // Over ten thousand hands the risk can only be lower.
+//! assert!(report.risk_of_ruin(3_000.0) <= forever + 0.05);
+//! println!(
+//!     "The worst 5% of downswings are over {:.0}bb",
+//!     report.downswing_percentile(0.95)
+//! );
+//! ```
+use rand::Rng;
+
+use crate::stats::ResultStats;
+
+/// A win rate and standard deviation, both in big blinds per 100 hands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WinRate {
+    pub bb_per_100: f64,
+    pub std_dev_per_100: f64,
+}
+
+impl WinRate {
+    pub fn new(bb_per_100: f64, std_dev_per_100: f64) -> Self {
+        Self {
+            bb_per_100,
+            std_dev_per_100,
+        }
+    }
+
+    /// Measure the win rate and standard deviation of per-hand results in
+    /// big blinds.
+    ///
+    /// # Panics
+    ///
+    /// If `results` is empty.
+    pub fn from_results(results: &[f64]) -> Self {
+        assert!(!results.is_empty(), "Need results to measure a win rate");
+        results.iter().copied().collect::<ResultStats>().win_rate()
+    }
+
+    fn mean_per_hand(&self) -> f64 {
+        self.bb_per_100 / 100.0
+    }
+
+    fn variance_per_hand(&self) -> f64 {
+        (self.std_dev_per_100 / 10.0).powi(2)
+    }
+
+    /// The chance of ever losing `bankroll` big blinds when playing forever.
+    /// Anyone who isn't winning goes broke eventually, so that's certain
+    /// without a positive win rate.
+    pub fn risk_of_ruin(&self, bankroll: f64) -> f64 {
+        if self.bb_per_100 <= 0.0 {
+            return 1.0;
+        }
+        if self.std_dev_per_100 <= 0.0 {
+            return 0.0;
+        }
+        (-2.0 * self.mean_per_hand() * bankroll / self.variance_per_hand())
+            .exp()
+            .min(1.0)
+    }
+
+    /// The bankroll in big blinds that keeps the chance of ever going broke
+    /// down to `risk`, or `None` if no bankroll will when not winning.
+    pub fn required_bankroll(&self, risk: f64) -> Option<f64> {
+        if self.bb_per_100 <= 0.0 {
+            return None;
+        }
+        let risk = risk.clamp(f64::MIN_POSITIVE, 1.0);
+        Some(-risk.ln() * self.variance_per_hand() / (2.0 * self.mean_per_hand()))
+    }
+
+    /// The range the real win rate is in, with the given z score
+    /// confidence (1.96 for 95%), after measuring this one over `hands`.
+    pub fn confidence_interval(&self, hands: usize, z: f64) -> (f64, f64) {
+        let error = z * self.std_dev_per_100 / (hands as f64 / 100.0).sqrt();
+        (self.bb_per_100 - error, self.bb_per_100 + error)
+    }
+}
+
+/// Where the result of each simulated hand comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandResults {
+    /// Normally distributed with this win rate.
+    Normal(WinRate),
+    /// Drawn at random, with replacement, from these per-hand results in
+    /// big blinds.
+    Resampled(Vec<f64>),
+}
+
+impl HandResults {
+    fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match self {
+            HandResults::Normal(win_rate) => {
+                win_rate.mean_per_hand()
+                    + win_rate.variance_per_hand().sqrt() * standard_normal(rng)
+            }
+            HandResults::Resampled(results) => results[rng.random_range(0..results.len())],
+        }
+    }
+}
+
+/// A standard normal sample by the Box-Muller transform.
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = 1.0 - rng.random::<f64>();
+    let u2: f64 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+/// Simulates many players each playing the same number of hands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BankrollSimulation {
+    /// Hands per trajectory.
+    pub hands: usize,
+    /// How many trajectories to play.
+    pub trials: usize,
+}
+
+impl BankrollSimulation {
+    pub fn new(hands: usize, trials: usize) -> Self {
+        Self { hands, trials }
+    }
+
+    /// Play out one trajectory, the running total in big blinds after each
+    /// hand. Useful for plotting.
+    ///
+    /// # Panics
+    ///
+    /// If `results` is `Resampled` from no results.
+    pub fn trajectory<R: Rng>(&self, results: &HandResults, rng: &mut R) -> Vec<f64> {
+        check_results(results);
+        let mut total = 0.0;
+        (0..self.hands)
+            .map(|_| {
+                total += results.sample(rng);
+                total
+            })
+            .collect()
+    }
+
+    /// Play out every trajectory and summarize them.
+    ///
+    /// # Panics
+    ///
+    /// If `results` is `Resampled` from no results.
+    pub fn run<R: Rng>(&self, results: &HandResults, rng: &mut R) -> VarianceReport {
+        check_results(results);
+        let mut report = VarianceReport {
+            hands: self.hands,
+            final_results: Vec::with_capacity(self.trials),
+            max_losses: Vec::with_capacity(self.trials),
+            downswings: Vec::with_capacity(self.trials),
+            breakeven_stretches: Vec::with_capacity(self.trials),
+        };
+        for _ in 0..self.trials {
+            let mut total = 0.0_f64;
+            let mut peak = 0.0_f64;
+            let mut peak_hand = 0;
+            let mut max_loss = 0.0_f64;
+            let mut downswing = 0.0_f64;
+            let mut stretch = 0;
+            for hand in 1..=self.hands {
+                total += results.sample(rng);
+                if total > peak {
+                    peak = total;
+                    peak_hand = hand;
+                }
+                max_loss = max_loss.max(-total);
+                downswing = downswing.max(peak - total);
+                stretch = stretch.max(hand - peak_hand);
+            }
+            report.final_results.push(total);
+            report.max_losses.push(max_loss);
+            report.downswings.push(downswing);
+            report.breakeven_stretches.push(stretch);
+        }
+        report.final_results.sort_by(f64::total_cmp);
+        report.max_losses.sort_by(f64::total_cmp);
+        report.downswings.sort_by(f64::total_cmp);
+        report.breakeven_stretches.sort_unstable();
+        report
+    }
+}
+
+fn check_results(results: &HandResults) {
+    if let HandResults::Resampled(results) = results {
+        assert!(!results.is_empty(), "Need results to resample");
+    }
+}
+
+/// What happened over every trajectory of a `BankrollSimulation`. Each of
+/// the lists has one entry per trajectory, sorted smallest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarianceReport {
+    /// Hands per trajectory.
+    pub hands: usize,
+    /// Big blinds won, or lost, by the end.
+    pub final_results: Vec<f64>,
+    /// The furthest below the starting point each got, in big blinds.
+    pub max_losses: Vec<f64>,
+    /// The biggest drop from a high point to a later low, in big blinds.
+    pub downswings: Vec<f64>,
+    /// The most hands played without reaching a new high.
+    pub breakeven_stretches: Vec<usize>,
+}
+
+impl VarianceReport {
+    /// The mean result per 100 hands across every trajectory.
+    pub fn mean_bb_per_100(&self) -> f64 {
+        if self.final_results.is_empty() || self.hands == 0 {
+            return 0.0;
+        }
+        let mean = self.final_results.iter().sum::<f64>() / self.final_results.len() as f64;
+        mean / self.hands as f64 * 100.0
+    }
+
+    /// The final result that `p` of trajectories ended at or below, so
+    /// `0.05` is an unlucky run and `0.95` a lucky one.
+    pub fn final_percentile(&self, p: f64) -> f64 {
+        percentile(&self.final_results, p)
+    }
+
+    /// The downswing that `p` of trajectories stayed within.
+    pub fn downswing_percentile(&self, p: f64) -> f64 {
+        percentile(&self.downswings, p)
+    }
+
+    /// The breakeven stretch, in hands, that `p` of trajectories stayed
+    /// within.
+    pub fn breakeven_stretch_percentile(&self, p: f64) -> usize {
+        percentile(&self.breakeven_stretches, p)
+    }
+
+    /// The fraction of trajectories that had a downswing of at least
+    /// `big_blinds`.
+    pub fn downswing_probability(&self, big_blinds: f64) -> f64 {
+        fraction_at_least(&self.downswings, big_blinds)
+    }
+
+    /// The fraction of trajectories that would have gone broke starting
+    /// with `bankroll` big blinds.
+    pub fn risk_of_ruin(&self, bankroll: f64) -> f64 {
+        fraction_at_least(&self.max_losses, bankroll)
+    }
+
+    /// The smallest bankroll in big blinds that went broke in no more than
+    /// `risk` of trajectories.
+    pub fn required_bankroll(&self, risk: f64) -> f64 {
+        let n = self.max_losses.len();
+        let allowed = (risk.clamp(0.0, 1.0) * n as f64).floor() as usize;
+        if allowed >= n {
+            return 0.0;
+        }
+        // Just more than the biggest loss of the trajectories that can't go
+        // broke.
+        self.max_losses[n - allowed - 1].next_up()
+    }
+}
+
+fn percentile<T: Copy + Default>(sorted: &[T], p: f64) -> T {
+    if sorted.is_empty() {
+        return T::default();
+    }
+    let idx = (p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64).round() as usize;
+    sorted[idx]
+}
+
+fn fraction_at_least(sorted: &[f64], value: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let below = sorted.partition_point(|v| *v < value);
+    (sorted.len() - below) as f64 / sorted.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_results() {
+        // Win 2bb or lose 1bb, half the time each.
+        let results: Vec<f64> = (0..1000)
+            .map(|i| if i % 2 == 0 { 2.0 } else { -1.0 })
+            .collect();
+        let win_rate = WinRate::from_results(&results);
+        assert!((win_rate.bb_per_100 - 50.0).abs() < 1e-9);
+        assert!((win_rate.std_dev_per_100 - 15.0).abs() < 1e-9);
+
+        let (low, high) = win_rate.confidence_interval(10_000, 1.96);
+        assert!((high - low - 2.0 * 1.96 * 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_risk_of_ruin() {
+        let win_rate = WinRate::new(5.0, 90.0);
+        let bankroll = win_rate.required_bankroll(0.05).unwrap();
+        assert!((win_rate.risk_of_ruin(bankroll) - 0.05).abs() < 1e-9);
+        // A bigger bankroll is safer.
+        assert!(win_rate.risk_of_ruin(bankroll * 2.0) < 0.05);
+
+        let losing = WinRate::new(-1.0, 90.0);
+        assert_eq!(1.0, losing.risk_of_ruin(1e9));
+        assert_eq!(None, losing.required_bankroll(0.05));
+    }
+
+    #[test]
+    fn test_simulation() {
+        let mut rng = rand::rng();
+        let win_rate = WinRate::new(10.0, 80.0);
+        let report =
+            BankrollSimulation::new(5_000, 400).run(&HandResults::Normal(win_rate), &mut rng);
+        assert_eq!(400, report.final_results.len());
+        // The mean of 400 trajectories of 5000 hands has a standard error of
+        // under 0.6bb/100.
+        assert!((report.mean_bb_per_100() - 10.0).abs() < 3.0);
+        assert!(report.final_percentile(0.05) < report.final_percentile(0.95));
+        // Every trajectory's downswing covers its loss from the start.
+        assert!(report.downswing_percentile(1.0) >= report.max_losses[report.max_losses.len() - 1]);
+
+        // Over a few thousand hands going broke is less likely than over
+        // forever, give or take the noise.
+        let bankroll = report.required_bankroll(0.1);
+        assert!(report.risk_of_ruin(bankroll) <= 0.1 + 1e-9);
+        assert!(report.risk_of_ruin(1000.0) <= win_rate.risk_of_ruin(1000.0) + 0.05);
+        assert_eq!(1.0, report.downswing_probability(0.0));
+        assert!(report.breakeven_stretch_percentile(0.5) <= 5_000);
+    }
+
+    #[test]
+    fn test_resampled() {
+        let mut rng = rand::rng();
+        // Always winning means never going down.
+        let results = HandResults::Resampled(vec![1.0, 2.0]);
+        let report = BankrollSimulation::new(100, 10).run(&results, &mut rng);
+        assert_eq!(0.0, report.risk_of_ruin(0.5));
+        assert_eq!(0.0, report.downswing_percentile(1.0));
+        assert_eq!(0, report.breakeven_stretch_percentile(1.0));
+        assert!(report.final_percentile(0.0) >= 100.0);
+
+        let trajectory = BankrollSimulation::new(100, 1).trajectory(&results, &mut rng);
+        assert_eq!(100, trajectory.len());
+        assert!(trajectory.windows(2).all(|w| w[1] > w[0]));
+    }
+}
@@ -0,0 +1,186 @@
+//! Every artifact format ever released must keep loading.
+//!
+//! Run with: cargo test --test test_compat --features=serde,arena
+
+#[cfg(feature = "serde")]
+mod tests {
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+
+    use rs_poker::arena::cfr::{CFRState, Checkpoint, NodeData, StateStore, StrategyProfile};
+    use rs_poker::arena::versioned::{FileFormat, Versioned, detect_format, upgrade_versioned};
+    use tempfile::tempdir;
+
+    /// Every file in the corpus with the format it was written in.
+    const CORPUS: &[(&str, Option<&str>, u32)] = &[
+        ("state_store_v0.json", None, 0),
+        ("state_store_v1.json", Some("state_store"), 1),
+        ("state_store_v1_data_first.json", Some("state_store"), 1),
+        ("state_store_v2.json", Some("state_store"), 2),
+        ("cfr_state_v0.json", None, 0),
+        ("cfr_state_v1.json", Some("cfr_state"), 1),
+        ("cfr_state_v2.json", Some("cfr_state"), 2),
+        ("strategy_profile_v1.json", Some("strategy_profile"), 1),
+        ("checkpoint_v1.json", Some("checkpoint"), 1),
+        ("checkpoint_v2.json", Some("checkpoint"), 2),
+    ];
+
+    fn corpus_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/compat")
+    }
+
+    fn corpus_files(prefix: &str) -> Vec<PathBuf> {
+        CORPUS
+            .iter()
+            .filter(|(name, _, _)| name.starts_with(prefix))
+            .map(|(name, _, _)| corpus_dir().join(name))
+            .collect()
+    }
+
+    fn check_tree(cfr_state: &CFRState, player_idx: usize) {
+        let root = cfr_state.get(0).unwrap();
+        assert!(root.data.is_root());
+        let chance = cfr_state.get(root.get_child(0).unwrap()).unwrap();
+        assert!(chance.data.is_chance());
+        assert_eq!(1, chance.get_count(12));
+
+        let decision = cfr_state.get(chance.get_child(12).unwrap()).unwrap();
+        match &*decision.data {
+            NodeData::Player(player_data) => {
+                assert_eq!(player_idx, player_data.player_idx);
+                // Version 2 files keep the regret matcher, older ones wrote
+                // null for it.
+                if let Some(matcher) = &player_data.regret_matcher {
+                    assert_eq!(vec![0.0, 1.0], matcher.best_weight());
+                }
+            }
+            other => panic!("Expected a player node, found {other}"),
+        }
+        match &*cfr_state.get(decision.get_child(1).unwrap()).unwrap().data {
+            NodeData::Terminal(terminal) => assert_eq!(15.0, terminal.total_utility),
+            other => panic!("Expected a terminal node, found {other}"),
+        }
+        assert_eq!(10.0, cfr_state.starting_game_state().big_blind);
+    }
+
+    fn check_profile(profile: &StrategyProfile) {
+        assert_eq!(2, profile.len());
+        assert_eq!(Some(&[0.25, 0.75][..]), profile.get(0, &[0, 12]));
+        assert_eq!(Some(&[1.0, 0.0][..]), profile.get(1, &[0, 12]));
+    }
+
+    #[test]
+    fn test_corpus_is_complete() {
+        let mut files: Vec<_> = fs::read_dir(corpus_dir())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".json"))
+            .collect();
+        files.sort();
+        let mut listed: Vec<_> = CORPUS.iter().map(|(name, _, _)| name.to_string()).collect();
+        listed.sort();
+        assert_eq!(listed, files, "Add new fixture files to CORPUS");
+    }
+
+    #[test]
+    fn test_detect_format() {
+        for (name, kind, version) in CORPUS {
+            let format = detect_format(File::open(corpus_dir().join(name)).unwrap()).unwrap();
+            assert_eq!(
+                FileFormat {
+                    kind: kind.map(str::to_string),
+                    version: *version,
+                },
+                format,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_state_store() {
+        for path in corpus_files("state_store") {
+            let store = StateStore::load_from_file(&path).unwrap();
+            assert_eq!(2, store.len(), "{}", path.display());
+            for player_idx in 0..2 {
+                check_tree(&store.get_state(player_idx).unwrap(), player_idx);
+                assert_eq!(2, store.traversal_len(player_idx));
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_cfr_state() {
+        for path in corpus_files("cfr_state") {
+            check_tree(&CFRState::load_from_file(&path).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn test_load_strategy_profile() {
+        for path in corpus_files("strategy_profile") {
+            check_profile(&StrategyProfile::load_from_file(&path).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_load_checkpoint() {
+        for path in corpus_files("checkpoint") {
+            let checkpoint = Checkpoint::load_from_file(&path).unwrap();
+            assert_eq!(10, checkpoint.iterations);
+            assert_eq!(vec![7], checkpoint.seeds);
+            check_tree(&checkpoint.state_store.get_state(0).unwrap(), 0);
+        }
+        let v1 = Checkpoint::load_from_file(&corpus_dir().join("checkpoint_v1.json")).unwrap();
+        assert!(v1.convergence.points().is_empty());
+        let v2 = Checkpoint::load_from_file(&corpus_dir().join("checkpoint_v2.json")).unwrap();
+        assert_eq!(10, v2.convergence.last().unwrap().iteration);
+    }
+
+    fn check_upgrade<T: Versioned>(prefix: &str, check: impl Fn(&T)) {
+        let dir = tempdir().unwrap();
+        for original in corpus_files(prefix) {
+            let path = dir.path().join(original.file_name().unwrap());
+            fs::copy(&original, &path).unwrap();
+
+            let before = detect_format(File::open(&original).unwrap()).unwrap();
+            assert_eq!(before, upgrade_versioned::<T>(&path).unwrap());
+
+            let current = FileFormat {
+                kind: Some(T::KIND.to_string()),
+                version: T::VERSION,
+            };
+            assert_eq!(current, detect_format(File::open(&path).unwrap()).unwrap());
+            check(&rs_poker::arena::versioned::load_versioned(&path).unwrap());
+
+            // Upgrading again leaves the file alone.
+            let upgraded = fs::read(&path).unwrap();
+            assert_eq!(current, upgrade_versioned::<T>(&path).unwrap());
+            assert_eq!(upgraded, fs::read(&path).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_upgrade() {
+        check_upgrade::<StateStore>("state_store", |store| {
+            check_tree(&store.get_state(1).unwrap(), 1);
+        });
+        check_upgrade::<CFRState>("cfr_state", |cfr_state| check_tree(cfr_state, 0));
+        check_upgrade::<StrategyProfile>("strategy_profile", check_profile);
+        check_upgrade::<Checkpoint>("checkpoint", |checkpoint| {
+            check_tree(&checkpoint.state_store.get_state(1).unwrap(), 1);
+        });
+    }
+
+    #[test]
+    fn test_upgrade_wrong_kind() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("profile.json");
+        fs::copy(corpus_dir().join("strategy_profile_v1.json"), &path).unwrap();
+        assert!(upgrade_versioned::<StateStore>(&path).is_err());
+        assert_eq!(
+            fs::read(corpus_dir().join("strategy_profile_v1.json")).unwrap(),
+            fs::read(&path).unwrap()
+        );
+    }
+}
@@ -0,0 +1,223 @@
+//! WebAssembly bindings for the `rs_poker` evaluator and range tools.
+//!
+//! Build with `wasm-pack build --target web` from this directory. Cards are
+//! passed as strings like `"As"`, or as a single byte (`suit * 13 + value`)
+//! where that's more convenient.
+//!
+//! ```js
+//! import init, { rankHand, parseRange, equity } from "./pkg/rs_poker_wasm.js";
+//!
+//! await init();
+//! rankHand("AsKsQsJsTs").category; // "StraightFlush"
+//! parseRange("KQo+,AA").length;    // 30
+//! equity(["AsAh", "7c2d"], "", 10000);
+//! ```
+//!
+//! Everything runs on the calling thread, so keep equity iterations small
+//! enough not to block the page or move the calls into a web worker.
+#![deny(clippy::all)]
+
+use rs_poker::core::{self, Card, RSPokerError, Rankable};
+use rs_poker::holdem::{MonteCarloGame, RangeParser};
+use wasm_bindgen::prelude::*;
+
+/// The strength of a made hand. Compare two ranks with `compare`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rank(core::Rank);
+
+#[wasm_bindgen]
+impl Rank {
+    /// The hand category, for example `"TwoPair"`.
+    #[wasm_bindgen(getter)]
+    pub fn category(&self) -> String {
+        self.parts().0.to_string()
+    }
+
+    /// Breaks ties between hands of the same category, higher is better.
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> u32 {
+        self.parts().1
+    }
+
+    /// -1, 0 or 1 as this rank is weaker than, equal to or stronger than
+    /// `other`.
+    pub fn compare(&self, other: &Rank) -> i32 {
+        self.cmp(other) as i32
+    }
+}
+
+impl Rank {
+    fn parts(&self) -> (&'static str, u32) {
+        match self.0 {
+            core::Rank::HighCard(v) => ("HighCard", v),
+            core::Rank::OnePair(v) => ("OnePair", v),
+            core::Rank::TwoPair(v) => ("TwoPair", v),
+            core::Rank::ThreeOfAKind(v) => ("ThreeOfAKind", v),
+            core::Rank::Straight(v) => ("Straight", v),
+            core::Rank::Flush(v) => ("Flush", v),
+            core::Rank::FullHouse(v) => ("FullHouse", v),
+            core::Rank::FourOfAKind(v) => ("FourOfAKind", v),
+            core::Rank::StraightFlush(v) => ("StraightFlush", v),
+        }
+    }
+}
+
+// The exported functions are thin wrappers that turn errors into JS
+// exceptions. The work is done here so it can be tested natively, where
+// `JsError` isn't available.
+
+fn rank_hand_impl(hand: &str) -> Result<Rank, String> {
+    rank(core::Hand::new_from_str(hand).map_err(|e| e.to_string())?)
+}
+
+fn rank(hand: core::Hand) -> Result<Rank, String> {
+    if !(5..=7).contains(&hand.count()) {
+        return Err("a hand needs five to seven cards to rank".to_string());
+    }
+    Ok(Rank(hand.rank()))
+}
+
+fn rank_cards_impl(cards: &[u8]) -> Result<Rank, String> {
+    let mut hand = core::Hand::new();
+    for &card in cards {
+        if card >= 52 {
+            return Err(format!("invalid card {card}"));
+        }
+        if !hand.insert(Card::from(card)) {
+            return Err(RSPokerError::DuplicateCardInHand(Card::from(card)).to_string());
+        }
+    }
+    rank(hand)
+}
+
+fn parse_range_impl(range: &str) -> Result<Vec<String>, String> {
+    let hands = RangeParser::parse_many(range).map_err(|e| e.to_string())?;
+    Ok(hands
+        .iter()
+        .map(|hand| hand.iter().map(|c| c.to_string()).collect())
+        .collect())
+}
+
+fn equity_impl(hands: &[String], board: &str, iterations: usize) -> Result<Vec<f32>, String> {
+    if hands.len() < 2 || iterations == 0 {
+        return Err("equity needs at least two hands and one iteration".to_string());
+    }
+    let board = core::Hand::new_from_str(board).map_err(|e| e.to_string())?;
+    if board.count() > 5 {
+        return Err("the board can't have more than five cards".to_string());
+    }
+    let mut seen = board;
+    let hands = hands
+        .iter()
+        .map(|hole| {
+            let mut hole = core::Hand::new_from_str(hole)?;
+            if let Some(card) = hole.iter().find(|card| seen.contains(card)) {
+                return Err(RSPokerError::DuplicateCardInHand(card));
+            }
+            seen.extend(hole.iter());
+            hole.extend(board.iter());
+            Ok(hole)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut game = MonteCarloGame::new(hands).map_err(|e| e.to_string())?;
+    Ok(game.estimate_equity(iterations))
+}
+
+/// Parse a card like `"As"` into its byte encoding.
+#[wasm_bindgen(js_name = parseCard)]
+pub fn parse_card(card: &str) -> Result<u8, JsError> {
+    Card::try_from(card)
+        .map(u8::from)
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// The string form of a card's byte encoding.
+#[wasm_bindgen(js_name = cardToString)]
+pub fn card_to_string(card: u8) -> Result<String, JsError> {
+    if card < 52 {
+        Ok(Card::from(card).to_string())
+    } else {
+        Err(JsError::new("card must be in 0..52"))
+    }
+}
+
+/// Rank a hand of five to seven cards, for example `"AsKsQsJsTs"`.
+#[wasm_bindgen(js_name = rankHand)]
+pub fn rank_hand(hand: &str) -> Result<Rank, JsError> {
+    rank_hand_impl(hand).map_err(|e| JsError::new(&e))
+}
+
+/// Rank five to seven cards given as bytes.
+#[wasm_bindgen(js_name = rankCards)]
+pub fn rank_cards(cards: &[u8]) -> Result<Rank, JsError> {
+    rank_cards_impl(cards).map_err(|e| JsError::new(&e))
+}
+
+/// Every two card combination in a comma separated range like
+/// `"KQo+,AA"`, as strings like `"KsQd"`.
+#[wasm_bindgen(js_name = parseRange)]
+pub fn parse_range(range: &str) -> Result<Vec<String>, JsError> {
+    parse_range_impl(range).map_err(|e| JsError::new(&e))
+}
+
+/// Estimate the all in equity of holdem hands with Monte Carlo simulation.
+/// Ties are split so the equities add up to one.
+#[wasm_bindgen]
+pub fn equity(hands: Vec<String>, board: &str, iterations: usize) -> Result<Vec<f32>, JsError> {
+    equity_impl(&hands, board, iterations).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        let royal = rank_hand_impl("AsKsQsJsTs").unwrap();
+        assert_eq!("StraightFlush", royal.category());
+        let pair = rank_hand_impl("AsAdQs2c3d").unwrap();
+        assert_eq!("OnePair", pair.category());
+        assert_eq!(1, royal.compare(&pair));
+        assert_eq!(0, pair.compare(&pair));
+
+        assert!(rank_hand_impl("AsAd").is_err());
+        assert!(rank_hand_impl("nope").is_err());
+    }
+
+    #[test]
+    fn test_rank_cards() {
+        let cards: Vec<u8> = ["As", "Ks", "Qs", "Js", "Ts"]
+            .iter()
+            .map(|c| u8::from(Card::try_from(*c).unwrap()))
+            .collect();
+        assert_eq!(rank_hand_impl("AsKsQsJsTs"), rank_cards_impl(&cards));
+        assert!(rank_cards_impl(&[0, 0, 1, 2, 3]).is_err());
+        assert!(rank_cards_impl(&[52, 0, 1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_parse_range() {
+        let combos = parse_range_impl("KQo+,AA").unwrap();
+        assert_eq!(30, combos.len());
+        assert!(combos.iter().all(|c| c.len() == 4));
+        assert!(parse_range_impl("KZ").is_err());
+    }
+
+    #[test]
+    fn test_equity() {
+        let hands = vec!["AsAh".to_string(), "7c2d".to_string()];
+        let equity = equity_impl(&hands, "", 2_000).unwrap();
+        assert!(equity[0] > 0.75);
+        assert!((equity[0] + equity[1] - 1.0).abs() < 1e-3);
+
+        assert_eq!(
+            vec![0.0, 1.0],
+            equity_impl(&hands, "7d7h2c3s9s", 10).unwrap()
+        );
+        assert!(equity_impl(&hands, "As", 10).is_err());
+        assert!(equity_impl(&hands[..1], "", 10).is_err());
+    }
+}